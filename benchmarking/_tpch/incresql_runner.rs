@@ -32,7 +32,7 @@ impl IncresqlRunner {
         let address = listen_address.to_string();
         std::thread::spawn(move || {
             eprintln!("Server Running");
-            server.listen(&address).unwrap();
+            server.listen(&address, None).unwrap();
         });
 
         let mysql_connection = mysql::Conn::new(client_url)?;
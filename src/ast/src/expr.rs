@@ -1,3 +1,4 @@
+use crate::rel::logical::LogicalOperator;
 use data::rust_decimal::Decimal;
 use data::{DataType, Datum, SortOrder};
 use functions::{AggregateFunction, Function, FunctionSignature};
@@ -19,6 +20,9 @@ pub enum Expression {
     CompiledAggregate(CompiledAggregate),
     ColumnReference(ColumnReference),
     CompiledColumnReference(CompiledColumnReference),
+    Subquery(Box<Subquery>),
+    Parameter(Parameter),
+    WindowFunction(Box<WindowFunction>),
 }
 
 impl Default for Expression {
@@ -40,6 +44,8 @@ pub struct FunctionCall {
 pub struct Cast {
     pub expr: Box<Expression>,
     pub datatype: DataType,
+    // TRY_CAST, gives null for values that can't be converted even with strict_casts on
+    pub try_cast: bool,
 }
 
 /// Represents a scalar function call once its been resolved and type
@@ -50,8 +56,6 @@ pub struct CompiledFunctionCall {
     // enum a bit hence the boxed slices instead of vec's
     pub function: &'static dyn Function,
     pub args: Box<[Expression]>,
-    // Used to store the evaluation results of the sub expressions during execution
-    pub expr_buffer: Box<[Datum<'static>]>,
     pub signature: Box<FunctionSignature<'static>>,
 }
 
@@ -69,8 +73,6 @@ impl Eq for CompiledFunctionCall {}
 pub struct CompiledAggregate {
     pub function: &'static dyn AggregateFunction,
     pub args: Box<[Expression]>,
-    // Used to store the evaluation results of the sub expressions during execution
-    pub expr_buffer: Box<[Datum<'static>]>,
     pub signature: Box<FunctionSignature<'static>>,
 }
 
@@ -102,6 +104,50 @@ pub struct CompiledColumnReference {
     pub datatype: DataType,
 }
 
+/// A subquery used as a predicate, ie EXISTS (SELECT ...) or foo IN (SELECT ...), or as a
+/// value, ie SELECT (SELECT max(x) FROM bar) FROM foo.
+/// These only live as far as the optimizer where they get rewritten as joins.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Subquery {
+    pub negated: bool,
+    // The left hand side of an IN subquery, None for EXISTS
+    pub operand: Option<Expression>,
+    // A scalar subquery evaluates to the single column of its single row, null if it's empty
+    pub scalar: bool,
+    pub query: LogicalOperator,
+}
+
+/// An aggregate evaluated for each row over the rows of its partition around it, rather than
+/// collapsing them, ie sum(amount) OVER (PARTITION BY customer ORDER BY day).
+/// The planner pulls these out of the select list into a window operator.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct WindowFunction {
+    pub function: Expression,
+    pub partition_by: Vec<Expression>,
+    pub order_by: Vec<SortExpression>,
+    pub frame: WindowFrame,
+}
+
+/// The rows of the partition the function sees for each row
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum WindowFrame {
+    // Every row up to and including the current row's peers (the rows that tie with it on the
+    // ORDER BY), so the whole partition when there's no ORDER BY. This is the default.
+    Range,
+    // ROWS BETWEEN n PRECEDING AND CURRENT ROW, None for UNBOUNDED PRECEDING
+    Rows(Option<u64>),
+}
+
+/// A placeholder for a value bound when executing a prepared statement,
+/// ie SELECT * FROM foo WHERE id = ?
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Parameter {
+    // Parameters are numbered in the order they appear in the sql
+    pub index: usize,
+    // Inferred by the planner from how the parameter is used, null until then.
+    pub datatype: DataType,
+}
+
 /// Named expression, ie select foo as bar
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct NamedExpression {
@@ -124,9 +170,17 @@ impl Expression {
             Expression::CompiledFunctionCall(function_call) => Box::from(function_call.args.iter()),
             Expression::CompiledAggregate(function_call) => Box::from(function_call.args.iter()),
             Expression::Cast(cast) => Box::from(once(&*cast.expr)),
+            // The subquery's own expressions are scoped to its query so aren't children here
+            Expression::Subquery(subquery) => Box::from(subquery.operand.iter()),
+            Expression::WindowFunction(window) => Box::from(
+                once(&window.function)
+                    .chain(window.partition_by.iter())
+                    .chain(window.order_by.iter().map(|se| &se.expression)),
+            ),
             Expression::CompiledColumnReference(_)
             | Expression::Constant(_, _)
-            | Expression::ColumnReference(_) => Box::from(empty()),
+            | Expression::ColumnReference(_)
+            | Expression::Parameter(_) => Box::from(empty()),
         }
     }
 
@@ -141,9 +195,32 @@ impl Expression {
                 Box::from(function_call.args.iter_mut())
             }
             Expression::Cast(cast) => Box::from(once(&mut *cast.expr)),
+            Expression::Subquery(subquery) => Box::from(subquery.operand.iter_mut()),
+            Expression::WindowFunction(window) => {
+                let window = window.as_mut();
+                Box::from(
+                    once(&mut window.function)
+                        .chain(window.partition_by.iter_mut())
+                        .chain(window.order_by.iter_mut().map(|se| &mut se.expression)),
+                )
+            }
             Expression::CompiledColumnReference(_)
             | Expression::Constant(_, _)
-            | Expression::ColumnReference(_) => Box::from(empty()),
+            | Expression::ColumnReference(_)
+            | Expression::Parameter(_) => Box::from(empty()),
+        }
+    }
+
+    /// Calls the visitor for every parameter in the expression, including any inside subqueries.
+    /// The visitor is passed the expression itself so parameters can be swapped out for values.
+    pub fn visit_parameters_mut<F: FnMut(&mut Expression)>(&mut self, visitor: &mut F) {
+        match self {
+            Expression::Parameter(_) => visitor(self),
+            Expression::Subquery(subquery) => subquery.query.visit_parameters_mut(visitor),
+            _ => {}
+        }
+        for child in self.children_mut() {
+            child.visit_parameters_mut(visitor);
         }
     }
 }
@@ -200,13 +277,29 @@ lazy_static! {
     static ref IDENTIFIER_OK: Regex = Regex::new(r"^([a-z]|_)([a-z,0-9]|_)*$").unwrap();
 }
 
+/// Formats a function name as sql, any name containing anything other that letters and
+/// underscores is quoted unless it's a namespaced name made up of two that don't
+pub fn quote_function_name(name: &str) -> String {
+    let mut parts = name.splitn(2, '.');
+    if parts.all(|part| IDENTIFIER_OK.is_match(part)) {
+        name.to_string()
+    } else {
+        format!("`{}`", name)
+    }
+}
+
 impl Display for Expression {
     /// Formats the expression back to sql
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Expression::Constant(d, dt) => f.write_fmt(format_args!("{:#}", d.typed_with(*dt))),
-            Expression::Cast(c) => f.write_fmt(format_args!("CAST({} AS {})", c.expr, c.datatype)),
-            // For any function name containing anything other that letters and underscores we'll quote.
+            Expression::Cast(c) => f.write_fmt(format_args!(
+                "{}CAST({} AS {})",
+                if c.try_cast { "TRY_" } else { "" },
+                c.expr,
+                c.datatype
+            )),
+            // Function names that aren't plain identifiers (ie operators) are quoted.
             Expression::FunctionCall(function_call) => {
                 let args = function_call
                     .args
@@ -214,11 +307,11 @@ impl Display for Expression {
                     .map(ToString::to_string)
                     .collect::<Vec<_>>()
                     .join(", ");
-                if IDENTIFIER_OK.is_match(&function_call.function_name) {
-                    f.write_fmt(format_args!("{}({})", function_call.function_name, args))
-                } else {
-                    f.write_fmt(format_args!("`{}`({})", function_call.function_name, args))
-                }
+                f.write_fmt(format_args!(
+                    "{}({})",
+                    quote_function_name(&function_call.function_name),
+                    args
+                ))
             }
             Expression::CompiledFunctionCall(function_call) => {
                 let args = function_call
@@ -227,11 +320,11 @@ impl Display for Expression {
                     .map(ToString::to_string)
                     .collect::<Vec<_>>()
                     .join(", ");
-                if IDENTIFIER_OK.is_match(&function_call.signature.name) {
-                    f.write_fmt(format_args!("{}({})", function_call.signature.name, args))
-                } else {
-                    f.write_fmt(format_args!("`{}`({})", function_call.signature.name, args))
-                }
+                f.write_fmt(format_args!(
+                    "{}({})",
+                    quote_function_name(&function_call.signature.name),
+                    args
+                ))
             }
             Expression::CompiledAggregate(function_call) => {
                 let args = function_call
@@ -240,11 +333,11 @@ impl Display for Expression {
                     .map(ToString::to_string)
                     .collect::<Vec<_>>()
                     .join(", ");
-                if IDENTIFIER_OK.is_match(&function_call.signature.name) {
-                    f.write_fmt(format_args!("{}({})", function_call.signature.name, args))
-                } else {
-                    f.write_fmt(format_args!("`{}`({})", function_call.signature.name, args))
-                }
+                f.write_fmt(format_args!(
+                    "{}({})",
+                    quote_function_name(&function_call.signature.name),
+                    args
+                ))
             }
             Expression::ColumnReference(column_reference) => Display::fmt(column_reference, f),
             Expression::CompiledColumnReference(column_reference) => {
@@ -252,6 +345,54 @@ impl Display for Expression {
                 // our sources
                 f.write_fmt(format_args!("<OFFSET {}>", &column_reference.offset))
             }
+            Expression::Subquery(subquery) => {
+                if let Some(operand) = &subquery.operand {
+                    f.write_fmt(format_args!("{} ", operand))?;
+                }
+                if subquery.negated {
+                    f.write_str("NOT ")?;
+                }
+                if subquery.operand.is_some() {
+                    f.write_str("IN(<SUBQUERY>)")
+                } else if subquery.scalar {
+                    f.write_str("(<SUBQUERY>)")
+                } else {
+                    f.write_str("EXISTS(<SUBQUERY>)")
+                }
+            }
+            Expression::Parameter(_) => f.write_str("?"),
+            Expression::WindowFunction(window) => {
+                f.write_fmt(format_args!("{} OVER (", window.function))?;
+                let mut clauses = vec![];
+                if !window.partition_by.is_empty() {
+                    let partition_by: Vec<_> = window
+                        .partition_by
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect();
+                    clauses.push(format!("PARTITION BY {}", partition_by.join(", ")));
+                }
+                if !window.order_by.is_empty() {
+                    let order_by: Vec<_> = window
+                        .order_by
+                        .iter()
+                        .map(|se| match se.ordering {
+                            SortOrder::Asc => se.expression.to_string(),
+                            SortOrder::Desc => format!("{} DESC", se.expression),
+                        })
+                        .collect();
+                    clauses.push(format!("ORDER BY {}", order_by.join(", ")));
+                }
+                match window.frame {
+                    WindowFrame::Range => {}
+                    WindowFrame::Rows(None) => clauses.push("ROWS UNBOUNDED PRECEDING".to_string()),
+                    WindowFrame::Rows(Some(preceding)) => clauses.push(format!(
+                        "ROWS BETWEEN {} PRECEDING AND CURRENT ROW",
+                        preceding
+                    )),
+                }
+                f.write_fmt(format_args!("{})", clauses.join(" ")))
+            }
         }
     }
 }
@@ -377,6 +518,7 @@ mod tests {
                 Expression::Cast(Cast {
                     expr: Box::new(Expression::from("5")),
                     datatype: DataType::Integer,
+                    try_cast: false,
                 }),
                 Expression::FunctionCall(FunctionCall {
                     function_name: "pow".to_string(),
@@ -1,5 +1,7 @@
 use crate::expr::{Expression, NamedExpression, SortExpression};
-use data::DataType;
+use data::json::OwnedJson;
+use data::rust_decimal::Decimal;
+use data::{DataType, LogicalTimestamp};
 use std::iter::{empty, once};
 use storage::Table;
 
@@ -11,6 +13,7 @@ pub enum LogicalOperator {
     Single, // No from clause, ie select 1 + 1
     Project(Project),
     GroupBy(GroupBy),
+    Window(Window),
     Filter(Filter),
     Join(Join),
     Sort(Sort),
@@ -23,6 +26,7 @@ pub enum LogicalOperator {
     TableInsert(TableInsert),
     NegateFreq(Box<LogicalOperator>),
     FileScan(FileScan),
+    FileCopy(FileCopy),
 }
 
 impl Default for LogicalOperator {
@@ -45,23 +49,69 @@ pub struct GroupBy {
     pub source: Box<LogicalOperator>,
 }
 
+/// Outputs each source row followed by the values of the window functions for it, created by
+/// the planner from the window functions in a select list. The functions all share the same
+/// PARTITION BY and ORDER BY, queries using a few different ones get a window operator each.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Window {
+    // Each of these is an Expression::WindowFunction
+    pub functions: Vec<NamedExpression>,
+    pub source: Box<LogicalOperator>,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Join {
     pub left: Box<LogicalOperator>,
     pub right: Box<LogicalOperator>,
     pub on: Expression,
     pub join_type: JoinType,
+    // Set by the HASH_JOIN hint, otherwise the optimizer picks which input to build from
+    pub build_side: Option<JoinSide>,
+}
+
+/// Which input of a join the hash table is built from
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum JoinSide {
+    Left,
+    Right,
 }
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum JoinType {
     Inner,
     LeftOuter,
+    // Right joins are rewritten as left joins with their inputs swapped by the planner so
+    // they never make it as far as execution.
+    RightOuter,
+    FullOuter,
+    // Semi and anti joins only output the left columns, they're created by the planner when
+    // rewriting EXISTS/IN subqueries.
+    LeftSemi,
+    LeftAnti,
+    // A left outer join where each left row can match at most one right row, created by the
+    // planner for scalar subqueries.
+    LeftSingle,
+}
+
+impl JoinType {
+    /// Returns true if the right side's columns appear in the output of the join
+    pub fn outputs_right(&self) -> bool {
+        match self {
+            JoinType::Inner
+            | JoinType::LeftOuter
+            | JoinType::RightOuter
+            | JoinType::FullOuter
+            | JoinType::LeftSingle => true,
+            JoinType::LeftSemi | JoinType::LeftAnti => false,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Filter {
     pub predicate: Expression,
     pub source: Box<LogicalOperator>,
+    // Set by the NO_PUSHDOWN hint, the predicate is evaluated here rather than pushed down
+    pub pinned: bool,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -103,12 +153,34 @@ pub struct UnionAll {
 pub struct TableReference {
     pub database: Option<String>,
     pub table: String,
+    pub sample: Option<TableSample>,
+    // AS OF TIMESTAMP, reads the table as it was at the time, for views every table in the view
+    pub as_of: Option<LogicalTimestamp>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct ResolvedTable {
     pub columns: Vec<(String, DataType)>,
     pub table: Table,
+    pub sample: Option<TableSample>,
+    pub as_of: Option<LogicalTimestamp>,
+}
+
+/// A TABLESAMPLE clause, ie "FROM foo TABLESAMPLE BERNOULLI(1) REPEATABLE(42)"
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TableSample {
+    pub method: SampleMethod,
+    // Between 0 and 100
+    pub percentage: Decimal,
+    pub seed: Option<i64>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SampleMethod {
+    // Each row is picked independently
+    Bernoulli,
+    // Blocks of consecutive rows are picked or skipped together
+    System,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -118,26 +190,115 @@ pub struct TableInsert {
     // the existing code paths. Only TableReference and resolved table
     // are valid here.
     pub table: Box<LogicalOperator>,
+    // The columns the source's columns go into, ie INSERT INTO t (a, c) ..., the table's other
+    // columns are null. Empty for all the table's columns in order.
+    pub columns: Vec<String>,
     pub source: Box<LogicalOperator>,
+    // The json schemas of the table's columns that have one, filled in by the planner. Rows
+    // being deleted aren't checked.
+    pub json_schemas: Vec<ColumnSchema>,
+}
+
+/// A json schema the values inserted into one of a table's json columns have to match,
+/// ie ALTER TABLE orders ALTER COLUMN doc SET SCHEMA '{"type": "object"}'
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ColumnSchema {
+    pub column: String,
+    // Where the column is in the table's rows
+    pub offset: usize,
+    pub schema: OwnedJson,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct FileScan {
     pub directory: String,
     pub serde_options: SerdeOptions,
+    // Typed columns pulled out of each document, these can only see the document as `data`.
+    // When empty the whole document is output as the single `data` column.
+    pub columns: Vec<NamedExpression>,
+    // Filled in when resolving tables if the error policy is DeadLetter.
+    pub dead_letters: Option<Table>,
+    // Filled in when resolving tables if we're resuming from the last ingested positions.
+    pub source_offsets: Option<Table>,
+    // Filled in when resolving tables, the scan waits while its directory is listed here.
+    pub paused_sources: Option<Table>,
+}
+
+/// Parses the records of a csv file straight into the columns of the table it's copied into,
+/// ie COPY orders FROM 'orders.csv'. Only ever found as the source of an insert.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FileCopy {
+    pub path: String,
+    pub serde_options: SerdeOptions,
+    // Each column parsed out of the record's text fields (field_1, field_2...), ie
+    // CAST(field_2 AS INTEGER). Filled in when resolving tables, one per column of the table.
+    pub columns: Vec<NamedExpression>,
+    // Filled in when resolving tables if the error policy is DeadLetter.
+    pub dead_letters: Option<Table>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct SerdeOptions {
     pub delimiter: u8,
+    pub format: FileFormat,
+    pub on_error: ErrorPolicy,
+    // Start reading each file from where the last insert from it left off
+    pub resume: bool,
+    // Throttles the scan so big backfills leave room for everything else
+    pub max_rows_per_second: Option<u64>,
+    // The most rows an insert from this source holds in a single write batch
+    pub max_batch_rows: Option<u64>,
+    // Set when the files are a change stream rather than plain rows
+    pub changes: Option<ChangeFormat>,
+    // A directory of plain rows that's loaded before following the change stream
+    pub snapshot: Option<String>,
 }
 
 impl Default for SerdeOptions {
     fn default() -> Self {
-        SerdeOptions { delimiter: b',' }
+        SerdeOptions {
+            delimiter: b',',
+            format: FileFormat::Csv,
+            on_error: ErrorPolicy::Fail,
+            resume: false,
+            max_rows_per_second: None,
+            max_batch_rows: None,
+            changes: None,
+            snapshot: None,
+        }
     }
 }
 
+/// What to do with rows that can't be decoded, skipped rows are still reported as warnings,
+/// dead lettered rows are also written to incresql.dead_letters.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ErrorPolicy {
+    Fail,
+    Skip,
+    DeadLetter,
+}
+
+/// How change events are laid out, Debezium events carry the row before and after the change
+/// along with an op of c(reate), r(ead during a snapshot), u(pdate) or d(elete).
+/// Maxwell events carry the row as data with a type of insert, update or delete, updates only
+/// have the old values of the changed columns.
+/// Changes come out of the scan as retractions(-1) of the old row and additions(+1) of the new.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ChangeFormat {
+    Debezium,
+    Maxwell,
+}
+
+/// Csv files come through as an array of strings per line, json files are expected to have
+/// one document per line. Each row of a parquet file comes through as an object keyed by
+/// column name, only the columns the scan's paths look at are read.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum FileFormat {
+    Csv,
+    Json,
+    Parquet,
+}
+
 impl LogicalOperator {
     /// Iterates over the named(output) expressions *owned* by this operator.
     /// To iterate over the output fields instead use one of the fields methods in the planner
@@ -145,6 +306,9 @@ impl LogicalOperator {
         match self {
             LogicalOperator::Project(project) => Box::from(project.expressions.iter()),
             LogicalOperator::GroupBy(group_by) => Box::from(group_by.expressions.iter()),
+            LogicalOperator::Window(window) => Box::from(window.functions.iter()),
+            LogicalOperator::FileScan(file_scan) => Box::from(file_scan.columns.iter()),
+            LogicalOperator::FileCopy(file_copy) => Box::from(file_copy.columns.iter()),
             LogicalOperator::Single
             | LogicalOperator::Filter(_)
             | LogicalOperator::Join(_)
@@ -156,8 +320,7 @@ impl LogicalOperator {
             | LogicalOperator::TableReference(_)
             | LogicalOperator::ResolvedTable(_)
             | LogicalOperator::TableInsert(_)
-            | LogicalOperator::NegateFreq(_)
-            | LogicalOperator::FileScan(_) => Box::from(empty()),
+            | LogicalOperator::NegateFreq(_) => Box::from(empty()),
         }
     }
 
@@ -167,6 +330,9 @@ impl LogicalOperator {
         match self {
             LogicalOperator::Project(project) => Box::from(project.expressions.iter_mut()),
             LogicalOperator::GroupBy(group_by) => Box::from(group_by.expressions.iter_mut()),
+            LogicalOperator::Window(window) => Box::from(window.functions.iter_mut()),
+            LogicalOperator::FileScan(file_scan) => Box::from(file_scan.columns.iter_mut()),
+            LogicalOperator::FileCopy(file_copy) => Box::from(file_copy.columns.iter_mut()),
             LogicalOperator::Single
             | LogicalOperator::Filter(_)
             | LogicalOperator::Join(_)
@@ -178,8 +344,7 @@ impl LogicalOperator {
             | LogicalOperator::TableReference(_)
             | LogicalOperator::ResolvedTable(_)
             | LogicalOperator::TableInsert(_)
-            | LogicalOperator::NegateFreq(_)
-            | LogicalOperator::FileScan(_) => Box::from(empty()),
+            | LogicalOperator::NegateFreq(_) => Box::from(empty()),
         }
     }
 
@@ -196,6 +361,9 @@ impl LogicalOperator {
                     .map(|ne| &mut ne.expression)
                     .chain(group_by.key_expressions.iter_mut()),
             ),
+            LogicalOperator::Window(window) => {
+                Box::from(window.functions.iter_mut().map(|ne| &mut ne.expression))
+            }
             LogicalOperator::Filter(filter) => Box::from(once(&mut filter.predicate)),
             LogicalOperator::Values(values) => {
                 Box::from(values.data.iter_mut().flat_map(|row| row.iter_mut()))
@@ -206,6 +374,12 @@ impl LogicalOperator {
                     .map(|se| &mut se.expression),
             ),
             LogicalOperator::Join(join) => Box::from(once(&mut join.on)),
+            LogicalOperator::FileScan(file_scan) => {
+                Box::from(file_scan.columns.iter_mut().map(|ne| &mut ne.expression))
+            }
+            LogicalOperator::FileCopy(file_copy) => {
+                Box::from(file_copy.columns.iter_mut().map(|ne| &mut ne.expression))
+            }
             LogicalOperator::Single
             | LogicalOperator::Limit(_)
             | LogicalOperator::TableAlias(_)
@@ -213,8 +387,17 @@ impl LogicalOperator {
             | LogicalOperator::TableReference(_)
             | LogicalOperator::ResolvedTable(_)
             | LogicalOperator::TableInsert(_)
-            | LogicalOperator::NegateFreq(_)
-            | LogicalOperator::FileScan(_) => Box::from(empty()),
+            | LogicalOperator::NegateFreq(_) => Box::from(empty()),
+        }
+    }
+
+    /// Calls the visitor for every parameter in the operator tree
+    pub fn visit_parameters_mut<F: FnMut(&mut Expression)>(&mut self, visitor: &mut F) {
+        for expr in self.expressions_mut() {
+            expr.visit_parameters_mut(visitor);
+        }
+        for child in self.children_mut() {
+            child.visit_parameters_mut(visitor);
         }
     }
 
@@ -223,6 +406,7 @@ impl LogicalOperator {
         match self {
             LogicalOperator::Project(project) => Box::from(once(project.source.as_mut())),
             LogicalOperator::GroupBy(group_by) => Box::from(once(group_by.source.as_mut())),
+            LogicalOperator::Window(window) => Box::from(once(window.source.as_mut())),
             LogicalOperator::Filter(filter) => Box::from(once(filter.source.as_mut())),
             LogicalOperator::Limit(limit) => Box::from(once(limit.source.as_mut())),
             LogicalOperator::Sort(sort) => Box::from(once(sort.source.as_mut())),
@@ -241,7 +425,8 @@ impl LogicalOperator {
             | LogicalOperator::Values(_)
             | LogicalOperator::TableReference(_)
             | LogicalOperator::ResolvedTable(_)
-            | LogicalOperator::FileScan(_) => Box::from(empty()),
+            | LogicalOperator::FileScan(_)
+            | LogicalOperator::FileCopy(_) => Box::from(empty()),
         }
     }
 }
@@ -1,6 +1,7 @@
-use crate::expr::{Expression, SortExpression};
-use crate::rel::logical::{JoinType, SerdeOptions};
+use crate::expr::{Expression, SortExpression, WindowFrame};
+use crate::rel::logical::{ColumnSchema, JoinType, SerdeOptions, TableSample};
 use data::{Datum, LogicalTimestamp};
+use std::iter::{empty, once};
 use storage::Table;
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -13,12 +14,89 @@ pub enum PointInTimeOperator {
     Sort(Sort),
     UnionAll(UnionAll),
     TableScan(TableScan),
+    TableChanges(TableChanges),
     TableInsert(TableInsert),
     NegateFreq(Box<PointInTimeOperator>),
     SortedGroup(Group),
     HashGroup(Group),
+    Window(Window),
     HashJoin(Join),
     FileScan(FileScan),
+    FileCopy(FileCopy),
+}
+
+impl PointInTimeOperator {
+    /// Returns an iterator over the operators children
+    pub fn children(&self) -> Box<dyn Iterator<Item = &PointInTimeOperator> + '_> {
+        match self {
+            PointInTimeOperator::Project(project) => Box::from(once(project.source.as_ref())),
+            PointInTimeOperator::Filter(filter) => Box::from(once(filter.source.as_ref())),
+            PointInTimeOperator::Limit(limit) => Box::from(once(limit.source.as_ref())),
+            PointInTimeOperator::Sort(sort) => Box::from(once(sort.source.as_ref())),
+            PointInTimeOperator::UnionAll(union_all) => Box::from(union_all.sources.iter()),
+            PointInTimeOperator::TableInsert(table_insert) => {
+                Box::from(once(table_insert.source.as_ref()))
+            }
+            PointInTimeOperator::NegateFreq(source) => Box::from(once(source.as_ref())),
+            PointInTimeOperator::SortedGroup(group) | PointInTimeOperator::HashGroup(group) => {
+                Box::from(once(group.source.as_ref()))
+            }
+            PointInTimeOperator::Window(window) => Box::from(once(window.source.as_ref())),
+            PointInTimeOperator::HashJoin(join) => {
+                Box::from(once(join.left.as_ref()).chain(once(join.right.as_ref())))
+            }
+            PointInTimeOperator::Single
+            | PointInTimeOperator::Values(_)
+            | PointInTimeOperator::TableScan(_)
+            | PointInTimeOperator::TableChanges(_)
+            | PointInTimeOperator::FileScan(_)
+            | PointInTimeOperator::FileCopy(_) => Box::from(empty()),
+        }
+    }
+
+    /// Returns an iterator over the operators children
+    pub fn children_mut(&mut self) -> Box<dyn Iterator<Item = &mut PointInTimeOperator> + '_> {
+        match self {
+            PointInTimeOperator::Project(project) => Box::from(once(project.source.as_mut())),
+            PointInTimeOperator::Filter(filter) => Box::from(once(filter.source.as_mut())),
+            PointInTimeOperator::Limit(limit) => Box::from(once(limit.source.as_mut())),
+            PointInTimeOperator::Sort(sort) => Box::from(once(sort.source.as_mut())),
+            PointInTimeOperator::UnionAll(union_all) => Box::from(union_all.sources.iter_mut()),
+            PointInTimeOperator::TableInsert(table_insert) => {
+                Box::from(once(table_insert.source.as_mut()))
+            }
+            PointInTimeOperator::NegateFreq(source) => Box::from(once(source.as_mut())),
+            PointInTimeOperator::SortedGroup(group) | PointInTimeOperator::HashGroup(group) => {
+                Box::from(once(group.source.as_mut()))
+            }
+            PointInTimeOperator::Window(window) => Box::from(once(window.source.as_mut())),
+            PointInTimeOperator::HashJoin(join) => {
+                Box::from(once(join.left.as_mut()).chain(once(join.right.as_mut())))
+            }
+            PointInTimeOperator::Single
+            | PointInTimeOperator::Values(_)
+            | PointInTimeOperator::TableScan(_)
+            | PointInTimeOperator::TableChanges(_)
+            | PointInTimeOperator::FileScan(_)
+            | PointInTimeOperator::FileCopy(_) => Box::from(empty()),
+        }
+    }
+}
+
+impl PointInTimeOperator {
+    /// Sets the timestamp every table scan in the tree reads as of, other than those already
+    /// pinned to one
+    pub fn set_scan_timestamps(&mut self, timestamp: LogicalTimestamp) {
+        if let PointInTimeOperator::TableScan(table_scan) = self {
+            // Tables read AS OF TIMESTAMP keep their own
+            if table_scan.timestamp == LogicalTimestamp::MAX {
+                table_scan.timestamp = timestamp;
+            }
+        }
+        for child in self.children_mut() {
+            child.set_scan_timestamps(timestamp);
+        }
+    }
 }
 
 impl Default for PointInTimeOperator {
@@ -62,12 +140,38 @@ pub struct UnionAll {
 pub struct TableScan {
     pub table: Table,
     pub timestamp: LogicalTimestamp,
+    // The ranges of the table to scan, in order. An empty list means nothing will be read.
+    pub ranges: Vec<ScanRange>,
+    pub sample: Option<TableSample>,
+}
+
+/// Reads the rows of the table that changed between from and to, with the change to their freq
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TableChanges {
+    pub table: Table,
+    pub from: LogicalTimestamp,
+    pub to: LogicalTimestamp,
+}
+
+/// An inclusive range of the table to scan, the bounds are prefixes of the table's primary key
+/// and are in the order of the primary key, ie from > to for descending keys.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct ScanRange {
+    pub from: Option<Vec<Datum<'static>>>,
+    pub to: Option<Vec<Datum<'static>>>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct TableInsert {
     pub table: Table,
     pub source: Box<PointInTimeOperator>,
+    // Set when the source resumes from its last position, the positions of the rows inserted
+    // are written alongside them.
+    pub source_offsets: Option<Table>,
+    // Rows are written in batches of at most this many rows
+    pub batch_rows: usize,
+    // Rows with json values that don't match their column's schema fail the insert
+    pub json_schemas: Vec<ColumnSchema>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -83,6 +187,19 @@ pub struct Group {
     pub key_len: usize,
 }
 
+/// Outputs each source row followed by the values of the window functions for it, the source
+/// has to be sorted by the partition keys and then the ORDER BY of the window.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Window {
+    pub source: Box<PointInTimeOperator>,
+    // Rows are in the same partition when these all match
+    pub partition_by: Vec<Expression>,
+    // Rows in a partition are peers when these all match too
+    pub order_by: Vec<Expression>,
+    // The aggregate of each window function along with its frame
+    pub functions: Vec<(Expression, WindowFrame)>,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Join {
     pub left: Box<PointInTimeOperator>,
@@ -96,4 +213,18 @@ pub struct Join {
 pub struct FileScan {
     pub directory: String,
     pub serde_options: SerdeOptions,
+    // Evaluated against the document, when empty the document itself is output.
+    pub columns: Vec<Expression>,
+    pub dead_letters: Option<Table>,
+    pub source_offsets: Option<Table>,
+    pub paused_sources: Option<Table>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FileCopy {
+    pub path: String,
+    pub serde_options: SerdeOptions,
+    // Evaluated against the record's fields as text, nulls for empty fields.
+    pub columns: Vec<Expression>,
+    pub dead_letters: Option<Table>,
 }
@@ -1,5 +1,6 @@
-use crate::rel::logical::LogicalOperator;
-use data::DataType;
+use crate::expr::Expression;
+use crate::rel::logical::{LogicalOperator, SerdeOptions};
+use data::{DataType, SortOrder};
 
 /// The top level structure parsed, could be a query or DDL statement.
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -8,19 +9,93 @@ pub enum Statement {
     ShowFunctions,
     ShowDatabases,
     ShowTables,
+    ShowWarnings,
+    ShowProcesslist,
     CreateDatabase(CreateDatabase),
     DropDatabase(String),
     UseDatabase(String),
     CreateTable(CreateTable),
     CreateView(CreateView),
+    CreateMaterializedView(CreateView),
+    RefreshMaterializedView(RefreshMaterializedView),
+    CreateExternalTable(CreateExternalTable),
     CompactTable(CompactTable),
+    AnalyzeTable(AnalyzeTable),
     DropTable(DropTable),
     Explain(Explain),
+    ExplainMaintenance(ExplainMaintenance),
+    SetVariable(SetVariable),
+    SetUserVariable(SetUserVariable),
+    InferSchema(InferSchema),
+    AlterSource(AlterSource),
+    AlterTableStorageTier(AlterTableStorageTier),
+    AlterTableModifyColumn(AlterTableModifyColumn),
+    AlterTableColumnMask(AlterTableColumnMask),
+    AlterTableColumnSchema(AlterTableColumnSchema),
+    AlterUser(AlterUser),
+    Grant(Grant),
+    CreateSink(CreateSink),
+    FlushSink(FlushSink),
+    DropSink(DropSink),
+    CreateProcedure(CreateProcedure),
+    CallProcedure(CallProcedure),
+    DropProcedure(DropProcedure),
+    CreateEvent(CreateEvent),
+    DropEvent(DropEvent),
+    CreatePolicy(CreatePolicy),
+    DropPolicy(DropPolicy),
+    CreateIndex(CreateIndex),
+    // KILL [CONNECTION | QUERY] connection id
+    Kill(u32),
+    // BEGIN or START TRANSACTION
+    Begin,
+    Commit,
+    Rollback,
+    // VACUUM, removes the old versions of rows that have fallen out of the retention window
+    Vacuum,
+}
+
+impl Statement {
+    /// Calls the visitor for every parameter in the statement
+    pub fn visit_parameters_mut<F: FnMut(&mut Expression)>(&mut self, visitor: &mut F) {
+        match self {
+            Statement::Query(query) => query.visit_parameters_mut(visitor),
+            Statement::Explain(explain) => explain.operator.visit_parameters_mut(visitor),
+            Statement::CreateView(create_view) | Statement::CreateMaterializedView(create_view) => {
+                create_view.query.visit_parameters_mut(visitor)
+            }
+            _ => {}
+        }
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct Explain {
     pub operator: LogicalOperator,
+    // EXPLAIN TYPES, annotates every part of each expression with its type
+    pub types: bool,
+}
+
+/// Explains how a view would be kept up to date as its inputs change,
+/// ie EXPLAIN MAINTENANCE FOR VIEW totals
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ExplainMaintenance {
+    pub database: Option<String>,
+    pub name: String,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct SetVariable {
+    pub name: String,
+    pub value: String,
+}
+
+/// SET @name = expr, the query's single value is kept on the session for later statements to
+/// reference as @name
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct SetUserVariable {
+    pub name: String,
+    pub query: LogicalOperator,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -33,6 +108,13 @@ pub struct CreateTable {
     pub database: Option<String>,
     pub name: String,
     pub columns: Vec<(String, DataType)>,
+    // PRIMARY KEY (c1 DESC, c2), the columns the rows are keyed on and their sort orders.
+    // Without one every column is part of the key, ascending
+    pub primary_key: Option<Vec<(String, SortOrder)>>,
+    // WITH (CHECKSUM = TRUE), the rows get checksummed as they're written
+    pub checksums: bool,
+    // WITH (NULL_BITMAP = TRUE), the rows are written with a bitmap of their null columns
+    pub null_bitmap: bool,
 }
 
 /// Create view we grab the raw text as well as the logical operator.
@@ -44,6 +126,72 @@ pub struct CreateView {
     pub name: String,
     pub sql: String,
     pub query: LogicalOperator,
+    pub state_ttl: Option<StateTtl>,
+}
+
+/// Materialized views keyed on an event time column can be given a state ttl, once a row's
+/// time falls more than ttl_ms behind the latest time in the view its group is treated as
+/// closed, late changes to it are then handled as per late_data.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct StateTtl {
+    pub ttl_ms: i64,
+    pub time_column: String,
+    pub late_data: LateData,
+}
+
+/// LATE_DATA = 'drop' | 'corrections' | 'retract'
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum LateData {
+    Drop,
+    Corrections,
+    Retract,
+}
+
+/// Recomputes a materialized view, CONCURRENTLY is accepted for compatibility but as readers
+/// never block on a refresh it behaves the same either way.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct RefreshMaterializedView {
+    pub database: Option<String>,
+    pub name: String,
+    pub concurrently: bool,
+}
+
+/// An external table is a typed view over the files in a directory, the columns are either
+/// given or inferred by sampling the files.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct CreateExternalTable {
+    pub database: Option<String>,
+    pub name: String,
+    pub directory: String,
+    pub serde_options: SerdeOptions,
+    pub columns: ExternalColumns,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum ExternalColumns {
+    Given(Vec<ExternalColumn>),
+    // Number of documents to sample
+    Auto(usize),
+}
+
+/// A column for an external table, the name can be a dotted path into the document
+/// ie user.address.city, failing that an explicit json path can be given.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ExternalColumn {
+    pub name: String,
+    pub datatype: DataType,
+    pub path: Option<String>,
+}
+
+/// The number of documents sampled when inferring a schema if not given.
+pub const DEFAULT_SAMPLE_SIZE: usize = 1000;
+
+/// Samples the documents in a directory and returns the column names, types and paths found.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct InferSchema {
+    pub directory: String,
+    pub serde_options: SerdeOptions,
+    pub sample_size: usize,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -57,3 +205,213 @@ pub struct CompactTable {
     pub database: Option<String>,
     pub name: String,
 }
+
+/// Gathers the stats the planner uses to estimate row counts, ie ANALYZE TABLE orders
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct AnalyzeTable {
+    pub database: Option<String>,
+    pub name: String,
+}
+
+/// Pauses or resumes the file sources read by a view/external table,
+/// ie ALTER SOURCE events PAUSE
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct AlterSource {
+    pub database: Option<String>,
+    pub name: String,
+    pub paused: bool,
+}
+
+/// Moves a table between rocksdb and files, cold tables are read back through a file scan,
+/// ie ALTER TABLE events SET STORAGE TIER COLD LOCATION '/archive/events'
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct AlterTableStorageTier {
+    pub database: Option<String>,
+    pub name: String,
+    pub tier: StorageTier,
+}
+
+/// Changes the type of a table's column, the rows are copied into a shadow table with the
+/// column cast to its new type which is then swapped in for the table,
+/// ie ALTER TABLE events MODIFY COLUMN amount DECIMAL(12, 2)
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct AlterTableModifyColumn {
+    pub database: Option<String>,
+    pub name: String,
+    pub column: String,
+    pub datatype: DataType,
+}
+
+/// Sets or drops (when mask is None) the mask of a table's column, users other than the table's
+/// owner see the mask expression's value in place of the column's, along with the mask's sql,
+/// ie ALTER TABLE customers ALTER COLUMN card SET MASK USING (mask_text(card, 4))
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct AlterTableColumnMask {
+    pub database: Option<String>,
+    pub name: String,
+    pub column: String,
+    pub mask: Option<(Expression, String)>,
+}
+
+/// Sets or drops (when schema is None) the json schema of a table's json column, rows inserted
+/// into the table fail unless the column's value matches the schema,
+/// ie ALTER TABLE orders ALTER COLUMN doc SET SCHEMA '{"type": "object", "required": ["id"]}'
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct AlterTableColumnSchema {
+    pub database: Option<String>,
+    pub name: String,
+    pub column: String,
+    pub schema: Option<String>,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum StorageTier {
+    Hot,
+    // The location the table's rows are written out to
+    Cold(String),
+}
+
+/// Sets or resets (when value is None) the default for a session variable that a user's
+/// connections start with, ie ALTER USER bob SET time_zone = '+02:00'
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct AlterUser {
+    pub user: String,
+    pub name: String,
+    pub value: Option<String>,
+}
+
+/// Grants (or revokes) a privilege on an object to a user,
+/// ie GRANT EXECUTE ON DIRECTORY 'data/' TO bob
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Grant {
+    pub revoke: bool,
+    pub object: GrantObject,
+    pub user: String,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum GrantObject {
+    // The directory file sources can read from, along with everything under it
+    Directory(String),
+}
+
+/// A sink writes the changes to a table/view out as change events,
+/// ie CREATE SINK s FROM orders INTO FILE('orders.json') FORMAT JSON
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct CreateSink {
+    pub database: Option<String>,
+    pub name: String,
+    pub source_database: Option<String>,
+    pub source: String,
+    pub target: SinkTarget,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum SinkTarget {
+    File(String),
+    // The broker and topic options as given
+    Kafka(Vec<(String, String)>),
+}
+
+/// Writes out the changes made since a sink was last flushed
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct FlushSink {
+    pub database: Option<String>,
+    pub name: String,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct DropSink {
+    pub database: Option<String>,
+    pub name: String,
+}
+
+/// CREATE PROCEDURE [db.]name() BEGIN ... END, the body's sql is what gets stored, it's parsed
+/// again each time the procedure is called
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct CreateProcedure {
+    pub database: Option<String>,
+    pub name: String,
+    pub body: Vec<ProcedureStatement>,
+    pub body_sql: String,
+}
+
+/// A statement in a procedure's body, procedures hold their state in user variables
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum ProcedureStatement {
+    Statement(Box<Statement>),
+    If(If),
+    While(While),
+}
+
+/// IF cond THEN ... [ELSEIF cond THEN ...] [ELSE ...] END IF, the first branch whose condition
+/// is true is run, otherwise the else
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct If {
+    pub branches: Vec<(Expression, Vec<ProcedureStatement>)>,
+    pub otherwise: Vec<ProcedureStatement>,
+}
+
+/// WHILE cond DO ... END WHILE
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct While {
+    pub condition: Expression,
+    pub body: Vec<ProcedureStatement>,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct CallProcedure {
+    pub database: Option<String>,
+    pub name: String,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct DropProcedure {
+    pub database: Option<String>,
+    pub name: String,
+}
+
+/// CREATE EVENT [db.]name ON SCHEDULE EVERY '5 minutes' DO statement, like procedures it's the
+/// statement's sql that gets stored
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct CreateEvent {
+    pub database: Option<String>,
+    pub name: String,
+    pub every_ms: i64,
+    pub statement: Box<Statement>,
+    pub sql: String,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct DropEvent {
+    pub database: Option<String>,
+    pub name: String,
+}
+
+/// CREATE POLICY name ON [db.]table USING (predicate), users other than the table's owner only
+/// see the rows matching the predicates of all of the table's policies
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct CreatePolicy {
+    pub name: String,
+    pub database: Option<String>,
+    pub table: String,
+    pub predicate: Expression,
+    pub predicate_sql: String,
+}
+
+/// CREATE INDEX name ON [db.]table (column), queries filtering on the column can scan the index
+/// rather than the whole table
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct CreateIndex {
+    pub name: String,
+    pub database: Option<String>,
+    pub table: String,
+    pub column: String,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct DropPolicy {
+    pub name: String,
+    pub database: Option<String>,
+    pub table: String,
+}
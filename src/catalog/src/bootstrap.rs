@@ -1,5 +1,14 @@
-use crate::{Catalog, CatalogError, DATABASES_TABLE_ID, PREFIX_METADATA_TABLE_ID, TABLES_TABLE_ID};
-use data::{DataType, Datum, SortOrder};
+use crate::{
+    Catalog, CatalogError, CHECKSUMMED_TABLES_TABLE_ID, COLUMN_MASKS_TABLE_ID,
+    COLUMN_SCHEMAS_TABLE_ID, COLUMN_STATS_TABLE_ID, CORRUPTED_ROWS_TABLE_ID, DATABASES_TABLE_ID,
+    DEAD_LETTERS_COLUMN_COUNT, DEAD_LETTERS_TABLE_ID, EVENTS_TABLE_ID, INCREMENTAL_VIEWS_TABLE_ID,
+    INDEXES_TABLE_ID, NULL_BITMAP_TABLES_TABLE_ID, PAUSED_SOURCES_TABLE_ID, POLICIES_TABLE_ID,
+    PREFIX_METADATA_TABLE_ID, PRIMARY_KEYS_TABLE_ID, PRIVILEGES_TABLE_ID, PROCEDURES_TABLE_ID,
+    SCHEMA_MIGRATIONS_TABLE_ID, SINKS_TABLE_ID, SOURCE_OFFSETS_TABLE_ID, STATE_TTLS_TABLE_ID,
+    STORAGE_STATS_TABLE_ID, TABLES_TABLE_ID, TABLE_OWNERS_TABLE_ID, USER_DEFAULTS_TABLE_ID,
+    WORKLOAD_STATS_TABLE_ID,
+};
+use data::{DataType, Datum, SortOrder, DECIMAL_MAX_PRECISION};
 
 impl Catalog {
     /// Function used on first boot to initialize system tables
@@ -16,7 +25,7 @@ impl Catalog {
             )?
             .is_some()
         {
-            return Ok(());
+            return self.bootstrap_ingestion_tables();
         }
 
         self.create_database_impl("incresql")?;
@@ -63,6 +72,367 @@ impl Catalog {
             true,
         )?;
 
+        self.bootstrap_ingestion_tables()
+    }
+
+    /// The tables used by file sources came after the other system tables so they're created
+    /// separately for databases that were initialized before they existed.
+    fn bootstrap_ingestion_tables(&mut self) -> Result<(), CatalogError> {
+        if !self.table_exists("incresql", "dead_letters")? {
+            self.create_table_impl(
+                "incresql",
+                "dead_letters",
+                DEAD_LETTERS_TABLE_ID,
+                &[
+                    ("logged_at".to_string(), DataType::Timestamp),
+                    ("source".to_string(), DataType::Text),
+                    ("offset".to_string(), DataType::BigInt),
+                    ("raw".to_string(), DataType::Text),
+                    ("error".to_string(), DataType::Text),
+                ],
+                &[SortOrder::Asc; DEAD_LETTERS_COLUMN_COUNT],
+                true,
+            )?;
+        }
+
+        if !self.table_exists("incresql", "source_offsets")? {
+            self.create_table_impl(
+                "incresql",
+                "source_offsets",
+                SOURCE_OFFSETS_TABLE_ID,
+                &[
+                    ("source".to_string(), DataType::Text),
+                    ("offset".to_string(), DataType::BigInt),
+                ],
+                &[SortOrder::Asc],
+                true,
+            )?;
+        }
+
+        if !self.table_exists("incresql", "paused_sources")? {
+            self.create_table_impl(
+                "incresql",
+                "paused_sources",
+                PAUSED_SOURCES_TABLE_ID,
+                &[("source".to_string(), DataType::Text)],
+                &[SortOrder::Asc],
+                true,
+            )?;
+        }
+
+        if !self.table_exists("incresql", "sinks")? {
+            self.create_table_impl(
+                "incresql",
+                "sinks",
+                SINKS_TABLE_ID,
+                &[
+                    ("database".to_string(), DataType::Text),
+                    ("name".to_string(), DataType::Text),
+                    ("source_database".to_string(), DataType::Text),
+                    ("source".to_string(), DataType::Text),
+                    ("path".to_string(), DataType::Text),
+                    ("emitted_at".to_string(), DataType::BigInt),
+                ],
+                &[SortOrder::Asc, SortOrder::Asc],
+                true,
+            )?;
+        }
+
+        if !self.table_exists("incresql", "state_ttls")? {
+            self.create_table_impl(
+                "incresql",
+                "state_ttls",
+                STATE_TTLS_TABLE_ID,
+                &[
+                    ("database".to_string(), DataType::Text),
+                    ("name".to_string(), DataType::Text),
+                    ("ttl_ms".to_string(), DataType::BigInt),
+                    ("time_column".to_string(), DataType::Text),
+                    ("late_data".to_string(), DataType::Text),
+                ],
+                &[SortOrder::Asc, SortOrder::Asc],
+                true,
+            )?;
+        }
+
+        if !self.table_exists("incresql", "storage_stats")? {
+            self.create_table_impl(
+                "incresql",
+                "storage_stats",
+                STORAGE_STATS_TABLE_ID,
+                &[
+                    ("name".to_string(), DataType::Text),
+                    (
+                        "value".to_string(),
+                        DataType::Decimal(DECIMAL_MAX_PRECISION, 4),
+                    ),
+                ],
+                &[SortOrder::Asc],
+                true,
+            )?;
+        }
+
+        if !self.table_exists("incresql", "corrupted_rows")? {
+            self.create_table_impl(
+                "incresql",
+                "corrupted_rows",
+                CORRUPTED_ROWS_TABLE_ID,
+                &[
+                    ("table_id".to_string(), DataType::BigInt),
+                    ("key".to_string(), DataType::ByteA),
+                    ("value".to_string(), DataType::ByteA),
+                    ("detected_at".to_string(), DataType::Timestamp),
+                ],
+                &[SortOrder::Asc, SortOrder::Asc],
+                true,
+            )?;
+        }
+
+        if !self.table_exists("incresql", "checksummed_tables")? {
+            self.create_table_impl(
+                "incresql",
+                "checksummed_tables",
+                CHECKSUMMED_TABLES_TABLE_ID,
+                &[("table_id".to_string(), DataType::BigInt)],
+                &[SortOrder::Asc],
+                true,
+            )?;
+        }
+
+        if !self.table_exists("incresql", "user_defaults")? {
+            self.create_table_impl(
+                "incresql",
+                "user_defaults",
+                USER_DEFAULTS_TABLE_ID,
+                &[
+                    ("user".to_string(), DataType::Text),
+                    ("name".to_string(), DataType::Text),
+                    ("value".to_string(), DataType::Text),
+                ],
+                &[SortOrder::Asc, SortOrder::Asc],
+                true,
+            )?;
+        }
+
+        if !self.table_exists("incresql", "workload_stats")? {
+            self.create_table_impl(
+                "incresql",
+                "workload_stats",
+                WORKLOAD_STATS_TABLE_ID,
+                &[
+                    ("class".to_string(), DataType::Text),
+                    ("statements".to_string(), DataType::BigInt),
+                    ("elapsed_ms".to_string(), DataType::BigInt),
+                    ("yielded_ms".to_string(), DataType::BigInt),
+                ],
+                &[SortOrder::Asc],
+                true,
+            )?;
+        }
+
+        if !self.table_exists("incresql", "schema_migrations")? {
+            self.create_table_impl(
+                "incresql",
+                "schema_migrations",
+                SCHEMA_MIGRATIONS_TABLE_ID,
+                &[
+                    ("database".to_string(), DataType::Text),
+                    ("table".to_string(), DataType::Text),
+                    ("column".to_string(), DataType::Text),
+                    ("datatype".to_string(), DataType::Text),
+                    ("rows_total".to_string(), DataType::BigInt),
+                    ("rows_copied".to_string(), DataType::BigInt),
+                    ("status".to_string(), DataType::Text),
+                ],
+                &[SortOrder::Asc, SortOrder::Asc],
+                true,
+            )?;
+        }
+
+        if !self.table_exists("incresql", "column_stats")? {
+            self.create_table_impl(
+                "incresql",
+                "column_stats",
+                COLUMN_STATS_TABLE_ID,
+                &[
+                    ("table_id".to_string(), DataType::BigInt),
+                    ("column".to_string(), DataType::Text),
+                    ("database".to_string(), DataType::Text),
+                    ("table".to_string(), DataType::Text),
+                    ("stats".to_string(), DataType::Json),
+                ],
+                &[SortOrder::Asc, SortOrder::Asc],
+                true,
+            )?;
+        }
+
+        if !self.table_exists("incresql", "null_bitmap_tables")? {
+            self.create_table_impl(
+                "incresql",
+                "null_bitmap_tables",
+                NULL_BITMAP_TABLES_TABLE_ID,
+                &[("table_id".to_string(), DataType::BigInt)],
+                &[SortOrder::Asc],
+                true,
+            )?;
+        }
+
+        if !self.table_exists("incresql", "procedures")? {
+            self.create_table_impl(
+                "incresql",
+                "procedures",
+                PROCEDURES_TABLE_ID,
+                &[
+                    ("database".to_string(), DataType::Text),
+                    ("name".to_string(), DataType::Text),
+                    ("sql".to_string(), DataType::Text),
+                ],
+                &[SortOrder::Asc, SortOrder::Asc],
+                true,
+            )?;
+        }
+
+        if !self.table_exists("incresql", "scheduled_events")? {
+            self.create_table_impl(
+                "incresql",
+                "scheduled_events",
+                EVENTS_TABLE_ID,
+                &[
+                    ("database".to_string(), DataType::Text),
+                    ("name".to_string(), DataType::Text),
+                    ("every_ms".to_string(), DataType::BigInt),
+                    ("sql".to_string(), DataType::Text),
+                    ("last_run_ms".to_string(), DataType::BigInt),
+                    ("last_error".to_string(), DataType::Text),
+                ],
+                &[SortOrder::Asc, SortOrder::Asc],
+                true,
+            )?;
+        }
+
+        if !self.table_exists("incresql", "incremental_views")? {
+            self.create_table_impl(
+                "incresql",
+                "incremental_views",
+                INCREMENTAL_VIEWS_TABLE_ID,
+                &[
+                    ("database".to_string(), DataType::Text),
+                    ("name".to_string(), DataType::Text),
+                    ("maintained_at_ms".to_string(), DataType::BigInt),
+                ],
+                &[SortOrder::Asc, SortOrder::Asc],
+                true,
+            )?;
+        }
+
+        if !self.table_exists("incresql", "policies")? {
+            self.create_table_impl(
+                "incresql",
+                "policies",
+                POLICIES_TABLE_ID,
+                &[
+                    ("database".to_string(), DataType::Text),
+                    ("table".to_string(), DataType::Text),
+                    ("name".to_string(), DataType::Text),
+                    ("predicate".to_string(), DataType::Text),
+                ],
+                &[SortOrder::Asc, SortOrder::Asc, SortOrder::Asc],
+                true,
+            )?;
+        }
+
+        if !self.table_exists("incresql", "column_masks")? {
+            self.create_table_impl(
+                "incresql",
+                "column_masks",
+                COLUMN_MASKS_TABLE_ID,
+                &[
+                    ("database".to_string(), DataType::Text),
+                    ("table".to_string(), DataType::Text),
+                    ("column".to_string(), DataType::Text),
+                    ("mask".to_string(), DataType::Text),
+                ],
+                &[SortOrder::Asc, SortOrder::Asc, SortOrder::Asc],
+                true,
+            )?;
+        }
+
+        if !self.table_exists("incresql", "table_owners")? {
+            self.create_table_impl(
+                "incresql",
+                "table_owners",
+                TABLE_OWNERS_TABLE_ID,
+                &[
+                    ("database".to_string(), DataType::Text),
+                    ("name".to_string(), DataType::Text),
+                    ("owner".to_string(), DataType::Text),
+                ],
+                &[SortOrder::Asc, SortOrder::Asc],
+                true,
+            )?;
+        }
+
+        if !self.table_exists("incresql", "privileges")? {
+            self.create_table_impl(
+                "incresql",
+                "privileges",
+                PRIVILEGES_TABLE_ID,
+                &[
+                    ("user".to_string(), DataType::Text),
+                    ("privilege".to_string(), DataType::Text),
+                    ("object_type".to_string(), DataType::Text),
+                    ("object".to_string(), DataType::Text),
+                ],
+                &[SortOrder::Asc; 4],
+                true,
+            )?;
+        }
+
+        if !self.table_exists("incresql", "primary_keys")? {
+            self.create_table_impl(
+                "incresql",
+                "primary_keys",
+                PRIMARY_KEYS_TABLE_ID,
+                &[
+                    ("table_id".to_string(), DataType::BigInt),
+                    ("columns".to_string(), DataType::Json),
+                ],
+                &[SortOrder::Asc],
+                true,
+            )?;
+        }
+
+        if !self.table_exists("incresql", "indexes")? {
+            self.create_table_impl(
+                "incresql",
+                "indexes",
+                INDEXES_TABLE_ID,
+                &[
+                    ("table_id".to_string(), DataType::BigInt),
+                    ("name".to_string(), DataType::Text),
+                    ("index_id".to_string(), DataType::BigInt),
+                    ("column".to_string(), DataType::Text),
+                ],
+                &[SortOrder::Asc, SortOrder::Asc],
+                true,
+            )?;
+        }
+
+        if !self.table_exists("incresql", "column_schemas")? {
+            self.create_table_impl(
+                "incresql",
+                "column_schemas",
+                COLUMN_SCHEMAS_TABLE_ID,
+                &[
+                    ("database".to_string(), DataType::Text),
+                    ("table".to_string(), DataType::Text),
+                    ("column".to_string(), DataType::Text),
+                    ("schema".to_string(), DataType::Text),
+                ],
+                &[SortOrder::Asc, SortOrder::Asc, SortOrder::Asc],
+                true,
+            )?;
+        }
         Ok(())
     }
 }
@@ -9,6 +9,26 @@ pub enum CatalogError {
     DatabaseAlreadyExists(String),
     DatabaseNotFound(String),
     DatabaseNotEmpty(String),
+    SinkAlreadyExists(String, String),
+    SinkNotFound(String, String),
+    ProcedureAlreadyExists(String, String),
+    ProcedureNotFound(String, String),
+    EventAlreadyExists(String, String),
+    EventNotFound(String, String),
+    // The policy name then the database and table it's on
+    PolicyAlreadyExists(String, String, String),
+    PolicyNotFound(String, String, String),
+    // The database, table and column
+    ColumnMaskNotFound(String, String, String),
+    ColumnSchemaNotFound(String, String, String),
+    ColumnNotFound(String, String, String),
+    // The index name then the database and table it's on
+    IndexAlreadyExists(String, String, String),
+    // The privilege, what it's on and the user
+    PrivilegeNotGranted(String, String, String),
+    NotAMaterializedView(String, String),
+    NotATable(String, String),
+    NotAColdTable(String, String),
 }
 
 impl Display for CatalogError {
@@ -31,6 +51,62 @@ impl Display for CatalogError {
                 "Database {} is not empty, please remote all contained tables first",
                 db
             )),
+            CatalogError::SinkAlreadyExists(db, sink) => {
+                f.write_fmt(format_args!("Sink {}.{} already exists", db, sink))
+            }
+            CatalogError::SinkNotFound(db, sink) => {
+                f.write_fmt(format_args!("Sink {}.{} not found", db, sink))
+            }
+            CatalogError::ProcedureAlreadyExists(db, procedure) => f.write_fmt(format_args!(
+                "Procedure {}.{} already exists",
+                db, procedure
+            )),
+            CatalogError::ProcedureNotFound(db, procedure) => {
+                f.write_fmt(format_args!("Procedure {}.{} not found", db, procedure))
+            }
+            CatalogError::EventAlreadyExists(db, event) => {
+                f.write_fmt(format_args!("Event {}.{} already exists", db, event))
+            }
+            CatalogError::EventNotFound(db, event) => {
+                f.write_fmt(format_args!("Event {}.{} not found", db, event))
+            }
+            CatalogError::PolicyAlreadyExists(name, db, table) => f.write_fmt(format_args!(
+                "Policy {} on {}.{} already exists",
+                name, db, table
+            )),
+            CatalogError::PolicyNotFound(name, db, table) => f.write_fmt(format_args!(
+                "Policy {} on {}.{} not found",
+                name, db, table
+            )),
+            CatalogError::ColumnMaskNotFound(db, table, column) => f.write_fmt(format_args!(
+                "Column {}.{}.{} isn't masked",
+                db, table, column
+            )),
+            CatalogError::ColumnSchemaNotFound(db, table, column) => f.write_fmt(format_args!(
+                "Column {}.{}.{} doesn't have a json schema",
+                db, table, column
+            )),
+            CatalogError::ColumnNotFound(db, table, column) => f.write_fmt(format_args!(
+                "Column {} not found in {}.{}",
+                column, db, table
+            )),
+            CatalogError::IndexAlreadyExists(name, db, table) => f.write_fmt(format_args!(
+                "Index {} on {}.{} already exists",
+                name, db, table
+            )),
+            CatalogError::PrivilegeNotGranted(privilege, object, user) => f.write_fmt(
+                format_args!("{} on {} isn't granted to {}", privilege, object, user),
+            ),
+            CatalogError::NotAMaterializedView(db, view) => {
+                f.write_fmt(format_args!("{}.{} is not a materialized view", db, view))
+            }
+            CatalogError::NotATable(db, table) => {
+                f.write_fmt(format_args!("{}.{} is not a table", db, table))
+            }
+            CatalogError::NotAColdTable(db, table) => f.write_fmt(format_args!(
+                "{}.{} is not in the cold storage tier",
+                db, table
+            )),
         }
     }
 }
@@ -1,13 +1,22 @@
 mod bootstrap;
 use data::json::JsonBuilder;
-use data::{DataType, Datum, LogicalTimestamp, SortOrder, TupleIter};
+use data::rust_decimal::prelude::ToPrimitive;
+use data::rust_decimal::Decimal;
+use data::{Clock, DataType, Datum, LogicalTimestamp, SortOrder, TupleIter};
 use std::convert::TryFrom;
-use storage::{Storage, StorageError, Table};
+use storage::{Storage, StorageError, Table, Writer, SYSTEM_TABLE_ID_LIMIT};
 
 mod error;
+mod stats;
+mod workload;
 pub use error::*;
+pub use stats::*;
 use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+pub use workload::*;
 
 /// The catalog is responsible for the lifecycles and naming of all the
 /// database objects.
@@ -24,30 +33,289 @@ pub struct Catalog {
     // Table listing tables
     // database_name:text(pk), table_name:text(pk), type:text, sql:text, sql_context:text, table_id:bigint, columns:json, system:bool
     tables_table: Table,
+    // Rows file sources couldn't decode
+    // logged_at:timestamp(pk), source:text(pk), offset:bigint(pk), raw:text(pk), error:text(pk)
+    dead_letters_table: Table,
+    // The position each file source has been ingested up to
+    // source:text(pk), offset:bigint
+    source_offsets_table: Table,
+    // File sources that have been paused
+    // source:text(pk)
+    paused_sources_table: Table,
+    // Sinks and how far through their source's changes they've written
+    // database:text(pk), name:text(pk), source_database:text, source:text, path:text, emitted_at:bigint
+    sinks_table: Table,
+    // The state ttls of materialized views that have one
+    // database:text(pk), name:text(pk), ttl_ms:bigint, time_column:text, late_data:text
+    state_ttls_table: Table,
+    // The storage engine's statistics, rewritten each time the table's looked up
+    // name:text(pk), value:decimal
+    storage_stats_table: Table,
+    // The tables created with checksums
+    // table_id:bigint(pk)
+    checksummed_tables_table: Table,
+    // The session variables each user's connections start with
+    // user:text(pk), name:text(pk), value:text
+    user_defaults_table: Table,
+    // The time spent by each workload class, rewritten each time the table's looked up
+    // class:text(pk), statements:bigint, elapsed_ms:bigint, yielded_ms:bigint
+    workload_stats_table: Table,
+    workload_stats: Arc<WorkloadStats>,
+    // The progress of the latest column type change made to each table
+    // database:text(pk), table:text(pk), column:text, datatype:text, rows_total:bigint,
+    // rows_copied:bigint, status:text
+    schema_migrations_table: Table,
+    // The stats ANALYZE gathered for each column of a table
+    // table_id:bigint(pk), column:text(pk), database:text, table:text, stats:json
+    column_stats_table: Table,
+    // The tables created with null bitmaps
+    // table_id:bigint(pk)
+    null_bitmap_tables_table: Table,
+    // Stored procedures, the sql of their bodies
+    // database:text(pk), name:text(pk), sql:text
+    procedures_table: Table,
+    // Statements run on a schedule, last_run_ms is when they were last run (or created)
+    // database:text(pk), name:text(pk), every_ms:bigint, sql:text, last_run_ms:bigint,
+    // last_error:text
+    events_table: Table,
+    // The materialized views that are maintained incrementally and the timestamp their rows are
+    // up to date as of
+    // database:text(pk), name:text(pk), maintained_at_ms:bigint
+    incremental_views_table: Table,
+    // Row level security, users other than a table's owner only see the rows matching the
+    // predicates of all of its policies
+    // database:text(pk), table:text(pk), name:text(pk), predicate:text
+    policies_table: Table,
+    // The user that created each table
+    // database:text(pk), name:text(pk), owner:text
+    table_owners_table: Table,
+    // The sql of the expressions users other than a table's owner see in place of its columns
+    // database:text(pk), table:text(pk), column:text(pk), mask:text
+    column_masks_table: Table,
+    // The privileges granted to each user, ie EXECUTE on the DIRECTORY 'data/'
+    // user:text(pk), privilege:text(pk), object_type:text(pk), object:text(pk)
+    privileges_table: Table,
+    // The columns of the tables created with a primary key, in key order
+    // table_id:bigint(pk), columns:json
+    primary_keys_table: Table,
+    // The secondary indexes on tables, each is a table of its own holding the table's rows keyed
+    // on the column first
+    // table_id:bigint(pk), name:text(pk), index_id:bigint, column:text
+    indexes_table: Table,
+    // The json schemas the values inserted into tables' json columns have to match
+    // database:text(pk), table:text(pk), column:text(pk), schema:text
+    column_schemas_table: Table,
+    // Bumped each time a table or view is created or dropped
+    version: u64,
+    // The items looked up since the version was last bumped, keyed by database then name, saves
+    // going to storage and parsing their columns for every query that uses them
+    item_cache: RwLock<HashMap<String, HashMap<String, CatalogItem>>>,
 }
 
 /// Represents an item returned by the catalog
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Clone)]
 pub struct CatalogItem {
     pub columns: Vec<(String, DataType)>,
     pub item: TableOrView,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Clone)]
 pub enum TableOrView {
     Table(Table),
     View(View),
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Clone)]
 pub struct View {
     pub sql: String,
     pub db_context: String,
 }
 
+/// A sink writes the changes made to a table or view out to a file, emitted_at is the
+/// timestamp (in ms) it has written changes up to, None if it's never been flushed.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Sink {
+    pub database: String,
+    pub name: String,
+    pub source_database: String,
+    pub source: String,
+    pub path: String,
+    pub emitted_at: Option<LogicalTimestamp>,
+}
+
+/// A statement that's run every every_ms, last_run_ms is when it was last run (or when it was
+/// created if it's yet to run) and last_error what it failed with that time, if it did fail.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Event {
+    pub database: String,
+    pub name: String,
+    pub every_ms: i64,
+    pub sql: String,
+    pub last_run_ms: i64,
+    pub last_error: Option<String>,
+}
+
+/// A row level security policy, the predicate's sql is AND-ed into the queries of users other
+/// than the table's owner
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Policy {
+    pub database: String,
+    pub table: String,
+    pub name: String,
+    pub predicate: String,
+}
+
+/// A privilege granted to a user, ie EXECUTE on the DIRECTORY 'data/' lets them read the files
+/// under data/
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Privilege {
+    pub user: String,
+    pub privilege: String,
+    pub object_type: String,
+    pub object: String,
+}
+
+/// Sessions that haven't logged in (ie the server's own) and root aren't restricted by
+/// privileges, they're also the only ones that can grant them.
+pub fn is_superuser(user: &str) -> bool {
+    user.is_empty() || user == "root"
+}
+
+/// A change to the type of one of a table's columns, the rows are copied into a shadow table
+/// with the column cast before it's swapped in for the table.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct SchemaMigration {
+    pub database: String,
+    pub table: String,
+    pub column: String,
+    pub datatype: DataType,
+    pub rows_total: i64,
+    pub rows_copied: i64,
+    pub status: MigrationStatus,
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum MigrationStatus {
+    Backfilling,
+    Done,
+    Failed,
+}
+
+impl MigrationStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            MigrationStatus::Backfilling => "backfilling",
+            MigrationStatus::Done => "done",
+            MigrationStatus::Failed => "failed",
+        }
+    }
+}
+
+/// How long a materialized view's groups stay open, measured back from the latest value of
+/// its time column, and what to do with rows that arrive for groups that have closed.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct StateTtl {
+    pub ttl_ms: i64,
+    pub time_column: String,
+    pub late_data: LateData,
+}
+
+/// What refreshes do with late rows, ie changes to groups that have already closed.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum LateData {
+    /// The closed rows are left as they are
+    Drop,
+    /// The closed rows are left as they are, the up to date version of any closed rows that
+    /// late rows have changed are kept in the view's corrections table
+    Corrections,
+    /// The closed rows are retracted and re-emitted with the late rows applied
+    Retract,
+}
+
+impl LateData {
+    fn as_str(&self) -> &'static str {
+        match self {
+            LateData::Drop => "drop",
+            LateData::Corrections => "corrections",
+            LateData::Retract => "retract",
+        }
+    }
+
+    fn from_str(late_data: &str) -> Self {
+        match late_data {
+            "corrections" => LateData::Corrections,
+            "retract" => LateData::Retract,
+            _ => LateData::Drop,
+        }
+    }
+}
+
+/// How a table's rows are stored, fixed when the table's created.
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+pub struct TableOptions {
+    /// Rows are checksummed, rows that have been corrupted on disk get moved out into
+    /// incresql.corrupted_rows as they're read
+    pub checksums: bool,
+    /// Rows are written with a bitmap of their null columns, saving space for wide tables that
+    /// are mostly nulls at the cost of range scans
+    pub null_bitmap: bool,
+    /// The indexes of the columns the rows are keyed on and their sort orders, the rest of the
+    /// columns are stored in the value. Without one every column is part of the key, ascending
+    pub primary_key: Option<Vec<(usize, SortOrder)>>,
+}
+
+/// The name of the table a materialized view with LateData::Corrections writes its corrections
+/// into, it lives in the same database as the view.
+pub fn corrections_table_name(view_name: &str) -> String {
+    format!("{}_corrections", view_name)
+}
+
+/// The name of the table an incrementally maintained view that aggregates over a union all keeps
+/// the partial aggregate states of the union's sources in, it lives in the same database as the
+/// view.
+pub fn partial_states_table_name(view_name: &str) -> String {
+    format!("{}_partial_states", view_name)
+}
+
+/// The columns of a partial states table. Rows are keyed on the index of the source in the union
+/// and the group's key (as an encoded tuple), along with the number of the source's rows in the
+/// group and the group's serialized aggregate state for the source.
+pub fn partial_states_columns() -> Vec<(String, DataType)> {
+    vec![
+        ("source".to_string(), DataType::Integer),
+        ("key".to_string(), DataType::ByteA),
+        ("rows".to_string(), DataType::BigInt),
+        ("state".to_string(), DataType::ByteA),
+    ]
+}
+
 const PREFIX_METADATA_TABLE_ID: u32 = 0;
 const DATABASES_TABLE_ID: u32 = 2;
 const TABLES_TABLE_ID: u32 = 4;
+const DEAD_LETTERS_TABLE_ID: u32 = 6;
+const DEAD_LETTERS_COLUMN_COUNT: usize = 5;
+const SOURCE_OFFSETS_TABLE_ID: u32 = 8;
+const PAUSED_SOURCES_TABLE_ID: u32 = 10;
+const SINKS_TABLE_ID: u32 = 12;
+const STATE_TTLS_TABLE_ID: u32 = 14;
+const STORAGE_STATS_TABLE_ID: u32 = 16;
+const CORRUPTED_ROWS_TABLE_ID: u32 = 18;
+const CHECKSUMMED_TABLES_TABLE_ID: u32 = 20;
+const USER_DEFAULTS_TABLE_ID: u32 = 22;
+const WORKLOAD_STATS_TABLE_ID: u32 = 24;
+const SCHEMA_MIGRATIONS_TABLE_ID: u32 = 26;
+const COLUMN_STATS_TABLE_ID: u32 = 28;
+const NULL_BITMAP_TABLES_TABLE_ID: u32 = 30;
+const PROCEDURES_TABLE_ID: u32 = 32;
+const EVENTS_TABLE_ID: u32 = 34;
+const INCREMENTAL_VIEWS_TABLE_ID: u32 = 36;
+const POLICIES_TABLE_ID: u32 = 38;
+const TABLE_OWNERS_TABLE_ID: u32 = 40;
+const COLUMN_MASKS_TABLE_ID: u32 = 42;
+const PRIVILEGES_TABLE_ID: u32 = 44;
+const PRIMARY_KEYS_TABLE_ID: u32 = 46;
+const INDEXES_TABLE_ID: u32 = 48;
+const COLUMN_SCHEMAS_TABLE_ID: u32 = 50;
 
 impl Catalog {
     /// Creates a catalog, wrapping the passed in storage
@@ -56,11 +324,98 @@ impl Catalog {
             storage.table(PREFIX_METADATA_TABLE_ID, 3, vec![SortOrder::Asc]);
         let databases_table = storage.table(DATABASES_TABLE_ID, 1, vec![SortOrder::Asc]);
         let tables_table = storage.table(TABLES_TABLE_ID, 8, vec![SortOrder::Asc, SortOrder::Asc]);
+        let dead_letters_table = storage.table(
+            DEAD_LETTERS_TABLE_ID,
+            DEAD_LETTERS_COLUMN_COUNT,
+            vec![SortOrder::Asc; DEAD_LETTERS_COLUMN_COUNT],
+        );
+        let source_offsets_table = storage.table(SOURCE_OFFSETS_TABLE_ID, 2, vec![SortOrder::Asc]);
+        let paused_sources_table = storage.table(PAUSED_SOURCES_TABLE_ID, 1, vec![SortOrder::Asc]);
+        let sinks_table = storage.table(SINKS_TABLE_ID, 6, vec![SortOrder::Asc, SortOrder::Asc]);
+        let state_ttls_table =
+            storage.table(STATE_TTLS_TABLE_ID, 5, vec![SortOrder::Asc, SortOrder::Asc]);
+        let storage_stats_table = storage.table(STORAGE_STATS_TABLE_ID, 2, vec![SortOrder::Asc]);
+        // Rows that fail their checksums are moved out of their tables into corrupted_rows
+        // by the storage layer
+        storage.set_quarantine_table(CORRUPTED_ROWS_TABLE_ID);
+        let checksummed_tables_table =
+            storage.table(CHECKSUMMED_TABLES_TABLE_ID, 1, vec![SortOrder::Asc]);
+        let user_defaults_table = storage.table(
+            USER_DEFAULTS_TABLE_ID,
+            3,
+            vec![SortOrder::Asc, SortOrder::Asc],
+        );
+        let workload_stats_table = storage.table(WORKLOAD_STATS_TABLE_ID, 4, vec![SortOrder::Asc]);
+        let schema_migrations_table = storage.table(
+            SCHEMA_MIGRATIONS_TABLE_ID,
+            7,
+            vec![SortOrder::Asc, SortOrder::Asc],
+        );
+        let column_stats_table = storage.table(
+            COLUMN_STATS_TABLE_ID,
+            5,
+            vec![SortOrder::Asc, SortOrder::Asc],
+        );
+        let null_bitmap_tables_table =
+            storage.table(NULL_BITMAP_TABLES_TABLE_ID, 1, vec![SortOrder::Asc]);
+        let procedures_table =
+            storage.table(PROCEDURES_TABLE_ID, 3, vec![SortOrder::Asc, SortOrder::Asc]);
+        let events_table = storage.table(EVENTS_TABLE_ID, 6, vec![SortOrder::Asc, SortOrder::Asc]);
+        let incremental_views_table = storage.table(
+            INCREMENTAL_VIEWS_TABLE_ID,
+            3,
+            vec![SortOrder::Asc, SortOrder::Asc],
+        );
+        let policies_table = storage.table(
+            POLICIES_TABLE_ID,
+            4,
+            vec![SortOrder::Asc, SortOrder::Asc, SortOrder::Asc],
+        );
+        let table_owners_table = storage.table(
+            TABLE_OWNERS_TABLE_ID,
+            3,
+            vec![SortOrder::Asc, SortOrder::Asc],
+        );
+        let column_masks_table = storage.table(
+            COLUMN_MASKS_TABLE_ID,
+            4,
+            vec![SortOrder::Asc, SortOrder::Asc, SortOrder::Asc],
+        );
+        let privileges_table = storage.table(PRIVILEGES_TABLE_ID, 4, vec![SortOrder::Asc; 4]);
+        let primary_keys_table = storage.table(PRIMARY_KEYS_TABLE_ID, 2, vec![SortOrder::Asc]);
+        let indexes_table = storage.table(INDEXES_TABLE_ID, 4, vec![SortOrder::Asc; 2]);
+        let column_schemas_table =
+            storage.table(COLUMN_SCHEMAS_TABLE_ID, 4, vec![SortOrder::Asc; 3]);
         let mut catalog = Catalog {
             storage,
             prefix_metadata_table,
             databases_table,
             tables_table,
+            dead_letters_table,
+            source_offsets_table,
+            paused_sources_table,
+            sinks_table,
+            state_ttls_table,
+            storage_stats_table,
+            checksummed_tables_table,
+            user_defaults_table,
+            workload_stats_table,
+            workload_stats: Arc::default(),
+            schema_migrations_table,
+            column_stats_table,
+            null_bitmap_tables_table,
+            procedures_table,
+            events_table,
+            incremental_views_table,
+            policies_table,
+            table_owners_table,
+            column_masks_table,
+            privileges_table,
+            primary_keys_table,
+            indexes_table,
+            column_schemas_table,
+            version: 0,
+            item_cache: RwLock::default(),
         };
         catalog.bootstrap()?;
         Ok(catalog)
@@ -71,485 +426,2805 @@ impl Catalog {
         Catalog::new(Storage::new_in_mem()?)
     }
 
-    /// Returns the catalog item with the given name
-    pub fn item(&self, database: &str, table: &str) -> Result<CatalogItem, CatalogError> {
-        let tables_pk = [Datum::from(database), Datum::from(table)];
-        let mut key_buf = vec![];
-        let mut value = vec![];
+    /// The table file sources write the rows they couldn't decode into
+    pub fn dead_letters_table(&self) -> Table {
+        self.dead_letters_table.clone()
+    }
 
-        let freq = self
-            .tables_table
-            .system_point_lookup(&tables_pk, &mut key_buf, &mut value)?
-            .unwrap_or(0);
-        if freq == 0 {
-            return Err(CatalogError::TableNotFound(
-                database.to_string(),
-                table.to_string(),
+    /// The table inserts record how far into each file source they've read
+    pub fn source_offsets_table(&self) -> Table {
+        self.source_offsets_table.clone()
+    }
+
+    /// The table listing the file sources that scans should wait on
+    pub fn paused_sources_table(&self) -> Table {
+        self.paused_sources_table.clone()
+    }
+
+    /// Pauses or resumes the file source, scans already running pick this up as they go
+    pub fn set_source_paused(&mut self, source: &str, paused: bool) -> Result<(), CatalogError> {
+        let tuple = [Datum::from(source)];
+        self.paused_sources_table
+            .atomic_write::<_, StorageError>(|batch| {
+                if paused {
+                    batch.system_write_tuple(&self.paused_sources_table, &tuple, 1);
+                } else {
+                    batch.system_delete_tuple(&self.paused_sources_table, &tuple);
+                }
+                Ok(())
+            })?;
+        Ok(())
+    }
+
+    /// Sets the default for a session variable for the user's connections, None removes it
+    pub fn set_user_default(
+        &mut self,
+        user: &str,
+        name: &str,
+        value: Option<&str>,
+    ) -> Result<(), CatalogError> {
+        let pk = [Datum::from(user), Datum::from(name)];
+        self.user_defaults_table
+            .atomic_write::<_, StorageError>(|batch| {
+                match value {
+                    Some(value) => batch.system_write_tuple(
+                        &self.user_defaults_table,
+                        &[pk[0].clone(), pk[1].clone(), Datum::from(value)],
+                        1,
+                    ),
+                    None => batch.system_delete_tuple(&self.user_defaults_table, &pk),
+                }
+                Ok(())
+            })?;
+        Ok(())
+    }
+
+    /// The session variables the user's connections start with, as (name, value)
+    pub fn user_defaults(&self, user: &str) -> Result<Vec<(String, String)>, CatalogError> {
+        let pk = [Datum::from(user)];
+        let mut defaults = vec![];
+        let mut iter =
+            self.user_defaults_table
+                .range_scan(Some(&pk), Some(&pk), LogicalTimestamp::MAX);
+        while let Some((tuple, _freq)) = iter.next()? {
+            defaults.push((
+                tuple[1].as_text().to_string(),
+                tuple[2].as_text().to_string(),
             ));
         }
-        let table_type = value[0].as_text();
-
-        let columns: Vec<_> = value[4]
-            .as_json()
-            .iter_array()
-            .unwrap()
-            .map(|col| {
-                let mut iter = col.iter_array().unwrap();
-                let col_name = iter.next().unwrap().get_string().unwrap();
-                let col_type =
-                    DataType::try_from(iter.next().unwrap().get_string().unwrap()).unwrap();
-                (col_name.to_string(), col_type)
-            })
-            .collect();
+        Ok(defaults)
+    }
 
-        let item = match table_type {
-            "table" => {
-                let id = value[3].as_bigint() as u32;
+    /// The clock the storage timestamps writes by
+    pub fn clock(&self) -> &Clock {
+        self.storage.clock()
+    }
 
-                let prefix_pk = [value[3].clone()];
-                self.prefix_metadata_table
-                    .system_point_lookup(&prefix_pk, &mut key_buf, &mut value)?
-                    .unwrap();
+    /// Returns a timestamp that all writes have been committed up to, see Storage::read_barrier
+    pub fn read_barrier(&self, wait: Duration) -> LogicalTimestamp {
+        self.storage.read_barrier(wait)
+    }
 
-                let pk = value[1]
-                    .as_json()
-                    .iter_array()
-                    .unwrap()
-                    .map(|b| {
-                        if b.get_boolean().unwrap() {
-                            SortOrder::Desc
-                        } else {
-                            SortOrder::Asc
-                        }
-                    })
-                    .collect();
+    /// Registers a new sink
+    pub fn create_sink(&mut self, sink: &Sink) -> Result<(), CatalogError> {
+        self.check_db_exists(&sink.database)?;
+        if self.sink(&sink.database, &sink.name).is_ok() {
+            return Err(CatalogError::SinkAlreadyExists(
+                sink.database.clone(),
+                sink.name.clone(),
+            ));
+        }
+        self.update_sink(sink)
+    }
 
-                TableOrView::Table(self.storage.table(id, columns.len(), pk))
-            }
-            "view" => TableOrView::View(View {
-                sql: value[1].as_text().to_string(),
-                db_context: value[2].as_text().to_string(),
-            }),
-            tt => panic!("Unknown table type {}", tt),
-        };
+    /// Returns the sink with the given name
+    pub fn sink(&self, database: &str, name: &str) -> Result<Sink, CatalogError> {
+        let pk = [Datum::from(database), Datum::from(name)];
+        let mut key_buf = vec![];
+        let mut value = vec![];
 
-        Ok(CatalogItem { columns, item })
+        if self
+            .sinks_table
+            .system_point_lookup(&pk, &mut key_buf, &mut value)?
+            .is_none()
+        {
+            return Err(CatalogError::SinkNotFound(
+                database.to_string(),
+                name.to_string(),
+            ));
+        }
+        Ok(Sink {
+            database: database.to_string(),
+            name: name.to_string(),
+            source_database: value[0].as_text().to_string(),
+            source: value[1].as_text().to_string(),
+            path: value[2].as_text().to_string(),
+            emitted_at: value[3]
+                .as_maybe_bigint()
+                .map(|ms| LogicalTimestamp::new(ms as u64)),
+        })
     }
 
-    /// Called to create a database
-    pub fn create_database(&mut self, database_name: &str) -> Result<(), CatalogError> {
-        self.check_db_not_exists(database_name)?;
-        self.create_database_impl(database_name)
+    /// Saves the sink, overwriting any existing sink with the same name
+    pub fn update_sink(&mut self, sink: &Sink) -> Result<(), CatalogError> {
+        let tuple = [
+            Datum::from(sink.database.as_str()),
+            Datum::from(sink.name.as_str()),
+            Datum::from(sink.source_database.as_str()),
+            Datum::from(sink.source.as_str()),
+            Datum::from(sink.path.as_str()),
+            sink.emitted_at
+                .map_or(Datum::Null, |timestamp| Datum::from(timestamp.ms as i64)),
+        ];
+        self.sinks_table.atomic_write::<_, StorageError>(|batch| {
+            batch.system_write_tuple(&self.sinks_table, &tuple, 1);
+            Ok(())
+        })?;
+        Ok(())
     }
 
-    /// Called to drop a database
-    pub fn drop_database(&mut self, database_name: &str) -> Result<(), CatalogError> {
-        self.check_db_exists(database_name)?;
-        self.check_db_empty(database_name)?;
-        // Write with freq -1
-        self.databases_table.atomic_write(|batch| {
-            batch.write_tuple(
-                &self.databases_table,
-                &[Datum::from(database_name)],
-                LogicalTimestamp::now(),
-                -1,
-            )
+    /// Drops a sink, the file it's written is left alone
+    pub fn drop_sink(&mut self, database: &str, name: &str) -> Result<(), CatalogError> {
+        self.sink(database, name)?;
+        let pk = [Datum::from(database), Datum::from(name)];
+        self.sinks_table.atomic_write::<_, StorageError>(|batch| {
+            batch.system_delete_tuple(&self.sinks_table, &pk);
+            Ok(())
         })?;
         Ok(())
     }
 
-    /// Creates a new table
-    pub fn create_table(
+    /// Stores a procedure's body
+    pub fn create_procedure(
         &mut self,
-        database_name: &str,
-        table_name: &str,
-        columns: &[(String, DataType)],
+        database: &str,
+        name: &str,
+        sql: &str,
     ) -> Result<(), CatalogError> {
-        self.check_db_exists(database_name)?;
-        self.check_table_not_exists(database_name, table_name)?;
-        let id = self.generate_table_id(table_name)?;
-        let pk: Vec<_> = columns.iter().map(|_| SortOrder::Asc).collect();
+        self.check_db_exists(database)?;
+        if self.procedure(database, name).is_ok() {
+            return Err(CatalogError::ProcedureAlreadyExists(
+                database.to_string(),
+                name.to_string(),
+            ));
+        }
+        let tuple = [Datum::from(database), Datum::from(name), Datum::from(sql)];
+        self.procedures_table
+            .atomic_write::<_, StorageError>(|batch| {
+                batch.system_write_tuple(&self.procedures_table, &tuple, 1);
+                Ok(())
+            })?;
+        Ok(())
+    }
 
-        self.create_table_impl(database_name, table_name, id, columns, &pk, false)
+    /// Returns the sql of the procedure's body
+    pub fn procedure(&self, database: &str, name: &str) -> Result<String, CatalogError> {
+        let pk = [Datum::from(database), Datum::from(name)];
+        let mut key_buf = vec![];
+        let mut value = vec![];
+
+        if self
+            .procedures_table
+            .system_point_lookup(&pk, &mut key_buf, &mut value)?
+            .is_none()
+        {
+            return Err(CatalogError::ProcedureNotFound(
+                database.to_string(),
+                name.to_string(),
+            ));
+        }
+        Ok(value[0].as_text().to_string())
     }
 
-    /// Creates a new view
-    pub fn create_view(
-        &mut self,
-        database_name: &str,
-        table_name: &str,
-        columns: &[(String, DataType)],
-        view_sql: &str,
-        view_context: &str,
-    ) -> Result<(), CatalogError> {
-        self.check_db_exists(database_name)?;
-        self.check_table_not_exists(database_name, table_name)?;
-        self.create_view_impl(
-            database_name,
-            table_name,
-            columns,
-            view_sql,
-            view_context,
-            false,
-        )
+    /// Drops a procedure
+    pub fn drop_procedure(&mut self, database: &str, name: &str) -> Result<(), CatalogError> {
+        self.procedure(database, name)?;
+        let pk = [Datum::from(database), Datum::from(name)];
+        self.procedures_table
+            .atomic_write::<_, StorageError>(|batch| {
+                batch.system_delete_tuple(&self.procedures_table, &pk);
+                Ok(())
+            })?;
+        Ok(())
     }
 
-    /// Drops a table or a view
-    pub fn drop_table(
-        &mut self,
-        database_name: &str,
-        table_name: &str,
-    ) -> Result<(), CatalogError> {
-        self.check_table_exists(database_name, table_name)?;
-        self.drop_table_impl(database_name, table_name)
+    /// Stores a new event
+    pub fn create_event(&mut self, event: &Event) -> Result<(), CatalogError> {
+        self.check_db_exists(&event.database)?;
+        if self.event(&event.database, &event.name).is_ok() {
+            return Err(CatalogError::EventAlreadyExists(
+                event.database.clone(),
+                event.name.clone(),
+            ));
+        }
+        self.update_event(event)
     }
 
-    /// Creates a database, doesn't do any checks to see if the database already exists etc.
-    fn create_database_impl(&mut self, database_name: &str) -> Result<(), CatalogError> {
-        self.databases_table.atomic_write(|batch| {
-            batch.write_tuple(
-                &self.databases_table,
-                &[Datum::from(database_name)],
-                LogicalTimestamp::now(),
-                1,
-            )
+    /// Returns the event with the given name
+    pub fn event(&self, database: &str, name: &str) -> Result<Event, CatalogError> {
+        let pk = [Datum::from(database), Datum::from(name)];
+        let mut key_buf = vec![];
+        let mut value = vec![];
+
+        if self
+            .events_table
+            .system_point_lookup(&pk, &mut key_buf, &mut value)?
+            .is_none()
+        {
+            return Err(CatalogError::EventNotFound(
+                database.to_string(),
+                name.to_string(),
+            ));
+        }
+        Ok(Event {
+            database: database.to_string(),
+            name: name.to_string(),
+            every_ms: value[0].as_bigint(),
+            sql: value[1].as_text().to_string(),
+            last_run_ms: value[2].as_bigint(),
+            last_error: value[3].as_maybe_text().map(str::to_string),
+        })
+    }
+
+    /// Returns all the events, ordered by database and name
+    pub fn events(&self) -> Result<Vec<Event>, CatalogError> {
+        let mut events = vec![];
+        let mut iter = self.events_table.full_scan(LogicalTimestamp::MAX);
+        while let Some((tuple, _freq)) = iter.next()? {
+            events.push(Event {
+                database: tuple[0].as_text().to_string(),
+                name: tuple[1].as_text().to_string(),
+                every_ms: tuple[2].as_bigint(),
+                sql: tuple[3].as_text().to_string(),
+                last_run_ms: tuple[4].as_bigint(),
+                last_error: tuple[5].as_maybe_text().map(str::to_string),
+            });
+        }
+        Ok(events)
+    }
+
+    /// Saves the event, overwriting any existing event with the same name
+    pub fn update_event(&mut self, event: &Event) -> Result<(), CatalogError> {
+        let tuple = [
+            Datum::from(event.database.as_str()),
+            Datum::from(event.name.as_str()),
+            Datum::from(event.every_ms),
+            Datum::from(event.sql.as_str()),
+            Datum::from(event.last_run_ms),
+            event.last_error.as_deref().map_or(Datum::Null, Datum::from),
+        ];
+        self.events_table.atomic_write::<_, StorageError>(|batch| {
+            batch.system_write_tuple(&self.events_table, &tuple, 1);
+            Ok(())
         })?;
         Ok(())
     }
 
-    /// Check database empty.
-    fn check_db_empty(&mut self, database_name: &str) -> Result<(), CatalogError> {
-        let db_datum = [Datum::from(database_name)];
-        let mut iter =
-            self.tables_table
-                .range_scan(Some(&db_datum), Some(&db_datum), LogicalTimestamp::MAX);
-        if iter.next()?.is_some() {
-            Err(CatalogError::DatabaseNotEmpty(database_name.to_string()))
-        } else {
+    /// Drops an event
+    pub fn drop_event(&mut self, database: &str, name: &str) -> Result<(), CatalogError> {
+        self.event(database, name)?;
+        let pk = [Datum::from(database), Datum::from(name)];
+        self.events_table.atomic_write::<_, StorageError>(|batch| {
+            batch.system_delete_tuple(&self.events_table, &pk);
             Ok(())
-        }
+        })?;
+        Ok(())
     }
 
-    /// Check for if a database with that name exists
-    fn check_db_exists(&mut self, database_name: &str) -> Result<(), CatalogError> {
-        if !self.db_exists(database_name)? {
-            Err(CatalogError::DatabaseNotFound(database_name.to_string()))
-        } else {
-            Ok(())
+    /// Stores a row level security policy on a table
+    pub fn create_policy(&mut self, policy: &Policy) -> Result<(), CatalogError> {
+        self.check_table_exists(&policy.database, &policy.table)?;
+        // Views are filtered by the policies on the tables they read from
+        let table_type = self.table_type(&policy.database, &policy.table)?;
+        if table_type != "table" && table_type != "materialized view" {
+            return Err(CatalogError::NotATable(
+                policy.database.clone(),
+                policy.table.clone(),
+            ));
         }
+        let pk = [
+            Datum::from(policy.database.as_str()),
+            Datum::from(policy.table.as_str()),
+            Datum::from(policy.name.as_str()),
+        ];
+        let mut key_buf = vec![];
+        let mut value = vec![];
+        if self
+            .policies_table
+            .system_point_lookup(&pk, &mut key_buf, &mut value)?
+            .is_some()
+        {
+            return Err(CatalogError::PolicyAlreadyExists(
+                policy.name.clone(),
+                policy.database.clone(),
+                policy.table.clone(),
+            ));
+        }
+        let tuple = [
+            pk[0].ref_clone(),
+            pk[1].ref_clone(),
+            pk[2].ref_clone(),
+            Datum::from(policy.predicate.as_str()),
+        ];
+        self.policies_table
+            .atomic_write::<_, StorageError>(|batch| {
+                batch.system_write_tuple(&self.policies_table, &tuple, 1);
+                Ok(())
+            })?;
+        Ok(())
     }
 
-    fn check_db_not_exists(&mut self, database_name: &str) -> Result<(), CatalogError> {
-        if self.db_exists(database_name)? {
-            Err(CatalogError::DatabaseAlreadyExists(
-                database_name.to_string(),
-            ))
-        } else {
-            Ok(())
+    /// Returns the policies on the table, ordered by name
+    pub fn policies(&self, database: &str, table: &str) -> Result<Vec<Policy>, CatalogError> {
+        let key = [Datum::from(database), Datum::from(table)];
+        let mut policies = vec![];
+        let mut iter =
+            self.policies_table
+                .range_scan(Some(&key), Some(&key), LogicalTimestamp::MAX);
+        while let Some((tuple, _freq)) = iter.next()? {
+            policies.push(Policy {
+                database: database.to_string(),
+                table: table.to_string(),
+                name: tuple[2].as_text().to_string(),
+                predicate: tuple[3].as_text().to_string(),
+            });
         }
+        Ok(policies)
     }
 
-    fn db_exists(&mut self, database_name: &str) -> Result<bool, CatalogError> {
-        let db_datum = [Datum::from(database_name)];
-        let mut iter = self.databases_table.range_scan(
-            Some(&db_datum),
-            Some(&db_datum),
-            LogicalTimestamp::MAX,
-        );
-        Ok(iter.next()?.is_some())
+    /// Drops a policy
+    pub fn drop_policy(
+        &mut self,
+        database: &str,
+        table: &str,
+        name: &str,
+    ) -> Result<(), CatalogError> {
+        if !self
+            .policies(database, table)?
+            .iter()
+            .any(|policy| policy.name == name)
+        {
+            return Err(CatalogError::PolicyNotFound(
+                name.to_string(),
+                database.to_string(),
+                table.to_string(),
+            ));
+        }
+        let pk = [Datum::from(database), Datum::from(table), Datum::from(name)];
+        self.policies_table
+            .atomic_write::<_, StorageError>(|batch| {
+                batch.system_delete_tuple(&self.policies_table, &pk);
+                Ok(())
+            })?;
+        Ok(())
     }
 
-    /// Check for if a database with that name exists
-    fn check_table_exists(
+    /// Creates a secondary index on the table's column. The index is backfilled with the
+    /// table's current rows and from then on is written alongside the table.
+    pub fn create_index(
         &mut self,
-        database_name: &str,
-        table_name: &str,
+        database: &str,
+        table: &str,
+        name: &str,
+        column: &str,
     ) -> Result<(), CatalogError> {
-        if !self.table_exists(database_name, table_name)? {
-            Err(CatalogError::TableNotFound(
-                database_name.to_string(),
-                table_name.to_string(),
-            ))
+        self.check_table_exists(database, table)?;
+        let item = self.item(database, table)?;
+        let base_table = match (&item.item, self.table_type(database, table)?.as_str()) {
+            (TableOrView::Table(base_table), "table") => base_table.clone(),
+            _ => {
+                return Err(CatalogError::NotATable(
+                    database.to_string(),
+                    table.to_string(),
+                ))
+            }
+        };
+        let column_idx = item
+            .columns
+            .iter()
+            .position(|(column_name, _)| column_name == column)
+            .ok_or_else(|| {
+                CatalogError::ColumnNotFound(
+                    database.to_string(),
+                    table.to_string(),
+                    column.to_string(),
+                )
+            })?;
+        let table_id = base_table.id();
+        if self
+            .index_rows(table_id)?
+            .iter()
+            .any(|(index_name, _, _)| index_name == name)
+        {
+            return Err(CatalogError::IndexAlreadyExists(
+                name.to_string(),
+                database.to_string(),
+                table.to_string(),
+            ));
+        }
+        let index_id = self.generate_table_id(name)?;
+        let index = self.index_table(index_id, column_idx, item.columns.len());
+
+        let mut rows = vec![];
+        let mut iter = base_table.full_scan(LogicalTimestamp::MAX);
+        while let Some((tuple, freq)) = iter.next()? {
+            rows.push((tuple.iter().map(Datum::as_static).collect::<Vec<_>>(), freq));
+        }
+
+        self.bump_version();
+        let now = self.storage.clock().now();
+        let pks = Datum::from(JsonBuilder::default().array(|array| {
+            for _ in 0..item.columns.len() {
+                array.push_bool(false);
+            }
+        }));
+        self.indexes_table
+            .atomic_write::<_, StorageError>(|batch| {
+                batch.write_tuple(
+                    &self.prefix_metadata_table,
+                    &[
+                        Datum::from(index_id as i64),
+                        Datum::from(item.columns.len() as i32),
+                        pks,
+                    ],
+                    now,
+                    1,
+                )?;
+                batch.system_write_tuple(
+                    &self.indexes_table,
+                    &[
+                        Datum::from(table_id as i64),
+                        Datum::from(name),
+                        Datum::from(index_id as i64),
+                        Datum::from(column),
+                    ],
+                    1,
+                );
+                for (tuple, freq) in &rows {
+                    batch.write_tuple(&index, tuple, now, *freq)?;
+                }
+                Ok(())
+            })?;
+        Ok(())
+    }
+
+    /// Returns the (name, index id, column) of each of the table's indexes, ordered by name
+    fn index_rows(&self, table_id: u32) -> Result<Vec<(String, u32, String)>, CatalogError> {
+        let pk = [Datum::from(table_id as i64)];
+        let mut rows = vec![];
+        let mut iter = self
+            .indexes_table
+            .range_scan(Some(&pk), Some(&pk), LogicalTimestamp::MAX);
+        while let Some((tuple, _freq)) = iter.next()? {
+            rows.push((
+                tuple[1].as_text().to_string(),
+                tuple[2].as_bigint() as u32,
+                tuple[3].as_text().to_string(),
+            ));
+        }
+        Ok(rows)
+    }
+
+    /// An index holds every column of the table, keyed on the indexed column then the rest
+    fn index_table(&self, index_id: u32, column: usize, column_count: usize) -> Table {
+        let mut key_columns = vec![column];
+        key_columns.extend((0..column_count).filter(|idx| *idx != column));
+        self.storage
+            .table(index_id, column_count, vec![SortOrder::Asc; column_count])
+            .with_primary_key(&key_columns)
+    }
+
+    /// Deletes the index rows and their prefix metadata, the data itself has to be
+    /// deleted with delete_range, see drop_index_data
+    fn drop_index_metadata(
+        &self,
+        batch: &mut Writer,
+        table_id: u32,
+        indexes: &[(String, u32, String)],
+        now: LogicalTimestamp,
+    ) -> Result<(), StorageError> {
+        for (name, index_id, _column) in indexes {
+            batch.system_delete_tuple(
+                &self.indexes_table,
+                &[Datum::from(table_id as i64), Datum::from(name.as_str())],
+            );
+            let prefix_key = [Datum::from(*index_id as i64)];
+            let mut prefix_iter = self.prefix_metadata_table.range_scan(
+                Some(&prefix_key),
+                Some(&prefix_key),
+                LogicalTimestamp::MAX,
+            );
+            if let Some((prefix_tuple, prefix_freq)) = prefix_iter.next()? {
+                batch.write_tuple(&self.prefix_metadata_table, prefix_tuple, now, -prefix_freq)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Deletes the rows of the indexes
+    fn drop_index_data(&self, indexes: &[(String, u32, String)]) -> Result<(), StorageError> {
+        self.tables_table
+            .atomic_write_without_index::<_, StorageError>(|write_batch| {
+                for (_name, index_id, _column) in indexes {
+                    write_batch.delete_range(index_id.to_be_bytes(), (index_id + 2).to_be_bytes());
+                }
+                Ok(())
+            })
+    }
+
+    /// Sets the mask of the table's column, or drops it when mask is None
+    pub fn set_column_mask(
+        &mut self,
+        database: &str,
+        table: &str,
+        column: &str,
+        mask: Option<&str>,
+    ) -> Result<(), CatalogError> {
+        let pk = [
+            Datum::from(database),
+            Datum::from(table),
+            Datum::from(column),
+        ];
+        if mask.is_none()
+            && !self
+                .column_masks(database, table)?
+                .iter()
+                .any(|(masked_column, _mask)| masked_column == column)
+        {
+            return Err(CatalogError::ColumnMaskNotFound(
+                database.to_string(),
+                table.to_string(),
+                column.to_string(),
+            ));
+        }
+        self.column_masks_table
+            .atomic_write::<_, StorageError>(|batch| {
+                if let Some(mask) = mask {
+                    let tuple = [
+                        pk[0].ref_clone(),
+                        pk[1].ref_clone(),
+                        pk[2].ref_clone(),
+                        Datum::from(mask),
+                    ];
+                    batch.system_write_tuple(&self.column_masks_table, &tuple, 1);
+                } else {
+                    batch.system_delete_tuple(&self.column_masks_table, &pk);
+                }
+                Ok(())
+            })?;
+        Ok(())
+    }
+
+    /// Sets the json schema of the table's column, or drops it when schema is None
+    pub fn set_column_schema(
+        &mut self,
+        database: &str,
+        table: &str,
+        column: &str,
+        schema: Option<&str>,
+    ) -> Result<(), CatalogError> {
+        let pk = [
+            Datum::from(database),
+            Datum::from(table),
+            Datum::from(column),
+        ];
+        if schema.is_none()
+            && !self
+                .column_schemas(database, table)?
+                .iter()
+                .any(|(schema_column, _schema)| schema_column == column)
+        {
+            return Err(CatalogError::ColumnSchemaNotFound(
+                database.to_string(),
+                table.to_string(),
+                column.to_string(),
+            ));
+        }
+        self.column_schemas_table
+            .atomic_write::<_, StorageError>(|batch| {
+                if let Some(schema) = schema {
+                    let tuple = [
+                        pk[0].ref_clone(),
+                        pk[1].ref_clone(),
+                        pk[2].ref_clone(),
+                        Datum::from(schema),
+                    ];
+                    batch.system_write_tuple(&self.column_schemas_table, &tuple, 1);
+                } else {
+                    batch.system_delete_tuple(&self.column_schemas_table, &pk);
+                }
+                Ok(())
+            })?;
+        Ok(())
+    }
+
+    /// Returns the columns of the table with json schemas along with their schemas
+    pub fn column_schemas(
+        &self,
+        database: &str,
+        table: &str,
+    ) -> Result<Vec<(String, String)>, CatalogError> {
+        let key = [Datum::from(database), Datum::from(table)];
+        let mut schemas = vec![];
+        let mut iter =
+            self.column_schemas_table
+                .range_scan(Some(&key), Some(&key), LogicalTimestamp::MAX);
+        while let Some((tuple, _freq)) = iter.next()? {
+            schemas.push((
+                tuple[2].as_text().to_string(),
+                tuple[3].as_text().to_string(),
+            ));
+        }
+        Ok(schemas)
+    }
+
+    /// Returns the masked columns of the table along with the sql of their masks
+    pub fn column_masks(
+        &self,
+        database: &str,
+        table: &str,
+    ) -> Result<Vec<(String, String)>, CatalogError> {
+        let key = [Datum::from(database), Datum::from(table)];
+        let mut masks = vec![];
+        let mut iter =
+            self.column_masks_table
+                .range_scan(Some(&key), Some(&key), LogicalTimestamp::MAX);
+        while let Some((tuple, _freq)) = iter.next()? {
+            masks.push((
+                tuple[2].as_text().to_string(),
+                tuple[3].as_text().to_string(),
+            ));
+        }
+        Ok(masks)
+    }
+
+    /// Records the user that created the table, policies don't apply to them
+    pub fn set_table_owner(
+        &mut self,
+        database: &str,
+        table: &str,
+        owner: &str,
+    ) -> Result<(), CatalogError> {
+        let tuple = [
+            Datum::from(database),
+            Datum::from(table),
+            Datum::from(owner),
+        ];
+        self.table_owners_table
+            .atomic_write::<_, StorageError>(|batch| {
+                batch.system_write_tuple(&self.table_owners_table, &tuple, 1);
+                Ok(())
+            })?;
+        Ok(())
+    }
+
+    /// Grants the privilege to its user, granting it again is a no-op
+    pub fn grant_privilege(&mut self, privilege: &Privilege) -> Result<(), CatalogError> {
+        let tuple = privilege_tuple(privilege);
+        self.privileges_table
+            .atomic_write::<_, StorageError>(|batch| {
+                batch.system_write_tuple(&self.privileges_table, &tuple, 1);
+                Ok(())
+            })?;
+        Ok(())
+    }
+
+    /// Revokes the privilege from its user
+    pub fn revoke_privilege(&mut self, privilege: &Privilege) -> Result<(), CatalogError> {
+        if !self.privileges(&privilege.user)?.contains(privilege) {
+            return Err(CatalogError::PrivilegeNotGranted(
+                privilege.privilege.clone(),
+                format!("{} {}", privilege.object_type, privilege.object),
+                privilege.user.clone(),
+            ));
+        }
+        let pk = privilege_tuple(privilege);
+        self.privileges_table
+            .atomic_write::<_, StorageError>(|batch| {
+                batch.system_delete_tuple(&self.privileges_table, &pk);
+                Ok(())
+            })?;
+        Ok(())
+    }
+
+    /// Returns the privileges granted to the user
+    pub fn privileges(&self, user: &str) -> Result<Vec<Privilege>, CatalogError> {
+        let key = [Datum::from(user)];
+        let mut privileges = vec![];
+        let mut iter =
+            self.privileges_table
+                .range_scan(Some(&key), Some(&key), LogicalTimestamp::MAX);
+        while let Some((tuple, _freq)) = iter.next()? {
+            privileges.push(Privilege {
+                user: user.to_string(),
+                privilege: tuple[1].as_text().to_string(),
+                object_type: tuple[2].as_text().to_string(),
+                object: tuple[3].as_text().to_string(),
+            });
+        }
+        Ok(privileges)
+    }
+
+    /// Returns the user that created the table, None for tables created before owners were
+    /// recorded and the system tables
+    pub fn table_owner(&self, database: &str, table: &str) -> Result<Option<String>, CatalogError> {
+        let pk = [Datum::from(database), Datum::from(table)];
+        let mut key_buf = vec![];
+        let mut value = vec![];
+        let owner = self
+            .table_owners_table
+            .system_point_lookup(&pk, &mut key_buf, &mut value)?
+            .map(|_| value[0].as_text().to_string());
+        Ok(owner)
+    }
+
+    /// Returns the version of the catalog, it's bumped each time a table or view is created or
+    /// dropped so anything derived from the catalog's items can tell when it's stale
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Returns the catalog item with the given name
+    pub fn item(&self, database: &str, table: &str) -> Result<CatalogItem, CatalogError> {
+        if database == "incresql" && table == "storage_stats" {
+            self.refresh_storage_stats()?;
+        }
+        if database == "incresql" && table == "workload_stats" {
+            self.refresh_workload_stats()?;
+        }
+        if let Some(item) = self
+            .item_cache
+            .read()
+            .unwrap()
+            .get(database)
+            .and_then(|items| items.get(table))
+        {
+            return Ok(item.clone());
+        }
+
+        let item = self.load_item(database, table)?;
+        self.item_cache
+            .write()
+            .unwrap()
+            .entry(database.to_string())
+            .or_default()
+            .insert(table.to_string(), item.clone());
+        Ok(item)
+    }
+
+    /// Reads the item in from storage
+    fn load_item(&self, database: &str, table: &str) -> Result<CatalogItem, CatalogError> {
+        let tables_pk = [Datum::from(database), Datum::from(table)];
+        let mut key_buf = vec![];
+        let mut value = vec![];
+
+        let freq = self
+            .tables_table
+            .system_point_lookup(&tables_pk, &mut key_buf, &mut value)?
+            .unwrap_or(0);
+        if freq == 0 {
+            return Err(CatalogError::TableNotFound(
+                database.to_string(),
+                table.to_string(),
+            ));
+        }
+        let table_type = value[0].as_text();
+
+        let columns: Vec<_> = value[4]
+            .as_json()
+            .iter_array()
+            .unwrap()
+            .map(|col| {
+                let mut iter = col.iter_array().unwrap();
+                let col_name = iter.next().unwrap().get_string().unwrap();
+                let col_type =
+                    DataType::try_from(iter.next().unwrap().get_string().unwrap()).unwrap();
+                (col_name.to_string(), col_type)
+            })
+            .collect();
+
+        let item = match table_type {
+            "table" | "materialized view" => {
+                let id = value[3].as_bigint() as u32;
+
+                let prefix_pk = [value[3].clone()];
+                self.prefix_metadata_table
+                    .system_point_lookup(&prefix_pk, &mut key_buf, &mut value)?
+                    .unwrap();
+
+                let pk = pk_sort_orders(&value[1]);
+
+                let mut table = self.storage_table(id, columns.len(), pk)?;
+                let primary_key = self.primary_keys_table.system_point_lookup(
+                    &prefix_pk,
+                    &mut key_buf,
+                    &mut value,
+                )?;
+                if primary_key.is_some() {
+                    let key_columns: Vec<_> = value[0]
+                        .as_json()
+                        .iter_array()
+                        .unwrap()
+                        .map(|column| column.get_number().unwrap().to_usize().unwrap())
+                        .collect();
+                    table = table.with_primary_key(&key_columns);
+                }
+                let mut indexes = vec![];
+                for (_name, index_id, column) in self.index_rows(id)? {
+                    if let Some(column) = columns.iter().position(|(name, _)| *name == column) {
+                        indexes.push(self.index_table(index_id, column, columns.len()));
+                    }
+                }
+                if !indexes.is_empty() {
+                    table = table.with_indexes(indexes);
+                }
+                TableOrView::Table(table)
+            }
+            "view" | "cold table" => TableOrView::View(View {
+                sql: value[1].as_text().to_string(),
+                db_context: value[2].as_text().to_string(),
+            }),
+            tt => panic!("Unknown table type {}", tt),
+        };
+
+        Ok(CatalogItem { columns, item })
+    }
+
+    /// Overwrites the storage stats table with the storage engine's current statistics
+    fn refresh_storage_stats(&self) -> Result<(), CatalogError> {
+        let statistics = self.storage.statistics();
+        let cache_lookups = statistics.block_cache_hits + statistics.block_cache_misses;
+        let cache_hit_ratio = if cache_lookups == 0 {
+            Decimal::from(0)
         } else {
-            Ok(())
+            (Decimal::from(statistics.block_cache_hits) / Decimal::from(cache_lookups)).round_dp(4)
+        };
+        let stats = [
+            (
+                "block_cache_hits",
+                Decimal::from(statistics.block_cache_hits),
+            ),
+            (
+                "block_cache_misses",
+                Decimal::from(statistics.block_cache_misses),
+            ),
+            ("block_cache_hit_ratio", cache_hit_ratio),
+            (
+                "write_stall_micros",
+                Decimal::from(statistics.write_stall_micros),
+            ),
+            (
+                "compaction_pending",
+                Decimal::from(statistics.compaction_pending as u8),
+            ),
+            (
+                "pending_compaction_bytes",
+                Decimal::from(statistics.pending_compaction_bytes),
+            ),
+            ("memtable_bytes", Decimal::from(statistics.memtable_bytes)),
+            ("live_data_bytes", Decimal::from(statistics.live_data_bytes)),
+        ];
+        self.storage_stats_table
+            .atomic_write::<_, StorageError>(|batch| {
+                for (name, value) in &stats {
+                    let tuple = [Datum::from(*name), Datum::from(*value)];
+                    batch.system_write_tuple(&self.storage_stats_table, &tuple, 1);
+                }
+                Ok(())
+            })?;
+        Ok(())
+    }
+
+    /// The totals the runtime keeps up to date as it runs each class of work
+    pub fn workload_stats(&self) -> Arc<WorkloadStats> {
+        Arc::clone(&self.workload_stats)
+    }
+
+    /// Overwrites the workload stats table with the current totals
+    fn refresh_workload_stats(&self) -> Result<(), CatalogError> {
+        let totals = self.workload_stats.totals();
+        self.workload_stats_table
+            .atomic_write::<_, StorageError>(|batch| {
+                for class_totals in &totals {
+                    let tuple = [
+                        Datum::from(class_totals.class.name()),
+                        Datum::from(class_totals.statements as i64),
+                        Datum::from((class_totals.elapsed_micros / 1000) as i64),
+                        Datum::from((class_totals.yielded_micros / 1000) as i64),
+                    ];
+                    batch.system_write_tuple(&self.workload_stats_table, &tuple, 1);
+                }
+                Ok(())
+            })?;
+        Ok(())
+    }
+
+    /// Compacts every table, including the catalog's own. Storage encrypted with a new key
+    /// re-encrypts what's compacted so this is how the old keys get retired.
+    pub fn compact_all_tables(&self) -> Result<(), CatalogError> {
+        let mut ids = vec![];
+        let mut iter = self.prefix_metadata_table.full_scan(LogicalTimestamp::MAX);
+        while let Some((tuple, _freq)) = iter.next()? {
+            ids.push((tuple[0].as_bigint() as u32, tuple[1].as_integer() as usize));
+        }
+        for (id, columns) in ids {
+            // Compaction only works off the id so there's no need to look up the primary key
+            self.storage.table(id, columns, vec![]).force_compaction()?;
+        }
+        Ok(())
+    }
+
+    /// Every table in storage, including indexes and the catalog's own, set up to read their
+    /// rows but without their indexes or primary keys.
+    pub fn storage_tables(&self) -> Result<Vec<Table>, CatalogError> {
+        let mut prefixes = vec![];
+        let mut iter = self.prefix_metadata_table.full_scan(LogicalTimestamp::MAX);
+        while let Some((tuple, _freq)) = iter.next()? {
+            prefixes.push((
+                tuple[0].as_bigint() as u32,
+                tuple[1].as_integer() as usize,
+                pk_sort_orders(&tuple[2]),
+            ));
         }
+        prefixes
+            .into_iter()
+            .map(|(id, columns, pk)| self.storage_table(id, columns, pk))
+            .collect()
     }
 
-    fn check_table_not_exists(
+    /// The table with the given prefix, with checksums or null bitmaps if it was created with
+    /// them
+    fn storage_table(
+        &self,
+        id: u32,
+        columns: usize,
+        pk: Vec<SortOrder>,
+    ) -> Result<Table, CatalogError> {
+        let prefix_pk = [Datum::from(id as i64)];
+        let mut key_buf = vec![];
+        let mut value = vec![];
+        let mut table = self.storage.table(id, columns, pk);
+        let checksums = self.checksummed_tables_table.system_point_lookup(
+            &prefix_pk,
+            &mut key_buf,
+            &mut value,
+        )?;
+        if checksums.is_some() {
+            table = table.with_checksums();
+        }
+        let null_bitmap = self.null_bitmap_tables_table.system_point_lookup(
+            &prefix_pk,
+            &mut key_buf,
+            &mut value,
+        )?;
+        if null_bitmap.is_some() {
+            table = table.with_null_bitmap();
+        }
+        Ok(table)
+    }
+
+    /// The oldest timestamp changes are still to be read from, by the incrementally maintained
+    /// views up to date as of it or the sinks that have written changes up to it. Old versions
+    /// of rows from since then have to be kept for them to catch up.
+    pub fn oldest_needed_changes(&self) -> Result<Option<LogicalTimestamp>, CatalogError> {
+        let mut oldest: Option<u64> = None;
+        let mut needed = |ms: i64| {
+            let ms = ms as u64;
+            oldest = Some(oldest.map_or(ms, |oldest| oldest.min(ms)));
+        };
+        let mut iter = self
+            .incremental_views_table
+            .full_scan(LogicalTimestamp::MAX);
+        while let Some((tuple, _freq)) = iter.next()? {
+            needed(tuple[2].as_bigint());
+        }
+        let mut iter = self.sinks_table.full_scan(LogicalTimestamp::MAX);
+        while let Some((tuple, _freq)) = iter.next()? {
+            if let Some(emitted_at) = tuple[5].as_maybe_bigint() {
+                needed(emitted_at);
+            }
+        }
+        Ok(oldest.map(LogicalTimestamp::new))
+    }
+
+    /// Called to create a database
+    pub fn create_database(&mut self, database_name: &str) -> Result<(), CatalogError> {
+        self.check_db_not_exists(database_name)?;
+        self.create_database_impl(database_name)
+    }
+
+    /// Called to drop a database
+    pub fn drop_database(&mut self, database_name: &str) -> Result<(), CatalogError> {
+        self.check_db_exists(database_name)?;
+        self.check_db_empty(database_name)?;
+        // Write with freq -1
+        self.databases_table.atomic_write(|batch| {
+            batch.write_tuple(
+                &self.databases_table,
+                &[Datum::from(database_name)],
+                self.storage.clock().now(),
+                -1,
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Creates a new table
+    pub fn create_table(
         &mut self,
         database_name: &str,
         table_name: &str,
+        columns: &[(String, DataType)],
     ) -> Result<(), CatalogError> {
-        if self.table_exists(database_name, table_name)? {
-            Err(CatalogError::TableAlreadyExists(
-                database_name.to_string(),
-                table_name.to_string(),
-            ))
-        } else {
-            Ok(())
-        }
+        self.check_db_exists(database_name)?;
+        self.check_table_not_exists(database_name, table_name)?;
+        let id = self.generate_table_id(table_name)?;
+        let pk: Vec<_> = columns.iter().map(|_| SortOrder::Asc).collect();
+
+        self.create_table_impl(database_name, table_name, id, columns, &pk, false)
     }
 
-    fn table_exists(
+    /// Creates a new table whose rows are checksummed, rows that have been corrupted on disk get
+    /// moved out into incresql.corrupted_rows as they're read
+    pub fn create_checksummed_table(
         &mut self,
         database_name: &str,
         table_name: &str,
-    ) -> Result<bool, CatalogError> {
-        let table_datum = [Datum::from(database_name), Datum::from(table_name)];
-        let mut iter = self.tables_table.range_scan(
-            Some(&table_datum),
-            Some(&table_datum),
-            LogicalTimestamp::MAX,
-        );
-        Ok(iter.next()?.is_some())
+        columns: &[(String, DataType)],
+    ) -> Result<(), CatalogError> {
+        let options = TableOptions {
+            checksums: true,
+            ..TableOptions::default()
+        };
+        self.create_table_with_options(database_name, table_name, columns, &options)
     }
 
-    fn generate_table_id(&mut self, table_name: &str) -> Result<u32, CatalogError> {
-        let mut hasher = DefaultHasher::new();
-        table_name.hash(&mut hasher);
-        let mut id = hasher.finish() as u32;
-        // Make sure table_id is even
-        if id & 1 == 1 {
-            id -= 1;
-        }
-        loop {
-            let proposed = [Datum::from(id as i64)];
-            let mut iter = self.prefix_metadata_table.range_scan(
-                Some(&proposed),
-                Some(&proposed),
-                LogicalTimestamp::MAX,
-            );
-            if iter.next()?.is_none() {
-                return Ok(id);
-            } else {
-                id += 2;
-            }
+    /// Creates a new table with the given options, see TableOptions
+    pub fn create_table_with_options(
+        &mut self,
+        database_name: &str,
+        table_name: &str,
+        columns: &[(String, DataType)],
+        options: &TableOptions,
+    ) -> Result<(), CatalogError> {
+        self.check_db_exists(database_name)?;
+        self.check_table_not_exists(database_name, table_name)?;
+        let id = self.generate_table_id(table_name)?;
+        let pk: Vec<_> = match &options.primary_key {
+            Some(primary_key) => primary_key.iter().map(|(_, order)| *order).collect(),
+            None => columns.iter().map(|_| SortOrder::Asc).collect(),
+        };
+
+        self.checksummed_tables_table
+            .atomic_write::<_, StorageError>(|batch| {
+                if options.checksums {
+                    batch.system_write_tuple(
+                        &self.checksummed_tables_table,
+                        &[Datum::from(id as i64)],
+                        1,
+                    );
+                }
+                if options.null_bitmap {
+                    batch.system_write_tuple(
+                        &self.null_bitmap_tables_table,
+                        &[Datum::from(id as i64)],
+                        1,
+                    );
+                }
+                if let Some(primary_key) = &options.primary_key {
+                    let key_columns = Datum::from(JsonBuilder::default().array(|array| {
+                        for (column, _) in primary_key {
+                            array.push_int(*column as i64);
+                        }
+                    }));
+                    batch.system_write_tuple(
+                        &self.primary_keys_table,
+                        &[Datum::from(id as i64), key_columns],
+                        1,
+                    );
+                }
+                Ok(())
+            })?;
+        self.create_table_impl(database_name, table_name, id, columns, &pk, false)
+    }
+
+    /// Creates a new view
+    pub fn create_view(
+        &mut self,
+        database_name: &str,
+        table_name: &str,
+        columns: &[(String, DataType)],
+        view_sql: &str,
+        view_context: &str,
+    ) -> Result<(), CatalogError> {
+        self.check_db_exists(database_name)?;
+        self.check_table_not_exists(database_name, table_name)?;
+        self.create_view_impl(
+            database_name,
+            table_name,
+            columns,
+            view_sql,
+            view_context,
+            "view",
+            false,
+        )
+    }
+
+    /// Creates a materialized view, its rows are stored in a table that's filled by refreshing it.
+    /// The state ttl, if given, is kept alongside it and dropped with it, as is the corrections
+    /// table for views that keep one and the partial states table for views with partial_states.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_materialized_view(
+        &mut self,
+        database_name: &str,
+        view_name: &str,
+        columns: &[(String, DataType)],
+        view_sql: &str,
+        view_context: &str,
+        state_ttl: Option<&StateTtl>,
+        partial_states: bool,
+    ) -> Result<(), CatalogError> {
+        self.check_db_exists(database_name)?;
+        self.check_table_not_exists(database_name, view_name)?;
+        let corrections = matches!(
+            state_ttl,
+            Some(StateTtl {
+                late_data: LateData::Corrections,
+                ..
+            })
+        );
+        if corrections {
+            self.check_table_not_exists(database_name, &corrections_table_name(view_name))?;
+        }
+        if partial_states {
+            self.check_table_not_exists(database_name, &partial_states_table_name(view_name))?;
+        }
+        let id = self.generate_table_id(view_name)?;
+        let pk: Vec<_> = columns.iter().map(|_| SortOrder::Asc).collect();
+        let view = View {
+            sql: view_sql.to_string(),
+            db_context: view_context.to_string(),
+        };
+        self.create_stored_item_impl(
+            database_name,
+            view_name,
+            id,
+            columns,
+            &pk,
+            false,
+            Some(&view),
+        )?;
+
+        if let Some(state_ttl) = state_ttl {
+            let tuple = [
+                Datum::from(database_name),
+                Datum::from(view_name),
+                Datum::from(state_ttl.ttl_ms),
+                Datum::from(state_ttl.time_column.as_str()),
+                Datum::from(state_ttl.late_data.as_str()),
+            ];
+            self.state_ttls_table
+                .atomic_write::<_, StorageError>(|batch| {
+                    batch.system_write_tuple(&self.state_ttls_table, &tuple, 1);
+                    Ok(())
+                })?;
+        }
+        if corrections {
+            self.create_table(database_name, &corrections_table_name(view_name), columns)?;
+        }
+        if partial_states {
+            self.create_table_with_options(
+                database_name,
+                &partial_states_table_name(view_name),
+                &partial_states_columns(),
+                &TableOptions {
+                    primary_key: Some(vec![(0, SortOrder::Asc), (1, SortOrder::Asc)]),
+                    ..TableOptions::default()
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Returns the definition of a materialized view along with the table holding its rows
+    pub fn materialized_view(
+        &self,
+        database_name: &str,
+        view_name: &str,
+    ) -> Result<(View, Table), CatalogError> {
+        let tables_pk = [Datum::from(database_name), Datum::from(view_name)];
+        let mut key_buf = vec![];
+        let mut value = vec![];
+        self.tables_table
+            .system_point_lookup(&tables_pk, &mut key_buf, &mut value)?;
+        let is_materialized_view =
+            value.first().and_then(Datum::as_maybe_text) == Some("materialized view");
+
+        match self.item(database_name, view_name)?.item {
+            TableOrView::Table(table) if is_materialized_view => Ok((
+                View {
+                    sql: value[1].as_text().to_string(),
+                    db_context: value[2].as_text().to_string(),
+                },
+                table,
+            )),
+            _ => Err(CatalogError::NotAMaterializedView(
+                database_name.to_string(),
+                view_name.to_string(),
+            )),
+        }
+    }
+
+    /// Returns the state ttl of a materialized view, None if it keeps all its groups open
+    pub fn state_ttl(
+        &self,
+        database_name: &str,
+        view_name: &str,
+    ) -> Result<Option<StateTtl>, CatalogError> {
+        let pk = [Datum::from(database_name), Datum::from(view_name)];
+        let mut key_buf = vec![];
+        let mut value = vec![];
+        let state_ttl = self
+            .state_ttls_table
+            .system_point_lookup(&pk, &mut key_buf, &mut value)?
+            .map(|_| StateTtl {
+                ttl_ms: value[0].as_bigint(),
+                time_column: value[1].as_text().to_string(),
+                late_data: LateData::from_str(value[2].as_text()),
+            });
+        Ok(state_ttl)
+    }
+
+    /// Returns the timestamp an incrementally maintained view's rows are up to date as of, None
+    /// for views that are refreshed by recomputing them
+    pub fn view_maintained_at(
+        &self,
+        database_name: &str,
+        view_name: &str,
+    ) -> Result<Option<LogicalTimestamp>, CatalogError> {
+        let pk = [Datum::from(database_name), Datum::from(view_name)];
+        let mut key_buf = vec![];
+        let mut value = vec![];
+        let maintained_at = self
+            .incremental_views_table
+            .system_point_lookup(&pk, &mut key_buf, &mut value)?
+            .map(|_| LogicalTimestamp::new(value[0].as_bigint() as u64));
+        Ok(maintained_at)
+    }
+
+    /// Marks the materialized view as maintained incrementally, its rows are taken to be up to
+    /// date as of the timestamp
+    pub fn set_view_maintained_at(
+        &mut self,
+        database_name: &str,
+        view_name: &str,
+        timestamp: LogicalTimestamp,
+    ) -> Result<(), CatalogError> {
+        self.incremental_views_table
+            .atomic_write::<_, StorageError>(|batch| {
+                self.write_view_maintained_at(batch, database_name, view_name, timestamp);
+                Ok(())
+            })?;
+        Ok(())
+    }
+
+    /// Like set_view_maintained_at but as part of a larger write, the changes to a view's rows
+    /// are written in the same batch so the two can't get out of step
+    pub fn write_view_maintained_at(
+        &self,
+        batch: &mut Writer,
+        database_name: &str,
+        view_name: &str,
+        timestamp: LogicalTimestamp,
+    ) {
+        let tuple = [
+            Datum::from(database_name),
+            Datum::from(view_name),
+            Datum::from(timestamp.ms as i64),
+        ];
+        batch.system_write_tuple(&self.incremental_views_table, &tuple, 1);
+    }
+
+    /// Swaps a table for a cold table, the sql reads the rows back from wherever they were
+    /// written out to. The table's rows are dropped so it's up to the caller to have copied them.
+    pub fn move_table_to_cold(
+        &mut self,
+        database_name: &str,
+        table_name: &str,
+        sql: &str,
+    ) -> Result<(), CatalogError> {
+        let columns = self.item(database_name, table_name)?.columns;
+        if self.table_type(database_name, table_name)? != "table" {
+            return Err(CatalogError::NotATable(
+                database_name.to_string(),
+                table_name.to_string(),
+            ));
+        }
+        self.drop_table_impl(database_name, table_name)?;
+        self.create_view_impl(
+            database_name,
+            table_name,
+            &columns,
+            sql,
+            database_name,
+            "cold table",
+            false,
+        )
+    }
+
+    /// Swaps a cold table back for an empty table with the same columns, returning the new table
+    /// for the caller to copy the rows back into.
+    pub fn move_table_to_hot(
+        &mut self,
+        database_name: &str,
+        table_name: &str,
+    ) -> Result<Table, CatalogError> {
+        let columns = self.item(database_name, table_name)?.columns;
+        if self.table_type(database_name, table_name)? != "cold table" {
+            return Err(CatalogError::NotAColdTable(
+                database_name.to_string(),
+                table_name.to_string(),
+            ));
+        }
+        self.drop_table_impl(database_name, table_name)?;
+        self.create_table(database_name, table_name, &columns)?;
+        match self.item(database_name, table_name)?.item {
+            TableOrView::Table(table) => Ok(table),
+            TableOrView::View(_) => unreachable!(),
+        }
+    }
+
+    /// Records the progress of a column type change, overwriting any earlier one for the table
+    pub fn update_schema_migration(
+        &mut self,
+        migration: &SchemaMigration,
+    ) -> Result<(), CatalogError> {
+        let tuple = [
+            Datum::from(migration.database.as_str()),
+            Datum::from(migration.table.as_str()),
+            Datum::from(migration.column.as_str()),
+            Datum::from(migration.datatype.to_string()),
+            Datum::from(migration.rows_total),
+            Datum::from(migration.rows_copied),
+            Datum::from(migration.status.as_str()),
+        ];
+        self.schema_migrations_table
+            .atomic_write::<_, StorageError>(|batch| {
+                batch.system_write_tuple(&self.schema_migrations_table, &tuple, 1);
+                Ok(())
+            })?;
+        Ok(())
+    }
+
+    /// Swaps a fully backfilled shadow table in for the table, the shadow takes over the
+    /// table's name and the table's rows are dropped. The catalog changes are made in one
+    /// write so readers see either the old table or the new one.
+    pub fn swap_in_shadow_table(
+        &mut self,
+        database_name: &str,
+        table_name: &str,
+        shadow_name: &str,
+    ) -> Result<(), CatalogError> {
+        for name in &[table_name, shadow_name] {
+            if self.table_type(database_name, name)? != "table" {
+                return Err(CatalogError::NotATable(
+                    database_name.to_string(),
+                    name.to_string(),
+                ));
+            }
+        }
+        self.bump_version();
+        let now = self.storage.clock().now();
+        let owned_row = |table: &Table, key: &[Datum]| -> Result<_, CatalogError> {
+            let mut iter = table.range_scan(Some(key), Some(key), LogicalTimestamp::MAX);
+            let (tuple, freq) = iter.next()?.unwrap();
+            Ok((tuple.iter().map(Datum::as_static).collect::<Vec<_>>(), freq))
+        };
+        let (table_tuple, table_freq) = owned_row(
+            &self.tables_table,
+            &[Datum::from(database_name), Datum::from(table_name)],
+        )?;
+        let (shadow_tuple, shadow_freq) = owned_row(
+            &self.tables_table,
+            &[Datum::from(database_name), Datum::from(shadow_name)],
+        )?;
+        let table_id = table_tuple[5].as_bigint() as u32;
+        let (prefix_tuple, prefix_freq) =
+            owned_row(&self.prefix_metadata_table, &table_tuple[5..6])?;
+
+        // The stats were gathered for the old column types
+        let stats_keys = self.column_stats_keys(table_id)?;
+        // and the indexes hold the old rows
+        let indexes = self.index_rows(table_id)?;
+
+        let mut renamed_tuple = shadow_tuple.clone();
+        renamed_tuple[1] = Datum::from(table_name.to_string());
+        self.tables_table.atomic_write::<_, StorageError>(|batch| {
+            batch.write_tuple(&self.tables_table, &table_tuple, now, -table_freq)?;
+            batch.write_tuple(&self.tables_table, &shadow_tuple, now, -shadow_freq)?;
+            batch.write_tuple(&self.tables_table, &renamed_tuple, now, 1)?;
+            batch.write_tuple(
+                &self.prefix_metadata_table,
+                &prefix_tuple,
+                now,
+                -prefix_freq,
+            )?;
+            batch.system_delete_tuple(
+                &self.checksummed_tables_table,
+                &[Datum::from(table_id as i64)],
+            );
+            batch.system_delete_tuple(
+                &self.null_bitmap_tables_table,
+                &[Datum::from(table_id as i64)],
+            );
+            batch.system_delete_tuple(&self.primary_keys_table, &[Datum::from(table_id as i64)]);
+            for key in &stats_keys {
+                batch.system_delete_tuple(&self.column_stats_table, key);
+            }
+            self.drop_index_metadata(batch, table_id, &indexes, now)
+        })?;
+        // Nothing refers to the old rows any more
+        self.tables_table
+            .atomic_write_without_index::<_, StorageError>(|write_batch| {
+                write_batch.delete_range(table_id.to_be_bytes(), (table_id + 2).to_be_bytes());
+                Ok(())
+            })?;
+        self.drop_index_data(&indexes)?;
+        Ok(())
+    }
+
+    /// Replaces the stats held for a table's columns with the ones passed in, as (column, stats)
+    pub fn set_column_stats(
+        &mut self,
+        database_name: &str,
+        table_name: &str,
+        table_id: u32,
+        stats: &[(String, ColumnStats)],
+    ) -> Result<(), CatalogError> {
+        let old_keys = self.column_stats_keys(table_id)?;
+        self.column_stats_table
+            .atomic_write::<_, StorageError>(|batch| {
+                for key in &old_keys {
+                    batch.system_delete_tuple(&self.column_stats_table, key);
+                }
+                for (column, column_stats) in stats {
+                    let tuple = [
+                        Datum::from(table_id as i64),
+                        Datum::from(column.as_str()),
+                        Datum::from(database_name),
+                        Datum::from(table_name),
+                        Datum::from(column_stats.to_json()),
+                    ];
+                    batch.system_write_tuple(&self.column_stats_table, &tuple, 1);
+                }
+                Ok(())
+            })?;
+        self.storage.reset_modifications(table_id);
+        Ok(())
+    }
+
+    /// The analyzed tables that have had more than threshold_percent of their rows changed
+    /// since they were analyzed, as (database, table)
+    pub fn stale_tables(
+        &self,
+        threshold_percent: u32,
+    ) -> Result<Vec<(String, String)>, CatalogError> {
+        let mut stale = vec![];
+        let mut last_table_id = None;
+        let mut iter = self.column_stats_table.full_scan(LogicalTimestamp::MAX);
+        while let Some((tuple, _freq)) = iter.next()? {
+            // Each of the table's columns has a row but one's enough to go on
+            let table_id = tuple[0].as_bigint() as u32;
+            if last_table_id == Some(table_id) {
+                continue;
+            }
+            last_table_id = Some(table_id);
+            let rows = stats::analyzed_rows(tuple[4].as_json()).unwrap_or(0).max(1) as u64;
+            let modifications = self.storage.modifications(table_id);
+            if modifications * 100 > rows * threshold_percent as u64 {
+                stale.push((
+                    tuple[2].as_text().to_string(),
+                    tuple[3].as_text().to_string(),
+                ));
+            }
+        }
+        Ok(stale)
+    }
+
+    /// The stats for each of the table's columns, in the same order as the columns passed in,
+    /// None for the columns that haven't been analyzed.
+    pub fn column_stats(
+        &self,
+        table_id: u32,
+        columns: &[(String, DataType)],
+    ) -> Result<Vec<Option<ColumnStats>>, CatalogError> {
+        let pk = [Datum::from(table_id as i64)];
+        let mut stats = vec![None; columns.len()];
+        let mut iter =
+            self.column_stats_table
+                .range_scan(Some(&pk), Some(&pk), LogicalTimestamp::MAX);
+        while let Some((tuple, _freq)) = iter.next()? {
+            if let Some(idx) = columns
+                .iter()
+                .position(|(alias, _)| alias == tuple[1].as_text())
+            {
+                stats[idx] = ColumnStats::from_json(tuple[4].as_json(), columns[idx].1);
+            }
+        }
+        Ok(stats)
+    }
+
+    /// The keys of the stats rows held for a table
+    fn column_stats_keys(&self, table_id: u32) -> Result<Vec<Vec<Datum<'static>>>, CatalogError> {
+        let pk = [Datum::from(table_id as i64)];
+        let mut keys = vec![];
+        let mut iter =
+            self.column_stats_table
+                .range_scan(Some(&pk), Some(&pk), LogicalTimestamp::MAX);
+        while let Some((tuple, _freq)) = iter.next()? {
+            keys.push(tuple[..2].iter().map(Datum::as_static).collect());
+        }
+        Ok(keys)
+    }
+
+    /// Returns the definition of a cold table, its sql reads the rows back out of their files
+    pub fn cold_table(&self, database_name: &str, table_name: &str) -> Result<View, CatalogError> {
+        match self.item(database_name, table_name)?.item {
+            TableOrView::View(view)
+                if self.table_type(database_name, table_name)? == "cold table" =>
+            {
+                Ok(view)
+            }
+            _ => Err(CatalogError::NotAColdTable(
+                database_name.to_string(),
+                table_name.to_string(),
+            )),
+        }
+    }
+
+    fn table_type(&self, database_name: &str, table_name: &str) -> Result<String, CatalogError> {
+        let tables_pk = [Datum::from(database_name), Datum::from(table_name)];
+        let mut key_buf = vec![];
+        let mut value = vec![];
+        self.tables_table
+            .system_point_lookup(&tables_pk, &mut key_buf, &mut value)?;
+        Ok(value
+            .first()
+            .and_then(Datum::as_maybe_text)
+            .unwrap_or_default()
+            .to_string())
+    }
+
+    /// Drops a table or a view
+    pub fn drop_table(
+        &mut self,
+        database_name: &str,
+        table_name: &str,
+    ) -> Result<(), CatalogError> {
+        self.check_table_exists(database_name, table_name)?;
+        if let Some(state_ttl) = self.state_ttl(database_name, table_name)? {
+            let corrections_table = corrections_table_name(table_name);
+            if state_ttl.late_data == LateData::Corrections
+                && self.table_exists(database_name, &corrections_table)?
+            {
+                self.drop_table_impl(database_name, &corrections_table)?;
+            }
+        }
+        let partial_states_table = partial_states_table_name(table_name);
+        if self
+            .view_maintained_at(database_name, table_name)?
+            .is_some()
+            && self.table_exists(database_name, &partial_states_table)?
+        {
+            self.drop_table_impl(database_name, &partial_states_table)?;
+        }
+        self.drop_table_impl(database_name, table_name)
+    }
+
+    /// Creates a database, doesn't do any checks to see if the database already exists etc.
+    fn create_database_impl(&mut self, database_name: &str) -> Result<(), CatalogError> {
+        self.databases_table.atomic_write(|batch| {
+            batch.write_tuple(
+                &self.databases_table,
+                &[Datum::from(database_name)],
+                self.storage.clock().now(),
+                1,
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Check database empty.
+    fn check_db_empty(&mut self, database_name: &str) -> Result<(), CatalogError> {
+        let db_datum = [Datum::from(database_name)];
+        let mut iter =
+            self.tables_table
+                .range_scan(Some(&db_datum), Some(&db_datum), LogicalTimestamp::MAX);
+        if iter.next()?.is_some() {
+            Err(CatalogError::DatabaseNotEmpty(database_name.to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Check for if a database with that name exists
+    fn check_db_exists(&mut self, database_name: &str) -> Result<(), CatalogError> {
+        if !self.db_exists(database_name)? {
+            Err(CatalogError::DatabaseNotFound(database_name.to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn check_db_not_exists(&mut self, database_name: &str) -> Result<(), CatalogError> {
+        if self.db_exists(database_name)? {
+            Err(CatalogError::DatabaseAlreadyExists(
+                database_name.to_string(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn db_exists(&mut self, database_name: &str) -> Result<bool, CatalogError> {
+        let db_datum = [Datum::from(database_name)];
+        let mut iter = self.databases_table.range_scan(
+            Some(&db_datum),
+            Some(&db_datum),
+            LogicalTimestamp::MAX,
+        );
+        Ok(iter.next()?.is_some())
+    }
+
+    /// Check for if a database with that name exists
+    fn check_table_exists(
+        &mut self,
+        database_name: &str,
+        table_name: &str,
+    ) -> Result<(), CatalogError> {
+        if !self.table_exists(database_name, table_name)? {
+            Err(CatalogError::TableNotFound(
+                database_name.to_string(),
+                table_name.to_string(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn check_table_not_exists(
+        &mut self,
+        database_name: &str,
+        table_name: &str,
+    ) -> Result<(), CatalogError> {
+        if self.table_exists(database_name, table_name)? {
+            Err(CatalogError::TableAlreadyExists(
+                database_name.to_string(),
+                table_name.to_string(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn table_exists(
+        &mut self,
+        database_name: &str,
+        table_name: &str,
+    ) -> Result<bool, CatalogError> {
+        let table_datum = [Datum::from(database_name), Datum::from(table_name)];
+        let mut iter = self.tables_table.range_scan(
+            Some(&table_datum),
+            Some(&table_datum),
+            LogicalTimestamp::MAX,
+        );
+        Ok(iter.next()?.is_some())
+    }
+
+    fn generate_table_id(&mut self, table_name: &str) -> Result<u32, CatalogError> {
+        let mut hasher = DefaultHasher::new();
+        table_name.hash(&mut hasher);
+        let mut id = hasher.finish() as u32;
+        // Make sure table_id is even
+        if id & 1 == 1 {
+            id -= 1;
+        }
+        // and clear of the system tables
+        if id < SYSTEM_TABLE_ID_LIMIT {
+            id += SYSTEM_TABLE_ID_LIMIT;
+        }
+        loop {
+            let proposed = [Datum::from(id as i64)];
+            let mut iter = self.prefix_metadata_table.range_scan(
+                Some(&proposed),
+                Some(&proposed),
+                LogicalTimestamp::MAX,
+            );
+            if iter.next()?.is_none() {
+                return Ok(id);
+            } else {
+                id += 2;
+            }
+        }
+    }
+
+    /// Creates a table but doesn't do any checks around the database, table, or id.
+    fn create_table_impl(
+        &mut self,
+        database_name: &str,
+        table_name: &str,
+        table_id: u32,
+        columns: &[(String, DataType)],
+        pks: &[SortOrder],
+        system: bool,
+    ) -> Result<(), CatalogError> {
+        self.create_stored_item_impl(
+            database_name,
+            table_name,
+            table_id,
+            columns,
+            pks,
+            system,
+            None,
+        )
+    }
+
+    /// Creates an item backed by a table, materialized views also keep their sql and context.
+    #[allow(clippy::too_many_arguments)]
+    fn create_stored_item_impl(
+        &mut self,
+        database_name: &str,
+        table_name: &str,
+        table_id: u32,
+        columns: &[(String, DataType)],
+        pks: &[SortOrder],
+        system: bool,
+        view: Option<&View>,
+    ) -> Result<(), CatalogError> {
+        self.bump_version();
+        let timestamp = self.storage.clock().now();
+
+        let columns_datum = Datum::from(JsonBuilder::default().array(|array| {
+            for (alias, datatype) in columns {
+                array.push_array(|col_array| {
+                    col_array.push_string(alias);
+                    col_array.push_string(&format!("{:#}", datatype));
+                })
+            }
+        }));
+
+        let pks = Datum::from(JsonBuilder::default().array(|array| {
+            for pk in pks {
+                array.push_bool(pk.is_desc());
+            }
+        }));
+
+        self.tables_table.atomic_write(|batch| {
+            let tuple = [
+                Datum::from(database_name),
+                Datum::from(table_name),
+                Datum::from(if view.is_some() {
+                    "materialized view"
+                } else {
+                    "table"
+                }),
+                view.map_or(Datum::Null, |view| Datum::from(view.sql.as_str())),
+                view.map_or(Datum::Null, |view| Datum::from(view.db_context.as_str())),
+                Datum::from(table_id as i64),
+                columns_datum,
+                Datum::from(system),
+            ];
+            batch.write_tuple(&self.tables_table, &tuple, timestamp, 1)?;
+
+            let tuple = [
+                Datum::from(table_id as i64),
+                Datum::from(columns.len() as i32),
+                pks,
+            ];
+            batch.write_tuple(&self.prefix_metadata_table, &tuple, timestamp, 1)
+        })?;
+        Ok(())
+    }
+
+    /// Creates a view but doesn't do any checks around name clashes etc, cold tables are stored
+    /// the same way but with their own type.
+    #[allow(clippy::too_many_arguments)]
+    fn create_view_impl(
+        &mut self,
+        database_name: &str,
+        table_name: &str,
+        columns: &[(String, DataType)],
+        sql: &str,
+        context: &str,
+        table_type: &str,
+        system: bool,
+    ) -> Result<(), CatalogError> {
+        self.bump_version();
+        let timestamp = self.storage.clock().now();
+
+        let columns_datum = Datum::from(JsonBuilder::default().array(|array| {
+            for (alias, datatype) in columns {
+                array.push_array(|col_array| {
+                    col_array.push_string(alias);
+                    col_array.push_string(&format!("{:#}", datatype));
+                })
+            }
+        }));
+
+        self.tables_table.atomic_write(|batch| {
+            let tuple = [
+                Datum::from(database_name),
+                Datum::from(table_name),
+                Datum::from(table_type),
+                Datum::from(sql),
+                Datum::from(context),
+                Datum::Null,
+                columns_datum,
+                Datum::from(system),
+            ];
+            batch.write_tuple(&self.tables_table, &tuple, timestamp, 1)
+        })?;
+        Ok(())
+    }
+
+    /// Moves the catalog onto a new version, dropping the cached items
+    fn bump_version(&mut self) {
+        self.version += 1;
+        self.item_cache.get_mut().unwrap().clear();
+    }
+
+    /// Drops a table or view but doesn't do any of the pre checks
+    fn drop_table_impl(
+        &mut self,
+        database_name: &str,
+        table_name: &str,
+    ) -> Result<(), CatalogError> {
+        self.bump_version();
+        let now = self.storage.clock().now();
+        let table_key = [Datum::from(database_name), Datum::from(table_name)];
+        let mut tables_iter =
+            self.tables_table
+                .range_scan(Some(&table_key), Some(&table_key), LogicalTimestamp::MAX);
+
+        let (table_tuple, table_freq) = tables_iter.next()?.unwrap();
+        let policy_names: Vec<_> = self
+            .policies(database_name, table_name)?
+            .into_iter()
+            .map(|policy| policy.name)
+            .collect();
+        let masked_columns: Vec<_> = self
+            .column_masks(database_name, table_name)?
+            .into_iter()
+            .map(|(column, _mask)| column)
+            .collect();
+        let schema_columns: Vec<_> = self
+            .column_schemas(database_name, table_name)?
+            .into_iter()
+            .map(|(column, _schema)| column)
+            .collect();
+        let stats_keys = match table_tuple[5].as_maybe_bigint() {
+            Some(table_id) => self.column_stats_keys(table_id as u32)?,
+            None => vec![],
+        };
+        let indexes = match table_tuple[5].as_maybe_bigint() {
+            Some(table_id) => self.index_rows(table_id as u32)?,
+            None => vec![],
+        };
+        self.tables_table.atomic_write::<_, StorageError>(|batch| {
+            match table_tuple[2].as_text() {
+                "table" | "materialized view" => {
+                    // first drop the data, then the meta data
+                    // TODO we should be able to genericise write batch and write batch WI so we can choose
+                    // to opt into/outof read after write vs higher perf(and delete range support!)
+                    let prefix_key = &table_tuple[5..6];
+                    let mut prefix_iter = self.prefix_metadata_table.range_scan(
+                        Some(&prefix_key),
+                        Some(&prefix_key),
+                        LogicalTimestamp::MAX,
+                    );
+
+                    let table_id = prefix_key[0].as_bigint() as u32;
+
+                    let (prefix_tuple, prefix_freq) = prefix_iter.next()?.unwrap();
+
+                    self.tables_table
+                        .atomic_write_without_index::<_, StorageError>(|write_batch| {
+                            write_batch
+                                .delete_range(table_id.to_be_bytes(), (table_id + 2).to_be_bytes());
+                            Ok(())
+                        })?;
+                    batch.write_tuple(
+                        &self.prefix_metadata_table,
+                        prefix_tuple,
+                        now,
+                        -prefix_freq,
+                    )?;
+                    batch.system_delete_tuple(&self.state_ttls_table, &table_key);
+                    batch.system_delete_tuple(&self.incremental_views_table, &table_key);
+                    batch.system_delete_tuple(
+                        &self.checksummed_tables_table,
+                        &[Datum::from(table_id as i64)],
+                    );
+                    batch.system_delete_tuple(
+                        &self.null_bitmap_tables_table,
+                        &[Datum::from(table_id as i64)],
+                    );
+                    batch.system_delete_tuple(
+                        &self.primary_keys_table,
+                        &[Datum::from(table_id as i64)],
+                    );
+                    for key in &stats_keys {
+                        batch.system_delete_tuple(&self.column_stats_table, key);
+                    }
+                    self.drop_index_metadata(batch, table_id, &indexes, now)?;
+                }
+                "view" | "cold table" => {}
+                tt => panic!("Unknown table type {}", tt),
+            }
+            batch.system_delete_tuple(&self.table_owners_table, &table_key);
+            for column in &masked_columns {
+                batch.system_delete_tuple(
+                    &self.column_masks_table,
+                    &[
+                        table_key[0].ref_clone(),
+                        table_key[1].ref_clone(),
+                        Datum::from(column.as_str()),
+                    ],
+                );
+            }
+            for column in &schema_columns {
+                batch.system_delete_tuple(
+                    &self.column_schemas_table,
+                    &[
+                        table_key[0].ref_clone(),
+                        table_key[1].ref_clone(),
+                        Datum::from(column.as_str()),
+                    ],
+                );
+            }
+            for name in &policy_names {
+                batch.system_delete_tuple(
+                    &self.policies_table,
+                    &[
+                        table_key[0].ref_clone(),
+                        table_key[1].ref_clone(),
+                        Datum::from(name.as_str()),
+                    ],
+                );
+            }
+
+            batch.write_tuple(&self.tables_table, table_tuple, now, -table_freq)?;
+
+            Ok(())
+        })?;
+        self.drop_index_data(&indexes)?;
+        Ok(())
+    }
+}
+
+fn privilege_tuple(privilege: &Privilege) -> [Datum<'_>; 4] {
+    [
+        Datum::from(privilege.user.as_str()),
+        Datum::from(privilege.privilege.as_str()),
+        Datum::from(privilege.object_type.as_str()),
+        Datum::from(privilege.object.as_str()),
+    ]
+}
+
+/// The sort orders of a table's pk from the prefix metadata's json, true for descending
+fn pk_sort_orders(pk_sorts: &Datum) -> Vec<SortOrder> {
+    pk_sorts
+        .as_json()
+        .iter_array()
+        .unwrap()
+        .map(|desc| {
+            if desc.get_boolean().unwrap() {
+                SortOrder::Desc
+            } else {
+                SortOrder::Asc
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_table() -> Result<(), CatalogError> {
+        let catalog = Catalog::new_for_test()?;
+        let item = catalog.item("incresql", "databases")?;
+        if let TableOrView::Table(table) = item.item {
+            let mut iter = table.full_scan(LogicalTimestamp::MAX);
+            assert_eq!(iter.next()?, Some(([Datum::from("default")].as_ref(), 1)));
+        } else {
+            panic!()
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_item_cache() -> Result<(), CatalogError> {
+        let mut catalog = Catalog::new_for_test()?;
+        let columns = vec![("a".to_string(), DataType::Integer)];
+        catalog.create_table("default", "test", &columns)?;
+        let version = catalog.version();
+
+        assert_eq!(catalog.item("default", "test")?.columns, columns);
+        // Served from the cache the second time around
+        assert_eq!(catalog.item("default", "test")?.columns, columns);
+
+        catalog.drop_table("default", "test")?;
+        assert!(catalog.version() > version);
+        assert!(catalog.item("default", "test").is_err());
+
+        let columns = vec![("b".to_string(), DataType::Text)];
+        catalog.create_table("default", "test", &columns)?;
+        assert_eq!(catalog.item("default", "test")?.columns, columns);
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_database() -> Result<(), CatalogError> {
+        let mut catalog = Catalog::new_for_test()?;
+        let dbs_table = catalog.item("incresql", "databases")?;
+
+        catalog.create_database("abc")?;
+
+        if let TableOrView::Table(table) = &dbs_table.item {
+            let mut iter = table.full_scan(LogicalTimestamp::MAX);
+            assert_eq!(iter.next()?, Some(([Datum::from("abc")].as_ref(), 1)));
+        } else {
+            panic!()
+        }
+
+        assert_eq!(
+            catalog.create_database("abc"),
+            Err(CatalogError::DatabaseAlreadyExists("abc".to_string()))
+        );
+
+        catalog.drop_database("abc")?;
+        if let TableOrView::Table(table) = &dbs_table.item {
+            let mut iter = table.full_scan(LogicalTimestamp::MAX);
+            assert_eq!(iter.next()?, Some(([Datum::from("default")].as_ref(), 1)));
+        } else {
+            panic!()
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_table() -> Result<(), CatalogError> {
+        let mut catalog = Catalog::new_for_test()?;
+        let columns = vec![("a".to_string(), DataType::Integer)];
+
+        catalog.create_table("default", "test", &columns)?;
+
+        let item = catalog.item("default", "test")?;
+        assert_eq!(item.columns, columns.as_slice());
+
+        catalog.drop_table("default", "test")?;
+        assert!(catalog.item("default", "test").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_view() -> Result<(), CatalogError> {
+        let mut catalog = Catalog::new_for_test()?;
+        let columns = vec![("a".to_string(), DataType::Integer)];
+
+        catalog.create_view("default", "test", &columns, "hello world", "foo")?;
+
+        let item = catalog.item("default", "test")?;
+        assert_eq!(item.columns, columns.as_slice());
+        assert_eq!(
+            item.item,
+            TableOrView::View(View {
+                sql: "hello world".to_string(),
+                db_context: "foo".to_string()
+            })
+        );
+
+        catalog.drop_table("default", "test")?;
+        assert!(catalog.item("default", "test").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_table_between_tiers() -> Result<(), CatalogError> {
+        let mut catalog = Catalog::new_for_test()?;
+        let columns = vec![("a".to_string(), DataType::Integer)];
+        catalog.create_table("default", "test", &columns)?;
+        catalog.create_view("default", "test_view", &columns, "hello world", "foo")?;
+
+        catalog.move_table_to_cold("default", "test", "hello world")?;
+        let item = catalog.item("default", "test")?;
+        assert_eq!(item.columns, columns.as_slice());
+        assert_eq!(
+            item.item,
+            TableOrView::View(View {
+                sql: "hello world".to_string(),
+                db_context: "default".to_string()
+            })
+        );
+        assert_eq!(
+            catalog.move_table_to_cold("default", "test", "hello world"),
+            Err(CatalogError::NotATable(
+                "default".to_string(),
+                "test".to_string()
+            ))
+        );
+
+        assert_eq!(
+            catalog.cold_table("default", "test")?.sql,
+            "hello world".to_string()
+        );
+        assert!(catalog.cold_table("default", "test_view").is_err());
+
+        let table = catalog.move_table_to_hot("default", "test")?;
+        assert_eq!(table.full_scan(LogicalTimestamp::MAX).next()?, None);
+        assert_eq!(
+            catalog.move_table_to_hot("default", "test_view"),
+            Err(CatalogError::NotAColdTable(
+                "default".to_string(),
+                "test_view".to_string()
+            ))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_materialized_view() -> Result<(), CatalogError> {
+        let mut catalog = Catalog::new_for_test()?;
+        let columns = vec![("a".to_string(), DataType::Integer)];
+
+        catalog.create_materialized_view(
+            "default", "test", &columns, "select 1", "foo", None, false,
+        )?;
+
+        let item = catalog.item("default", "test")?;
+        assert_eq!(item.columns, columns.as_slice());
+        let (view, table) = catalog.materialized_view("default", "test")?;
+        assert_eq!(item.item, TableOrView::Table(table));
+        assert_eq!(
+            view,
+            View {
+                sql: "select 1".to_string(),
+                db_context: "foo".to_string()
+            }
+        );
+
+        catalog.create_table("default", "plain", &columns)?;
+        assert_eq!(
+            catalog.materialized_view("default", "plain"),
+            Err(CatalogError::NotAMaterializedView(
+                "default".to_string(),
+                "plain".to_string()
+            ))
+        );
+
+        catalog.drop_table("default", "test")?;
+        assert!(catalog.item("default", "test").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_checksummed_table() -> Result<(), CatalogError> {
+        let mut catalog = Catalog::new_for_test()?;
+        let columns = vec![("a".to_string(), DataType::Integer)];
+
+        catalog.create_checksummed_table("default", "test", &columns)?;
+        catalog.create_table("default", "plain", &columns)?;
+
+        let checksums = |catalog: &Catalog, name: &str| match catalog.item("default", name) {
+            Ok(CatalogItem {
+                item: TableOrView::Table(table),
+                ..
+            }) => table.checksums(),
+            _ => panic!(),
+        };
+        assert!(checksums(&catalog, "test"));
+        assert!(!checksums(&catalog, "plain"));
+
+        // Recreating the table under the same name doesn't keep the checksums
+        catalog.drop_table("default", "test")?;
+        catalog.create_table("default", "test", &columns)?;
+        assert!(!checksums(&catalog, "test"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_table_with_options() -> Result<(), CatalogError> {
+        let mut catalog = Catalog::new_for_test()?;
+        let columns = vec![("a".to_string(), DataType::Integer)];
+        let options = TableOptions {
+            checksums: true,
+            null_bitmap: true,
+            ..TableOptions::default()
+        };
+
+        catalog.create_table_with_options("default", "test", &columns, &options)?;
+        catalog.create_table("default", "plain", &columns)?;
+
+        let options_of = |catalog: &Catalog, name: &str| match catalog.item("default", name) {
+            Ok(CatalogItem {
+                item: TableOrView::Table(table),
+                ..
+            }) => (table.checksums(), table.null_bitmap()),
+            _ => panic!(),
+        };
+        assert_eq!(options_of(&catalog, "test"), (true, true));
+        assert_eq!(options_of(&catalog, "plain"), (false, false));
+
+        catalog.drop_table("default", "test")?;
+        catalog.create_table("default", "test", &columns)?;
+        assert_eq!(options_of(&catalog, "test"), (false, false));
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_table_with_primary_key() -> Result<(), CatalogError> {
+        let mut catalog = Catalog::new_for_test()?;
+        let columns = vec![
+            ("a".to_string(), DataType::Integer),
+            ("b".to_string(), DataType::Text),
+            ("c".to_string(), DataType::Integer),
+        ];
+        let options = TableOptions {
+            primary_key: Some(vec![(2, SortOrder::Desc), (0, SortOrder::Asc)]),
+            ..TableOptions::default()
+        };
+
+        catalog.create_table_with_options("default", "test", &columns, &options)?;
+        catalog.create_table("default", "plain", &columns)?;
+
+        let key_of = |catalog: &Catalog, name: &str| match catalog.item("default", name) {
+            Ok(CatalogItem {
+                item: TableOrView::Table(table),
+                ..
+            }) => (
+                table.primary_key().map(<[usize]>::to_vec),
+                table.pk().to_vec(),
+            ),
+            _ => panic!(),
+        };
+        assert_eq!(
+            key_of(&catalog, "test"),
+            (Some(vec![2, 0]), vec![SortOrder::Desc, SortOrder::Asc])
+        );
+        assert_eq!(key_of(&catalog, "plain"), (None, vec![SortOrder::Asc; 3]));
+
+        catalog.drop_table("default", "test")?;
+        catalog.create_table("default", "test", &columns)?;
+        assert_eq!(key_of(&catalog, "test"), (None, vec![SortOrder::Asc; 3]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_indexes() -> Result<(), CatalogError> {
+        let mut catalog = Catalog::new_for_test()?;
+        let columns = vec![
+            ("a".to_string(), DataType::Integer),
+            ("b".to_string(), DataType::Integer),
+        ];
+        catalog.create_table("default", "test", &columns)?;
+        let table = match catalog.item("default", "test")?.item {
+            TableOrView::Table(table) => table,
+            TableOrView::View(_) => panic!(),
+        };
+        let row = [Datum::from(1), Datum::from(2)];
+        table.atomic_write::<_, StorageError>(|batch| {
+            batch.write_tuple(&table, &row, LogicalTimestamp::new(1), 1)
+        })?;
+
+        catalog.create_index("default", "test", "test_b", "b")?;
+        let table = match catalog.item("default", "test")?.item {
+            TableOrView::Table(table) => table,
+            TableOrView::View(_) => panic!(),
+        };
+        assert_eq!(table.indexes().len(), 1);
+        let index = table.indexes()[0].clone();
+        assert_eq!(index.key_columns(), &[1, 0]);
+        // Backfilled with the existing rows
+        let mut iter = index.full_scan(LogicalTimestamp::MAX);
+        assert_eq!(iter.next()?, Some((row.as_ref(), 1)));
+        assert_eq!(iter.next()?, None);
+
+        assert_eq!(
+            catalog.create_index("default", "test", "test_b", "a"),
+            Err(CatalogError::IndexAlreadyExists(
+                "test_b".to_string(),
+                "default".to_string(),
+                "test".to_string()
+            ))
+        );
+        assert_eq!(
+            catalog.create_index("default", "test", "test_c", "c"),
+            Err(CatalogError::ColumnNotFound(
+                "default".to_string(),
+                "test".to_string(),
+                "c".to_string()
+            ))
+        );
+
+        catalog.drop_table("default", "test")?;
+        let mut iter = index.full_scan(LogicalTimestamp::MAX);
+        assert_eq!(iter.next()?, None);
+        catalog.create_table("default", "test", &columns)?;
+        match catalog.item("default", "test")?.item {
+            TableOrView::Table(table) => assert!(table.indexes().is_empty()),
+            TableOrView::View(_) => panic!(),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_state_ttl() -> Result<(), CatalogError> {
+        let mut catalog = Catalog::new_for_test()?;
+        let columns = vec![("a".to_string(), DataType::Timestamp)];
+        let state_ttl = StateTtl {
+            ttl_ms: 1000,
+            time_column: "a".to_string(),
+            late_data: LateData::Corrections,
+        };
+
+        catalog.create_materialized_view(
+            "default",
+            "test",
+            &columns,
+            "select 1",
+            "foo",
+            Some(&state_ttl),
+            false,
+        )?;
+        catalog.create_materialized_view(
+            "default", "other", &columns, "select 1", "foo", None, false,
+        )?;
+        assert_eq!(catalog.state_ttl("default", "test")?, Some(state_ttl));
+        assert_eq!(catalog.state_ttl("default", "other")?, None);
+        assert_eq!(
+            catalog.item("default", "test_corrections")?.columns,
+            columns.as_slice()
+        );
+
+        catalog.drop_table("default", "test")?;
+        assert_eq!(catalog.state_ttl("default", "test")?, None);
+        assert!(catalog.item("default", "test_corrections").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_view_maintained_at() -> Result<(), CatalogError> {
+        let mut catalog = Catalog::new_for_test()?;
+        let columns = vec![("a".to_string(), DataType::Integer)];
+        catalog.create_materialized_view(
+            "default", "test", &columns, "select 1", "foo", None, false,
+        )?;
+        assert_eq!(catalog.view_maintained_at("default", "test")?, None);
+
+        catalog.set_view_maintained_at("default", "test", LogicalTimestamp::new(123))?;
+        assert_eq!(
+            catalog.view_maintained_at("default", "test")?,
+            Some(LogicalTimestamp::new(123))
+        );
+
+        catalog.drop_table("default", "test")?;
+        assert_eq!(catalog.view_maintained_at("default", "test")?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_oldest_needed_changes() -> Result<(), CatalogError> {
+        let mut catalog = Catalog::new_for_test()?;
+        assert_eq!(catalog.oldest_needed_changes()?, None);
+
+        let columns = vec![("a".to_string(), DataType::Integer)];
+        catalog.create_materialized_view(
+            "default", "test", &columns, "select 1", "foo", None, false,
+        )?;
+        catalog.set_view_maintained_at("default", "test", LogicalTimestamp::new(123))?;
+        let sink = Sink {
+            database: "default".to_string(),
+            name: "test_sink".to_string(),
+            source_database: "default".to_string(),
+            source: "test".to_string(),
+            path: "test.json".to_string(),
+            emitted_at: Some(LogicalTimestamp::new(100)),
+        };
+        catalog.create_sink(&sink)?;
+        assert_eq!(
+            catalog.oldest_needed_changes()?,
+            Some(LogicalTimestamp::new(100))
+        );
+
+        catalog.drop_sink("default", "test_sink")?;
+        assert_eq!(
+            catalog.oldest_needed_changes()?,
+            Some(LogicalTimestamp::new(123))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_partial_states_table() -> Result<(), CatalogError> {
+        let mut catalog = Catalog::new_for_test()?;
+        let columns = vec![("a".to_string(), DataType::Integer)];
+        catalog
+            .create_materialized_view("default", "test", &columns, "select 1", "foo", None, true)?;
+        catalog.set_view_maintained_at("default", "test", LogicalTimestamp::default())?;
+        let item = catalog.item("default", "test_partial_states")?;
+        assert_eq!(item.columns, partial_states_columns().as_slice());
+        if let TableOrView::Table(table) = item.item {
+            assert_eq!(table.primary_key(), Some([0, 1].as_ref()));
+        } else {
+            panic!()
         }
-    }
 
-    /// Creates a table but doesn't do any checks around the database, table, or id.
-    fn create_table_impl(
-        &mut self,
-        database_name: &str,
-        table_name: &str,
-        table_id: u32,
-        columns: &[(String, DataType)],
-        pks: &[SortOrder],
-        system: bool,
-    ) -> Result<(), CatalogError> {
-        let timestamp = LogicalTimestamp::now();
+        catalog.drop_table("default", "test")?;
+        assert!(!catalog.table_exists("default", "test_partial_states")?);
+        Ok(())
+    }
 
-        let columns_datum = Datum::from(JsonBuilder::default().array(|array| {
-            for (alias, datatype) in columns {
-                array.push_array(|col_array| {
-                    col_array.push_string(alias);
-                    col_array.push_string(&format!("{:#}", datatype));
-                })
-            }
-        }));
+    #[test]
+    fn test_sinks() -> Result<(), CatalogError> {
+        let mut catalog = Catalog::new_for_test()?;
+        let mut sink = Sink {
+            database: "default".to_string(),
+            name: "test".to_string(),
+            source_database: "default".to_string(),
+            source: "orders".to_string(),
+            path: "orders.json".to_string(),
+            emitted_at: None,
+        };
 
-        let pks = Datum::from(JsonBuilder::default().array(|array| {
-            for pk in pks {
-                array.push_bool(pk.is_desc());
-            }
-        }));
+        catalog.create_sink(&sink)?;
+        assert_eq!(catalog.sink("default", "test")?, sink);
+        assert_eq!(
+            catalog.create_sink(&sink),
+            Err(CatalogError::SinkAlreadyExists(
+                "default".to_string(),
+                "test".to_string()
+            ))
+        );
 
-        self.tables_table.atomic_write(|batch| {
-            let tuple = [
-                Datum::from(database_name),
-                Datum::from(table_name),
-                Datum::from("table"),
-                Datum::Null,
-                Datum::Null,
-                Datum::from(table_id as i64),
-                columns_datum,
-                Datum::from(system),
-            ];
-            batch.write_tuple(&self.tables_table, &tuple, timestamp, 1)?;
+        sink.emitted_at = Some(LogicalTimestamp::new(123));
+        catalog.update_sink(&sink)?;
+        assert_eq!(catalog.sink("default", "test")?, sink);
 
-            let tuple = [
-                Datum::from(table_id as i64),
-                Datum::from(columns.len() as i32),
-                pks,
-            ];
-            batch.write_tuple(&self.prefix_metadata_table, &tuple, timestamp, 1)
-        })?;
+        catalog.drop_sink("default", "test")?;
+        assert_eq!(
+            catalog.sink("default", "test"),
+            Err(CatalogError::SinkNotFound(
+                "default".to_string(),
+                "test".to_string()
+            ))
+        );
         Ok(())
     }
 
-    /// Creates a view but doesn't do any checks around name clashes etc
-    fn create_view_impl(
-        &mut self,
-        database_name: &str,
-        table_name: &str,
-        columns: &[(String, DataType)],
-        sql: &str,
-        context: &str,
-        system: bool,
-    ) -> Result<(), CatalogError> {
-        let timestamp = LogicalTimestamp::now();
-
-        let columns_datum = Datum::from(JsonBuilder::default().array(|array| {
-            for (alias, datatype) in columns {
-                array.push_array(|col_array| {
-                    col_array.push_string(alias);
-                    col_array.push_string(&format!("{:#}", datatype));
-                })
-            }
-        }));
+    #[test]
+    fn test_procedures() -> Result<(), CatalogError> {
+        let mut catalog = Catalog::new_for_test()?;
+        catalog.create_procedure("default", "test", "SET @x = 1;")?;
+        assert_eq!(catalog.procedure("default", "test")?, "SET @x = 1;");
+        assert_eq!(
+            catalog.create_procedure("default", "test", "SET @x = 2;"),
+            Err(CatalogError::ProcedureAlreadyExists(
+                "default".to_string(),
+                "test".to_string()
+            ))
+        );
+        assert!(catalog.create_procedure("missing", "test", "").is_err());
 
-        self.tables_table.atomic_write(|batch| {
-            let tuple = [
-                Datum::from(database_name),
-                Datum::from(table_name),
-                Datum::from("view"),
-                Datum::from(sql),
-                Datum::from(context),
-                Datum::Null,
-                columns_datum,
-                Datum::from(system),
-            ];
-            batch.write_tuple(&self.tables_table, &tuple, timestamp, 1)
-        })?;
+        catalog.drop_procedure("default", "test")?;
+        assert_eq!(
+            catalog.procedure("default", "test"),
+            Err(CatalogError::ProcedureNotFound(
+                "default".to_string(),
+                "test".to_string()
+            ))
+        );
         Ok(())
     }
 
-    /// Drops a table or view but doesn't do any of the pre checks
-    fn drop_table_impl(
-        &mut self,
-        database_name: &str,
-        table_name: &str,
-    ) -> Result<(), CatalogError> {
-        let now = LogicalTimestamp::now();
-        let table_key = [Datum::from(database_name), Datum::from(table_name)];
-        let mut tables_iter =
-            self.tables_table
-                .range_scan(Some(&table_key), Some(&table_key), LogicalTimestamp::MAX);
+    #[test]
+    fn test_events() -> Result<(), CatalogError> {
+        let mut catalog = Catalog::new_for_test()?;
+        let mut event = Event {
+            database: "default".to_string(),
+            name: "cleanup".to_string(),
+            every_ms: 60_000,
+            sql: "CALL cleanup".to_string(),
+            last_run_ms: 1000,
+            last_error: None,
+        };
+        catalog.create_event(&event)?;
+        assert_eq!(
+            catalog.create_event(&event),
+            Err(CatalogError::EventAlreadyExists(
+                "default".to_string(),
+                "cleanup".to_string()
+            ))
+        );
 
-        let (table_tuple, table_freq) = tables_iter.next()?.unwrap();
-        self.tables_table.atomic_write::<_, StorageError>(|batch| {
-            match table_tuple[2].as_text() {
-                "table" => {
-                    // first drop the data, then the meta data
-                    // TODO we should be able to genericise write batch and write batch WI so we can choose
-                    // to opt into/outof read after write vs higher perf(and delete range support!)
-                    let prefix_key = &table_tuple[5..6];
-                    let mut prefix_iter = self.prefix_metadata_table.range_scan(
-                        Some(&prefix_key),
-                        Some(&prefix_key),
-                        LogicalTimestamp::MAX,
-                    );
+        event.last_run_ms = 61_000;
+        event.last_error = Some("Procedure default.cleanup not found".to_string());
+        catalog.update_event(&event)?;
+        assert_eq!(catalog.event("default", "cleanup")?, event);
+        assert_eq!(catalog.events()?, vec![event]);
 
-                    let table_id = prefix_key[0].as_bigint() as u32;
+        catalog.drop_event("default", "cleanup")?;
+        assert_eq!(catalog.events()?, vec![]);
+        assert_eq!(
+            catalog.drop_event("default", "cleanup"),
+            Err(CatalogError::EventNotFound(
+                "default".to_string(),
+                "cleanup".to_string()
+            ))
+        );
+        Ok(())
+    }
 
-                    let (prefix_tuple, prefix_freq) = prefix_iter.next()?.unwrap();
+    #[test]
+    fn test_policies() -> Result<(), CatalogError> {
+        let mut catalog = Catalog::new_for_test()?;
+        let columns = vec![("tenant".to_string(), DataType::Text)];
+        catalog.create_table("default", "orders", &columns)?;
+        catalog.set_table_owner("default", "orders", "admin")?;
+        assert_eq!(
+            catalog.table_owner("default", "orders")?,
+            Some("admin".to_string())
+        );
+        assert_eq!(catalog.table_owner("incresql", "tables")?, None);
 
-                    self.tables_table
-                        .atomic_write_without_index::<_, StorageError>(|write_batch| {
-                            write_batch
-                                .delete_range(table_id.to_be_bytes(), (table_id + 2).to_be_bytes());
-                            Ok(())
-                        })?;
-                    batch.write_tuple(
-                        &self.prefix_metadata_table,
-                        prefix_tuple,
-                        now,
-                        -prefix_freq,
-                    )?;
-                }
-                "view" => {}
-                tt => panic!("Unknown table type {}", tt),
-            }
+        let policy = Policy {
+            database: "default".to_string(),
+            table: "orders".to_string(),
+            name: "tenants".to_string(),
+            predicate: "tenant = current_user()".to_string(),
+        };
+        catalog.create_policy(&policy)?;
+        assert_eq!(catalog.policies("default", "orders")?, vec![policy.clone()]);
+        assert_eq!(
+            catalog.create_policy(&policy),
+            Err(CatalogError::PolicyAlreadyExists(
+                "tenants".to_string(),
+                "default".to_string(),
+                "orders".to_string()
+            ))
+        );
+        assert!(catalog
+            .create_policy(&Policy {
+                table: "missing".to_string(),
+                ..policy.clone()
+            })
+            .is_err());
 
-            batch.write_tuple(&self.tables_table, table_tuple, now, -table_freq)?;
+        catalog.drop_policy("default", "orders", "tenants")?;
+        assert_eq!(catalog.policies("default", "orders")?, vec![]);
+        assert!(catalog.drop_policy("default", "orders", "tenants").is_err());
 
-            Ok(())
-        })?;
+        // Dropping the table drops its owner, policies and masks
+        catalog.create_policy(&policy)?;
+        catalog.set_column_mask("default", "orders", "tenant", Some("'x'"))?;
+        catalog.drop_table("default", "orders")?;
+        catalog.create_table("default", "orders", &columns)?;
+        assert_eq!(catalog.policies("default", "orders")?, vec![]);
+        assert_eq!(catalog.table_owner("default", "orders")?, None);
+        assert_eq!(catalog.column_masks("default", "orders")?, vec![]);
         Ok(())
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
     #[test]
-    fn test_get_table() -> Result<(), CatalogError> {
-        let catalog = Catalog::new_for_test()?;
-        let item = catalog.item("incresql", "databases")?;
-        if let TableOrView::Table(table) = item.item {
-            let mut iter = table.full_scan(LogicalTimestamp::MAX);
-            assert_eq!(iter.next()?, Some(([Datum::from("default")].as_ref(), 1)));
-        } else {
-            panic!()
-        }
+    fn test_column_masks() -> Result<(), CatalogError> {
+        let mut catalog = Catalog::new_for_test()?;
+        catalog.set_column_mask("default", "customers", "card", Some("mask_text(card, 4)"))?;
+        catalog.set_column_mask("default", "customers", "card", Some("'****'"))?;
+        catalog.set_column_mask("default", "customers", "email", Some("'hidden'"))?;
+        assert_eq!(
+            catalog.column_masks("default", "customers")?,
+            vec![
+                ("card".to_string(), "'****'".to_string()),
+                ("email".to_string(), "'hidden'".to_string())
+            ]
+        );
+        assert_eq!(catalog.column_masks("default", "other")?, vec![]);
+
+        catalog.set_column_mask("default", "customers", "card", None)?;
+        assert_eq!(
+            catalog.set_column_mask("default", "customers", "card", None),
+            Err(CatalogError::ColumnMaskNotFound(
+                "default".to_string(),
+                "customers".to_string(),
+                "card".to_string()
+            ))
+        );
+        assert_eq!(
+            catalog.column_masks("default", "customers")?,
+            vec![("email".to_string(), "'hidden'".to_string())]
+        );
         Ok(())
     }
 
     #[test]
-    fn test_create_database() -> Result<(), CatalogError> {
+    fn test_column_schemas() -> Result<(), CatalogError> {
         let mut catalog = Catalog::new_for_test()?;
-        let dbs_table = catalog.item("incresql", "databases")?;
+        let columns = [("doc".to_string(), DataType::Json)];
+        catalog.create_table("default", "orders", &columns)?;
+        catalog.set_column_schema("default", "orders", "doc", Some("true"))?;
+        catalog.set_column_schema("default", "orders", "doc", Some(r#"{"type": "object"}"#))?;
+        assert_eq!(
+            catalog.column_schemas("default", "orders")?,
+            vec![("doc".to_string(), r#"{"type": "object"}"#.to_string())]
+        );
+        assert_eq!(catalog.column_schemas("default", "other")?, vec![]);
 
-        catalog.create_database("abc")?;
+        catalog.set_column_schema("default", "orders", "doc", None)?;
+        assert_eq!(
+            catalog.set_column_schema("default", "orders", "doc", None),
+            Err(CatalogError::ColumnSchemaNotFound(
+                "default".to_string(),
+                "orders".to_string(),
+                "doc".to_string()
+            ))
+        );
 
-        if let TableOrView::Table(table) = &dbs_table.item {
-            let mut iter = table.full_scan(LogicalTimestamp::MAX);
-            assert_eq!(iter.next()?, Some(([Datum::from("abc")].as_ref(), 1)));
-        } else {
-            panic!()
-        }
+        // Dropping the table drops its schemas
+        catalog.set_column_schema("default", "orders", "doc", Some("true"))?;
+        catalog.drop_table("default", "orders")?;
+        catalog.create_table("default", "orders", &columns)?;
+        assert_eq!(catalog.column_schemas("default", "orders")?, vec![]);
+        Ok(())
+    }
 
+    #[test]
+    fn test_privileges() -> Result<(), CatalogError> {
+        let mut catalog = Catalog::new_for_test()?;
+        let privilege = |user: &str, object: &str| Privilege {
+            user: user.to_string(),
+            privilege: "EXECUTE".to_string(),
+            object_type: "DIRECTORY".to_string(),
+            object: object.to_string(),
+        };
+        catalog.grant_privilege(&privilege("bob", "data/"))?;
+        catalog.grant_privilege(&privilege("bob", "data/"))?;
+        catalog.grant_privilege(&privilege("bob", "logs/"))?;
+        catalog.grant_privilege(&privilege("bobby", "other/"))?;
         assert_eq!(
-            catalog.create_database("abc"),
-            Err(CatalogError::DatabaseAlreadyExists("abc".to_string()))
+            catalog.privileges("bob")?,
+            vec![privilege("bob", "data/"), privilege("bob", "logs/")]
         );
 
-        catalog.drop_database("abc")?;
-        if let TableOrView::Table(table) = &dbs_table.item {
-            let mut iter = table.full_scan(LogicalTimestamp::MAX);
-            assert_eq!(iter.next()?, Some(([Datum::from("default")].as_ref(), 1)));
-        } else {
-            panic!()
-        }
-
+        catalog.revoke_privilege(&privilege("bob", "data/"))?;
+        assert_eq!(
+            catalog.revoke_privilege(&privilege("bob", "data/")),
+            Err(CatalogError::PrivilegeNotGranted(
+                "EXECUTE".to_string(),
+                "DIRECTORY data/".to_string(),
+                "bob".to_string()
+            ))
+        );
+        assert_eq!(catalog.privileges("bob")?, vec![privilege("bob", "logs/")]);
+        assert!(is_superuser(""));
+        assert!(is_superuser("root"));
+        assert!(!is_superuser("bob"));
         Ok(())
     }
 
     #[test]
-    fn test_create_table() -> Result<(), CatalogError> {
+    fn test_user_defaults() -> Result<(), CatalogError> {
         let mut catalog = Catalog::new_for_test()?;
-        let columns = vec![("a".to_string(), DataType::Integer)];
+        catalog.set_user_default("bob", "query_timeout", Some("1000"))?;
+        catalog.set_user_default("bob", "autocommit", Some("0"))?;
+        catalog.set_user_default("alice", "autocommit", Some("1"))?;
+        assert_eq!(
+            catalog.user_defaults("bob")?,
+            vec![
+                ("autocommit".to_string(), "0".to_string()),
+                ("query_timeout".to_string(), "1000".to_string())
+            ]
+        );
+
+        catalog.set_user_default("bob", "autocommit", None)?;
+        assert_eq!(
+            catalog.user_defaults("bob")?,
+            vec![("query_timeout".to_string(), "1000".to_string())]
+        );
+        assert_eq!(catalog.user_defaults("carol")?, vec![]);
+        Ok(())
+    }
 
+    #[test]
+    fn test_column_stats() -> Result<(), CatalogError> {
+        let mut catalog = Catalog::new_for_test()?;
+        let columns = vec![
+            ("id".to_string(), DataType::Integer),
+            ("name".to_string(), DataType::Text),
+        ];
         catalog.create_table("default", "test", &columns)?;
+        let table_id = match catalog.item("default", "test")?.item {
+            TableOrView::Table(table) => table.id(),
+            TableOrView::View(_) => panic!(),
+        };
+        assert_eq!(catalog.column_stats(table_id, &columns)?, vec![None, None]);
 
-        let item = catalog.item("default", "test")?;
-        assert_eq!(item.columns, columns.as_slice());
+        let values = [(Datum::from(1), 1), (Datum::from(2), 1)];
+        let stats = ColumnStats::from_sorted_values(DataType::Integer, &values);
+        catalog.set_column_stats(
+            "default",
+            "test",
+            table_id,
+            &[("id".to_string(), stats.clone())],
+        )?;
+        assert_eq!(
+            catalog.column_stats(table_id, &columns)?,
+            vec![Some(stats), None]
+        );
 
         catalog.drop_table("default", "test")?;
-        assert!(catalog.item("default", "test").is_err());
+        assert_eq!(catalog.column_stats(table_id, &columns)?, vec![None, None]);
         Ok(())
     }
 
     #[test]
-    fn test_create_view() -> Result<(), CatalogError> {
+    fn test_stale_tables() -> Result<(), CatalogError> {
         let mut catalog = Catalog::new_for_test()?;
-        let columns = vec![("a".to_string(), DataType::Integer)];
+        let columns = vec![("id".to_string(), DataType::Integer)];
+        catalog.create_table("default", "test", &columns)?;
+        let table = match catalog.item("default", "test")?.item {
+            TableOrView::Table(table) => table,
+            TableOrView::View(_) => panic!(),
+        };
+        let insert = |ids: std::ops::Range<i32>| {
+            table.atomic_write::<_, StorageError>(|batch| {
+                for id in ids {
+                    batch.write_tuple(&table, &[Datum::from(id)], LogicalTimestamp::now(), 1)?;
+                }
+                Ok(())
+            })
+        };
+        insert(0..10)?;
+        // Never analyzed so never stale
+        assert_eq!(catalog.stale_tables(20)?, vec![]);
 
-        catalog.create_view("default", "test", &columns, "hello world", "foo")?;
+        let values: Vec<_> = (0..10).map(|id| (Datum::from(id), 1)).collect();
+        let stats = ColumnStats::from_sorted_values(DataType::Integer, &values);
+        catalog.set_column_stats("default", "test", table.id(), &[("id".to_string(), stats)])?;
+        insert(10..12)?;
+        assert_eq!(catalog.stale_tables(20)?, vec![]);
+        insert(12..13)?;
+        assert_eq!(
+            catalog.stale_tables(20)?,
+            vec![("default".to_string(), "test".to_string())]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_swap_in_shadow_table() -> Result<(), CatalogError> {
+        let mut catalog = Catalog::new_for_test()?;
+        catalog.create_table("default", "test", &[("a".to_string(), DataType::Text)])?;
+        let shadow_columns = vec![("a".to_string(), DataType::Integer)];
+        catalog.create_table("default", "test__shadow", &shadow_columns)?;
+        let shadow = match catalog.item("default", "test__shadow")?.item {
+            TableOrView::Table(table) => table,
+            TableOrView::View(_) => panic!(),
+        };
 
+        catalog.swap_in_shadow_table("default", "test", "test__shadow")?;
         let item = catalog.item("default", "test")?;
-        assert_eq!(item.columns, columns.as_slice());
+        assert_eq!(item.columns, shadow_columns);
+        assert_eq!(item.item, TableOrView::Table(shadow));
+        assert!(catalog.item("default", "test__shadow").is_err());
         assert_eq!(
-            item.item,
-            TableOrView::View(View {
-                sql: "hello world".to_string(),
-                db_context: "foo".to_string()
-            })
+            catalog.swap_in_shadow_table("default", "test", "missing"),
+            Err(CatalogError::NotATable(
+                "default".to_string(),
+                "missing".to_string()
+            ))
         );
-
-        catalog.drop_table("default", "test")?;
-        assert!(catalog.item("default", "test").is_err());
         Ok(())
     }
 }
@@ -0,0 +1,194 @@
+use data::json::{Json, JsonBuilder, OwnedJson};
+use data::rust_decimal::prelude::ToPrimitive;
+use data::rust_decimal::Decimal;
+use data::{DataType, Datum};
+
+/// Histograms are equi-depth with up to this many buckets, ie each bucket holds roughly the
+/// same number of the column's non null values. Columns with fewer distinct values get one
+/// bucket per value.
+pub const HISTOGRAM_BUCKETS: usize = 16;
+
+/// What ANALYZE found out about one of a table's columns, the planner uses these to estimate
+/// how many rows filters will let through.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ColumnStats {
+    pub rows: i64,
+    pub null_fraction: Decimal,
+    pub distinct: i64,
+    // The bucket boundaries from the smallest value to the largest, so one more than the number
+    // of buckets. Empty when the column's all nulls or its type has no meaningful ordering.
+    pub histogram: Vec<Datum<'static>>,
+}
+
+impl ColumnStats {
+    /// Works out the stats from the column's values and their frequencies, the values must
+    /// already be sorted and grouped so each distinct value appears once.
+    pub fn from_sorted_values(datatype: DataType, values: &[(Datum<'static>, i64)]) -> Self {
+        let rows: i64 = values.iter().map(|(_datum, freq)| *freq).sum();
+        let nulls: i64 = values
+            .iter()
+            .filter(|(datum, _freq)| datum.is_null())
+            .map(|(_datum, freq)| *freq)
+            .sum();
+        let non_null: Vec<_> = values
+            .iter()
+            .filter(|(datum, _freq)| !datum.is_null())
+            .collect();
+        let null_fraction = if rows == 0 {
+            Decimal::new(0, 0)
+        } else {
+            (Decimal::from(nulls) / Decimal::from(rows))
+                .round_dp(4)
+                .normalize()
+        };
+
+        let mut histogram = vec![];
+        if has_histogram(datatype) && !non_null.is_empty() {
+            // Walk the values picking out the ones that every 1/buckets of the rows fall on
+            let non_null_rows = rows - nulls;
+            let buckets = HISTOGRAM_BUCKETS.min(non_null.len()) as i64;
+            let mut seen = 0;
+            let mut bucket = 1;
+            histogram.push(non_null[0].0.clone());
+            for (datum, freq) in &non_null {
+                seen += freq;
+                while bucket < buckets && seen * buckets >= bucket * non_null_rows {
+                    histogram.push(datum.clone());
+                    bucket += 1;
+                }
+            }
+            histogram.push(non_null[non_null.len() - 1].0.clone());
+        }
+
+        ColumnStats {
+            rows,
+            null_fraction,
+            distinct: non_null.len() as i64,
+            histogram,
+        }
+    }
+
+    pub fn to_json(&self) -> OwnedJson {
+        JsonBuilder::default().object(|object| {
+            object.push_int("rows", self.rows);
+            object.push_decimal("null_fraction", self.null_fraction);
+            object.push_int("distinct", self.distinct);
+            object.push_array("histogram", |array| {
+                for datum in &self.histogram {
+                    match datum {
+                        Datum::Boolean(b) => array.push_bool(*b),
+                        Datum::Integer(i) => array.push_int(*i as i64),
+                        Datum::BigInt(i) => array.push_int(*i),
+                        Datum::Decimal(d) => array.push_decimal(*d),
+                        Datum::ByteAOwned(_) | Datum::ByteAInline(..) | Datum::ByteARef(_) => {
+                            array.push_string(datum.as_text())
+                        }
+                        _ => array.push_null(),
+                    }
+                }
+            });
+        })
+    }
+
+    /// Reads the stats back out of their json, the column's type is needed to turn the
+    /// histogram's values back into datums.
+    pub fn from_json(json: Json, datatype: DataType) -> Option<Self> {
+        let mut stats = ColumnStats {
+            rows: 0,
+            null_fraction: Decimal::new(0, 0),
+            distinct: 0,
+            histogram: vec![],
+        };
+        for (key, value) in json.iter_object()? {
+            match key {
+                "rows" => stats.rows = value.get_number()?.to_i64()?,
+                "null_fraction" => stats.null_fraction = value.get_number()?,
+                "distinct" => stats.distinct = value.get_number()?.to_i64()?,
+                "histogram" => {
+                    for bound in value.iter_array()? {
+                        stats.histogram.push(datum_from_json(bound, datatype)?);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Some(stats)
+    }
+}
+
+/// The number of rows the table had when the stats were gathered
+pub(crate) fn analyzed_rows(json: Json) -> Option<i64> {
+    json.iter_object()?
+        .find(|(key, _value)| *key == "rows")
+        .and_then(|(_key, value)| value.get_number()?.to_i64())
+}
+
+/// Histograms are only kept for the types the planner can compare constants against
+fn has_histogram(datatype: DataType) -> bool {
+    matches!(
+        datatype,
+        DataType::Boolean
+            | DataType::Integer
+            | DataType::BigInt
+            | DataType::Decimal(..)
+            | DataType::Text
+            | DataType::Date
+            | DataType::Timestamp
+    )
+}
+
+fn datum_from_json(json: Json, datatype: DataType) -> Option<Datum<'static>> {
+    match datatype {
+        DataType::Boolean => json.get_boolean().map(Datum::from),
+        DataType::Integer | DataType::Date => json.get_number()?.to_i32().map(Datum::Integer),
+        DataType::BigInt | DataType::Timestamp => json.get_number()?.to_i64().map(Datum::BigInt),
+        DataType::Decimal(..) => json.get_number().map(Datum::from),
+        DataType::Text => json.get_string().map(|s| Datum::from(s.to_string())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_sorted_values() {
+        let mut values = vec![(Datum::Null, 2)];
+        values.extend((1..=10).map(|i| (Datum::from(i), if i == 10 { 9 } else { 1 })));
+        let stats = ColumnStats::from_sorted_values(DataType::Integer, &values);
+        assert_eq!(stats.rows, 20);
+        assert_eq!(stats.null_fraction, Decimal::new(1, 1));
+        assert_eq!(stats.distinct, 10);
+        // Half the non null rows are 10 so it takes up the top half of the buckets
+        assert_eq!(
+            stats.histogram,
+            [1, 2, 4, 6, 8, 9, 10, 10, 10, 10, 10]
+                .iter()
+                .map(|i| Datum::from(*i))
+                .collect::<Vec<_>>()
+        );
+
+        let values: Vec<_> = (0..1000).map(|i| (Datum::from(i), 1)).collect();
+        let stats = ColumnStats::from_sorted_values(DataType::Integer, &values);
+        assert_eq!(stats.histogram.len(), HISTOGRAM_BUCKETS + 1);
+
+        let nulls = ColumnStats::from_sorted_values(DataType::Integer, &[(Datum::Null, 3)]);
+        assert_eq!(nulls.null_fraction, Decimal::new(1, 0));
+        assert!(nulls.histogram.is_empty());
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let values: Vec<_> = ["a", "b", "c"]
+            .iter()
+            .map(|s| (Datum::from(s.to_string()), 1))
+            .collect();
+        let stats = ColumnStats::from_sorted_values(DataType::Text, &values);
+        let json = stats.to_json();
+        assert_eq!(
+            ColumnStats::from_json(json.as_json(), DataType::Text),
+            Some(stats)
+        );
+    }
+}
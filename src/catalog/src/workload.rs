@@ -0,0 +1,109 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The kinds of work competing for the cpu, interactive statements are the ones clients are
+/// waiting on while maintenance (refreshing views, flushing sinks) gives way to them.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum WorkloadClass {
+    Interactive,
+    Maintenance,
+}
+
+impl WorkloadClass {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WorkloadClass::Interactive => "interactive",
+            WorkloadClass::Maintenance => "maintenance",
+        }
+    }
+}
+
+/// Running totals of the statements run and time spent by each workload class, the catalog
+/// publishes them as incresql.workload_stats.
+#[derive(Debug, Default)]
+pub struct WorkloadStats {
+    classes: [ClassStats; 2],
+}
+
+#[derive(Debug, Default)]
+struct ClassStats {
+    statements: AtomicU64,
+    elapsed_micros: AtomicU64,
+    yielded_micros: AtomicU64,
+}
+
+/// A snapshot of one class's totals
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ClassTotals {
+    pub class: WorkloadClass,
+    pub statements: u64,
+    pub elapsed_micros: u64,
+    pub yielded_micros: u64,
+}
+
+impl WorkloadStats {
+    fn class(&self, class: WorkloadClass) -> &ClassStats {
+        &self.classes[class as usize]
+    }
+
+    pub fn record_statement(&self, class: WorkloadClass) {
+        self.class(class).statements.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_elapsed(&self, class: WorkloadClass, micros: u64) {
+        self.class(class)
+            .elapsed_micros
+            .fetch_add(micros, Ordering::Relaxed);
+    }
+
+    pub fn record_yielded(&self, class: WorkloadClass, micros: u64) {
+        self.class(class)
+            .yielded_micros
+            .fetch_add(micros, Ordering::Relaxed);
+    }
+
+    pub fn totals(&self) -> Vec<ClassTotals> {
+        [WorkloadClass::Interactive, WorkloadClass::Maintenance]
+            .iter()
+            .map(|class| {
+                let stats = self.class(*class);
+                ClassTotals {
+                    class: *class,
+                    statements: stats.statements.load(Ordering::Relaxed),
+                    elapsed_micros: stats.elapsed_micros.load(Ordering::Relaxed),
+                    yielded_micros: stats.yielded_micros.load(Ordering::Relaxed),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_totals() {
+        let stats = WorkloadStats::default();
+        stats.record_statement(WorkloadClass::Maintenance);
+        stats.record_elapsed(WorkloadClass::Maintenance, 10);
+        stats.record_yielded(WorkloadClass::Maintenance, 30);
+        stats.record_statement(WorkloadClass::Interactive);
+        assert_eq!(
+            stats.totals(),
+            vec![
+                ClassTotals {
+                    class: WorkloadClass::Interactive,
+                    statements: 1,
+                    elapsed_micros: 0,
+                    yielded_micros: 0
+                },
+                ClassTotals {
+                    class: WorkloadClass::Maintenance,
+                    statements: 1,
+                    elapsed_micros: 10,
+                    yielded_micros: 30
+                }
+            ]
+        );
+    }
+}
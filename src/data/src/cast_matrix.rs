@@ -0,0 +1,182 @@
+use crate::{DataType, DECIMAL_MAX_PRECISION};
+
+/// How a value of one type can be turned into a value of another.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum CastKind {
+    /// The value is cast without being asked wherever the other type is expected, ie function
+    /// args, inserts and union alls. The cost ranks the casts when there's more than one
+    /// function signature the args could be cast to, 0 is for types that are the same as far
+    /// as the cast is concerned.
+    Implicit(u32),
+    /// Only with an explicit CAST, these can fail or lose information
+    Explicit,
+    /// Never, not even with an explicit CAST
+    Forbidden,
+}
+
+/// One of each type, all the pairs of these make up the cast matrix
+pub const ALL_TYPES: [DataType; 12] = [
+    DataType::Null,
+    DataType::Boolean,
+    DataType::Integer,
+    DataType::BigInt,
+    DataType::Decimal(DECIMAL_MAX_PRECISION, 2),
+    DataType::Text,
+    DataType::ByteA,
+    DataType::Json,
+    DataType::Date,
+    DataType::Timestamp,
+    DataType::JsonPath,
+    DataType::Enum(0),
+];
+
+/// Every pair of types along with how the first can be cast to the second
+pub fn cast_matrix() -> impl Iterator<Item = (DataType, DataType, CastKind)> {
+    ALL_TYPES.iter().flat_map(|from| {
+        ALL_TYPES
+            .iter()
+            .map(move |to| (*from, *to, cast_kind(*from, *to)))
+    })
+}
+
+/// How a value of the from type can be cast to the to type. This is the one place the casts
+/// are decided, the cast functions registered for each type have to agree with it.
+pub fn cast_kind(from: DataType, to: DataType) -> CastKind {
+    let numeric = |datatype: DataType| {
+        matches!(
+            datatype,
+            DataType::Integer | DataType::BigInt | DataType::Decimal(..)
+        )
+    };
+    match (from, to) {
+        // Nulls are nulls whatever the type
+        (DataType::Null, _) => CastKind::Implicit(0),
+        (_, DataType::Null) => CastKind::Forbidden,
+        _ if from == to => CastKind::Implicit(0),
+        // Only the precision and scale change, values that don't fit are left to the
+        // session's decimal_overflow
+        (DataType::Decimal(..), DataType::Decimal(..)) => CastKind::Implicit(0),
+        (DataType::Integer, DataType::BigInt)
+        | (DataType::BigInt, DataType::Decimal(..))
+        | (DataType::Enum(_), DataType::Text)
+        | (DataType::Text, DataType::JsonPath) => CastKind::Implicit(1),
+        (DataType::Integer, DataType::Decimal(..)) => CastKind::Implicit(2),
+        // Anything can be written out as text and parsed back from it
+        (_, DataType::Text) | (DataType::Text, _) => CastKind::Explicit,
+        (DataType::Json, DataType::Boolean) => CastKind::Explicit,
+        (DataType::Json, _) | (DataType::Boolean, _) if numeric(to) => CastKind::Explicit,
+        (_, DataType::Json) if numeric(from) || from == DataType::Boolean => CastKind::Explicit,
+        (_, _) if numeric(from) && numeric(to) => CastKind::Explicit,
+        (DataType::Date, DataType::Timestamp)
+        | (DataType::Timestamp, DataType::Date)
+        | (DataType::Enum(_), DataType::Enum(_)) => CastKind::Explicit,
+        _ => CastKind::Forbidden,
+    }
+}
+
+/// The type values of both types can be implicitly cast to, used to line up the columns of
+/// union alls. Decimals are widened to fit the digits of both sides.
+pub fn common_type(a: DataType, b: DataType) -> Option<DataType> {
+    if a == b {
+        return Some(a);
+    }
+    if let (DataType::Decimal(..), _) | (_, DataType::Decimal(..)) = (a, b) {
+        if let (Some((p1, s1)), Some((p2, s2))) = (decimal_digits(a), decimal_digits(b)) {
+            let scale = s1.max(s2);
+            let precision = (p1 - s1).max(p2 - s2) + scale;
+            return Some(DataType::Decimal(
+                precision.min(DECIMAL_MAX_PRECISION),
+                scale,
+            ));
+        }
+    }
+    match (cast_kind(a, b), cast_kind(b, a)) {
+        (CastKind::Implicit(_), _) => Some(b),
+        (_, CastKind::Implicit(_)) => Some(a),
+        _ => None,
+    }
+}
+
+/// The precision and scale of the decimals the type can be implicitly cast to without losing
+/// any digits
+fn decimal_digits(datatype: DataType) -> Option<(u8, u8)> {
+    match datatype {
+        DataType::Integer => Some((10, 0)),
+        DataType::BigInt => Some((19, 0)),
+        DataType::Decimal(p, s) => Some((p, s)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cast_kind() {
+        assert_eq!(
+            cast_kind(DataType::Null, DataType::Date),
+            CastKind::Implicit(0)
+        );
+        assert_eq!(
+            cast_kind(DataType::Integer, DataType::Decimal(4, 2)),
+            CastKind::Implicit(2)
+        );
+        assert_eq!(
+            cast_kind(DataType::BigInt, DataType::Integer),
+            CastKind::Explicit
+        );
+        assert_eq!(
+            cast_kind(DataType::Json, DataType::Boolean),
+            CastKind::Explicit
+        );
+        assert_eq!(
+            cast_kind(DataType::Date, DataType::Text),
+            CastKind::Explicit
+        );
+        assert_eq!(
+            cast_kind(DataType::Boolean, DataType::Date),
+            CastKind::Forbidden
+        );
+        assert_eq!(
+            cast_kind(DataType::Date, DataType::Integer),
+            CastKind::Forbidden
+        );
+        assert_eq!(
+            cast_kind(DataType::Integer, DataType::Null),
+            CastKind::Forbidden
+        );
+
+        // Every type can be cast to itself and to text
+        for datatype in ALL_TYPES.iter() {
+            assert_eq!(cast_kind(*datatype, *datatype), CastKind::Implicit(0));
+            assert_ne!(cast_kind(*datatype, DataType::Text), CastKind::Forbidden);
+        }
+        assert_eq!(cast_matrix().count(), ALL_TYPES.len() * ALL_TYPES.len());
+    }
+
+    #[test]
+    fn test_common_type() {
+        assert_eq!(
+            common_type(DataType::Null, DataType::Text),
+            Some(DataType::Text)
+        );
+        assert_eq!(
+            common_type(DataType::BigInt, DataType::Integer),
+            Some(DataType::BigInt)
+        );
+        assert_eq!(
+            common_type(DataType::Integer, DataType::Decimal(4, 2)),
+            Some(DataType::Decimal(12, 2))
+        );
+        assert_eq!(
+            common_type(DataType::Decimal(10, 4), DataType::Decimal(8, 1)),
+            Some(DataType::Decimal(11, 4))
+        );
+        assert_eq!(
+            common_type(DataType::Text, DataType::Enum(0)),
+            Some(DataType::Text)
+        );
+        assert_eq!(common_type(DataType::Text, DataType::Integer), None);
+    }
+}
@@ -0,0 +1,98 @@
+use crate::LogicalTimestamp;
+use chrono::{NaiveDateTime, Utc};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Where "now" comes from when timestamping writes, picking read barriers and evaluating now().
+/// Normally the system time, tests can use a virtual clock instead that only moves when it's
+/// told to so MVCC behaviour can be tested without racing the real clock. Clones share the
+/// same time.
+#[derive(Debug, Clone, Default)]
+pub struct Clock {
+    // The current virtual time in ms since 1970, None for the system clock
+    virtual_ms: Option<Arc<AtomicU64>>,
+}
+
+impl Clock {
+    /// A clock that follows the system time
+    pub fn system() -> Self {
+        Clock { virtual_ms: None }
+    }
+
+    /// A clock that starts at ms since 1970 and only moves when advanced or set
+    pub fn new_virtual(ms: u64) -> Self {
+        Clock {
+            virtual_ms: Some(Arc::new(AtomicU64::new(ms))),
+        }
+    }
+
+    pub fn is_virtual(&self) -> bool {
+        self.virtual_ms.is_some()
+    }
+
+    pub fn now(&self) -> LogicalTimestamp {
+        match &self.virtual_ms {
+            Some(ms) => LogicalTimestamp::new(ms.load(Ordering::SeqCst)),
+            None => LogicalTimestamp::now(),
+        }
+    }
+
+    /// The current time as a utc date time, for the system clock this keeps the system time's
+    /// full precision.
+    pub fn now_utc(&self) -> NaiveDateTime {
+        match &self.virtual_ms {
+            Some(ms) => {
+                let ms = ms.load(Ordering::SeqCst);
+                NaiveDateTime::from_timestamp((ms / 1000) as i64, (ms % 1000) as u32 * 1_000_000)
+            }
+            None => Utc::now().naive_utc(),
+        }
+    }
+
+    /// Moves a virtual clock forward by ms, panics for the system clock.
+    pub fn advance(&self, ms: u64) {
+        self.virtual_ms().fetch_add(ms, Ordering::SeqCst);
+    }
+
+    /// Sets a virtual clock's time, it can be moved backwards. Panics for the system clock.
+    pub fn set(&self, ms: u64) {
+        self.virtual_ms().store(ms, Ordering::SeqCst);
+    }
+
+    fn virtual_ms(&self) -> &AtomicU64 {
+        self.virtual_ms
+            .as_ref()
+            .expect("Only virtual clocks can be moved")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_virtual_clock() {
+        let clock = Clock::new_virtual(1_000);
+        let shared = clock.clone();
+        assert!(clock.is_virtual());
+        assert_eq!(clock.now(), LogicalTimestamp::new(1_000));
+
+        shared.advance(1_500);
+        assert_eq!(clock.now(), LogicalTimestamp::new(2_500));
+        assert_eq!(
+            clock.now_utc(),
+            NaiveDateTime::from_timestamp(2, 500_000_000)
+        );
+
+        clock.set(10);
+        assert_eq!(shared.now(), LogicalTimestamp::new(10));
+    }
+
+    #[test]
+    fn test_system_clock() {
+        let clock = Clock::system();
+        assert!(!clock.is_virtual());
+        let before = LogicalTimestamp::now();
+        assert!(clock.now() >= before);
+    }
+}
@@ -1,6 +1,8 @@
 use regex::Regex;
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt::{Display, Formatter};
+use std::sync::RwLock;
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub enum DataType {
@@ -17,6 +19,9 @@ pub enum DataType {
     Date,
     Timestamp,
     JsonPath,
+    // An id for the set of labels the enum can take, see DataType::new_enum.
+    // Values are stored as the index of their label.
+    Enum(u32),
 }
 
 pub const DECIMAL_MAX_PRECISION: u8 = 28;
@@ -36,8 +41,70 @@ impl DataType {
             DataType::Date => "to_date",
             DataType::Timestamp => "to_timestamp",
             DataType::JsonPath => "to_jsonpath",
+            DataType::Enum(_) => "to_enum",
         }
     }
+
+    /// The variant of the cast function that always gives null for values it can't convert,
+    /// even with strict_casts on
+    pub fn try_cast_function(&self) -> &'static str {
+        match self {
+            DataType::Null => panic!("Attempted cast to null"),
+            DataType::Boolean => "try_to_bool",
+            DataType::Integer => "try_to_int",
+            DataType::BigInt => "try_to_bigint",
+            DataType::Decimal(..) => "try_to_decimal",
+            DataType::Text => "try_to_text",
+            DataType::ByteA => "try_to_bytes",
+            DataType::Json => "try_to_json",
+            DataType::Date => "try_to_date",
+            DataType::Timestamp => "try_to_timestamp",
+            DataType::JsonPath => "try_to_jsonpath",
+            DataType::Enum(_) => "try_to_enum",
+        }
+    }
+
+    /// Returns the enum type for the labels. The label sets are interned for the life of the
+    /// process so the type can stay Copy, the ids aren't stable across restarts so it's the
+    /// labels that get persisted (as part of the type's string form).
+    pub fn new_enum(labels: &[String]) -> DataType {
+        if let Some(id) = ENUM_LABELS.read().unwrap().ids.get(labels) {
+            return DataType::Enum(*id);
+        }
+        let mut enum_labels = ENUM_LABELS.write().unwrap();
+        if let Some(id) = enum_labels.ids.get(labels) {
+            return DataType::Enum(*id);
+        }
+        let id = enum_labels.labels.len() as u32;
+        let leaked: &'static [String] = Box::leak(labels.to_vec().into_boxed_slice());
+        enum_labels.labels.push(leaked);
+        enum_labels.ids.insert(labels.to_vec(), id);
+        DataType::Enum(id)
+    }
+
+    /// The labels of an enum type, in the order of their codes
+    pub fn enum_labels(&self) -> Option<&'static [String]> {
+        if let DataType::Enum(id) = self {
+            ENUM_LABELS
+                .read()
+                .unwrap()
+                .labels
+                .get(*id as usize)
+                .copied()
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Default)]
+struct EnumLabels {
+    labels: Vec<&'static [String]>,
+    ids: HashMap<Vec<String>, u32>,
+}
+
+lazy_static! {
+    static ref ENUM_LABELS: RwLock<EnumLabels> = RwLock::default();
 }
 
 impl Display for DataType {
@@ -54,6 +121,18 @@ impl Display for DataType {
             DataType::Date => f.write_str("DATE"),
             DataType::Timestamp => f.write_str("TIMESTAMP"),
             DataType::JsonPath => f.write_str("JSONPATH"),
+            // Written out as sql so casts to the type can be parsed back in
+            DataType::Enum(_) => {
+                f.write_str("ENUM(")?;
+                for (idx, label) in self.enum_labels().unwrap_or_default().iter().enumerate() {
+                    if idx != 0 {
+                        f.write_str(",")?;
+                    }
+                    let escaped = label.replace('\\', "\\\\").replace('\'', "\\'");
+                    f.write_fmt(format_args!("'{}'", escaped))?;
+                }
+                f.write_str(")")
+            }
         }
     }
 }
@@ -79,6 +158,10 @@ impl TryFrom<&str> for DataType {
             "JSONPATH" => Ok(DataType::JsonPath),
             "DATE" => Ok(DataType::Date),
             "TIMESTAMP" => Ok(DataType::Timestamp),
+            _ if value.starts_with("ENUM(") && value.ends_with(')') => {
+                parse_enum_labels(&value[5..(value.len() - 1)])
+                    .map(|labels| DataType::new_enum(&labels))
+            }
             _ => DECIMAL_RE
                 .captures(value)
                 .map(|d_match| {
@@ -91,6 +174,32 @@ impl TryFrom<&str> for DataType {
     }
 }
 
+/// Parses the 'a','b' label list written out by display, quotes and backslashes in the labels
+/// are escaped with a backslash
+fn parse_enum_labels(list: &str) -> Result<Vec<String>, ()> {
+    let mut labels = vec![];
+    let mut chars = list.chars();
+    loop {
+        if chars.next() != Some('\'') {
+            return Err(());
+        }
+        let mut label = String::new();
+        loop {
+            match chars.next().ok_or(())? {
+                '\'' => break,
+                '\\' => label.push(chars.next().ok_or(())?),
+                c => label.push(c),
+            }
+        }
+        labels.push(label);
+        match chars.next() {
+            None => return Ok(labels),
+            Some(',') => {}
+            Some(_) => return Err(()),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -109,4 +218,19 @@ mod tests {
             Ok(DataType::Decimal(1, 2))
         );
     }
+
+    #[test]
+    fn test_enum() {
+        let labels = vec!["new".to_string(), "it's".to_string()];
+        let datatype = DataType::new_enum(&labels);
+        assert_eq!(datatype, DataType::new_enum(&labels));
+        assert_ne!(datatype, DataType::new_enum(&labels[..1]));
+        assert_eq!(datatype.enum_labels(), Some(labels.as_slice()));
+        assert_eq!(DataType::Text.enum_labels(), None);
+
+        assert_eq!(datatype.to_string(), r"ENUM('new','it\'s')");
+        assert_eq!(DataType::try_from(r"ENUM('new','it\'s')"), Ok(datatype));
+        assert_eq!(DataType::try_from("ENUM('new',)"), Err(()));
+        assert_eq!(DataType::try_from("ENUM()"), Err(()));
+    }
 }
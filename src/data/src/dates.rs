@@ -0,0 +1,213 @@
+use chrono::naive::{MAX_DATE, MIN_DATE};
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use std::str::FromStr;
+
+// Dates are stored as (year << 9) + ordinal day so they sort by their integer value. Years follow
+// the proleptic gregorian calendar, year 0 is 1 BC, -1 is 2 BC and so on, the shift being
+// arithmetic keeps them in order too. Timestamps are stored as milliseconds since the unix epoch.
+// The range of both is chrono's, roughly 262,000 years either side of year 0.
+
+/// Encodes the date into the integer it's stored as
+pub fn encode_date(date: NaiveDate) -> i32 {
+    (date.year() << 9) + (date.ordinal() as i32)
+}
+
+/// Decodes a date encoded by encode_date, returns None if the integer isn't a valid date
+pub fn decode_date(encoded: i32) -> Option<NaiveDate> {
+    NaiveDate::from_yo_opt(encoded >> 9, (encoded & 511) as u32)
+}
+
+/// Decodes a timestamp stored as milliseconds, returns None if it's out of range
+pub fn decode_timestamp(millis: i64) -> Option<NaiveDateTime> {
+    let seconds = millis.div_euclid(1000);
+    let millis = millis.rem_euclid(1000);
+    NaiveDateTime::from_timestamp_opt(seconds, millis as u32 * 1_000_000)
+}
+
+/// Parses a date written as YYYY-MM-DD, years before 1 AD are written with a minus sign and
+/// years after 9999 with a plus sign, ie -0044-03-15. The error says what's wrong with it.
+pub fn parse_date(s: &str) -> Result<NaiveDate, String> {
+    NaiveDate::from_str(s).map_err(|_| explain_date(s))
+}
+
+/// Parses a timestamp written as YYYY-MM-DDTHH:MM:SS with optional fractional seconds, a space
+/// can be used in place of the T. The error says what's wrong with it.
+pub fn parse_timestamp(s: &str) -> Result<NaiveDateTime, String> {
+    NaiveDateTime::from_str(s)
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f"))
+        .map_err(|_| {
+            let date = s.split(|c| c == 'T' || c == ' ').next().unwrap_or(s);
+            if NaiveDate::from_str(date).is_ok() {
+                "expected a time like HH:MM:SS".to_string()
+            } else {
+                explain_date(date)
+            }
+        })
+}
+
+/// Works out why the text isn't a valid date
+fn explain_date(s: &str) -> String {
+    let malformed = || "expected a date like YYYY-MM-DD".to_string();
+    let (sign, unsigned) = match s.as_bytes().first() {
+        Some(b'-') => (-1, &s[1..]),
+        Some(b'+') => (1, &s[1..]),
+        _ => (1, s),
+    };
+    let mut parts = unsigned.splitn(3, '-').map(|part| {
+        if !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()) {
+            part.parse::<i64>().ok()
+        } else {
+            None
+        }
+    });
+    let (year, month, day) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(Some(year)), Some(Some(month)), Some(Some(day))) => (sign * year, month, day),
+        _ => return malformed(),
+    };
+
+    if year < MIN_DATE.year() as i64 || year > MAX_DATE.year() as i64 {
+        format!(
+            "year {} is outside of {} to {}",
+            year,
+            MIN_DATE.year(),
+            MAX_DATE.year()
+        )
+    } else if month < 1 || month > 12 {
+        format!("month {} doesn't exist", month)
+    } else if day < 1
+        || day > 31
+        || NaiveDate::from_ymd_opt(year as i32, month as u32, day as u32).is_none()
+    {
+        format!("day {} doesn't exist in {:04}-{:02}", day, year, month)
+    } else {
+        malformed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Datum, SortOrder};
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(
+            parse_date("2020-02-29"),
+            Ok(NaiveDate::from_ymd(2020, 2, 29))
+        );
+        assert_eq!(
+            parse_date("-0044-03-15"),
+            Ok(NaiveDate::from_ymd(-44, 3, 15))
+        );
+        assert_eq!(
+            parse_date("+12020-01-01"),
+            Ok(NaiveDate::from_ymd(12020, 1, 1))
+        );
+        assert_eq!(
+            parse_date("2021-02-29"),
+            Err("day 29 doesn't exist in 2021-02".to_string())
+        );
+        assert_eq!(
+            parse_date("2021-04-31"),
+            Err("day 31 doesn't exist in 2021-04".to_string())
+        );
+        assert_eq!(
+            parse_date("2010-13-45"),
+            Err("month 13 doesn't exist".to_string())
+        );
+        assert_eq!(
+            parse_date("0000-00-00"),
+            Err("month 0 doesn't exist".to_string())
+        );
+        assert_eq!(
+            parse_date("999999-01-01"),
+            Err("year 999999 is outside of -262144 to 262143".to_string())
+        );
+        assert_eq!(
+            parse_date("2010-1s-01"),
+            Err("expected a date like YYYY-MM-DD".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(
+            parse_timestamp("-0044-03-15 12:00:00.5"),
+            Ok(NaiveDate::from_ymd(-44, 3, 15).and_hms_milli(12, 0, 0, 500))
+        );
+        assert_eq!(
+            parse_timestamp("2021-02-30T10:00:00"),
+            Err("day 30 doesn't exist in 2021-02".to_string())
+        );
+        assert_eq!(
+            parse_timestamp("2021-02-03 25:00:00"),
+            Err("expected a time like HH:MM:SS".to_string())
+        );
+    }
+
+    #[test]
+    fn test_date_display() {
+        let display = |date: NaiveDate| date.to_string();
+        assert_eq!(display(NaiveDate::from_ymd(-44, 3, 15)), "-0044-03-15");
+        assert_eq!(display(NaiveDate::from_ymd(0, 1, 1)), "0000-01-01");
+        assert_eq!(display(NaiveDate::from_ymd(12020, 1, 1)), "+12020-01-01");
+    }
+
+    #[test]
+    fn test_extremes_round_trip() {
+        let dates = [
+            MIN_DATE,
+            NaiveDate::from_ymd(-1, 12, 31),
+            NaiveDate::from_ymd(0, 1, 1),
+            NaiveDate::from_ymd(0, 12, 31),
+            NaiveDate::from_ymd(1, 1, 1),
+            NaiveDate::from_ymd(1970, 1, 1),
+            MAX_DATE,
+        ];
+        for sort_order in &[SortOrder::Asc, SortOrder::Desc] {
+            let mut encoded: Vec<_> = dates
+                .iter()
+                .map(|date| {
+                    let mut buffer = vec![];
+                    Datum::from(*date).as_sortable_bytes(*sort_order, &mut buffer);
+                    buffer
+                })
+                .collect();
+            let in_order = encoded.clone();
+            encoded.sort();
+            if sort_order.is_desc() {
+                encoded.reverse();
+            }
+            assert_eq!(encoded, in_order);
+
+            for (date, buffer) in dates.iter().zip(&encoded) {
+                let mut datum = Datum::Null;
+                datum.from_sortable_bytes(buffer);
+                assert_eq!(datum.as_date(), *date);
+            }
+        }
+
+        let timestamps = [
+            MIN_DATE.and_hms(0, 0, 0),
+            NaiveDate::from_ymd(-44, 3, 15).and_hms_milli(12, 0, 0, 1),
+            NaiveDate::from_ymd(1969, 12, 31).and_hms_milli(23, 59, 59, 999),
+            NaiveDate::from_ymd(1970, 1, 1).and_hms(0, 0, 0),
+            MAX_DATE.and_hms_milli(23, 59, 59, 999),
+        ];
+        let mut previous = None;
+        for timestamp in &timestamps {
+            let mut buffer = vec![];
+            Datum::from(*timestamp).as_sortable_bytes(SortOrder::Asc, &mut buffer);
+            assert!(previous < Some(buffer.clone()));
+            let mut datum = Datum::Null;
+            datum.from_sortable_bytes(&buffer);
+            assert_eq!(datum.as_timestamp(), *timestamp);
+            previous = Some(buffer);
+        }
+
+        assert_eq!(decode_date(encode_date(MAX_DATE) + 1), None);
+        assert_eq!(decode_date(1 << 9), None);
+        assert_eq!(decode_timestamp(i64::MAX), None);
+        assert_eq!(decode_timestamp(i64::MIN), None);
+    }
+}
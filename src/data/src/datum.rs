@@ -1,7 +1,7 @@
 use crate::json::{Json, OwnedJson};
 use crate::jsonpath_utils::JsonPathExpression;
-use crate::DataType;
-use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use crate::{decode_date, decode_timestamp, encode_date, DataType};
+use chrono::{NaiveDate, NaiveDateTime};
 use rust_decimal::Decimal;
 use std::cmp::Ordering;
 use std::fmt::{Debug, Display, Formatter};
@@ -102,6 +102,28 @@ impl<'a> Datum<'a> {
         matches!(self, Datum::Null)
     }
 
+    /// Returns true if this is the kind of value a column of the datatype holds, nulls fit
+    /// any datatype
+    pub fn fits(&self, datatype: DataType) -> bool {
+        if self.is_null() {
+            return true;
+        }
+        match datatype {
+            DataType::Null => false,
+            DataType::Boolean => matches!(self, Datum::Boolean(_)),
+            DataType::Integer | DataType::Date | DataType::Enum(_) => {
+                matches!(self, Datum::Integer(_))
+            }
+            DataType::BigInt | DataType::Timestamp => matches!(self, Datum::BigInt(_)),
+            DataType::Decimal(..) => matches!(self, Datum::Decimal(_)),
+            DataType::Text | DataType::ByteA | DataType::Json => matches!(
+                self,
+                Datum::ByteAOwned(_) | Datum::ByteAInline(..) | Datum::ByteARef(_)
+            ),
+            DataType::JsonPath => matches!(self, Datum::Jsonpath(_) | Datum::JsonpathRef(_)),
+        }
+    }
+
     /// Returns true if this value is equal to another.
     /// According to sql rules, null != null, this is the behaviour if null_safe = false,
     /// if null_safe is set to true then null == null
@@ -228,7 +250,7 @@ impl From<Decimal> for Datum<'static> {
 
 impl From<NaiveDate> for Datum<'static> {
     fn from(d: NaiveDate) -> Self {
-        Datum::Integer((d.year() << 9) + (d.ordinal() as i32))
+        Datum::Integer(encode_date(d))
     }
 }
 
@@ -324,11 +346,26 @@ impl Display for TypedDatum<'_> {
             }
             Datum::Boolean(b) => f.write_str(if *b { "TRUE" } else { "FALSE" }),
             Datum::Integer(i) => match self.datatype {
-                DataType::Date => Display::fmt(&self.datum.as_date(), f),
+                DataType::Date => match self.datum.as_maybe_date() {
+                    Some(date) => Display::fmt(&date, f),
+                    None => Display::fmt(i, f),
+                },
+                // Enums are stored as the index of their label
+                DataType::Enum(_) => {
+                    let labels = self.datatype.enum_labels().unwrap();
+                    match labels.get(*i as usize) {
+                        Some(label) if f.alternate() => Debug::fmt(label.as_str(), f),
+                        Some(label) => f.write_str(label),
+                        None => Display::fmt(i, f),
+                    }
+                }
                 _ => Display::fmt(i, f),
             },
             Datum::BigInt(i) => match self.datatype {
-                DataType::Timestamp => Display::fmt(&self.datum.as_timestamp(), f),
+                DataType::Timestamp => match self.datum.as_maybe_timestamp() {
+                    Some(timestamp) => Display::fmt(&timestamp, f),
+                    None => Display::fmt(i, f),
+                },
                 _ => Display::fmt(i, f),
             },
             Datum::Decimal(d) => {
@@ -445,9 +482,10 @@ impl<'a> Datum<'a> {
         }
     }
 
+    /// Returns None for integers that don't encode a valid date as well as for non integers
     pub fn as_maybe_date(&self) -> Option<NaiveDate> {
         if let Datum::Integer(i) = self {
-            Some(NaiveDate::from_yo(i >> 9, (i & 511) as u32))
+            decode_date(*i)
         } else {
             None
         }
@@ -458,14 +496,10 @@ impl<'a> Datum<'a> {
         self.as_maybe_date().unwrap()
     }
 
+    /// Returns None for timestamps out of range as well as for non bigints
     pub fn as_maybe_timestamp(&self) -> Option<NaiveDateTime> {
         if let Datum::BigInt(i) = self {
-            let seconds = i.div_euclid(1000);
-            let millis = i.rem_euclid(1000);
-            Some(NaiveDateTime::from_timestamp(
-                seconds,
-                millis as u32 * 1000000,
-            ))
+            decode_timestamp(*i)
         } else {
             None
         }
@@ -536,6 +570,17 @@ mod tests {
         assert_eq!(24, size_of::<Datum>());
     }
 
+    #[test]
+    fn test_datum_fits() {
+        assert!(Datum::Null.fits(DataType::Integer));
+        assert!(Datum::from(1).fits(DataType::Integer));
+        assert!(Datum::from(1).fits(DataType::Date));
+        assert!(!Datum::from(1).fits(DataType::BigInt));
+        assert!(Datum::from("a").fits(DataType::Text));
+        assert!(!Datum::from("a").fits(DataType::Decimal(10, 2)));
+        assert!(!Datum::from(true).fits(DataType::Null));
+    }
+
     #[test]
     fn test_datum_ref_clone() {
         assert_eq!(Datum::from(1).ref_clone(), Datum::Integer(1));
@@ -770,4 +815,14 @@ mod tests {
             r#"{"one":1,"two":2}"#
         );
     }
+
+    #[test]
+    fn test_datum_display_enum() {
+        let datatype = DataType::new_enum(&["new".to_string(), "paid".to_string()]);
+        assert_eq!(format!("{}", Datum::from(1).typed_with(datatype)), "paid");
+        assert_eq!(
+            format!("{:#}", Datum::from(0).typed_with(datatype)),
+            "\"new\""
+        );
+    }
 }
@@ -0,0 +1,392 @@
+use crate::encoding_core::SortableEncoding;
+use crate::jsonpath_utils::JsonPathExpression;
+use crate::{Datum, SortOrder};
+use std::fmt::{Display, Formatter};
+
+// TUPLE ENCODING
+// A tuple is written as the number of datums (as a sortable u64, always ascending) followed by
+// each datum's sortable encoding. Each datum starts with a byte saying what it is and which way
+// it's sorted so tuples can be read back without knowing their columns' types or sort orders.
+// This is how storage lays out the primary key part of its keys and the rest of the tuple in
+// its values, so tools can use read_tuple to make sense of rocksdb files without any context.
+// encode_tuple/decode_tuple wrap the same layout with a leading format version for tuples
+// stored or passed around outside of the tables.
+
+/// The version byte encode_tuple writes before the tuple, to be bumped if the layout changes.
+pub const TUPLE_ENCODING_VERSION: u8 = 1;
+
+/// Why bytes couldn't be read back as a tuple
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum DecodeError {
+    UnsupportedVersion(u8),
+    UnknownDatum(u8),
+    Truncated,
+    InvalidJsonpath,
+    WrongDatumCount(usize, usize),
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::UnsupportedVersion(version) => f.write_fmt(format_args!(
+                "Unsupported tuple encoding version {}",
+                version
+            )),
+            DecodeError::UnknownDatum(tag) => {
+                f.write_fmt(format_args!("Unknown datum encoding {}", tag))
+            }
+            DecodeError::Truncated => f.write_str("Tuple encoding is truncated"),
+            DecodeError::InvalidJsonpath => f.write_str("Invalid jsonpath in tuple encoding"),
+            DecodeError::WrongDatumCount(expected, actual) => f.write_fmt(format_args!(
+                "Expected {} datums in tuple encoding but found {}",
+                expected, actual
+            )),
+        }
+    }
+}
+
+/// Writes the tuple prefixed with TUPLE_ENCODING_VERSION
+pub fn encode_tuple(tuple: &[Datum], buffer: &mut Vec<u8>) {
+    buffer.push(TUPLE_ENCODING_VERSION);
+    write_tuple(tuple, buffer);
+}
+
+/// Reads a tuple written by encode_tuple, returning it along with the rest of the buffer
+pub fn decode_tuple(buffer: &[u8]) -> Result<(Vec<Datum<'static>>, &[u8]), DecodeError> {
+    match buffer.first() {
+        Some(&TUPLE_ENCODING_VERSION) => read_tuple(&buffer[1..]),
+        Some(version) => Err(DecodeError::UnsupportedVersion(*version)),
+        None => Err(DecodeError::Truncated),
+    }
+}
+
+/// Writes the number of datums followed by the datums sorted ascending, without a version.
+pub fn write_tuple(tuple: &[Datum], buffer: &mut Vec<u8>) {
+    (tuple.len() as u64).write_sortable_bytes(SortOrder::Asc, buffer);
+    for datum in tuple {
+        datum.as_sortable_bytes(SortOrder::Asc, buffer);
+    }
+}
+
+/// Reads a tuple written by write_tuple or laid out the same way with datums sorted either
+/// way. Unlike Datum::from_sortable_bytes this checks the bytes are all there rather than
+/// panicking, so it's safe to use on data that might be corrupt.
+pub fn read_tuple(buffer: &[u8]) -> Result<(Vec<Datum<'static>>, &[u8]), DecodeError> {
+    varint_unsigned_len(buffer)?;
+    let mut count = 0_u64;
+    let mut rem = count.read_sortable_bytes(SortOrder::Asc, buffer);
+
+    let mut tuple = vec![];
+    for _ in 0..count {
+        let (datum, datum_rem) = read_datum(rem)?;
+        tuple.push(datum);
+        rem = datum_rem;
+    }
+    Ok((tuple, rem))
+}
+
+/// The number of bytes in the null bitmap of a sparse tuple of count datums
+pub fn null_bitmap_len(count: usize) -> usize {
+    (count + 7) / 8
+}
+
+/// Writes the number of datums, then a bitmap of which of them are null (bit idx % 8 of byte
+/// idx / 8) followed by only the datums that aren't null. Each datum's sorted according to
+/// sort_orders, ascending once it runs out. Tuples with lots of nulls pay a bit per null rather
+/// than a byte, but they no longer sort by their datums.
+pub fn write_sparse_tuple(tuple: &[Datum], sort_orders: &[SortOrder], buffer: &mut Vec<u8>) {
+    (tuple.len() as u64).write_sortable_bytes(SortOrder::Asc, buffer);
+    let bitmap_start = buffer.len();
+    buffer.resize(bitmap_start + null_bitmap_len(tuple.len()), 0);
+    for (idx, datum) in tuple.iter().enumerate() {
+        if datum.is_null() {
+            buffer[bitmap_start + idx / 8] |= 1 << (idx % 8);
+        } else {
+            let sort_order = sort_orders.get(idx).copied().unwrap_or(SortOrder::Asc);
+            datum.as_sortable_bytes(sort_order, buffer);
+        }
+    }
+}
+
+/// Reads a tuple written by write_sparse_tuple, checking the bytes are all there.
+pub fn read_sparse_tuple(buffer: &[u8]) -> Result<(Vec<Datum<'static>>, &[u8]), DecodeError> {
+    varint_unsigned_len(buffer)?;
+    let mut count = 0_u64;
+    let rem = count.read_sortable_bytes(SortOrder::Asc, buffer);
+    let bitmap_len = check_len(rem, null_bitmap_len(count as usize))?;
+    let (bitmap, mut rem) = rem.split_at(bitmap_len);
+
+    let mut tuple = vec![];
+    for idx in 0..(count as usize) {
+        if bitmap[idx / 8] & (1 << (idx % 8)) != 0 {
+            tuple.push(Datum::Null);
+        } else {
+            let (datum, datum_rem) = read_datum(rem)?;
+            tuple.push(datum);
+            rem = datum_rem;
+        }
+    }
+    Ok((tuple, rem))
+}
+
+/// Reads a single datum written by Datum::as_sortable_bytes, checking it's all there first.
+pub fn read_datum(buffer: &[u8]) -> Result<(Datum<'static>, &[u8]), DecodeError> {
+    let len = datum_len(buffer)?;
+    let tag = buffer[0];
+    if tag == 8 || tag == !8 {
+        let sort_order = if tag < 127 {
+            SortOrder::Asc
+        } else {
+            SortOrder::Desc
+        };
+        let mut bytes = vec![];
+        bytes.read_sortable_bytes(sort_order, &buffer[1..len]);
+        let valid = std::str::from_utf8(&bytes)
+            .ok()
+            .and_then(JsonPathExpression::parse)
+            .is_some();
+        if !valid {
+            return Err(DecodeError::InvalidJsonpath);
+        }
+    }
+    let mut datum = Datum::Null;
+    datum.from_sortable_bytes(&buffer[..len]);
+    Ok((datum, &buffer[len..]))
+}
+
+/// The number of bytes the datum at the start of the buffer takes up
+fn datum_len(buffer: &[u8]) -> Result<usize, DecodeError> {
+    let tag = *buffer.first().ok_or(DecodeError::Truncated)?;
+    let rem = &buffer[1..];
+    let body_len = match tag {
+        1 | 254 | 2 | 253 | 3 | 252 => 0,
+        4 | 251 | 5 | 250 => varint_signed_len(rem)?,
+        6 | 249 => decimal_len(rem)?,
+        7 | 248 | 8 | 247 => bytes_len(rem, tag > 127)?,
+        _ => return Err(DecodeError::UnknownDatum(tag)),
+    };
+    Ok(1 + body_len)
+}
+
+/// Lengths of the encodings written by encoding_core
+fn varint_unsigned_len(buffer: &[u8]) -> Result<usize, DecodeError> {
+    let len = match buffer.first().ok_or(DecodeError::Truncated)? {
+        253 => 3,
+        254 => 5,
+        255 => 9,
+        _ => 1,
+    };
+    check_len(buffer, len)
+}
+
+fn varint_signed_len(buffer: &[u8]) -> Result<usize, DecodeError> {
+    let len = match buffer.first().ok_or(DecodeError::Truncated)? {
+        0 | 255 => 9,
+        1 | 254 => 5,
+        2 | 253 => 3,
+        3 | 252 => 2,
+        _ => 1,
+    };
+    check_len(buffer, len)
+}
+
+fn decimal_len(buffer: &[u8]) -> Result<usize, DecodeError> {
+    // Negative decimals have their bytes inverted
+    let last = match buffer.first().ok_or(DecodeError::Truncated)? {
+        127 => return Ok(1),
+        128..=254 => 0,
+        0..=126 => !0,
+        byte => return Err(DecodeError::UnknownDatum(*byte)),
+    };
+    // Up to three 4 byte chunks of the mantissa, the first two followed by whether there's
+    // another
+    let mut len = 1;
+    for _ in 0..2 {
+        len = check_len(buffer, len + 5)?;
+        if buffer[len - 1] == last {
+            return Ok(len);
+        }
+    }
+    check_len(buffer, len + 4)
+}
+
+fn bytes_len(buffer: &[u8], desc: bool) -> Result<usize, DecodeError> {
+    // Chunks of 8 bytes, each followed by a byte saying whether there's another
+    let more = if desc { !9 } else { 9 };
+    let mut len = 0;
+    loop {
+        check_len(buffer, len + 9)?;
+        len += 9;
+        if buffer[len - 1] != more {
+            return Ok(len);
+        }
+    }
+}
+
+fn check_len(buffer: &[u8], len: usize) -> Result<usize, DecodeError> {
+    if buffer.len() < len {
+        Err(DecodeError::Truncated)
+    } else {
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    /// A splitmix64 sequence so the generated tuples are the same every run
+    struct Generator(u64);
+
+    impl Generator {
+        fn next(&mut self) -> u64 {
+            self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = self.0;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        }
+
+        fn datum(&mut self) -> Datum<'static> {
+            match self.next() % 8 {
+                0 => Datum::Null,
+                1 => Datum::from(self.next() & 1 == 0),
+                2 => Datum::Integer(self.next() as i32 >> (self.next() % 32)),
+                3 => Datum::BigInt(self.next() as i64 >> (self.next() % 64)),
+                4 => Datum::from(Decimal::new(
+                    self.next() as i64 >> (self.next() % 64),
+                    (self.next() % 28) as u32,
+                )),
+                5 => {
+                    let len = (self.next() % 40) as usize;
+                    let bytes: Vec<_> = (0..len).map(|_| self.next() as u8).collect();
+                    Datum::from(bytes)
+                }
+                6 => Datum::Jsonpath(Box::from(JsonPathExpression::parse("$.a[1]").unwrap())),
+                _ => Datum::from(format!("text {}", self.next())),
+            }
+        }
+
+        fn tuple(&mut self) -> Vec<Datum<'static>> {
+            let len = (self.next() % 6) as usize;
+            (0..len).map(|_| self.datum()).collect()
+        }
+    }
+
+    /// Datums are compared by their encodings as some like jsonpaths can't be compared
+    fn assert_same(actual: &[Datum], expected: &[Datum]) {
+        let (mut actual_buffer, mut expected_buffer) = (vec![], vec![]);
+        write_tuple(actual, &mut actual_buffer);
+        write_tuple(expected, &mut expected_buffer);
+        assert_eq!(
+            actual_buffer, expected_buffer,
+            "{:?} != {:?}",
+            actual, expected
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut generator = Generator(42);
+        for _ in 0..1000 {
+            let tuple = generator.tuple();
+            let mut buffer = vec![];
+            encode_tuple(&tuple, &mut buffer);
+            buffer.push(99);
+
+            let (decoded, rem) = decode_tuple(&buffer).unwrap();
+            assert_same(&decoded, &tuple);
+            assert_eq!(rem, &[99]);
+        }
+    }
+
+    #[test]
+    fn test_read_sorted_desc() {
+        let mut generator = Generator(7);
+        for _ in 0..1000 {
+            let tuple = generator.tuple();
+            let mut buffer = vec![];
+            (tuple.len() as u64).write_sortable_bytes(SortOrder::Asc, &mut buffer);
+            for datum in &tuple {
+                datum.as_sortable_bytes(SortOrder::Desc, &mut buffer);
+            }
+
+            let (decoded, rem) = read_tuple(&buffer).unwrap();
+            assert_same(&decoded, &tuple);
+            assert!(rem.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_truncated() {
+        let mut generator = Generator(1);
+        for _ in 0..200 {
+            let tuple = generator.tuple();
+            let mut buffer = vec![];
+            encode_tuple(&tuple, &mut buffer);
+            for len in 0..buffer.len() {
+                assert_eq!(decode_tuple(&buffer[..len]), Err(DecodeError::Truncated));
+            }
+        }
+    }
+
+    #[test]
+    fn test_sparse_round_trip() {
+        let mut generator = Generator(3);
+        for _ in 0..1000 {
+            let tuple = generator.tuple();
+            let sort_orders: Vec<_> = tuple
+                .iter()
+                .map(|_| {
+                    if generator.next() & 1 == 0 {
+                        SortOrder::Asc
+                    } else {
+                        SortOrder::Desc
+                    }
+                })
+                .collect();
+            let mut buffer = vec![];
+            write_sparse_tuple(&tuple, &sort_orders, &mut buffer);
+            buffer.push(99);
+
+            let (decoded, rem) = read_sparse_tuple(&buffer).unwrap();
+            assert_same(&decoded, &tuple);
+            assert_eq!(rem, &[99]);
+            for len in 0..(buffer.len() - 1) {
+                assert_eq!(
+                    read_sparse_tuple(&buffer[..len]),
+                    Err(DecodeError::Truncated)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_sparse_size() {
+        let mut tuple = vec![Datum::Null; 40];
+        tuple[20] = Datum::from(1);
+        let (mut dense, mut sparse) = (vec![], vec![]);
+        write_tuple(&tuple, &mut dense);
+        write_sparse_tuple(&tuple, &[], &mut sparse);
+        assert_eq!(dense.len(), 1 + 39 + 2);
+        assert_eq!(sparse.len(), 1 + 5 + 2);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let mut buffer = vec![];
+        encode_tuple(&[Datum::from(1)], &mut buffer);
+        buffer[0] = 2;
+        assert_eq!(
+            decode_tuple(&buffer),
+            Err(DecodeError::UnsupportedVersion(2))
+        );
+
+        assert_eq!(read_tuple(&[1, 0]), Err(DecodeError::UnknownDatum(0)));
+
+        let mut buffer = vec![1, 8];
+        b"$$$".write_sortable_bytes(SortOrder::Asc, &mut buffer);
+        assert_eq!(read_tuple(&buffer), Err(DecodeError::InvalidJsonpath));
+    }
+}
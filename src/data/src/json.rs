@@ -79,6 +79,14 @@ impl OwnedJson {
     }
 }
 
+impl From<Json<'_>> for OwnedJson {
+    fn from(json: Json<'_>) -> Self {
+        OwnedJson {
+            bytes: json.bytes.to_vec(),
+        }
+    }
+}
+
 impl Debug for OwnedJson {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_str(&serde_json::to_string(&self.as_json()).unwrap())
@@ -482,6 +490,12 @@ impl ObjectJsonBuilder<'_> {
         self.inner.push_string(key);
         self.inner.push_object(f);
     }
+
+    /// Append existing json
+    pub fn push_json(&mut self, key: &str, j: Json) {
+        self.inner.push_string(key);
+        self.inner.push_json(j);
+    }
 }
 
 /// Impl part of JsonBuilder that knows how to work with all the types.
@@ -522,9 +536,10 @@ impl JsonBuilderInner {
         }
     }
 
-    /// Push a decimal onto the json tape
+    /// Push a decimal onto the json tape, trailing zeros are dropped so equal numbers always have
+    /// the same bytes, ie 1.50 and 1.5 compare, hash and group the same
     pub(crate) fn push_decimal(&mut self, d: Decimal) {
-        d.normalize();
+        let d = d.normalize();
         let unpacked = d.unpack();
         let mut m =
             unpacked.lo as i128 + ((unpacked.mid as i128) << 32) + ((unpacked.hi as i128) << 64);
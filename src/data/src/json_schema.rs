@@ -0,0 +1,371 @@
+use crate::json::{Json, JsonType};
+use rust_decimal::Decimal;
+
+/// Json Schema validation.
+/// Supports the structural parts of the spec, the keywords that can be checked with nothing but
+/// the document and the schema: type, enum, const, the number, string and array bounds,
+/// properties, required, additionalProperties, items and the allOf, anyOf, oneOf and not
+/// combinators. Keywords we don't know about are ignored as the spec asks, so schemas written
+/// for other validators still work, they just check less.
+
+/// Checks the document against the schema, returning where in the document it first fails and
+/// why, ie "$.items[1].qty: expected integer"
+pub fn validate(doc: Json, schema: Json) -> Result<(), String> {
+    validate_at(doc, schema, &mut "$".to_string())
+}
+
+/// Checks the schema is something we can validate against, json schemas are objects or the
+/// booleans true (anything goes) and false (nothing does)
+pub fn check_schema(schema: Json) -> Result<(), String> {
+    match schema.json_type() {
+        JsonType::Object | JsonType::Boolean => Ok(()),
+        _ => Err("A json schema has to be an object or a boolean".to_string()),
+    }
+}
+
+fn validate_at(doc: Json, schema: Json, path: &mut String) -> Result<(), String> {
+    if let Some(allowed) = schema.get_boolean() {
+        return if allowed {
+            Ok(())
+        } else {
+            fail(path, "not allowed by the schema")
+        };
+    }
+    let keywords = match schema.iter_object() {
+        Some(keywords) => keywords,
+        None => return Ok(()),
+    };
+
+    for (keyword, value) in keywords {
+        match keyword {
+            "type" => {
+                let matches = match value.iter_array() {
+                    Some(mut types) => types.any(|t| is_type(doc, t.get_string())),
+                    None => is_type(doc, value.get_string()),
+                };
+                if !matches {
+                    return fail(path, &format!("expected {}", type_names(value)));
+                }
+            }
+            "enum" => {
+                if let Some(mut values) = value.iter_array() {
+                    if !values.any(|v| json_equal(doc, v)) {
+                        return fail(path, "not one of the enum's values");
+                    }
+                }
+            }
+            "const" => {
+                if !json_equal(doc, value) {
+                    return fail(path, "not the const value");
+                }
+            }
+            "minimum" | "maximum" | "exclusiveMinimum" | "exclusiveMaximum" => {
+                if let (Some(number), Some(bound)) = (doc.get_number(), value.get_number()) {
+                    let within = match keyword {
+                        "minimum" => number >= bound,
+                        "maximum" => number <= bound,
+                        "exclusiveMinimum" => number > bound,
+                        _ => number < bound,
+                    };
+                    if !within {
+                        return fail(
+                            path,
+                            &format!("{} is outside the {} {}", number, keyword, bound),
+                        );
+                    }
+                }
+            }
+            "minLength" | "maxLength" => {
+                if let (Some(string), Some(bound)) = (doc.get_string(), value.get_number()) {
+                    let len = Decimal::from(string.chars().count() as u64);
+                    if (keyword == "minLength" && len < bound)
+                        || (keyword == "maxLength" && len > bound)
+                    {
+                        return fail(
+                            path,
+                            &format!("length {} is outside the {} {}", len, keyword, bound),
+                        );
+                    }
+                }
+            }
+            "minItems" | "maxItems" => {
+                if let (Some(items), Some(bound)) = (doc.iter_array(), value.get_number()) {
+                    let len = Decimal::from(items.count() as u64);
+                    if (keyword == "minItems" && len < bound)
+                        || (keyword == "maxItems" && len > bound)
+                    {
+                        return fail(
+                            path,
+                            &format!("{} items is outside the {} {}", len, keyword, bound),
+                        );
+                    }
+                }
+            }
+            "required" => {
+                if let (Some(_), Some(required)) = (doc.iter_object(), value.iter_array()) {
+                    for key in required.filter_map(|key| key.get_string()) {
+                        if property(doc, key).is_none() {
+                            return fail(path, &format!("missing required property {}", key));
+                        }
+                    }
+                }
+            }
+            "properties" => {
+                if let (Some(properties), Some(_)) = (doc.iter_object(), value.iter_object()) {
+                    for (key, property_doc) in properties {
+                        if let Some(property_schema) = property(value, key) {
+                            with_key(path, key, |path| {
+                                validate_at(property_doc, property_schema, path)
+                            })?;
+                        }
+                    }
+                }
+            }
+            "additionalProperties" => {
+                if let Some(properties) = doc.iter_object() {
+                    let known = property(schema, "properties");
+                    for (key, property_doc) in properties {
+                        if known.and_then(|known| property(known, key)).is_none() {
+                            with_key(path, key, |path| validate_at(property_doc, value, path))?;
+                        }
+                    }
+                }
+            }
+            "items" => {
+                if let Some(items) = doc.iter_array() {
+                    for (idx, item) in items.enumerate() {
+                        let len = path.len();
+                        path.push_str(&format!("[{}]", idx));
+                        validate_at(item, value, path)?;
+                        path.truncate(len);
+                    }
+                }
+            }
+            "allOf" | "anyOf" | "oneOf" => {
+                if let Some(schemas) = value.iter_array() {
+                    let mut matched = 0;
+                    let mut first_error = None;
+                    for sub_schema in schemas {
+                        match validate_at(doc, sub_schema, &mut path.clone()) {
+                            Ok(()) => matched += 1,
+                            Err(err) => {
+                                first_error.get_or_insert(err);
+                            }
+                        }
+                    }
+                    match (keyword, first_error) {
+                        ("allOf", Some(err)) => return Err(err),
+                        ("anyOf", Some(err)) if matched == 0 => return Err(err),
+                        ("oneOf", _) if matched != 1 => {
+                            return fail(
+                                path,
+                                &format!("matches {} of the oneOf schemas, not 1", matched),
+                            )
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            "not" => {
+                if validate_at(doc, value, &mut path.clone()).is_ok() {
+                    return fail(path, "matches the not schema");
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn fail(path: &str, reason: &str) -> Result<(), String> {
+    Err(format!("{}: {}", path, reason))
+}
+
+/// Validates with the key appended to the path, the path's put back after
+fn with_key<F: FnOnce(&mut String) -> Result<(), String>>(
+    path: &mut String,
+    key: &str,
+    f: F,
+) -> Result<(), String> {
+    let len = path.len();
+    path.push('.');
+    path.push_str(key);
+    f(path)?;
+    path.truncate(len);
+    Ok(())
+}
+
+fn property<'a>(object: Json<'a>, key: &str) -> Option<Json<'a>> {
+    object
+        .iter_object()?
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| v)
+}
+
+/// Whether the document is of the json schema type, integers are numbers without a fraction
+fn is_type(doc: Json, type_name: Option<&str>) -> bool {
+    match (type_name, doc.json_type()) {
+        (Some("null"), JsonType::Null) => true,
+        (Some("boolean"), JsonType::Boolean) => true,
+        (Some("object"), JsonType::Object) => true,
+        (Some("array"), JsonType::Array) => true,
+        (Some("number"), JsonType::Number) => true,
+        (Some("string"), JsonType::String) => true,
+        (Some("integer"), JsonType::Number) => doc
+            .get_number()
+            .map_or(false, |number| number.trunc() == number),
+        _ => false,
+    }
+}
+
+fn type_names(types: Json) -> String {
+    match types.iter_array() {
+        Some(types) => types
+            .filter_map(|t| t.get_string())
+            .collect::<Vec<_>>()
+            .join(" or "),
+        None => types.get_string().unwrap_or_default().to_string(),
+    }
+}
+
+/// Json equality as the spec defines it, numbers are equal if their values are, ie 1 and 1.0,
+/// and the keys of objects can be in any order
+fn json_equal(a: Json, b: Json) -> bool {
+    match (a.json_type(), b.json_type()) {
+        (JsonType::Number, JsonType::Number) => a.get_number() == b.get_number(),
+        (JsonType::Array, JsonType::Array) => {
+            let a_items: Vec<_> = a.iter_array().unwrap().collect();
+            let b_items: Vec<_> = b.iter_array().unwrap().collect();
+            a_items.len() == b_items.len()
+                && a_items
+                    .into_iter()
+                    .zip(b_items)
+                    .all(|(a, b)| json_equal(a, b))
+        }
+        (JsonType::Object, JsonType::Object) => {
+            a.iter_object().unwrap().count() == b.iter_object().unwrap().count()
+                && a.iter_object()
+                    .unwrap()
+                    .all(|(key, a)| property(b, key).map_or(false, |b| json_equal(a, b)))
+        }
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::OwnedJson;
+
+    fn check(doc: &str, schema: &str) -> Result<(), String> {
+        let doc = OwnedJson::parse(doc).unwrap();
+        let schema = OwnedJson::parse(schema).unwrap();
+        validate(doc.as_json(), schema.as_json())
+    }
+
+    #[test]
+    fn test_types() {
+        assert_eq!(check("1", r#"{"type": "integer"}"#), Ok(()));
+        assert_eq!(check("1.0", r#"{"type": "integer"}"#), Ok(()));
+        assert_eq!(
+            check("1.5", r#"{"type": "integer"}"#),
+            Err("$: expected integer".to_string())
+        );
+        assert_eq!(check("null", r#"{"type": ["string", "null"]}"#), Ok(()));
+        assert_eq!(
+            check("true", r#"{"type": ["string", "null"]}"#),
+            Err("$: expected string or null".to_string())
+        );
+        assert_eq!(check("[1]", "true"), Ok(()));
+        assert_eq!(
+            check("[1]", "false"),
+            Err("$: not allowed by the schema".to_string())
+        );
+        // Unknown keywords are ignored
+        assert_eq!(check("1", r#"{"format": "email"}"#), Ok(()));
+    }
+
+    #[test]
+    fn test_objects() {
+        let schema = r#"{
+            "type": "object",
+            "required": ["id"],
+            "properties": {
+                "id": {"type": "integer", "minimum": 1},
+                "items": {"type": "array", "maxItems": 2, "items": {"type": "object", "properties": {"qty": {"type": "integer"}}}}
+            },
+            "additionalProperties": {"type": "string"}
+        }"#;
+        assert_eq!(
+            check(r#"{"id": 1, "items": [{"qty": 2}], "note": "hi"}"#, schema),
+            Ok(())
+        );
+        assert_eq!(
+            check(r#"{"items": []}"#, schema),
+            Err("$: missing required property id".to_string())
+        );
+        assert_eq!(
+            check(r#"{"id": 0}"#, schema),
+            Err("$.id: 0 is outside the minimum 1".to_string())
+        );
+        assert_eq!(
+            check(r#"{"id": 1, "items": [{"qty": 2}, {"qty": "x"}]}"#, schema),
+            Err("$.items[1].qty: expected integer".to_string())
+        );
+        assert_eq!(
+            check(r#"{"id": 1, "items": [{}, {}, {}]}"#, schema),
+            Err("$.items: 3 items is outside the maxItems 2".to_string())
+        );
+        assert_eq!(
+            check(r#"{"id": 1, "note": 5}"#, schema),
+            Err("$.note: expected string".to_string())
+        );
+    }
+
+    #[test]
+    fn test_values() {
+        assert_eq!(check("1.0", r#"{"enum": ["a", 1]}"#), Ok(()));
+        assert_eq!(
+            check(r#""b""#, r#"{"enum": ["a", 1]}"#),
+            Err("$: not one of the enum's values".to_string())
+        );
+        assert_eq!(
+            check(r#"{"b": 2, "a": [1]}"#, r#"{"const": {"a": [1], "b": 2}}"#),
+            Ok(())
+        );
+        assert_eq!(
+            check(r#""abcd""#, r#"{"maxLength": 3}"#),
+            Err("$: length 4 is outside the maxLength 3".to_string())
+        );
+    }
+
+    #[test]
+    fn test_combinators() {
+        let any_of = r#"{"anyOf": [{"type": "string"}, {"type": "integer"}]}"#;
+        assert_eq!(check("1", any_of), Ok(()));
+        assert_eq!(check("true", any_of), Err("$: expected string".to_string()));
+
+        let one_of = r#"{"oneOf": [{"type": "number"}, {"type": "integer"}]}"#;
+        assert_eq!(check("1.5", one_of), Ok(()));
+        assert_eq!(
+            check("1", one_of),
+            Err("$: matches 2 of the oneOf schemas, not 1".to_string())
+        );
+
+        assert_eq!(
+            check("1", r#"{"allOf": [{"type": "integer"}, {"maximum": 0}]}"#),
+            Err("$: 1 is outside the maximum 0".to_string())
+        );
+        assert_eq!(
+            check("1", r#"{"not": {"type": "integer"}}"#),
+            Err("$: matches the not schema".to_string())
+        );
+    }
+
+    #[test]
+    fn test_check_schema() {
+        assert!(check_schema(OwnedJson::parse("{}").unwrap().as_json()).is_ok());
+        assert!(check_schema(OwnedJson::parse("false").unwrap().as_json()).is_ok());
+        assert!(check_schema(OwnedJson::parse("[]").unwrap().as_json()).is_err());
+    }
+}
@@ -101,7 +101,10 @@ impl<'de> Visitor<'de> for &mut JsonBuilderInner {
     where
         E: Error,
     {
-        self.push_decimal(Decimal::from_f64(v).unwrap());
+        match Decimal::from_f64(v) {
+            Some(d) => self.push_decimal(d),
+            None => self.push_string(&non_decimal_number(v)),
+        }
         Ok(())
     }
 
@@ -157,7 +160,7 @@ impl<'de> Visitor<'de> for &mut JsonBuilderInner {
         let mut key = if let Some(str) = map.next_key::<&str>()? {
             if str == "$serde_json::private::Number" {
                 let value = map.next_value::<String>()?;
-                self.push_decimal(Decimal::from_str(&value).unwrap());
+                push_number(self, &value);
                 return Ok(());
             }
             Some(str)
@@ -177,6 +180,54 @@ impl<'de> Visitor<'de> for &mut JsonBuilderInner {
     }
 }
 
+/// Json numbers are held as decimals, numbers written with an exponent are converted. Numbers that
+/// don't fit in a decimal are kept as strings rather than being rounded to something else or
+/// failing the whole document, see non_decimal_number.
+fn push_number(builder: &mut JsonBuilderInner, number: &str) {
+    let decimal = match number.find(|c| c == 'e' || c == 'E') {
+        Some(e) => from_scientific(&number[..e], &number[e + 1..]),
+        None => Decimal::from_str(number).ok(),
+    };
+    match decimal {
+        Some(d) => builder.push_decimal(d),
+        None => match number.parse::<f64>() {
+            // Too small to be anything but zero
+            Ok(f) if f.abs() < 1.0 => builder.push_decimal(Decimal::from(0)),
+            _ => builder.push_string(number),
+        },
+    }
+}
+
+/// Converts mantissa * 10^exponent to a decimal if it fits
+fn from_scientific(mantissa: &str, exponent: &str) -> Option<Decimal> {
+    let mut d = Decimal::from_str(mantissa).ok()?;
+    let scale = d.scale() as i64 - exponent.parse::<i64>().ok()?;
+    if !(-28..=28).contains(&scale) {
+        None
+    } else if scale >= 0 {
+        d.set_scale(scale as u32).ok()?;
+        Some(d)
+    } else {
+        d.set_scale(0).ok()?;
+        (0..-scale).try_fold(d, |d, _| d.checked_mul(Decimal::from(10)))
+    }
+}
+
+/// The text kept for a float with no decimal equivalent. NaN and the infinities are written the
+/// way javascript prints them, there's only the one NaN so they all compare, group and sort
+/// together as strings.
+fn non_decimal_number(f: f64) -> String {
+    if f.is_nan() {
+        "NaN".to_string()
+    } else if f == f64::INFINITY {
+        "Infinity".to_string()
+    } else if f == f64::NEG_INFINITY {
+        "-Infinity".to_string()
+    } else {
+        f.to_string()
+    }
+}
+
 impl<'de> DeserializeSeed<'de> for &mut JsonBuilderInner {
     type Value = ();
 
@@ -191,6 +242,8 @@ impl<'de> DeserializeSeed<'de> for &mut JsonBuilderInner {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde::de::value::{Error as ValueError, F64Deserializer};
+    use serde::de::IntoDeserializer;
 
     #[test]
     fn test_to_json() {
@@ -242,4 +295,34 @@ mod tests {
         let expected = r#"[-1234567890123456789012345,-123456789012345.6789012345]"#;
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn test_equal_numbers_encode_the_same() {
+        let parse = |s: &str| OwnedJson::parse(s).unwrap();
+        assert_eq!(parse("1.50"), parse("1.5"));
+        assert_eq!(parse("[1.0, -0.0]"), parse("[1, 0]"));
+        assert_eq!(parse("1.5e3"), parse("1500"));
+        assert_eq!(
+            serde_json::to_string(&parse("[2.50, 1E2, 25e-3]").as_json()).unwrap(),
+            "[2.5,100,0.025]"
+        );
+    }
+
+    #[test]
+    fn test_non_decimal_numbers() {
+        let parse = |s: &str| serde_json::to_string(&OwnedJson::parse(s).unwrap().as_json());
+        assert_eq!(parse("[1e300, -1e300]").unwrap(), r#"["1e300","-1e300"]"#);
+        assert_eq!(parse("1e-300").unwrap(), "0");
+
+        let from_f64 = |f: f64| {
+            let deserializer: F64Deserializer<ValueError> = f.into_deserializer();
+            let owned_json = OwnedJson::deserialize(deserializer).unwrap();
+            serde_json::to_string(&owned_json.as_json()).unwrap()
+        };
+        assert_eq!(from_f64(1.5), "1.5");
+        assert_eq!(from_f64(f64::NAN), r#""NaN""#);
+        assert_eq!(from_f64(-f64::NAN), r#""NaN""#);
+        assert_eq!(from_f64(f64::INFINITY), r#""Infinity""#);
+        assert_eq!(from_f64(f64::NEG_INFINITY), r#""-Infinity""#);
+    }
 }
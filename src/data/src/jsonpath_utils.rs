@@ -1,15 +1,18 @@
-use crate::json::{Json, JsonType};
+use crate::json::{Json, JsonBuilder, JsonType, OwnedJson};
 use nom::branch::alt;
 use nom::bytes::complete::escaped_transform;
-use nom::bytes::complete::{is_not, tag, tag_no_case, take, take_while};
+use nom::bytes::complete::{is_not, tag, tag_no_case, take, take_while, take_while1};
+use nom::character::complete::{digit1, multispace0};
 use nom::combinator::{all_consuming, cut, map, map_res, opt, recognize, value};
 use nom::error::context;
 use nom::lib::std::cmp::Ordering;
 use nom::lib::std::fmt::Formatter;
-use nom::multi::many0;
-use nom::sequence::{delimited, pair, preceded};
+use nom::multi::{fold_many0, many0};
+use nom::sequence::{delimited, pair, preceded, tuple};
 use nom::{AsChar, IResult};
+use rust_decimal::Decimal;
 use std::fmt::Display;
+use std::str::FromStr;
 
 /// Jsonpath utils.
 /// Jsonpath expressions start at a single root and with each path section the expression
@@ -39,9 +42,14 @@ impl JsonPathExpression {
     /// is to return values wrapped in a json array if this is true, otherwise to return
     /// the singular value (or null)
     pub fn could_return_many(&self) -> bool {
-        self.selectors
-            .iter()
-            .any(|selector| selector == &JsonPathSelector::Wildcard)
+        self.selectors.iter().any(|selector| {
+            matches!(
+                selector,
+                JsonPathSelector::Wildcard
+                    | JsonPathSelector::RecursiveDescent(_)
+                    | JsonPathSelector::Filter(_)
+            )
+        })
     }
 
     /// Evaluates the given jsonpath and calls a call back for each match.
@@ -63,6 +71,14 @@ impl JsonPathExpression {
     pub fn original(&self) -> &str {
         &self.original
     }
+
+    /// The key the path starts with if it starts by looking up a key, ie a for $.a.b
+    pub fn first_key(&self) -> Option<&str> {
+        match self.selectors.first() {
+            Some(JsonPathSelector::StringIdentifier(key)) => Some(key),
+            _ => None,
+        }
+    }
 }
 
 impl Display for JsonPathExpression {
@@ -88,6 +104,53 @@ enum JsonPathSelector {
     Wildcard,
     StringIdentifier(String),
     NumericIdentifier(i64),
+    // ie $..id, the selector is applied to the node and every node nested under it
+    RecursiveDescent(Box<JsonPathSelector>),
+    // ie $[?(@.qty > 1)], selects the children the filter is true for
+    Filter(Box<JsonPathFilter>),
+}
+
+/// The predicate of a filter selector, evaluated against each child with @ being the child
+#[derive(Clone, Eq, PartialEq, Debug)]
+enum JsonPathFilter {
+    // ie @.qty, true if the relative path matches anything
+    Exists(Vec<JsonPathSelector>),
+    // ie @.qty > 1, compares the first match of the relative path with the literal
+    Compare(Vec<JsonPathSelector>, Comparison, OwnedJson),
+    And(Box<JsonPathFilter>, Box<JsonPathFilter>),
+    Or(Box<JsonPathFilter>, Box<JsonPathFilter>),
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+}
+
+/// Passes a match on to the rest of the selectors, or to the call back if there aren't any
+fn evaluate_rest<'a, 'b: 'a, F: FnMut(Json<'b>)>(
+    input: Json<'b>,
+    rest: &'a [JsonPathSelector],
+    f: &mut F,
+) {
+    if rest.is_empty() {
+        f(input);
+    } else {
+        rest[0].evaluate(input, &rest[1..], f);
+    }
+}
+
+/// The values of an object or the elements of an array
+fn children(input: Json) -> Vec<Json> {
+    match input.json_type() {
+        JsonType::Object => input.iter_object().unwrap().map(|(_, v)| v).collect(),
+        JsonType::Array => input.iter_array().unwrap().collect(),
+        _ => vec![],
+    }
 }
 
 impl JsonPathSelector {
@@ -95,92 +158,71 @@ impl JsonPathSelector {
     pub fn evaluate<'a, 'b: 'a, F: FnMut(Json<'b>)>(
         &'a self,
         input: Json<'b>,
-        rest: &[JsonPathSelector],
+        rest: &'a [JsonPathSelector],
         f: &mut F,
     ) {
+        match self {
+            JsonPathSelector::RecursiveDescent(selector) => {
+                selector.evaluate(input, rest, f);
+                for child in children(input) {
+                    self.evaluate(child, rest, f);
+                }
+                return;
+            }
+            JsonPathSelector::Filter(filter) => {
+                for child in children(input) {
+                    if filter.matches(child) {
+                        evaluate_rest(child, rest, f);
+                    }
+                }
+                return;
+            }
+            _ => {}
+        }
+
         match input.json_type() {
             JsonType::Object => {
-                let kv_iter = input.iter_object().unwrap();
+                let mut kv_iter = input.iter_object().unwrap();
                 match self {
-                    JsonPathSelector::Wildcard => {
-                        for (_, v) in kv_iter {
-                            if rest.is_empty() {
-                                f(v);
-                            } else {
-                                rest[0].evaluate(v, &rest[1..], f);
-                            }
-                        }
-                    }
                     JsonPathSelector::StringIdentifier(str) => {
-                        for (k, v) in kv_iter {
-                            if k.eq_ignore_ascii_case(str) {
-                                if rest.is_empty() {
-                                    f(v);
-                                } else {
-                                    rest[0].evaluate(v, &rest[1..], f);
-                                }
-                                break;
-                            }
+                        if let Some((_, v)) = kv_iter.find(|(k, _)| k.eq_ignore_ascii_case(str)) {
+                            evaluate_rest(v, rest, f);
                         }
                     }
                     JsonPathSelector::NumericIdentifier(idx) => {
                         // This seems to match the behaviour of of other jsonpath implementations.
                         // I think its because in JS arrays are semantically objects with the indexes
                         // as keys
-                        for (k, v) in kv_iter {
-                            if k.eq(&idx.to_string()) {
-                                if rest.is_empty() {
-                                    f(v);
-                                } else {
-                                    rest[0].evaluate(v, &rest[1..], f);
-                                }
-                                break;
-                            }
+                        let idx = idx.to_string();
+                        if let Some((_, v)) = kv_iter.find(|(k, _)| *k == idx) {
+                            evaluate_rest(v, rest, f);
+                        }
+                    }
+                    // Wildcards, the other selectors were handled above
+                    _ => {
+                        for (_, v) in kv_iter {
+                            evaluate_rest(v, rest, f);
                         }
                     }
                 }
             }
             JsonType::Array => {
-                let v_iter = input.iter_array().unwrap();
-
-                match self {
-                    JsonPathSelector::Wildcard => {
+                let mut v_iter = input.iter_array().unwrap();
+                let idx = match self {
+                    JsonPathSelector::StringIdentifier(s) => s.parse::<i64>().ok(),
+                    JsonPathSelector::NumericIdentifier(i) => Some(*i),
+                    // Wildcards
+                    _ => {
                         for v in v_iter {
-                            if rest.is_empty() {
-                                f(v);
-                            } else {
-                                rest[0].evaluate(v, &rest[1..], f);
-                            }
+                            evaluate_rest(v, rest, f);
                         }
+                        return;
                     }
-                    JsonPathSelector::StringIdentifier(s) => {
-                        if let Ok(i) = s.parse::<i64>() {
-                            if i >= 0 {
-                                for (idx, v) in v_iter.enumerate() {
-                                    if idx == i as usize {
-                                        if rest.is_empty() {
-                                            f(v);
-                                        } else {
-                                            rest[0].evaluate(v, &rest[1..], f);
-                                        }
-                                        break;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    JsonPathSelector::NumericIdentifier(i) => {
-                        if *i >= 0 {
-                            for (idx, v) in v_iter.enumerate() {
-                                if idx == *i as usize {
-                                    if rest.is_empty() {
-                                        f(v);
-                                    } else {
-                                        rest[0].evaluate(v, &rest[1..], f);
-                                    }
-                                    break;
-                                }
-                            }
+                };
+                if let Some(idx) = idx {
+                    if idx >= 0 {
+                        if let Some(v) = v_iter.nth(idx as usize) {
+                            evaluate_rest(v, rest, f);
                         }
                     }
                 }
@@ -190,6 +232,59 @@ impl JsonPathSelector {
     }
 }
 
+impl JsonPathFilter {
+    /// Whether the filter's true for the node
+    fn matches(&self, input: Json) -> bool {
+        match self {
+            JsonPathFilter::Exists(selectors) => first_match(selectors, input).is_some(),
+            JsonPathFilter::Compare(selectors, comparison, literal) => {
+                match first_match(selectors, input) {
+                    Some(value) => comparison.compare(value, literal.as_json()),
+                    None => false,
+                }
+            }
+            JsonPathFilter::And(left, right) => left.matches(input) && right.matches(input),
+            JsonPathFilter::Or(left, right) => left.matches(input) || right.matches(input),
+        }
+    }
+}
+
+/// The first match of a relative path, the path of a filter
+fn first_match<'b>(selectors: &[JsonPathSelector], input: Json<'b>) -> Option<Json<'b>> {
+    let mut result = None;
+    evaluate_rest(input, selectors, &mut |j| {
+        if result.is_none() {
+            result = Some(j)
+        }
+    });
+    result
+}
+
+impl Comparison {
+    /// Numbers and strings are ordered, anything else can only be compared for equality
+    fn compare(self, left: Json, right: Json) -> bool {
+        let ordering = match (left.json_type(), right.json_type()) {
+            (JsonType::Number, JsonType::Number) => left.get_number().cmp(&right.get_number()),
+            (JsonType::String, JsonType::String) => left.get_string().cmp(&right.get_string()),
+            _ => {
+                return match self {
+                    Comparison::Equal => left == right,
+                    Comparison::NotEqual => left != right,
+                    _ => false,
+                }
+            }
+        };
+        match self {
+            Comparison::Equal => ordering == Ordering::Equal,
+            Comparison::NotEqual => ordering != Ordering::Equal,
+            Comparison::LessThan => ordering == Ordering::Less,
+            Comparison::LessThanOrEqual => ordering != Ordering::Greater,
+            Comparison::GreaterThan => ordering == Ordering::Greater,
+            Comparison::GreaterThanOrEqual => ordering != Ordering::Less,
+        }
+    }
+}
+
 type ParserResult<'a, T> = IResult<&'a str, T>;
 
 fn parse_expression(input: &str) -> ParserResult<Vec<JsonPathSelector>> {
@@ -200,22 +295,140 @@ fn parse_selector(input: &str) -> ParserResult<JsonPathSelector> {
     // Its always either dot or bracket notation, numbers only seem to be able to work via
     // bracket notation
     alt((
-        value(JsonPathSelector::Wildcard, alt((tag(".*"), tag("[*]")))),
+        map(
+            preceded(tag(".."), alt((bracket_selector, dot_selector))),
+            |selector| JsonPathSelector::RecursiveDescent(Box::new(selector)),
+        ),
+        bracket_selector,
+        preceded(tag("."), dot_selector),
+    ))(input)
+}
+
+fn bracket_selector(input: &str) -> ParserResult<JsonPathSelector> {
+    alt((
+        value(JsonPathSelector::Wildcard, tag("[*]")),
+        map(
+            delimited(
+                tag("[?("),
+                cut(delimited(multispace0, filter, multispace0)),
+                cut(context("Missing closing bracket", tag(")]"))),
+            ),
+            |filter| JsonPathSelector::Filter(Box::new(filter)),
+        ),
         map(delimited(tag("["), integer, tag("]")), |i| {
             JsonPathSelector::NumericIdentifier(i)
         }),
-        map(preceded(tag("."), integer), |i: i64| {
-            JsonPathSelector::NumericIdentifier(i)
-        }),
         map(delimited(tag("["), quoted_string, tag("]")), |s| {
             JsonPathSelector::StringIdentifier(s)
         }),
-        map(preceded(tag("."), is_not(".[")), |s: &str| {
+    ))(input)
+}
+
+/// What follows a dot
+fn dot_selector(input: &str) -> ParserResult<JsonPathSelector> {
+    alt((
+        value(JsonPathSelector::Wildcard, tag("*")),
+        map(integer, JsonPathSelector::NumericIdentifier),
+        map(is_not(".["), |s: &str| {
             JsonPathSelector::StringIdentifier(s.to_string())
         }),
     ))(input)
 }
 
+/// Filters are comparisons joined by && and ||, with && binding tighter
+fn filter(input: &str) -> ParserResult<JsonPathFilter> {
+    let (input, first) = and_filter(input)?;
+    fold_many0(
+        preceded(delimited(multispace0, tag("||"), multispace0), and_filter),
+        first,
+        |left, right| JsonPathFilter::Or(Box::new(left), Box::new(right)),
+    )(input)
+}
+
+fn and_filter(input: &str) -> ParserResult<JsonPathFilter> {
+    let (input, first) = comparison_filter(input)?;
+    fold_many0(
+        preceded(
+            delimited(multispace0, tag("&&"), multispace0),
+            comparison_filter,
+        ),
+        first,
+        |left, right| JsonPathFilter::And(Box::new(left), Box::new(right)),
+    )(input)
+}
+
+fn comparison_filter(input: &str) -> ParserResult<JsonPathFilter> {
+    map(
+        pair(
+            preceded(tag("@"), many0(relative_selector)),
+            opt(pair(
+                delimited(multispace0, comparison, multispace0),
+                literal,
+            )),
+        ),
+        |(selectors, comparison)| match comparison {
+            Some((comparison, literal)) => JsonPathFilter::Compare(selectors, comparison, literal),
+            None => JsonPathFilter::Exists(selectors),
+        },
+    )(input)
+}
+
+/// The path in a filter, the keys after dots stop at anything that could be an operator
+fn relative_selector(input: &str) -> ParserResult<JsonPathSelector> {
+    alt((
+        bracket_selector,
+        preceded(
+            tag("."),
+            alt((
+                value(JsonPathSelector::Wildcard, tag("*")),
+                map(integer, JsonPathSelector::NumericIdentifier),
+                map(
+                    take_while1(|c: char| c.is_alphanumeric() || c == '_' || c == '$'),
+                    |s: &str| JsonPathSelector::StringIdentifier(s.to_string()),
+                ),
+            )),
+        ),
+    ))(input)
+}
+
+fn comparison(input: &str) -> ParserResult<Comparison> {
+    alt((
+        value(Comparison::Equal, tag("==")),
+        value(Comparison::NotEqual, tag("!=")),
+        value(Comparison::LessThanOrEqual, tag("<=")),
+        value(Comparison::GreaterThanOrEqual, tag(">=")),
+        value(Comparison::LessThan, tag("<")),
+        value(Comparison::GreaterThan, tag(">")),
+    ))(input)
+}
+
+/// The value compared against, strings can be single or double quoted
+fn literal(input: &str) -> ParserResult<OwnedJson> {
+    alt((
+        map(alt((quoted_string, single_quoted_string)), |s| {
+            JsonBuilder::default().string(&s)
+        }),
+        value(JsonBuilder::default().bool(true), tag_no_case("true")),
+        value(JsonBuilder::default().bool(false), tag_no_case("false")),
+        value(JsonBuilder::default().null(), tag_no_case("null")),
+        map_res(
+            recognize(tuple((opt(tag("-")), digit1, opt(pair(tag("."), digit1))))),
+            |s: &str| Decimal::from_str(s).map(|d| JsonBuilder::default().decimal(d)),
+        ),
+    ))(input)
+}
+
+fn single_quoted_string(input: &str) -> ParserResult<String> {
+    alt((
+        value(String::new(), tag("''")),
+        delimited(
+            tag("'"),
+            escaped_transform(is_not("'\\"), '\\', take(1_usize)),
+            cut(context("Missing closing single quote", tag("'"))),
+        ),
+    ))(input)
+}
+
 // Quoted String and Integer functions are lifted from the parser with some tweaks.
 
 /// String's are double or single quoted
@@ -420,4 +633,100 @@ mod tests {
             Some(expected.as_json())
         );
     }
+
+    fn evaluate_all(path: &str, json: &str) -> Vec<OwnedJson> {
+        let expr = JsonPathExpression::parse(path).unwrap();
+        let input = OwnedJson::parse(json).unwrap();
+        let mut results = vec![];
+        expr.evaluate(input.as_json(), &mut |j| results.push(OwnedJson::from(j)));
+        results
+    }
+
+    fn parse_all(jsons: &[&str]) -> Vec<OwnedJson> {
+        jsons.iter().map(|j| OwnedJson::parse(j).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_recursive_descent() {
+        let expr = JsonPathExpression::parse("$..id").unwrap();
+        assert_eq!(
+            expr.selectors,
+            vec![JsonPathSelector::RecursiveDescent(Box::new(
+                JsonPathSelector::StringIdentifier("id".to_string())
+            ))]
+        );
+        assert_eq!(expr.could_return_many(), true);
+
+        let json = r#"{"id": 1, "items": [{"id": 2}, {"child": {"id": 3}}]}"#;
+        assert_eq!(evaluate_all("$..id", json), parse_all(&["1", "2", "3"]));
+        assert_eq!(evaluate_all("$.items..id", json), parse_all(&["2", "3"]));
+        assert_eq!(evaluate_all("$..[0]", json), parse_all(&[r#"{"id": 2}"#]));
+        assert_eq!(
+            evaluate_all("$..*", r#"{"a": {"b": 1}}"#),
+            parse_all(&[r#"{"b": 1}"#, "1"])
+        );
+    }
+
+    #[test]
+    fn test_wildcard_path() {
+        let json = r#"{"items": [{"price": 1.5}, {"price": 2}, {"name": "x"}]}"#;
+        assert_eq!(
+            evaluate_all("$.items[*].price", json),
+            parse_all(&["1.5", "2"])
+        );
+    }
+
+    #[test]
+    fn test_filter() {
+        let json = r#"[
+            {"name": "a", "qty": 1, "tags": ["x"]},
+            {"name": "b", "qty": 2},
+            {"name": "c", "qty": 3, "ok": true}
+        ]"#;
+        let names = |path| {
+            evaluate_all(path, json)
+                .iter()
+                .map(|j| j.as_json().get_string().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("$[?(@.qty > 1)].name"), vec!["b", "c"]);
+        assert_eq!(names("$[?(@.qty<=2)].name"), vec!["a", "b"]);
+        assert_eq!(names("$[?(@.qty == 1.0)].name"), vec!["a"]);
+        assert_eq!(names("$[?(@.name != 'b')].name"), vec!["a", "c"]);
+        assert_eq!(names(r#"$[?(@.name == "c")].name"#), vec!["c"]);
+        assert_eq!(names("$[?(@.ok == true)].name"), vec!["c"]);
+        assert_eq!(names("$[?(@.tags)].name"), vec!["a"]);
+        assert_eq!(names("$[?(@.tags[0] == 'x')].name"), vec!["a"]);
+        assert_eq!(
+            names("$[?(@.qty > 1 && @.qty < 3 || @.name == 'a')].name"),
+            vec!["a", "b"]
+        );
+        // Strings and numbers aren't ordered against each other
+        assert_eq!(names("$[?(@.name > 1)].name"), Vec::<String>::new());
+        assert_eq!(
+            JsonPathExpression::parse("$[?(@.qty > 1)]")
+                .unwrap()
+                .could_return_many(),
+            true
+        );
+
+        assert_eq!(JsonPathExpression::parse("$[?(@.qty > )]"), None);
+        assert_eq!(JsonPathExpression::parse("$[?(@.qty > 1]"), None);
+        assert_eq!(JsonPathExpression::parse("$[?(qty > 1)]"), None);
+    }
+
+    #[test]
+    fn test_first_key() {
+        let first_key = |path| {
+            JsonPathExpression::parse(path)
+                .unwrap()
+                .first_key()
+                .map(str::to_string)
+        };
+        assert_eq!(first_key("$.a.b"), Some("a".to_string()));
+        assert_eq!(first_key(r#"$["my key"][0]"#), Some("my key".to_string()));
+        assert_eq!(first_key("$[0]"), None);
+        assert_eq!(first_key("$.*"), None);
+        assert_eq!(first_key("$"), None);
+    }
 }
@@ -2,20 +2,35 @@
 // NaiveDate
 pub use chrono;
 pub use rust_decimal;
+mod cast_matrix;
+mod clock;
 mod datatype;
+mod dates;
 mod datum;
 pub mod encoding_core;
 mod encoding_datum;
+pub mod encoding_tuple;
 pub mod json;
 mod json_serde;
+mod locale;
 mod session;
 mod tuple_iter;
+pub use cast_matrix::*;
+pub use clock::Clock;
 pub use datatype::*;
+pub use dates::*;
 pub use datum::Datum;
+pub use locale::Locale;
 use serde::export::Formatter;
-pub use session::Session;
+pub use session::{
+    is_utf8_charset, DecimalOverflow, InvalidUtf8, Priority, Session, Warning, WarningLevel,
+    DEFAULT_MAX_INTERMEDIATE_ROWS, DEFAULT_MAX_RECURSION_DEPTH, DEFAULT_MAX_VALUE_BYTES,
+    MAX_WARNINGS, WARN_DATA_OUT_OF_RANGE, WARN_DATA_TRUNCATED, WARN_GENERIC, WARN_ROW_REJECTED,
+    WARN_TRUNCATED_WRONG_VALUE,
+};
 use std::fmt::Display;
 pub use tuple_iter::*;
+pub mod json_schema;
 pub mod jsonpath_utils;
 
 #[macro_use]
@@ -69,4 +84,50 @@ impl LogicalTimestamp {
                 .as_millis() as u64,
         }
     }
+
+    /// Parses a utc timestamp written the same as a TIMESTAMP literal, ie 2020-01-31 12:00:00
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let millis = parse_timestamp(s)?.timestamp_millis();
+        if millis < 0 {
+            return Err("expected a timestamp after 1970-01-01".to_string());
+        }
+        Ok(LogicalTimestamp::new(millis as u64))
+    }
+}
+
+/// Parses a duration such as '5 minutes', '1h' or '250ms' into milliseconds, a bare number is
+/// taken to be seconds.
+pub fn parse_duration_ms(duration: &str) -> Option<i64> {
+    let duration = duration.trim();
+    let unit_start = duration
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(duration.len());
+    let count: i64 = duration[..unit_start].parse().ok()?;
+    let unit_millis = match duration[unit_start..].trim_start().to_lowercase().as_str() {
+        "ms" | "millisecond" | "milliseconds" => 1,
+        "" | "s" | "sec" | "secs" | "second" | "seconds" => 1000,
+        "m" | "min" | "mins" | "minute" | "minutes" => 60 * 1000,
+        "h" | "hour" | "hours" => 60 * 60 * 1000,
+        "d" | "day" | "days" => 24 * 60 * 60 * 1000,
+        "w" | "week" | "weeks" => 7 * 24 * 60 * 60 * 1000,
+        _ => return None,
+    };
+    count.checked_mul(unit_millis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration_ms() {
+        assert_eq!(parse_duration_ms("250ms"), Some(250));
+        assert_eq!(parse_duration_ms("30"), Some(30_000));
+        assert_eq!(parse_duration_ms("5 Minutes"), Some(300_000));
+        assert_eq!(parse_duration_ms("1h"), Some(3_600_000));
+        assert_eq!(parse_duration_ms("2 days"), Some(172_800_000));
+        assert_eq!(parse_duration_ms("1 week"), Some(604_800_000));
+        assert_eq!(parse_duration_ms("minutes"), None);
+        assert_eq!(parse_duration_ms("5 parsecs"), None);
+    }
 }
@@ -0,0 +1,183 @@
+/// The names used when formatting dates for a given locale, set per session via lc_time.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Locale {
+    pub name: &'static str,
+    pub month_names: [&'static str; 12],
+    pub abbreviated_month_names: [&'static str; 12],
+    // Days start from monday
+    pub day_names: [&'static str; 7],
+    pub abbreviated_day_names: [&'static str; 7],
+}
+
+pub const EN_US: Locale = Locale {
+    name: "en_US",
+    month_names: [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ],
+    abbreviated_month_names: [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ],
+    day_names: [
+        "Monday",
+        "Tuesday",
+        "Wednesday",
+        "Thursday",
+        "Friday",
+        "Saturday",
+        "Sunday",
+    ],
+    abbreviated_day_names: ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+};
+
+const DE_DE: Locale = Locale {
+    name: "de_DE",
+    month_names: [
+        "Januar",
+        "Februar",
+        "März",
+        "April",
+        "Mai",
+        "Juni",
+        "Juli",
+        "August",
+        "September",
+        "Oktober",
+        "November",
+        "Dezember",
+    ],
+    abbreviated_month_names: [
+        "Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez",
+    ],
+    day_names: [
+        "Montag",
+        "Dienstag",
+        "Mittwoch",
+        "Donnerstag",
+        "Freitag",
+        "Samstag",
+        "Sonntag",
+    ],
+    abbreviated_day_names: ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+};
+
+const ES_ES: Locale = Locale {
+    name: "es_ES",
+    month_names: [
+        "enero",
+        "febrero",
+        "marzo",
+        "abril",
+        "mayo",
+        "junio",
+        "julio",
+        "agosto",
+        "septiembre",
+        "octubre",
+        "noviembre",
+        "diciembre",
+    ],
+    abbreviated_month_names: [
+        "ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sep", "oct", "nov", "dic",
+    ],
+    day_names: [
+        "lunes",
+        "martes",
+        "miércoles",
+        "jueves",
+        "viernes",
+        "sábado",
+        "domingo",
+    ],
+    abbreviated_day_names: ["lun", "mar", "mié", "jue", "vie", "sáb", "dom"],
+};
+
+const FR_FR: Locale = Locale {
+    name: "fr_FR",
+    month_names: [
+        "janvier",
+        "février",
+        "mars",
+        "avril",
+        "mai",
+        "juin",
+        "juillet",
+        "août",
+        "septembre",
+        "octobre",
+        "novembre",
+        "décembre",
+    ],
+    abbreviated_month_names: [
+        "janv", "févr", "mars", "avr", "mai", "juin", "juil", "août", "sept", "oct", "nov", "déc",
+    ],
+    day_names: [
+        "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+    ],
+    abbreviated_day_names: ["lun", "mar", "mer", "jeu", "ven", "sam", "dim"],
+};
+
+const IT_IT: Locale = Locale {
+    name: "it_IT",
+    month_names: [
+        "gennaio",
+        "febbraio",
+        "marzo",
+        "aprile",
+        "maggio",
+        "giugno",
+        "luglio",
+        "agosto",
+        "settembre",
+        "ottobre",
+        "novembre",
+        "dicembre",
+    ],
+    abbreviated_month_names: [
+        "gen", "feb", "mar", "apr", "mag", "giu", "lug", "ago", "set", "ott", "nov", "dic",
+    ],
+    day_names: [
+        "lunedì",
+        "martedì",
+        "mercoledì",
+        "giovedì",
+        "venerdì",
+        "sabato",
+        "domenica",
+    ],
+    abbreviated_day_names: ["lun", "mar", "mer", "gio", "ven", "sab", "dom"],
+};
+
+static LOCALES: [&Locale; 5] = [&EN_US, &DE_DE, &ES_ES, &FR_FR, &IT_IT];
+
+impl Locale {
+    /// Looks up a locale by name, ie de_DE, ignoring case.
+    pub fn from_name(name: &str) -> Option<&'static Locale> {
+        LOCALES
+            .iter()
+            .find(|locale| locale.name.eq_ignore_ascii_case(name))
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(Locale::from_name("de_de").unwrap().month_names[2], "März");
+        assert_eq!(Locale::from_name("en_US"), Some(&EN_US));
+        assert_eq!(Locale::from_name("xx_XX"), None);
+    }
+}
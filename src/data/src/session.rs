@@ -1,22 +1,443 @@
-use std::sync::atomic::AtomicBool;
-use std::sync::RwLock;
+use crate::clock::Clock;
+use crate::locale::{Locale, EN_US};
+use crate::{DataType, Datum, LogicalTimestamp};
+use chrono::{FixedOffset, NaiveDateTime};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Stores any and all session variables.
 #[derive(Debug)]
 pub struct Session {
     pub user: RwLock<String>,
+    // What the client reported about itself in the handshake, ie program_name, _client_name and
+    // _client_version
+    pub client_attributes: RwLock<HashMap<String, String>>,
+    // The sql of the statement being run, or the last one run, shown in SHOW PROCESSLIST
+    pub statement: RwLock<String>,
     pub current_database: RwLock<String>,
     pub connection_id: u32,
     pub kill_flag: AtomicBool,
+    // Used for month/day names when formatting dates
+    pub lc_time: RwLock<&'static Locale>,
+    // Timestamps are stored as utc, this is the offset used when formatting them
+    pub time_zone: RwLock<FixedOffset>,
+    // Implicit limit for top level selects that don't have a LIMIT, None means no limit
+    pub sql_select_limit: RwLock<Option<i64>>,
+    // Sorts the output of top level selects so rows always come back in the same order
+    pub deterministic_order: RwLock<bool>,
+    // Reads every table in a statement as of one timestamp that all writes have committed up to
+    pub read_barrier: RwLock<bool>,
+    // Reads every table as it was at this timestamp, statements can only read while it's set
+    pub as_of: RwLock<Option<LogicalTimestamp>>,
+    // Captured at the start of each statement so now() is the same throughout a statement
+    pub statement_timestamp: RwLock<NaiveDateTime>,
+    // When set each statement's random() sequence starts from here, otherwise it's random
+    pub seed: RwLock<Option<u64>>,
+    // The non-fatal problems hit during this statement, listed by SHOW WARNINGS. Only the first
+    // MAX_WARNINGS are kept, warning_count counts them all
+    warnings: Mutex<Vec<Warning>>,
+    warning_count: AtomicU64,
+    // How far resumable file sources have been read, inserts commit these with their writes
+    pub pending_source_offsets: Mutex<HashMap<String, i64>>,
+    // Statements queued for admission are let in highest priority first
+    pub priority: RwLock<Priority>,
+    // Optimizer rules switched off with SET optimizer_rules
+    pub disabled_optimizer_rules: RwLock<HashSet<String>>,
+    // Plain casts of values that can't be converted error rather than giving null
+    pub strict_casts: RwLock<bool>,
+    // What happens to decimals too big for their type, ie the result of a multiply
+    pub decimal_overflow: RwLock<DecimalOverflow>,
+    // What happens to text that isn't valid utf8 coming from clients, files or casts from bytes
+    pub invalid_utf8: RwLock<InvalidUtf8>,
+    // Values set with SET @name = ..., referenced in later statements as @name
+    pub user_variables: RwLock<HashMap<String, (Datum<'static>, DataType)>>,
+    // The first cast to fail this statement when strict_casts is on, or decimal to overflow
+    cast_error: Mutex<Option<String>>,
+    // The percentage of queries whose results are checked against an unoptimized plan's
+    pub plan_validation: RwLock<u8>,
+    plan_validation_count: AtomicU64,
+    // How deep views can be nested in views and procedures can call procedures
+    pub max_recursion_depth: RwLock<u32>,
+    // The most rows a materialized view refresh holds in memory at once
+    pub max_intermediate_rows: RwLock<u64>,
+    // The biggest text, json or bytes value that can be inserted or read from a file
+    pub max_value_bytes: RwLock<u64>,
+    // Where statement timestamps come from, the runtime's clock
+    pub clock: Clock,
+    random_state: AtomicU64,
+}
+
+/// How a session's statements are ordered against other sessions' when they have to queue
+/// to be admitted.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+}
+
+impl Priority {
+    /// Parses low, normal or high, any case
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "low" => Some(Priority::Low),
+            "normal" | "default" => Some(Priority::Normal),
+            "high" => Some(Priority::High),
+            _ => None,
+        }
+    }
+}
+
+/// What to do with a decimal that has more digits before the decimal point than its type
+/// allows for.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DecimalOverflow {
+    // Fail the statement
+    Error,
+    // Clamp to the biggest (or smallest) value the type can hold, with a warning
+    Saturate,
+    // Drop digits after the decimal point to make room, with a warning
+    Rescale,
+}
+
+impl DecimalOverflow {
+    /// Parses error, saturate or rescale, any case
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "error" | "default" => Some(DecimalOverflow::Error),
+            "saturate" => Some(DecimalOverflow::Saturate),
+            "rescale" => Some(DecimalOverflow::Rescale),
+            _ => None,
+        }
+    }
+}
+
+/// What to do with text that comes in from outside (clients, files, bytes cast to text) that
+/// isn't valid utf8. Text already stored has been through this so is trusted to be valid.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum InvalidUtf8 {
+    // Reject the text, how depends on where it came from
+    Error,
+    // Swap the invalid sequences for the U+FFFD replacement character
+    Replace,
+}
+
+impl InvalidUtf8 {
+    /// Parses error or replace, any case
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "error" | "default" => Some(InvalidUtf8::Error),
+            "replace" => Some(InvalidUtf8::Replace),
+            _ => None,
+        }
+    }
+
+    /// Decodes the bytes as utf8, replacing or erroring on any invalid sequences. The error is
+    /// the offset of the first invalid byte.
+    pub fn decode(self, bytes: &[u8]) -> Result<Cow<str>, usize> {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => Ok(Cow::Borrowed(text)),
+            Err(_) if self == InvalidUtf8::Replace => Ok(String::from_utf8_lossy(bytes)),
+            Err(err) => Err(err.valid_up_to()),
+        }
+    }
+}
+
+/// The character sets SET NAMES and character_set_client accept, text is only ever utf8
+pub fn is_utf8_charset(name: &str) -> bool {
+    matches!(
+        name.to_lowercase().as_str(),
+        "utf8" | "utf8mb4" | "utf8mb3" | "default"
+    )
+}
+
+/// The most warnings kept for SHOW WARNINGS per statement, mysql's default max_error_count
+pub const MAX_WARNINGS: usize = 1024;
+
+/// The default max_recursion_depth, well past anything but a view or procedure that refers
+/// back to itself
+pub const DEFAULT_MAX_RECURSION_DEPTH: u32 = 32;
+
+/// The default max_intermediate_rows
+pub const DEFAULT_MAX_INTERMEDIATE_ROWS: u64 = 10_000_000;
+
+/// The default max_value_bytes, mysql's default max_allowed_packet
+pub const DEFAULT_MAX_VALUE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Warning codes, mysql's where there's an equivalent
+pub const WARN_GENERIC: u16 = 1000;
+pub const WARN_DATA_OUT_OF_RANGE: u16 = 1264;
+pub const WARN_DATA_TRUNCATED: u16 = 1265;
+pub const WARN_TRUNCATED_WRONG_VALUE: u16 = 1292;
+pub const WARN_ROW_REJECTED: u16 = 1366;
+
+/// How serious a warning is, shown as SHOW WARNINGS' Level.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum WarningLevel {
+    Note,
+    Warning,
+}
+
+impl WarningLevel {
+    pub fn name(self) -> &'static str {
+        match self {
+            WarningLevel::Note => "Note",
+            WarningLevel::Warning => "Warning",
+        }
+    }
+}
+
+/// A non-fatal problem hit while running a statement, ie a value that was rounded or a row a
+/// file source skipped. They're counted in the statement's result and listed by SHOW WARNINGS.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Warning {
+    pub level: WarningLevel,
+    pub code: u16,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn new(code: u16, message: String) -> Self {
+        Warning {
+            level: WarningLevel::Warning,
+            code,
+            message,
+        }
+    }
+
+    /// A warning for something the user most likely expected, ie rounding to a column's scale
+    pub fn note(code: u16, message: String) -> Self {
+        Warning {
+            level: WarningLevel::Note,
+            code,
+            message,
+        }
+    }
 }
 
 impl Session {
     pub fn new(connection_id: u32) -> Self {
+        Session::new_with_clock(connection_id, Clock::system())
+    }
+
+    /// Creates a session whose statement timestamps come from the clock rather than the system
+    /// time
+    pub fn new_with_clock(connection_id: u32, clock: Clock) -> Self {
         Session {
             user: RwLock::from(String::new()),
+            client_attributes: RwLock::from(HashMap::new()),
+            statement: RwLock::from(String::new()),
             current_database: RwLock::from(String::from("default")),
             connection_id,
             kill_flag: AtomicBool::from(false),
+            lc_time: RwLock::from(&EN_US),
+            time_zone: RwLock::from(FixedOffset::east_opt(0).unwrap()),
+            sql_select_limit: RwLock::from(None),
+            deterministic_order: RwLock::from(false),
+            read_barrier: RwLock::from(false),
+            as_of: RwLock::from(None),
+            statement_timestamp: RwLock::from(clock.now_utc()),
+            seed: RwLock::from(None),
+            warnings: Mutex::from(vec![]),
+            warning_count: AtomicU64::from(0),
+            pending_source_offsets: Mutex::from(HashMap::new()),
+            priority: RwLock::from(Priority::Normal),
+            disabled_optimizer_rules: RwLock::from(HashSet::new()),
+            strict_casts: RwLock::from(false),
+            decimal_overflow: RwLock::from(DecimalOverflow::Error),
+            invalid_utf8: RwLock::from(InvalidUtf8::Error),
+            user_variables: RwLock::from(HashMap::new()),
+            cast_error: Mutex::from(None),
+            plan_validation: RwLock::from(0),
+            plan_validation_count: AtomicU64::from(0),
+            max_recursion_depth: RwLock::from(DEFAULT_MAX_RECURSION_DEPTH),
+            max_intermediate_rows: RwLock::from(DEFAULT_MAX_INTERMEDIATE_ROWS),
+            max_value_bytes: RwLock::from(DEFAULT_MAX_VALUE_BYTES),
+            clock,
+            random_state: AtomicU64::from(0),
         }
     }
+
+    /// Returns the client reported attribute, ie program_name
+    pub fn client_attribute(&self, name: &str) -> Option<String> {
+        self.client_attributes.read().unwrap().get(name).cloned()
+    }
+
+    /// Called before running each statement, captures the statement timestamp and resets the
+    /// random sequence so re-running a statement with the same seed gives the same results.
+    pub fn start_statement(&self) {
+        *self.statement_timestamp.write().unwrap() = self.clock.now_utc();
+        self.warnings.lock().unwrap().clear();
+        self.warning_count.store(0, Ordering::Relaxed);
+        self.pending_source_offsets.lock().unwrap().clear();
+        self.cast_error.lock().unwrap().take();
+        let seed = self.seed.read().unwrap().unwrap_or_else(|| {
+            let nanos = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_nanos() as u64)
+                .unwrap_or_default();
+            nanos ^ ((self.connection_id as u64) << 32)
+        });
+        self.random_state.store(seed, Ordering::Relaxed);
+    }
+
+    /// Records a warning to send back to the client with this statement's results
+    pub fn add_warning(&self, warning: Warning) {
+        self.warning_count.fetch_add(1, Ordering::Relaxed);
+        let mut warnings = self.warnings.lock().unwrap();
+        if warnings.len() < MAX_WARNINGS {
+            warnings.push(warning);
+        }
+    }
+
+    /// The warnings raised by this statement, up to MAX_WARNINGS of them
+    pub fn warnings(&self) -> Vec<Warning> {
+        self.warnings.lock().unwrap().clone()
+    }
+
+    /// The number of warnings raised by this statement, including any past MAX_WARNINGS
+    pub fn warning_count(&self) -> u64 {
+        self.warning_count.load(Ordering::Relaxed)
+    }
+
+    /// Records a cast that failed under strict_casts or a value that overflowed its type, only
+    /// the first failure is kept
+    pub fn cast_failed(&self, message: String) {
+        self.cast_error.lock().unwrap().get_or_insert(message);
+    }
+
+    /// Takes the failed cast recorded by cast_failed, executors check this as they go to fail
+    /// the statement
+    pub fn take_cast_error(&self) -> Option<String> {
+        self.cast_error.lock().unwrap().take()
+    }
+
+    /// Decodes text from a client or file under the session's invalid_utf8 policy
+    pub fn decode_utf8<'a>(&self, bytes: &'a [u8]) -> Result<Cow<'a, str>, usize> {
+        self.invalid_utf8.read().unwrap().decode(bytes)
+    }
+
+    /// Whether the query about to run should have its plan validated, picks plan_validation
+    /// percent of queries evenly spread out rather than at random so failures can be reproduced.
+    pub fn sample_plan_validation(&self) -> bool {
+        let percent = *self.plan_validation.read().unwrap() as u64;
+        if percent == 0 {
+            return false;
+        }
+        let count = self.plan_validation_count.fetch_add(1, Ordering::Relaxed);
+        (count + 1) * percent / 100 > count * percent / 100
+    }
+
+    /// The next value in this statement's random sequence, using splitmix64.
+    pub fn next_random(&self) -> u64 {
+        let mut z = self
+            .random_state
+            .fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::Relaxed)
+            .wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_random() {
+        let session = Session::new(1);
+        *session.seed.write().unwrap() = Some(42);
+        session.start_statement();
+        let first = (session.next_random(), session.next_random());
+        assert_ne!(first.0, first.1);
+
+        session.start_statement();
+        assert_eq!((session.next_random(), session.next_random()), first);
+    }
+
+    #[test]
+    fn test_sample_plan_validation() {
+        let session = Session::new(1);
+        assert!(!session.sample_plan_validation());
+
+        *session.plan_validation.write().unwrap() = 100;
+        assert!((0..10).all(|_| session.sample_plan_validation()));
+
+        *session.plan_validation.write().unwrap() = 25;
+        let sampled = (0..100)
+            .filter(|_| session.sample_plan_validation())
+            .count();
+        assert_eq!(sampled, 25);
+    }
+
+    #[test]
+    fn test_cast_error() {
+        let session = Session::new(1);
+        session.cast_failed("first".to_string());
+        session.cast_failed("second".to_string());
+        assert_eq!(session.take_cast_error(), Some("first".to_string()));
+        assert_eq!(session.take_cast_error(), None);
+
+        session.cast_failed("first".to_string());
+        session.start_statement();
+        assert_eq!(session.take_cast_error(), None);
+    }
+
+    #[test]
+    fn test_decode_utf8() {
+        let session = Session::new(1);
+        assert_eq!(session.decode_utf8(b"abc"), Ok(Cow::Borrowed("abc")));
+        assert_eq!(session.decode_utf8(b"ab\xffc"), Err(2));
+
+        *session.invalid_utf8.write().unwrap() = InvalidUtf8::Replace;
+        assert_eq!(
+            session.decode_utf8(b"ab\xffc"),
+            Ok(Cow::Owned::<str>("ab\u{FFFD}c".to_string()))
+        );
+        assert_eq!(
+            InvalidUtf8::from_name("REPLACE"),
+            Some(InvalidUtf8::Replace)
+        );
+        assert!(is_utf8_charset("utf8mb4"));
+        assert!(!is_utf8_charset("latin1"));
+    }
+
+    #[test]
+    fn test_warnings() {
+        let session = Session::new(1);
+        for idx in 0..(MAX_WARNINGS + 1) {
+            session.add_warning(Warning::new(WARN_GENERIC, idx.to_string()));
+        }
+        session.add_warning(Warning::note(WARN_DATA_TRUNCATED, "rounded".to_string()));
+        assert_eq!(session.warning_count(), MAX_WARNINGS as u64 + 2);
+        let warnings = session.warnings();
+        assert_eq!(warnings.len(), MAX_WARNINGS);
+        assert_eq!(warnings[0], Warning::new(WARN_GENERIC, "0".to_string()));
+
+        session.start_statement();
+        assert_eq!(session.warning_count(), 0);
+        assert_eq!(session.warnings(), vec![]);
+    }
+
+    #[test]
+    fn test_statement_timestamp_from_clock() {
+        let clock = Clock::new_virtual(60_000);
+        let session = Session::new_with_clock(1, clock.clone());
+        session.start_statement();
+        assert_eq!(
+            *session.statement_timestamp.read().unwrap(),
+            NaiveDateTime::from_timestamp(60, 0)
+        );
+
+        clock.advance(1_000);
+        session.start_statement();
+        assert_eq!(
+            *session.statement_timestamp.read().unwrap(),
+            NaiveDateTime::from_timestamp(61, 0)
+        );
+    }
 }
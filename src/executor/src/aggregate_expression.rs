@@ -1,6 +1,8 @@
-use crate::scalar_expression::EvalScalarRow;
-use crate::utils::{right_size_new, right_size_new_to};
+use crate::arena::{ArenaBuffers, ExprArena};
+use crate::scalar_expression::eval_args;
+use crate::utils::right_size_new_to;
 use ast::expr::{CompiledAggregate, CompiledColumnReference, Expression};
+use data::encoding_tuple::{decode_tuple, encode_tuple, DecodeError};
 use data::{DataType, Datum, Session};
 use functions::{Function, FunctionSignature};
 
@@ -71,38 +73,38 @@ impl AggregateExpression {
         }
     }
 
+    /// The number of arena buffers needed to finalize the expression
+    fn arena_size(&self) -> usize {
+        match self {
+            AggregateExpression::ScalarFunctionCall(funct) => {
+                1 + funct.args.iter().map(Self::arena_size).sum::<usize>()
+            }
+            _ => 0,
+        }
+    }
+
     /// Applies new inputs to the expression
     pub fn apply(
-        &mut self,
+        &self,
         session: &Session,
         row: &[Datum],
         freq: i64,
         state: &mut [Datum<'static>],
+        arena: &mut ExprArena,
     ) {
         match self {
             AggregateExpression::ScalarFunctionCall(funct) => {
                 let mut offset = 0_usize;
-                for sub_expr in funct.args.iter_mut() {
-                    sub_expr.apply(session, row, freq, &mut state[offset..]);
+                for sub_expr in funct.args.iter() {
+                    sub_expr.apply(session, row, freq, &mut state[offset..], arena);
                     offset += sub_expr.state_len();
                 }
             }
             AggregateExpression::CompiledAggregate(function_call) => {
-                // Eval sub exprs, see notes in scalar_expression.rs for notes.
-                if function_call.expr_buffer.len() != function_call.args.len() {
-                    function_call.expr_buffer = Box::from(right_size_new(&function_call.args))
-                }
-
-                let buf = unsafe {
-                    std::mem::transmute::<&mut Box<[Datum<'_>]>, &mut Box<[Datum<'_>]>>(
-                        &mut function_call.expr_buffer,
-                    )
-                };
-                function_call.args.eval_scalar(session, row, buf);
-
+                let args = eval_args(&function_call.args, session, row, arena);
                 function_call
                     .function
-                    .apply(&function_call.signature, &buf, freq, state)
+                    .apply(&function_call.signature, args, freq, state)
             }
             AggregateExpression::ColumnReference(column_ref) => {
                 // Grabs a copy of the column ref unless we've already set it
@@ -114,33 +116,115 @@ impl AggregateExpression {
         }
     }
 
-    /// Returns the output of the expression at the current time
-    pub fn finalize<'a>(&'a mut self, session: &Session, state: &'a [Datum<'a>]) -> Datum<'a> {
+    /// Merges the input state into state, ie the states of the same group built up from
+    /// different sources
+    pub fn merge(&self, input_state: &[Datum<'static>], state: &mut [Datum<'static>]) {
         match self {
-            AggregateExpression::Constant(datum, _) => datum.ref_clone(),
-            AggregateExpression::ScalarFunctionCall(function_call) => {
-                // Eval sub exprs, see notes in scalar_expression.rs for notes.
-                if function_call.expr_buffer.len() != function_call.args.len() {
-                    function_call.expr_buffer = Box::from(right_size_new(&function_call.args))
+            AggregateExpression::ScalarFunctionCall(funct) => {
+                let mut offset = 0_usize;
+                for arg in funct.args.iter() {
+                    arg.merge(&input_state[offset..], &mut state[offset..]);
+                    offset += arg.state_len();
+                }
+            }
+            AggregateExpression::CompiledAggregate(function_call) => {
+                function_call
+                    .function
+                    .merge(&function_call.signature, input_state, state)
+            }
+            AggregateExpression::ColumnReference(_) => {
+                if state[0].is_null() {
+                    state[0] = input_state[0].clone()
+                }
+            }
+            AggregateExpression::Constant(_, _) => {}
+        }
+    }
+
+    /// Appends the state to the buffer, each aggregate function writes its own state, see
+    /// AggregateFunction::serialize_state.
+    pub fn serialize_state(&self, state: &[Datum<'static>], buffer: &mut Vec<u8>) {
+        match self {
+            AggregateExpression::ScalarFunctionCall(funct) => {
+                let mut offset = 0_usize;
+                for arg in funct.args.iter() {
+                    arg.serialize_state(&state[offset..], buffer);
+                    offset += arg.state_len();
                 }
+            }
+            AggregateExpression::CompiledAggregate(funct) => {
+                funct.function.serialize_state(state, buffer);
+            }
+            AggregateExpression::Constant(_, _) => {}
+            AggregateExpression::ColumnReference(_) => {
+                encode_tuple(&state[..1], buffer);
+            }
+        }
+    }
 
-                let buf = unsafe {
-                    std::mem::transmute::<&mut Box<[Datum<'_>]>, &mut Box<[Datum<'_>]>>(
-                        &mut function_call.expr_buffer,
-                    )
-                };
+    /// Reads a state written by serialize_state into state, returning the rest of the buffer
+    pub fn deserialize_state<'a>(
+        &self,
+        buffer: &'a [u8],
+        state: &mut [Datum<'static>],
+    ) -> Result<&'a [u8], DecodeError> {
+        match self {
+            AggregateExpression::ScalarFunctionCall(funct) => {
+                let mut offset = 0_usize;
+                let mut rem = buffer;
+                for arg in funct.args.iter() {
+                    rem = arg.deserialize_state(rem, &mut state[offset..])?;
+                    offset += arg.state_len();
+                }
+                Ok(rem)
+            }
+            AggregateExpression::CompiledAggregate(funct) => {
+                funct.function.deserialize_state(buffer, state)
+            }
+            AggregateExpression::Constant(_, _) => Ok(buffer),
+            AggregateExpression::ColumnReference(_) => {
+                let (mut tuple, rem) = decode_tuple(buffer)?;
+                if tuple.len() != 1 {
+                    return Err(DecodeError::WrongDatumCount(1, tuple.len()));
+                }
+                state[0] = tuple.pop().unwrap();
+                Ok(rem)
+            }
+        }
+    }
+
+    /// Returns the output of the expression at the current time, the results of the scalar
+    /// functions over the aggregates are kept in the arena
+    pub fn finalize<'a>(
+        &'a self,
+        session: &Session,
+        state: &'a [Datum<'a>],
+        arena: &'a mut ExprArena,
+    ) -> Datum<'a> {
+        let mut buffers = arena.buffers(self.arena_size());
+        self.finalize_into(session, state, &mut buffers)
+    }
+
+    fn finalize_into<'a>(
+        &'a self,
+        session: &Session,
+        state: &'a [Datum<'a>],
+        buffers: &mut ArenaBuffers<'a>,
+    ) -> Datum<'a> {
+        match self {
+            AggregateExpression::Constant(datum, _) => datum.ref_clone(),
+            AggregateExpression::ScalarFunctionCall(function_call) => {
+                let args = buffers.take();
                 let mut offset = 0_usize;
-                for (idx, expr) in function_call.args.iter_mut().enumerate() {
-                    let state_len = expr.state_len();
-                    buf[idx] = expr.finalize(session, &state[offset..]);
-                    offset += state_len;
+                for expr in function_call.args.iter() {
+                    args.push(expr.finalize_into(session, &state[offset..], buffers));
+                    offset += expr.state_len();
                 }
+                let args: &'a [Datum<'a>] = args;
 
-                function_call.function.execute(
-                    session,
-                    &function_call.signature,
-                    &function_call.expr_buffer,
-                )
+                function_call
+                    .function
+                    .execute(session, &function_call.signature, args)
             }
             AggregateExpression::CompiledAggregate(function_call) => function_call
                 .function
@@ -156,8 +240,6 @@ impl AggregateExpression {
 pub struct ScalarFunctionCall {
     function: &'static dyn Function,
     args: Box<[AggregateExpression]>,
-    // Used to store the evaluation results of the sub expressions during execution
-    expr_buffer: Box<[Datum<'static>]>,
     signature: Box<FunctionSignature<'static>>,
 }
 
@@ -187,7 +269,6 @@ impl From<&Expression> for AggregateExpression {
                 AggregateExpression::ScalarFunctionCall(ScalarFunctionCall {
                     function: function.function,
                     args: args.into_boxed_slice(),
-                    expr_buffer: Box::from(vec![]),
                     signature: function.signature.clone(),
                 })
             }
@@ -195,9 +276,16 @@ impl From<&Expression> for AggregateExpression {
                 AggregateExpression::ColumnReference(column_ref.clone())
             }
 
-            Expression::FunctionCall(_) | Expression::ColumnReference(_) | Expression::Cast(_) => {
+            Expression::FunctionCall(_)
+            | Expression::ColumnReference(_)
+            | Expression::Cast(_)
+            | Expression::Subquery(_)
+            | Expression::Parameter(_) => {
                 panic!("Hit uncompiled expressions when converting to aggregation")
             }
+            Expression::WindowFunction(_) => {
+                panic!("Hit window function when converting to aggregation")
+            }
         }
     }
 }
@@ -213,12 +301,27 @@ pub trait EvalAggregateRow {
 
     fn state_len(&self) -> usize;
     fn reset(&self, state: &mut [Datum<'static>]);
-    fn apply(&mut self, session: &Session, row: &[Datum], freq: i64, state: &mut [Datum<'static>]);
+    fn apply(
+        &self,
+        session: &Session,
+        row: &[Datum],
+        freq: i64,
+        state: &mut [Datum<'static>],
+        arena: &mut ExprArena,
+    );
+    fn merge(&self, input_state: &[Datum<'static>], state: &mut [Datum<'static>]);
+    fn serialize_state(&self, state: &[Datum<'static>], buffer: &mut Vec<u8>);
+    fn deserialize_state<'a>(
+        &self,
+        buffer: &'a [u8],
+        state: &mut [Datum<'static>],
+    ) -> Result<&'a [u8], DecodeError>;
     fn finalize<'a>(
-        &'a mut self,
+        &'a self,
         session: &Session,
         state: &'a [Datum<'a>],
         target: &mut [Datum<'a>],
+        arena: &'a mut ExprArena,
     );
 }
 
@@ -237,25 +340,63 @@ impl EvalAggregateRow for [AggregateExpression] {
         }
     }
 
-    fn apply(&mut self, session: &Session, row: &[Datum], freq: i64, state: &mut [Datum<'static>]) {
+    fn apply(
+        &self,
+        session: &Session,
+        row: &[Datum],
+        freq: i64,
+        state: &mut [Datum<'static>],
+        arena: &mut ExprArena,
+    ) {
+        let mut offset = 0_usize;
+        for expr in self.iter() {
+            expr.apply(&session, row, freq, &mut state[offset..], arena);
+            offset += expr.state_len();
+        }
+    }
+
+    fn merge(&self, input_state: &[Datum<'static>], state: &mut [Datum<'static>]) {
+        let mut offset = 0_usize;
+        for expr in self.iter() {
+            expr.merge(&input_state[offset..], &mut state[offset..]);
+            offset += expr.state_len();
+        }
+    }
+
+    fn serialize_state(&self, state: &[Datum<'static>], buffer: &mut Vec<u8>) {
+        let mut offset = 0_usize;
+        for expr in self.iter() {
+            expr.serialize_state(&state[offset..], buffer);
+            offset += expr.state_len();
+        }
+    }
+
+    fn deserialize_state<'a>(
+        &self,
+        buffer: &'a [u8],
+        state: &mut [Datum<'static>],
+    ) -> Result<&'a [u8], DecodeError> {
         let mut offset = 0_usize;
-        for expr in self.iter_mut() {
-            expr.apply(&session, row, freq, &mut state[offset..]);
+        let mut rem = buffer;
+        for expr in self.iter() {
+            rem = expr.deserialize_state(rem, &mut state[offset..])?;
             offset += expr.state_len();
         }
+        Ok(rem)
     }
 
     fn finalize<'a>(
-        &'a mut self,
+        &'a self,
         session: &Session,
         state: &'a [Datum<'a>],
         target: &mut [Datum<'a>],
+        arena: &'a mut ExprArena,
     ) {
+        let mut buffers = arena.buffers(self.iter().map(AggregateExpression::arena_size).sum());
         let mut offset = 0_usize;
-        for (idx, expr) in self.iter_mut().enumerate() {
-            let state_len = expr.state_len();
-            target[idx] = expr.finalize(session, &state[offset..]);
-            offset += state_len;
+        for (idx, expr) in self.iter().enumerate() {
+            target[idx] = expr.finalize_into(session, &state[offset..], &mut buffers);
+            offset += expr.state_len();
         }
     }
 }
@@ -263,17 +404,20 @@ impl EvalAggregateRow for [AggregateExpression] {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::right_size_new;
     use ast::expr::{CompiledColumnReference, CompiledFunctionCall};
+    use data::rust_decimal::Decimal;
     use functions::registry::Registry;
 
     #[test]
     fn test_eval_constant() {
         let expression = Expression::from(1234);
         let session = Session::new(1);
+        let mut arena = ExprArena::default();
 
-        let mut agg_expression = AggregateExpression::from(&expression);
+        let agg_expression = AggregateExpression::from(&expression);
 
-        let result = agg_expression.finalize(&session, &[]);
+        let result = agg_expression.finalize(&session, &[], &mut arena);
 
         assert_eq!(result, Datum::from(1234));
     }
@@ -285,15 +429,16 @@ mod tests {
             datatype: DataType::Integer,
         });
         let session = Session::new(1);
+        let mut arena = ExprArena::default();
 
-        let mut agg_expression = AggregateExpression::from(&expression);
+        let agg_expression = AggregateExpression::from(&expression);
 
         let mut state = right_size_new_to(agg_expression.state_len());
         agg_expression.reset(&mut state);
-        agg_expression.apply(&session, &[Datum::from(1)], 1, &mut state);
-        agg_expression.apply(&session, &[Datum::Null], 2, &mut state);
+        agg_expression.apply(&session, &[Datum::from(1)], 1, &mut state, &mut arena);
+        agg_expression.apply(&session, &[Datum::Null], 2, &mut state, &mut arena);
 
-        let result = agg_expression.finalize(&session, &state);
+        let result = agg_expression.finalize(&session, &state, &mut arena);
 
         assert_eq!(result, Datum::from(1));
     }
@@ -315,19 +460,19 @@ mod tests {
                 },
             )]
             .into_boxed_slice(),
-            expr_buffer: vec![].into_boxed_slice(),
             signature: Box::new(sig),
         });
         let session = Session::new(1);
+        let mut arena = ExprArena::default();
 
-        let mut agg_expression = AggregateExpression::from(&expression);
+        let agg_expression = AggregateExpression::from(&expression);
 
         let mut state = right_size_new_to(agg_expression.state_len());
         agg_expression.reset(&mut state);
-        agg_expression.apply(&session, &[Datum::from(1)], 1, &mut state);
-        agg_expression.apply(&session, &[Datum::from(3)], 2, &mut state);
+        agg_expression.apply(&session, &[Datum::from(1)], 1, &mut state, &mut arena);
+        agg_expression.apply(&session, &[Datum::from(3)], 2, &mut state, &mut arena);
 
-        let result = agg_expression.finalize(&session, &state);
+        let result = agg_expression.finalize(&session, &state, &mut arena);
 
         assert_eq!(result, Datum::from(7));
     }
@@ -347,14 +492,14 @@ mod tests {
                 Expression::Constant(Datum::from(3), DataType::Integer),
             ]
             .into_boxed_slice(),
-            expr_buffer: vec![].into_boxed_slice(),
             signature: Box::new(sig),
         });
         let session = Session::new(1);
+        let mut arena = ExprArena::default();
 
-        let mut agg_expression = AggregateExpression::from(&expression);
+        let agg_expression = AggregateExpression::from(&expression);
 
-        let result = agg_expression.finalize(&session, &[]);
+        let result = agg_expression.finalize(&session, &[], &mut arena);
 
         assert_eq!(result, Datum::from(4));
     }
@@ -371,23 +516,93 @@ mod tests {
         });
         let expression3 = Expression::from(1234);
         let session = Session::new(1);
+        let mut arena = ExprArena::default();
 
-        let mut agg_expressions = vec![
+        let agg_expressions = vec![
             AggregateExpression::from(&expression1),
             AggregateExpression::from(&expression2),
             AggregateExpression::from(&expression3),
         ];
 
         let mut state = agg_expressions.initialize();
-        agg_expressions.apply(&session, &[Datum::from(1), Datum::Null], 1, &mut state);
-        agg_expressions.apply(&session, &[Datum::Null, Datum::from(5)], 2, &mut state);
+        agg_expressions.apply(
+            &session,
+            &[Datum::from(1), Datum::Null],
+            1,
+            &mut state,
+            &mut arena,
+        );
+        agg_expressions.apply(
+            &session,
+            &[Datum::Null, Datum::from(5)],
+            2,
+            &mut state,
+            &mut arena,
+        );
 
         let mut target = right_size_new(&agg_expressions);
-        agg_expressions.finalize(&session, &state, &mut target);
+        agg_expressions.finalize(&session, &state, &mut target, &mut arena);
 
         assert_eq!(
             target,
             vec![Datum::from(1), Datum::from(5), Datum::from(1234)]
         );
     }
+
+    #[test]
+    fn test_serialize_and_merge_row() {
+        let signature = FunctionSignature {
+            name: "avg",
+            args: vec![DataType::Integer],
+            ret: DataType::Null,
+        };
+        let (sig, function) = Registry::default().resolve_function(&signature).unwrap();
+        let column_ref = Expression::CompiledColumnReference(CompiledColumnReference {
+            offset: 0,
+            datatype: DataType::Integer,
+        });
+        let avg = Expression::CompiledAggregate(CompiledAggregate {
+            function: function.as_aggregate(),
+            args: vec![column_ref.clone()].into_boxed_slice(),
+            signature: Box::new(sig),
+        });
+        let session = Session::new(1);
+        let mut arena = ExprArena::default();
+
+        let agg_expressions = vec![
+            AggregateExpression::from(&column_ref),
+            AggregateExpression::from(&avg),
+            AggregateExpression::from(&Expression::from(1234)),
+        ];
+
+        let mut state = agg_expressions.initialize();
+        agg_expressions.apply(&session, &[Datum::from(1)], 1, &mut state, &mut arena);
+        agg_expressions.apply(&session, &[Datum::from(5)], 2, &mut state, &mut arena);
+
+        let mut buffer = vec![];
+        agg_expressions.serialize_state(&state, &mut buffer);
+
+        let mut restored = agg_expressions.initialize();
+        let rem = agg_expressions
+            .deserialize_state(&buffer, &mut restored)
+            .unwrap();
+        assert!(rem.is_empty());
+        assert_eq!(restored, state);
+
+        // Merged with the state of other rows for the same group
+        let mut other = agg_expressions.initialize();
+        agg_expressions.apply(&session, &[Datum::from(2)], 1, &mut other, &mut arena);
+        agg_expressions.merge(&other, &mut restored);
+
+        let mut target = right_size_new(&agg_expressions);
+        agg_expressions.finalize(&session, &restored, &mut target, &mut arena);
+        assert_eq!(
+            target,
+            vec![
+                Datum::from(1),
+                Datum::from(Decimal::new(13, 0) / Decimal::new(4, 0)),
+                Datum::from(1234)
+            ]
+        );
+    }
 }
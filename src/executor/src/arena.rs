@@ -0,0 +1,76 @@
+use data::Datum;
+use std::fmt::{Debug, Formatter};
+
+/// Holds the evaluated args of the function calls in an expression. Function results can borrow
+/// from their args (ie substring of a computed string) so the args have to live as long as the
+/// result does. Operators keep an arena each and reuse it row after row so the buffers are only
+/// allocated once.
+#[derive(Default)]
+pub struct ExprArena {
+    buffers: Vec<Vec<Datum<'static>>>,
+}
+
+// The datums left behind may borrow from rows that are long gone so they're never looked at
+impl Debug for ExprArena {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ExprArena")
+    }
+}
+
+impl ExprArena {
+    /// Clears out whatever the last evaluation left behind and hands out count empty buffers
+    /// that live as long as the arena's borrowed for.
+    pub fn buffers<'a>(&'a mut self, count: usize) -> ArenaBuffers<'a> {
+        if self.buffers.len() < count {
+            self.buffers.resize_with(count, Vec::new);
+        }
+        let buffers = &mut self.buffers[..count];
+        for buffer in buffers.iter_mut() {
+            buffer.clear();
+        }
+        // The buffers are empty so there's nothing 'static in them to be read back with a
+        // shorter lifetime, and what's written into them can only be read through this borrow
+        // as they're cleared again before being handed out the next time.
+        let buffers =
+            unsafe { &mut *(buffers as *mut [Vec<Datum<'static>>] as *mut [Vec<Datum<'a>>]) };
+        ArenaBuffers { buffers }
+    }
+}
+
+/// The buffers handed out by an arena, taken one by one in the order the function calls are
+/// evaluated.
+pub struct ArenaBuffers<'a> {
+    buffers: &'a mut [Vec<Datum<'a>>],
+}
+
+impl<'a> ArenaBuffers<'a> {
+    /// Takes the next buffer, the arena's sized up front so there's always one to take
+    pub fn take(&mut self) -> &'a mut Vec<Datum<'a>> {
+        let (first, rest) = std::mem::take(&mut self.buffers)
+            .split_first_mut()
+            .expect("Arena too small for the expression");
+        self.buffers = rest;
+        first
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arena() {
+        let mut arena = ExprArena::default();
+        let text = String::from("abc");
+        {
+            let mut buffers = arena.buffers(2);
+            buffers.take().push(Datum::from(text.as_str()));
+            buffers.take().push(Datum::from(1));
+        }
+        // Each evaluation starts from empty buffers
+        let mut buffers = arena.buffers(1);
+        assert!(buffers.take().is_empty());
+        assert_eq!(arena.buffers.len(), 2);
+        assert!(arena.buffers[0].is_empty());
+    }
+}
@@ -1,17 +1,32 @@
+use data::encoding_tuple::DecodeError;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 use storage::StorageError;
 
-mod aggregate_expression;
+pub mod aggregate_expression;
+mod arena;
 pub mod point_in_time;
 mod scalar_expression;
 mod utils;
 
+pub use arena::ExprArena;
+
 #[derive(Debug, Eq, PartialEq)]
 pub enum ExecutionError {
     StorageError(StorageError),
     IOError(String),
     DecodingError(String),
+    // Raised by the plan validation checks when an executor returns something it shouldn't
+    ValidationError(String),
+    // A plain cast of a value that couldn't be converted while strict_casts is on
+    CastError(String),
+    // A scalar subquery returned more than one row for a row of the query using it
+    SubqueryRowCount,
+    // The size of a value being inserted and the max_value_bytes it's bigger than
+    ValueTooLarge(usize, u64),
+    // The column and where and why its value doesn't match the column's json schema
+    JsonSchemaViolation(String, String),
+    Killed,
 }
 
 impl Error for ExecutionError {}
@@ -22,6 +37,20 @@ impl Display for ExecutionError {
             ExecutionError::StorageError(err) => Display::fmt(err, f),
             ExecutionError::IOError(err) => f.write_str(err),
             ExecutionError::DecodingError(err) => f.write_str(err),
+            ExecutionError::ValidationError(err) => f.write_str(err),
+            ExecutionError::CastError(err) => f.write_str(err),
+            ExecutionError::SubqueryRowCount => {
+                f.write_str("More than one row returned by a subquery used as an expression")
+            }
+            ExecutionError::ValueTooLarge(size, max) => f.write_fmt(format_args!(
+                "Value of {} bytes is bigger than max_value_bytes of {}",
+                size, max
+            )),
+            ExecutionError::JsonSchemaViolation(column, reason) => f.write_fmt(format_args!(
+                "Value for column {} doesn't match its json schema - {}",
+                column, reason
+            )),
+            ExecutionError::Killed => f.write_str("Query was killed"),
         }
     }
 }
@@ -38,8 +67,20 @@ impl From<std::io::Error> for ExecutionError {
     }
 }
 
+impl From<DecodeError> for ExecutionError {
+    fn from(err: DecodeError) -> Self {
+        ExecutionError::DecodingError(err.to_string())
+    }
+}
+
 impl From<csv::Error> for ExecutionError {
     fn from(err: csv::Error) -> Self {
         ExecutionError::DecodingError(err.to_string())
     }
 }
+
+impl From<parquet::errors::ParquetError> for ExecutionError {
+    fn from(err: parquet::errors::ParquetError) -> Self {
+        ExecutionError::DecodingError(err.to_string())
+    }
+}
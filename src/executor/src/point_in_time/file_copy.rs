@@ -0,0 +1,287 @@
+use crate::arena::ExprArena;
+use crate::point_in_time::file_scan::{open_decompressed, reject};
+use crate::point_in_time::table_insert::check_value_sizes;
+use crate::scalar_expression::EvalScalarRow;
+use crate::utils::{right_size_new, transmute_buf, transmute_muf_buf};
+use crate::ExecutionError;
+use ast::expr::Expression;
+use ast::rel::logical::{ErrorPolicy, SerdeOptions};
+use csv::{ByteRecord, Reader, StringRecord};
+use data::{Datum, InvalidUtf8, Session, TupleIter};
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+use storage::Table;
+
+/// Parses the records of a csv file straight into the columns of the table it's copied into.
+/// The columns are evaluated against the record's fields as text, with nulls for empty fields,
+/// casting them to the types of the table's columns. Compressed files are decompressed as
+/// they're read, as with file scans.
+/// Records with the wrong number of fields, invalid utf8 or fields that can't be cast are
+/// handled according to the error policy, io errors always fail the copy. Invalid utf8 is only
+/// an error if the session's invalid_utf8 policy doesn't replace it, as are values bigger than
+/// the session's max_value_bytes.
+pub struct FileCopyExecutor {
+    path: String,
+    serde_options: SerdeOptions,
+    session: Arc<Session>,
+    columns: Vec<Expression>,
+    dead_letters: Option<Table>,
+    // Opened on the first advance so a missing file fails the copy rather than the planning
+    reader: Option<Reader<Box<dyn Read>>>,
+    record: StringRecord,
+    // Records are read as bytes first when invalid utf8 is replaced
+    byte_record: ByteRecord,
+    // The record's fields, borrowed from the record
+    fields: Vec<Datum<'static>>,
+    tuple_buffer: Vec<Datum<'static>>,
+    done: bool,
+    arena: ExprArena,
+}
+
+impl FileCopyExecutor {
+    pub fn new(
+        session: Arc<Session>,
+        path: String,
+        serde_options: SerdeOptions,
+        columns: Vec<Expression>,
+        dead_letters: Option<Table>,
+    ) -> Self {
+        let fields = right_size_new(&columns);
+        let tuple_buffer = right_size_new(&columns);
+        FileCopyExecutor {
+            path,
+            serde_options,
+            session,
+            columns,
+            dead_letters,
+            reader: None,
+            record: StringRecord::new(),
+            byte_record: ByteRecord::new(),
+            fields,
+            tuple_buffer,
+            done: false,
+            arena: ExprArena::default(),
+        }
+    }
+
+    fn reject(&self, offset: i64, raw: String, error: String) -> Result<(), ExecutionError> {
+        reject(
+            &self.session,
+            self.serde_options.on_error,
+            &self.dead_letters,
+            &self.path,
+            offset,
+            raw,
+            error,
+        )
+    }
+
+    /// The record as it was in the file, for the dead letters table
+    fn raw_record(&self) -> String {
+        let delimiter = (self.serde_options.delimiter as char).to_string();
+        self.record.iter().collect::<Vec<_>>().join(&delimiter)
+    }
+
+    /// Finds the first field that was given but came out of its cast as null
+    fn invalid_field(&self) -> Option<usize> {
+        self.fields
+            .iter()
+            .zip(self.tuple_buffer.iter())
+            .position(|(field, column)| !field.is_null() && column.is_null())
+    }
+}
+
+impl TupleIter for FileCopyExecutor {
+    type E = ExecutionError;
+
+    // Like the file scan, the fields borrow from the record and the columns may borrow from the
+    // fields so we need to muddle with the lifetimes, the record is only replaced when we're
+    // advanced.
+    fn advance(&mut self) -> Result<(), Self::E> {
+        if self.done {
+            return Ok(());
+        }
+        if self.reader.is_none() {
+            let file = open_decompressed(Path::new(&self.path), 0)?;
+            self.reader = Some(
+                csv::ReaderBuilder::new()
+                    .has_headers(false)
+                    .flexible(true)
+                    .delimiter(self.serde_options.delimiter)
+                    .from_reader(file),
+            );
+        }
+
+        let invalid_utf8 = *self.session.invalid_utf8.read().unwrap();
+        let max_value_bytes = *self.session.max_value_bytes.read().unwrap();
+        loop {
+            let reader = self.reader.as_mut().unwrap();
+            let read_result = match invalid_utf8 {
+                InvalidUtf8::Error => reader.read_record(&mut self.record),
+                InvalidUtf8::Replace => {
+                    let read_result = reader.read_byte_record(&mut self.byte_record);
+                    self.record = StringRecord::from_byte_record_lossy(self.byte_record.clone());
+                    read_result
+                }
+            };
+            let offset = self.record.position().map_or(0, |p| p.byte() as i64);
+            match read_result {
+                Ok(false) => {
+                    self.done = true;
+                    return Ok(());
+                }
+                Ok(true) => {}
+                Err(err) if err.is_io_error() => return Err(err.into()),
+                Err(err) => {
+                    let offset = err.position().map_or(offset, |p| p.byte() as i64);
+                    self.reject(offset, String::new(), err.to_string())?;
+                    continue;
+                }
+            }
+
+            if self.record.len() != self.columns.len() {
+                self.reject(
+                    offset,
+                    self.raw_record(),
+                    format!(
+                        "Expected {} fields, found {}",
+                        self.columns.len(),
+                        self.record.len()
+                    ),
+                )?;
+                continue;
+            }
+
+            let fields = transmute_muf_buf(&mut self.fields);
+            for (datum, field) in fields.iter_mut().zip(self.record.iter()) {
+                *datum = if field.is_empty() {
+                    Datum::Null
+                } else {
+                    Datum::from(field)
+                };
+            }
+            self.columns.eval_scalar(
+                &self.session,
+                transmute_buf(&self.fields),
+                transmute_muf_buf(&mut self.tuple_buffer),
+                &mut self.arena,
+            );
+
+            match self.invalid_field() {
+                Some(idx) => {
+                    let error = format!(
+                        "Invalid value \"{}\" for column {}",
+                        &self.record[idx],
+                        idx + 1
+                    );
+                    self.reject(offset, self.raw_record(), error)?;
+                }
+                None => match check_value_sizes(&self.tuple_buffer, max_value_bytes) {
+                    Ok(()) => return Ok(()),
+                    Err(err) => self.reject(offset, String::new(), err.to_string())?,
+                },
+            }
+        }
+    }
+
+    fn get(&self) -> Option<(&[Datum], i64)> {
+        if self.done {
+            None
+        } else {
+            Some((transmute_buf(&self.tuple_buffer), 1))
+        }
+    }
+
+    fn column_count(&self) -> usize {
+        self.columns.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::expr::CompiledColumnReference;
+    use data::DataType;
+
+    fn text_columns(count: usize) -> Vec<Expression> {
+        (0..count)
+            .map(|offset| {
+                Expression::CompiledColumnReference(CompiledColumnReference {
+                    offset,
+                    datatype: DataType::Text,
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_file_copy() -> Result<(), ExecutionError> {
+        let mut executor = FileCopyExecutor::new(
+            Arc::new(Session::new(1)),
+            "../../test_data/csv/simple.csv".to_string(),
+            SerdeOptions::default(),
+            text_columns(3),
+            None,
+        );
+
+        assert_eq!(
+            executor.next()?,
+            Some((
+                [Datum::from("123"), Datum::from("abc"), Datum::from("12.1")].as_ref(),
+                1
+            ))
+        );
+        assert_eq!(
+            executor.next()?,
+            Some((
+                [Datum::from("456"), Datum::from("d,ef"), Datum::from("13.2")].as_ref(),
+                1
+            ))
+        );
+        assert_eq!(executor.next()?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_copy_rejected() -> Result<(), ExecutionError> {
+        let session = Arc::new(Session::new(1));
+        let serde_options = SerdeOptions {
+            on_error: ErrorPolicy::Skip,
+            ..SerdeOptions::default()
+        };
+        let mut executor = FileCopyExecutor::new(
+            Arc::clone(&session),
+            "../../test_data/csv/simple.csv".to_string(),
+            serde_options,
+            text_columns(2),
+            None,
+        );
+
+        assert_eq!(executor.next()?, None);
+        assert_eq!(session.warning_count(), 2);
+        assert_eq!(
+            session.warnings()[0].message,
+            "Row skipped, Expected 2 fields, found 3 (../../test_data/csv/simple.csv at byte 0)"
+        );
+
+        let mut executor = FileCopyExecutor::new(
+            Arc::new(Session::new(1)),
+            "../../test_data/csv/simple.csv".to_string(),
+            SerdeOptions::default(),
+            text_columns(2),
+            None,
+        );
+        assert!(executor.next().is_err());
+
+        let mut executor = FileCopyExecutor::new(
+            Arc::new(Session::new(1)),
+            "../../test_data/csv/missing.csv".to_string(),
+            SerdeOptions::default(),
+            text_columns(2),
+            None,
+        );
+        assert!(executor.next().is_err());
+        Ok(())
+    }
+}
@@ -1,38 +1,333 @@
+use crate::arena::ExprArena;
+use crate::point_in_time::parquet_files::{parquet_rows, projected_keys};
+use crate::scalar_expression::EvalScalarRow;
+use crate::utils::{right_size_new, transmute_buf, transmute_muf_buf};
 use crate::ExecutionError;
-use ast::rel::logical::SerdeOptions;
-use data::json::{JsonBuilder, OwnedJson};
-use data::{Datum, TupleIter};
-use std::iter::{empty, once};
-use std::path::PathBuf;
+use ast::expr::Expression;
+use ast::rel::logical::{ChangeFormat, ErrorPolicy, FileFormat, SerdeOptions};
+use csv::ByteRecord;
+use data::json::{Json, JsonBuilder, JsonType, OwnedJson};
+use data::{Datum, InvalidUtf8, Session, TupleIter, Warning, WARN_ROW_REJECTED};
+use flate2::bufread::MultiGzDecoder;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::iter::{empty, from_fn, once};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use storage::Table;
 
-/// Walks all the files in the directory reads them in as json.
+// How often we look to see if the source has been paused or resumed
+const PAUSE_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Walks all the files in the directory reads them in as json, gzip and zstd compressed files
+/// are decompressed as they're read.
+/// If we've been given columns they're evaluated against each document, otherwise the
+/// document itself is output. Parquet files only have the columns the paths look at read.
+/// Rows that can't be decoded are handled according to the error policy, io errors always
+/// fail the scan.
+/// When resuming, each file is read from the offset stored in the source offsets table and
+/// the offset after each row is left in the session for the insert to commit.
+/// Reads are throttled to the source's max_rows_per_second and wait while it's paused.
+/// Change streams load any snapshot directory first, then each change event is output as the
+/// rows it removes and adds with frequencies of -1 and +1.
 pub struct FileScanExecutor {
-    lines: Box<dyn Iterator<Item = Result<OwnedJson, ExecutionError>>>,
+    lines: Box<dyn Iterator<Item = Result<Line, ExecutionError>>>,
+    session: Arc<Session>,
+    columns: Vec<Expression>,
+    on_error: ErrorPolicy,
+    dead_letters: Option<Table>,
+    resume: bool,
+    throttle: Throttle,
+    // The rows left to output from the current change event
+    changes: std::vec::IntoIter<(OwnedJson, i64)>,
+    // Where the current line ends, recorded once all of its rows have been output
+    line_end: Option<(Arc<str>, i64)>,
+    freq: i64,
     tuple: [Datum<'static>; 1],
+    tuple_buffer: Vec<Datum<'static>>,
     done: bool,
+    arena: ExprArena,
+}
+
+/// A row read from a file along with where it came from.
+#[derive(Debug, PartialEq)]
+struct Line {
+    source: Arc<str>,
+    // Byte offsets of the start of the row and just past the end of it
+    start: i64,
+    end: i64,
+    content: Content,
+}
+
+/// Either a decoded document, the rows from a change event or the raw text of a row we
+/// couldn't decode.
+#[derive(Debug, PartialEq)]
+enum Content {
+    Document(OwnedJson),
+    Changes(Vec<(OwnedJson, i64)>),
+    Rejected { raw: String, error: String },
 }
 
 impl FileScanExecutor {
-    pub fn new(directory: String, serde_options: SerdeOptions) -> Self {
-        let file_entries = entries(PathBuf::from(directory));
+    pub fn new(
+        session: Arc<Session>,
+        directory: String,
+        serde_options: SerdeOptions,
+        columns: Vec<Expression>,
+        dead_letters: Option<Table>,
+        source_offsets: Option<Table>,
+        paused_sources: Option<Table>,
+    ) -> Self {
+        let snapshot_entries = match &serde_options.snapshot {
+            Some(snapshot) => entries(PathBuf::from(snapshot)),
+            None => Box::from(empty()),
+        };
+        let file_entries = entries(PathBuf::from(&directory));
+        let tuple_buffer = right_size_new(&columns);
+        let on_error = serde_options.on_error;
+        let resume = source_offsets.is_some();
+        let throttle = Throttle {
+            max_rows_per_second: serde_options.max_rows_per_second,
+            directory,
+            paused_sources,
+            started: Instant::now(),
+            rows: 0,
+            last_pause_check: None,
+        };
+
+        // The columns are evaluated against the rows of change events rather than the events
+        let keys = projected_keys(&columns);
+        let limits = Limits {
+            invalid_utf8: *session.invalid_utf8.read().unwrap(),
+            max_value_bytes: *session.max_value_bytes.read().unwrap(),
+        };
+        let file_keys = if serde_options.changes.is_none() {
+            keys.clone()
+        } else {
+            None
+        };
+
+        let snapshot_options = serde_options.clone();
+        let snapshot_offsets = source_offsets.clone();
+        let snapshot_lines = snapshot_entries
+            .flat_map(move |e| lines(e, &snapshot_options, &keys, limits, &snapshot_offsets));
+        let file_lines = file_entries.flat_map(move |e| {
+            let file_lines = lines(e, &serde_options, &file_keys, limits, &source_offsets);
+            match serde_options.changes {
+                Some(format) => Box::from(file_lines.map(move |line| {
+                    line.map(|mut line| {
+                        line.content = change_rows(format, line.content);
+                        line
+                    })
+                })),
+                None => file_lines,
+            }
+        });
 
         FileScanExecutor {
-            lines: Box::from(file_entries.flat_map(move |e| csv_lines(e, &serde_options))),
+            lines: Box::from(snapshot_lines.chain(file_lines)),
+            session,
+            columns,
+            on_error,
+            dead_letters,
+            resume,
+            throttle,
+            changes: vec![].into_iter(),
+            line_end: None,
+            freq: 1,
             tuple: [Datum::Null; 1],
+            tuple_buffer,
             done: false,
+            arena: ExprArena::default(),
+        }
+    }
+
+    fn reject(
+        &self,
+        source: &str,
+        offset: i64,
+        raw: String,
+        error: String,
+    ) -> Result<(), ExecutionError> {
+        reject(
+            &self.session,
+            self.on_error,
+            &self.dead_letters,
+            source,
+            offset,
+            raw,
+            error,
+        )
+    }
+
+    /// Once all the rows from a line have been output, the insert is free to commit its offset.
+    fn record_line_end(&mut self) {
+        if let Some((source, offset)) = self.line_end.take() {
+            if self.resume {
+                let mut pending = self.session.pending_source_offsets.lock().unwrap();
+                if let Some(pending_offset) = pending.get_mut(source.as_ref()) {
+                    *pending_offset = offset;
+                } else {
+                    pending.insert(source.to_string(), offset);
+                }
+            }
         }
     }
 }
 
+/// Handles a row that couldn't be decoded according to the error policy, failing the statement
+/// or skipping it with a warning and writing it to the dead letters table if there is one.
+pub(super) fn reject(
+    session: &Session,
+    on_error: ErrorPolicy,
+    dead_letters: &Option<Table>,
+    source: &str,
+    offset: i64,
+    raw: String,
+    error: String,
+) -> Result<(), ExecutionError> {
+    if on_error == ErrorPolicy::Fail {
+        return Err(ExecutionError::DecodingError(format!(
+            "{} ({} at byte {})",
+            error, source, offset
+        )));
+    }
+
+    session.add_warning(Warning::new(
+        WARN_ROW_REJECTED,
+        format!("Row skipped, {} ({} at byte {})", error, source, offset),
+    ));
+    if let Some(table) = dead_letters {
+        let logged_at = *session.statement_timestamp.read().unwrap();
+        let tuple = [
+            Datum::from(logged_at),
+            Datum::from(source),
+            Datum::from(offset),
+            Datum::from(raw),
+            Datum::from(error),
+        ];
+        table.atomic_write::<_, ExecutionError>(|batch| {
+            batch.write_tuple(table, &tuple, table.clock().now(), 1)?;
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
+/// Keeps the scan under its rate limit and holds it up while the source is paused.
+struct Throttle {
+    directory: String,
+    max_rows_per_second: Option<u64>,
+    paused_sources: Option<Table>,
+    started: Instant,
+    rows: u64,
+    last_pause_check: Option<Instant>,
+}
+
+impl Throttle {
+    fn wait(&mut self, session: &Session) -> Result<(), ExecutionError> {
+        self.wait_while_paused(session)?;
+        if let Some(max_rows_per_second) = self.max_rows_per_second {
+            let due = Duration::from_secs_f64(self.rows as f64 / max_rows_per_second as f64);
+            let elapsed = self.started.elapsed();
+            if due > elapsed {
+                sleep(due - elapsed);
+            }
+            self.rows += 1;
+        }
+        Ok(())
+    }
+
+    fn wait_while_paused(&mut self, session: &Session) -> Result<(), ExecutionError> {
+        let table = match &self.paused_sources {
+            Some(table) => table,
+            None => return Ok(()),
+        };
+        if matches!(self.last_pause_check, Some(last) if last.elapsed() < PAUSE_CHECK_INTERVAL) {
+            return Ok(());
+        }
+
+        let pk = [Datum::from(self.directory.as_str())];
+        let mut key_buf = vec![];
+        let mut rest = vec![];
+        let mut paused_at = None;
+        while table
+            .system_point_lookup(&pk, &mut key_buf, &mut rest)?
+            .is_some()
+        {
+            if session.kill_flag.load(Ordering::Relaxed) {
+                return Err(ExecutionError::Killed);
+            }
+            paused_at.get_or_insert_with(Instant::now);
+            sleep(PAUSE_CHECK_INTERVAL);
+        }
+        // Time spent paused doesn't count against the rate limit
+        if let Some(paused_at) = paused_at {
+            self.started += paused_at.elapsed();
+        }
+        self.last_pause_check = Some(Instant::now());
+        Ok(())
+    }
+}
+
 impl TupleIter for FileScanExecutor {
     type E = ExecutionError;
 
+    // Like the project, the columns may borrow from the document so we need to muddle with
+    // the lifetimes, the document is only replaced when we're advanced.
     fn advance(&mut self) -> Result<(), Self::E> {
-        if let Some(next) = self.lines.next() {
-            let line = next?;
-            self.tuple[0] = Datum::from(line);
-        } else {
-            self.done = true;
+        loop {
+            if let Some((document, freq)) = self.changes.next() {
+                if self.changes.len() == 0 {
+                    self.record_line_end();
+                }
+                self.tuple[0] = Datum::from(document);
+                self.freq = freq;
+                break;
+            }
+
+            match self.lines.next() {
+                Some(next) => {
+                    let line = next?;
+                    self.throttle.wait(&self.session)?;
+                    self.line_end = Some((Arc::clone(&line.source), line.end));
+                    match line.content {
+                        Content::Document(document) => {
+                            self.record_line_end();
+                            self.tuple[0] = Datum::from(document);
+                            self.freq = 1;
+                            break;
+                        }
+                        Content::Changes(changes) => {
+                            if changes.is_empty() {
+                                self.record_line_end();
+                            }
+                            self.changes = changes.into_iter();
+                        }
+                        Content::Rejected { raw, error } => {
+                            self.reject(&line.source, line.start, raw, error)?;
+                            self.record_line_end();
+                        }
+                    }
+                }
+                None => {
+                    self.done = true;
+                    return Ok(());
+                }
+            }
+        }
+
+        if !self.columns.is_empty() {
+            self.columns.eval_scalar(
+                &self.session,
+                &self.tuple,
+                transmute_muf_buf(&mut self.tuple_buffer),
+                &mut self.arena,
+            );
         }
         Ok(())
     }
@@ -40,19 +335,25 @@ impl TupleIter for FileScanExecutor {
     fn get(&self) -> Option<(&[Datum], i64)> {
         if self.done {
             None
+        } else if self.columns.is_empty() {
+            Some((&self.tuple, self.freq))
         } else {
-            Some((&self.tuple, 1))
+            Some((transmute_buf(&self.tuple_buffer), self.freq))
         }
     }
 
     fn column_count(&self) -> usize {
-        1
+        if self.columns.is_empty() {
+            1
+        } else {
+            self.columns.len()
+        }
     }
 }
 
 /// Returns a flattened iterator of all the files within a director
 /// horrible unwrapping and rewrapping of result types
-fn entries(entry: PathBuf) -> Box<dyn Iterator<Item = Result<PathBuf, std::io::Error>>> {
+pub(super) fn entries(entry: PathBuf) -> Box<dyn Iterator<Item = Result<PathBuf, std::io::Error>>> {
     if entry.is_file() {
         Box::from(once(Ok(entry)))
     } else if entry.is_dir() {
@@ -84,51 +385,560 @@ fn entries(entry: PathBuf) -> Box<dyn Iterator<Item = Result<PathBuf, std::io::E
 //     }
 // }
 
+/// Opens the file, if we're resuming it's positioned at the last offset we ingested up to.
+/// Returns the file, its name and the offset we started at.
+fn open(
+    entry: Result<PathBuf, std::io::Error>,
+    source_offsets: &Option<Table>,
+) -> Result<(Box<dyn Read>, Arc<str>, i64), ExecutionError> {
+    let entry = entry?;
+    let source: Arc<str> = Arc::from(entry.display().to_string());
+    let offset = resume_offset(&source, source_offsets)?;
+    let file = open_decompressed(&entry, offset as u64)?;
+    Ok((file, source, offset))
+}
+
+/// How a file is compressed, gzip and zstd files are decompressed as they're read
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Works out the compression from the file's extension, or failing that its first few bytes
+fn compression(path: &Path, file: &mut File) -> Result<Compression, std::io::Error> {
+    match path.extension().and_then(OsStr::to_str) {
+        Some("gz") | Some("gzip") => return Ok(Compression::Gzip),
+        Some("zst") | Some("zstd") => return Ok(Compression::Zstd),
+        _ => {}
+    }
+    let mut magic = Vec::with_capacity(ZSTD_MAGIC.len());
+    (&mut *file)
+        .take(ZSTD_MAGIC.len() as u64)
+        .read_to_end(&mut magic)?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(if magic.starts_with(&GZIP_MAGIC) {
+        Compression::Gzip
+    } else if magic.starts_with(&ZSTD_MAGIC) {
+        Compression::Zstd
+    } else {
+        Compression::None
+    })
+}
+
+/// Opens the file decompressing it if it's compressed, positioned at the offset. Offsets into
+/// compressed files are offsets into the decompressed bytes, there's no seeking to them so
+/// everything before them is decompressed and thrown away.
+pub(super) fn open_decompressed(path: &Path, offset: u64) -> Result<Box<dyn Read>, std::io::Error> {
+    let mut file = File::open(path)?;
+    let mut reader: Box<dyn Read> = match compression(path, &mut file)? {
+        Compression::None => {
+            if offset != 0 {
+                file.seek(SeekFrom::Start(offset))?;
+            }
+            return Ok(Box::new(file));
+        }
+        // Concatenated gzip files are still valid gzip files
+        Compression::Gzip => Box::new(MultiGzDecoder::new(BufReader::new(file))),
+        Compression::Zstd => Box::new(zstd::Decoder::new(file)?),
+    };
+    std::io::copy(&mut (&mut reader).take(offset), &mut std::io::sink())?;
+    Ok(reader)
+}
+
+/// The offset we last ingested the source up to or 0 if we're not resuming
+fn resume_offset(source: &str, source_offsets: &Option<Table>) -> Result<i64, ExecutionError> {
+    if let Some(table) = source_offsets {
+        let mut key_buf = vec![];
+        let mut rest = vec![];
+        if table
+            .system_point_lookup(&[Datum::from(source)], &mut key_buf, &mut rest)?
+            .is_some()
+        {
+            return Ok(rest[0].as_bigint());
+        }
+    }
+    Ok(0)
+}
+
+/// The session's rules for what's read from files, captured when the scan is created
+#[derive(Debug, Copy, Clone)]
+struct Limits {
+    invalid_utf8: InvalidUtf8,
+    // Records and documents bigger than this are rejected without being decoded
+    max_value_bytes: u64,
+}
+
+fn lines(
+    entry: Result<PathBuf, std::io::Error>,
+    serde_options: &SerdeOptions,
+    keys: &Option<Vec<String>>,
+    limits: Limits,
+    source_offsets: &Option<Table>,
+) -> Box<dyn Iterator<Item = Result<Line, ExecutionError>>> {
+    match serde_options.format {
+        FileFormat::Csv => csv_lines(entry, serde_options, limits, source_offsets),
+        FileFormat::Json => json_lines(entry, limits, source_offsets),
+        FileFormat::Parquet => parquet_lines(entry, keys, source_offsets),
+    }
+}
+
 fn csv_lines(
     entry: Result<PathBuf, std::io::Error>,
     serde_options: &SerdeOptions,
-) -> Box<dyn Iterator<Item = Result<OwnedJson, ExecutionError>>> {
-    match entry {
-        Ok(entry) => {
-            let mut builder = csv::ReaderBuilder::new();
-            builder.has_headers(false);
-            builder.delimiter(serde_options.delimiter);
-            let reader_result = builder.from_path(entry);
-            match reader_result {
-                Ok(reader) => Box::from(reader.into_records().map(|record_result| {
-                    record_result
-                        .map(|record| {
-                            JsonBuilder::default().array(|array| {
-                                for col in record.iter() {
-                                    array.push_string(col);
-                                }
-                            })
-                        })
-                        .map_err(ExecutionError::from)
-                })),
-                Err(e) => Box::from(once(Err(e.into()))),
+    limits: Limits,
+    source_offsets: &Option<Table>,
+) -> Box<dyn Iterator<Item = Result<Line, ExecutionError>>> {
+    let (file, source, base_offset) = match open(entry, source_offsets) {
+        Ok(opened) => opened,
+        Err(e) => return Box::from(once(Err(e))),
+    };
+    let delimiter = serde_options.delimiter;
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .delimiter(delimiter)
+        .from_reader(file);
+    let mut record = ByteRecord::new();
+
+    Box::from(from_fn(move || {
+        let read_result = reader.read_byte_record(&mut record);
+        let end = reader.position().byte() as i64 + base_offset;
+        let line = |start: Option<&csv::Position>, content| Line {
+            source: Arc::clone(&source),
+            start: start.map_or(0, |p| p.byte() as i64) + base_offset,
+            end,
+            content,
+        };
+        match read_result {
+            Ok(false) => None,
+            Ok(true) if record.as_slice().len() as u64 > limits.max_value_bytes => {
+                let content = Content::Rejected {
+                    raw: String::new(),
+                    error: format!(
+                        "Csv record of {} bytes is bigger than max_value_bytes of {}",
+                        record.as_slice().len(),
+                        limits.max_value_bytes
+                    ),
+                };
+                Some(Ok(line(record.position(), content)))
+            }
+            Ok(true) => {
+                let fields: Result<Vec<_>, _> = record
+                    .iter()
+                    .map(|field| limits.invalid_utf8.decode(field))
+                    .collect();
+                let content = match fields {
+                    Ok(fields) => Content::Document(JsonBuilder::default().array(|array| {
+                        for field in fields {
+                            array.push_string(&field);
+                        }
+                    })),
+                    Err(_) => {
+                        let fields: Vec<_> = record
+                            .iter()
+                            .map(|field| String::from_utf8_lossy(field).into_owned())
+                            .collect();
+                        Content::Rejected {
+                            raw: fields.join(&(delimiter as char).to_string()),
+                            error: "Invalid utf8 in csv record".to_string(),
+                        }
+                    }
+                };
+                Some(Ok(line(record.position(), content)))
+            }
+            Err(err) if err.is_io_error() => Some(Err(err.into())),
+            Err(err) => Some(Ok(line(
+                err.position(),
+                Content::Rejected {
+                    raw: String::new(),
+                    error: err.to_string(),
+                },
+            ))),
+        }
+    }))
+}
+
+/// Reads a file with one json document per line, blank lines are skipped.
+/// Lines longer than max_value_bytes are skipped over rather than read into memory.
+fn json_lines(
+    entry: Result<PathBuf, std::io::Error>,
+    limits: Limits,
+    source_offsets: &Option<Table>,
+) -> Box<dyn Iterator<Item = Result<Line, ExecutionError>>> {
+    let (file, source, mut offset) = match open(entry, source_offsets) {
+        Ok(opened) => opened,
+        Err(e) => return Box::from(once(Err(e))),
+    };
+    let mut reader = BufReader::new(file);
+
+    Box::from(from_fn(move || loop {
+        let mut buf = vec![];
+        // At most one byte past the limit (or the newline) is read
+        let limit = limits.max_value_bytes.saturating_add(1);
+        let mut len = match (&mut reader).take(limit).read_until(b'\n', &mut buf) {
+            Ok(0) => return None,
+            Ok(len) => len,
+            Err(e) => return Some(Err(e.into())),
+        };
+        let too_large = buf.last() != Some(&b'\n') && buf.len() as u64 > limits.max_value_bytes;
+        if too_large {
+            len += match skip_line(&mut reader) {
+                Ok(skipped) => skipped,
+                Err(e) => return Some(Err(e.into())),
+            };
+        }
+        let start = offset;
+        offset += len as i64;
+        if !too_large && buf.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        if buf.last() == Some(&b'\n') {
+            buf.pop();
+        }
+
+        let rejected = |raw, error: &str| Content::Rejected {
+            raw,
+            error: error.to_string(),
+        };
+        let content = if too_large {
+            let error = format!(
+                "Json document bigger than max_value_bytes of {}",
+                limits.max_value_bytes
+            );
+            rejected(String::new(), &error)
+        } else {
+            match limits.invalid_utf8.decode(&buf) {
+                Ok(line) => match OwnedJson::parse(&line) {
+                    Some(document) => Content::Document(document),
+                    None => rejected(line.into_owned(), "Invalid json document"),
+                },
+                Err(_) => rejected(
+                    String::from_utf8_lossy(&buf).into_owned(),
+                    "Invalid utf8 in json document",
+                ),
+            }
+        };
+        return Some(Ok(Line {
+            source: Arc::clone(&source),
+            start,
+            end: offset,
+            content,
+        }));
+    }))
+}
+
+/// Skips past the rest of the line without holding on to it, returning the number of bytes
+/// skipped including the newline
+fn skip_line(reader: &mut impl BufRead) -> Result<usize, std::io::Error> {
+    let mut skipped = 0;
+    loop {
+        let available = reader.fill_buf()?;
+        if available.is_empty() {
+            return Ok(skipped);
+        }
+        match available.iter().position(|b| *b == b'\n') {
+            Some(idx) => {
+                reader.consume(idx + 1);
+                return Ok(skipped + idx + 1);
             }
+            None => {
+                let len = available.len();
+                reader.consume(len);
+                skipped += len;
+            }
+        }
+    }
+}
+
+/// Reads a parquet file a row at a time, having no lines to speak of the offsets are row
+/// numbers rather than bytes.
+fn parquet_lines(
+    entry: Result<PathBuf, std::io::Error>,
+    keys: &Option<Vec<String>>,
+    source_offsets: &Option<Table>,
+) -> Box<dyn Iterator<Item = Result<Line, ExecutionError>>> {
+    let opened = entry.map_err(ExecutionError::from).and_then(|entry| {
+        let source: Arc<str> = Arc::from(entry.display().to_string());
+        let offset = resume_offset(&source, source_offsets)?;
+        let rows = parquet_rows(&entry, keys)?;
+        Ok((rows, source, offset))
+    });
+    let (rows, source, offset) = match opened {
+        Ok(opened) => opened,
+        Err(e) => return Box::from(once(Err(e))),
+    };
+
+    Box::from(
+        rows.enumerate()
+            .skip(offset as usize)
+            .map(move |(row_number, document)| {
+                Ok(Line {
+                    source: Arc::clone(&source),
+                    start: row_number as i64,
+                    end: row_number as i64 + 1,
+                    content: Content::Document(document),
+                })
+            }),
+    )
+}
+
+/// Turns a change event into the rows it removes and adds, null documents are the tombstones
+/// that follow deletes so don't change anything.
+fn change_rows(format: ChangeFormat, content: Content) -> Content {
+    let document = match content {
+        Content::Document(document) => document,
+        content => return content,
+    };
+    let event = document.as_json();
+    if event.is_null() {
+        return Content::Changes(vec![]);
+    }
+
+    let changes = match format {
+        ChangeFormat::Debezium => debezium_changes(event),
+        ChangeFormat::Maxwell => maxwell_changes(event),
+    };
+    match changes {
+        Ok(changes) => Content::Changes(changes),
+        Err(error) => Content::Rejected {
+            raw: format!("{:?}", document),
+            error,
+        },
+    }
+}
+
+/// Debezium events have the rows before and after the change, they may be wrapped in a
+/// schema/payload envelope.
+fn debezium_changes(mut event: Json) -> Result<Vec<(OwnedJson, i64)>, String> {
+    if let Some(payload) = object_field(event, "payload") {
+        event = payload;
+    }
+    let row = |key| {
+        object_field(event, key)
+            .map(OwnedJson::from)
+            .ok_or_else(|| format!("Change event has no {} row", key))
+    };
+
+    match object_field(event, "op").and_then(|op| op.get_string()) {
+        Some("c") | Some("r") => Ok(vec![(row("after")?, 1)]),
+        Some("u") => Ok(vec![(row("before")?, -1), (row("after")?, 1)]),
+        Some("d") => Ok(vec![(row("before")?, -1)]),
+        Some("t") => Err("Truncate events aren't supported".to_string()),
+        Some(op) => Err(format!("Unknown change event op {}", op)),
+        None => Err("Change event has no op".to_string()),
+    }
+}
+
+/// Maxwell events have the row after the change as data, updates only hold the old values of
+/// the columns that changed so the row before is data with those laid over the top.
+fn maxwell_changes(event: Json) -> Result<Vec<(OwnedJson, i64)>, String> {
+    let data = object_field(event, "data").ok_or("Change event has no data row")?;
+
+    match object_field(event, "type").and_then(|op| op.get_string()) {
+        Some("insert") | Some("bootstrap-insert") => Ok(vec![(OwnedJson::from(data), 1)]),
+        Some("update") => {
+            let old = object_field(event, "old").ok_or("Update event has no old values")?;
+            let before = JsonBuilder::default().object(|object| {
+                for (key, value) in data.iter_object().into_iter().flatten() {
+                    let old_value = old
+                        .iter_object()
+                        .into_iter()
+                        .flatten()
+                        .find(|(k, _)| *k == key);
+                    object.push_json(key, old_value.map_or(value, |(_, old_value)| old_value));
+                }
+            });
+            Ok(vec![(before, -1), (OwnedJson::from(data), 1)])
         }
-        Err(e) => Box::from(once(Err(e.into()))),
+        Some("delete") => Ok(vec![(OwnedJson::from(data), -1)]),
+        // Maxwell marks the start and end of a bootstrap, neither carry any rows
+        Some("bootstrap-start") | Some("bootstrap-complete") => Ok(vec![]),
+        Some(op) => Err(format!("Unknown change event type {}", op)),
+        None => Err("Change event has no type".to_string()),
     }
 }
 
+/// The value of the key if the json is an object and the value is an object or string.
+fn object_field<'a>(json: Json<'a>, key: &str) -> Option<Json<'a>> {
+    json.iter_object()?
+        .find(|(k, _)| *k == key)
+        .map(|(_, value)| value)
+        .filter(|value| matches!(value.json_type(), JsonType::Object | JsonType::String))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use data::DEFAULT_MAX_VALUE_BYTES;
+    use std::io::Write;
+
+    fn limits(invalid_utf8: InvalidUtf8) -> Limits {
+        Limits {
+            invalid_utf8,
+            max_value_bytes: DEFAULT_MAX_VALUE_BYTES,
+        }
+    }
+
+    #[test]
+    fn test_json_lines() -> Result<(), ExecutionError> {
+        let path = PathBuf::from("../../test_data/json/people.json");
+
+        let mut line_iter = json_lines(Ok(path), limits(InvalidUtf8::Error), &None);
+
+        let expected_line1 = OwnedJson::parse(
+            r#"{"name":"alice","age":34,"address":{"city":"Wanaka","postcode":"9305"},"joined":"2019-02-03"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            line_iter.next().unwrap()?.content,
+            Content::Document(expected_line1)
+        );
+        assert_eq!(line_iter.count(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_utf8_lines() -> Result<(), ExecutionError> {
+        let path = std::env::temp_dir().join(format!("incresql-utf8-{}", std::process::id()));
+        std::fs::write(&path, b"{\"a\":\"b\xffc\"}\n")?;
+
+        let line = json_lines(Ok(path.clone()), limits(InvalidUtf8::Error), &None)
+            .next()
+            .unwrap()?;
+        assert_eq!(
+            line.content,
+            Content::Rejected {
+                raw: "{\"a\":\"b\u{FFFD}c\"}".to_string(),
+                error: "Invalid utf8 in json document".to_string()
+            }
+        );
+        let line = json_lines(Ok(path.clone()), limits(InvalidUtf8::Replace), &None)
+            .next()
+            .unwrap()?;
+        let expected = OwnedJson::parse("{\"a\":\"b\u{FFFD}c\"}").unwrap();
+        assert_eq!(line.content, Content::Document(expected));
+
+        std::fs::write(&path, b"a,b\xff\n")?;
+        let options = SerdeOptions::default();
+        let line = csv_lines(
+            Ok(path.clone()),
+            &options,
+            limits(InvalidUtf8::Replace),
+            &None,
+        )
+        .next()
+        .unwrap()?;
+        let expected = OwnedJson::parse("[\"a\",\"b\u{FFFD}\"]").unwrap();
+        assert_eq!(line.content, Content::Document(expected));
+
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_compressed_lines() -> Result<(), ExecutionError> {
+        let contents = b"{\"a\":1}\n{\"a\":2}\n";
+        let directory = std::env::temp_dir();
+        let gzip_path = directory.join(format!("incresql-{}.json.gz", std::process::id()));
+        let mut encoder =
+            flate2::write::GzEncoder::new(File::create(&gzip_path)?, Default::default());
+        encoder.write_all(contents)?;
+        encoder.finish()?;
+        // Without an extension zstd files are picked up by their magic bytes
+        let zstd_path = directory.join(format!("incresql-zstd-{}", std::process::id()));
+        std::fs::write(&zstd_path, zstd::encode_all(contents.as_ref(), 0)?)?;
+
+        for path in &[gzip_path, zstd_path] {
+            let lines: Vec<_> = json_lines(Ok(path.clone()), limits(InvalidUtf8::Error), &None)
+                .collect::<Result<_, _>>()?;
+            assert_eq!(lines.len(), 2);
+            assert_eq!((lines[1].start, lines[1].end), (8, 16));
+            assert_eq!(
+                lines[1].content,
+                Content::Document(OwnedJson::parse(r#"{"a":2}"#).unwrap())
+            );
+
+            // Resuming skips over the decompressed bytes
+            let mut reader = open_decompressed(path, 8)?;
+            let mut rest = String::new();
+            reader.read_to_string(&mut rest)?;
+            assert_eq!(rest, "{\"a\":2}\n");
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_value_bytes_lines() -> Result<(), ExecutionError> {
+        let path = std::env::temp_dir().join(format!("incresql-max-bytes-{}", std::process::id()));
+        std::fs::write(&path, b"[1,2,3,4,5]\n[1]\n")?;
+        let limits = Limits {
+            invalid_utf8: InvalidUtf8::Error,
+            max_value_bytes: 5,
+        };
+
+        // The long line is skipped without being read in, the next one still starts after it
+        let lines: Vec<_> =
+            json_lines(Ok(path.clone()), limits, &None).collect::<Result<_, _>>()?;
+        assert_eq!(
+            lines[0].content,
+            Content::Rejected {
+                raw: String::new(),
+                error: "Json document bigger than max_value_bytes of 5".to_string()
+            }
+        );
+        assert_eq!((lines[0].end, lines[1].start), (12, 12));
+        assert_eq!(
+            lines[1].content,
+            Content::Document(OwnedJson::parse("[1]").unwrap())
+        );
+
+        std::fs::write(&path, b"abc,def\na,b\n")?;
+        let options = SerdeOptions::default();
+        let lines: Vec<_> =
+            csv_lines(Ok(path.clone()), &options, limits, &None).collect::<Result<_, _>>()?;
+        assert_eq!(
+            lines[0].content,
+            Content::Rejected {
+                raw: String::new(),
+                error: "Csv record of 6 bytes is bigger than max_value_bytes of 5".to_string()
+            }
+        );
+        assert_eq!(
+            lines[1].content,
+            Content::Document(OwnedJson::parse(r#"["a","b"]"#).unwrap())
+        );
+
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
 
     #[test]
     fn test_csv_lines() -> Result<(), ExecutionError> {
         let path = PathBuf::from("../../test_data/csv/simple.csv");
 
-        let mut line_iter = csv_lines(Ok(path), &SerdeOptions::default());
+        let mut line_iter = csv_lines(
+            Ok(path),
+            &SerdeOptions::default(),
+            limits(InvalidUtf8::Error),
+            &None,
+        );
 
         let expected_line1 = OwnedJson::parse(r#"["123","abc","12.1"]"#).unwrap();
         let expected_line2 = OwnedJson::parse(r#"["456","d,ef","13.2"]"#).unwrap();
 
-        assert_eq!(line_iter.next().unwrap().unwrap(), expected_line1);
-        assert_eq!(line_iter.next().unwrap().unwrap(), expected_line2);
-        assert_eq!(line_iter.next(), None);
+        let line1 = line_iter.next().unwrap()?;
+        assert_eq!(line1.content, Content::Document(expected_line1));
+        assert_eq!((line1.start, line1.end), (0, 13));
+        let line2 = line_iter.next().unwrap()?;
+        assert_eq!(line2.content, Content::Document(expected_line2));
+        assert_eq!(line2.start, 13);
+        assert!(line_iter.next().is_none());
 
         Ok(())
     }
@@ -137,7 +947,15 @@ mod tests {
     fn test_single_csv() -> Result<(), ExecutionError> {
         let directory = "../../test_data/csv/simple.csv".to_string();
 
-        let mut executor = FileScanExecutor::new(directory, SerdeOptions::default());
+        let mut executor = FileScanExecutor::new(
+            Arc::new(Session::new(1)),
+            directory,
+            SerdeOptions::default(),
+            vec![],
+            None,
+            None,
+            None,
+        );
 
         let expected_line1 = OwnedJson::parse(r#"["123","abc","12.1"]"#).unwrap();
         let expected_line2 = OwnedJson::parse(r#"["456","d,ef","13.2"]"#).unwrap();
@@ -159,7 +977,15 @@ mod tests {
     fn test_csv_director() -> Result<(), ExecutionError> {
         let directory = "../../test_data/csv".to_string();
 
-        let mut executor = FileScanExecutor::new(directory, SerdeOptions::default());
+        let mut executor = FileScanExecutor::new(
+            Arc::new(Session::new(1)),
+            directory,
+            SerdeOptions::default(),
+            vec![],
+            None,
+            None,
+            None,
+        );
 
         let expected_line1 = OwnedJson::parse(r#"["123","abc","12.1"]"#).unwrap();
 
@@ -171,4 +997,197 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_json_lines_rejected() -> Result<(), ExecutionError> {
+        let path = PathBuf::from("../../test_data/bad_json/events.json");
+
+        let lines: Result<Vec<_>, _> =
+            json_lines(Ok(path), limits(InvalidUtf8::Error), &None).collect();
+        let source: Arc<str> = Arc::from("../../test_data/bad_json/events.json");
+
+        assert_eq!(
+            lines?,
+            vec![
+                Line {
+                    source: Arc::clone(&source),
+                    start: 0,
+                    end: 10,
+                    content: Content::Document(OwnedJson::parse(r#"{"id":1}"#).unwrap())
+                },
+                Line {
+                    source: Arc::clone(&source),
+                    start: 10,
+                    end: 19,
+                    content: Content::Rejected {
+                        raw: "not json".to_string(),
+                        error: "Invalid json document".to_string()
+                    }
+                },
+                Line {
+                    source,
+                    start: 19,
+                    end: 29,
+                    content: Content::Document(OwnedJson::parse(r#"{"id":3}"#).unwrap())
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_error_policies() -> Result<(), ExecutionError> {
+        let directory = "../../test_data/bad_json".to_string();
+        let session = Arc::new(Session::new(1));
+        let serde_options = |on_error| SerdeOptions {
+            delimiter: b',',
+            format: FileFormat::Json,
+            on_error,
+            resume: false,
+            max_rows_per_second: None,
+            max_batch_rows: None,
+            changes: None,
+            snapshot: None,
+        };
+
+        let mut executor = FileScanExecutor::new(
+            Arc::clone(&session),
+            directory.clone(),
+            serde_options(ErrorPolicy::Fail),
+            vec![],
+            None,
+            None,
+            None,
+        );
+        assert!(executor.next().is_ok());
+        assert_eq!(
+            executor.next(),
+            Err(ExecutionError::DecodingError(
+                "Invalid json document (../../test_data/bad_json/events.json at byte 10)"
+                    .to_string()
+            ))
+        );
+
+        let mut executor = FileScanExecutor::new(
+            Arc::clone(&session),
+            directory,
+            serde_options(ErrorPolicy::Skip),
+            vec![],
+            None,
+            None,
+            None,
+        );
+        let mut count = 0;
+        while executor.next()?.is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 2);
+        assert_eq!(session.warning_count(), 1);
+        assert_eq!(session.warnings()[0].code, WARN_ROW_REJECTED);
+        Ok(())
+    }
+
+    #[test]
+    fn test_debezium_changes() {
+        let changes = |event: &str| {
+            change_rows(
+                ChangeFormat::Debezium,
+                Content::Document(OwnedJson::parse(event).unwrap()),
+            )
+        };
+        let row = |row: &str| OwnedJson::parse(row).unwrap();
+
+        assert_eq!(
+            changes(r#"{"op": "r", "before": null, "after": {"id": 1}}"#),
+            Content::Changes(vec![(row(r#"{"id": 1}"#), 1)])
+        );
+        assert_eq!(
+            changes(r#"{"payload": {"op": "u", "before": {"id": 1}, "after": {"id": 2}}}"#),
+            Content::Changes(vec![(row(r#"{"id": 1}"#), -1), (row(r#"{"id": 2}"#), 1)])
+        );
+        assert_eq!(
+            changes(r#"{"op": "d", "before": {"id": 1}, "after": null}"#),
+            Content::Changes(vec![(row(r#"{"id": 1}"#), -1)])
+        );
+        assert_eq!(changes("null"), Content::Changes(vec![]));
+        assert_eq!(
+            changes(r#"{"op": "d", "after": {"id": 1}}"#),
+            Content::Rejected {
+                raw: r#"{"op":"d","after":{"id":1}}"#.to_string(),
+                error: "Change event has no before row".to_string()
+            }
+        );
+        assert_eq!(
+            changes(r#"{"op": "t"}"#),
+            Content::Rejected {
+                raw: r#"{"op":"t"}"#.to_string(),
+                error: "Truncate events aren't supported".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_maxwell_changes() {
+        let changes = |event: &str| {
+            change_rows(
+                ChangeFormat::Maxwell,
+                Content::Document(OwnedJson::parse(event).unwrap()),
+            )
+        };
+        let row = |row: &str| OwnedJson::parse(row).unwrap();
+
+        assert_eq!(
+            changes(r#"{"type": "insert", "data": {"id": 1, "name": "a"}}"#),
+            Content::Changes(vec![(row(r#"{"id": 1, "name": "a"}"#), 1)])
+        );
+        assert_eq!(
+            changes(r#"{"type": "update", "data": {"id": 1, "name": "b"}, "old": {"name": "a"}}"#),
+            Content::Changes(vec![
+                (row(r#"{"id": 1, "name": "a"}"#), -1),
+                (row(r#"{"id": 1, "name": "b"}"#), 1)
+            ])
+        );
+        assert_eq!(
+            changes(r#"{"type": "delete", "data": {"id": 1, "name": "b"}}"#),
+            Content::Changes(vec![(row(r#"{"id": 1, "name": "b"}"#), -1)])
+        );
+        assert_eq!(
+            changes(r#"{"type": "bootstrap-start", "data": {}}"#),
+            Content::Changes(vec![])
+        );
+        assert_eq!(
+            changes(r#"{"type": "table-create", "data": {}}"#),
+            Content::Rejected {
+                raw: r#"{"type":"table-create","data":{}}"#.to_string(),
+                error: "Unknown change event type table-create".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_snapshot_then_changes() -> Result<(), ExecutionError> {
+        let serde_options = SerdeOptions {
+            format: FileFormat::Json,
+            changes: Some(ChangeFormat::Debezium),
+            snapshot: Some("../../test_data/cdc/snapshot".to_string()),
+            ..SerdeOptions::default()
+        };
+        let mut executor = FileScanExecutor::new(
+            Arc::new(Session::new(1)),
+            "../../test_data/cdc/changes".to_string(),
+            serde_options,
+            vec![],
+            None,
+            None,
+            None,
+        );
+
+        let mut freqs = vec![];
+        while let Some((_, freq)) = executor.next()? {
+            freqs.push(freq);
+        }
+        // 2 snapshot rows, then a create, an update, a delete, a tombstone and another create
+        assert_eq!(freqs, vec![1, 1, 1, -1, 1, -1, 1]);
+        Ok(())
+    }
 }
@@ -1,3 +1,4 @@
+use crate::arena::ExprArena;
 use crate::point_in_time::BoxedExecutor;
 use crate::scalar_expression::EvalScalar;
 use crate::ExecutionError;
@@ -9,6 +10,7 @@ pub struct FilterExecutor {
     source: BoxedExecutor,
     session: Arc<Session>,
     predicate: Expression,
+    arena: ExprArena,
 }
 
 impl FilterExecutor {
@@ -17,6 +19,7 @@ impl FilterExecutor {
             source,
             session,
             predicate,
+            arena: ExprArena::default(),
         }
     }
 }
@@ -25,7 +28,11 @@ impl TupleIter for FilterExecutor {
     type E = ExecutionError;
     fn advance(&mut self) -> Result<(), ExecutionError> {
         while let Some((tuple, _freq)) = self.source.next()? {
-            if self.predicate.eval_scalar(&self.session, tuple) == Datum::from(true) {
+            if self
+                .predicate
+                .eval_scalar(&self.session, tuple, &mut self.arena)
+                == Datum::from(true)
+            {
                 break;
             }
         }
@@ -1,4 +1,5 @@
 use crate::aggregate_expression::{AggregateExpression, EvalAggregateRow};
+use crate::arena::ExprArena;
 use crate::point_in_time::BoxedExecutor;
 use crate::utils::{right_size_new, transmute_muf_buf};
 use crate::ExecutionError;
@@ -22,6 +23,7 @@ pub struct HashGroupExecutor {
     output_state: Vec<Datum<'static>>,
     output_tuple: Vec<Datum<'static>>,
     done: bool,
+    arena: ExprArena,
 }
 
 impl HashGroupExecutor {
@@ -43,6 +45,7 @@ impl HashGroupExecutor {
             output_tuple,
             output_state: vec![],
             done: false,
+            arena: ExprArena::default(),
         }
     }
 }
@@ -60,13 +63,14 @@ impl TupleIter for HashGroupExecutor {
                 }
 
                 if let Some(state) = self.state.get_mut(&key_buf) {
-                    self.expressions.apply(&self.session, tuple, freq, state);
+                    self.expressions
+                        .apply(&self.session, tuple, freq, state, &mut self.arena);
                 } else {
                     let mut key = vec![];
                     std::mem::swap(&mut key, &mut key_buf);
                     let mut state = self.expressions.initialize();
                     self.expressions
-                        .apply(&self.session, tuple, freq, &mut state);
+                        .apply(&self.session, tuple, freq, &mut state, &mut self.arena);
                     self.state.insert(key, state);
                 }
             }
@@ -85,6 +89,7 @@ impl TupleIter for HashGroupExecutor {
                 &self.session,
                 &self.output_state,
                 transmute_muf_buf(&mut self.output_tuple),
+                &mut self.arena,
             );
         } else {
             self.done = true;
@@ -153,7 +158,6 @@ mod tests {
                     },
                 )]
                 .into_boxed_slice(),
-                expr_buffer: vec![].into_boxed_slice(),
                 signature: Box::new(sig),
             }),
         ];
@@ -1,3 +1,4 @@
+use crate::arena::ExprArena;
 use crate::point_in_time::BoxedExecutor;
 use crate::scalar_expression::EvalScalar;
 use crate::utils::{right_size_new_to, transmute_muf_buf};
@@ -5,15 +6,19 @@ use crate::ExecutionError;
 use ast::expr::Expression;
 use ast::rel::logical::JoinType;
 use data::{Datum, Session, TupleIter};
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::slice::Iter;
 use std::sync::Arc;
+use std::vec::IntoIter;
 
 /// A hash join takes 2 inputs left and right with the join keys being the first key_len
 /// columns being the equi join condition.  Any non-equi-join conditions can be filtered
 /// by a downstream filter executor.
 /// The right input will be fully consumed first to populate the hashtable.
 /// The output rows will be a combination of left and right.
+/// For full outer joins the right rows that never matched are output null padded once the left
+/// input has been exhausted.
 pub struct HashJoinExecutor {
     left: BoxedExecutor,
     right: BoxedExecutor,
@@ -23,14 +28,20 @@ pub struct HashJoinExecutor {
     left_len: usize,
     join_type: JoinType,
     hash_table: Option<HashMap<Vec<Datum<'static>>, Bucket>>,
+    // Right rows with a null join key, these never match but full outer joins still output them
+    null_keyed: Vec<(Vec<Datum<'static>>, i64)>,
+    unmatched_right: Option<IntoIter<(Vec<Datum<'static>>, i64)>>,
     tuple_buf: Vec<Datum<'static>>,
     left_freq: i64,
     freq: i64,
-    bucket_iter: Iter<'static, (Vec<Datum<'static>>, i64)>,
+    bucket_iter: Iter<'static, BucketEntry>,
     done: bool,
+    arena: ExprArena,
 }
 
-type Bucket = Vec<(Vec<Datum<'static>>, i64)>;
+/// The non key columns of a right row, its freq and whether any left row has matched it
+type BucketEntry = (Vec<Datum<'static>>, i64, Cell<bool>);
+type Bucket = Vec<BucketEntry>;
 
 impl HashJoinExecutor {
     /// Creates a new hash join executor, due to join conditions for left outer joins
@@ -55,11 +66,14 @@ impl HashJoinExecutor {
             left_len,
             join_type,
             hash_table: None,
+            null_keyed: vec![],
+            unmatched_right: None,
             tuple_buf,
             left_freq: 0,
             freq: 0,
             bucket_iter: [].iter(),
             done: false,
+            arena: ExprArena::default(),
         }
     }
 }
@@ -75,13 +89,35 @@ impl TupleIter for HashJoinExecutor {
         // The offset where we must write the non-key columns out to.
         let right_offset = self.left.column_count() + self.key_len;
 
+        // Once the left side is exhausted a full outer join outputs the unmatched right rows.
+        if let Some(unmatched_right) = &mut self.unmatched_right {
+            if let Some((right_tuple, freq)) = unmatched_right.next() {
+                let buf = transmute_muf_buf(&mut self.tuple_buf);
+                for d in &mut buf[..(self.left_len)] {
+                    *d = Datum::Null;
+                }
+                for (idx, datum) in right_tuple.into_iter().enumerate() {
+                    buf[self.left_len + idx] = datum;
+                }
+                self.freq = freq;
+            } else {
+                self.done = true;
+            }
+            return Ok(());
+        }
+
         // If we're part way through iterating through a bucket lets carry on.
-        while let Some((right_tuple, freq)) = self.bucket_iter.next() {
+        while let Some((right_tuple, freq, matched)) = self.bucket_iter.next() {
             let buf = transmute_muf_buf(&mut self.tuple_buf);
             for (idx, datum) in right_tuple.iter().enumerate() {
                 buf[right_offset + idx] = datum.ref_clone();
             }
-            if self.non_equi_condition.eval_scalar(&self.session, buf) == Datum::from(true) {
+            if self
+                .non_equi_condition
+                .eval_scalar(&self.session, buf, &mut self.arena)
+                == Datum::from(true)
+            {
+                matched.set(true);
                 self.freq = *freq * self.left_freq;
                 return Ok(());
             }
@@ -98,6 +134,10 @@ impl TupleIter for HashJoinExecutor {
                 if key.iter().any(Datum::is_null) {
                     // If any of the join keys are null we don't want to put into
                     // the join.
+                    if self.join_type == JoinType::FullOuter {
+                        self.null_keyed
+                            .push((tuple.iter().map(Datum::as_static).collect(), freq));
+                    }
                     continue;
                 }
                 let rest = tuple[(self.key_len)..]
@@ -106,7 +146,7 @@ impl TupleIter for HashJoinExecutor {
                     .collect();
 
                 let bucket = hash_table.entry(key).or_default();
-                bucket.push((rest, freq));
+                bucket.push((rest, freq, Cell::new(false)));
             }
             self.hash_table = Some(hash_table);
         }
@@ -116,6 +156,86 @@ impl TupleIter for HashJoinExecutor {
         // Walk down the left tuples until we find a hit.
         'outer: loop {
             if let Some((tuple, left_freq)) = self.left.next()? {
+                if self.join_type == JoinType::LeftSingle {
+                    // Single joins output each left row once along with its only match, or
+                    // null padded if there isn't one.
+                    let buf = transmute_muf_buf(&mut self.tuple_buf);
+                    for (idx, datum) in tuple.iter().enumerate() {
+                        buf[idx] = datum.ref_clone();
+                        if idx < self.key_len {
+                            buf[idx + right_offset - self.key_len] = datum.ref_clone();
+                        }
+                    }
+
+                    let mut matched = None;
+                    if let Some(bucket) = hash_table.get(&tuple[0..(self.key_len)]) {
+                        for (right_tuple, right_freq, _matched) in bucket {
+                            for (idx, datum) in right_tuple.iter().enumerate() {
+                                buf[right_offset + idx] = datum.ref_clone();
+                            }
+                            if self.non_equi_condition.eval_scalar(
+                                &self.session,
+                                buf,
+                                &mut self.arena,
+                            ) == Datum::from(true)
+                            {
+                                if matched.is_some() || *right_freq > 1 {
+                                    return Err(ExecutionError::SubqueryRowCount);
+                                }
+                                matched = Some(right_tuple);
+                            }
+                        }
+                    }
+
+                    if let Some(right_tuple) = matched {
+                        for (idx, datum) in right_tuple.iter().enumerate() {
+                            buf[right_offset + idx] = datum.ref_clone();
+                        }
+                    } else {
+                        for d in &mut buf[(self.left_len)..] {
+                            *d = Datum::Null;
+                        }
+                    }
+                    self.freq = left_freq;
+                    break;
+                }
+
+                if !self.join_type.outputs_right() {
+                    // Semi/anti joins output each left row at most once depending on
+                    // whether there's any match on the right.
+                    let buf = transmute_muf_buf(&mut self.tuple_buf);
+                    for (idx, datum) in tuple.iter().enumerate() {
+                        buf[idx] = datum.ref_clone();
+                        if idx < self.key_len {
+                            buf[idx + right_offset - self.key_len] = datum.ref_clone();
+                        }
+                    }
+
+                    let mut matched = false;
+                    if let Some(bucket) = hash_table.get(&tuple[0..(self.key_len)]) {
+                        for (right_tuple, _right_freq, _matched) in bucket {
+                            for (idx, datum) in right_tuple.iter().enumerate() {
+                                buf[right_offset + idx] = datum.ref_clone();
+                            }
+                            if self.non_equi_condition.eval_scalar(
+                                &self.session,
+                                buf,
+                                &mut self.arena,
+                            ) == Datum::from(true)
+                            {
+                                matched = true;
+                                break;
+                            }
+                        }
+                    }
+
+                    if matched == (self.join_type == JoinType::LeftSemi) {
+                        self.freq = left_freq;
+                        break;
+                    }
+                    continue;
+                }
+
                 if let Some(bucket) = hash_table.get(&tuple[0..(self.key_len)]) {
                     // We've got a hit, populate the left side of the tuple
                     let buf = transmute_muf_buf(&mut self.tuple_buf);
@@ -130,21 +250,24 @@ impl TupleIter for HashJoinExecutor {
                     self.bucket_iter = unsafe { std::mem::transmute(bucket.iter()) };
 
                     // Process the first item in the bucket
-                    while let Some((right_tuple, right_freq)) = self.bucket_iter.next() {
+                    while let Some((right_tuple, right_freq, matched)) = self.bucket_iter.next() {
                         for (idx, datum) in right_tuple.iter().enumerate() {
                             buf[right_offset + idx] = datum.ref_clone();
                         }
                         self.freq = *right_freq * left_freq;
 
-                        if self.non_equi_condition.eval_scalar(&self.session, buf)
+                        if self
+                            .non_equi_condition
+                            .eval_scalar(&self.session, buf, &mut self.arena)
                             == Datum::from(true)
                         {
+                            matched.set(true);
                             break 'outer;
                         }
                     }
                 }
 
-                if self.join_type == JoinType::LeftOuter {
+                if self.join_type == JoinType::LeftOuter || self.join_type == JoinType::FullOuter {
                     // Populate the left side of the output tuple
                     let buf = transmute_muf_buf(&mut self.tuple_buf);
                     for (idx, datum) in tuple.iter().enumerate() {
@@ -158,6 +281,19 @@ impl TupleIter for HashJoinExecutor {
 
                     break;
                 }
+            } else if self.join_type == JoinType::FullOuter {
+                // Gather up the right rows that nothing matched and go output them
+                let mut unmatched_right = std::mem::take(&mut self.null_keyed);
+                for (key, bucket) in hash_table.iter() {
+                    for (rest, freq, matched) in bucket {
+                        if !matched.get() {
+                            let tuple = key.iter().chain(rest.iter()).cloned().collect();
+                            unmatched_right.push((tuple, *freq));
+                        }
+                    }
+                }
+                self.unmatched_right = Some(unmatched_right.into_iter());
+                return self.advance();
             } else {
                 // We're done...
                 self.done = true;
@@ -172,12 +308,16 @@ impl TupleIter for HashJoinExecutor {
         if self.done {
             None
         } else {
-            Some((&self.tuple_buf, self.freq))
+            Some((&self.tuple_buf[..self.column_count()], self.freq))
         }
     }
 
     fn column_count(&self) -> usize {
-        self.left.column_count() + self.right.column_count()
+        if self.join_type.outputs_right() {
+            self.left.column_count() + self.right.column_count()
+        } else {
+            self.left.column_count()
+        }
     }
 }
 
@@ -186,8 +326,10 @@ mod tests {
     use super::*;
     use crate::point_in_time::sort::SortExecutor;
     use crate::point_in_time::values::ValuesExecutor;
-    use ast::expr::{CompiledColumnReference, Expression, SortExpression};
+    use ast::expr::{CompiledColumnReference, CompiledFunctionCall, Expression, SortExpression};
     use data::{DataType, Session, SortOrder};
+    use functions::registry::Registry;
+    use functions::FunctionSignature;
     use std::sync::Arc;
 
     #[test]
@@ -390,4 +532,256 @@ mod tests {
         assert_eq!(sorted.next()?, None);
         Ok(())
     }
+
+    #[test]
+    fn test_full_outer_join() -> Result<(), ExecutionError> {
+        let left_values = vec![
+            vec![Datum::from("a"), Datum::from(1)],
+            vec![Datum::from("b"), Datum::from(2)],
+            vec![Datum::Null, Datum::from(3)],
+        ];
+        let right_values = vec![
+            vec![Datum::from("a"), Datum::from(4)],
+            vec![Datum::from("b"), Datum::from(5)],
+            vec![Datum::from("c"), Datum::from(6)],
+            vec![Datum::Null, Datum::from(7)],
+        ];
+        let (sig, lt_function) = Registry::default()
+            .resolve_function(&FunctionSignature {
+                name: "<",
+                args: vec![DataType::Integer, DataType::Integer],
+                ret: DataType::Null,
+            })
+            .unwrap();
+        // A residual condition that b's match fails, right column 1 < 5
+        let condition = Expression::CompiledFunctionCall(CompiledFunctionCall {
+            function: lt_function.as_scalar(),
+            args: Box::from(vec![
+                Expression::CompiledColumnReference(CompiledColumnReference {
+                    offset: 3,
+                    datatype: DataType::Integer,
+                }),
+                Expression::from(5),
+            ]),
+            signature: Box::new(sig),
+        });
+
+        let executor = HashJoinExecutor::new(
+            Box::from(ValuesExecutor::new(Box::from(left_values.into_iter()), 2)),
+            Box::from(ValuesExecutor::new(Box::from(right_values.into_iter()), 2)),
+            1,
+            condition,
+            JoinType::FullOuter,
+            Arc::new(Session::new(1)),
+        );
+
+        // Sort on the two numeric columns
+        let mut sorted = SortExecutor::new(
+            Arc::new(Session::new(1)),
+            Box::from(executor),
+            vec![
+                SortExpression {
+                    ordering: SortOrder::Asc,
+                    expression: Expression::CompiledColumnReference(CompiledColumnReference {
+                        offset: 1,
+                        datatype: DataType::Integer,
+                    }),
+                },
+                SortExpression {
+                    ordering: SortOrder::Asc,
+                    expression: Expression::CompiledColumnReference(CompiledColumnReference {
+                        offset: 3,
+                        datatype: DataType::Integer,
+                    }),
+                },
+            ],
+        );
+
+        let mut rows = vec![];
+        while let Some((tuple, freq)) = sorted.next()? {
+            rows.push((tuple.iter().map(Datum::as_static).collect::<Vec<_>>(), freq));
+        }
+        assert_eq!(
+            rows,
+            vec![
+                // The unmatched right rows, including those with null keys
+                (
+                    vec![Datum::Null, Datum::Null, Datum::from("b"), Datum::from(5)],
+                    1
+                ),
+                (
+                    vec![Datum::Null, Datum::Null, Datum::from("c"), Datum::from(6)],
+                    1
+                ),
+                (
+                    vec![Datum::Null, Datum::Null, Datum::Null, Datum::from(7)],
+                    1
+                ),
+                (
+                    vec![
+                        Datum::from("a"),
+                        Datum::from(1),
+                        Datum::from("a"),
+                        Datum::from(4)
+                    ],
+                    1
+                ),
+                // b only matches on the key so it's output unmatched from both sides
+                (
+                    vec![Datum::from("b"), Datum::from(2), Datum::Null, Datum::Null],
+                    1
+                ),
+                (
+                    vec![Datum::Null, Datum::from(3), Datum::Null, Datum::Null],
+                    1
+                ),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_join() -> Result<(), ExecutionError> {
+        let left_values = vec![
+            vec![Datum::from("a"), Datum::from(1)],
+            vec![Datum::from("b"), Datum::from(2)],
+        ];
+        let right_values = vec![
+            vec![Datum::from("a"), Datum::from(3)],
+            vec![Datum::from("c"), Datum::from(4)],
+        ];
+        let mut single_join = HashJoinExecutor::new(
+            Box::from(ValuesExecutor::new(
+                Box::from(left_values.clone().into_iter()),
+                2,
+            )),
+            Box::from(ValuesExecutor::new(Box::from(right_values.into_iter()), 2)),
+            1,
+            Expression::from(true),
+            JoinType::LeftSingle,
+            Arc::new(Session::new(1)),
+        );
+        assert_eq!(
+            single_join.next()?,
+            Some((
+                [
+                    Datum::from("a"),
+                    Datum::from(1),
+                    Datum::from("a"),
+                    Datum::from(3)
+                ]
+                .as_ref(),
+                1
+            ))
+        );
+        assert_eq!(
+            single_join.next()?,
+            Some((
+                [Datum::from("b"), Datum::from(2), Datum::Null, Datum::Null].as_ref(),
+                1
+            ))
+        );
+        assert_eq!(single_join.next()?, None);
+
+        // Two matches for a
+        let right_values = vec![
+            vec![Datum::from("a"), Datum::from(3)],
+            vec![Datum::from("a"), Datum::from(4)],
+        ];
+        let mut single_join = HashJoinExecutor::new(
+            Box::from(ValuesExecutor::new(Box::from(left_values.into_iter()), 2)),
+            Box::from(ValuesExecutor::new(Box::from(right_values.into_iter()), 2)),
+            1,
+            Expression::from(true),
+            JoinType::LeftSingle,
+            Arc::new(Session::new(1)),
+        );
+        assert!(single_join.next().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_semi_and_anti_join() -> Result<(), ExecutionError> {
+        let left_values = vec![
+            vec![Datum::from("a"), Datum::from(1)],
+            vec![Datum::from("b"), Datum::from(2)],
+            vec![Datum::from("c"), Datum::from(3)],
+            vec![Datum::Null, Datum::from(4)],
+        ];
+        // Multiple matches for a, b only matches on the key and c doesn't match at all.
+        let right_values = vec![
+            vec![Datum::from("a"), Datum::from(1)],
+            vec![Datum::from("a"), Datum::from(1)],
+            vec![Datum::from("b"), Datum::from(5)],
+            vec![Datum::Null, Datum::from(4)],
+        ];
+        let (sig, eq_function) = Registry::default()
+            .resolve_function(&FunctionSignature {
+                name: "=",
+                args: vec![DataType::Integer, DataType::Integer],
+                ret: DataType::Null,
+            })
+            .unwrap();
+        // Left column 1 = right column 1
+        let condition = Expression::CompiledFunctionCall(CompiledFunctionCall {
+            function: eq_function.as_scalar(),
+            args: Box::from(vec![
+                Expression::CompiledColumnReference(CompiledColumnReference {
+                    offset: 1,
+                    datatype: DataType::Integer,
+                }),
+                Expression::CompiledColumnReference(CompiledColumnReference {
+                    offset: 3,
+                    datatype: DataType::Integer,
+                }),
+            ]),
+            signature: Box::new(sig),
+        });
+
+        let mut semi_join = HashJoinExecutor::new(
+            Box::from(ValuesExecutor::new(
+                Box::from(left_values.clone().into_iter()),
+                2,
+            )),
+            Box::from(ValuesExecutor::new(
+                Box::from(right_values.clone().into_iter()),
+                2,
+            )),
+            1,
+            condition.clone(),
+            JoinType::LeftSemi,
+            Arc::new(Session::new(1)),
+        );
+
+        assert_eq!(semi_join.column_count(), 2);
+        assert_eq!(
+            semi_join.next()?,
+            Some(([Datum::from("a"), Datum::from(1)].as_ref(), 1))
+        );
+        assert_eq!(semi_join.next()?, None);
+
+        let mut anti_join = HashJoinExecutor::new(
+            Box::from(ValuesExecutor::new(Box::from(left_values.into_iter()), 2)),
+            Box::from(ValuesExecutor::new(Box::from(right_values.into_iter()), 2)),
+            1,
+            condition,
+            JoinType::LeftAnti,
+            Arc::new(Session::new(1)),
+        );
+
+        assert_eq!(
+            anti_join.next()?,
+            Some(([Datum::from("b"), Datum::from(2)].as_ref(), 1))
+        );
+        assert_eq!(
+            anti_join.next()?,
+            Some(([Datum::from("c"), Datum::from(3)].as_ref(), 1))
+        );
+        assert_eq!(
+            anti_join.next()?,
+            Some(([Datum::Null, Datum::from(4)].as_ref(), 1))
+        );
+        assert_eq!(anti_join.next()?, None);
+        Ok(())
+    }
 }
@@ -1,3 +1,4 @@
+use crate::point_in_time::file_copy::FileCopyExecutor;
 use crate::point_in_time::file_scan::FileScanExecutor;
 use crate::point_in_time::filter::FilterExecutor;
 use crate::point_in_time::hash_group::HashGroupExecutor;
@@ -5,56 +6,108 @@ use crate::point_in_time::hash_join::HashJoinExecutor;
 use crate::point_in_time::limit::LimitExecutor;
 use crate::point_in_time::negate_freq::NegateFreqExecutor;
 use crate::point_in_time::project::ProjectExecutor;
+use crate::point_in_time::shared_scan::SharedScans;
 use crate::point_in_time::single::SingleExecutor;
 use crate::point_in_time::sort::SortExecutor;
 use crate::point_in_time::sorted_group::SortedGroupExecutor;
+use crate::point_in_time::table_changes::TableChangesExecutor;
 use crate::point_in_time::table_insert::TableInsertExecutor;
 use crate::point_in_time::table_scan::TableScanExecutor;
 use crate::point_in_time::union_all::UnionAllExecutor;
+use crate::point_in_time::validate::{CheckTypesExecutor, CompareExecutor};
 use crate::point_in_time::values::ValuesExecutor;
+use crate::point_in_time::window::WindowExecutor;
 use crate::ExecutionError;
 use ast::rel::point_in_time::PointInTimeOperator;
-use data::{Session, TupleIter};
+use data::{DataType, Session, TupleIter};
 use std::sync::Arc;
+use storage::Transaction;
 
+pub use parquet_files::parquet_schema;
+
+mod file_copy;
 mod file_scan;
 mod filter;
 mod hash_group;
 mod hash_join;
 mod limit;
 mod negate_freq;
+mod parquet_files;
 mod project;
+mod shared_scan;
 mod single;
 mod sort;
 mod sorted_group;
+mod table_changes;
 mod table_insert;
 mod table_scan;
 mod union_all;
+mod validate;
 mod values;
+mod window;
 
 pub type BoxedExecutor = Box<dyn TupleIter<E = ExecutionError>>;
 
+/// Builds the executor for the plan, tables scanned more than once by the plan are only read
+/// from storage once.
 pub fn build_executor(session: &Arc<Session>, plan: &PointInTimeOperator) -> BoxedExecutor {
+    build(session, &SharedScans::new(plan), plan)
+}
+
+/// Builds the executor for the plan with its table scans and inserts made within the transaction
+pub fn build_transaction_executor(
+    session: &Arc<Session>,
+    plan: &PointInTimeOperator,
+    transaction: &Transaction,
+) -> BoxedExecutor {
+    let mut plan = plan.clone();
+    use_transaction(&mut plan, transaction);
+    build_executor(session, &plan)
+}
+
+fn use_transaction(plan: &mut PointInTimeOperator, transaction: &Transaction) {
+    match plan {
+        PointInTimeOperator::TableScan(table_scan) => {
+            table_scan.table = transaction.table(&table_scan.table);
+        }
+        PointInTimeOperator::TableInsert(table_insert) => {
+            table_insert.table = transaction.table(&table_insert.table);
+            if let Some(source_offsets) = &mut table_insert.source_offsets {
+                *source_offsets = transaction.table(source_offsets);
+            }
+        }
+        _ => {}
+    }
+    for child in plan.children_mut() {
+        use_transaction(child, transaction);
+    }
+}
+
+fn build(
+    session: &Arc<Session>,
+    shared_scans: &SharedScans,
+    plan: &PointInTimeOperator,
+) -> BoxedExecutor {
     match plan {
         PointInTimeOperator::Single => Box::from(SingleExecutor::new()),
         PointInTimeOperator::Project(project) => Box::from(ProjectExecutor::new(
             Arc::clone(session),
-            build_executor(session, &project.source),
+            build(session, shared_scans, &project.source),
             project.expressions.clone(),
         )),
         PointInTimeOperator::Filter(filter) => Box::from(FilterExecutor::new(
             Arc::clone(session),
-            build_executor(session, &filter.source),
+            build(session, shared_scans, &filter.source),
             filter.predicate.clone(),
         )),
         PointInTimeOperator::Limit(limit) => Box::from(LimitExecutor::new(
-            build_executor(session, &limit.source),
+            build(session, shared_scans, &limit.source),
             limit.offset,
             limit.limit,
         )),
         PointInTimeOperator::Sort(sort) => Box::from(SortExecutor::new(
             Arc::clone(session),
-            build_executor(session, &sort.source),
+            build(session, shared_scans, &sort.source),
             sort.sort_expressions.clone(),
         )),
         PointInTimeOperator::Values(values) => Box::from(ValuesExecutor::new(
@@ -65,39 +118,74 @@ pub fn build_executor(session: &Arc<Session>, plan: &PointInTimeOperator) -> Box
             union_all
                 .sources
                 .iter()
-                .map(|source| build_executor(session, source))
+                .map(|source| build(session, shared_scans, source))
                 .collect(),
         )),
-        PointInTimeOperator::TableScan(table_scan) => Box::from(TableScanExecutor::new(
-            table_scan.table.clone(),
-            table_scan.timestamp,
+        PointInTimeOperator::TableScan(table_scan) => match shared_scans.reader(table_scan) {
+            Some(reader) => Box::from(reader),
+            None => Box::from(TableScanExecutor::new(
+                table_scan.table.clone(),
+                table_scan.timestamp,
+                table_scan.ranges.clone(),
+                table_scan.sample.as_ref(),
+            )),
+        },
+        PointInTimeOperator::TableChanges(table_changes) => Box::from(TableChangesExecutor::new(
+            table_changes.table.clone(),
+            table_changes.from,
+            table_changes.to,
         )),
         PointInTimeOperator::TableInsert(table_insert) => Box::from(TableInsertExecutor::new(
-            build_executor(session, &table_insert.source),
+            build(session, shared_scans, &table_insert.source),
+            Arc::clone(session),
             table_insert.table.clone(),
+            table_insert.source_offsets.clone(),
+            table_insert.batch_rows,
+            table_insert.json_schemas.clone(),
         )),
-        PointInTimeOperator::NegateFreq(source) => {
-            Box::from(NegateFreqExecutor::new(build_executor(session, &source)))
-        }
+        PointInTimeOperator::NegateFreq(source) => Box::from(NegateFreqExecutor::new(build(
+            session,
+            shared_scans,
+            &source,
+        ))),
         PointInTimeOperator::SortedGroup(group) => Box::from(SortedGroupExecutor::new(
-            build_executor(session, &group.source),
+            build(session, shared_scans, &group.source),
             Arc::clone(&session),
             group.key_len,
             group.expressions.clone(),
         )),
         PointInTimeOperator::HashGroup(group) => Box::from(HashGroupExecutor::new(
-            build_executor(session, &group.source),
+            build(session, shared_scans, &group.source),
             Arc::clone(&session),
             group.key_len,
             group.expressions.clone(),
         )),
+        PointInTimeOperator::Window(window) => Box::from(WindowExecutor::new(
+            build(session, shared_scans, &window.source),
+            Arc::clone(session),
+            window.partition_by.clone(),
+            window.order_by.clone(),
+            window.functions.clone(),
+        )),
         PointInTimeOperator::FileScan(file_scan) => Box::from(FileScanExecutor::new(
+            Arc::clone(session),
             file_scan.directory.clone(),
             file_scan.serde_options.clone(),
+            file_scan.columns.clone(),
+            file_scan.dead_letters.clone(),
+            file_scan.source_offsets.clone(),
+            file_scan.paused_sources.clone(),
+        )),
+        PointInTimeOperator::FileCopy(file_copy) => Box::from(FileCopyExecutor::new(
+            Arc::clone(session),
+            file_copy.path.clone(),
+            file_copy.serde_options.clone(),
+            file_copy.columns.clone(),
+            file_copy.dead_letters.clone(),
         )),
         PointInTimeOperator::HashJoin(join) => Box::from(HashJoinExecutor::new(
-            build_executor(session, &join.left),
-            build_executor(session, &join.right),
+            build(session, shared_scans, &join.left),
+            build(session, shared_scans, &join.right),
             join.key_len,
             join.non_equi_condition.clone(),
             join.join_type,
@@ -106,6 +194,21 @@ pub fn build_executor(session: &Arc<Session>, plan: &PointInTimeOperator) -> Box
     }
 }
 
+/// Wraps the executor checking each row it returns has the columns and types the planner said
+/// it would
+pub fn build_type_checked_executor(
+    source: BoxedExecutor,
+    datatypes: Vec<DataType>,
+) -> BoxedExecutor {
+    Box::from(CheckTypesExecutor::new(source, datatypes))
+}
+
+/// Wraps the executor checking it returns the same rows as expected, in any order, once it's
+/// exhausted
+pub fn build_compared_executor(source: BoxedExecutor, expected: BoxedExecutor) -> BoxedExecutor {
+    Box::from(CompareExecutor::new(source, expected))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
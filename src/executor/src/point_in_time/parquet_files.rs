@@ -0,0 +1,348 @@
+use crate::point_in_time::file_scan::entries;
+use crate::ExecutionError;
+use ast::expr::Expression;
+use data::chrono::{Duration, NaiveDate, NaiveDateTime};
+use data::json::{JsonBuilder, OwnedJson};
+use data::jsonpath_utils::JsonPathExpression;
+use data::rust_decimal::Decimal;
+use data::{DataType, Datum, DECIMAL_MAX_PRECISION, DECIMAL_MAX_SCALE};
+use parquet::basic::{LogicalType, Type as PhysicalType};
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::record::reader::RowIter;
+use parquet::record::{Field, Row};
+use parquet::schema::types::Type;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+// Floating point columns don't carry a scale so we'll give them plenty
+const FLOAT_SCALE: u8 = 10;
+
+/// The top level columns of the first parquet file in the directory along with the types
+/// their values turn into once they've been pulled out of the row's document.
+pub fn parquet_schema(directory: &str) -> Result<Vec<(String, DataType)>, ExecutionError> {
+    let path = match entries(PathBuf::from(directory)).next() {
+        Some(path) => path?,
+        None => return Ok(vec![]),
+    };
+    let reader = SerializedFileReader::new(File::open(path)?)?;
+    Ok(reader
+        .metadata()
+        .file_metadata()
+        .schema()
+        .get_fields()
+        .iter()
+        .map(|field| (field.name().to_string(), datatype(field)))
+        .collect())
+}
+
+/// Maps a parquet column onto one of our types, anything nested becomes json.
+fn datatype(field: &Type) -> DataType {
+    if !field.is_primitive() {
+        return DataType::Json;
+    }
+    let logical_type = field.get_basic_info().logical_type();
+    match (field.get_physical_type(), logical_type) {
+        (_, LogicalType::DECIMAL) => DataType::Decimal(
+            (field.get_precision() as u8).min(DECIMAL_MAX_PRECISION),
+            (field.get_scale() as u8).min(DECIMAL_MAX_SCALE),
+        ),
+        (PhysicalType::BOOLEAN, _) => DataType::Boolean,
+        (PhysicalType::INT32, LogicalType::DATE) => DataType::Date,
+        (PhysicalType::INT32, LogicalType::UINT_32) => DataType::BigInt,
+        (PhysicalType::INT32, _) => DataType::Integer,
+        (PhysicalType::INT64, LogicalType::TIMESTAMP_MILLIS)
+        | (PhysicalType::INT64, LogicalType::TIMESTAMP_MICROS)
+        | (PhysicalType::INT96, _) => DataType::Timestamp,
+        (PhysicalType::INT64, LogicalType::UINT_64) => DataType::Decimal(20, 0),
+        (PhysicalType::INT64, _) => DataType::BigInt,
+        (PhysicalType::FLOAT, _) | (PhysicalType::DOUBLE, _) => {
+            DataType::Decimal(DECIMAL_MAX_PRECISION, FLOAT_SCALE)
+        }
+        (_, LogicalType::JSON) | (_, LogicalType::BSON) => DataType::Json,
+        // Strings, enums and raw bytes
+        (PhysicalType::BYTE_ARRAY, _) | (PhysicalType::FIXED_LEN_BYTE_ARRAY, _) => DataType::Text,
+    }
+}
+
+/// The top level keys the columns look up in each document, or None if they need the whole
+/// document. Only the parquet columns for these keys need to be read.
+pub(super) fn projected_keys(columns: &[Expression]) -> Option<Vec<String>> {
+    if columns.is_empty() {
+        return None;
+    }
+    let mut keys = vec![];
+    for column in columns {
+        if !collect_keys(column, &mut keys) {
+            return None;
+        }
+    }
+    Some(keys)
+}
+
+/// Adds the keys looked up by the expression, returns false if the document is used some
+/// other way.
+fn collect_keys(expression: &Expression, keys: &mut Vec<String>) -> bool {
+    match expression {
+        Expression::CompiledFunctionCall(function_call)
+            if matches!(function_call.signature.name, "->" | "->>")
+                && matches!(
+                    function_call.args[0],
+                    Expression::CompiledColumnReference(_)
+                ) =>
+        {
+            match constant_path(&function_call.args[1])
+                .and_then(|path| path.first_key().map(str::to_string))
+            {
+                Some(key) => {
+                    keys.push(key);
+                    true
+                }
+                None => false,
+            }
+        }
+        Expression::CompiledColumnReference(_) => false,
+        expression => expression.children().all(|child| collect_keys(child, keys)),
+    }
+}
+
+/// The path if it's a constant, the constant may still be wrapped in its cast to a jsonpath
+/// if constant folding has been switched off.
+fn constant_path(expression: &Expression) -> Option<JsonPathExpression> {
+    match expression {
+        Expression::Constant(Datum::Jsonpath(path), _) => Some(path.as_ref().clone()),
+        Expression::Constant(datum, DataType::Text) => {
+            datum.as_maybe_text().and_then(JsonPathExpression::parse)
+        }
+        Expression::CompiledFunctionCall(function_call) if function_call.args.len() == 1 => {
+            constant_path(&function_call.args[0])
+        }
+        _ => None,
+    }
+}
+
+/// Reads the rows of the file as json objects, only reading the columns for the keys if
+/// we're given them. Keys are matched ignoring case like the json paths that look them up.
+pub(super) fn parquet_rows(
+    path: &Path,
+    keys: &Option<Vec<String>>,
+) -> Result<impl Iterator<Item = OwnedJson>, ExecutionError> {
+    let reader = SerializedFileReader::new(File::open(path)?)?;
+    let projection = match keys {
+        Some(keys) => {
+            let schema = reader.metadata().file_metadata().schema();
+            let mut fields: Vec<_> = schema
+                .get_fields()
+                .iter()
+                .filter(|field| {
+                    keys.iter()
+                        .any(|key| key.eq_ignore_ascii_case(field.name()))
+                })
+                .cloned()
+                .collect();
+            // Something still has to be read to know how many rows there are
+            if fields.is_empty() {
+                fields.extend(schema.get_fields().first().cloned());
+            }
+            Some(
+                Type::group_type_builder(schema.name())
+                    .with_fields(&mut fields)
+                    .build()?,
+            )
+        }
+        None => None,
+    };
+    let rows = RowIter::from_file_into(Box::new(reader)).project(projection)?;
+    Ok(rows.map(|row| row_json(&row)))
+}
+
+fn row_json(row: &Row) -> OwnedJson {
+    JsonBuilder::default().object(|object| {
+        for (name, field) in row.get_column_iter() {
+            object.push_json(name, field_json(field).as_json());
+        }
+    })
+}
+
+/// Dates and timestamps are written out the same way they'd be found in a json file
+fn field_json(field: &Field) -> OwnedJson {
+    let builder = JsonBuilder::default();
+    match field {
+        Field::Null => builder.null(),
+        Field::Bool(b) => builder.bool(*b),
+        Field::Byte(i) => builder.int(*i as i64),
+        Field::Short(i) => builder.int(*i as i64),
+        Field::Int(i) => builder.int(*i as i64),
+        Field::Long(i) => builder.int(*i),
+        Field::UByte(i) => builder.int(*i as i64),
+        Field::UShort(i) => builder.int(*i as i64),
+        Field::UInt(i) => builder.int(*i as i64),
+        Field::ULong(i) => builder.decimal(Decimal::from(*i)),
+        Field::Float(f) => decimal_json(builder, &f.to_string()),
+        Field::Double(f) => decimal_json(builder, &f.to_string()),
+        // The field's display writes out all the digits
+        Field::Decimal(_) => decimal_json(builder, &field.to_string()),
+        Field::Str(s) => builder.string(s),
+        Field::Bytes(bytes) => builder.string(&String::from_utf8_lossy(bytes.data())),
+        Field::Date(days) => {
+            let date = NaiveDate::from_ymd(1970, 1, 1) + Duration::days(*days as i64);
+            builder.string(&date.to_string())
+        }
+        Field::TimestampMillis(millis) => {
+            timestamp_json(builder, Duration::milliseconds(*millis as i64))
+        }
+        Field::TimestampMicros(micros) => {
+            timestamp_json(builder, Duration::microseconds(*micros as i64))
+        }
+        Field::Group(row) => row_json(row),
+        Field::ListInternal(list) => builder.array(|array| {
+            for element in list.elements() {
+                array.push_json(field_json(element).as_json());
+            }
+        }),
+        Field::MapInternal(map) => builder.object(|object| {
+            for (key, value) in map.entries() {
+                let key = match key {
+                    Field::Str(key) => key.clone(),
+                    key => key.to_string(),
+                };
+                object.push_json(&key, field_json(value).as_json());
+            }
+        }),
+    }
+}
+
+/// Floats that are too big for a decimal or aren't numbers at all come out as nulls
+fn decimal_json(builder: JsonBuilder, s: &str) -> OwnedJson {
+    match Decimal::from_str(s) {
+        Ok(d) => builder.decimal(d),
+        Err(_) => builder.null(),
+    }
+}
+
+fn timestamp_json(builder: JsonBuilder, since_epoch: Duration) -> OwnedJson {
+    let timestamp = NaiveDateTime::from_timestamp(0, 0) + since_epoch;
+    builder.string(&timestamp.format("%Y-%m-%dT%H:%M:%S%.f").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::expr::{CompiledColumnReference, CompiledFunctionCall};
+    use functions::registry::Registry;
+    use functions::FunctionSignature;
+    use parquet::schema::types::TypePtr;
+
+    fn primitive(physical_type: PhysicalType, logical_type: LogicalType) -> Type {
+        Type::primitive_type_builder("a", physical_type)
+            .with_logical_type(logical_type)
+            .with_precision(10)
+            .with_scale(2)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_datatype() {
+        let datatype =
+            |physical_type, logical_type| datatype(&primitive(physical_type, logical_type));
+        assert_eq!(
+            datatype(PhysicalType::BOOLEAN, LogicalType::NONE),
+            DataType::Boolean
+        );
+        assert_eq!(
+            datatype(PhysicalType::INT32, LogicalType::NONE),
+            DataType::Integer
+        );
+        assert_eq!(
+            datatype(PhysicalType::INT32, LogicalType::DATE),
+            DataType::Date
+        );
+        assert_eq!(
+            datatype(PhysicalType::INT64, LogicalType::NONE),
+            DataType::BigInt
+        );
+        assert_eq!(
+            datatype(PhysicalType::INT64, LogicalType::TIMESTAMP_MICROS),
+            DataType::Timestamp
+        );
+        assert_eq!(
+            datatype(PhysicalType::FIXED_LEN_BYTE_ARRAY, LogicalType::DECIMAL),
+            DataType::Decimal(10, 2)
+        );
+        assert_eq!(
+            datatype(PhysicalType::DOUBLE, LogicalType::NONE),
+            DataType::Decimal(28, 10)
+        );
+        assert_eq!(
+            datatype(PhysicalType::BYTE_ARRAY, LogicalType::UTF8),
+            DataType::Text
+        );
+        assert_eq!(
+            datatype(PhysicalType::BYTE_ARRAY, LogicalType::JSON),
+            DataType::Json
+        );
+
+        let group = Type::group_type_builder("g")
+            .with_fields(&mut vec![TypePtr::new(primitive(
+                PhysicalType::INT32,
+                LogicalType::NONE,
+            ))])
+            .build()
+            .unwrap();
+        assert_eq!(datatype(&group), DataType::Json);
+    }
+
+    #[test]
+    fn test_field_json() {
+        let json = |field: Field| field_json(&field);
+        let parse = |s| OwnedJson::parse(s).unwrap();
+        assert_eq!(json(Field::Null), parse("null"));
+        assert_eq!(json(Field::Int(-5)), parse("-5"));
+        assert_eq!(json(Field::Double(1.5)), parse("1.5"));
+        assert_eq!(json(Field::Double(f64::NAN)), parse("null"));
+        assert_eq!(json(Field::Str("abc".to_string())), parse(r#""abc""#));
+        assert_eq!(json(Field::Date(18262)), parse(r#""2020-01-01""#));
+        assert_eq!(
+            json(Field::TimestampMillis(1_577_872_800_123)),
+            parse(r#""2020-01-01T10:00:00.123""#)
+        );
+    }
+
+    #[test]
+    fn test_projected_keys() {
+        let (signature, function) = Registry::default()
+            .resolve_function(&FunctionSignature {
+                name: "->",
+                args: vec![DataType::Json, DataType::JsonPath],
+                ret: DataType::Null,
+            })
+            .unwrap();
+        let document = Expression::CompiledColumnReference(CompiledColumnReference {
+            offset: 0,
+            datatype: DataType::Json,
+        });
+        let extract = |path: &str| {
+            Expression::CompiledFunctionCall(CompiledFunctionCall {
+                function: function.as_scalar(),
+                args: Box::from(vec![
+                    document.clone(),
+                    Expression::Constant(
+                        Datum::Jsonpath(Box::new(JsonPathExpression::parse(path).unwrap())),
+                        DataType::JsonPath,
+                    ),
+                ]),
+                signature: Box::new(signature.clone()),
+            })
+        };
+
+        assert_eq!(
+            projected_keys(&[extract("$.a.b"), extract("$.c"), Expression::from(1)]),
+            Some(vec!["a".to_string(), "c".to_string()])
+        );
+        // Paths that don't start with a key or using the document directly need all of it
+        assert_eq!(projected_keys(&[extract("$.a"), extract("$[0]")]), None);
+        assert_eq!(projected_keys(&[extract("$.a"), document.clone()]), None);
+        assert_eq!(projected_keys(&[]), None);
+    }
+}
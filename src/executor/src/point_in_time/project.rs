@@ -1,3 +1,4 @@
+use crate::arena::ExprArena;
 use crate::point_in_time::BoxedExecutor;
 use crate::scalar_expression::EvalScalarRow;
 use crate::utils::*;
@@ -12,6 +13,7 @@ pub struct ProjectExecutor {
     expressions: Vec<Expression>,
 
     tuple_buffer: Vec<Datum<'static>>,
+    arena: ExprArena,
 }
 
 impl ProjectExecutor {
@@ -22,6 +24,7 @@ impl ProjectExecutor {
             session,
             expressions,
             tuple_buffer,
+            arena: ExprArena::default(),
         }
     }
 }
@@ -41,6 +44,7 @@ impl TupleIter for ProjectExecutor {
                 &self.session,
                 tuple,
                 transmute_muf_buf(&mut self.tuple_buffer),
+                &mut self.arena,
             );
         }
         Ok(())
@@ -0,0 +1,366 @@
+use crate::point_in_time::table_scan::TableScanExecutor;
+use crate::ExecutionError;
+use ast::rel::point_in_time::{PointInTimeOperator, ScanRange, TableScan};
+use data::{Datum, LogicalTimestamp, TupleIter};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use storage::Table;
+
+/// How many rows are held for the readers of a shared scan that have fallen behind. Once it's
+/// full the reader in front takes the scan for itself and the others carry on with scans of
+/// their own once they've caught up.
+const SHARED_SCAN_BUFFER_ROWS: usize = 10_000;
+
+/// The table scans that appear more than once in a plan, ie for self joins or a view used
+/// twice. Each one is read from storage once with the rows handed out to every occurrence.
+pub struct SharedScans {
+    scans: Vec<(TableScan, Rc<RefCell<SharedScan>>)>,
+}
+
+impl SharedScans {
+    pub fn new(plan: &PointInTimeOperator) -> Self {
+        let mut counts = vec![];
+        count_scans(plan, &mut counts);
+        let scans = counts
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|(table_scan, _)| {
+                let shared = SharedScan::new(table_scan, SHARED_SCAN_BUFFER_ROWS);
+                (table_scan.clone(), Rc::new(RefCell::new(shared)))
+            })
+            .collect();
+        SharedScans { scans }
+    }
+
+    /// Returns a reader of the scan if it's shared with other parts of the plan
+    pub fn reader(&self, table_scan: &TableScan) -> Option<SharedTableScanExecutor> {
+        self.scans
+            .iter()
+            .find(|(scan, _)| scan == table_scan)
+            .map(|(_, shared)| SharedTableScanExecutor::new(Rc::clone(shared)))
+    }
+}
+
+/// Counts the occurrences of each scan that can be shared. Sampled scans pick different rows
+/// each time and scans of null bitmap tables can't be picked up part way through.
+fn count_scans<'a>(plan: &'a PointInTimeOperator, counts: &mut Vec<(&'a TableScan, usize)>) {
+    if let PointInTimeOperator::TableScan(table_scan) = plan {
+        if table_scan.sample.is_none() && !table_scan.table.null_bitmap() {
+            match counts.iter_mut().find(|(scan, _)| *scan == table_scan) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((table_scan, 1)),
+            }
+        }
+    }
+    for child in plan.children() {
+        count_scans(child, counts);
+    }
+}
+
+/// The scan along with the rows read from it that some of its readers have yet to get to
+struct SharedScan {
+    table: Table,
+    timestamp: LogicalTimestamp,
+    // Taken by the reader in front once the buffer fills up. Boxed as the scan's storage iter
+    // borrows its table, so it can't move once it's been advanced.
+    scan: Option<Box<TableScanExecutor>>,
+    // Where the readers behind carry on from once the scan's been taken, the key is of the last
+    // row buffered which they've already seen
+    resume: Option<(Vec<ScanRange>, Vec<Datum<'static>>)>,
+    exhausted: bool,
+    rows: VecDeque<(Vec<Datum<'static>>, i64)>,
+    max_rows: usize,
+    // The position in the scan of the first buffered row
+    rows_start: usize,
+    // The position of the next row for each reader, None once they're done with the buffer
+    positions: Vec<Option<usize>>,
+}
+
+/// What a reader gets next from a shared scan
+enum SharedRow {
+    Row(i64),
+    End,
+    // The reader is to carry on by itself, skipping the row with the key if it's read first
+    Scan(Box<TableScanExecutor>, Option<Vec<Datum<'static>>>),
+}
+
+impl SharedScan {
+    fn new(table_scan: &TableScan, max_rows: usize) -> Self {
+        SharedScan {
+            table: table_scan.table.clone(),
+            timestamp: table_scan.timestamp,
+            scan: Some(Box::new(TableScanExecutor::new(
+                table_scan.table.clone(),
+                table_scan.timestamp,
+                table_scan.ranges.clone(),
+                None,
+            ))),
+            resume: None,
+            exhausted: false,
+            rows: VecDeque::new(),
+            max_rows,
+            rows_start: 0,
+            positions: vec![],
+        }
+    }
+
+    /// Reads the reader's next row into row, reading on from the scan if it's in front
+    fn next_row(
+        &mut self,
+        reader: usize,
+        row: &mut Vec<Datum<'static>>,
+    ) -> Result<SharedRow, ExecutionError> {
+        let position = match self.positions[reader] {
+            Some(position) => position,
+            None => return Ok(SharedRow::End),
+        };
+
+        if position == self.rows_start + self.rows.len() {
+            if !self.exhausted {
+                let mut scan = match self.scan.take() {
+                    Some(scan) => scan,
+                    None => {
+                        self.finish(reader);
+                        let (ranges, key) = self.resume.clone().unwrap();
+                        let scan = Box::new(TableScanExecutor::new(
+                            self.table.clone(),
+                            self.timestamp,
+                            ranges,
+                            None,
+                        ));
+                        return Ok(SharedRow::Scan(scan, Some(key)));
+                    }
+                };
+                if self.rows.len() >= self.max_rows {
+                    self.resume = scan.remaining_ranges();
+                    self.finish(reader);
+                    return Ok(SharedRow::Scan(scan, None));
+                }
+                let result = scan.advance();
+                match scan.get() {
+                    Some((tuple, freq)) => self
+                        .rows
+                        .push_back((tuple.iter().map(Datum::as_static).collect(), freq)),
+                    None => self.exhausted = true,
+                }
+                self.scan = Some(scan);
+                result?;
+            }
+            if self.exhausted {
+                self.finish(reader);
+                return Ok(SharedRow::End);
+            }
+        }
+
+        self.positions[reader] = Some(position + 1);
+        let needed_by_others = self.positions.iter().flatten().any(|p| *p <= position);
+        let freq = if position == self.rows_start && !needed_by_others {
+            let (tuple, freq) = self.rows.pop_front().unwrap();
+            self.rows_start += 1;
+            *row = tuple;
+            freq
+        } else {
+            let (tuple, freq) = &self.rows[position - self.rows_start];
+            row.clear();
+            row.extend(tuple.iter().cloned());
+            *freq
+        };
+        Ok(SharedRow::Row(freq))
+    }
+
+    /// Marks the reader as done with the buffer, dropping any rows no one else needs
+    fn finish(&mut self, reader: usize) {
+        self.positions[reader] = None;
+        match self.positions.iter().flatten().min().copied() {
+            Some(min) => {
+                while self.rows_start < min {
+                    self.rows.pop_front();
+                    self.rows_start += 1;
+                }
+            }
+            None => self.rows.clear(),
+        }
+    }
+}
+
+/// One of the readers of a shared scan, see SharedScans
+pub struct SharedTableScanExecutor {
+    shared: Rc<RefCell<SharedScan>>,
+    reader: usize,
+    column_count: usize,
+    // Set once the reader carries on by itself
+    own_scan: Option<Box<TableScanExecutor>>,
+    skip_key: Option<Vec<Datum<'static>>>,
+    row: Vec<Datum<'static>>,
+    freq: i64,
+    has_row: bool,
+}
+
+impl SharedTableScanExecutor {
+    fn new(shared: Rc<RefCell<SharedScan>>) -> Self {
+        let (reader, column_count) = {
+            let mut scan = shared.borrow_mut();
+            let start = scan.rows_start;
+            scan.positions.push(Some(start));
+            (scan.positions.len() - 1, scan.table.column_count())
+        };
+        SharedTableScanExecutor {
+            shared,
+            reader,
+            column_count,
+            own_scan: None,
+            skip_key: None,
+            row: vec![],
+            freq: 0,
+            has_row: false,
+        }
+    }
+}
+
+impl TupleIter for SharedTableScanExecutor {
+    type E = ExecutionError;
+
+    fn advance(&mut self) -> Result<(), ExecutionError> {
+        if self.own_scan.is_none() {
+            let next = self
+                .shared
+                .borrow_mut()
+                .next_row(self.reader, &mut self.row)?;
+            match next {
+                SharedRow::Row(freq) => {
+                    self.freq = freq;
+                    self.has_row = true;
+                    return Ok(());
+                }
+                SharedRow::End => {
+                    self.has_row = false;
+                    return Ok(());
+                }
+                SharedRow::Scan(scan, skip_key) => {
+                    self.own_scan = Some(scan);
+                    self.skip_key = skip_key;
+                    self.has_row = false;
+                }
+            }
+        }
+
+        let scan = self.own_scan.as_mut().unwrap();
+        scan.advance()?;
+        if let Some(skip_key) = self.skip_key.take() {
+            let key_columns = self.shared.borrow().table.key_columns().to_vec();
+            let seen = scan.get().map_or(false, |(tuple, _)| {
+                key_columns
+                    .iter()
+                    .zip(&skip_key)
+                    .all(|(column, datum)| tuple[*column] == *datum)
+            });
+            if seen {
+                scan.advance()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn get(&self) -> Option<(&[Datum], i64)> {
+        match &self.own_scan {
+            Some(scan) => scan.get(),
+            None if self.has_row => Some((&self.row, self.freq)),
+            None => None,
+        }
+    }
+
+    fn column_count(&self) -> usize {
+        self.column_count
+    }
+}
+
+impl Drop for SharedTableScanExecutor {
+    fn drop(&mut self) {
+        if let Ok(mut shared) = self.shared.try_borrow_mut() {
+            shared.finish(self.reader);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use catalog::{Catalog, TableOrView};
+    use storage::Storage;
+
+    fn shared_scan(max_rows: usize) -> Rc<RefCell<SharedScan>> {
+        let storage = Storage::new_in_mem().unwrap();
+        let catalog = Catalog::new(storage).unwrap();
+        let table = if let TableOrView::Table(table) =
+            catalog.item("incresql", "databases").unwrap().item
+        {
+            table
+        } else {
+            panic!()
+        };
+        let table_scan = TableScan {
+            table,
+            timestamp: LogicalTimestamp::MAX,
+            ranges: vec![ScanRange::default()],
+            sample: None,
+        };
+        Rc::new(RefCell::new(SharedScan::new(&table_scan, max_rows)))
+    }
+
+    fn read_all(executor: &mut SharedTableScanExecutor) -> Result<Vec<String>, ExecutionError> {
+        let mut names = vec![];
+        while let Some((tuple, _freq)) = executor.next()? {
+            names.push(tuple[0].as_text().to_string());
+        }
+        Ok(names)
+    }
+
+    #[test]
+    fn test_shared_scan() -> Result<(), ExecutionError> {
+        let expected = vec!["default", "incresql", "information_schema"];
+        let shared = shared_scan(SHARED_SCAN_BUFFER_ROWS);
+        let mut a = SharedTableScanExecutor::new(Rc::clone(&shared));
+        let mut b = SharedTableScanExecutor::new(Rc::clone(&shared));
+
+        // In step
+        assert_eq!(a.next()?, Some(([Datum::from("default")].as_ref(), 1)));
+        assert_eq!(b.next()?, Some(([Datum::from("default")].as_ref(), 1)));
+        assert!(shared.borrow().rows.is_empty());
+
+        // One reader behind the other
+        assert_eq!(read_all(&mut a)?, &expected[1..]);
+        assert_eq!(shared.borrow().rows.len(), 2);
+        assert_eq!(read_all(&mut b)?, &expected[1..]);
+        assert!(shared.borrow().rows.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_shared_scan_full_buffer() -> Result<(), ExecutionError> {
+        let expected = vec!["default", "incresql", "information_schema"];
+        let shared = shared_scan(1);
+        let mut a = SharedTableScanExecutor::new(Rc::clone(&shared));
+        let mut b = SharedTableScanExecutor::new(Rc::clone(&shared));
+        let mut c = SharedTableScanExecutor::new(Rc::clone(&shared));
+
+        // a takes the scan for itself once it gets a row ahead, b and c carry on from the row
+        // that was buffered for them
+        assert_eq!(read_all(&mut a)?, expected);
+        assert!(a.own_scan.is_some());
+        assert_eq!(read_all(&mut b)?, expected);
+        assert_eq!(read_all(&mut c)?, expected);
+        assert!(shared.borrow().rows.is_empty());
+
+        // A reader that's dropped doesn't hold onto rows
+        let shared = shared_scan(SHARED_SCAN_BUFFER_ROWS);
+        let mut a = SharedTableScanExecutor::new(Rc::clone(&shared));
+        let b = SharedTableScanExecutor::new(Rc::clone(&shared));
+        a.next()?;
+        assert_eq!(shared.borrow().rows.len(), 1);
+        drop(b);
+        assert!(shared.borrow().rows.is_empty());
+        assert_eq!(read_all(&mut a)?, &expected[1..]);
+        Ok(())
+    }
+}
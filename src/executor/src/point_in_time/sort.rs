@@ -1,3 +1,4 @@
+use crate::arena::ExprArena;
 use crate::point_in_time::BoxedExecutor;
 use crate::scalar_expression::EvalScalar;
 use crate::utils::right_size_new_to;
@@ -19,6 +20,7 @@ pub struct SortExecutor {
     state: State,
     tuple_buffer: Vec<Datum<'static>>,
     freq: i64,
+    arena: ExprArena,
 }
 
 #[derive(Eq, PartialEq)]
@@ -60,6 +62,7 @@ impl SortExecutor {
             state: State::Ready,
             tuple_buffer,
             freq: 0,
+            arena: ExprArena::default(),
         }
     }
 }
@@ -119,8 +122,10 @@ impl SortExecutor {
         while let Some((tuple, freq)) = self.source.next()? {
             let start = self.sort_buffer.len() as u32;
 
-            for sort_expr in &mut self.sort_expressions {
-                let datum = sort_expr.expression.eval_scalar(&self.session, tuple);
+            for sort_expr in &self.sort_expressions {
+                let datum = sort_expr
+                    .expression
+                    .eval_scalar(&self.session, tuple, &mut self.arena);
                 datum.as_sortable_bytes(sort_expr.ordering, &mut self.sort_buffer);
             }
 
@@ -1,4 +1,5 @@
 use crate::aggregate_expression::{AggregateExpression, EvalAggregateRow};
+use crate::arena::ExprArena;
 use crate::point_in_time::BoxedExecutor;
 use crate::utils::{right_size_new, transmute_muf_buf};
 use crate::ExecutionError;
@@ -22,6 +23,7 @@ pub struct SortedGroupExecutor {
     current_state: Vec<Datum<'static>>,
     output_tuple: Vec<Datum<'static>>,
     state: State,
+    arena: ExprArena,
 }
 
 #[derive(Eq, PartialEq)]
@@ -49,6 +51,7 @@ impl SortedGroupExecutor {
             current_state,
             output_tuple,
             state: State::Initial,
+            arena: ExprArena::default(),
         }
     }
 }
@@ -70,13 +73,19 @@ impl TupleIter for SortedGroupExecutor {
         if self.key_len == 0 && self.state == State::Initial {
             self.expressions.reset(&mut self.current_state);
             while let Some((tuple, freq)) = self.source.next()? {
-                self.expressions
-                    .apply(&self.session, tuple, freq, &mut self.current_state);
+                self.expressions.apply(
+                    &self.session,
+                    tuple,
+                    freq,
+                    &mut self.current_state,
+                    &mut self.arena,
+                );
             }
             self.expressions.finalize(
                 &self.session,
                 &self.current_state,
                 transmute_muf_buf(&mut self.output_tuple),
+                &mut self.arena,
             );
             self.state = State::Processing;
         } else if self.key_len == 0 && self.state == State::Processing {
@@ -86,8 +95,13 @@ impl TupleIter for SortedGroupExecutor {
 
             let group_hash = if let Some((tuple, freq)) = self.source.next()? {
                 self.expressions.reset(&mut self.current_state);
-                self.expressions
-                    .apply(&self.session, tuple, freq, &mut self.current_state);
+                self.expressions.apply(
+                    &self.session,
+                    tuple,
+                    freq,
+                    &mut self.current_state,
+                    &mut self.arena,
+                );
                 hash_tuple(tuple, self.key_len)
             } else {
                 self.state = State::Done;
@@ -103,11 +117,17 @@ impl TupleIter for SortedGroupExecutor {
                             &self.session,
                             &self.current_state,
                             transmute_muf_buf(&mut self.output_tuple),
+                            &mut self.arena,
                         );
                         break;
                     }
-                    self.expressions
-                        .apply(&self.session, tuple, freq, &mut self.current_state);
+                    self.expressions.apply(
+                        &self.session,
+                        tuple,
+                        freq,
+                        &mut self.current_state,
+                        &mut self.arena,
+                    );
                     // "advance" the inter
                     self.source.lock_in();
                 } else {
@@ -117,6 +137,7 @@ impl TupleIter for SortedGroupExecutor {
                         &self.session,
                         &self.current_state,
                         transmute_muf_buf(&mut self.output_tuple),
+                        &mut self.arena,
                     );
                     break;
                 }
@@ -184,7 +205,6 @@ mod tests {
                     },
                 )]
                 .into_boxed_slice(),
-                expr_buffer: vec![].into_boxed_slice(),
                 signature: Box::new(sig),
             }),
         ];
@@ -229,7 +249,6 @@ mod tests {
         let expressions = vec![Expression::CompiledAggregate(CompiledAggregate {
             function: count_function.as_aggregate(),
             args: vec![].into_boxed_slice(),
-            expr_buffer: vec![].into_boxed_slice(),
             signature: Box::new(sig),
         })];
 
@@ -0,0 +1,72 @@
+use crate::ExecutionError;
+use data::{Datum, LogicalTimestamp, TupleIter};
+use storage::{StorageError, Table};
+
+/// Reads the changes made to a table between two timestamps, rows that were added come out with
+/// a positive freq and rows that were removed with a negative one. Feeding these through the
+/// operators of a linear query (filters, projects etc) gives the change to the query's rows.
+pub struct TableChangesExecutor {
+    // We must drop changes_iter first
+    changes_iter: Box<dyn TupleIter<E = StorageError>>,
+    table: Table,
+}
+
+impl TableChangesExecutor {
+    pub fn new(table: Table, from: LogicalTimestamp, to: LogicalTimestamp) -> Self {
+        // The lifetime of an rocksdb iter is tied to the underlying rocksdb.
+        // In our case table holds an Arc<db> so if we keep that alive we're ok.
+        let changes_iter: Box<dyn TupleIter<E = StorageError>> =
+            Box::from(table.changes_scan(from, to));
+        let changes_iter = unsafe {
+            std::mem::transmute::<
+                Box<dyn TupleIter<E = StorageError>>,
+                Box<dyn TupleIter<E = StorageError>>,
+            >(changes_iter)
+        };
+        TableChangesExecutor {
+            changes_iter,
+            table,
+        }
+    }
+}
+
+impl TupleIter for TableChangesExecutor {
+    type E = ExecutionError;
+
+    fn advance(&mut self) -> Result<(), ExecutionError> {
+        Ok(self.changes_iter.advance()?)
+    }
+
+    fn get(&self) -> Option<(&[Datum], i64)> {
+        self.changes_iter.get()
+    }
+
+    fn column_count(&self) -> usize {
+        self.table.column_count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::SortOrder;
+    use storage::Storage;
+
+    #[test]
+    fn test_table_changes_executor() -> Result<(), ExecutionError> {
+        let storage = Storage::new_in_mem()?;
+        let table = storage.table(1234, 1, vec![SortOrder::Asc]);
+        table.atomic_write::<_, StorageError>(|writer| {
+            writer.write_tuple(&table, &[Datum::from(1)], LogicalTimestamp::new(10), 1)?;
+            writer.write_tuple(&table, &[Datum::from(2)], LogicalTimestamp::new(10), 1)?;
+            writer.write_tuple(&table, &[Datum::from(1)], LogicalTimestamp::new(20), -1)?;
+            Ok(())
+        })?;
+
+        let mut executor =
+            TableChangesExecutor::new(table, LogicalTimestamp::new(15), LogicalTimestamp::new(25));
+        assert_eq!(executor.next()?, Some(([Datum::from(1)].as_ref(), -1)));
+        assert_eq!(executor.next()?, None);
+        Ok(())
+    }
+}
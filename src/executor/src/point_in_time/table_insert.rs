@@ -1,20 +1,42 @@
 use crate::point_in_time::BoxedExecutor;
 use crate::ExecutionError;
-use data::{Datum, LogicalTimestamp, PeekableIter, TupleIter};
+use ast::rel::logical::ColumnSchema;
+use data::json_schema::validate;
+use data::{Datum, PeekableIter, Session, TupleIter};
+use std::sync::Arc;
 use storage::Table;
 
 /// When advance is called this simply inserts all tuples
-/// into the table
+/// into the table, a batch at a time through the storage's bulk write path.
+/// If we're inserting from a resumable file source, the offsets it has read up to are written
+/// in the same batch as the rows so a restart picks up exactly where we left off.
+/// Text, json and bytes values bigger than the session's max_value_bytes fail the insert, as do
+/// json values that don't match their column's json schema.
 pub struct TableInsertExecutor {
     source: PeekableIter<dyn TupleIter<E = ExecutionError>>,
+    session: Arc<Session>,
     table: Table,
+    source_offsets: Option<Table>,
+    batch_rows: usize,
+    json_schemas: Vec<ColumnSchema>,
 }
 
 impl TableInsertExecutor {
-    pub fn new(source: BoxedExecutor, table: Table) -> Self {
+    pub fn new(
+        source: BoxedExecutor,
+        session: Arc<Session>,
+        table: Table,
+        source_offsets: Option<Table>,
+        batch_rows: usize,
+        json_schemas: Vec<ColumnSchema>,
+    ) -> Self {
         TableInsertExecutor {
             source: PeekableIter::from(source),
+            session,
             table,
+            source_offsets,
+            batch_rows,
+            json_schemas,
         }
     }
 }
@@ -25,18 +47,39 @@ impl TupleIter for TableInsertExecutor {
     fn advance(&mut self) -> Result<(), ExecutionError> {
         let iter = &mut self.source;
         let table = &self.table;
+        let session = &self.session;
+        let source_offsets = &self.source_offsets;
+        let batch_rows = self.batch_rows;
+        let json_schemas = &self.json_schemas;
+        let max_value_bytes = *session.max_value_bytes.read().unwrap();
+        let mut rows: Vec<(Vec<Datum<'static>>, i64)> = vec![];
 
         while iter.peek()?.is_some() {
+            // Chunk our write batches as we don't want to blow out our memory.
+            // We'll lose atomicity but tables are only really meant for lookup
+            // data etc not for etl type workloads
+            rows.clear();
+            while let Some((tuple, freq)) = iter.next()? {
+                // Bail before the row that failed its cast is written
+                if let Some(err) = session.take_cast_error() {
+                    return Err(ExecutionError::CastError(err));
+                }
+                check_value_sizes(tuple, max_value_bytes)?;
+                check_json_schemas(tuple, json_schemas)?;
+                rows.push((tuple.iter().map(Datum::as_static).collect(), freq));
+                if rows.len() == batch_rows {
+                    break;
+                }
+            }
             table.atomic_write::<_, ExecutionError>(|batch| {
-                // Chunk our write batches as we don't want to blow out our memory.
-                // We'll lose atomicity but tables are only really meant for lookup
-                // data etc not for etl type workloads
-                let mut c = 10000;
-                while let Some((tuple, freq)) = iter.next()? {
-                    batch.write_tuple(table, tuple, LogicalTimestamp::now(), freq)?;
-                    c -= 1;
-                    if c == 0 {
-                        break;
+                batch.write_tuples(table, &rows, table.clock().now())?;
+                if let Some(source_offsets) = source_offsets {
+                    for (source, offset) in session.pending_source_offsets.lock().unwrap().drain() {
+                        batch.system_write_tuple(
+                            source_offsets,
+                            &[Datum::from(source), Datum::from(offset)],
+                            1,
+                        );
                     }
                 }
                 Ok(())
@@ -54,13 +97,43 @@ impl TupleIter for TableInsertExecutor {
     }
 }
 
+/// Errors if any of the values stored as bytes are bigger than max_value_bytes
+pub(super) fn check_value_sizes(
+    tuple: &[Datum],
+    max_value_bytes: u64,
+) -> Result<(), ExecutionError> {
+    for datum in tuple {
+        if let Some(bytes) = datum.as_maybe_bytea() {
+            if bytes.len() as u64 > max_value_bytes {
+                return Err(ExecutionError::ValueTooLarge(bytes.len(), max_value_bytes));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Errors if any of the json values don't match their column's json schema, nulls always match
+fn check_json_schemas(
+    tuple: &[Datum],
+    json_schemas: &[ColumnSchema],
+) -> Result<(), ExecutionError> {
+    for json_schema in json_schemas {
+        if let Some(json) = tuple[json_schema.offset].as_maybe_json() {
+            validate(json, json_schema.schema.as_json()).map_err(|reason| {
+                ExecutionError::JsonSchemaViolation(json_schema.column.clone(), reason)
+            })?;
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::point_in_time::values::ValuesExecutor;
     use crate::ExecutionError;
     use catalog::{Catalog, TableOrView};
-    use data::DataType;
+    use data::{DataType, LogicalTimestamp};
 
     #[test]
     fn test_insert_executor() -> Result<(), ExecutionError> {
@@ -83,7 +156,14 @@ mod tests {
         ];
         let source = Box::from(ValuesExecutor::new(Box::from(values.into_iter()), 2));
 
-        let mut executor = TableInsertExecutor::new(source, table.clone());
+        let mut executor = TableInsertExecutor::new(
+            source,
+            Arc::new(Session::new(1)),
+            table.clone(),
+            None,
+            2,
+            vec![],
+        );
         assert_eq!(executor.next()?, None);
 
         let mut table_iter = table.full_scan(LogicalTimestamp::MAX);
@@ -93,4 +173,14 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_check_value_sizes() {
+        let tuple = [Datum::from(1), Datum::from("abcd")];
+        assert!(check_value_sizes(&tuple, 4).is_ok());
+        assert!(matches!(
+            check_value_sizes(&tuple, 3),
+            Err(ExecutionError::ValueTooLarge(4, 3))
+        ));
+    }
 }
@@ -1,28 +1,88 @@
 use crate::ExecutionError;
+use ast::rel::logical::{SampleMethod, TableSample};
+use ast::rel::point_in_time::ScanRange;
 use data::{Datum, LogicalTimestamp, TupleIter};
+use num_traits::ToPrimitive;
+use std::time::{SystemTime, UNIX_EPOCH};
 use storage::{StorageError, Table};
 
+/// Scans each of the ranges of the table in turn.
 pub struct TableScanExecutor {
     // We must drop scan_iter first
-    scan_iter: Box<dyn TupleIter<E = StorageError>>,
-    #[allow(dead_code)]
+    scan_iter: Option<Box<dyn TupleIter<E = StorageError>>>,
+    // The range scan_iter is over
+    range: Option<ScanRange>,
+    ranges: std::vec::IntoIter<ScanRange>,
+    timestamp: LogicalTimestamp,
     table: Table,
+    sampler: Option<Sampler>,
+    // The freq of the current row, BERNOULLI sampling only keeps some of a row's freq
+    freq: i64,
 }
 
 impl TableScanExecutor {
-    pub fn new(table: Table, timestamp: LogicalTimestamp) -> Self {
+    pub fn new(
+        table: Table,
+        timestamp: LogicalTimestamp,
+        ranges: Vec<ScanRange>,
+        sample: Option<&TableSample>,
+    ) -> Self {
+        TableScanExecutor {
+            scan_iter: None,
+            range: None,
+            ranges: ranges.into_iter(),
+            timestamp,
+            table,
+            sampler: sample.map(Sampler::new),
+            freq: 0,
+        }
+    }
+
+    /// The ranges left to scan starting from the current row, along with the row's key, so the
+    /// scan can be picked up by another executor. The current row will be the first row read
+    /// from them unless it's since been deleted.
+    pub fn remaining_ranges(&self) -> Option<(Vec<ScanRange>, Vec<Datum<'static>>)> {
+        let (tuple, _freq) = self.get()?;
+        let key: Vec<_> = self
+            .table
+            .key_columns()
+            .iter()
+            .map(|column| tuple[*column].as_static())
+            .collect();
+        let mut ranges = vec![ScanRange {
+            from: Some(key.clone()),
+            to: self.range.as_ref().and_then(|range| range.to.clone()),
+        }];
+        ranges.extend(self.ranges.as_slice().iter().cloned());
+        Some((ranges, key))
+    }
+
+    fn range_scan(&mut self, range: &ScanRange) -> Box<dyn TupleIter<E = StorageError>> {
         // The lifetime of an rocksdb iter is tied to the underlying rocksdb.
         // In our case table holds an Arc<db> so if we keep that alive we're ok.
         // so below we fudge the lifetimes to make it work
-        let scan_iter = Box::from(table.full_scan(timestamp));
-        let scan_iter = unsafe {
+        let scan_iter: Box<dyn TupleIter<E = StorageError>> = match &mut self.sampler {
+            Some(sampler) if sampler.method == SampleMethod::System => {
+                let mut block_sampler = sampler.fork();
+                Box::from(self.table.sampled_range_scan(
+                    range.from.as_deref(),
+                    range.to.as_deref(),
+                    self.timestamp,
+                    Box::new(move || block_sampler.keep_block()),
+                ))
+            }
+            _ => Box::from(self.table.range_scan(
+                range.from.as_deref(),
+                range.to.as_deref(),
+                self.timestamp,
+            )),
+        };
+        unsafe {
             std::mem::transmute::<
                 Box<dyn TupleIter<E = StorageError>>,
                 Box<dyn TupleIter<E = StorageError>>,
             >(scan_iter)
-        };
-
-        TableScanExecutor { scan_iter, table }
+        }
     }
 }
 
@@ -30,16 +90,129 @@ impl TupleIter for TableScanExecutor {
     type E = ExecutionError;
 
     fn advance(&mut self) -> Result<(), ExecutionError> {
-        self.scan_iter.advance()?;
-        Ok(())
+        loop {
+            if let Some(scan_iter) = &mut self.scan_iter {
+                scan_iter.advance()?;
+                if let Some((_tuple, freq)) = scan_iter.get() {
+                    self.freq = match &mut self.sampler {
+                        Some(sampler) if sampler.method == SampleMethod::Bernoulli => {
+                            sampler.sample_freq(freq)
+                        }
+                        _ => freq,
+                    };
+                    if self.freq != 0 {
+                        return Ok(());
+                    }
+                    // None of the row was sampled, try the next row from the same range
+                    continue;
+                }
+            }
+
+            match self.ranges.next() {
+                Some(range) => {
+                    self.scan_iter = Some(self.range_scan(&range));
+                    self.range = Some(range);
+                }
+                None => {
+                    self.scan_iter = None;
+                    self.range = None;
+                    return Ok(());
+                }
+            }
+        }
     }
 
     fn get(&self) -> Option<(&[Datum], i64)> {
-        self.scan_iter.get()
+        self.scan_iter
+            .as_ref()
+            .and_then(|scan_iter| scan_iter.get())
+            .map(|(tuple, _freq)| (tuple, self.freq))
     }
 
     fn column_count(&self) -> usize {
-        self.scan_iter.column_count()
+        self.table.column_count()
+    }
+}
+
+/// Decides which rows to keep for a TABLESAMPLE clause. BERNOULLI samples each unit of a row's
+/// freq on its own while SYSTEM keeps or skips whole blocks of rows, which the storage layer
+/// seeks past.
+struct Sampler {
+    method: SampleMethod,
+    probability: f64,
+    state: u64,
+}
+
+impl Sampler {
+    fn new(sample: &TableSample) -> Self {
+        let seed = sample.seed.map(|seed| seed as u64).unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_nanos() as u64)
+                .unwrap_or_default()
+        });
+        Sampler {
+            method: sample.method,
+            probability: sample.percentage.to_f64().unwrap_or_default() / 100.0,
+            state: seed,
+        }
+    }
+
+    /// A sampler seeded off this one, for handing off to the storage layer for each range.
+    fn fork(&mut self) -> Sampler {
+        self.next_f64();
+        Sampler {
+            method: self.method,
+            probability: self.probability,
+            state: self.state,
+        }
+    }
+
+    fn keep_block(&mut self) -> bool {
+        self.next_f64() < self.probability
+    }
+
+    /// The part of freq that's kept, ie freq trials each kept with the sampling probability.
+    fn sample_freq(&mut self, freq: i64) -> i64 {
+        let kept = self.binomial(freq.wrapping_abs() as u64) as i64;
+        if freq < 0 {
+            -kept
+        } else {
+            kept
+        }
+    }
+
+    /// The number of successes out of trials. Rather than rolling for each trial this jumps
+    /// straight to the next success, the number of failures before it being geometrically
+    /// distributed.
+    fn binomial(&mut self, trials: u64) -> u64 {
+        if self.probability >= 1.0 {
+            return trials;
+        } else if self.probability <= 0.0 {
+            return 0;
+        }
+        let log_failure = (1.0 - self.probability).ln();
+        let mut successes = 0;
+        let mut remaining = trials;
+        loop {
+            let failures = ((1.0 - self.next_f64()).ln() / log_failure).floor();
+            if failures >= remaining as f64 {
+                return successes;
+            }
+            successes += 1;
+            remaining -= failures as u64 + 1;
+        }
+    }
+
+    /// A uniformly distributed number in [0, 1), using splitmix64. We don't need anything
+    /// cryptographically secure, just cheap and repeatable for a given seed.
+    fn next_f64(&mut self) -> f64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1_u64 << 53) as f64
     }
 }
 
@@ -47,6 +220,7 @@ impl TupleIter for TableScanExecutor {
 mod tests {
     use super::*;
     use catalog::{Catalog, TableOrView};
+    use data::rust_decimal::Decimal;
     use storage::Storage;
 
     #[test]
@@ -61,7 +235,50 @@ mod tests {
             panic!()
         };
 
-        let mut executor = TableScanExecutor::new(table, LogicalTimestamp::MAX);
+        let mut executor = TableScanExecutor::new(
+            table,
+            LogicalTimestamp::MAX,
+            vec![ScanRange::default()],
+            None,
+        );
+        assert_eq!(
+            executor.next()?,
+            Some(([Datum::from("default")].as_ref(), 1))
+        );
+        assert_eq!(
+            executor.next()?,
+            Some(([Datum::from("incresql")].as_ref(), 1))
+        );
+        assert_eq!(
+            executor.next()?,
+            Some(([Datum::from("information_schema")].as_ref(), 1))
+        );
+        assert_eq!(executor.next()?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_table_scan_executor_ranges() -> Result<(), ExecutionError> {
+        let storage = Storage::new_in_mem()?;
+        let catalog = Catalog::new(storage).unwrap();
+        let table = if let TableOrView::Table(table) =
+            catalog.item("incresql", "databases").unwrap().item
+        {
+            table
+        } else {
+            panic!()
+        };
+
+        let range = |from: &str, to: &str| ScanRange {
+            from: Some(vec![Datum::from(from.to_string())]),
+            to: Some(vec![Datum::from(to.to_string())]),
+        };
+        let mut executor = TableScanExecutor::new(
+            table.clone(),
+            LogicalTimestamp::MAX,
+            vec![range("default", "default"), range("i", "j")],
+            None,
+        );
         assert_eq!(
             executor.next()?,
             Some(([Datum::from("default")].as_ref(), 1))
@@ -75,6 +292,44 @@ mod tests {
             Some(([Datum::from("information_schema")].as_ref(), 1))
         );
         assert_eq!(executor.next()?, None);
+
+        let mut executor = TableScanExecutor::new(table, LogicalTimestamp::MAX, vec![], None);
+        assert_eq!(executor.next()?, None);
         Ok(())
     }
+
+    #[test]
+    fn test_sampler() {
+        let sample = |method, percentage: i32| TableSample {
+            method,
+            percentage: Decimal::from(percentage),
+            seed: Some(42),
+        };
+
+        let mut sampler = Sampler::new(&sample(SampleMethod::Bernoulli, 10));
+        let kept: i64 = (0..10000).map(|_| sampler.sample_freq(1)).sum();
+        assert!(kept > 800 && kept < 1200, "kept {}", kept);
+
+        // Each unit of a row's freq is sampled
+        let kept = sampler.sample_freq(10000);
+        assert!(kept > 800 && kept < 1200, "kept {}", kept);
+        let kept = sampler.sample_freq(-10000);
+        assert!(kept < -800 && kept > -1200, "kept {}", kept);
+
+        let mut sampler = Sampler::new(&sample(SampleMethod::Bernoulli, 0));
+        assert_eq!(sampler.sample_freq(10000), 0);
+        let mut sampler = Sampler::new(&sample(SampleMethod::Bernoulli, 100));
+        assert_eq!(sampler.sample_freq(-3), -3);
+
+        let mut sampler = Sampler::new(&sample(SampleMethod::System, 100));
+        assert!((0..100).all(|_| sampler.keep_block()));
+
+        // The same seed gives the same sample
+        let mut a = Sampler::new(&sample(SampleMethod::Bernoulli, 50));
+        let mut b = Sampler::new(&sample(SampleMethod::Bernoulli, 50));
+        assert!((0..100).all(|_| a.sample_freq(5) == b.sample_freq(5)));
+        let mut a = Sampler::new(&sample(SampleMethod::System, 50)).fork();
+        let mut b = Sampler::new(&sample(SampleMethod::System, 50)).fork();
+        assert!((0..100).all(|_| a.keep_block() == b.keep_block()));
+    }
 }
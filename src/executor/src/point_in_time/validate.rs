@@ -0,0 +1,185 @@
+use crate::point_in_time::BoxedExecutor;
+use crate::ExecutionError;
+use data::{DataType, Datum, TupleIter};
+use std::collections::BTreeMap;
+
+/// Checks that each row coming out of the source has the number of columns and the types the
+/// planner said it would.
+pub struct CheckTypesExecutor {
+    source: BoxedExecutor,
+    datatypes: Vec<DataType>,
+}
+
+impl CheckTypesExecutor {
+    pub fn new(source: BoxedExecutor, datatypes: Vec<DataType>) -> Self {
+        CheckTypesExecutor { source, datatypes }
+    }
+}
+
+impl TupleIter for CheckTypesExecutor {
+    type E = ExecutionError;
+
+    fn advance(&mut self) -> Result<(), ExecutionError> {
+        self.source.advance()?;
+        if let Some((tuple, _freq)) = self.source.get() {
+            if tuple.len() != self.datatypes.len() {
+                return Err(ExecutionError::ValidationError(format!(
+                    "Expected rows of {} columns but got {}",
+                    self.datatypes.len(),
+                    tuple.len()
+                )));
+            }
+            for (idx, (datum, datatype)) in tuple.iter().zip(&self.datatypes).enumerate() {
+                if !datum.fits(*datatype) {
+                    return Err(ExecutionError::ValidationError(format!(
+                        "Expected a {} in column {} but got {:?}",
+                        datatype, idx, datum
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn get(&self) -> Option<(&[Datum], i64)> {
+        self.source.get()
+    }
+
+    fn column_count(&self) -> usize {
+        self.source.column_count()
+    }
+}
+
+/// Passes the source's rows through while keeping track of them, once the source's exhausted
+/// they're checked against the rows of another executor that should give the same results.
+/// Rows are compared ignoring their order.
+pub struct CompareExecutor {
+    source: BoxedExecutor,
+    // Taken once it's been compared against
+    expected: Option<BoxedExecutor>,
+    seen: BTreeMap<Vec<Datum<'static>>, i64>,
+}
+
+impl CompareExecutor {
+    pub fn new(source: BoxedExecutor, expected: BoxedExecutor) -> Self {
+        CompareExecutor {
+            source,
+            expected: Some(expected),
+            seen: BTreeMap::new(),
+        }
+    }
+}
+
+impl TupleIter for CompareExecutor {
+    type E = ExecutionError;
+
+    fn advance(&mut self) -> Result<(), ExecutionError> {
+        self.source.advance()?;
+        if let Some((tuple, freq)) = self.source.get() {
+            let row = tuple.iter().map(Datum::as_static).collect();
+            *self.seen.entry(row).or_insert(0) += freq;
+            return Ok(());
+        }
+
+        let mut expected = match self.expected.take() {
+            Some(mut expected) => collect_rows(&mut expected)?,
+            None => return Ok(()),
+        };
+        for (row, freq) in std::mem::take(&mut self.seen) {
+            let expected_freq = expected.remove(&row).unwrap_or(0);
+            if freq != expected_freq {
+                return Err(mismatch(&row, freq, expected_freq));
+            }
+        }
+        if let Some((row, expected_freq)) = expected.into_iter().next() {
+            return Err(mismatch(&row, 0, expected_freq));
+        }
+        Ok(())
+    }
+
+    fn get(&self) -> Option<(&[Datum], i64)> {
+        self.source.get()
+    }
+
+    fn column_count(&self) -> usize {
+        self.source.column_count()
+    }
+}
+
+/// Reads all of the executor's rows, rows that cancel each other out are dropped.
+fn collect_rows(
+    executor: &mut BoxedExecutor,
+) -> Result<BTreeMap<Vec<Datum<'static>>, i64>, ExecutionError> {
+    let mut rows = BTreeMap::new();
+    while let Some((tuple, freq)) = executor.next()? {
+        let row = tuple.iter().map(Datum::as_static).collect();
+        *rows.entry(row).or_insert(0) += freq;
+    }
+    rows.retain(|_row, freq| *freq != 0);
+    Ok(rows)
+}
+
+fn mismatch(row: &[Datum], freq: i64, expected_freq: i64) -> ExecutionError {
+    ExecutionError::ValidationError(format!(
+        "Optimized plan returned {:?} {} times but the unoptimized plan returned it {} times",
+        row, freq, expected_freq
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point_in_time::values::ValuesExecutor;
+
+    fn values(rows: Vec<Vec<Datum<'static>>>) -> BoxedExecutor {
+        Box::from(ValuesExecutor::new(Box::from(rows.into_iter()), 1))
+    }
+
+    fn read_all(mut executor: BoxedExecutor) -> Result<usize, ExecutionError> {
+        let mut count = 0;
+        while executor.next()?.is_some() {
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    #[test]
+    fn test_check_types_executor() {
+        let executor = CheckTypesExecutor::new(
+            values(vec![vec![Datum::from(1)], vec![Datum::Null]]),
+            vec![DataType::Integer],
+        );
+        assert_eq!(read_all(Box::from(executor)), Ok(2));
+
+        let executor =
+            CheckTypesExecutor::new(values(vec![vec![Datum::from(1)]]), vec![DataType::Text]);
+        assert!(read_all(Box::from(executor)).is_err());
+
+        let executor = CheckTypesExecutor::new(
+            values(vec![vec![Datum::from(1)]]),
+            vec![DataType::Integer, DataType::Integer],
+        );
+        assert!(read_all(Box::from(executor)).is_err());
+    }
+
+    #[test]
+    fn test_compare_executor() {
+        let executor = CompareExecutor::new(
+            values(vec![vec![Datum::from(1)], vec![Datum::from(2)]]),
+            values(vec![vec![Datum::from(2)], vec![Datum::from(1)]]),
+        );
+        assert_eq!(read_all(Box::from(executor)), Ok(2));
+
+        let executor = CompareExecutor::new(
+            values(vec![vec![Datum::from(1)], vec![Datum::from(1)]]),
+            values(vec![vec![Datum::from(1)]]),
+        );
+        assert!(read_all(Box::from(executor)).is_err());
+
+        let executor = CompareExecutor::new(
+            values(vec![vec![Datum::from(1)]]),
+            values(vec![vec![Datum::from(1)], vec![Datum::from(2)]]),
+        );
+        assert!(read_all(Box::from(executor)).is_err());
+    }
+}
@@ -0,0 +1,276 @@
+use crate::aggregate_expression::AggregateExpression;
+use crate::arena::ExprArena;
+use crate::point_in_time::BoxedExecutor;
+use crate::scalar_expression::EvalScalar;
+use crate::utils::right_size_new_to;
+use crate::ExecutionError;
+use ast::expr::{Expression, WindowFrame};
+use data::{Datum, PeekableIter, Session, TupleIter};
+use std::sync::Arc;
+
+/// Evaluates window functions, each row is output followed by the value of each window
+/// function for it. The source has to arrive sorted by the partition keys then the window's
+/// order by, each partition is buffered up in turn and the functions are run over it.
+pub struct WindowExecutor {
+    source: PeekableIter<dyn TupleIter<E = ExecutionError>>,
+    session: Arc<Session>,
+    partition_by: Vec<Expression>,
+    order_by: Vec<Expression>,
+    functions: Vec<(AggregateExpression, WindowFrame)>,
+    // The rows of the current partition, these get the function values appended once the
+    // whole partition has been read
+    rows: Vec<Vec<Datum<'static>>>,
+    // The order by values of each row, rows with the same values are peers
+    order_keys: Vec<Vec<Datum<'static>>>,
+    position: usize,
+    arena: ExprArena,
+}
+
+impl WindowExecutor {
+    pub fn new(
+        source: BoxedExecutor,
+        session: Arc<Session>,
+        partition_by: Vec<Expression>,
+        order_by: Vec<Expression>,
+        functions: Vec<(Expression, WindowFrame)>,
+    ) -> Self {
+        let functions = functions
+            .iter()
+            .map(|(function, frame)| (AggregateExpression::from(function), *frame))
+            .collect();
+        WindowExecutor {
+            source: PeekableIter::from(source),
+            session,
+            partition_by,
+            order_by,
+            functions,
+            rows: vec![],
+            order_keys: vec![],
+            position: 0,
+            arena: ExprArena::default(),
+        }
+    }
+
+    /// Reads the rows of the next partition from the source, leaves rows empty if the source
+    /// is exhausted.
+    fn read_partition(&mut self) -> Result<(), ExecutionError> {
+        let WindowExecutor {
+            source,
+            session,
+            partition_by,
+            order_by,
+            rows,
+            order_keys,
+            arena,
+            ..
+        } = self;
+        rows.clear();
+        order_keys.clear();
+        let mut partition_key: Option<Vec<Datum<'static>>> = None;
+
+        while let Some((tuple, freq)) = source.peek()? {
+            let key: Vec<_> = partition_by
+                .iter()
+                .map(|expr| expr.eval_scalar(session, tuple, arena).as_static())
+                .collect();
+            match &partition_key {
+                Some(current) if *current != key => break,
+                Some(_) => {}
+                None => partition_key = Some(key),
+            }
+
+            let order_key: Vec<_> = order_by
+                .iter()
+                .map(|expr| expr.eval_scalar(session, tuple, arena).as_static())
+                .collect();
+            let row: Vec<_> = tuple.iter().map(Datum::as_static).collect();
+            // Each copy of a row is a row of its own as far as row numbers and frames go
+            for _ in 0..freq {
+                rows.push(row.clone());
+                order_keys.push(order_key.clone());
+            }
+            source.lock_in();
+        }
+        Ok(())
+    }
+
+    /// Runs each function over the buffered partition, appending its value to each row
+    fn evaluate_partition(&mut self) {
+        let session = &self.session;
+        let rows = &self.rows;
+        let order_keys = &self.order_keys;
+        let arena = &mut self.arena;
+        let mut columns = vec![];
+
+        for (function, frame) in &self.functions {
+            let mut state = right_size_new_to(function.state_len());
+            function.reset(&mut state);
+            let mut values = Vec::with_capacity(rows.len());
+
+            match *frame {
+                // Everything up to and including the current row's peers
+                WindowFrame::Range => {
+                    let mut start = 0;
+                    while start < rows.len() {
+                        let mut end = start + 1;
+                        while end < rows.len() && order_keys[end] == order_keys[start] {
+                            end += 1;
+                        }
+                        for row in &rows[start..end] {
+                            function.apply(session, row, 1, &mut state, arena);
+                        }
+                        let value = function.finalize(session, &state, arena).as_static();
+                        values.resize(end, value);
+                        start = end;
+                    }
+                }
+                WindowFrame::Rows(None) => {
+                    for row in rows {
+                        function.apply(session, row, 1, &mut state, arena);
+                        values.push(function.finalize(session, &state, arena).as_static());
+                    }
+                }
+                WindowFrame::Rows(Some(preceding)) => {
+                    let preceding = preceding as usize;
+                    let retractable = match function {
+                        AggregateExpression::CompiledAggregate(aggregate) => {
+                            aggregate.function.supports_retract()
+                        }
+                        _ => false,
+                    };
+                    for (idx, row) in rows.iter().enumerate() {
+                        if retractable {
+                            // Slide the frame along by retracting the row that fell out of it
+                            function.apply(session, row, 1, &mut state, arena);
+                            if idx > preceding {
+                                function.apply(
+                                    session,
+                                    &rows[idx - preceding - 1],
+                                    -1,
+                                    &mut state,
+                                    arena,
+                                );
+                            }
+                        } else {
+                            function.reset(&mut state);
+                            for row in &rows[idx.saturating_sub(preceding)..=idx] {
+                                function.apply(session, row, 1, &mut state, arena);
+                            }
+                        }
+                        values.push(function.finalize(session, &state, arena).as_static());
+                    }
+                }
+            }
+            columns.push(values);
+        }
+
+        for values in columns {
+            for (row, value) in self.rows.iter_mut().zip(values) {
+                row.push(value);
+            }
+        }
+    }
+}
+
+impl TupleIter for WindowExecutor {
+    type E = ExecutionError;
+
+    fn advance(&mut self) -> Result<(), ExecutionError> {
+        if self.position + 1 < self.rows.len() {
+            self.position += 1;
+        } else {
+            self.position = 0;
+            self.read_partition()?;
+            self.evaluate_partition();
+        }
+        Ok(())
+    }
+
+    fn get(&self) -> Option<(&[Datum], i64)> {
+        self.rows.get(self.position).map(|row| (row.as_slice(), 1))
+    }
+
+    fn column_count(&self) -> usize {
+        self.source.column_count() + self.functions.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point_in_time::values::ValuesExecutor;
+    use ast::expr::{CompiledAggregate, CompiledColumnReference};
+    use data::DataType;
+    use functions::registry::Registry;
+    use functions::FunctionSignature;
+
+    fn aggregate(name: &str, args: Vec<Expression>) -> Expression {
+        let (sig, function) = Registry::default()
+            .resolve_function(&FunctionSignature {
+                name,
+                args: args.iter().map(|_| DataType::Integer).collect(),
+                ret: DataType::Null,
+            })
+            .unwrap();
+        Expression::CompiledAggregate(CompiledAggregate {
+            function: function.as_aggregate(),
+            args: args.into_boxed_slice(),
+            signature: Box::new(sig),
+        })
+    }
+
+    fn column(offset: usize) -> Expression {
+        Expression::CompiledColumnReference(CompiledColumnReference {
+            offset,
+            datatype: DataType::Integer,
+        })
+    }
+
+    #[test]
+    fn test_window_executor() -> Result<(), ExecutionError> {
+        let session = Arc::new(Session::new(1));
+        // Already sorted by the partition (col 0) then the order by (col 1)
+        let values = vec![
+            vec![Datum::from(1), Datum::from(1)],
+            vec![Datum::from(1), Datum::from(2)],
+            vec![Datum::from(1), Datum::from(2)],
+            vec![Datum::from(1), Datum::from(3)],
+            vec![Datum::from(2), Datum::from(5)],
+        ];
+        let source = Box::from(ValuesExecutor::new(Box::from(values.into_iter()), 2));
+
+        let functions = vec![
+            // row_number()
+            (aggregate("count", vec![]), WindowFrame::Rows(None)),
+            // sum(col1) OVER (PARTITION BY col0 ORDER BY col1)
+            (aggregate("sum", vec![column(1)]), WindowFrame::Range),
+            // sum(col1) OVER (... ROWS 1 PRECEDING)
+            (
+                aggregate("sum", vec![column(1)]),
+                WindowFrame::Rows(Some(1)),
+            ),
+        ];
+        let mut executor =
+            WindowExecutor::new(source, session, vec![column(0)], vec![column(1)], functions);
+
+        // partition, value, row number, sum over the peers, sum over the sliding frame
+        for &(partition, value, row_number, sum, sliding) in &[
+            (1, 1, 1, 1, 1),
+            (1, 2, 2, 5, 3),
+            (1, 2, 3, 5, 4),
+            (1, 3, 4, 8, 5),
+            (2, 5, 1, 5, 5),
+        ] {
+            let expected = [
+                Datum::from(partition),
+                Datum::from(value),
+                Datum::from(row_number as i64),
+                Datum::from(sum),
+                Datum::from(sliding),
+            ];
+            assert_eq!(executor.next()?, Some((expected.as_ref(), 1)));
+        }
+        assert_eq!(executor.next()?, None);
+        Ok(())
+    }
+}
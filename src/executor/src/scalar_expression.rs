@@ -1,75 +1,120 @@
-use crate::utils::right_size_new;
+use crate::arena::{ArenaBuffers, ExprArena};
 use ast::expr::Expression;
 use data::{Datum, Session};
+use functions::check_cast;
 
 pub trait EvalScalar {
-    /// Evaluates an expression as a scalar context, needs to be mutable due to the buffers we keep
-    /// for intermediate results
-    fn eval_scalar<'a>(&'a mut self, session: &Session, row: &'a [Datum<'a>]) -> Datum<'a>;
+    /// Evaluates an expression as a scalar context, the results of the function calls within it
+    /// are kept in the arena as the result may borrow from them
+    fn eval_scalar<'a>(
+        &'a self,
+        session: &Session,
+        row: &'a [Datum<'a>],
+        arena: &'a mut ExprArena,
+    ) -> Datum<'a>;
 }
 
 impl EvalScalar for Expression {
-    /// Evaluates a "row" of expressions as a scalar context
-    #[allow(clippy::transmute_ptr_to_ptr)]
-    fn eval_scalar<'a>(&'a mut self, session: &Session, row: &'a [Datum<'a>]) -> Datum<'a> {
-        match self {
-            // literal.clone() seemed to confuse IntelliJ here...
-            Expression::Constant(literal, _) => Datum::ref_clone(literal),
-            Expression::CompiledFunctionCall(function_call) => {
-                // Due to datum's being able to reference data from source datums, we need to hold
-                // onto all the intermediate datums just in case. Rust lifetimes don't really allow
-                // us to do this in an easy way without ref counting and allocating so hence we put
-                // the buffer in the expression datastructure itself and use a little unsafe to muck
-                // with the lifetimes
-
-                // right size
-                if function_call.expr_buffer.len() != function_call.args.len() {
-                    function_call.expr_buffer = Box::from(right_size_new(&function_call.args))
-                }
-
-                let buf = unsafe {
-                    std::mem::transmute::<&mut Box<[Datum<'_>]>, &mut Box<[Datum<'_>]>>(
-                        &mut function_call.expr_buffer,
-                    )
-                };
-                function_call.args.eval_scalar(session, row, buf);
-
-                function_call
-                    .function
-                    .execute(session, &function_call.signature, buf)
-            }
-            Expression::CompiledColumnReference(column_reference) => {
-                row[column_reference.offset].ref_clone()
-            }
-            // These should be compiled away by this point
-            Expression::FunctionCall(_) | Expression::Cast(_) | Expression::ColumnReference(_) => {
-                panic!("Hit uncompiled expression during evaluation {:?}", self)
-            }
-            Expression::CompiledAggregate(_) => {
-                panic!("Hit aggregate function in scalar expression {:?}", self)
-            }
-        }
+    fn eval_scalar<'a>(
+        &'a self,
+        session: &Session,
+        row: &'a [Datum<'a>],
+        arena: &'a mut ExprArena,
+    ) -> Datum<'a> {
+        let mut buffers = arena.buffers(arena_size(self));
+        eval(self, session, row, &mut buffers)
     }
 }
 
 pub trait EvalScalarRow {
+    /// Evaluates a "row" of expressions as a scalar context
     fn eval_scalar<'a>(
-        &'a mut self,
+        &'a self,
         session: &Session,
         source: &'a [Datum<'a>],
         target: &mut [Datum<'a>],
+        arena: &'a mut ExprArena,
     );
 }
 
 impl EvalScalarRow for [Expression] {
     fn eval_scalar<'a>(
-        &'a mut self,
+        &'a self,
         session: &Session,
         source: &'a [Datum<'a>],
         target: &mut [Datum<'a>],
+        arena: &'a mut ExprArena,
     ) {
-        for (idx, expr) in self.iter_mut().enumerate() {
-            target[idx] = expr.eval_scalar(session, source);
+        let mut buffers = arena.buffers(self.iter().map(arena_size).sum());
+        for (idx, expr) in self.iter().enumerate() {
+            target[idx] = eval(expr, session, source, &mut buffers);
+        }
+    }
+}
+
+/// Evaluates the args of a function call into a buffer from the arena
+pub(crate) fn eval_args<'a>(
+    args: &'a [Expression],
+    session: &Session,
+    row: &'a [Datum<'a>],
+    arena: &'a mut ExprArena,
+) -> &'a [Datum<'a>] {
+    let mut buffers = arena.buffers(1 + args.iter().map(arena_size).sum::<usize>());
+    let evaluated = buffers.take();
+    for arg in args.iter() {
+        evaluated.push(eval(arg, session, row, &mut buffers));
+    }
+    evaluated
+}
+
+/// The number of arena buffers needed to evaluate the expression, one per function call
+fn arena_size(expr: &Expression) -> usize {
+    match expr {
+        Expression::CompiledFunctionCall(function_call) => {
+            1 + function_call.args.iter().map(arena_size).sum::<usize>()
+        }
+        _ => 0,
+    }
+}
+
+fn eval<'a>(
+    expr: &'a Expression,
+    session: &Session,
+    row: &'a [Datum<'a>],
+    buffers: &mut ArenaBuffers<'a>,
+) -> Datum<'a> {
+    match expr {
+        // literal.clone() seemed to confuse IntelliJ here...
+        Expression::Constant(literal, _) => Datum::ref_clone(literal),
+        Expression::CompiledFunctionCall(function_call) => {
+            // Due to datum's being able to reference data from source datums, we need to hold
+            // onto all the intermediate datums just in case, they go in the arena which outlives
+            // the result
+            let args = buffers.take();
+            for arg in function_call.args.iter() {
+                args.push(eval(arg, session, row, buffers));
+            }
+            let args: &'a [Datum<'a>] = args;
+
+            let result = function_call
+                .function
+                .execute(session, &function_call.signature, args);
+            check_cast(session, &function_call.signature, args, &result);
+            result
+        }
+        Expression::CompiledColumnReference(column_reference) => {
+            row[column_reference.offset].ref_clone()
+        }
+        // These should be compiled/planned away by this point
+        Expression::FunctionCall(_)
+        | Expression::Cast(_)
+        | Expression::ColumnReference(_)
+        | Expression::Subquery(_) => {
+            panic!("Hit uncompiled expression during evaluation {:?}", expr)
+        }
+        Expression::Parameter(_) => panic!("Hit unbound parameter during evaluation {:?}", expr),
+        Expression::CompiledAggregate(_) | Expression::WindowFunction(_) => {
+            panic!("Hit aggregate function in scalar expression {:?}", expr)
         }
     }
 }
@@ -84,9 +129,13 @@ mod tests {
 
     #[test]
     fn test_eval_scalar_literal() {
-        let mut expression = Expression::from(1234);
+        let expression = Expression::from(1234);
         let session = Session::new(1);
-        assert_eq!(expression.eval_scalar(&session, &[]), Datum::from(1234));
+        let mut arena = ExprArena::default();
+        assert_eq!(
+            expression.eval_scalar(&session, &[], &mut arena),
+            Datum::from(1234)
+        );
     }
 
     #[test]
@@ -102,23 +151,38 @@ mod tests {
 
         let function = function_type.as_scalar();
 
-        let mut expression = Expression::CompiledFunctionCall(CompiledFunctionCall {
-            function,
-            signature: Box::from(computed_signature),
-            expr_buffer: Box::from(vec![]),
-            args: Box::from(vec![Expression::from(3), Expression::from(4)]),
-        });
+        let add = |args: Vec<Expression>| {
+            Expression::CompiledFunctionCall(CompiledFunctionCall {
+                function,
+                signature: Box::from(computed_signature.clone()),
+                args: Box::from(args),
+            })
+        };
+        let expression = add(vec![
+            add(vec![Expression::from(1), Expression::from(2)]),
+            Expression::from(4),
+        ]);
 
         let session = Session::new(1);
-        assert_eq!(expression.eval_scalar(&session, &[]), Datum::from(7));
+        let mut arena = ExprArena::default();
+        assert_eq!(
+            expression.eval_scalar(&session, &[], &mut arena),
+            Datum::from(7)
+        );
+        // The arena's reused for the next row
+        assert_eq!(
+            expression.eval_scalar(&session, &[], &mut arena),
+            Datum::from(7)
+        );
     }
 
     #[test]
     fn test_eval_scalar_row() {
-        let mut expressions = vec![Expression::from(1234), Expression::from(5678)];
+        let expressions = vec![Expression::from(1234), Expression::from(5678)];
         let session = Session::new(1);
         let mut target = vec![Datum::Null, Datum::Null];
-        expressions.eval_scalar(&session, &[], &mut target);
+        let mut arena = ExprArena::default();
+        expressions.eval_scalar(&session, &[], &mut target, &mut arena);
 
         assert_eq!(target, vec![Datum::from(1234), Datum::from(5678)]);
     }
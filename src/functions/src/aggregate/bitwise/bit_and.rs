@@ -0,0 +1,126 @@
+use crate::aggregate::bitwise::{
+    apply_bit_counts, finalize_bit_counts, initialize_bit_counts, input_value, merge_bit_counts,
+};
+use crate::registry::Registry;
+use crate::{AggregateFunction, FunctionDefinition, FunctionSignature, FunctionType};
+use data::{DataType, Datum};
+
+#[derive(Debug)]
+struct BitAnd {
+    bits: usize,
+}
+
+/// A bit is set in the output if it was set in every non-null input
+impl AggregateFunction for BitAnd {
+    fn state_size(&self) -> usize {
+        self.bits + 1
+    }
+
+    fn initialize(&self, state: &mut [Datum<'static>]) {
+        initialize_bit_counts(&mut state[..self.state_size()]);
+    }
+
+    fn apply<'a>(
+        &self,
+        _signature: &FunctionSignature<'a>,
+        args: &[Datum<'a>],
+        freq: i64,
+        state: &mut [Datum<'static>],
+    ) {
+        if let Some(value) = input_value(self.bits, &args[0]) {
+            apply_bit_counts(self.bits, value, freq, state);
+        }
+    }
+
+    fn merge<'a>(
+        &self,
+        _signature: &FunctionSignature<'a>,
+        input_state: &[Datum<'static>],
+        state: &mut [Datum<'static>],
+    ) {
+        merge_bit_counts(
+            &input_state[..self.state_size()],
+            &mut state[..self.state_size()],
+        );
+    }
+
+    fn finalize<'a>(&self, _signature: &FunctionSignature, state: &'a [Datum<'a>]) -> Datum<'a> {
+        finalize_bit_counts(self.bits, state, |count, bit_count| count == bit_count)
+    }
+
+    fn supports_retract(&self) -> bool {
+        true
+    }
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "bit_and",
+        vec![DataType::Integer],
+        DataType::Integer,
+        FunctionType::Aggregate(&BitAnd { bits: 32 }),
+    ));
+
+    registry.register_function(FunctionDefinition::new(
+        "bit_and",
+        vec![DataType::BigInt],
+        DataType::BigInt,
+        FunctionType::Aggregate(&BitAnd { bits: 64 }),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUMMY_SIG: FunctionSignature = FunctionSignature {
+        name: "bit_and",
+        args: vec![],
+        ret: DataType::Integer,
+    };
+
+    #[test]
+    fn test_apply_int() {
+        let funct = &BitAnd { bits: 32 };
+        let mut state = vec![Datum::Null; funct.state_size()];
+        funct.initialize(&mut state);
+        assert_eq!(funct.finalize(&DUMMY_SIG, &state), Datum::Null);
+
+        funct.apply(&DUMMY_SIG, &[Datum::from(0b1110)], 2, &mut state);
+        funct.apply(&DUMMY_SIG, &[Datum::from(0b0111)], 1, &mut state);
+        funct.apply(&DUMMY_SIG, &[Datum::Null], 1, &mut state);
+        assert_eq!(funct.finalize(&DUMMY_SIG, &state), Datum::from(0b0110));
+
+        // Retract
+        funct.apply(&DUMMY_SIG, &[Datum::from(0b0111)], -1, &mut state);
+        assert_eq!(funct.finalize(&DUMMY_SIG, &state), Datum::from(0b1110));
+    }
+
+    #[test]
+    fn test_apply_bigint_negative() {
+        let funct = &BitAnd { bits: 64 };
+        let mut state = vec![Datum::Null; funct.state_size()];
+        funct.initialize(&mut state);
+
+        funct.apply(&DUMMY_SIG, &[Datum::from(-1_i64)], 1, &mut state);
+        funct.apply(&DUMMY_SIG, &[Datum::from(-2_i64)], 1, &mut state);
+        assert_eq!(funct.finalize(&DUMMY_SIG, &state), Datum::from(-2_i64));
+    }
+
+    #[test]
+    fn test_merge() {
+        let funct = &BitAnd { bits: 32 };
+
+        let mut state1 = vec![Datum::Null; funct.state_size()];
+        funct.initialize(&mut state1);
+        funct.apply(&DUMMY_SIG, &[Datum::from(0b1110)], 1, &mut state1);
+
+        let mut state2 = vec![Datum::Null; funct.state_size()];
+        funct.initialize(&mut state2);
+        funct.apply(&DUMMY_SIG, &[Datum::from(0b0111)], 1, &mut state2);
+
+        funct.merge(&DUMMY_SIG, &state2, &mut state1);
+
+        assert_eq!(funct.finalize(&DUMMY_SIG, &state1), Datum::from(0b0110))
+    }
+}
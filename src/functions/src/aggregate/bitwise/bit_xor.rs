@@ -0,0 +1,121 @@
+use crate::aggregate::bitwise::{input_value, output_datum};
+use crate::registry::Registry;
+use crate::{AggregateFunction, FunctionDefinition, FunctionSignature, FunctionType};
+use data::{DataType, Datum};
+
+#[derive(Debug)]
+struct BitXor {
+    bits: usize,
+}
+
+/// Xor is its own inverse so we only need to keep the running value
+/// along with a count of the non-null values (to know when to return null).
+impl AggregateFunction for BitXor {
+    fn state_size(&self) -> usize {
+        2
+    }
+
+    fn initialize(&self, state: &mut [Datum<'static>]) {
+        state[0] = Datum::from(0_i64);
+        state[1] = Datum::from(0_i64);
+    }
+
+    fn apply<'a>(
+        &self,
+        _signature: &FunctionSignature<'a>,
+        args: &[Datum<'a>],
+        freq: i64,
+        state: &mut [Datum<'static>],
+    ) {
+        if let Some(value) = input_value(self.bits, &args[0]) {
+            // Applying a value an even number of times cancels itself out
+            if freq % 2 != 0 {
+                *state[0].as_bigint_mut() ^= value;
+            }
+            *state[1].as_bigint_mut() += freq;
+        }
+    }
+
+    fn merge<'a>(
+        &self,
+        _signature: &FunctionSignature<'a>,
+        input_state: &[Datum<'static>],
+        state: &mut [Datum<'static>],
+    ) {
+        *state[0].as_bigint_mut() ^= input_state[0].as_bigint();
+        *state[1].as_bigint_mut() += input_state[1].as_bigint();
+    }
+
+    fn finalize<'a>(&self, _signature: &FunctionSignature, state: &'a [Datum<'a>]) -> Datum<'a> {
+        if state[1].as_bigint() == 0 {
+            Datum::Null
+        } else {
+            output_datum(self.bits, state[0].as_bigint() as u64)
+        }
+    }
+
+    fn supports_retract(&self) -> bool {
+        true
+    }
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "bit_xor",
+        vec![DataType::Integer],
+        DataType::Integer,
+        FunctionType::Aggregate(&BitXor { bits: 32 }),
+    ));
+
+    registry.register_function(FunctionDefinition::new(
+        "bit_xor",
+        vec![DataType::BigInt],
+        DataType::BigInt,
+        FunctionType::Aggregate(&BitXor { bits: 64 }),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUMMY_SIG: FunctionSignature = FunctionSignature {
+        name: "bit_xor",
+        args: vec![],
+        ret: DataType::Integer,
+    };
+
+    #[test]
+    fn test_apply_int() {
+        let funct = &BitXor { bits: 32 };
+        let mut state = vec![Datum::Null, Datum::Null];
+        funct.initialize(&mut state);
+        assert_eq!(funct.finalize(&DUMMY_SIG, &state), Datum::Null);
+
+        funct.apply(&DUMMY_SIG, &[Datum::from(0b1100)], 1, &mut state);
+        funct.apply(&DUMMY_SIG, &[Datum::from(0b0110)], 3, &mut state);
+        funct.apply(&DUMMY_SIG, &[Datum::Null], 1, &mut state);
+        assert_eq!(funct.finalize(&DUMMY_SIG, &state), Datum::from(0b1010));
+
+        // Retract
+        funct.apply(&DUMMY_SIG, &[Datum::from(0b0110)], -1, &mut state);
+        assert_eq!(funct.finalize(&DUMMY_SIG, &state), Datum::from(0b1100));
+    }
+
+    #[test]
+    fn test_merge() {
+        let funct = &BitXor { bits: 64 };
+
+        let mut state1 = vec![Datum::Null, Datum::Null];
+        funct.initialize(&mut state1);
+        funct.apply(&DUMMY_SIG, &[Datum::from(0b1100_i64)], 1, &mut state1);
+
+        let mut state2 = vec![Datum::Null, Datum::Null];
+        funct.initialize(&mut state2);
+        funct.apply(&DUMMY_SIG, &[Datum::from(0b0110_i64)], 1, &mut state2);
+
+        funct.merge(&DUMMY_SIG, &state2, &mut state1);
+
+        assert_eq!(funct.finalize(&DUMMY_SIG, &state1), Datum::from(0b1010_i64))
+    }
+}
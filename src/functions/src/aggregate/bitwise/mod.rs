@@ -0,0 +1,74 @@
+mod bit_and;
+mod bit_or;
+mod bit_xor;
+
+use crate::registry::Registry;
+use data::Datum;
+
+pub fn register_builtins(registry: &mut Registry) {
+    bit_and::register_builtins(registry);
+    bit_or::register_builtins(registry);
+    bit_xor::register_builtins(registry);
+}
+
+// bit_and and bit_or aren't retractable by just looking at the current value, to support
+// retraction we instead keep a count of the non-null values seen followed by a count for
+// each bit position of how many values had that bit set.
+// ie state = <non-null count>:<bit 0 count>:<bit 1 count>...
+
+/// Reads the input as an i64 regardless of whether it's an int or bigint.
+fn input_value(bits: usize, datum: &Datum) -> Option<i64> {
+    if bits == 32 {
+        datum.as_maybe_integer().map(i64::from)
+    } else {
+        datum.as_maybe_bigint()
+    }
+}
+
+/// Turns the computed bits back into an int or bigint datum.
+fn output_datum(bits: usize, value: u64) -> Datum<'static> {
+    if bits == 32 {
+        Datum::from(value as i32)
+    } else {
+        Datum::from(value as i64)
+    }
+}
+
+fn initialize_bit_counts(state: &mut [Datum<'static>]) {
+    for datum in state {
+        *datum = Datum::from(0_i64);
+    }
+}
+
+fn apply_bit_counts(bits: usize, value: i64, freq: i64, state: &mut [Datum<'static>]) {
+    *state[0].as_bigint_mut() += freq;
+    for bit in 0..bits {
+        if value & (1 << bit) != 0 {
+            *state[bit + 1].as_bigint_mut() += freq;
+        }
+    }
+}
+
+fn merge_bit_counts(input_state: &[Datum<'static>], state: &mut [Datum<'static>]) {
+    for (datum, input) in state.iter_mut().zip(input_state) {
+        *datum.as_bigint_mut() += input.as_bigint();
+    }
+}
+
+/// Builds the result from the bit counts, the predicate is passed the count of non-null values
+/// and the count for the bit and returns whether the bit should be set.
+fn finalize_bit_counts<F: Fn(i64, i64) -> bool>(
+    bits: usize,
+    state: &[Datum],
+    predicate: F,
+) -> Datum<'static> {
+    let count = state[0].as_bigint();
+    if count == 0 {
+        return Datum::Null;
+    }
+
+    let value = (0..bits)
+        .filter(|bit| predicate(count, state[bit + 1].as_bigint()))
+        .fold(0_u64, |acc, bit| acc | (1 << bit));
+    output_datum(bits, value)
+}
@@ -0,0 +1,121 @@
+use crate::registry::Registry;
+use crate::{AggregateFunction, FunctionDefinition, FunctionSignature, FunctionType};
+use data::{DataType, Datum};
+
+#[derive(Debug)]
+struct BoolAnd {}
+
+/// Keeps a count of the true and false values seen, this
+/// lets us retract values without having to keep them all around.
+impl AggregateFunction for BoolAnd {
+    fn state_size(&self) -> usize {
+        2
+    }
+
+    fn initialize(&self, state: &mut [Datum<'static>]) {
+        state[0] = Datum::from(0_i64);
+        state[1] = Datum::from(0_i64);
+    }
+
+    fn apply<'a>(
+        &self,
+        _signature: &FunctionSignature<'a>,
+        args: &[Datum<'a>],
+        freq: i64,
+        state: &mut [Datum<'static>],
+    ) {
+        match args[0].as_maybe_boolean() {
+            Some(true) => *state[0].as_bigint_mut() += freq,
+            Some(false) => *state[1].as_bigint_mut() += freq,
+            None => {}
+        }
+    }
+
+    fn merge<'a>(
+        &self,
+        _signature: &FunctionSignature<'a>,
+        input_state: &[Datum<'static>],
+        state: &mut [Datum<'static>],
+    ) {
+        *state[0].as_bigint_mut() += input_state[0].as_bigint();
+        *state[1].as_bigint_mut() += input_state[1].as_bigint();
+    }
+
+    fn finalize<'a>(&self, _signature: &FunctionSignature, state: &'a [Datum<'a>]) -> Datum<'a> {
+        if state[1].as_bigint() != 0 {
+            Datum::from(false)
+        } else if state[0].as_bigint() != 0 {
+            Datum::from(true)
+        } else {
+            Datum::Null
+        }
+    }
+
+    fn supports_retract(&self) -> bool {
+        true
+    }
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "bool_and",
+        vec![DataType::Boolean],
+        DataType::Boolean,
+        FunctionType::Aggregate(&BoolAnd {}),
+    ));
+
+    // Every is the sql standard name for bool_and
+    registry.register_function(FunctionDefinition::new(
+        "every",
+        vec![DataType::Boolean],
+        DataType::Boolean,
+        FunctionType::Aggregate(&BoolAnd {}),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUMMY_SIG: FunctionSignature = FunctionSignature {
+        name: "bool_and",
+        args: vec![],
+        ret: DataType::Boolean,
+    };
+
+    #[test]
+    fn test_apply() {
+        let funct = &BoolAnd {};
+        let mut state = vec![Datum::Null, Datum::Null];
+        funct.initialize(&mut state);
+        assert_eq!(funct.finalize(&DUMMY_SIG, &state), Datum::Null);
+
+        funct.apply(&DUMMY_SIG, &[Datum::from(true)], 2, &mut state);
+        funct.apply(&DUMMY_SIG, &[Datum::Null], 1, &mut state);
+        assert_eq!(funct.finalize(&DUMMY_SIG, &state), Datum::from(true));
+
+        funct.apply(&DUMMY_SIG, &[Datum::from(false)], 1, &mut state);
+        assert_eq!(funct.finalize(&DUMMY_SIG, &state), Datum::from(false));
+
+        // Retract the false
+        funct.apply(&DUMMY_SIG, &[Datum::from(false)], -1, &mut state);
+        assert_eq!(funct.finalize(&DUMMY_SIG, &state), Datum::from(true));
+    }
+
+    #[test]
+    fn test_merge() {
+        let funct = &BoolAnd {};
+
+        let mut state1 = vec![Datum::Null, Datum::Null];
+        funct.initialize(&mut state1);
+        funct.apply(&DUMMY_SIG, &[Datum::from(true)], 1, &mut state1);
+
+        let mut state2 = vec![Datum::Null, Datum::Null];
+        funct.initialize(&mut state2);
+        funct.apply(&DUMMY_SIG, &[Datum::from(false)], 1, &mut state2);
+
+        funct.merge(&DUMMY_SIG, &state2, &mut state1);
+
+        assert_eq!(funct.finalize(&DUMMY_SIG, &state1), Datum::from(false))
+    }
+}
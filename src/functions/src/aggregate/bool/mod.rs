@@ -0,0 +1,9 @@
+mod bool_and;
+mod bool_or;
+
+use crate::registry::Registry;
+
+pub fn register_builtins(registry: &mut Registry) {
+    bool_and::register_builtins(registry);
+    bool_or::register_builtins(registry);
+}
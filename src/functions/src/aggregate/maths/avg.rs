@@ -187,6 +187,7 @@ pub fn register_builtins(registry: &mut Registry) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use data::encoding_tuple::DecodeError;
 
     const DUMMY_SIG: FunctionSignature = FunctionSignature {
         name: "avg",
@@ -241,4 +242,62 @@ mod tests {
 
         assert_eq!(answer, Datum::from(Decimal::new(5, 0)))
     }
+
+    #[test]
+    fn test_serialized_state_merge() {
+        let funct = &DecimalAvg {};
+
+        let mut state1 = vec![Datum::Null, Datum::Null];
+        funct.initialize(&mut state1);
+        funct.apply(
+            &DUMMY_SIG,
+            &[Datum::from(Decimal::new(15, 1))],
+            2,
+            &mut state1,
+        );
+
+        let mut state2 = vec![Datum::Null, Datum::Null];
+        funct.initialize(&mut state2);
+        funct.apply(
+            &DUMMY_SIG,
+            &[Datum::from(Decimal::new(6, 0))],
+            1,
+            &mut state2,
+        );
+
+        // Written out one after another as if persisted and then read back by someone else
+        let mut buffer = vec![];
+        funct.serialize_state(&state1, &mut buffer);
+        let state1_len = buffer.len();
+        funct.serialize_state(&state2, &mut buffer);
+
+        let mut restored1 = vec![Datum::Null, Datum::Null];
+        let mut restored2 = vec![Datum::Null, Datum::Null];
+        let rem = funct.deserialize_state(&buffer, &mut restored1).unwrap();
+        let rem = funct.deserialize_state(rem, &mut restored2).unwrap();
+        assert!(rem.is_empty());
+        assert_eq!(restored1, state1);
+
+        funct.merge(&DUMMY_SIG, &restored2, &mut restored1);
+        let answer = funct.finalize(&DUMMY_SIG, &restored1);
+        // 1.5 + 1.5 + 6 = 9, 9/3 = 3
+        assert_eq!(answer, Datum::from(Decimal::new(3, 0)));
+
+        assert_eq!(
+            funct.deserialize_state(&buffer[..state1_len - 1], &mut restored1),
+            Err(DecodeError::Truncated)
+        );
+    }
+
+    #[test]
+    fn test_deserialize_wrong_state() {
+        let mut buffer = vec![];
+        data::encoding_tuple::encode_tuple(&[Datum::from(1_i64)], &mut buffer);
+
+        let mut state = vec![Datum::Null, Datum::Null];
+        assert_eq!(
+            IntAvg {}.deserialize_state(&buffer, &mut state),
+            Err(DecodeError::WrongDatumCount(2, 1))
+        );
+    }
 }
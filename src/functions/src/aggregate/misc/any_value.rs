@@ -0,0 +1,94 @@
+use crate::registry::Registry;
+use crate::{AggregateFunction, FunctionDefinition, FunctionSignature, FunctionType};
+use data::{DataType, Datum};
+
+#[derive(Debug)]
+struct AnyValue {}
+
+/// Returns an arbitrary non-null value from the group, useful to carry along columns that
+/// are functionally dependent on the grouping keys.
+impl AggregateFunction for AnyValue {
+    fn apply<'a>(
+        &self,
+        _signature: &FunctionSignature<'a>,
+        args: &[Datum<'a>],
+        _freq: i64,
+        state: &mut [Datum<'static>],
+    ) {
+        if state[0].is_null() {
+            state[0] = args[0].as_static();
+        }
+    }
+
+    fn merge<'a>(
+        &self,
+        _signature: &FunctionSignature<'a>,
+        input_state: &[Datum<'static>],
+        state: &mut [Datum<'static>],
+    ) {
+        if state[0].is_null() {
+            state[0] = input_state[0].as_static();
+        }
+    }
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    for datatype in &[
+        DataType::Boolean,
+        DataType::Integer,
+        DataType::BigInt,
+        DataType::Text,
+        DataType::ByteA,
+        DataType::Date,
+        DataType::Timestamp,
+        DataType::Json,
+        DataType::Decimal(0, 0),
+    ] {
+        registry.register_function(FunctionDefinition::new_with_type_resolver(
+            "any_value",
+            vec![*datatype],
+            |args| args[0],
+            FunctionType::Aggregate(&AnyValue {}),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUMMY_SIG: FunctionSignature = FunctionSignature {
+        name: "any_value",
+        args: vec![],
+        ret: DataType::Integer,
+    };
+
+    #[test]
+    fn test_apply() {
+        let funct = &AnyValue {};
+        let mut state = vec![Datum::Null];
+        funct.initialize(&mut state);
+
+        funct.apply(&DUMMY_SIG, &[Datum::Null], 1, &mut state);
+        funct.apply(&DUMMY_SIG, &[Datum::from(1)], 1, &mut state);
+        funct.apply(&DUMMY_SIG, &[Datum::from(2)], 1, &mut state);
+
+        assert_eq!(funct.finalize(&DUMMY_SIG, &state), Datum::from(1))
+    }
+
+    #[test]
+    fn test_merge() {
+        let funct = &AnyValue {};
+
+        let mut state1 = vec![Datum::Null];
+        funct.initialize(&mut state1);
+
+        let mut state2 = vec![Datum::Null];
+        funct.initialize(&mut state2);
+        funct.apply(&DUMMY_SIG, &[Datum::from(2)], 1, &mut state2);
+
+        funct.merge(&DUMMY_SIG, &state2, &mut state1);
+
+        assert_eq!(funct.finalize(&DUMMY_SIG, &state1), Datum::from(2))
+    }
+}
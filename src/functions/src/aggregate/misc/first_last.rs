@@ -0,0 +1,274 @@
+use crate::registry::Registry;
+use crate::{AggregateFunction, FunctionDefinition, FunctionSignature, FunctionType};
+use data::{DataType, Datum};
+
+/// Returns the first non-null value, if an ordering expression is passed as the second
+/// argument then it's the value with the lowest ordering value, otherwise it's simply the first
+/// value we see.
+#[derive(Debug)]
+struct First {}
+
+/// Returns the last non-null value, if an ordering expression is passed as the second
+/// argument then it's the value with the highest ordering value, otherwise it's simply the last
+/// value we see.
+#[derive(Debug)]
+struct Last {}
+
+// State for both is <value>:<ordering value>
+
+impl AggregateFunction for First {
+    fn state_size(&self) -> usize {
+        2
+    }
+
+    fn initialize(&self, state: &mut [Datum<'static>]) {
+        state[0] = Datum::Null;
+        state[1] = Datum::Null;
+    }
+
+    fn apply<'a>(
+        &self,
+        _signature: &FunctionSignature<'a>,
+        args: &[Datum<'a>],
+        _freq: i64,
+        state: &mut [Datum<'static>],
+    ) {
+        apply_if(&args[0], args.get(1), state, |new, current| new < current);
+    }
+
+    fn merge<'a>(
+        &self,
+        signature: &FunctionSignature<'a>,
+        input_state: &[Datum<'static>],
+        state: &mut [Datum<'static>],
+    ) {
+        let ordering = if signature.args.len() > 1 {
+            Some(&input_state[1])
+        } else {
+            None
+        };
+        apply_if(&input_state[0], ordering, state, |new, current| {
+            new < current
+        });
+    }
+}
+
+impl AggregateFunction for Last {
+    fn state_size(&self) -> usize {
+        2
+    }
+
+    fn initialize(&self, state: &mut [Datum<'static>]) {
+        state[0] = Datum::Null;
+        state[1] = Datum::Null;
+    }
+
+    fn apply<'a>(
+        &self,
+        _signature: &FunctionSignature<'a>,
+        args: &[Datum<'a>],
+        _freq: i64,
+        state: &mut [Datum<'static>],
+    ) {
+        apply_if(&args[0], args.get(1), state, |new, current| new >= current);
+    }
+
+    fn merge<'a>(
+        &self,
+        signature: &FunctionSignature<'a>,
+        input_state: &[Datum<'static>],
+        state: &mut [Datum<'static>],
+    ) {
+        let ordering = if signature.args.len() > 1 {
+            Some(&input_state[1])
+        } else {
+            None
+        };
+        apply_if(&input_state[0], ordering, state, |new, current| {
+            new >= current
+        });
+    }
+}
+
+/// Replaces the state with the input value if the value is non-null and either the state is
+/// empty or the predicate(new_ordering, current_ordering) returns true.
+/// For the single arg variants the ordering is missing and so it's compared as null vs null,
+/// which gives us first seen/last seen semantics.
+fn apply_if<F: Fn(&Datum, &Datum) -> bool>(
+    value: &Datum,
+    ordering: Option<&Datum>,
+    state: &mut [Datum<'static>],
+    predicate: F,
+) {
+    if value.is_null() {
+        return;
+    }
+    // When ordering by an expression, rows with a null ordering value are ignored
+    let ordering = match ordering {
+        Some(ordering) if ordering.is_null() => return,
+        Some(ordering) => ordering,
+        None => &Datum::Null,
+    };
+
+    if state[0].is_null() || predicate(ordering, &state[1]) {
+        state[0] = value.as_static();
+        state[1] = ordering.as_static();
+    }
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    let datatypes = [
+        DataType::Boolean,
+        DataType::Integer,
+        DataType::BigInt,
+        DataType::Text,
+        DataType::ByteA,
+        DataType::Date,
+        DataType::Timestamp,
+        DataType::Json,
+        DataType::Decimal(0, 0),
+    ];
+
+    for datatype in &datatypes {
+        registry.register_function(FunctionDefinition::new_with_type_resolver(
+            "first",
+            vec![*datatype],
+            |args| args[0],
+            FunctionType::Aggregate(&First {}),
+        ));
+        registry.register_function(FunctionDefinition::new_with_type_resolver(
+            "last",
+            vec![*datatype],
+            |args| args[0],
+            FunctionType::Aggregate(&Last {}),
+        ));
+
+        for ordering_datatype in &datatypes {
+            registry.register_function(FunctionDefinition::new_with_type_resolver(
+                "first",
+                vec![*datatype, *ordering_datatype],
+                |args| args[0],
+                FunctionType::Aggregate(&First {}),
+            ));
+            registry.register_function(FunctionDefinition::new_with_type_resolver(
+                "last",
+                vec![*datatype, *ordering_datatype],
+                |args| args[0],
+                FunctionType::Aggregate(&Last {}),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUMMY_SIG: FunctionSignature = FunctionSignature {
+        name: "first",
+        args: vec![],
+        ret: DataType::Integer,
+    };
+
+    #[test]
+    fn test_first_apply() {
+        let funct = &First {};
+        let mut state = vec![Datum::Null, Datum::Null];
+        funct.initialize(&mut state);
+
+        funct.apply(&DUMMY_SIG, &[Datum::Null], 1, &mut state);
+        funct.apply(&DUMMY_SIG, &[Datum::from(1)], 1, &mut state);
+        funct.apply(&DUMMY_SIG, &[Datum::from(2)], 1, &mut state);
+
+        assert_eq!(funct.finalize(&DUMMY_SIG, &state), Datum::from(1))
+    }
+
+    #[test]
+    fn test_first_apply_ordered() {
+        let funct = &First {};
+        let mut state = vec![Datum::Null, Datum::Null];
+        funct.initialize(&mut state);
+
+        funct.apply(&DUMMY_SIG, &[Datum::from(1), Datum::from(5)], 1, &mut state);
+        funct.apply(&DUMMY_SIG, &[Datum::from(2), Datum::from(3)], 1, &mut state);
+        funct.apply(&DUMMY_SIG, &[Datum::from(3), Datum::Null], 1, &mut state);
+        funct.apply(&DUMMY_SIG, &[Datum::from(4), Datum::from(4)], 1, &mut state);
+
+        assert_eq!(funct.finalize(&DUMMY_SIG, &state), Datum::from(2))
+    }
+
+    #[test]
+    fn test_last_apply() {
+        let funct = &Last {};
+        let mut state = vec![Datum::Null, Datum::Null];
+        funct.initialize(&mut state);
+
+        funct.apply(&DUMMY_SIG, &[Datum::from(1)], 1, &mut state);
+        funct.apply(&DUMMY_SIG, &[Datum::from(2)], 1, &mut state);
+        funct.apply(&DUMMY_SIG, &[Datum::Null], 1, &mut state);
+
+        assert_eq!(funct.finalize(&DUMMY_SIG, &state), Datum::from(2))
+    }
+
+    #[test]
+    fn test_last_apply_ordered() {
+        let funct = &Last {};
+        let mut state = vec![Datum::Null, Datum::Null];
+        funct.initialize(&mut state);
+
+        funct.apply(&DUMMY_SIG, &[Datum::from(1), Datum::from(5)], 1, &mut state);
+        funct.apply(&DUMMY_SIG, &[Datum::from(2), Datum::from(3)], 1, &mut state);
+        funct.apply(&DUMMY_SIG, &[Datum::from(3), Datum::Null], 1, &mut state);
+
+        assert_eq!(funct.finalize(&DUMMY_SIG, &state), Datum::from(1))
+    }
+
+    #[test]
+    fn test_merge_ordered() {
+        let funct = &First {};
+
+        let mut state1 = vec![Datum::Null, Datum::Null];
+        funct.initialize(&mut state1);
+        funct.apply(
+            &DUMMY_SIG,
+            &[Datum::from(1), Datum::from(5)],
+            1,
+            &mut state1,
+        );
+
+        let mut state2 = vec![Datum::Null, Datum::Null];
+        funct.initialize(&mut state2);
+        funct.apply(
+            &DUMMY_SIG,
+            &[Datum::from(2), Datum::from(3)],
+            1,
+            &mut state2,
+        );
+
+        let ordered_sig = FunctionSignature {
+            name: "first",
+            args: vec![DataType::Integer, DataType::Integer],
+            ret: DataType::Integer,
+        };
+        funct.merge(&ordered_sig, &state2, &mut state1);
+
+        assert_eq!(funct.finalize(&DUMMY_SIG, &state1), Datum::from(2))
+    }
+
+    #[test]
+    fn test_merge_unordered() {
+        let funct = &Last {};
+
+        let mut state1 = vec![Datum::Null, Datum::Null];
+        funct.initialize(&mut state1);
+        funct.apply(&DUMMY_SIG, &[Datum::from(1)], 1, &mut state1);
+
+        let mut state2 = vec![Datum::Null, Datum::Null];
+        funct.initialize(&mut state2);
+        funct.apply(&DUMMY_SIG, &[Datum::from(2)], 1, &mut state2);
+
+        funct.merge(&DUMMY_SIG, &state2, &mut state1);
+
+        assert_eq!(funct.finalize(&DUMMY_SIG, &state1), Datum::from(2))
+    }
+}
@@ -1,7 +1,11 @@
+mod any_value;
 mod count;
+mod first_last;
 
 use crate::registry::Registry;
 
 pub fn register_builtins(registry: &mut Registry) {
+    any_value::register_builtins(registry);
     count::register_builtins(registry);
+    first_last::register_builtins(registry);
 }
@@ -1,9 +1,13 @@
 use crate::registry::Registry;
 
+mod bitwise;
+mod bool;
 mod maths;
 mod misc;
 
 pub fn register_builtins(registry: &mut Registry) {
+    bitwise::register_builtins(registry);
+    bool::register_builtins(registry);
     maths::register_builtins(registry);
     misc::register_builtins(registry);
 }
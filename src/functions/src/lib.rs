@@ -3,7 +3,10 @@ pub mod registry;
 mod scalar;
 
 use crate::registry::Registry;
-use data::{DataType, Datum, Session};
+use data::encoding_tuple::{decode_tuple, encode_tuple, DecodeError};
+use data::{
+    parse_date, parse_timestamp, DataType, Datum, Session, Warning, WARN_TRUNCATED_WRONG_VALUE,
+};
 use std::fmt::{Debug, Formatter};
 
 /// The signature for a function. Signatures are scanned to find a match during planning.
@@ -35,6 +38,20 @@ pub enum FunctionType {
     Compound(CompoundFunction),
 }
 
+/// How a function's result depends on more than its args, the planner only folds, caches and
+/// incrementally maintains what it's safe to for each.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Volatility {
+    /// Always gives the same result for the same args, ie lower()
+    Immutable,
+    /// Gives the same result for the same args within a statement but may change between
+    /// statements, ie now() or current_user(). These can be folded when planning but not kept
+    /// around in a materialized view that's maintained incrementally.
+    Stable,
+    /// Can give a different result each time it's called with the same args, ie random()
+    Volatile,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct CompoundFunction {
     pub function_name: &'static str,
@@ -48,6 +65,15 @@ pub enum CompoundFunctionArg {
 }
 
 impl FunctionType {
+    /// The volatility of the function, aggregates and compound functions (which are swapped for
+    /// their sub-functions before they're used) are immutable
+    pub fn volatility(&self) -> Volatility {
+        match self {
+            FunctionType::Scalar(function) => function.volatility(),
+            FunctionType::Aggregate(_) | FunctionType::Compound(_) => Volatility::Immutable,
+        }
+    }
+
     /// Helper for tests, unwraps the scalar function inside
     pub fn as_scalar(&self) -> &'static dyn Function {
         if let FunctionType::Scalar(f) = self {
@@ -101,6 +127,10 @@ impl FunctionDefinition {
             function,
         }
     }
+
+    pub fn volatility(&self) -> Volatility {
+        self.function.volatility()
+    }
 }
 
 /// A function implementation
@@ -111,6 +141,13 @@ pub trait Function: Debug + Sync + 'static {
         signature: &FunctionSignature,
         args: &'a [Datum<'a>],
     ) -> Datum<'a>;
+
+    /// Whether the function can give a different result for the same args, see Volatility.
+    /// Functions reading the session or the clock should be stable, those that can change
+    /// row to row volatile.
+    fn volatility(&self) -> Volatility {
+        Volatility::Immutable
+    }
 }
 
 /// A function implementation for aggregate functions.
@@ -156,6 +193,70 @@ pub trait AggregateFunction: Debug + Sync + 'static {
     fn supports_retract(&self) -> bool {
         false
     }
+
+    /// Appends the state to the buffer in a form that can be stored or sent elsewhere and read
+    /// back by deserialize_state, even by a later version, so the state can outlive the process
+    /// and be merged with states built up elsewhere. The default writes the state's datums as a
+    /// versioned tuple which suits any state made up of plain values.
+    fn serialize_state(&self, state: &[Datum<'static>], buffer: &mut Vec<u8>) {
+        encode_tuple(&state[..self.state_size()], buffer);
+    }
+
+    /// Reads a state written by serialize_state into state, returning the rest of the buffer.
+    fn deserialize_state<'a>(
+        &self,
+        buffer: &'a [u8],
+        state: &mut [Datum<'static>],
+    ) -> Result<&'a [u8], DecodeError> {
+        let (tuple, rem) = decode_tuple(buffer)?;
+        if tuple.len() != self.state_size() {
+            return Err(DecodeError::WrongDatumCount(self.state_size(), tuple.len()));
+        }
+        for (target, datum) in state.iter_mut().zip(tuple) {
+            *target = datum;
+        }
+        Ok(rem)
+    }
+}
+
+/// Plain casts give null for values they can't convert with a warning, unless strict_casts is on
+/// in which case the failure's recorded against the session to fail the statement. Called with
+/// the result of every function call, the try_ casts have their own names so are never checked.
+pub fn check_cast(
+    session: &Session,
+    signature: &FunctionSignature,
+    args: &[Datum],
+    result: &Datum,
+) {
+    if result.is_null()
+        && signature.ret != DataType::Null
+        && signature.name == signature.ret.cast_function()
+        && args.first().map_or(false, |arg| !arg.is_null())
+    {
+        let mut message = format!(
+            "Cannot cast {:#} to {}",
+            args[0].typed_with(signature.args[0]),
+            signature.ret
+        );
+        // Say what's wrong with text that didn't make a valid date or time, or bytes that
+        // aren't valid utf8
+        let reason = match (signature.args[0], signature.ret) {
+            (DataType::Text, DataType::Date) => parse_date(args[0].as_text()).err(),
+            (DataType::Text, DataType::Timestamp) => parse_timestamp(args[0].as_text()).err(),
+            (DataType::ByteA, DataType::Text) => std::str::from_utf8(args[0].as_bytea())
+                .err()
+                .map(|err| format!("invalid utf8 at byte {}", err.valid_up_to())),
+            _ => None,
+        };
+        if let Some(reason) = reason {
+            message = format!("{}, {}", message, reason);
+        }
+        if *session.strict_casts.read().unwrap() {
+            session.cast_failed(message);
+        } else {
+            session.add_warning(Warning::new(WARN_TRUNCATED_WRONG_VALUE, message));
+        }
+    }
 }
 
 fn register_builtins(registry: &mut Registry) {
@@ -2,7 +2,7 @@ use crate::{
     register_builtins, CompoundFunction, CompoundFunctionArg, FunctionDefinition,
     FunctionSignature, FunctionType,
 };
-use data::DataType;
+use data::{cast_kind, CastKind, DataType};
 use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter};
 
@@ -40,6 +40,29 @@ impl Display for FunctionResolutionError {
     }
 }
 
+/// An error registering an embedder's function, see Registry::register_extension_function
+#[derive(Debug, Eq, PartialEq)]
+pub enum RegistrationError {
+    // Namespaces and function names have to be plain identifiers, ie ext and my_func
+    InvalidName(String),
+    // A variant of the function taking the same args is already registered
+    DuplicateFunction(String, Vec<DataType>),
+}
+
+impl Display for RegistrationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegistrationError::InvalidName(name) => {
+                f.write_fmt(format_args!("\"{}\" isn't a valid function name", name))
+            }
+            RegistrationError::DuplicateFunction(function_name, args) => f.write_fmt(format_args!(
+                "Function \"{}\" accepting types {:?} is already registered",
+                function_name, args
+            )),
+        }
+    }
+}
+
 impl Registry {
     pub fn new(with_builtins: bool) -> Self {
         let mut registry = Registry {
@@ -60,6 +83,55 @@ impl Registry {
             .push(function_definition);
     }
 
+    /// Registers an embedder's function alongside the builtins, it's called as namespace.name
+    /// so it can't clash with the builtins or the functions of other namespaces. As with the
+    /// builtins a function can have variants taking different args, registering a variant with
+    /// the same args as one that's already registered is an error.
+    pub fn register_extension_function(
+        &mut self,
+        namespace: &str,
+        mut function_definition: FunctionDefinition,
+    ) -> Result<(), RegistrationError> {
+        for name in &[namespace, function_definition.signature.name] {
+            if !is_identifier(name) {
+                return Err(RegistrationError::InvalidName(name.to_string()));
+            }
+        }
+        // Identifiers are lower cased as they're parsed
+        let name = format!("{}.{}", namespace, function_definition.signature.name).to_lowercase();
+        let args = &function_definition.signature.args;
+        let name = match self.functions.get_key_value(name.as_str()) {
+            Some((_, existing)) if existing.iter().any(|def| def.signature.args == *args) => {
+                return Err(RegistrationError::DuplicateFunction(name, args.clone()));
+            }
+            Some((name, _)) => *name,
+            // Functions are registered once as the runtime's set up so leaking the name to live
+            // as long as the builtins' doesn't add up
+            None => Box::leak(name.into_boxed_str()),
+        };
+        function_definition.signature.name = name;
+        self.register_function(function_definition);
+        Ok(())
+    }
+
+    /// Registers all the variants of an already registered function under another name too
+    pub(crate) fn register_alias(&mut self, alias: &'static str, name: &str) {
+        let definitions: Vec<_> = self.functions[name]
+            .iter()
+            .map(|definition| FunctionDefinition {
+                signature: FunctionSignature {
+                    name: alias,
+                    ..definition.signature.clone()
+                },
+                custom_return_type_resolver: definition.custom_return_type_resolver,
+                function: definition.function.clone(),
+            })
+            .collect();
+        for definition in definitions {
+            self.register_function(definition);
+        }
+    }
+
     pub fn resolve_function(
         &self,
         function_signature: &FunctionSignature,
@@ -168,27 +240,33 @@ impl Registry {
     /// from one type to another type. None is returned where
     /// we wont type widen.
     /// 0 is the highest closeness, we use this for identity or upcasting nulls, ie
-    /// int -> int.
+    /// int -> int. Other than the wildcards in signatures the ranks are the costs of the
+    /// implicit casts in the cast matrix.
     fn datatype_rank(from: DataType, to: DataType) -> Option<u32> {
-        if from == to || from == DataType::Null || to == DataType::Null {
-            return Some(0);
-        }
-
         match (from, to) {
-            // Special case for decimal, functions that accept decimal
-            // accept any sized decimals.
-            (DataType::Decimal(_, _), DataType::Decimal(_, _)) => Some(0),
-            // Int can be cast to bigint and decimal safely
-            (DataType::Integer, DataType::BigInt) => Some(1),
-            (DataType::Integer, DataType::Decimal(_, _)) => Some(2),
-            // Bigint can be cast to decimal safely
-            (DataType::BigInt, DataType::Decimal(_, _)) => Some(1),
-            (DataType::Text, DataType::JsonPath) => Some(1),
-            _ => None,
+            // Signatures taking null take anything, ie to_text
+            (_, DataType::Null) => Some(0),
+            // Special case for enums, functions that accept enums accept any enum, and
+            // enums can be compared etc as their labels
+            (DataType::Enum(_), DataType::Enum(_)) => Some(0),
+            _ => match cast_kind(from, to) {
+                CastKind::Implicit(rank) => Some(rank),
+                CastKind::Explicit | CastKind::Forbidden => None,
+            },
         }
     }
 }
 
+/// Whether the name can be written unquoted, a letter or underscore followed by letters,
+/// digits or underscores
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -227,6 +305,64 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_registry_resolve_alias() {
+        let registry = Registry::new(true);
+
+        let sig = FunctionSignature {
+            name: "try_to_decimal",
+            args: vec![DataType::Text],
+            ret: DataType::Decimal(10, 2),
+        };
+
+        let (function_sig, _function) = registry.resolve_function(&sig).unwrap();
+
+        assert_eq!(function_sig.name, "try_to_decimal");
+        assert_eq!(function_sig.ret, DataType::Decimal(10, 2));
+    }
+
+    #[test]
+    fn test_register_extension_function() {
+        let mut registry = Registry::new(true);
+        let (_, function) = registry
+            .resolve_function(&FunctionSignature {
+                name: "soundex",
+                args: vec![DataType::Text],
+                ret: DataType::Null,
+            })
+            .unwrap();
+        let definition =
+            |args| FunctionDefinition::new("My_Soundex", args, DataType::Text, function.clone());
+
+        registry
+            .register_extension_function("ext", definition(vec![DataType::Text]))
+            .unwrap();
+        registry
+            .register_extension_function("ext", definition(vec![DataType::Integer]))
+            .unwrap();
+        assert_eq!(
+            registry.register_extension_function("ext", definition(vec![DataType::Text])),
+            Err(RegistrationError::DuplicateFunction(
+                "ext.my_soundex".to_string(),
+                vec![DataType::Text]
+            ))
+        );
+        assert_eq!(
+            registry.register_extension_function("ext.more", definition(vec![DataType::Text])),
+            Err(RegistrationError::InvalidName("ext.more".to_string()))
+        );
+
+        let sig = FunctionSignature {
+            name: "ext.my_soundex",
+            args: vec![DataType::Text],
+            ret: DataType::Null,
+        };
+        let (function_sig, _function) = registry.resolve_function(&sig).unwrap();
+        assert_eq!(function_sig.ret, DataType::Text);
+        // The builtin's untouched
+        assert!(registry.list_functions().any(|name| name == "soundex"));
+    }
+
     #[test]
     fn test_registry_resolve_null_param() {
         let registry = Registry::new(true);
@@ -28,6 +28,8 @@ pub fn register_builtins(registry: &mut Registry) {
         DataType::Decimal(0, 0),
         DataType::Text,
         DataType::Date,
+        DataType::Timestamp,
+        DataType::Enum(0),
     ] {
         registry.register_function(FunctionDefinition::new(
             ">",
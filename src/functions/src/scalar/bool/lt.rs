@@ -28,6 +28,9 @@ pub fn register_builtins(registry: &mut Registry) {
         DataType::Decimal(0, 0),
         DataType::Text,
         DataType::Date,
+        DataType::Timestamp,
+        // Enums compare in the order their labels were declared in
+        DataType::Enum(0),
     ] {
         registry.register_function(FunctionDefinition::new(
             "<",
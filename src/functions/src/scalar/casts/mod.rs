@@ -1,9 +1,13 @@
 use crate::registry::Registry;
+use crate::{Function, FunctionSignature};
+use data::{Datum, Session};
 
 mod to_bigint;
 mod to_bool;
+mod to_bytes;
 mod to_date;
 mod to_decimal;
+mod to_enum;
 mod to_int;
 mod to_json;
 mod to_jsonpath;
@@ -11,15 +15,81 @@ mod to_text;
 mod to_timestamp;
 mod type_of;
 
+/// A cast from a type to itself, the value's left as is
+#[derive(Debug)]
+struct Identity {}
+
+impl Function for Identity {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        args[0].ref_clone()
+    }
+}
+
 pub fn register_builtins(registry: &mut Registry) {
     to_bigint::register_builtins(registry);
     to_bool::register_builtins(registry);
+    to_bytes::register_builtins(registry);
     to_date::register_builtins(registry);
     to_decimal::register_builtins(registry);
+    to_enum::register_builtins(registry);
     to_int::register_builtins(registry);
     to_json::register_builtins(registry);
     to_jsonpath::register_builtins(registry);
     to_text::register_builtins(registry);
     to_timestamp::register_builtins(registry);
     type_of::register_builtins(registry);
+
+    // The try_ variants are the same functions, they're just never checked under strict_casts
+    for (try_name, name) in &[
+        ("try_to_bigint", "to_bigint"),
+        ("try_to_bool", "to_bool"),
+        ("try_to_bytes", "to_bytes"),
+        ("try_to_date", "to_date"),
+        ("try_to_decimal", "to_decimal"),
+        ("try_to_enum", "to_enum"),
+        ("try_to_int", "to_int"),
+        ("try_to_json", "to_json"),
+        ("try_to_jsonpath", "to_jsonpath"),
+        ("try_to_text", "to_text"),
+        ("try_to_timestamp", "to_timestamp"),
+    ] {
+        registry.register_alias(try_name, name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FunctionType;
+    use data::{cast_matrix, CastKind, DataType};
+
+    #[test]
+    fn test_cast_matrix() {
+        // The cast functions have to line up with the cast matrix, every cast that's allowed
+        // needs a cast function of its own
+        let registry = Registry::default();
+        for (from, to, kind) in cast_matrix().filter(|(_, to, _)| *to != DataType::Null) {
+            for name in &[to.cast_function(), to.try_cast_function()] {
+                let resolved = registry.resolve_function(&FunctionSignature {
+                    name,
+                    args: vec![from],
+                    ret: to,
+                });
+                let has_cast = matches!(resolved, Ok((_, FunctionType::Scalar(_))));
+                assert_eq!(
+                    has_cast,
+                    kind != CastKind::Forbidden,
+                    "{} from {} to {}",
+                    name,
+                    from,
+                    to
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,62 @@
+use crate::registry::Registry;
+use crate::scalar::casts::Identity;
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
+use data::{DataType, Datum, Session};
+
+/// The utf8 bytes of the text
+#[derive(Debug)]
+struct ToBytesFromText {}
+
+impl Function for ToBytesFromText {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        if let Some(s) = args[0].as_maybe_text() {
+            Datum::ByteARef(s.as_bytes())
+        } else {
+            Datum::Null
+        }
+    }
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "to_bytes",
+        vec![DataType::Text],
+        DataType::ByteA,
+        FunctionType::Scalar(&ToBytesFromText {}),
+    ));
+
+    registry.register_function(FunctionDefinition::new(
+        "to_bytes",
+        vec![DataType::ByteA],
+        DataType::ByteA,
+        FunctionType::Scalar(&Identity {}),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUMMY_SIG: FunctionSignature = FunctionSignature {
+        name: "to_bytes",
+        args: vec![],
+        ret: DataType::ByteA,
+    };
+
+    #[test]
+    fn test_text() {
+        assert_eq!(
+            ToBytesFromText {}.execute(&Session::new(1), &DUMMY_SIG, &[Datum::from("abc")]),
+            Datum::from(b"abc".to_vec())
+        );
+        assert_eq!(
+            ToBytesFromText {}.execute(&Session::new(1), &DUMMY_SIG, &[Datum::Null]),
+            Datum::Null
+        );
+    }
+}
@@ -1,8 +1,7 @@
 use crate::registry::Registry;
+use crate::scalar::casts::Identity;
 use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
-use data::chrono::NaiveDate;
-use data::{DataType, Datum, Session};
-use std::str::FromStr;
+use data::{parse_date, DataType, Datum, Session};
 
 #[derive(Debug)]
 struct ToDateFromText {}
@@ -15,16 +14,30 @@ impl Function for ToDateFromText {
         args: &'a [Datum<'a>],
     ) -> Datum<'a> {
         if let Some(s) = args[0].as_maybe_text() {
-            NaiveDate::from_str(s)
-                .ok()
-                .map(Datum::from)
-                .unwrap_or_default()
+            parse_date(s).ok().map(Datum::from).unwrap_or_default()
         } else {
             Datum::Null
         }
     }
 }
 
+#[derive(Debug)]
+struct ToDateFromTimestamp {}
+
+impl Function for ToDateFromTimestamp {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        args[0]
+            .as_maybe_timestamp()
+            .map(|timestamp| Datum::from(timestamp.date()))
+            .unwrap_or_default()
+    }
+}
+
 pub fn register_builtins(registry: &mut Registry) {
     registry.register_function(FunctionDefinition::new(
         "to_date",
@@ -32,11 +45,26 @@ pub fn register_builtins(registry: &mut Registry) {
         DataType::Date,
         FunctionType::Scalar(&ToDateFromText {}),
     ));
+
+    registry.register_function(FunctionDefinition::new(
+        "to_date",
+        vec![DataType::Date],
+        DataType::Date,
+        FunctionType::Scalar(&Identity {}),
+    ));
+
+    registry.register_function(FunctionDefinition::new(
+        "to_date",
+        vec![DataType::Timestamp],
+        DataType::Date,
+        FunctionType::Scalar(&ToDateFromTimestamp {}),
+    ));
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use data::chrono::NaiveDate;
 
     const DUMMY_SIG: FunctionSignature = FunctionSignature {
         name: "to_date",
@@ -67,4 +95,20 @@ mod tests {
             Datum::Null
         )
     }
+
+    #[test]
+    fn test_from_text_bce() {
+        assert_eq!(
+            ToDateFromText {}.execute(&Session::new(1), &DUMMY_SIG, &[Datum::from("-0044-03-15")]),
+            Datum::from(NaiveDate::from_ymd(-44, 3, 15))
+        )
+    }
+
+    #[test]
+    fn test_from_text_impossible() {
+        assert_eq!(
+            ToDateFromText {}.execute(&Session::new(1), &DUMMY_SIG, &[Datum::from("2021-02-29")]),
+            Datum::Null
+        )
+    }
 }
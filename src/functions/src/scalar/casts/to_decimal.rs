@@ -1,9 +1,32 @@
 use crate::registry::Registry;
+use crate::scalar::maths::fit_decimal;
 use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
 use data::rust_decimal::Decimal;
-use data::{DataType, Datum, Session, DECIMAL_MAX_PRECISION, DECIMAL_MAX_SCALE};
+use data::{
+    DataType, Datum, Session, Warning, DECIMAL_MAX_PRECISION, DECIMAL_MAX_SCALE,
+    WARN_DATA_TRUNCATED,
+};
 use std::str::FromStr;
 
+/// We'll rescale to match the cast, (down scaling only, no point upscaling as it just potentially
+/// loses data). Values that get rounded are noted as a warning, values too big for the cast are
+/// left to the session's decimal_overflow.
+fn rescale<'a>(session: &Session, signature: &FunctionSignature, mut d: Decimal) -> Datum<'a> {
+    if let DataType::Decimal(_p, s) = signature.ret {
+        if (s as u32) < d.scale() {
+            let original = d;
+            d.rescale(s as u32);
+            if d != original {
+                session.add_warning(Warning::note(
+                    WARN_DATA_TRUNCATED,
+                    format!("Data truncated, {} rounded to {}", original, d),
+                ));
+            }
+        }
+    }
+    fit_decimal(session, signature, Some(d), false)
+}
+
 #[derive(Debug)]
 struct ToDecimalFromBoolean {}
 
@@ -32,12 +55,12 @@ struct ToDecimalFromInt {}
 impl Function for ToDecimalFromInt {
     fn execute<'a>(
         &self,
-        _session: &Session,
-        _signature: &FunctionSignature,
+        session: &Session,
+        signature: &FunctionSignature,
         args: &'a [Datum<'a>],
     ) -> Datum<'a> {
         if let Some(a) = args[0].as_maybe_integer() {
-            Datum::from(Decimal::from(a))
+            fit_decimal(session, signature, Some(Decimal::from(a)), false)
         } else {
             Datum::Null
         }
@@ -50,12 +73,12 @@ struct ToDecimalFromBigInt {}
 impl Function for ToDecimalFromBigInt {
     fn execute<'a>(
         &self,
-        _session: &Session,
-        _signature: &FunctionSignature,
+        session: &Session,
+        signature: &FunctionSignature,
         args: &'a [Datum<'a>],
     ) -> Datum<'a> {
         if let Some(a) = args[0].as_maybe_bigint() {
-            Datum::from(Decimal::from(a))
+            fit_decimal(session, signature, Some(Decimal::from(a)), false)
         } else {
             Datum::Null
         }
@@ -68,21 +91,12 @@ struct ToDecimalFromDecimal {}
 impl Function for ToDecimalFromDecimal {
     fn execute<'a>(
         &self,
-        _session: &Session,
+        session: &Session,
         signature: &FunctionSignature,
         args: &'a [Datum<'a>],
     ) -> Datum<'a> {
-        if let Some(mut d) = args[0].as_maybe_decimal() {
-            if let DataType::Decimal(_p, s) = signature.ret {
-                // We'll rescale to match the cast, (down scaling only, no point upscaling as it just potentially loses
-                // data
-                if (s as u32) < d.scale() {
-                    d.rescale(s as u32);
-                }
-                Datum::from(d)
-            } else {
-                panic!()
-            }
+        if let Some(d) = args[0].as_maybe_decimal() {
+            rescale(session, signature, d)
         } else {
             Datum::Null
         }
@@ -95,18 +109,13 @@ struct ToDecimalFromText {}
 impl Function for ToDecimalFromText {
     fn execute<'a>(
         &self,
-        _session: &Session,
+        session: &Session,
         signature: &FunctionSignature,
         args: &'a [Datum<'a>],
     ) -> Datum<'a> {
         if let Some(a) = args[0].as_maybe_text() {
-            if let (Ok(mut d), DataType::Decimal(_p, s)) = (Decimal::from_str(a), signature.ret) {
-                // We'll rescale to match the cast, (down scaling only, no point upscaling as it just potentially loses
-                // data
-                if (s as u32) < d.scale() {
-                    d.rescale(s as u32);
-                }
-                Datum::from(d)
+            if let Ok(d) = Decimal::from_str(a) {
+                rescale(session, signature, d)
             } else {
                 Datum::Null
             }
@@ -122,30 +131,19 @@ struct ToDecimalFromJson {}
 impl Function for ToDecimalFromJson {
     fn execute<'a>(
         &self,
-        _session: &Session,
+        session: &Session,
         signature: &FunctionSignature,
         args: &'a [Datum<'a>],
     ) -> Datum<'a> {
         // We need to try both the json::number and the json::text and do rescaling
-        if let (Some(mut d), DataType::Decimal(_p, s)) = (
-            args[0].as_maybe_json().and_then(|j| j.get_number()),
-            signature.ret,
-        ) {
-            if (s as u32) < d.scale() {
-                d.rescale(s as u32);
-            }
-            Datum::from(d)
-        } else if let (Some(mut d), DataType::Decimal(_p, s)) = (
-            args[0]
-                .as_maybe_json()
-                .and_then(|j| j.get_string())
-                .and_then(|s| Decimal::from_str(s).ok()),
-            signature.ret,
-        ) {
-            if (s as u32) < d.scale() {
-                d.rescale(s as u32);
-            }
-            Datum::from(d)
+        if let Some(d) = args[0].as_maybe_json().and_then(|j| j.get_number()) {
+            rescale(session, signature, d)
+        } else if let Some(d) = args[0]
+            .as_maybe_json()
+            .and_then(|j| j.get_string())
+            .and_then(|s| Decimal::from_str(s).ok())
+        {
+            rescale(session, signature, d)
         } else {
             Datum::Null
         }
@@ -244,14 +242,31 @@ mod tests {
 
     #[test]
     fn test_from_decimal() {
+        let session = Session::new(1);
         assert_eq!(
             ToDecimalFromDecimal {}.execute(
-                &Session::new(1),
+                &session,
                 &DUMMY_SIG,
                 &[Datum::from(Decimal::new(123456, 4))]
             ),
             Datum::from(Decimal::new(1235, 2))
-        )
+        );
+        // Dropping trailing zeros doesn't lose anything
+        assert_eq!(
+            ToDecimalFromDecimal {}.execute(
+                &session,
+                &DUMMY_SIG,
+                &[Datum::from(Decimal::new(123400, 4))]
+            ),
+            Datum::from(Decimal::new(1234, 2))
+        );
+        assert_eq!(
+            session.warnings(),
+            vec![Warning::note(
+                WARN_DATA_TRUNCATED,
+                "Data truncated, 12.3456 rounded to 12.35".to_string()
+            )]
+        );
     }
 
     #[test]
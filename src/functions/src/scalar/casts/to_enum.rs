@@ -0,0 +1,124 @@
+use crate::registry::Registry;
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
+use data::{DataType, Datum, Session};
+
+/// Returns the code of the label in the enum type, the index of the label in the enum's labels
+fn enum_code(datatype: DataType, label: &str) -> Datum<'static> {
+    datatype
+        .enum_labels()
+        .and_then(|labels| labels.iter().position(|l| l == label))
+        .map(|idx| Datum::from(idx as i32))
+        .unwrap_or(Datum::Null)
+}
+
+#[derive(Debug)]
+struct ToEnumFromText {}
+
+impl Function for ToEnumFromText {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        if let Some(label) = args[0].as_maybe_text() {
+            enum_code(signature.ret, label)
+        } else {
+            Datum::Null
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ToEnumFromEnum {}
+
+impl Function for ToEnumFromEnum {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        if signature.args[0] == signature.ret {
+            return args[0].ref_clone();
+        }
+        // Different label sets so we match them up by label
+        let label = args[0].as_maybe_integer().and_then(|code| {
+            signature.args[0]
+                .enum_labels()
+                .and_then(|labels| labels.get(code as usize))
+        });
+        if let Some(label) = label {
+            enum_code(signature.ret, label)
+        } else {
+            Datum::Null
+        }
+    }
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "to_enum",
+        vec![DataType::Text],
+        DataType::Enum(0),
+        FunctionType::Scalar(&ToEnumFromText {}),
+    ));
+
+    registry.register_function(FunctionDefinition::new(
+        "to_enum",
+        vec![DataType::Enum(0)],
+        DataType::Enum(0),
+        FunctionType::Scalar(&ToEnumFromEnum {}),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signature(from: DataType, to: DataType) -> FunctionSignature<'static> {
+        FunctionSignature {
+            name: "to_enum",
+            args: vec![from],
+            ret: to,
+        }
+    }
+
+    #[test]
+    fn test_from_text() {
+        let datatype = DataType::new_enum(&["new".to_string(), "paid".to_string()]);
+        let sig = signature(DataType::Text, datatype);
+        let session = Session::new(1);
+        assert_eq!(
+            ToEnumFromText {}.execute(&session, &sig, &[Datum::from("paid")]),
+            Datum::from(1)
+        );
+        assert_eq!(
+            ToEnumFromText {}.execute(&session, &sig, &[Datum::from("void")]),
+            Datum::Null
+        );
+        assert_eq!(
+            ToEnumFromText {}.execute(&session, &sig, &[Datum::Null]),
+            Datum::Null
+        );
+    }
+
+    #[test]
+    fn test_from_enum() {
+        let from = DataType::new_enum(&["new".to_string(), "paid".to_string()]);
+        let to = DataType::new_enum(&["paid".to_string(), "void".to_string()]);
+        let session = Session::new(1);
+        assert_eq!(
+            ToEnumFromEnum {}.execute(&session, &signature(from, from), &[Datum::from(1)]),
+            Datum::from(1)
+        );
+        assert_eq!(
+            ToEnumFromEnum {}.execute(&session, &signature(from, to), &[Datum::from(1)]),
+            Datum::from(0)
+        );
+        assert_eq!(
+            ToEnumFromEnum {}.execute(&session, &signature(from, to), &[Datum::from(0)]),
+            Datum::Null
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::registry::Registry;
+use crate::scalar::casts::Identity;
 use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
 use data::json::{JsonBuilder, OwnedJson};
 use data::{DataType, Datum, Session};
@@ -128,6 +129,13 @@ pub fn register_builtins(registry: &mut Registry) {
         DataType::Json,
         FunctionType::Scalar(&ToJsonFromText {}),
     ));
+
+    registry.register_function(FunctionDefinition::new(
+        "to_json",
+        vec![DataType::Json],
+        DataType::Json,
+        FunctionType::Scalar(&Identity {}),
+    ));
 }
 
 #[cfg(test)]
@@ -1,12 +1,41 @@
 use crate::registry::Registry;
+use crate::scalar::casts::Identity;
 use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
 use data::jsonpath_utils::JsonPathExpression;
 use data::{DataType, Datum, Session};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// How many compiled paths are kept around per thread before the cache is cleared
+const COMPILED_PATHS_CAPACITY: usize = 256;
+
+thread_local! {
+    // Constant paths are compiled once when they're folded, paths that come from columns are
+    // usually the same handful of paths over and over so we skip parsing them for every row.
+    static COMPILED_PATHS: RefCell<HashMap<String, JsonPathExpression>> =
+        RefCell::new(HashMap::new());
+}
 
 /// Compiles a jsonpath expression into a json object
 #[derive(Debug)]
 struct ToJsonpath {}
 
+/// Parses the path, reusing the compiled path if this thread's parsed it recently
+fn compile(json_path: &str) -> Option<JsonPathExpression> {
+    COMPILED_PATHS.with(|compiled_paths| {
+        let mut compiled_paths = compiled_paths.borrow_mut();
+        if let Some(expr) = compiled_paths.get(json_path) {
+            return Some(expr.clone());
+        }
+        let expr = JsonPathExpression::parse(json_path)?;
+        if compiled_paths.len() >= COMPILED_PATHS_CAPACITY {
+            compiled_paths.clear();
+        }
+        compiled_paths.insert(json_path.to_string(), expr.clone());
+        Some(expr)
+    })
+}
+
 impl Function for ToJsonpath {
     fn execute<'a>(
         &self,
@@ -15,7 +44,7 @@ impl Function for ToJsonpath {
         args: &'a [Datum<'a>],
     ) -> Datum<'a> {
         if let Some(json_path) = args[0].as_maybe_text() {
-            if let Some(expr) = JsonPathExpression::parse(json_path) {
+            if let Some(expr) = compile(json_path) {
                 Datum::Jsonpath(Box::new(expr))
             } else {
                 Datum::Null
@@ -33,6 +62,13 @@ pub fn register_builtins(registry: &mut Registry) {
         DataType::JsonPath,
         FunctionType::Scalar(&ToJsonpath {}),
     ));
+
+    registry.register_function(FunctionDefinition::new(
+        "to_jsonpath",
+        vec![DataType::JsonPath],
+        DataType::JsonPath,
+        FunctionType::Scalar(&Identity {}),
+    ));
 }
 
 #[cfg(test)]
@@ -52,5 +88,14 @@ mod tests {
             ToJsonpath {}.execute(&Session::new(1), &DUMMY_SIG, &[Datum::from(r#"$.foo"#)]),
             Datum::Jsonpath(Box::from(JsonPathExpression::parse("$.foo").unwrap()))
         );
+        // The second time it comes out of the cache
+        assert_eq!(
+            ToJsonpath {}.execute(&Session::new(1), &DUMMY_SIG, &[Datum::from(r#"$.foo"#)]),
+            Datum::Jsonpath(Box::from(JsonPathExpression::parse("$.foo").unwrap()))
+        );
+        assert_eq!(
+            ToJsonpath {}.execute(&Session::new(1), &DUMMY_SIG, &[Datum::from(r#"$.foo["#)]),
+            Datum::Null
+        );
     }
 }
@@ -1,6 +1,7 @@
 use crate::registry::Registry;
 use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
 use data::{DataType, Datum, Session};
+use std::borrow::Cow;
 
 #[derive(Debug)]
 struct ToTextFromText {}
@@ -38,6 +39,29 @@ impl Function for ToTextFromBoolean {
     }
 }
 
+/// Decodes the bytes as utf8, invalid bytes are replaced or give null (and so fail the cast)
+/// depending on the session's invalid_utf8 policy
+#[derive(Debug)]
+struct ToTextFromBytes {}
+
+impl Function for ToTextFromBytes {
+    fn execute<'a>(
+        &self,
+        session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        match args[0]
+            .as_maybe_bytea()
+            .map(|bytes| session.decode_utf8(bytes))
+        {
+            Some(Ok(Cow::Borrowed(text))) => Datum::from(text),
+            Some(Ok(Cow::Owned(text))) => Datum::from(text),
+            _ => Datum::Null,
+        }
+    }
+}
+
 #[derive(Debug)]
 struct ToTextFromAny {}
 
@@ -71,6 +95,13 @@ pub fn register_builtins(registry: &mut Registry) {
         FunctionType::Scalar(&ToTextFromText {}),
     ));
 
+    registry.register_function(FunctionDefinition::new(
+        "to_text",
+        vec![DataType::ByteA],
+        DataType::Text,
+        FunctionType::Scalar(&ToTextFromBytes {}),
+    ));
+
     registry.register_function(FunctionDefinition::new(
         "to_text",
         vec![DataType::Null],
@@ -83,6 +114,7 @@ pub fn register_builtins(registry: &mut Registry) {
 mod tests {
     use super::*;
     use data::rust_decimal::Decimal;
+    use data::InvalidUtf8;
 
     fn sig(input_type: DataType) -> FunctionSignature<'static> {
         FunctionSignature {
@@ -112,6 +144,30 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_from_bytes() {
+        let session = Session::new(1);
+        let bytes = [Datum::from(b"ab\xffc".to_vec())];
+        assert_eq!(
+            ToTextFromBytes {}.execute(
+                &session,
+                &sig(DataType::ByteA),
+                &[Datum::from(b"abc".to_vec())]
+            ),
+            Datum::from("abc")
+        );
+        assert_eq!(
+            ToTextFromBytes {}.execute(&session, &sig(DataType::ByteA), &bytes),
+            Datum::Null
+        );
+
+        *session.invalid_utf8.write().unwrap() = InvalidUtf8::Replace;
+        assert_eq!(
+            ToTextFromBytes {}.execute(&session, &sig(DataType::ByteA), &bytes),
+            Datum::from("ab\u{FFFD}c")
+        );
+    }
+
     #[test]
     fn test_from_int() {
         assert_eq!(
@@ -1,8 +1,7 @@
 use crate::registry::Registry;
+use crate::scalar::casts::Identity;
 use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
-use data::chrono::NaiveDateTime;
-use data::{DataType, Datum, Session};
-use std::str::FromStr;
+use data::{parse_timestamp, DataType, Datum, Session};
 
 #[derive(Debug)]
 struct ToTimestampFromText {}
@@ -15,16 +14,32 @@ impl Function for ToTimestampFromText {
         args: &'a [Datum<'a>],
     ) -> Datum<'a> {
         if let Some(s) = args[0].as_maybe_text() {
-            NaiveDateTime::from_str(s)
-                .ok()
-                .map(Datum::from)
-                .unwrap_or_default()
+            // Also accepts timestamps the way we print them so they survive a round trip as text
+            parse_timestamp(s).ok().map(Datum::from).unwrap_or_default()
         } else {
             Datum::Null
         }
     }
 }
 
+/// Dates are taken as midnight at the start of the day
+#[derive(Debug)]
+struct ToTimestampFromDate {}
+
+impl Function for ToTimestampFromDate {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        args[0]
+            .as_maybe_date()
+            .map(|date| Datum::from(date.and_hms(0, 0, 0)))
+            .unwrap_or_default()
+    }
+}
+
 pub fn register_builtins(registry: &mut Registry) {
     registry.register_function(FunctionDefinition::new(
         "to_timestamp",
@@ -32,12 +47,26 @@ pub fn register_builtins(registry: &mut Registry) {
         DataType::Timestamp,
         FunctionType::Scalar(&ToTimestampFromText {}),
     ));
+
+    registry.register_function(FunctionDefinition::new(
+        "to_timestamp",
+        vec![DataType::Timestamp],
+        DataType::Timestamp,
+        FunctionType::Scalar(&Identity {}),
+    ));
+
+    registry.register_function(FunctionDefinition::new(
+        "to_timestamp",
+        vec![DataType::Date],
+        DataType::Timestamp,
+        FunctionType::Scalar(&ToTimestampFromDate {}),
+    ));
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use data::chrono::{NaiveDate, NaiveTime};
+    use data::chrono::{NaiveDate, NaiveDateTime, NaiveTime};
 
     const DUMMY_SIG: FunctionSignature = FunctionSignature {
         name: "to_date",
@@ -68,6 +97,21 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_from_displayed_text() {
+        assert_eq!(
+            ToTimestampFromText {}.execute(
+                &Session::new(1),
+                &DUMMY_SIG,
+                &[Datum::from("2010-10-01 10:00:00.5")]
+            ),
+            Datum::from(NaiveDateTime::new(
+                NaiveDate::from_ymd(2010, 10, 1),
+                NaiveTime::from_hms_milli(10, 0, 0, 500)
+            ))
+        )
+    }
+
     #[test]
     fn test_from_text_malformed() {
         assert_eq!(
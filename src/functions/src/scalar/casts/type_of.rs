@@ -5,6 +5,9 @@ use data::{DataType, Datum, Session};
 #[derive(Debug)]
 struct TypeOf {}
 
+/// type_of(expr), also typeof(expr)
+/// The type the planner resolved for the expression, handy for seeing what implicit casts and
+/// decimal precision/scale a calculation ends up with.
 impl Function for TypeOf {
     fn execute<'a>(
         &self,
@@ -23,6 +26,8 @@ pub fn register_builtins(registry: &mut Registry) {
         DataType::Text,
         FunctionType::Scalar(&TypeOf {}),
     ));
+
+    registry.register_alias("typeof", "type_of");
 }
 
 #[cfg(test)]
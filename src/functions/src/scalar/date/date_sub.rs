@@ -7,15 +7,25 @@ use data::{DataType, Datum, Session};
 struct DateSub {}
 
 /// date_sub(date, int)
+/// Dates that end up out of range fail the statement.
 impl Function for DateSub {
     fn execute<'a>(
         &self,
-        _session: &Session,
+        session: &Session,
         _signature: &FunctionSignature,
         args: &'a [Datum<'a>],
     ) -> Datum<'a> {
         if let (Some(date), Some(days)) = (args[0].as_maybe_date(), args[1].as_maybe_integer()) {
-            Datum::from(date - Duration::days(days as i64))
+            match date.checked_sub_signed(Duration::days(days as i64)) {
+                Some(date) => Datum::from(date),
+                None => {
+                    session.cast_failed(format!(
+                        "Out of range value for DATE, {} minus {} days",
+                        date, days
+                    ));
+                    Datum::Null
+                }
+            }
         } else {
             Datum::Null
         }
@@ -64,4 +74,24 @@ mod tests {
             Datum::from(NaiveDate::from_ymd(2020, 05, 10))
         )
     }
+
+    #[test]
+    fn test_date_sub_out_of_range() {
+        let session = Session::new(1);
+        assert_eq!(
+            DateSub {}.execute(
+                &session,
+                &DUMMY_SIG,
+                &[
+                    Datum::from(NaiveDate::from_ymd(-262000, 1, 1)),
+                    Datum::from(1_000_000)
+                ]
+            ),
+            Datum::Null
+        );
+        assert_eq!(
+            session.take_cast_error(),
+            Some("Out of range value for DATE, -262000-01-01 minus 1000000 days".to_string())
+        );
+    }
 }
@@ -1,6 +1,8 @@
 use crate::registry::Registry;
 mod date_sub;
+mod time_bucket;
 
 pub fn register_builtins(registry: &mut Registry) {
     date_sub::register_builtins(registry);
+    time_bucket::register_builtins(registry);
 }
@@ -0,0 +1,160 @@
+use crate::registry::Registry;
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
+use data::chrono::{Duration, NaiveDateTime};
+use data::{parse_duration_ms, DataType, Datum, Session};
+
+#[derive(Debug)]
+struct TimeBucket {}
+
+/// time_bucket(width, timestamp)
+/// Truncates the timestamp down to the start of its bucket, buckets are aligned to the unix
+/// epoch. The width can either be a number of milliseconds or text such as '15 minutes' or
+/// '1h'. Widths that aren't positive or can't be parsed give null, as do buckets starting before
+/// the earliest timestamp.
+impl Function for TimeBucket {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        let width = if signature.args[0] == DataType::Text {
+            args[0].as_maybe_text().and_then(parse_duration_ms)
+        } else {
+            args[0].as_maybe_bigint()
+        };
+
+        if let (Some(width), Some(timestamp)) = (width, args[1].as_maybe_timestamp()) {
+            if width > 0 {
+                return time_bucket(width, timestamp).map_or(Datum::Null, Datum::from);
+            }
+        }
+        Datum::Null
+    }
+}
+
+fn time_bucket(width: i64, timestamp: NaiveDateTime) -> Option<NaiveDateTime> {
+    // timestamp_millis drops anything under a millisecond, that has to go too
+    let offset = Duration::milliseconds(timestamp.timestamp_millis().rem_euclid(width))
+        + Duration::nanoseconds(i64::from(timestamp.timestamp_subsec_nanos() % 1_000_000));
+    timestamp.checked_sub_signed(offset)
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "time_bucket",
+        vec![DataType::BigInt, DataType::Timestamp],
+        DataType::Timestamp,
+        FunctionType::Scalar(&TimeBucket {}),
+    ));
+
+    registry.register_function(FunctionDefinition::new(
+        "time_bucket",
+        vec![DataType::Text, DataType::Timestamp],
+        DataType::Timestamp,
+        FunctionType::Scalar(&TimeBucket {}),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::chrono::naive::MIN_DATE;
+    use data::chrono::NaiveDate;
+
+    fn sig(datatype: DataType) -> FunctionSignature<'static> {
+        FunctionSignature {
+            name: "time_bucket",
+            args: vec![datatype, DataType::Timestamp],
+            ret: DataType::Timestamp,
+        }
+    }
+
+    fn timestamp(h: u32, m: u32, s: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(2020, 3, 1).and_hms(h, m, s)
+    }
+
+    #[test]
+    fn test_null() {
+        assert_eq!(
+            TimeBucket {}.execute(
+                &Session::new(1),
+                &sig(DataType::BigInt),
+                &[Datum::Null, Datum::from(timestamp(1, 2, 3))]
+            ),
+            Datum::Null
+        );
+        assert_eq!(
+            TimeBucket {}.execute(
+                &Session::new(1),
+                &sig(DataType::BigInt),
+                &[Datum::from(0_i64), Datum::from(timestamp(1, 2, 3))]
+            ),
+            Datum::Null
+        );
+        assert_eq!(
+            TimeBucket {}.execute(
+                &Session::new(1),
+                &sig(DataType::Text),
+                &[Datum::from("5 fortnights"), Datum::from(timestamp(1, 2, 3))]
+            ),
+            Datum::Null
+        );
+    }
+
+    #[test]
+    fn test_time_bucket() {
+        assert_eq!(
+            TimeBucket {}.execute(
+                &Session::new(1),
+                &sig(DataType::BigInt),
+                &[Datum::from(60_000_i64), Datum::from(timestamp(1, 2, 3))]
+            ),
+            Datum::from(timestamp(1, 2, 0))
+        );
+        assert_eq!(
+            TimeBucket {}.execute(
+                &Session::new(1),
+                &sig(DataType::Text),
+                &[Datum::from("15 minutes"), Datum::from(timestamp(1, 29, 59))]
+            ),
+            Datum::from(timestamp(1, 15, 0))
+        );
+        // Anything under a millisecond is dropped too
+        assert_eq!(
+            TimeBucket {}.execute(
+                &Session::new(1),
+                &sig(DataType::BigInt),
+                &[
+                    Datum::from(1_i64),
+                    Datum::from(NaiveDate::from_ymd(2020, 3, 1).and_hms_micro(1, 2, 3, 456_789))
+                ]
+            ),
+            Datum::from(NaiveDate::from_ymd(2020, 3, 1).and_hms_milli(1, 2, 3, 456))
+        );
+        assert_eq!(
+            time_bucket(
+                1000,
+                NaiveDate::from_ymd(1969, 12, 31).and_hms_micro(23, 59, 59, 999_999)
+            ),
+            Some(NaiveDate::from_ymd(1969, 12, 31).and_hms(23, 59, 59))
+        );
+        // Before the epoch still rounds down
+        assert_eq!(
+            time_bucket(
+                24 * 60 * 60 * 1000,
+                NaiveDate::from_ymd(1969, 12, 31).and_hms(12, 0, 0)
+            ),
+            Some(NaiveDate::from_ymd(1969, 12, 31).and_hms(0, 0, 0))
+        );
+        // Nothing to round down to
+        assert_eq!(
+            time_bucket(24 * 60 * 60 * 1000, MIN_DATE.and_hms(12, 0, 0)),
+            Some(MIN_DATE.and_hms(0, 0, 0))
+        );
+        assert_eq!(
+            time_bucket(7 * 24 * 60 * 60 * 1000, MIN_DATE.and_hms(12, 0, 0)),
+            None
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use super::format_number::NumberPattern;
+use crate::registry::Registry;
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
+use data::{DataType, Datum, Session};
+
+#[derive(Debug)]
+struct FormatCurrency {}
+
+/// format_currency(decimal, currency_code)
+/// Formats the amount with the currency's symbol, grouped thousands and the currency's number
+/// of minor digits, ie format_currency(-1234.5, 'USD') = -$1,234.50. Amounts are rounded with
+/// banker's rounding. Unknown currency codes return null.
+impl Function for FormatCurrency {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        if let (Some(amount), Some(code)) = (args[0].as_maybe_decimal(), args[1].as_maybe_text()) {
+            if let Some((symbol, minor_digits)) = currency(code) {
+                let pattern = NumberPattern {
+                    prefix: symbol,
+                    suffix: "",
+                    min_integer_digits: 1,
+                    grouping_size: Some(3),
+                    min_fraction_digits: minor_digits,
+                    max_fraction_digits: minor_digits,
+                    percent: false,
+                };
+                return Datum::from(pattern.format(amount.round_dp(minor_digits as u32)));
+            }
+        }
+        Datum::Null
+    }
+}
+
+/// The symbol and number of minor digits of the currency
+fn currency(code: &str) -> Option<(&'static str, usize)> {
+    let currency = match code.to_ascii_uppercase().as_str() {
+        "AUD" => ("A$", 2),
+        "CAD" => ("CA$", 2),
+        "CHF" => ("CHF ", 2),
+        "CNY" => ("CN¥", 2),
+        "EUR" => ("€", 2),
+        "GBP" => ("£", 2),
+        "INR" => ("₹", 2),
+        "JPY" => ("¥", 0),
+        "KRW" => ("₩", 0),
+        "NZD" => ("NZ$", 2),
+        "USD" => ("$", 2),
+        _ => return None,
+    };
+    Some(currency)
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "format_currency",
+        vec![DataType::Decimal(0, 0), DataType::Text],
+        DataType::Text,
+        FunctionType::Scalar(&FormatCurrency {}),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::rust_decimal::Decimal;
+    use std::str::FromStr;
+
+    const DUMMY_SIG: FunctionSignature = FunctionSignature {
+        name: "format_currency",
+        args: vec![],
+        ret: DataType::Text,
+    };
+
+    fn format_currency(amount: &str, code: &str) -> Datum<'static> {
+        FormatCurrency {}
+            .execute(
+                &Session::new(1),
+                &DUMMY_SIG,
+                &[
+                    Datum::from(Decimal::from_str(amount).unwrap()),
+                    Datum::from(code),
+                ],
+            )
+            .as_static()
+    }
+
+    #[test]
+    fn test_null() {
+        assert_eq!(
+            FormatCurrency {}.execute(
+                &Session::new(1),
+                &DUMMY_SIG,
+                &[Datum::Null, Datum::from("USD")]
+            ),
+            Datum::Null
+        );
+        assert_eq!(format_currency("1", "XYZ"), Datum::Null);
+    }
+
+    #[test]
+    fn test_format_currency() {
+        assert_eq!(format_currency("-1234.5", "USD"), Datum::from("-$1,234.50"));
+        assert_eq!(
+            format_currency("1234567", "eur"),
+            Datum::from("€1,234,567.00")
+        );
+        // Banker's rounding
+        assert_eq!(format_currency("0.125", "GBP"), Datum::from("£0.12"));
+        assert_eq!(format_currency("2500.5", "JPY"), Datum::from("¥2,500"));
+        assert_eq!(format_currency("2501.5", "JPY"), Datum::from("¥2,502"));
+        assert_eq!(format_currency("-0.001", "CHF"), Datum::from("CHF 0.00"));
+    }
+}
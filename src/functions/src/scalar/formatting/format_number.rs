@@ -0,0 +1,212 @@
+use crate::registry::Registry;
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
+use data::rust_decimal::Decimal;
+use data::{DataType, Datum, Session};
+
+#[derive(Debug)]
+struct FormatNumber {}
+
+/// format_number(decimal, pattern)
+/// Patterns follow the usual #,##0.00 style, 0 is a digit that's always shown, # is a digit
+/// that's only shown if needed, a comma marks the grouping size and anything before or after
+/// the digits is copied across as is. A % in the pattern multiplies the number by 100.
+/// Invalid patterns return null.
+impl Function for FormatNumber {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        if let (Some(number), Some(pattern)) = (args[0].as_maybe_decimal(), args[1].as_maybe_text())
+        {
+            if let Some(pattern) = NumberPattern::parse(pattern) {
+                return Datum::from(pattern.format(number));
+            }
+        }
+        Datum::Null
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub(super) struct NumberPattern<'a> {
+    pub(super) prefix: &'a str,
+    pub(super) suffix: &'a str,
+    pub(super) min_integer_digits: usize,
+    pub(super) grouping_size: Option<usize>,
+    pub(super) min_fraction_digits: usize,
+    pub(super) max_fraction_digits: usize,
+    pub(super) percent: bool,
+}
+
+impl<'a> NumberPattern<'a> {
+    fn parse(pattern: &'a str) -> Option<Self> {
+        let is_pattern_char = |c: char| matches!(c, '#' | '0' | ',' | '.');
+        let start = pattern.find(is_pattern_char)?;
+        let end = pattern.rfind(is_pattern_char)? + 1;
+        let (prefix, body, suffix) = (&pattern[..start], &pattern[start..end], &pattern[end..]);
+
+        let mut parts = body.splitn(2, '.');
+        let integer_part = parts.next().unwrap();
+        let fraction_part = parts.next().unwrap_or("");
+        if fraction_part.contains(&[',', '.'][..]) {
+            return None;
+        }
+
+        let grouping_size = integer_part
+            .rfind(',')
+            .map(|idx| integer_part.len() - idx - 1)
+            .filter(|size| *size > 0);
+
+        Some(NumberPattern {
+            prefix,
+            suffix,
+            min_integer_digits: integer_part.matches('0').count(),
+            grouping_size,
+            min_fraction_digits: fraction_part.matches('0').count(),
+            max_fraction_digits: fraction_part.len(),
+            percent: prefix.contains('%') || suffix.contains('%'),
+        })
+    }
+
+    pub(super) fn format(&self, mut number: Decimal) -> String {
+        if self.percent {
+            number *= Decimal::from(100);
+        }
+        let negative = number.is_sign_negative();
+        let digits = number.abs().to_string();
+        let mut parts = digits.splitn(2, '.');
+        let mut integer_digits: Vec<u8> = parts.next().unwrap().bytes().collect();
+        let mut fraction_digits: Vec<u8> = parts.next().unwrap_or("").bytes().collect();
+
+        // Round half away from zero, working on the digits directly.
+        if fraction_digits.len() > self.max_fraction_digits {
+            let round_up = fraction_digits[self.max_fraction_digits] >= b'5';
+            fraction_digits.truncate(self.max_fraction_digits);
+            if round_up && increment(&mut fraction_digits) && increment(&mut integer_digits) {
+                integer_digits.insert(0, b'1');
+            }
+        }
+        while fraction_digits.len() > self.min_fraction_digits
+            && fraction_digits.last() == Some(&b'0')
+        {
+            fraction_digits.pop();
+        }
+        while fraction_digits.len() < self.min_fraction_digits {
+            fraction_digits.push(b'0');
+        }
+
+        while integer_digits.first() == Some(&b'0') {
+            integer_digits.remove(0);
+        }
+        while integer_digits.len() < self.min_integer_digits {
+            integer_digits.insert(0, b'0');
+        }
+
+        let mut formatted = String::new();
+        let is_zero = integer_digits
+            .iter()
+            .chain(fraction_digits.iter())
+            .all(|digit| *digit == b'0');
+        if negative && !is_zero {
+            formatted.push('-');
+        }
+        formatted.push_str(self.prefix);
+        for (idx, digit) in integer_digits.iter().enumerate() {
+            if let Some(grouping_size) = self.grouping_size {
+                let remaining = integer_digits.len() - idx;
+                if idx != 0 && remaining % grouping_size == 0 {
+                    formatted.push(',');
+                }
+            }
+            formatted.push(*digit as char);
+        }
+        if !fraction_digits.is_empty() {
+            formatted.push('.');
+            formatted.extend(fraction_digits.iter().map(|digit| *digit as char));
+        }
+        formatted.push_str(self.suffix);
+        formatted
+    }
+}
+
+/// Adds one to the ascii digits, returns true if it carried past the first digit.
+fn increment(digits: &mut [u8]) -> bool {
+    for digit in digits.iter_mut().rev() {
+        if *digit == b'9' {
+            *digit = b'0';
+        } else {
+            *digit += 1;
+            return false;
+        }
+    }
+    true
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "format_number",
+        vec![DataType::Decimal(0, 0), DataType::Text],
+        DataType::Text,
+        FunctionType::Scalar(&FormatNumber {}),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const DUMMY_SIG: FunctionSignature = FunctionSignature {
+        name: "format_number",
+        args: vec![],
+        ret: DataType::Text,
+    };
+
+    fn format_number(number: &str, pattern: &str) -> Datum<'static> {
+        FormatNumber {}
+            .execute(
+                &Session::new(1),
+                &DUMMY_SIG,
+                &[
+                    Datum::from(Decimal::from_str(number).unwrap()),
+                    Datum::from(pattern),
+                ],
+            )
+            .as_static()
+    }
+
+    #[test]
+    fn test_null() {
+        assert_eq!(
+            FormatNumber {}.execute(
+                &Session::new(1),
+                &DUMMY_SIG,
+                &[Datum::Null, Datum::from("#")]
+            ),
+            Datum::Null
+        );
+        // Patterns without any digits
+        assert_eq!(format_number("1", "abc"), Datum::Null);
+    }
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(
+            format_number("1234567.891", "#,##0.00"),
+            Datum::from("1,234,567.89")
+        );
+        assert_eq!(format_number("0.5", "#,##0.00"), Datum::from("0.50"));
+        assert_eq!(
+            format_number("-1234.5", "$#,##0.00"),
+            Datum::from("-$1,234.50")
+        );
+        assert_eq!(format_number("1.005", "0.##"), Datum::from("1.01"));
+        assert_eq!(format_number("1.1", "0.##"), Datum::from("1.1"));
+        assert_eq!(format_number("999.999", "0.00"), Datum::from("1000.00"));
+        assert_eq!(format_number("0.256", "0.0%"), Datum::from("25.6%"));
+        assert_eq!(format_number("7", "000"), Datum::from("007"));
+        assert_eq!(format_number("-0.001", "0.00"), Datum::from("0.00"));
+        assert_eq!(format_number("12345", "#,####"), Datum::from("1,2345"));
+    }
+}
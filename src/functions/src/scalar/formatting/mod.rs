@@ -0,0 +1,10 @@
+use crate::registry::Registry;
+mod format_currency;
+mod format_number;
+mod to_char;
+
+pub fn register_builtins(registry: &mut Registry) {
+    format_currency::register_builtins(registry);
+    format_number::register_builtins(registry);
+    to_char::register_builtins(registry);
+}
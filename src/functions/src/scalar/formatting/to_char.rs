@@ -0,0 +1,276 @@
+use crate::registry::Registry;
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType, Volatility};
+use data::chrono::{Datelike, Duration, FixedOffset, NaiveDateTime, Timelike};
+use data::{DataType, Datum, Locale, Session};
+use std::fmt::Write;
+
+#[derive(Debug)]
+struct ToChar {}
+
+/// to_char(date|timestamp, pattern)
+/// Uses postgres style patterns, ie 'Day DD Month YYYY HH24:MI:SS', month and day names come
+/// from the session's lc_time locale. The case of a name pattern controls the case of the
+/// output, ie MONTH, Month or month. Anything inside double quotes is copied across as is.
+/// Timestamps are shifted into the session's time_zone, dates are left alone.
+impl Function for ToChar {
+    fn execute<'a>(
+        &self,
+        session: &Session,
+        signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        let time_zone = *session.time_zone.read().unwrap();
+        let (timestamp, time_zone) = if signature.args[0] == DataType::Date {
+            let date = args[0].as_maybe_date();
+            let utc = FixedOffset::east_opt(0).unwrap();
+            (date.and_then(|date| date.and_hms_opt(0, 0, 0)), utc)
+        } else {
+            let timestamp = args[0].as_maybe_timestamp();
+            let offset = Duration::seconds(time_zone.local_minus_utc() as i64);
+            let shifted = timestamp.and_then(|timestamp| timestamp.checked_add_signed(offset));
+            (shifted, time_zone)
+        };
+
+        if let (Some(timestamp), Some(pattern)) = (timestamp, args[1].as_maybe_text()) {
+            let locale = *session.lc_time.read().unwrap();
+            Datum::from(to_char(timestamp, time_zone, pattern, locale))
+        } else {
+            Datum::Null
+        }
+    }
+
+    // The output depends on the session's time_zone and lc_time
+    fn volatility(&self) -> Volatility {
+        Volatility::Stable
+    }
+}
+
+/// Patterns for names, these are matched exactly as the case of the pattern controls the case
+/// of the output.
+const NAME_PATTERNS: [&str; 16] = [
+    "MONTH", "Month", "month", "MON", "Mon", "mon", "DAY", "Day", "day", "DY", "Dy", "dy", "AM",
+    "PM", "am", "pm",
+];
+
+/// Numeric patterns, these are matched ignoring case. Longer patterns have to come before any
+/// of their prefixes.
+const NUMERIC_PATTERNS: [&str; 13] = [
+    "HH24", "HH12", "HH", "YYYY", "YY", "MM", "MI", "MS", "DD", "D", "SS", "US", "TZ",
+];
+
+fn to_char(
+    timestamp: NaiveDateTime,
+    time_zone: FixedOffset,
+    pattern: &str,
+    locale: &Locale,
+) -> String {
+    let mut output = String::new();
+    let mut rest = pattern;
+
+    while let Some(c) = rest.chars().next() {
+        if c == '"' {
+            let end = rest[1..].find('"').map(|idx| idx + 1).unwrap_or(rest.len());
+            output.push_str(&rest[1..end]);
+            rest = &rest[(end + 1).min(rest.len())..];
+        } else if let Some(token) = find_pattern(rest) {
+            write_token(token, &timestamp, time_zone, locale, &mut output);
+            rest = &rest[token.len()..];
+        } else {
+            output.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    output
+}
+
+fn find_pattern(input: &str) -> Option<&'static str> {
+    let name = NAME_PATTERNS
+        .iter()
+        .find(|pattern| input.starts_with(*pattern));
+    let numeric = || {
+        NUMERIC_PATTERNS.iter().find(|pattern| {
+            input
+                .get(..pattern.len())
+                .map_or(false, |prefix| prefix.eq_ignore_ascii_case(pattern))
+        })
+    };
+    name.or_else(numeric).copied()
+}
+
+fn write_token(
+    token: &str,
+    timestamp: &NaiveDateTime,
+    time_zone: FixedOffset,
+    locale: &Locale,
+    output: &mut String,
+) {
+    let month_idx = timestamp.month0() as usize;
+    let day_idx = timestamp.weekday().num_days_from_monday() as usize;
+    let hour_12 = match timestamp.hour() % 12 {
+        0 => 12,
+        hour => hour,
+    };
+    let is_pm = timestamp.hour() >= 12;
+    // Writing to a string can't fail
+    let _ = match token {
+        "YYYY" => write!(output, "{:04}", timestamp.year()),
+        "YY" => write!(output, "{:02}", timestamp.year().rem_euclid(100)),
+        "MM" => write!(output, "{:02}", timestamp.month()),
+        "DD" => write!(output, "{:02}", timestamp.day()),
+        "D" => write!(output, "{}", timestamp.weekday().number_from_sunday()),
+        "HH24" => write!(output, "{:02}", timestamp.hour()),
+        "HH12" | "HH" => write!(output, "{:02}", hour_12),
+        "MI" => write!(output, "{:02}", timestamp.minute()),
+        "SS" => write!(output, "{:02}", timestamp.second()),
+        "MS" => write!(output, "{:03}", timestamp.nanosecond() / 1_000_000),
+        "US" => write!(output, "{:06}", timestamp.nanosecond() / 1_000),
+        "AM" | "PM" => output.write_str(if is_pm { "PM" } else { "AM" }),
+        "am" | "pm" => output.write_str(if is_pm { "pm" } else { "am" }),
+        "TZ" => write!(output, "{}", time_zone),
+        "MONTH" | "Month" | "month" => {
+            output.write_str(&with_case(token, locale.month_names[month_idx]))
+        }
+        "MON" | "Mon" | "mon" => {
+            output.write_str(&with_case(token, locale.abbreviated_month_names[month_idx]))
+        }
+        "DAY" | "Day" | "day" => output.write_str(&with_case(token, locale.day_names[day_idx])),
+        "DY" | "Dy" | "dy" => {
+            output.write_str(&with_case(token, locale.abbreviated_day_names[day_idx]))
+        }
+        _ => unreachable!(),
+    };
+}
+
+/// Matches the case of the name to the case of the pattern, for title case patterns we just
+/// capitalize the first letter and leave the rest as the locale has it.
+fn with_case(pattern: &str, name: &str) -> String {
+    if pattern.chars().all(char::is_uppercase) {
+        name.to_uppercase()
+    } else if pattern.chars().all(char::is_lowercase) {
+        name.to_lowercase()
+    } else {
+        let mut chars = name.chars();
+        chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect())
+            .unwrap_or_default()
+    }
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "to_char",
+        vec![DataType::Date, DataType::Text],
+        DataType::Text,
+        FunctionType::Scalar(&ToChar {}),
+    ));
+
+    registry.register_function(FunctionDefinition::new(
+        "to_char",
+        vec![DataType::Timestamp, DataType::Text],
+        DataType::Text,
+        FunctionType::Scalar(&ToChar {}),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::chrono::NaiveDate;
+
+    fn sig(datatype: DataType) -> FunctionSignature<'static> {
+        FunctionSignature {
+            name: "to_char",
+            args: vec![datatype, DataType::Text],
+            ret: DataType::Text,
+        }
+    }
+
+    #[test]
+    fn test_null() {
+        assert_eq!(
+            ToChar {}.execute(
+                &Session::new(1),
+                &sig(DataType::Date),
+                &[Datum::Null, Datum::from("YYYY")]
+            ),
+            Datum::Null
+        )
+    }
+
+    #[test]
+    fn test_to_char_date() {
+        assert_eq!(
+            ToChar {}.execute(
+                &Session::new(1),
+                &sig(DataType::Date),
+                &[
+                    Datum::from(NaiveDate::from_ymd(2020, 3, 1)),
+                    Datum::from("Day, DD Month YYYY (Dy MON mm/yy) \"DD\"")
+                ]
+            ),
+            Datum::from("Sunday, 01 March 2020 (Sun MAR 03/20) DD")
+        )
+    }
+
+    #[test]
+    fn test_to_char_timestamp() {
+        assert_eq!(
+            ToChar {}.execute(
+                &Session::new(1),
+                &sig(DataType::Timestamp),
+                &[
+                    Datum::from(NaiveDate::from_ymd(2020, 3, 1).and_hms_milli(13, 5, 9, 12)),
+                    Datum::from("YYYY-MM-DD HH24:MI:SS.MS HH12 PM D")
+                ]
+            ),
+            Datum::from("2020-03-01 13:05:09.012 01 PM 1")
+        )
+    }
+
+    #[test]
+    fn test_to_char_locale() {
+        let session = Session::new(1);
+        *session.lc_time.write().unwrap() = Locale::from_name("fr_FR").unwrap();
+        assert_eq!(
+            ToChar {}.execute(
+                &session,
+                &sig(DataType::Date),
+                &[
+                    Datum::from(NaiveDate::from_ymd(2020, 2, 3)),
+                    Datum::from("Day DD month, DAY")
+                ]
+            ),
+            Datum::from("Lundi 03 février, LUNDI")
+        )
+    }
+
+    #[test]
+    fn test_to_char_time_zone() {
+        let session = Session::new(1);
+        *session.time_zone.write().unwrap() = FixedOffset::west_opt(5 * 3600).unwrap();
+        assert_eq!(
+            ToChar {}.execute(
+                &session,
+                &sig(DataType::Timestamp),
+                &[
+                    Datum::from(NaiveDate::from_ymd(2020, 3, 1).and_hms(2, 0, 0)),
+                    Datum::from("YYYY-MM-DD HH24:MI TZ")
+                ]
+            ),
+            Datum::from("2020-02-29 21:00 -05:00")
+        );
+        // Dates don't have a time so aren't shifted
+        assert_eq!(
+            ToChar {}.execute(
+                &session,
+                &sig(DataType::Date),
+                &[
+                    Datum::from(NaiveDate::from_ymd(2020, 3, 1)),
+                    Datum::from("YYYY-MM-DD TZ")
+                ]
+            ),
+            Datum::from("2020-03-01 +00:00")
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use crate::registry::Registry;
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
+use data::json::OwnedJson;
+use data::json_schema::validate;
+use data::{DataType, Datum, Session};
+
+/// Checks a json document against a json schema, see data::json_schema for the keywords that
+/// are supported. The schema can be given as text, text that isn't json gives null.
+#[derive(Debug)]
+struct JsonMatchesSchema {}
+
+impl Function for JsonMatchesSchema {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        let doc = match args[0].as_maybe_json() {
+            Some(doc) => doc,
+            None => return Datum::Null,
+        };
+        if signature.args[1] == DataType::Text {
+            match args[1].as_maybe_text().and_then(OwnedJson::parse) {
+                Some(schema) => Datum::from(validate(doc, schema.as_json()).is_ok()),
+                None => Datum::Null,
+            }
+        } else if let Some(schema) = args[1].as_maybe_json() {
+            Datum::from(validate(doc, schema).is_ok())
+        } else {
+            Datum::Null
+        }
+    }
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "json_matches_schema",
+        vec![DataType::Json, DataType::Json],
+        DataType::Boolean,
+        FunctionType::Scalar(&JsonMatchesSchema {}),
+    ));
+    registry.register_function(FunctionDefinition::new(
+        "json_matches_schema",
+        vec![DataType::Json, DataType::Text],
+        DataType::Boolean,
+        FunctionType::Scalar(&JsonMatchesSchema {}),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(doc: &str, schema: Datum, schema_type: DataType) -> Datum<'static> {
+        let signature = FunctionSignature {
+            name: "json_matches_schema",
+            args: vec![DataType::Json, schema_type],
+            ret: DataType::Boolean,
+        };
+        let doc = Datum::from(OwnedJson::parse(doc).unwrap());
+        JsonMatchesSchema {}
+            .execute(&Session::new(1), &signature, &[doc, schema])
+            .as_static()
+    }
+
+    #[test]
+    fn test_json_matches_schema() {
+        let schema = r#"{"type": "object", "required": ["id"]}"#;
+        assert_eq!(
+            matches(
+                r#"{"id": 1}"#,
+                Datum::from(OwnedJson::parse(schema).unwrap()),
+                DataType::Json
+            ),
+            Datum::from(true)
+        );
+        assert_eq!(
+            matches(r#"{"id": 1}"#, Datum::from(schema), DataType::Text),
+            Datum::from(true)
+        );
+        assert_eq!(
+            matches(r#"{"a": 1}"#, Datum::from(schema), DataType::Text),
+            Datum::from(false)
+        );
+        assert_eq!(
+            matches(r#"{"id": 1}"#, Datum::from("{"), DataType::Text),
+            Datum::Null
+        );
+        assert_eq!(
+            matches(r#"{"id": 1}"#, Datum::Null, DataType::Json),
+            Datum::Null
+        );
+    }
+}
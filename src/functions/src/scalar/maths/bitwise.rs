@@ -0,0 +1,221 @@
+use crate::registry::Registry;
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
+use data::{DataType, Datum, Session};
+use num_traits::PrimInt;
+use std::mem::size_of;
+
+#[derive(Debug, Copy, Clone)]
+enum BitwiseOp {
+    And,
+    Or,
+    Xor,
+    ShiftLeft,
+    ShiftRight,
+}
+
+/// The binary bitwise operators, &, |, ^, << and >>.
+/// Shifting by a negative amount or by the width of the type or more shifts everything out, so
+/// gives 0, or -1 when right shifting a negative number.
+#[derive(Debug)]
+struct Bitwise {
+    op: BitwiseOp,
+}
+
+impl Function for Bitwise {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        if signature.ret == DataType::Integer {
+            if let (Some(a), Some(b)) = (args[0].as_maybe_integer(), args[1].as_maybe_integer()) {
+                return Datum::from(apply(self.op, a, b));
+            }
+        } else if let (Some(a), Some(b)) = (args[0].as_maybe_bigint(), args[1].as_maybe_bigint()) {
+            return Datum::from(apply(self.op, a, b));
+        }
+        Datum::Null
+    }
+}
+
+fn apply<T: PrimInt>(op: BitwiseOp, a: T, b: T) -> T {
+    let bits = size_of::<T>() * 8;
+    let shift = b.to_usize().filter(|shift| *shift < bits);
+    let sign_fill = if a < T::zero() { !T::zero() } else { T::zero() };
+    match op {
+        BitwiseOp::And => a & b,
+        BitwiseOp::Or => a | b,
+        BitwiseOp::Xor => a ^ b,
+        BitwiseOp::ShiftLeft => shift.map(|shift| a << shift).unwrap_or_else(T::zero),
+        BitwiseOp::ShiftRight => shift.map(|shift| a >> shift).unwrap_or(sign_fill),
+    }
+}
+
+/// The unary bitwise inversion operator, ~
+#[derive(Debug)]
+struct BitwiseNot {}
+
+impl Function for BitwiseNot {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        if signature.ret == DataType::Integer {
+            args[0]
+                .as_maybe_integer()
+                .map(|a| Datum::from(!a))
+                .unwrap_or(Datum::Null)
+        } else {
+            args[0]
+                .as_maybe_bigint()
+                .map(|a| Datum::from(!a))
+                .unwrap_or(Datum::Null)
+        }
+    }
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    let operators: [(&str, &'static Bitwise); 5] = [
+        ("&", &Bitwise { op: BitwiseOp::And }),
+        ("|", &Bitwise { op: BitwiseOp::Or }),
+        ("^", &Bitwise { op: BitwiseOp::Xor }),
+        (
+            "<<",
+            &Bitwise {
+                op: BitwiseOp::ShiftLeft,
+            },
+        ),
+        (
+            ">>",
+            &Bitwise {
+                op: BitwiseOp::ShiftRight,
+            },
+        ),
+    ];
+    for datatype in &[DataType::Integer, DataType::BigInt] {
+        for (name, function) in &operators {
+            registry.register_function(FunctionDefinition::new(
+                name,
+                vec![*datatype, *datatype],
+                *datatype,
+                FunctionType::Scalar(*function),
+            ));
+        }
+
+        registry.register_function(FunctionDefinition::new(
+            "~",
+            vec![*datatype],
+            *datatype,
+            FunctionType::Scalar(&BitwiseNot {}),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sig(datatype: DataType) -> FunctionSignature<'static> {
+        FunctionSignature {
+            name: "&",
+            args: vec![datatype, datatype],
+            ret: datatype,
+        }
+    }
+
+    fn bitwise(op: BitwiseOp, a: Datum<'static>, b: Datum<'static>) -> Datum<'static> {
+        let datatype = if a.as_maybe_integer().is_some() {
+            DataType::Integer
+        } else {
+            DataType::BigInt
+        };
+        Bitwise { op }
+            .execute(&Session::new(1), &sig(datatype), &[a, b])
+            .as_static()
+    }
+
+    #[test]
+    fn test_null() {
+        assert_eq!(
+            Bitwise { op: BitwiseOp::And }.execute(
+                &Session::new(1),
+                &sig(DataType::Integer),
+                &[Datum::Null, Datum::from(1)]
+            ),
+            Datum::Null
+        );
+        assert_eq!(
+            BitwiseNot {}.execute(&Session::new(1), &sig(DataType::BigInt), &[Datum::Null]),
+            Datum::Null
+        );
+    }
+
+    #[test]
+    fn test_bitwise() {
+        let (a, b) = (Datum::from(12), Datum::from(10));
+        assert_eq!(
+            bitwise(BitwiseOp::And, a.clone(), b.clone()),
+            Datum::from(8)
+        );
+        assert_eq!(
+            bitwise(BitwiseOp::Or, a.clone(), b.clone()),
+            Datum::from(14)
+        );
+        assert_eq!(bitwise(BitwiseOp::Xor, a, b), Datum::from(6));
+
+        let (a, b) = (Datum::from(12_i64), Datum::from(10_i64));
+        assert_eq!(bitwise(BitwiseOp::And, a, b), Datum::from(8_i64));
+    }
+
+    #[test]
+    fn test_shifts() {
+        assert_eq!(
+            bitwise(BitwiseOp::ShiftLeft, Datum::from(1), Datum::from(4)),
+            Datum::from(16)
+        );
+        assert_eq!(
+            bitwise(
+                BitwiseOp::ShiftLeft,
+                Datum::from(1_i64),
+                Datum::from(40_i64)
+            ),
+            Datum::from(1_i64 << 40)
+        );
+        assert_eq!(
+            bitwise(BitwiseOp::ShiftRight, Datum::from(-16), Datum::from(2)),
+            Datum::from(-4)
+        );
+        // Shifting everything out
+        assert_eq!(
+            bitwise(BitwiseOp::ShiftLeft, Datum::from(1), Datum::from(32)),
+            Datum::from(0)
+        );
+        assert_eq!(
+            bitwise(BitwiseOp::ShiftLeft, Datum::from(1), Datum::from(-1)),
+            Datum::from(0)
+        );
+        assert_eq!(
+            bitwise(BitwiseOp::ShiftRight, Datum::from(-5), Datum::from(99)),
+            Datum::from(-1)
+        );
+    }
+
+    #[test]
+    fn test_bitwise_not() {
+        assert_eq!(
+            BitwiseNot {}.execute(&Session::new(1), &sig(DataType::Integer), &[Datum::from(0)]),
+            Datum::from(-1)
+        );
+        assert_eq!(
+            BitwiseNot {}.execute(
+                &Session::new(1),
+                &sig(DataType::BigInt),
+                &[Datum::from(5_i64)]
+            ),
+            Datum::from(-6_i64)
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use crate::registry::Registry;
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
+use data::rust_decimal::Decimal;
+use data::{DataType, Datum, Session};
+use num_traits::ToPrimitive;
+
+#[derive(Debug)]
+struct ToCents {}
+
+/// to_cents(decimal)
+/// The amount as a whole number of cents (or any other hundredth of a unit), rounded with
+/// banker's rounding. Amounts too large for a bigint give null.
+impl Function for ToCents {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        args[0]
+            .as_maybe_decimal()
+            .and_then(|d| d.checked_mul(Decimal::from(100)))
+            .and_then(|cents| cents.round_dp(0).to_i64())
+            .map(Datum::from)
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug)]
+struct FromCents {}
+
+/// from_cents(bigint)
+/// The inverse of to_cents, turns a number of cents back into an amount with two decimal places
+impl Function for FromCents {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        if let Some(cents) = args[0].as_maybe_bigint() {
+            Datum::from(Decimal::new(cents, 2))
+        } else {
+            Datum::Null
+        }
+    }
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "to_cents",
+        vec![DataType::Decimal(0, 0)],
+        DataType::BigInt,
+        FunctionType::Scalar(&ToCents {}),
+    ));
+
+    // A bigint has up to 19 digits
+    registry.register_function(FunctionDefinition::new(
+        "from_cents",
+        vec![DataType::BigInt],
+        DataType::Decimal(19, 2),
+        FunctionType::Scalar(&FromCents {}),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const DUMMY_SIG: FunctionSignature = FunctionSignature {
+        name: "to_cents",
+        args: vec![],
+        ret: DataType::BigInt,
+    };
+
+    fn decimal(d: &str) -> Datum<'static> {
+        Datum::from(Decimal::from_str(d).unwrap())
+    }
+
+    fn to_cents(d: &str) -> Datum<'static> {
+        ToCents {}
+            .execute(&Session::new(1), &DUMMY_SIG, &[decimal(d)])
+            .as_static()
+    }
+
+    #[test]
+    fn test_null() {
+        assert_eq!(
+            ToCents {}.execute(&Session::new(1), &DUMMY_SIG, &[Datum::Null]),
+            Datum::Null
+        );
+        assert_eq!(
+            FromCents {}.execute(&Session::new(1), &DUMMY_SIG, &[Datum::Null]),
+            Datum::Null
+        );
+        assert_eq!(to_cents("100000000000000000000"), Datum::Null);
+    }
+
+    #[test]
+    fn test_cents() {
+        assert_eq!(to_cents("12.34"), Datum::from(1234_i64));
+        assert_eq!(to_cents("0.125"), Datum::from(12_i64));
+        assert_eq!(to_cents("-0.135"), Datum::from(-14_i64));
+        assert_eq!(
+            FromCents {}.execute(&Session::new(1), &DUMMY_SIG, &[Datum::from(-1234_i64)]),
+            decimal("-12.34")
+        );
+    }
+}
@@ -1,13 +1,176 @@
 use crate::registry::Registry;
+use crate::FunctionSignature;
+use data::rust_decimal::Decimal;
+use data::{DataType, Datum, DecimalOverflow, Session, Warning, WARN_DATA_OUT_OF_RANGE};
 
 mod add;
+mod bitwise;
+mod cents;
 mod divide;
 mod multiply;
+mod random;
+mod round_bankers;
 mod subtract;
 
 pub fn register_builtins(registry: &mut Registry) {
     add::register_builtins(registry);
+    bitwise::register_builtins(registry);
+    cents::register_builtins(registry);
     divide::register_builtins(registry);
     multiply::register_builtins(registry);
+    random::register_builtins(registry);
+    round_bankers::register_builtins(registry);
     subtract::register_builtins(registry);
 }
+
+/// Fits the result of a decimal operation to the signature's return type. Digits past the type's
+/// scale are rounded off, values with too many digits before the decimal point are dealt with
+/// according to the session's decimal_overflow. A None value is one that overflowed the decimal
+/// itself, negative being the sign it would have had.
+pub(crate) fn fit_decimal(
+    session: &Session,
+    signature: &FunctionSignature,
+    value: Option<Decimal>,
+    negative: bool,
+) -> Datum<'static> {
+    let (p, s) = match (signature.ret, value) {
+        (DataType::Decimal(p, s), _) => (p as u32, s as u32),
+        (_, Some(d)) => return Datum::from(d),
+        (_, None) => return Datum::Null,
+    };
+    let mut d = match value {
+        Some(d) => d,
+        None => return overflow(session, signature.ret, None, negative),
+    };
+    if d.scale() > s {
+        d.rescale(s);
+    }
+    if d.abs() <= max_decimal(p, s) {
+        Datum::from(d)
+    } else {
+        overflow(session, signature.ret, Some(d), d.is_sign_negative())
+    }
+}
+
+fn overflow(
+    session: &Session,
+    datatype: DataType,
+    value: Option<Decimal>,
+    negative: bool,
+) -> Datum<'static> {
+    let (p, s) = match datatype {
+        DataType::Decimal(p, s) => (p as u32, s as u32),
+        _ => unreachable!(),
+    };
+    let out_of_range = match value {
+        Some(d) => format!("Out of range value {} for {}", d, datatype),
+        None => format!("Out of range value for {}", datatype),
+    };
+    let policy = *session.decimal_overflow.read().unwrap();
+    let fitted = match (policy, value) {
+        (DecimalOverflow::Error, _) => {
+            session.cast_failed(out_of_range);
+            return Datum::Null;
+        }
+        (DecimalOverflow::Saturate, _) => {
+            let max = max_decimal(p, s);
+            Some(if negative { -max } else { max })
+        }
+        // The fewer digits after the decimal point, the more room before it
+        (DecimalOverflow::Rescale, Some(d)) => (0..s).rev().find_map(|scale| {
+            let mut rescaled = d;
+            rescaled.rescale(scale);
+            if rescaled.abs() <= max_decimal(p, scale) {
+                Some(rescaled)
+            } else {
+                None
+            }
+        }),
+        (DecimalOverflow::Rescale, None) => None,
+    };
+    match fitted {
+        Some(fitted) => {
+            session.add_warning(Warning::new(
+                WARN_DATA_OUT_OF_RANGE,
+                format!("{}, changed to {}", out_of_range, fitted),
+            ));
+            Datum::from(fitted)
+        }
+        None => {
+            session.add_warning(Warning::new(
+                WARN_DATA_OUT_OF_RANGE,
+                format!("{}, changed to NULL", out_of_range),
+            ));
+            Datum::Null
+        }
+    }
+}
+
+/// The biggest value a DECIMAL(p, s) can hold, ie 999.99 for DECIMAL(5, 2)
+fn max_decimal(p: u32, s: u32) -> Decimal {
+    let mantissa = 10_u128.pow(p) - 1;
+    Decimal::from_parts(
+        mantissa as u32,
+        (mantissa >> 32) as u32,
+        (mantissa >> 64) as u32,
+        false,
+        s,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn fit(session: &Session, value: &str, ret: DataType) -> Datum<'static> {
+        let signature = FunctionSignature {
+            name: "*",
+            args: vec![],
+            ret,
+        };
+        fit_decimal(session, &signature, Decimal::from_str(value).ok(), false)
+    }
+
+    fn decimal(value: &str) -> Datum<'static> {
+        Datum::from(Decimal::from_str(value).unwrap())
+    }
+
+    #[test]
+    fn test_fit_decimal() {
+        let session = Session::new(1);
+        assert_eq!(max_decimal(5, 2), Decimal::new(99999, 2));
+        assert_eq!(
+            fit(&session, "123.456", DataType::Decimal(5, 2)),
+            decimal("123.46")
+        );
+        assert_eq!(session.take_cast_error(), None);
+
+        assert_eq!(
+            fit(&session, "-1234.5", DataType::Decimal(5, 2)),
+            Datum::Null
+        );
+        assert_eq!(
+            session.take_cast_error(),
+            Some("Out of range value -1234.5 for DECIMAL(5,2)".to_string())
+        );
+
+        *session.decimal_overflow.write().unwrap() = DecimalOverflow::Saturate;
+        assert_eq!(
+            fit(&session, "-1234.5", DataType::Decimal(5, 2)),
+            decimal("-999.99")
+        );
+
+        *session.decimal_overflow.write().unwrap() = DecimalOverflow::Rescale;
+        assert_eq!(
+            fit(&session, "-1234.5", DataType::Decimal(5, 2)),
+            decimal("-1234.5")
+        );
+        assert_eq!(
+            fit(&session, "123456", DataType::Decimal(5, 2)),
+            Datum::Null
+        );
+        assert_eq!(session.warning_count(), 3);
+        assert_eq!(session.take_cast_error(), None);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::registry::Registry;
+use crate::scalar::maths::fit_decimal;
 use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
 use data::{DataType, Datum, Session, DECIMAL_MAX_PRECISION, DECIMAL_MAX_SCALE};
 use std::cmp::min;
@@ -45,12 +46,17 @@ struct MultiplyDecimal {}
 impl Function for MultiplyDecimal {
     fn execute<'a>(
         &self,
-        _session: &Session,
-        _signature: &FunctionSignature,
+        session: &Session,
+        signature: &FunctionSignature,
         args: &'a [Datum<'a>],
     ) -> Datum<'a> {
         if let (Some(a), Some(b)) = (args[0].as_maybe_decimal(), args[1].as_maybe_decimal()) {
-            Datum::from(a * b)
+            fit_decimal(
+                session,
+                signature,
+                a.checked_mul(b),
+                a.is_sign_negative() != b.is_sign_negative(),
+            )
         } else {
             Datum::Null
         }
@@ -149,4 +155,23 @@ mod tests {
             Datum::from(Decimal::new(6000, 3))
         )
     }
+
+    #[test]
+    fn test_multiply_decimal_overflow() {
+        let session = Session::new(1);
+        let signature = FunctionSignature {
+            name: "*",
+            args: vec![],
+            ret: DataType::Decimal(DECIMAL_MAX_PRECISION, 0),
+        };
+        let big = Datum::from(Decimal::new(i64::MAX, 0));
+        assert_eq!(
+            MultiplyDecimal {}.execute(&session, &signature, &[big.ref_clone(), big.ref_clone()]),
+            Datum::Null
+        );
+        assert_eq!(
+            session.take_cast_error(),
+            Some("Out of range value for DECIMAL(28,0)".to_string())
+        );
+    }
 }
@@ -0,0 +1,68 @@
+use crate::registry::Registry;
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType, Volatility};
+use data::rust_decimal::Decimal;
+use data::{DataType, Datum, Session, DECIMAL_MAX_PRECISION, DECIMAL_MAX_SCALE};
+
+#[derive(Debug)]
+struct Random {}
+
+/// random()
+/// A random number in [0, 1), the sequence is reset at the start of each statement so with the
+/// seed session variable set re-running a statement gives the same numbers.
+impl Function for Random {
+    fn execute<'a>(
+        &self,
+        session: &Session,
+        _signature: &FunctionSignature,
+        _args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        let scale = 10_u64.pow(DECIMAL_MAX_SCALE as u32);
+        let digits = session.next_random() % scale;
+        Datum::from(Decimal::new(digits as i64, DECIMAL_MAX_SCALE as u32))
+    }
+
+    fn volatility(&self) -> Volatility {
+        Volatility::Volatile
+    }
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "random",
+        vec![],
+        DataType::Decimal(DECIMAL_MAX_PRECISION, DECIMAL_MAX_SCALE),
+        FunctionType::Scalar(&Random {}),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUMMY_SIG: FunctionSignature = FunctionSignature {
+        name: "random",
+        args: vec![],
+        ret: DataType::Decimal(DECIMAL_MAX_PRECISION, DECIMAL_MAX_SCALE),
+    };
+
+    #[test]
+    fn test_random() {
+        let session = Session::new(1);
+        *session.seed.write().unwrap() = Some(7);
+        session.start_statement();
+        let first: Vec<_> = (0..10)
+            .map(|_| Random {}.execute(&session, &DUMMY_SIG, &[]).as_static())
+            .collect();
+        for datum in &first {
+            let value = datum.as_maybe_decimal().unwrap();
+            assert!(value >= Decimal::from(0) && value < Decimal::from(1));
+        }
+        assert_ne!(first[0], first[1]);
+
+        session.start_statement();
+        let second: Vec<_> = (0..10)
+            .map(|_| Random {}.execute(&session, &DUMMY_SIG, &[]).as_static())
+            .collect();
+        assert_eq!(first, second);
+    }
+}
@@ -0,0 +1,107 @@
+use crate::registry::Registry;
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
+use data::{DataType, Datum, Session, DECIMAL_MAX_PRECISION};
+use std::cmp::min;
+
+#[derive(Debug)]
+struct RoundBankers {}
+
+/// round_bankers(decimal [, scale])
+/// Rounds to the scale (0 if not given) with halves going to the nearest even digit, ie 2.5 -> 2
+/// and 3.5 -> 4, so rounding lots of amounts doesn't bias their total. Negative scales give null.
+impl Function for RoundBankers {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        let scale = match args.get(1) {
+            Some(scale) => scale.as_maybe_integer(),
+            None => Some(0),
+        };
+        if let (Some(d), Some(scale)) = (args[0].as_maybe_decimal(), scale) {
+            if scale >= 0 {
+                return Datum::from(d.round_dp(scale as u32));
+            }
+        }
+        Datum::Null
+    }
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    // Rounding can carry into another integer digit
+    registry.register_function(FunctionDefinition::new_with_type_resolver(
+        "round_bankers",
+        vec![DataType::Decimal(0, 0)],
+        |args| match args[0] {
+            DataType::Decimal(p, s) => DataType::Decimal(min(p - s + 1, DECIMAL_MAX_PRECISION), 0),
+            _ => DataType::Decimal(DECIMAL_MAX_PRECISION, 0),
+        },
+        FunctionType::Scalar(&RoundBankers {}),
+    ));
+
+    // The scale's only known at runtime so we keep the decimal's scale
+    registry.register_function(FunctionDefinition::new_with_type_resolver(
+        "round_bankers",
+        vec![DataType::Decimal(0, 0), DataType::Integer],
+        |args| match args[0] {
+            DataType::Decimal(p, s) => DataType::Decimal(min(p + 1, DECIMAL_MAX_PRECISION), s),
+            _ => DataType::Decimal(DECIMAL_MAX_PRECISION, 0),
+        },
+        FunctionType::Scalar(&RoundBankers {}),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::rust_decimal::Decimal;
+    use std::str::FromStr;
+
+    const DUMMY_SIG: FunctionSignature = FunctionSignature {
+        name: "round_bankers",
+        args: vec![],
+        ret: DataType::Decimal(10, 2),
+    };
+
+    fn round_bankers(d: &str, scale: i32) -> Datum<'static> {
+        RoundBankers {}
+            .execute(
+                &Session::new(1),
+                &DUMMY_SIG,
+                &[
+                    Datum::from(Decimal::from_str(d).unwrap()),
+                    Datum::from(scale),
+                ],
+            )
+            .as_static()
+    }
+
+    fn decimal(d: &str) -> Datum<'static> {
+        Datum::from(Decimal::from_str(d).unwrap())
+    }
+
+    #[test]
+    fn test_null() {
+        assert_eq!(
+            RoundBankers {}.execute(&Session::new(1), &DUMMY_SIG, &[Datum::Null]),
+            Datum::Null
+        );
+        assert_eq!(round_bankers("1.5", -1), Datum::Null);
+    }
+
+    #[test]
+    fn test_round_bankers() {
+        assert_eq!(
+            RoundBankers {}.execute(&Session::new(1), &DUMMY_SIG, &[decimal("2.5")]),
+            decimal("2")
+        );
+        assert_eq!(round_bankers("3.5", 0), decimal("4"));
+        assert_eq!(round_bankers("-2.5", 0), decimal("-2"));
+        assert_eq!(round_bankers("1.005", 2), decimal("1.00"));
+        assert_eq!(round_bankers("1.015", 2), decimal("1.02"));
+        assert_eq!(round_bankers("1.0151", 2), decimal("1.02"));
+        assert_eq!(round_bankers("1.1", 4), decimal("1.1"));
+    }
+}
@@ -1,4 +1,5 @@
 use crate::registry::Registry;
+use crate::scalar::maths::fit_decimal;
 use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
 use data::{DataType, Datum, Session, DECIMAL_MAX_PRECISION};
 use std::cmp::{max, min};
@@ -45,12 +46,12 @@ struct SubtractDecimal {}
 impl Function for SubtractDecimal {
     fn execute<'a>(
         &self,
-        _session: &Session,
-        _signature: &FunctionSignature,
+        session: &Session,
+        signature: &FunctionSignature,
         args: &'a [Datum<'a>],
     ) -> Datum<'a> {
         if let (Some(a), Some(b)) = (args[0].as_maybe_decimal(), args[1].as_maybe_decimal()) {
-            Datum::from(a - b)
+            fit_decimal(session, signature, a.checked_sub(b), a.is_sign_negative())
         } else {
             Datum::Null
         }
@@ -2,17 +2,23 @@ use crate::registry::Registry;
 mod bool;
 mod casts;
 mod date;
+mod formatting;
 mod json;
 mod maths;
 mod misc;
+mod network;
 mod session;
+mod strings;
 
 pub fn register_builtins(registry: &mut Registry) {
     bool::register_builtins(registry);
     casts::register_builtins(registry);
     date::register_builtins(registry);
+    formatting::register_builtins(registry);
     json::register_builtins(registry);
     maths::register_builtins(registry);
     misc::register_builtins(registry);
+    network::register_builtins(registry);
     session::register_builtins(registry);
+    strings::register_builtins(registry);
 }
@@ -0,0 +1,164 @@
+use crate::registry::Registry;
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
+use data::{DataType, Datum, Session};
+use std::net::{IpAddr, Ipv4Addr};
+use std::str::FromStr;
+
+#[derive(Debug)]
+struct Ipv4ToInt {}
+
+/// ipv4_to_int(text)
+/// Converts a dotted quad ipv4 address into its numeric value, ie 10.0.0.1 -> 167772161.
+/// Returns null for anything that isn't a valid ipv4 address.
+impl Function for Ipv4ToInt {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        args[0]
+            .as_maybe_text()
+            .and_then(|s| Ipv4Addr::from_str(s).ok())
+            .map(|ip| Datum::from(u32::from(ip) as i64))
+            .unwrap_or(Datum::Null)
+    }
+}
+
+#[derive(Debug)]
+struct IsInCidr {}
+
+/// is_in_cidr(ip, cidr)
+/// Returns true if the ip address falls within the cidr block, ie 10.1.2.3 is in 10.0.0.0/8.
+/// Works for both ipv4 and ipv6, an ipv4 address is never in an ipv6 block and vice versa.
+/// Returns null if either the address or the block is invalid.
+impl Function for IsInCidr {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        if let (Some(ip), Some(cidr)) = (args[0].as_maybe_text(), args[1].as_maybe_text()) {
+            if let (Ok(ip), Some((network, prefix_len))) = (IpAddr::from_str(ip), parse_cidr(cidr))
+            {
+                return Datum::from(is_in_cidr(ip, network, prefix_len));
+            }
+        }
+        Datum::Null
+    }
+}
+
+/// Parses a cidr block, ie 192.168.0.0/16, a missing prefix length means a single address.
+fn parse_cidr(cidr: &str) -> Option<(IpAddr, u32)> {
+    let mut parts = cidr.splitn(2, '/');
+    let network = IpAddr::from_str(parts.next().unwrap()).ok()?;
+    let max_len = if network.is_ipv4() { 32 } else { 128 };
+    let prefix_len = match parts.next() {
+        Some(len) => u32::from_str(len).ok().filter(|len| *len <= max_len)?,
+        None => max_len,
+    };
+    Some((network, prefix_len))
+}
+
+fn is_in_cidr(ip: IpAddr, network: IpAddr, prefix_len: u32) -> bool {
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix_len).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix_len).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "ipv4_to_int",
+        vec![DataType::Text],
+        DataType::BigInt,
+        FunctionType::Scalar(&Ipv4ToInt {}),
+    ));
+
+    registry.register_function(FunctionDefinition::new(
+        "is_in_cidr",
+        vec![DataType::Text, DataType::Text],
+        DataType::Boolean,
+        FunctionType::Scalar(&IsInCidr {}),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUMMY_SIG: FunctionSignature = FunctionSignature {
+        name: "is_in_cidr",
+        args: vec![],
+        ret: DataType::Boolean,
+    };
+
+    fn is_in_cidr(ip: &str, cidr: &str) -> Datum<'static> {
+        IsInCidr {}
+            .execute(
+                &Session::new(1),
+                &DUMMY_SIG,
+                &[Datum::from(ip), Datum::from(cidr)],
+            )
+            .as_static()
+    }
+
+    #[test]
+    fn test_null() {
+        assert_eq!(
+            Ipv4ToInt {}.execute(&Session::new(1), &DUMMY_SIG, &[Datum::Null]),
+            Datum::Null
+        );
+        assert_eq!(
+            IsInCidr {}.execute(
+                &Session::new(1),
+                &DUMMY_SIG,
+                &[Datum::Null, Datum::from("10.0.0.0/8")]
+            ),
+            Datum::Null
+        );
+    }
+
+    #[test]
+    fn test_ipv4_to_int() {
+        let ipv4_to_int = |ip: &str| {
+            Ipv4ToInt {}
+                .execute(&Session::new(1), &DUMMY_SIG, &[Datum::from(ip)])
+                .as_static()
+        };
+        assert_eq!(ipv4_to_int("10.0.0.1"), Datum::from(167_772_161_i64));
+        assert_eq!(
+            ipv4_to_int("255.255.255.255"),
+            Datum::from(4_294_967_295_i64)
+        );
+        assert_eq!(ipv4_to_int("256.0.0.1"), Datum::Null);
+        assert_eq!(ipv4_to_int("::1"), Datum::Null);
+    }
+
+    #[test]
+    fn test_is_in_cidr() {
+        assert_eq!(is_in_cidr("10.1.2.3", "10.0.0.0/8"), Datum::from(true));
+        assert_eq!(is_in_cidr("11.1.2.3", "10.0.0.0/8"), Datum::from(false));
+        assert_eq!(is_in_cidr("192.168.1.1", "0.0.0.0/0"), Datum::from(true));
+        assert_eq!(is_in_cidr("192.168.1.1", "192.168.1.1"), Datum::from(true));
+        assert_eq!(
+            is_in_cidr("2001:db8::1", "2001:db8::/32"),
+            Datum::from(true)
+        );
+        assert_eq!(
+            is_in_cidr("2001:db9::1", "2001:db8::/32"),
+            Datum::from(false)
+        );
+        assert_eq!(is_in_cidr("10.1.2.3", "2001:db8::/32"), Datum::from(false));
+        assert_eq!(is_in_cidr("10.1.2.3", "10.0.0.0/33"), Datum::Null);
+        assert_eq!(is_in_cidr("nonsense", "10.0.0.0/8"), Datum::Null);
+    }
+}
@@ -0,0 +1,10 @@
+use crate::registry::Registry;
+mod inet;
+mod url;
+mod url_encoding;
+
+pub fn register_builtins(registry: &mut Registry) {
+    inet::register_builtins(registry);
+    url::register_builtins(registry);
+    url_encoding::register_builtins(registry);
+}
@@ -0,0 +1,233 @@
+use super::url_encoding::url_decode;
+use crate::registry::Registry;
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
+use data::{DataType, Datum, Session};
+
+#[derive(Debug)]
+enum UrlPart {
+    Host,
+    Path,
+    Query,
+}
+
+#[derive(Debug)]
+struct UrlExtract {
+    part: UrlPart,
+}
+
+/// url_extract_host(url), url_extract_path(url), url_extract_query(url)
+/// Pulls a single part out of a url. The host has any user info and port removed, the query is
+/// returned without the leading ?. Urls without a scheme, ie /index.html?a=b, are treated as
+/// just a path and query. Missing parts give an empty string.
+impl Function for UrlExtract {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        if let Some(url) = args[0].as_maybe_text() {
+            let url = Url::parse(url);
+            Datum::from(match self.part {
+                UrlPart::Host => url.host,
+                UrlPart::Path => url.path,
+                UrlPart::Query => url.query,
+            })
+        } else {
+            Datum::Null
+        }
+    }
+}
+
+#[derive(Debug)]
+struct UrlExtractParam {}
+
+/// url_extract_param(url, name)
+/// Returns the decoded value of the first query parameter with the given name, or null if the
+/// parameter isn't there. Parameters without a value, ie ?a&b=1, give an empty string.
+impl Function for UrlExtractParam {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        if let (Some(url), Some(name)) = (args[0].as_maybe_text(), args[1].as_maybe_text()) {
+            Url::parse(url)
+                .query
+                .split('&')
+                .map(|param| {
+                    let mut parts = param.splitn(2, '=');
+                    (parts.next().unwrap(), parts.next().unwrap_or(""))
+                })
+                .find(|(key, _)| url_decode(key).as_deref() == Some(name))
+                .and_then(|(_, value)| url_decode(value))
+                .map(Datum::from)
+                .unwrap_or(Datum::Null)
+        } else {
+            Datum::Null
+        }
+    }
+}
+
+/// The parts of a url we know how to extract, all borrowed from the original string.
+#[derive(Debug, PartialEq)]
+struct Url<'a> {
+    host: &'a str,
+    path: &'a str,
+    query: &'a str,
+}
+
+impl<'a> Url<'a> {
+    fn parse(url: &'a str) -> Self {
+        // Drop the fragment, it's never sent to the server anyway
+        let url = url.split('#').next().unwrap();
+        let (url, query) = match url.find('?') {
+            Some(idx) => (&url[..idx], &url[idx + 1..]),
+            None => (url, ""),
+        };
+        let (host, path) = match url.find("://") {
+            Some(idx) => {
+                let rest = &url[idx + 3..];
+                let path_start = rest.find('/').unwrap_or(rest.len());
+                (host_of(&rest[..path_start]), &rest[path_start..])
+            }
+            None => ("", url),
+        };
+        Url { host, path, query }
+    }
+}
+
+/// Strips the user info and port from an authority, ie user:pass@host:80 -> host
+fn host_of(authority: &str) -> &str {
+    let host = authority.rsplit('@').next().unwrap();
+    if host.starts_with('[') {
+        // Ipv6 literal, the port comes after the closing bracket
+        host.find(']').map(|idx| &host[..=idx]).unwrap_or(host)
+    } else {
+        host.split(':').next().unwrap()
+    }
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "url_extract_host",
+        vec![DataType::Text],
+        DataType::Text,
+        FunctionType::Scalar(&UrlExtract {
+            part: UrlPart::Host,
+        }),
+    ));
+
+    registry.register_function(FunctionDefinition::new(
+        "url_extract_path",
+        vec![DataType::Text],
+        DataType::Text,
+        FunctionType::Scalar(&UrlExtract {
+            part: UrlPart::Path,
+        }),
+    ));
+
+    registry.register_function(FunctionDefinition::new(
+        "url_extract_query",
+        vec![DataType::Text],
+        DataType::Text,
+        FunctionType::Scalar(&UrlExtract {
+            part: UrlPart::Query,
+        }),
+    ));
+
+    registry.register_function(FunctionDefinition::new(
+        "url_extract_param",
+        vec![DataType::Text, DataType::Text],
+        DataType::Text,
+        FunctionType::Scalar(&UrlExtractParam {}),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUMMY_SIG: FunctionSignature = FunctionSignature {
+        name: "url_extract_host",
+        args: vec![],
+        ret: DataType::Text,
+    };
+
+    #[test]
+    fn test_null() {
+        assert_eq!(
+            UrlExtract {
+                part: UrlPart::Host
+            }
+            .execute(&Session::new(1), &DUMMY_SIG, &[Datum::Null]),
+            Datum::Null
+        );
+        assert_eq!(
+            UrlExtractParam {}.execute(
+                &Session::new(1),
+                &DUMMY_SIG,
+                &[Datum::from("/?a=1"), Datum::Null]
+            ),
+            Datum::Null
+        );
+    }
+
+    #[test]
+    fn test_url_parse() {
+        assert_eq!(
+            Url::parse("https://user:pw@www.example.com:8080/a/b.html?x=1&y=2#top"),
+            Url {
+                host: "www.example.com",
+                path: "/a/b.html",
+                query: "x=1&y=2"
+            }
+        );
+        assert_eq!(
+            Url::parse("http://example.com"),
+            Url {
+                host: "example.com",
+                path: "",
+                query: ""
+            }
+        );
+        assert_eq!(
+            Url::parse("http://[::1]:80/?q"),
+            Url {
+                host: "[::1]",
+                path: "/",
+                query: "q"
+            }
+        );
+        assert_eq!(
+            Url::parse("/index.html?a=b"),
+            Url {
+                host: "",
+                path: "/index.html",
+                query: "a=b"
+            }
+        );
+    }
+
+    #[test]
+    fn test_url_extract_param() {
+        let extract = |url: &str, name: &str| {
+            UrlExtractParam {}
+                .execute(
+                    &Session::new(1),
+                    &DUMMY_SIG,
+                    &[Datum::from(url), Datum::from(name)],
+                )
+                .as_static()
+        };
+        assert_eq!(
+            extract("/search?q=hello+world%21&page=2", "q"),
+            Datum::from("hello world!")
+        );
+        assert_eq!(extract("/search?q=a&page=2", "page"), Datum::from("2"));
+        assert_eq!(extract("/search?flag&page=2", "flag"), Datum::from(""));
+        assert_eq!(extract("/search?q=a", "missing"), Datum::Null);
+        assert_eq!(extract("/search?q=a#page=2", "page"), Datum::Null);
+    }
+}
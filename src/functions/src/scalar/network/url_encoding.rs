@@ -0,0 +1,137 @@
+use crate::registry::Registry;
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
+use data::{DataType, Datum, Session};
+use std::fmt::Write;
+
+#[derive(Debug)]
+struct UrlEncode {}
+
+/// url_encode(text)
+/// Percent encodes everything apart from the unreserved characters, A-Z a-z 0-9 - _ . ~
+impl Function for UrlEncode {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        if let Some(s) = args[0].as_maybe_text() {
+            let mut encoded = String::with_capacity(s.len());
+            for byte in s.bytes() {
+                if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+                    encoded.push(byte as char);
+                } else {
+                    // Writing to a string can't fail
+                    let _ = write!(encoded, "%{:02X}", byte);
+                }
+            }
+            Datum::from(encoded)
+        } else {
+            Datum::Null
+        }
+    }
+}
+
+#[derive(Debug)]
+struct UrlDecode {}
+
+/// url_decode(text)
+/// Decodes percent encoded bytes and turns + into spaces. Malformed escapes are left as is,
+/// returns null if the decoded bytes aren't valid utf8.
+impl Function for UrlDecode {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        args[0]
+            .as_maybe_text()
+            .and_then(url_decode)
+            .map(Datum::from)
+            .unwrap_or(Datum::Null)
+    }
+}
+
+pub(super) fn url_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let escaped = s
+                    .get(idx + 1..idx + 3)
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                if let Some(byte) = escaped {
+                    decoded.push(byte);
+                    idx += 2;
+                } else {
+                    decoded.push(b'%');
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        idx += 1;
+    }
+    String::from_utf8(decoded).ok()
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "url_encode",
+        vec![DataType::Text],
+        DataType::Text,
+        FunctionType::Scalar(&UrlEncode {}),
+    ));
+
+    registry.register_function(FunctionDefinition::new(
+        "url_decode",
+        vec![DataType::Text],
+        DataType::Text,
+        FunctionType::Scalar(&UrlDecode {}),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUMMY_SIG: FunctionSignature = FunctionSignature {
+        name: "url_encode",
+        args: vec![],
+        ret: DataType::Text,
+    };
+
+    #[test]
+    fn test_null() {
+        assert_eq!(
+            UrlEncode {}.execute(&Session::new(1), &DUMMY_SIG, &[Datum::Null]),
+            Datum::Null
+        );
+        assert_eq!(
+            UrlDecode {}.execute(&Session::new(1), &DUMMY_SIG, &[Datum::Null]),
+            Datum::Null
+        );
+    }
+
+    #[test]
+    fn test_url_encode() {
+        assert_eq!(
+            UrlEncode {}.execute(&Session::new(1), &DUMMY_SIG, &[Datum::from("a b/c?d=é~")]),
+            Datum::from("a%20b%2Fc%3Fd%3D%C3%A9~")
+        );
+    }
+
+    #[test]
+    fn test_url_decode() {
+        assert_eq!(
+            url_decode("a%20b+c%2Fd%C3%A9"),
+            Some("a b c/dé".to_string())
+        );
+        assert_eq!(url_decode("100%"), Some("100%".to_string()));
+        assert_eq!(url_decode("%zz%4"), Some("%zz%4".to_string()));
+        assert_eq!(url_decode("%FF"), None);
+    }
+}
@@ -0,0 +1,53 @@
+use crate::registry::Registry;
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType, Volatility};
+use data::{DataType, Datum, Session};
+
+#[derive(Debug)]
+struct CurrentUser {}
+
+/// current_user()
+/// The user the connection logged in as, handy in row level security policies
+impl Function for CurrentUser {
+    fn execute<'a>(
+        &self,
+        session: &Session,
+        _signature: &FunctionSignature,
+        _args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        Datum::from(session.user.read().unwrap().to_string())
+    }
+
+    fn volatility(&self) -> Volatility {
+        Volatility::Stable
+    }
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "current_user",
+        vec![],
+        DataType::Text,
+        FunctionType::Scalar(&CurrentUser {}),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUMMY_SIG: FunctionSignature = FunctionSignature {
+        name: "current_user",
+        args: vec![],
+        ret: DataType::Text,
+    };
+
+    #[test]
+    fn test_current_user() {
+        let session = Session::new(1);
+        *session.user.write().unwrap() = "bob".to_string();
+        assert_eq!(
+            CurrentUser {}.execute(&session, &DUMMY_SIG, &[]),
+            Datum::from("bob")
+        )
+    }
+}
@@ -1,5 +1,5 @@
 use crate::registry::Registry;
-use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType, Volatility};
 use data::{DataType, Datum, Session};
 
 #[derive(Debug)]
@@ -14,6 +14,10 @@ impl Function for Database {
     ) -> Datum<'a> {
         Datum::from(session.current_database.read().unwrap().to_string())
     }
+
+    fn volatility(&self) -> Volatility {
+        Volatility::Stable
+    }
 }
 
 pub fn register_builtins(registry: &mut Registry) {
@@ -1,7 +1,11 @@
 use crate::registry::Registry;
 
+mod current_user;
 mod database;
+mod now;
 
 pub fn register_builtins(registry: &mut Registry) {
+    current_user::register_builtins(registry);
     database::register_builtins(registry);
+    now::register_builtins(registry);
 }
@@ -0,0 +1,55 @@
+use crate::registry::Registry;
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType, Volatility};
+use data::{DataType, Datum, Session};
+
+#[derive(Debug)]
+struct Now {}
+
+/// now()
+/// The time the current statement started, so it's the same for every row.
+impl Function for Now {
+    fn execute<'a>(
+        &self,
+        session: &Session,
+        _signature: &FunctionSignature,
+        _args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        Datum::from(*session.statement_timestamp.read().unwrap())
+    }
+
+    fn volatility(&self) -> Volatility {
+        Volatility::Stable
+    }
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "now",
+        vec![],
+        DataType::Timestamp,
+        FunctionType::Scalar(&Now {}),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::chrono::NaiveDate;
+
+    const DUMMY_SIG: FunctionSignature = FunctionSignature {
+        name: "now",
+        args: vec![],
+        ret: DataType::Timestamp,
+    };
+
+    #[test]
+    fn test_now() {
+        let session = Session::new(1);
+        let timestamp = NaiveDate::from_ymd(2020, 3, 1).and_hms(13, 5, 9);
+        *session.statement_timestamp.write().unwrap() = timestamp;
+        assert_eq!(
+            Now {}.execute(&session, &DUMMY_SIG, &[]),
+            Datum::from(timestamp)
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use crate::registry::Registry;
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
+use data::json::JsonType;
+use data::{DataType, Datum, Session};
+
+#[derive(Debug)]
+struct ArrayToString {}
+
+/// array_to_string(json_array, delimiter)
+/// Joins the elements of a json array with the delimiter. Strings are added unquoted, null
+/// elements are skipped and anything else is added as its json text. Returns null if the json
+/// isn't an array.
+impl Function for ArrayToString {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        if let (Some(json), Some(delimiter)) = (args[0].as_maybe_json(), args[1].as_maybe_text()) {
+            if let Some(elements) = json.iter_array() {
+                let parts: Vec<_> = elements
+                    .filter(|element| element.json_type() != JsonType::Null)
+                    .map(|element| match element.get_string() {
+                        Some(s) => s.to_string(),
+                        None => Datum::from(element).typed_with(DataType::Json).to_string(),
+                    })
+                    .collect();
+                return Datum::from(parts.join(delimiter));
+            }
+        }
+        Datum::Null
+    }
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "array_to_string",
+        vec![DataType::Json, DataType::Text],
+        DataType::Text,
+        FunctionType::Scalar(&ArrayToString {}),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::json::OwnedJson;
+
+    const DUMMY_SIG: FunctionSignature = FunctionSignature {
+        name: "array_to_string",
+        args: vec![],
+        ret: DataType::Text,
+    };
+
+    fn array_to_string(json: &str, delimiter: &str) -> Datum<'static> {
+        ArrayToString {}
+            .execute(
+                &Session::new(1),
+                &DUMMY_SIG,
+                &[
+                    Datum::from(OwnedJson::parse(json).unwrap()),
+                    Datum::from(delimiter),
+                ],
+            )
+            .as_static()
+    }
+
+    #[test]
+    fn test_null() {
+        assert_eq!(
+            ArrayToString {}.execute(
+                &Session::new(1),
+                &DUMMY_SIG,
+                &[Datum::Null, Datum::from(",")]
+            ),
+            Datum::Null
+        );
+        assert_eq!(array_to_string(r#"{"a":1}"#, ","), Datum::Null);
+    }
+
+    #[test]
+    fn test_array_to_string() {
+        assert_eq!(
+            array_to_string(r#"["a", null, 1.5, true, [1,2], "b"]"#, "|"),
+            Datum::from("a|1.5|true|[1,2]|b")
+        );
+        assert_eq!(array_to_string("[]", ","), Datum::from(""));
+    }
+}
@@ -0,0 +1,230 @@
+use crate::registry::Registry;
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
+use data::{DataType, Datum, Session};
+
+/// How a collation compares text
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+struct Collation {
+    fold_case: bool,
+    fold_accents: bool,
+    // Runs of digits compare as numbers, ie file2 < file10
+    numeric: bool,
+}
+
+/// binary - as is
+/// ci - case insensitive
+/// ci_ai - case and accent insensitive
+/// natural - case and accent insensitive with numbers compared as numbers
+fn collation(name: &str) -> Option<Collation> {
+    let (fold_case, fold_accents, numeric) = match name.to_ascii_lowercase().as_str() {
+        "binary" => (false, false, false),
+        "ci" => (true, false, false),
+        "ci_ai" => (true, true, false),
+        "natural" => (true, true, true),
+        _ => return None,
+    };
+    Some(Collation {
+        fold_case,
+        fold_accents,
+        numeric,
+    })
+}
+
+const NATURAL: Collation = Collation {
+    fold_case: true,
+    fold_accents: true,
+    numeric: true,
+};
+
+#[derive(Debug)]
+struct NaturalSortKey {}
+
+/// natural_sort_key(text)
+/// A key that sorts text the way people expect, ie "file2" before "File10" and "é" next to "e".
+impl Function for NaturalSortKey {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        if let Some(s) = args[0].as_maybe_text() {
+            Datum::from(sort_key(s, NATURAL))
+        } else {
+            Datum::Null
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CollationKey {}
+
+/// collation_key(text, collation)
+/// A key that sorts and compares text as per the collation, null for unknown collations.
+impl Function for CollationKey {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        if let (Some(s), Some(collation)) = (
+            args[0].as_maybe_text(),
+            args[1].as_maybe_text().and_then(collation),
+        ) {
+            Datum::from(sort_key(s, collation))
+        } else {
+            Datum::Null
+        }
+    }
+}
+
+/// Builds the bytes that sort the same way the collation would sort the text. Numbers are
+/// written as a '0' followed by their digit count and their digits without any leading zeros
+/// so they sort by value, and in the same place amongst other characters as a digit would.
+fn sort_key(s: &str, collation: Collation) -> Vec<u8> {
+    let mut key = Vec::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    let mut char_buf = [0; 4];
+    while let Some(c) = chars.next() {
+        if collation.numeric && c.is_ascii_digit() {
+            let mut digits = vec![c as u8];
+            while let Some(digit) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                digits.push(*digit as u8);
+                chars.next();
+            }
+            let leading_zeros = digits.iter().take_while(|d| **d == b'0').count();
+            let digits = &digits[leading_zeros..];
+            key.push(b'0');
+            key.push(digits.len().min(u8::MAX as usize) as u8);
+            key.extend_from_slice(digits);
+        } else if collation.fold_case {
+            for lower in c.to_lowercase() {
+                let folded = if collation.fold_accents {
+                    fold_accent(lower)
+                } else {
+                    lower
+                };
+                key.extend_from_slice(folded.encode_utf8(&mut char_buf).as_bytes());
+            }
+        } else {
+            key.extend_from_slice(c.encode_utf8(&mut char_buf).as_bytes());
+        }
+    }
+    key
+}
+
+/// Strips the accent from the lowercase latin letters, anything else is returned as is
+fn fold_accent(c: char) -> char {
+    match c {
+        'à'..='å' | 'ā' | 'ă' | 'ą' => 'a',
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => 'c',
+        'ď' | 'đ' => 'd',
+        'è'..='ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => 'e',
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => 'g',
+        'ĥ' | 'ħ' => 'h',
+        'ì'..='ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => 'i',
+        'ĵ' => 'j',
+        'ķ' => 'k',
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => 'l',
+        'ñ' | 'ń' | 'ņ' | 'ň' => 'n',
+        'ò'..='ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => 'o',
+        'ŕ' | 'ŗ' | 'ř' => 'r',
+        'ś' | 'ŝ' | 'ş' | 'š' => 's',
+        'ţ' | 'ť' | 'ŧ' => 't',
+        'ù'..='ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => 'u',
+        'ŵ' => 'w',
+        'ý' | 'ÿ' | 'ŷ' => 'y',
+        'ź' | 'ż' | 'ž' => 'z',
+        _ => c,
+    }
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "natural_sort_key",
+        vec![DataType::Text],
+        DataType::ByteA,
+        FunctionType::Scalar(&NaturalSortKey {}),
+    ));
+
+    registry.register_function(FunctionDefinition::new(
+        "collation_key",
+        vec![DataType::Text, DataType::Text],
+        DataType::ByteA,
+        FunctionType::Scalar(&CollationKey {}),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUMMY_SIG: FunctionSignature = FunctionSignature {
+        name: "collation_key",
+        args: vec![],
+        ret: DataType::ByteA,
+    };
+
+    fn sorted(values: &[&str], collation: &str) -> Vec<String> {
+        let collation = super::collation(collation).unwrap();
+        let mut values: Vec<_> = values.iter().map(|s| s.to_string()).collect();
+        values.sort_by_key(|s| sort_key(s, collation));
+        values
+    }
+
+    #[test]
+    fn test_null() {
+        assert_eq!(
+            CollationKey {}.execute(
+                &Session::new(1),
+                &DUMMY_SIG,
+                &[Datum::Null, Datum::from("natural")]
+            ),
+            Datum::Null
+        );
+        assert_eq!(
+            CollationKey {}.execute(
+                &Session::new(1),
+                &DUMMY_SIG,
+                &[Datum::from("a"), Datum::from("unknown")]
+            ),
+            Datum::Null
+        );
+    }
+
+    #[test]
+    fn test_natural() {
+        assert_eq!(
+            sorted(
+                &["file10", "File2", "file1", "file02b", "file", "é1"],
+                "natural"
+            ),
+            vec!["é1", "file", "file1", "File2", "file02b", "file10"]
+        );
+        assert_eq!(sort_key("Émile", NATURAL), sort_key("emile", NATURAL));
+        assert_eq!(sort_key("007", NATURAL), sort_key("7", NATURAL));
+        // Punctuation still sorts before numbers which sort before letters
+        assert_eq!(sorted(&["a", "9", " "], "natural"), vec![" ", "9", "a"]);
+    }
+
+    #[test]
+    fn test_collations() {
+        assert_eq!(
+            sorted(&["b", "B", "a", "A"], "binary"),
+            vec!["A", "B", "a", "b"]
+        );
+        assert_eq!(
+            sorted(&["b", "A", "a2", "a10"], "CI"),
+            vec!["A", "a10", "a2", "b"]
+        );
+        assert_ne!(
+            sort_key("é", collation("ci").unwrap()),
+            sort_key("e", collation("ci").unwrap())
+        );
+        assert_eq!(
+            sort_key("É", collation("ci_ai").unwrap()),
+            sort_key("e", collation("ci_ai").unwrap())
+        );
+    }
+}
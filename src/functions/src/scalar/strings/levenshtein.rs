@@ -0,0 +1,93 @@
+use crate::registry::Registry;
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
+use data::{DataType, Datum, Session};
+
+#[derive(Debug)]
+struct Levenshtein {}
+
+/// levenshtein(text, text)
+/// The number of single character insertions, deletions or substitutions needed to turn one
+/// string into the other.
+impl Function for Levenshtein {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        if let (Some(a), Some(b)) = (args[0].as_maybe_text(), args[1].as_maybe_text()) {
+            Datum::from(levenshtein(a, b) as i32)
+        } else {
+            Datum::Null
+        }
+    }
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // Only need to keep the previous row of the distance matrix around.
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = diagonal + if a_char == *b_char { 0 } else { 1 };
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "levenshtein",
+        vec![DataType::Text, DataType::Text],
+        DataType::Integer,
+        FunctionType::Scalar(&Levenshtein {}),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUMMY_SIG: FunctionSignature = FunctionSignature {
+        name: "levenshtein",
+        args: vec![],
+        ret: DataType::Integer,
+    };
+
+    #[test]
+    fn test_null() {
+        assert_eq!(
+            Levenshtein {}.execute(
+                &Session::new(1),
+                &DUMMY_SIG,
+                &[Datum::Null, Datum::from("a")]
+            ),
+            Datum::Null
+        )
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        let distance = |a: &str, b: &str| {
+            Levenshtein {}
+                .execute(
+                    &Session::new(1),
+                    &DUMMY_SIG,
+                    &[Datum::from(a), Datum::from(b)],
+                )
+                .as_integer()
+        };
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("", "abc"), 3);
+        assert_eq!(distance("abc", ""), 3);
+        assert_eq!(distance("same", "same"), 0);
+        assert_eq!(distance("flaw", "lawn"), 2);
+        // Counts characters rather than bytes
+        assert_eq!(distance("café", "cafe"), 1);
+    }
+}
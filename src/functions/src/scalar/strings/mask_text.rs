@@ -0,0 +1,79 @@
+use crate::registry::Registry;
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
+use data::{DataType, Datum, Session};
+
+#[derive(Debug)]
+struct MaskText {}
+
+/// mask_text(text, keep_last)
+/// Replaces every character but the last keep_last with a *, ie for showing the last 4 digits
+/// of a card number in a column mask. Negative counts mask every character.
+impl Function for MaskText {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        if let (Some(s), Some(keep_last)) = (args[0].as_maybe_text(), args[1].as_maybe_integer()) {
+            let len = s.chars().count();
+            let masked = len.saturating_sub(keep_last.max(0) as usize);
+            let mut text = "*".repeat(masked);
+            text.extend(s.chars().skip(masked));
+            Datum::from(text)
+        } else {
+            Datum::Null
+        }
+    }
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "mask_text",
+        vec![DataType::Text, DataType::Integer],
+        DataType::Text,
+        FunctionType::Scalar(&MaskText {}),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUMMY_SIG: FunctionSignature = FunctionSignature {
+        name: "mask_text",
+        args: vec![],
+        ret: DataType::Text,
+    };
+
+    fn mask_text(s: &str, keep_last: i32) -> Datum<'static> {
+        MaskText {}
+            .execute(
+                &Session::new(1),
+                &DUMMY_SIG,
+                &[Datum::from(s), Datum::from(keep_last)],
+            )
+            .as_static()
+    }
+
+    #[test]
+    fn test_null() {
+        assert_eq!(
+            MaskText {}.execute(&Session::new(1), &DUMMY_SIG, &[Datum::Null, Datum::from(4)]),
+            Datum::Null
+        );
+    }
+
+    #[test]
+    fn test_mask_text() {
+        assert_eq!(
+            mask_text("4111222233334444", 4),
+            Datum::from("************4444")
+        );
+        assert_eq!(mask_text("héllo", 2), Datum::from("***lo"));
+        assert_eq!(mask_text("abc", 5), Datum::from("abc"));
+        assert_eq!(mask_text("abc", 0), Datum::from("***"));
+        assert_eq!(mask_text("abc", -1), Datum::from("***"));
+        assert_eq!(mask_text("", 4), Datum::from(""));
+    }
+}
@@ -0,0 +1,21 @@
+use crate::registry::Registry;
+
+mod array_to_string;
+mod collation_key;
+mod levenshtein;
+mod mask_text;
+mod similarity;
+mod soundex;
+mod split_part;
+mod string_to_array;
+
+pub fn register_builtins(registry: &mut Registry) {
+    array_to_string::register_builtins(registry);
+    collation_key::register_builtins(registry);
+    levenshtein::register_builtins(registry);
+    mask_text::register_builtins(registry);
+    similarity::register_builtins(registry);
+    soundex::register_builtins(registry);
+    split_part::register_builtins(registry);
+    string_to_array::register_builtins(registry);
+}
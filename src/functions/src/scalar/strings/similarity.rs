@@ -0,0 +1,115 @@
+use crate::registry::Registry;
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
+use data::rust_decimal::Decimal;
+use data::{DataType, Datum, Session};
+use std::collections::HashSet;
+
+#[derive(Debug)]
+struct Similarity {}
+
+/// similarity(text, text)
+/// Trigram similarity as per postgres' pg_trgm, the number of trigrams the strings share
+/// divided by the total number of distinct trigrams. Ranges from 0 (nothing in common) to 1.
+impl Function for Similarity {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        if let (Some(a), Some(b)) = (args[0].as_maybe_text(), args[1].as_maybe_text()) {
+            let (a, b) = (trigrams(a), trigrams(b));
+            let shared = a.intersection(&b).count();
+            let total = a.len() + b.len() - shared;
+            if total == 0 {
+                Datum::from(Decimal::new(0, SCALE))
+            } else {
+                let mut similarity = Decimal::from(shared as u64) / Decimal::from(total as u64);
+                similarity = similarity.round_dp(SCALE);
+                similarity.rescale(SCALE);
+                Datum::from(similarity)
+            }
+        } else {
+            Datum::Null
+        }
+    }
+}
+
+const SCALE: u32 = 4;
+
+/// Splits the string into words of alphanumeric characters and returns the set of trigrams,
+/// each word is lowercased and padded with two spaces in front and one behind, ie
+/// cat -> "  c", " ca", "cat", "at "
+fn trigrams(s: &str) -> HashSet<[char; 3]> {
+    let mut trigrams = HashSet::new();
+    for word in s.split(|c: char| !c.is_alphanumeric()) {
+        if word.is_empty() {
+            continue;
+        }
+        let padded: Vec<char> = "  "
+            .chars()
+            .chain(word.chars().flat_map(char::to_lowercase))
+            .chain(" ".chars())
+            .collect();
+        for window in padded.windows(3) {
+            trigrams.insert([window[0], window[1], window[2]]);
+        }
+    }
+    trigrams
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "similarity",
+        vec![DataType::Text, DataType::Text],
+        DataType::Decimal(5, SCALE as u8),
+        FunctionType::Scalar(&Similarity {}),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const DUMMY_SIG: FunctionSignature = FunctionSignature {
+        name: "similarity",
+        args: vec![],
+        ret: DataType::Decimal(5, 4),
+    };
+
+    fn similarity(a: &str, b: &str) -> Decimal {
+        Similarity {}
+            .execute(
+                &Session::new(1),
+                &DUMMY_SIG,
+                &[Datum::from(a), Datum::from(b)],
+            )
+            .as_decimal()
+    }
+
+    #[test]
+    fn test_null() {
+        assert_eq!(
+            Similarity {}.execute(
+                &Session::new(1),
+                &DUMMY_SIG,
+                &[Datum::from("a"), Datum::Null]
+            ),
+            Datum::Null
+        )
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity("word", "word"), Decimal::from(1));
+        assert_eq!(similarity("Word", "wORD"), Decimal::from(1));
+        assert_eq!(similarity("abc", "xyz"), Decimal::from(0));
+        assert_eq!(similarity("", ""), Decimal::from(0));
+        // 4 shared out of 7 distinct trigrams, words has "rds" and "ds " where word has "rd "
+        assert_eq!(
+            similarity("word", "words"),
+            Decimal::from_str("0.5714").unwrap()
+        );
+    }
+}
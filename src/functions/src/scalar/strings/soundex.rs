@@ -0,0 +1,110 @@
+use crate::registry::Registry;
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
+use data::{DataType, Datum, Session};
+
+#[derive(Debug)]
+struct Soundex {}
+
+/// soundex(text)
+/// The american soundex code for the string, ie Robert -> R163. Anything that isn't an ascii
+/// letter is ignored, a string without any letters gives an empty string.
+impl Function for Soundex {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        if let Some(s) = args[0].as_maybe_text() {
+            Datum::from(soundex(s))
+        } else {
+            Datum::Null
+        }
+    }
+}
+
+/// The digit for each letter, 0 for vowels (which separate repeated digits) and None for h and
+/// w (which don't).
+fn code(letter: char) -> Option<u8> {
+    match letter {
+        'B' | 'F' | 'P' | 'V' => Some(b'1'),
+        'C' | 'G' | 'J' | 'K' | 'Q' | 'S' | 'X' | 'Z' => Some(b'2'),
+        'D' | 'T' => Some(b'3'),
+        'L' => Some(b'4'),
+        'M' | 'N' => Some(b'5'),
+        'R' => Some(b'6'),
+        'H' | 'W' => None,
+        _ => Some(b'0'),
+    }
+}
+
+fn soundex(s: &str) -> String {
+    let mut letters = s
+        .chars()
+        .filter(char::is_ascii_alphabetic)
+        .map(|c| c.to_ascii_uppercase());
+
+    let first = match letters.next() {
+        Some(first) => first,
+        None => return String::new(),
+    };
+    let mut result = vec![first as u8];
+    let mut last_code = code(first);
+    for letter in letters {
+        if result.len() == 4 {
+            break;
+        }
+        let letter_code = code(letter);
+        match letter_code {
+            Some(b'0') => last_code = letter_code,
+            Some(digit) if letter_code != last_code => {
+                result.push(digit);
+                last_code = letter_code;
+            }
+            _ => {}
+        }
+    }
+    result.resize(4, b'0');
+    String::from_utf8(result).unwrap()
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "soundex",
+        vec![DataType::Text],
+        DataType::Text,
+        FunctionType::Scalar(&Soundex {}),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUMMY_SIG: FunctionSignature = FunctionSignature {
+        name: "soundex",
+        args: vec![],
+        ret: DataType::Text,
+    };
+
+    #[test]
+    fn test_null() {
+        assert_eq!(
+            Soundex {}.execute(&Session::new(1), &DUMMY_SIG, &[Datum::Null]),
+            Datum::Null
+        )
+    }
+
+    #[test]
+    fn test_soundex() {
+        assert_eq!(soundex("Robert"), "R163");
+        assert_eq!(soundex("Rupert"), "R163");
+        assert_eq!(soundex("Rubin"), "R150");
+        assert_eq!(soundex("Ashcraft"), "A261");
+        assert_eq!(soundex("Tymczak"), "T522");
+        assert_eq!(soundex("Pfister"), "P236");
+        assert_eq!(soundex("Honeyman"), "H555");
+        assert_eq!(soundex("a"), "A000");
+        assert_eq!(soundex("123"), "");
+    }
+}
@@ -0,0 +1,97 @@
+use crate::registry::Registry;
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
+use data::{DataType, Datum, Session};
+
+#[derive(Debug)]
+struct SplitPart {}
+
+/// split_part(text, delimiter, n)
+/// Splits the string on the delimiter and returns the nth field, counting from 1. Negative
+/// positions count back from the end of the string. Positions past either end give an empty
+/// string while a position of 0 gives null.
+impl Function for SplitPart {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        if let (Some(s), Some(delimiter), Some(n)) = (
+            args[0].as_maybe_text(),
+            args[1].as_maybe_text(),
+            args[2].as_maybe_integer(),
+        ) {
+            if n == 0 {
+                return Datum::Null;
+            }
+            let field = if delimiter.is_empty() {
+                // An empty delimiter means the whole string is the only field
+                Some(s).filter(|_| n == 1 || n == -1)
+            } else if n > 0 {
+                s.split(delimiter).nth(n as usize - 1)
+            } else {
+                s.rsplit(delimiter).nth((-(n as i64)) as usize - 1)
+            };
+            Datum::from(field.unwrap_or(""))
+        } else {
+            Datum::Null
+        }
+    }
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "split_part",
+        vec![DataType::Text, DataType::Text, DataType::Integer],
+        DataType::Text,
+        FunctionType::Scalar(&SplitPart {}),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUMMY_SIG: FunctionSignature = FunctionSignature {
+        name: "split_part",
+        args: vec![],
+        ret: DataType::Text,
+    };
+
+    fn split_part(s: &str, delimiter: &str, n: i32) -> Datum<'static> {
+        SplitPart {}
+            .execute(
+                &Session::new(1),
+                &DUMMY_SIG,
+                &[Datum::from(s), Datum::from(delimiter), Datum::from(n)],
+            )
+            .as_static()
+    }
+
+    #[test]
+    fn test_null() {
+        assert_eq!(
+            SplitPart {}.execute(
+                &Session::new(1),
+                &DUMMY_SIG,
+                &[Datum::Null, Datum::from(","), Datum::from(1)]
+            ),
+            Datum::Null
+        );
+        assert_eq!(split_part("a,b", ",", 0), Datum::Null);
+    }
+
+    #[test]
+    fn test_split_part() {
+        assert_eq!(split_part("a,b,c", ",", 1), Datum::from("a"));
+        assert_eq!(split_part("a,b,c", ",", 3), Datum::from("c"));
+        assert_eq!(split_part("a,b,c", ",", 4), Datum::from(""));
+        assert_eq!(split_part("a,b,c", ",", -1), Datum::from("c"));
+        assert_eq!(split_part("a,b,c", ",", -3), Datum::from("a"));
+        assert_eq!(split_part("a,b,c", ",", -4), Datum::from(""));
+        assert_eq!(split_part("a::b::c", "::", 2), Datum::from("b"));
+        assert_eq!(split_part("a,,c", ",", 2), Datum::from(""));
+        assert_eq!(split_part("abc", "", 1), Datum::from("abc"));
+        assert_eq!(split_part("abc", "", 2), Datum::from(""));
+    }
+}
@@ -0,0 +1,94 @@
+use crate::registry::Registry;
+use crate::{Function, FunctionDefinition, FunctionSignature, FunctionType};
+use data::json::JsonBuilder;
+use data::{DataType, Datum, Session};
+
+#[derive(Debug)]
+struct StringToArray {}
+
+/// string_to_array(text, delimiter)
+/// Splits the string on the delimiter into a json array of strings. An empty string gives an
+/// empty array and an empty delimiter gives an array holding the whole string.
+impl Function for StringToArray {
+    fn execute<'a>(
+        &self,
+        _session: &Session,
+        _signature: &FunctionSignature,
+        args: &'a [Datum<'a>],
+    ) -> Datum<'a> {
+        if let (Some(s), Some(delimiter)) = (args[0].as_maybe_text(), args[1].as_maybe_text()) {
+            let json = JsonBuilder::default().array(|array| {
+                if s.is_empty() {
+                    return;
+                }
+                if delimiter.is_empty() {
+                    array.push_string(s);
+                } else {
+                    s.split(delimiter).for_each(|part| array.push_string(part));
+                }
+            });
+            Datum::from(json)
+        } else {
+            Datum::Null
+        }
+    }
+}
+
+pub fn register_builtins(registry: &mut Registry) {
+    registry.register_function(FunctionDefinition::new(
+        "string_to_array",
+        vec![DataType::Text, DataType::Text],
+        DataType::Json,
+        FunctionType::Scalar(&StringToArray {}),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::json::OwnedJson;
+
+    const DUMMY_SIG: FunctionSignature = FunctionSignature {
+        name: "string_to_array",
+        args: vec![],
+        ret: DataType::Json,
+    };
+
+    fn string_to_array(s: &str, delimiter: &str) -> Datum<'static> {
+        StringToArray {}
+            .execute(
+                &Session::new(1),
+                &DUMMY_SIG,
+                &[Datum::from(s), Datum::from(delimiter)],
+            )
+            .as_static()
+    }
+
+    #[test]
+    fn test_null() {
+        assert_eq!(
+            StringToArray {}.execute(
+                &Session::new(1),
+                &DUMMY_SIG,
+                &[Datum::from("a"), Datum::Null]
+            ),
+            Datum::Null
+        );
+    }
+
+    #[test]
+    fn test_string_to_array() {
+        assert_eq!(
+            string_to_array("a,b,,c", ","),
+            Datum::from(OwnedJson::parse(r#"["a","b","","c"]"#).unwrap())
+        );
+        assert_eq!(
+            string_to_array("a b", ""),
+            Datum::from(OwnedJson::parse(r#"["a b"]"#).unwrap())
+        );
+        assert_eq!(
+            string_to_array("", ","),
+            Datum::from(OwnedJson::parse("[]").unwrap())
+        );
+    }
+}
@@ -1,8 +1,11 @@
-use runtime::Runtime;
+use runtime::hooks::QueryLog;
+use runtime::{AdmissionLimits, CompactionStyle, EncryptionKey, Runtime, StorageOptions};
 use server::Server;
 use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
 
-use clap::{App, Arg};
+use clap::{App, Arg, ArgMatches};
 #[cfg(not(windows))]
 use jemallocator::Jemalloc;
 
@@ -18,14 +21,202 @@ fn main() -> Result<(), Box<dyn Error>> {
                 .long("directory")
                 .default_value("target/test_db"),
         )
+        .arg(
+            Arg::with_name("storage-profile")
+                .long("storage-profile")
+                .default_value("default")
+                .possible_values(&["default", "ingest"])
+                .help("Starting point for the rocksdb tuning, ingest favours write throughput"),
+        )
+        .arg(
+            Arg::with_name("block-cache-mb")
+                .long("block-cache-mb")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("write-buffer-mb")
+                .long("write-buffer-mb")
+                .takes_value(true)
+                .help("Size of each memtable"),
+        )
+        .arg(
+            Arg::with_name("max-write-buffers")
+                .long("max-write-buffers")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("background-jobs")
+                .long("background-jobs")
+                .takes_value(true)
+                .help("Max number of concurrent flushes and compactions"),
+        )
+        .arg(
+            Arg::with_name("compaction-style")
+                .long("compaction-style")
+                .possible_values(&["level", "universal", "fifo"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("encryption-key-env")
+                .long("encryption-key-env")
+                .takes_value(true)
+                .help("Environment variable holding the hex encoded key to encrypt data at rest"),
+        )
+        .arg(
+            Arg::with_name("encryption-key-command")
+                .long("encryption-key-command")
+                .takes_value(true)
+                .conflicts_with("encryption-key-env")
+                .help("Command that prints the hex encoded key, ie to fetch it from a KMS"),
+        )
+        .arg(
+            Arg::with_name("previous-encryption-keys-env")
+                .long("previous-encryption-keys-env")
+                .takes_value(true)
+                .help("Environment variable holding comma separated keys being rotated out"),
+        )
+        .arg(
+            Arg::with_name("encrypt-existing-data")
+                .long("encrypt-existing-data")
+                .help("Encrypt a database written without encryption, it's done before starting up"),
+        )
+        .arg(
+            Arg::with_name("max-concurrent-statements")
+                .long("max-concurrent-statements")
+                .takes_value(true)
+                .help("Statements over this limit queue, highest session priority first"),
+        )
+        .arg(
+            Arg::with_name("max-queued-statements")
+                .long("max-queued-statements")
+                .takes_value(true)
+                .help("Statements arriving when the queue is full are rejected"),
+        )
+        .arg(
+            Arg::with_name("maintenance-share")
+                .long("maintenance-share")
+                .takes_value(true)
+                .help("Percentage of time view refreshes and sink flushes get while queries run"),
+        )
+        .arg(
+            Arg::with_name("auto-analyze-threshold")
+                .long("auto-analyze-threshold")
+                .takes_value(true)
+                .help("Percentage of an analyzed table's rows that have to change before it's analyzed again, 0 turns it off"),
+        )
+        .arg(
+            Arg::with_name("vacuum-retention-secs")
+                .long("vacuum-retention-secs")
+                .takes_value(true)
+                .help("How long old versions of rows are kept for AS OF reads, defaults to a day"),
+        )
+        .arg(
+            Arg::with_name("query-log")
+                .long("query-log")
+                .takes_value(true)
+                .help("File each statement is appended to along with the connection and client that sent it"),
+        )
+        .arg(
+            Arg::with_name("postgres-listen")
+                .long("postgres-listen")
+                .takes_value(true)
+                .help("Address to also accept postgres connections on, ie 0.0.0.0:5432"),
+        )
         .get_matches();
     let listen_address = "0.0.0.0:3307";
     let path = matches.value_of("directory").unwrap();
     eprintln!("Initializing Runtime");
-    let runtime = Runtime::new(path)?;
+    let runtime = Runtime::new_with_options(path, &storage_options(&matches)?)?;
+    runtime.set_admission_limits(admission_limits(&matches)?);
+    if let Some(share) = matches.value_of("maintenance-share") {
+        runtime.set_maintenance_share(share.parse()?);
+    }
+    if let Some(threshold) = matches.value_of("auto-analyze-threshold") {
+        runtime.set_auto_analyze_threshold(threshold.parse()?);
+    }
+    if let Some(retention) = matches.value_of("vacuum-retention-secs") {
+        runtime.set_vacuum_retention(Duration::from_secs(retention.parse()?));
+    }
+    if let Some(path) = matches.value_of("query-log") {
+        runtime.add_hook(Arc::new(QueryLog::to_file(path)?));
+    }
     eprintln!("Initializing Server");
     let mut server = Server::new(runtime);
     eprintln!("Server Running");
-    server.listen(listen_address)?;
+    server.listen(listen_address, matches.value_of("postgres-listen"))?;
     Ok(())
 }
+
+/// The storage profile with any of the individual settings given overriding it.
+fn storage_options(matches: &ArgMatches) -> Result<StorageOptions, Box<dyn Error>> {
+    let mut options = StorageOptions::profile(matches.value_of("storage-profile").unwrap())
+        .ok_or("Unknown storage profile")?;
+    let mb = |name: &str| -> Result<Option<usize>, Box<dyn Error>> {
+        match matches.value_of(name) {
+            Some(value) => Ok(Some(value.parse::<usize>()? * 1024 * 1024)),
+            None => Ok(None),
+        }
+    };
+    if let Some(block_cache_size) = mb("block-cache-mb")? {
+        options.block_cache_size = block_cache_size;
+    }
+    if let Some(write_buffer_size) = mb("write-buffer-mb")? {
+        options.write_buffer_size = write_buffer_size;
+    }
+    if let Some(max_write_buffers) = matches.value_of("max-write-buffers") {
+        options.max_write_buffer_number = max_write_buffers.parse()?;
+    }
+    if let Some(background_jobs) = matches.value_of("background-jobs") {
+        options.max_background_jobs = background_jobs.parse()?;
+    }
+    if let Some(compaction_style) = matches.value_of("compaction-style") {
+        options.compaction_style = compaction_style.parse::<CompactionStyle>()?;
+    }
+    options.encryption_key = encryption_key(matches)?;
+    options.previous_encryption_keys = previous_encryption_keys(matches)?;
+    options.encrypt_existing_data = matches.is_present("encrypt-existing-data");
+    Ok(options)
+}
+
+fn admission_limits(matches: &ArgMatches) -> Result<AdmissionLimits, Box<dyn Error>> {
+    let mut limits = AdmissionLimits::default();
+    if let Some(max_concurrent) = matches.value_of("max-concurrent-statements") {
+        limits.max_concurrent = Some(max_concurrent.parse()?);
+    }
+    if let Some(max_queued) = matches.value_of("max-queued-statements") {
+        limits.max_queued = max_queued.parse()?;
+    }
+    Ok(limits)
+}
+
+/// The key to encrypt data at rest with, taken from the environment or the output of a command.
+fn encryption_key(matches: &ArgMatches) -> Result<Option<EncryptionKey>, Box<dyn Error>> {
+    let hex = if let Some(var) = matches.value_of("encryption-key-env") {
+        std::env::var(var).map_err(|err| format!("Couldn't read {}: {}", var, err))?
+    } else if let Some(command) = matches.value_of("encryption-key-command") {
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .output()?;
+        if !output.status.success() {
+            return Err(format!("Encryption key command failed with {}", output.status).into());
+        }
+        String::from_utf8(output.stdout)?
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(EncryptionKey::from_hex(&hex)?))
+}
+
+fn previous_encryption_keys(matches: &ArgMatches) -> Result<Vec<EncryptionKey>, Box<dyn Error>> {
+    let var = match matches.value_of("previous-encryption-keys-env") {
+        Some(var) => var,
+        None => return Ok(vec![]),
+    };
+    let keys = std::env::var(var).map_err(|err| format!("Couldn't read {}: {}", var, err))?;
+    let mut previous_keys = vec![];
+    for hex in keys.split(',') {
+        previous_keys.push(EncryptionKey::from_hex(hex)?);
+    }
+    Ok(previous_keys)
+}
@@ -127,6 +127,7 @@ pub fn identifier_str(input: &str) -> ParserResult<String> {
                     kw("FULL"),
                     kw("ON"),
                     kw("IS"),
+                    kw("TABLESAMPLE"),
                 )))),
                 pair(
                     take_while_m_n(1, 1, |c: char| {
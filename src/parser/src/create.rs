@@ -1,21 +1,39 @@
-use crate::atoms::{and_recognise, identifier_str, kw, qualified_reference};
+use crate::atoms::{and_recognise, identifier_str, kw, qualified_reference, quoted_string};
+use crate::expression::{expression, sort_order};
 use crate::literals::datatype;
-use crate::select::select;
+use crate::procedure::create_procedure;
+use crate::select::{json_path, select, serde_options};
+use crate::statement::{sample_size, statement};
 use crate::whitespace::ws_0;
 use crate::ParserResult;
-use ast::statement::{CreateDatabase, CreateTable, CreateView, Statement};
-use data::DataType;
+use ast::statement::{
+    CreateDatabase, CreateEvent, CreateExternalTable, CreateIndex, CreatePolicy, CreateSink,
+    CreateTable, CreateView, ExternalColumn, ExternalColumns, LateData, SinkTarget, StateTtl,
+    Statement, DEFAULT_SAMPLE_SIZE,
+};
+use data::{parse_duration_ms, DataType, SortOrder};
 use nom::branch::alt;
 use nom::bytes::complete::tag;
-use nom::combinator::{cut, map};
-use nom::multi::separated_list0;
-use nom::sequence::{pair, preceded, separated_pair, tuple};
+use nom::combinator::{cut, map, map_opt, opt, value, verify};
+use nom::multi::{separated_list0, separated_list1};
+use nom::sequence::{delimited, pair, preceded, separated_pair, terminated, tuple};
 
 /// Parses a create statement
 pub fn create(input: &str) -> ParserResult<Statement> {
     preceded(
         kw("CREATE"),
-        cut(alt((create_database, create_table, create_view))),
+        cut(alt((
+            create_database,
+            create_table,
+            create_view,
+            create_materialized_view,
+            create_external_table,
+            create_sink,
+            create_procedure,
+            create_event,
+            create_policy,
+            create_index,
+        ))),
     )(input)
 }
 
@@ -27,50 +45,432 @@ fn create_database(input: &str) -> ParserResult<Statement> {
 }
 
 fn create_table(input: &str) -> ParserResult<Statement> {
-    map(
+    map_opt(
         preceded(
             pair(ws_0, kw("TABLE")),
             cut(tuple((
                 ws_0,
                 qualified_reference,
                 tuple((ws_0, tag("("), ws_0)),
-                separated_list0(tuple((ws_0, tag(","), ws_0)), column_spec),
+                separated_list0(tuple((ws_0, tag(","), ws_0)), table_element),
                 tuple((ws_0, tag(")"))),
+                opt(preceded(ws_0, table_options)),
             ))),
         ),
-        |(_, (db_name, table_name), _, columns, _)| {
-            Statement::CreateTable(CreateTable {
+        |(_, (db_name, table_name), _, elements, _, options)| {
+            let mut columns = vec![];
+            let mut primary_key = None;
+            for element in elements {
+                match element {
+                    TableElement::Column(column) => columns.push(column),
+                    // Only the one primary key is allowed
+                    TableElement::PrimaryKey(_) if primary_key.is_some() => return None,
+                    TableElement::PrimaryKey(key) => primary_key = Some(key),
+                }
+            }
+            let mut checksums = false;
+            let mut null_bitmap = false;
+            for (option, enabled) in options.unwrap_or_default() {
+                match option {
+                    TableOption::Checksum => checksums = enabled,
+                    TableOption::NullBitmap => null_bitmap = enabled,
+                }
+            }
+            Some(Statement::CreateTable(CreateTable {
                 database: db_name,
                 name: table_name,
                 columns,
+                primary_key,
+                checksums,
+                null_bitmap,
+            }))
+        },
+    )(input)
+}
+
+enum TableElement {
+    Column((String, DataType)),
+    PrimaryKey(Vec<(String, SortOrder)>),
+}
+
+/// Either a column or the table's PRIMARY KEY (c1 DESC, c2)
+fn table_element(input: &str) -> ParserResult<TableElement> {
+    alt((
+        map(primary_key, TableElement::PrimaryKey),
+        map(column_spec, TableElement::Column),
+    ))(input)
+}
+
+fn primary_key(input: &str) -> ParserResult<Vec<(String, SortOrder)>> {
+    preceded(
+        tuple((kw("PRIMARY"), ws_0, kw("KEY"), ws_0, tag("("), ws_0)),
+        cut(terminated(
+            separated_list1(
+                tuple((ws_0, tag(","), ws_0)),
+                pair(
+                    identifier_str,
+                    map(opt(preceded(ws_0, sort_order)), |order| {
+                        order.unwrap_or(SortOrder::Asc)
+                    }),
+                ),
+            ),
+            pair(ws_0, tag(")")),
+        )),
+    )(input)
+}
+
+#[derive(Debug, Copy, Clone)]
+enum TableOption {
+    Checksum,
+    NullBitmap,
+}
+
+/// WITH (CHECKSUM = TRUE | FALSE, NULL_BITMAP = TRUE | FALSE)
+fn table_options(input: &str) -> ParserResult<Vec<(TableOption, bool)>> {
+    delimited(
+        tuple((kw("WITH"), ws_0, tag("("), ws_0)),
+        separated_list1(
+            tuple((ws_0, tag(","), ws_0)),
+            separated_pair(
+                alt((
+                    value(TableOption::Checksum, kw("CHECKSUM")),
+                    value(TableOption::NullBitmap, kw("NULL_BITMAP")),
+                )),
+                tuple((ws_0, tag("="), ws_0)),
+                alt((value(true, kw("TRUE")), value(false, kw("FALSE")))),
+            ),
+        ),
+        tuple((ws_0, tag(")"))),
+    )(input)
+}
+
+/// An external table either lists its columns or infers them with AUTO, ie
+/// CREATE EXTERNAL TABLE foo FROM 'data/' WITH (format = 'json') AUTO SAMPLE 100
+fn create_external_table(input: &str) -> ParserResult<Statement> {
+    map_opt(
+        preceded(
+            tuple((ws_0, kw("EXTERNAL"), ws_0, kw("TABLE"))),
+            cut(tuple((
+                preceded(ws_0, qualified_reference),
+                opt(preceded(
+                    ws_0,
+                    delimited(
+                        pair(tag("("), ws_0),
+                        separated_list1(tuple((ws_0, tag(","), ws_0)), external_column_spec),
+                        pair(ws_0, tag(")")),
+                    ),
+                )),
+                preceded(tuple((ws_0, kw("FROM"), ws_0)), quoted_string),
+                opt(preceded(ws_0, serde_options)),
+                opt(preceded(
+                    pair(ws_0, kw("AUTO")),
+                    opt(preceded(tuple((ws_0, kw("SAMPLE"), ws_0)), sample_size)),
+                )),
+            ))),
+        ),
+        |((database, name), columns, directory, serde_options, auto)| {
+            let columns = match (columns, auto) {
+                (Some(columns), None) => ExternalColumns::Given(columns),
+                (None, Some(sample_size)) => {
+                    ExternalColumns::Auto(sample_size.unwrap_or(DEFAULT_SAMPLE_SIZE))
+                }
+                _ => return None,
+            };
+            Some(Statement::CreateExternalTable(CreateExternalTable {
+                database,
+                name,
+                directory,
+                serde_options: serde_options.unwrap_or_default(),
+                columns,
+            }))
+        },
+    )(input)
+}
+
+/// CREATE SINK s FROM orders INTO FILE('orders.json') FORMAT JSON, or
+/// INTO KAFKA(brokers = 'localhost:9092', topic = 'orders')
+fn create_sink(input: &str) -> ParserResult<Statement> {
+    map(
+        preceded(
+            pair(ws_0, kw("SINK")),
+            cut(tuple((
+                preceded(ws_0, qualified_reference),
+                preceded(tuple((ws_0, kw("FROM"), ws_0)), qualified_reference),
+                preceded(tuple((ws_0, kw("INTO"), ws_0)), sink_target),
+                opt(tuple((ws_0, kw("FORMAT"), ws_0, kw("JSON")))),
+            ))),
+        ),
+        |((database, name), (source_database, source), target, _format)| {
+            Statement::CreateSink(CreateSink {
+                database,
+                name,
+                source_database,
+                source,
+                target,
+            })
+        },
+    )(input)
+}
+
+/// EVENT [db.]name ON SCHEDULE EVERY '5 minutes' DO statement
+fn create_event(input: &str) -> ParserResult<Statement> {
+    map(
+        preceded(
+            pair(ws_0, kw("EVENT")),
+            cut(tuple((
+                preceded(ws_0, qualified_reference),
+                preceded(
+                    tuple((
+                        ws_0,
+                        kw("ON"),
+                        ws_0,
+                        kw("SCHEDULE"),
+                        ws_0,
+                        kw("EVERY"),
+                        ws_0,
+                    )),
+                    map_opt(quoted_string, |every| {
+                        parse_duration_ms(&every).filter(|every| *every > 0)
+                    }),
+                ),
+                preceded(
+                    tuple((ws_0, kw("DO"), ws_0)),
+                    and_recognise(event_statement),
+                ),
+            ))),
+        ),
+        |((database, name), every_ms, (statement, sql))| {
+            Statement::CreateEvent(CreateEvent {
+                database,
+                name,
+                every_ms,
+                statement: Box::new(statement),
+                sql: sql.to_string(),
+            })
+        },
+    )(input)
+}
+
+/// Events creating events would pile up with each run so that's the one thing they can't do
+fn event_statement(input: &str) -> ParserResult<Statement> {
+    verify(statement, |statement| {
+        !matches!(statement, Statement::CreateEvent(_))
+    })(input)
+}
+
+/// POLICY name ON [db.]table USING (predicate)
+fn create_policy(input: &str) -> ParserResult<Statement> {
+    map(
+        preceded(
+            pair(ws_0, kw("POLICY")),
+            cut(tuple((
+                preceded(ws_0, identifier_str),
+                preceded(tuple((ws_0, kw("ON"), ws_0)), qualified_reference),
+                preceded(
+                    tuple((ws_0, kw("USING"), ws_0, tag("("), ws_0)),
+                    and_recognise(expression),
+                ),
+                pair(ws_0, tag(")")),
+            ))),
+        ),
+        |(name, (database, table), (predicate, predicate_sql), _)| {
+            Statement::CreatePolicy(CreatePolicy {
+                name,
+                database,
+                table,
+                predicate,
+                predicate_sql: predicate_sql.to_string(),
+            })
+        },
+    )(input)
+}
+
+/// INDEX name ON [db.]table (column)
+fn create_index(input: &str) -> ParserResult<Statement> {
+    map(
+        preceded(
+            pair(ws_0, kw("INDEX")),
+            cut(tuple((
+                preceded(ws_0, identifier_str),
+                preceded(tuple((ws_0, kw("ON"), ws_0)), qualified_reference),
+                delimited(
+                    tuple((ws_0, tag("("), ws_0)),
+                    identifier_str,
+                    pair(ws_0, tag(")")),
+                ),
+            ))),
+        ),
+        |(name, (database, table), column)| {
+            Statement::CreateIndex(CreateIndex {
+                name,
+                database,
+                table,
+                column,
             })
         },
     )(input)
 }
 
+fn sink_target(input: &str) -> ParserResult<SinkTarget> {
+    alt((
+        map(
+            preceded(
+                kw("FILE"),
+                delimited(
+                    tuple((ws_0, tag("("), ws_0)),
+                    quoted_string,
+                    pair(ws_0, tag(")")),
+                ),
+            ),
+            SinkTarget::File,
+        ),
+        map(
+            preceded(
+                kw("KAFKA"),
+                delimited(
+                    tuple((ws_0, tag("("), ws_0)),
+                    separated_list0(
+                        tuple((ws_0, tag(","), ws_0)),
+                        separated_pair(
+                            identifier_str,
+                            tuple((ws_0, tag("="), ws_0)),
+                            quoted_string,
+                        ),
+                    ),
+                    pair(ws_0, tag(")")),
+                ),
+            ),
+            SinkTarget::Kafka,
+        ),
+    ))(input)
+}
+
 fn column_spec(input: &str) -> ParserResult<(String, DataType)> {
     separated_pair(identifier_str, ws_0, datatype)(input)
 }
 
+/// ie `user.address.city TEXT` or `city TEXT PATH '$.user.address.city'`
+fn external_column_spec(input: &str) -> ParserResult<ExternalColumn> {
+    map(
+        tuple((
+            separated_list1(tag("."), identifier_str),
+            preceded(ws_0, datatype),
+            opt(preceded(tuple((ws_0, kw("PATH"), ws_0)), json_path)),
+        )),
+        |(name, datatype, path)| ExternalColumn {
+            name: name.join("."),
+            datatype,
+            path,
+        },
+    )(input)
+}
+
 fn create_view(input: &str) -> ParserResult<Statement> {
     map(
         preceded(
             pair(ws_0, kw("VIEW")),
-            cut(tuple((
-                ws_0,
-                qualified_reference,
-                ws_0,
-                kw("AS"),
-                ws_0,
-                and_recognise(select),
-            ))),
+            cut(verify(view_definition, |view| view.state_ttl.is_none())),
         ),
-        |(_, (db_name, table_name), _, _, _, (query, query_sql))| {
-            Statement::CreateView(CreateView {
-                database: db_name,
-                name: table_name,
-                sql: query_sql.to_string(),
-                query,
+        Statement::CreateView,
+    )(input)
+}
+
+/// CREATE MATERIALIZED VIEW foo [WITH (STATE_TTL = '1 hour', TIME_COLUMN = 'bar')] AS select ...
+fn create_materialized_view(input: &str) -> ParserResult<Statement> {
+    map(
+        preceded(
+            tuple((ws_0, kw("MATERIALIZED"), ws_0, kw("VIEW"))),
+            cut(view_definition),
+        ),
+        Statement::CreateMaterializedView,
+    )(input)
+}
+
+fn view_definition(input: &str) -> ParserResult<CreateView> {
+    map(
+        tuple((
+            ws_0,
+            qualified_reference,
+            opt(preceded(ws_0, state_ttl)),
+            ws_0,
+            kw("AS"),
+            ws_0,
+            and_recognise(select),
+        )),
+        |(_, (db_name, table_name), state_ttl, _, _, _, (query, query_sql))| CreateView {
+            database: db_name,
+            name: table_name,
+            sql: query_sql.to_string(),
+            query,
+            state_ttl,
+        },
+    )(input)
+}
+
+enum StateTtlOption {
+    Ttl(i64),
+    TimeColumn(String),
+    LateData(LateData),
+}
+
+/// WITH (STATE_TTL = '1 hour', TIME_COLUMN = 'bar' [, LATE_DATA = 'drop']), the ttl and time
+/// column are required, late data defaults to being dropped.
+fn state_ttl(input: &str) -> ParserResult<StateTtl> {
+    map_opt(
+        delimited(
+            tuple((kw("WITH"), ws_0, tag("("), ws_0)),
+            separated_list1(
+                tuple((ws_0, tag(","), ws_0)),
+                alt((
+                    map_opt(
+                        preceded(
+                            tuple((kw("STATE_TTL"), ws_0, tag("="), ws_0)),
+                            quoted_string,
+                        ),
+                        |ttl| {
+                            parse_duration_ms(&ttl)
+                                .filter(|ttl| *ttl > 0)
+                                .map(StateTtlOption::Ttl)
+                        },
+                    ),
+                    map(
+                        preceded(
+                            tuple((kw("TIME_COLUMN"), ws_0, tag("="), ws_0)),
+                            quoted_string,
+                        ),
+                        StateTtlOption::TimeColumn,
+                    ),
+                    map_opt(
+                        preceded(
+                            tuple((kw("LATE_DATA"), ws_0, tag("="), ws_0)),
+                            quoted_string,
+                        ),
+                        |late_data| match late_data.to_lowercase().as_str() {
+                            "drop" => Some(StateTtlOption::LateData(LateData::Drop)),
+                            "corrections" => Some(StateTtlOption::LateData(LateData::Corrections)),
+                            "retract" => Some(StateTtlOption::LateData(LateData::Retract)),
+                            _ => None,
+                        },
+                    ),
+                )),
+            ),
+            tuple((ws_0, tag(")"))),
+        ),
+        |options| {
+            let mut ttl_ms = None;
+            let mut time_column = None;
+            let mut late_data = LateData::Drop;
+            for option in options {
+                match option {
+                    StateTtlOption::Ttl(ttl) => ttl_ms = Some(ttl),
+                    StateTtlOption::TimeColumn(column) => time_column = Some(column),
+                    StateTtlOption::LateData(policy) => late_data = policy,
+                }
+            }
+            Some(StateTtl {
+                ttl_ms: ttl_ms?,
+                time_column: time_column?,
+                late_data,
             })
         },
     )(input)
@@ -80,7 +480,8 @@ fn create_view(input: &str) -> ParserResult<Statement> {
 mod tests {
     use super::*;
     use ast::expr::{Expression, NamedExpression};
-    use ast::rel::logical::{LogicalOperator, Project};
+    use ast::rel::logical::{ErrorPolicy, FileFormat, LogicalOperator, Project, SerdeOptions};
+    use ast::statement::CallProcedure;
 
     #[test]
     fn test_create_database() {
@@ -104,9 +505,137 @@ mod tests {
                 columns: vec![
                     ("c1".to_string(), DataType::Integer),
                     ("c2".to_string(), DataType::Boolean)
-                ]
+                ],
+                primary_key: None,
+                checksums: false,
+                null_bitmap: false,
+            })
+        );
+        assert_eq!(
+            create("Create table bar (c1 INT) with (checksum = true)")
+                .unwrap()
+                .1,
+            Statement::CreateTable(CreateTable {
+                database: None,
+                name: "bar".to_string(),
+                columns: vec![("c1".to_string(), DataType::Integer)],
+                primary_key: None,
+                checksums: true,
+                null_bitmap: false,
             })
         );
+        assert_eq!(
+            create("Create table bar (c1 INT) with (null_bitmap = true, checksum = true)")
+                .unwrap()
+                .1,
+            Statement::CreateTable(CreateTable {
+                database: None,
+                name: "bar".to_string(),
+                columns: vec![("c1".to_string(), DataType::Integer)],
+                primary_key: None,
+                checksums: true,
+                null_bitmap: true,
+            })
+        );
+        assert_eq!(
+            create("Create table bar (c1 INT, c2 TEXT, primary key (c2 desc, c1))")
+                .unwrap()
+                .1,
+            Statement::CreateTable(CreateTable {
+                database: None,
+                name: "bar".to_string(),
+                columns: vec![
+                    ("c1".to_string(), DataType::Integer),
+                    ("c2".to_string(), DataType::Text)
+                ],
+                primary_key: Some(vec![
+                    ("c2".to_string(), SortOrder::Desc),
+                    ("c1".to_string(), SortOrder::Asc)
+                ]),
+                checksums: false,
+                null_bitmap: false,
+            })
+        );
+        assert!(create("Create table bar (c1 INT, primary key (c1), primary key (c1))").is_err());
+    }
+
+    #[test]
+    fn test_create_external_table() {
+        assert_eq!(
+            create(r#"Create external table foo.bar (c1 INT, c2 TEXT) from "data/""#)
+                .unwrap()
+                .1,
+            Statement::CreateExternalTable(CreateExternalTable {
+                database: Some("foo".to_string()),
+                name: "bar".to_string(),
+                directory: "data/".to_string(),
+                serde_options: SerdeOptions::default(),
+                columns: ExternalColumns::Given(vec![
+                    ExternalColumn {
+                        name: "c1".to_string(),
+                        datatype: DataType::Integer,
+                        path: None
+                    },
+                    ExternalColumn {
+                        name: "c2".to_string(),
+                        datatype: DataType::Text,
+                        path: None
+                    },
+                ])
+            })
+        );
+
+        assert_eq!(
+            create(
+                r#"CREATE EXTERNAL TABLE bar (user.address.city TEXT, zip INT PATH '$.user["zip code"]') FROM 'data/'"#
+            )
+            .unwrap()
+            .1,
+            Statement::CreateExternalTable(CreateExternalTable {
+                database: None,
+                name: "bar".to_string(),
+                directory: "data/".to_string(),
+                serde_options: SerdeOptions::default(),
+                columns: ExternalColumns::Given(vec![
+                    ExternalColumn {
+                        name: "user.address.city".to_string(),
+                        datatype: DataType::Text,
+                        path: None
+                    },
+                    ExternalColumn {
+                        name: "zip".to_string(),
+                        datatype: DataType::Integer,
+                        path: Some(r#"$.user["zip code"]"#.to_string())
+                    },
+                ])
+            })
+        );
+
+        assert_eq!(
+            create(r#"CREATE EXTERNAL TABLE bar FROM 'data/' WITH (format='json') AUTO SAMPLE 5"#)
+                .unwrap()
+                .1,
+            Statement::CreateExternalTable(CreateExternalTable {
+                database: None,
+                name: "bar".to_string(),
+                directory: "data/".to_string(),
+                serde_options: SerdeOptions {
+                    delimiter: b',',
+                    format: FileFormat::Json,
+                    on_error: ErrorPolicy::Fail,
+                    resume: false,
+                    max_rows_per_second: None,
+                    max_batch_rows: None,
+                    changes: None,
+                    snapshot: None
+                },
+                columns: ExternalColumns::Auto(5)
+            })
+        );
+
+        // Needs exactly one of a column list or AUTO
+        assert!(create(r#"CREATE EXTERNAL TABLE bar FROM 'data/'"#).is_err());
+        assert!(create(r#"CREATE EXTERNAL TABLE bar (c1 INT) FROM 'data/' AUTO"#).is_err());
     }
 
     #[test]
@@ -124,8 +653,179 @@ mod tests {
                         expression: Expression::from(1)
                     }],
                     source: Box::new(Default::default())
-                })
+                }),
+                state_ttl: None
             })
         );
     }
+
+    #[test]
+    fn test_create_event() {
+        assert_eq!(
+            create("CREATE EVENT db.cleanup ON SCHEDULE EVERY '5 minutes' DO CALL expire_rows")
+                .unwrap()
+                .1,
+            Statement::CreateEvent(CreateEvent {
+                database: Some("db".to_string()),
+                name: "cleanup".to_string(),
+                every_ms: 300_000,
+                statement: Box::new(Statement::CallProcedure(CallProcedure {
+                    database: None,
+                    name: "expire_rows".to_string()
+                })),
+                sql: "CALL expire_rows".to_string()
+            })
+        );
+
+        assert!(create("CREATE EVENT e ON SCHEDULE EVERY 'often' DO SELECT 1").is_err());
+        assert!(create("CREATE EVENT e ON SCHEDULE EVERY '0 minutes' DO SELECT 1").is_err());
+        assert!(create(
+            "CREATE EVENT e ON SCHEDULE EVERY '1h' DO CREATE EVENT f ON SCHEDULE EVERY '1h' DO SELECT 1"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_create_policy() {
+        assert_eq!(
+            create("CREATE POLICY tenants ON db.orders USING ( tenant = current_user() )")
+                .unwrap()
+                .1,
+            Statement::CreatePolicy(CreatePolicy {
+                name: "tenants".to_string(),
+                database: Some("db".to_string()),
+                table: "orders".to_string(),
+                predicate: expression("tenant = current_user()").unwrap().1,
+                predicate_sql: "tenant = current_user()".to_string()
+            })
+        );
+
+        assert!(create("CREATE POLICY tenants ON orders").is_err());
+    }
+
+    #[test]
+    fn test_create_index() {
+        assert_eq!(
+            create("CREATE INDEX orders_customer ON db.orders ( customer )")
+                .unwrap()
+                .1,
+            Statement::CreateIndex(CreateIndex {
+                name: "orders_customer".to_string(),
+                database: Some("db".to_string()),
+                table: "orders".to_string(),
+                column: "customer".to_string()
+            })
+        );
+
+        assert!(create("CREATE INDEX orders_customer ON orders").is_err());
+        assert!(create("CREATE INDEX orders_customer ON orders (a, b)").is_err());
+    }
+
+    #[test]
+    fn test_create_sink() {
+        assert_eq!(
+            create("CREATE SINK s FROM foo.bar INTO FILE('out.json') FORMAT JSON")
+                .unwrap()
+                .1,
+            Statement::CreateSink(CreateSink {
+                database: None,
+                name: "s".to_string(),
+                source_database: Some("foo".to_string()),
+                source: "bar".to_string(),
+                target: SinkTarget::File("out.json".to_string())
+            })
+        );
+
+        assert_eq!(
+            create("CREATE SINK s FROM bar INTO KAFKA(brokers = 'localhost:9092', topic = 'bar')")
+                .unwrap()
+                .1,
+            Statement::CreateSink(CreateSink {
+                database: None,
+                name: "s".to_string(),
+                source_database: None,
+                source: "bar".to_string(),
+                target: SinkTarget::Kafka(vec![
+                    ("brokers".to_string(), "localhost:9092".to_string()),
+                    ("topic".to_string(), "bar".to_string())
+                ])
+            })
+        );
+    }
+
+    #[test]
+    fn test_create_materialized_view() {
+        assert_eq!(
+            create("CREATE MATERIALIZED VIEW bar AS select 1")
+                .unwrap()
+                .1,
+            Statement::CreateMaterializedView(CreateView {
+                database: None,
+                name: "bar".to_string(),
+                sql: "select 1".to_string(),
+                query: LogicalOperator::Project(Project {
+                    distinct: false,
+                    expressions: vec![NamedExpression {
+                        alias: None,
+                        expression: Expression::from(1)
+                    }],
+                    source: Box::new(Default::default())
+                }),
+                state_ttl: None
+            })
+        );
+    }
+
+    #[test]
+    fn test_create_materialized_view_with_state_ttl() {
+        let statement = create(
+            "CREATE MATERIALIZED VIEW bar WITH (state_ttl = '1 hour', time_column = 'bucket') \
+             AS select 1",
+        )
+        .unwrap()
+        .1;
+        if let Statement::CreateMaterializedView(create_view) = statement {
+            assert_eq!(
+                create_view.state_ttl,
+                Some(StateTtl {
+                    ttl_ms: 3_600_000,
+                    time_column: "bucket".to_string(),
+                    late_data: LateData::Drop
+                })
+            );
+        } else {
+            panic!("Expected a materialized view")
+        }
+
+        let statement = create(
+            "CREATE MATERIALIZED VIEW bar WITH (late_data = 'Retract', state_ttl = '5m', \
+             time_column = 'bucket') AS select 1",
+        )
+        .unwrap()
+        .1;
+        if let Statement::CreateMaterializedView(create_view) = statement {
+            assert_eq!(
+                create_view.state_ttl,
+                Some(StateTtl {
+                    ttl_ms: 300_000,
+                    time_column: "bucket".to_string(),
+                    late_data: LateData::Retract
+                })
+            );
+        } else {
+            panic!("Expected a materialized view")
+        }
+
+        assert!(
+            create("CREATE MATERIALIZED VIEW bar WITH (state_ttl = '1 hour') AS select 1").is_err()
+        );
+        assert!(create(
+            "CREATE MATERIALIZED VIEW bar WITH (state_ttl = '0', time_column = 'b') AS select 1"
+        )
+        .is_err());
+        assert!(
+            create("CREATE VIEW bar WITH (state_ttl = '1h', time_column = 'b') AS select 1")
+                .is_err()
+        );
+    }
 }
@@ -25,7 +25,7 @@ pub fn delete(input: &str) -> ParserResult<LogicalOperator> {
             // For the from portion of the delete we should wrap the table in an alias to support
             // qualified references in the where clauses
             let table_alias =
-                if let LogicalOperator::TableReference(TableReference { database: _, table }) =
+                if let LogicalOperator::TableReference(TableReference { table, .. }) =
                     &table_reference
                 {
                     table
@@ -42,6 +42,7 @@ pub fn delete(input: &str) -> ParserResult<LogicalOperator> {
                 query = LogicalOperator::Filter(Filter {
                     predicate,
                     source: Box::new(query),
+                    pinned: false,
                 });
             }
 
@@ -55,6 +56,8 @@ pub fn delete(input: &str) -> ParserResult<LogicalOperator> {
 
             LogicalOperator::TableInsert(TableInsert {
                 table: Box::new(table_reference),
+                columns: vec![],
+                json_schemas: vec![],
                 source: Box::new(LogicalOperator::NegateFreq(Box::from(query))),
             })
         },
@@ -64,7 +67,12 @@ pub fn delete(input: &str) -> ParserResult<LogicalOperator> {
 /// Parse as a table_reference
 fn table_reference(input: &str) -> ParserResult<LogicalOperator> {
     map(qualified_reference, |(database, table)| {
-        LogicalOperator::TableReference(TableReference { database, table })
+        LogicalOperator::TableReference(TableReference {
+            database,
+            table,
+            sample: None,
+            as_of: None,
+        })
     })(input)
 }
 
@@ -77,12 +85,16 @@ mod tests {
         let table_ref = LogicalOperator::TableReference(TableReference {
             database: None,
             table: "foo".to_string(),
+            sample: None,
+            as_of: None,
         });
 
         assert_eq!(
             delete("delete from foo limit 1").unwrap().1,
             LogicalOperator::TableInsert(TableInsert {
                 table: Box::new(table_ref.clone()),
+                columns: vec![],
+                json_schemas: vec![],
                 source: Box::new(LogicalOperator::NegateFreq(Box::new(
                     LogicalOperator::Limit(Limit {
                         offset: 0,
@@ -1,14 +1,17 @@
 use crate::atoms::{identifier_str, kw, qualified_reference};
 use crate::whitespace::ws_0;
 use crate::ParserResult;
-use ast::statement::{DropTable, Statement};
+use ast::statement::{DropEvent, DropPolicy, DropProcedure, DropSink, DropTable, Statement};
 use nom::branch::alt;
 use nom::combinator::{cut, map};
 use nom::sequence::{preceded, tuple};
 
 /// Parses a drop statement
 pub fn drop_(input: &str) -> ParserResult<Statement> {
-    preceded(kw("DROP"), cut(alt((database, table))))(input)
+    preceded(
+        kw("DROP"),
+        cut(alt((database, table, sink, procedure, event, policy))),
+    )(input)
 }
 
 fn database(input: &str) -> ParserResult<Statement> {
@@ -30,6 +33,49 @@ fn table(input: &str) -> ParserResult<Statement> {
     )(input)
 }
 
+fn sink(input: &str) -> ParserResult<Statement> {
+    map(
+        tuple((ws_0, kw("SINK"), ws_0, qualified_reference)),
+        |(_, _, _, (database, name))| Statement::DropSink(DropSink { database, name }),
+    )(input)
+}
+
+fn procedure(input: &str) -> ParserResult<Statement> {
+    map(
+        tuple((ws_0, kw("PROCEDURE"), ws_0, qualified_reference)),
+        |(_, _, _, (database, name))| Statement::DropProcedure(DropProcedure { database, name }),
+    )(input)
+}
+
+fn event(input: &str) -> ParserResult<Statement> {
+    map(
+        tuple((ws_0, kw("EVENT"), ws_0, qualified_reference)),
+        |(_, _, _, (database, name))| Statement::DropEvent(DropEvent { database, name }),
+    )(input)
+}
+
+fn policy(input: &str) -> ParserResult<Statement> {
+    map(
+        tuple((
+            ws_0,
+            kw("POLICY"),
+            ws_0,
+            identifier_str,
+            ws_0,
+            kw("ON"),
+            ws_0,
+            qualified_reference,
+        )),
+        |(_, _, _, name, _, _, _, (database, table))| {
+            Statement::DropPolicy(DropPolicy {
+                name,
+                database,
+                table,
+            })
+        },
+    )(input)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -60,4 +106,49 @@ mod tests {
             })
         );
     }
+
+    #[test]
+    fn test_drop_sink() {
+        assert_eq!(
+            drop_("drop sink foo.bar").unwrap().1,
+            Statement::DropSink(DropSink {
+                database: Some("foo".to_string()),
+                name: "bar".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_drop_procedure() {
+        assert_eq!(
+            drop_("drop procedure p").unwrap().1,
+            Statement::DropProcedure(DropProcedure {
+                database: None,
+                name: "p".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_drop_event() {
+        assert_eq!(
+            drop_("drop event db.e").unwrap().1,
+            Statement::DropEvent(DropEvent {
+                database: Some("db".to_string()),
+                name: "e".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_drop_policy() {
+        assert_eq!(
+            drop_("drop policy tenants on db.orders").unwrap().1,
+            Statement::DropPolicy(DropPolicy {
+                name: "tenants".to_string(),
+                database: Some("db".to_string()),
+                table: "orders".to_string()
+            })
+        );
+    }
 }
@@ -1,15 +1,21 @@
 use crate::atoms::{as_clause, identifier_str, kw};
 use crate::literals::{datatype, literal};
+use crate::select::{order_clause, select};
 use crate::whitespace::ws_0;
 use crate::ParserResult;
-use ast::expr::{Cast, ColumnReference, Expression, FunctionCall, NamedExpression, SortExpression};
-use data::SortOrder;
+use ast::expr::{
+    Cast, ColumnReference, Expression, FunctionCall, NamedExpression, Parameter, SortExpression,
+    Subquery, WindowFrame, WindowFunction,
+};
+use ast::rel::logical::LogicalOperator;
+use data::{DataType, SortOrder};
 use nom::branch::{alt, Alt};
 use nom::bytes::complete::tag;
-use nom::combinator::{cut, map, opt, value};
+use nom::character::complete::digit1;
+use nom::combinator::{cut, map, map_res, opt, value};
 use nom::error::VerboseError;
-use nom::multi::{many0, separated_list0};
-use nom::sequence::{delimited, pair, preceded, separated_pair, tuple};
+use nom::multi::{many0, separated_list0, separated_list1};
+use nom::sequence::{delimited, pair, preceded, separated_pair, terminated, tuple};
 
 /// Parses a bog standard expression, ie 1 + 2
 /// operators precedence according to https://dev.mysql.com/doc/refman/8.0/en/operator-precedence.html
@@ -41,7 +47,7 @@ pub fn sort_expression(input: &str) -> ParserResult<SortExpression> {
     ))(input)
 }
 
-fn sort_order(input: &str) -> ParserResult<SortOrder> {
+pub fn sort_order(input: &str) -> ParserResult<SortOrder> {
     alt((
         value(SortOrder::Asc, kw("ASC")),
         value(SortOrder::Desc, kw("DESC")),
@@ -75,6 +81,18 @@ fn expression_2(input: &str) -> ParserResult<Expression> {
     ))(input)
 }
 
+/// The optional trailing part of a level 3 expression
+enum Tail3 {
+    Between(Expression, Expression),
+    In(bool, InTarget),
+}
+
+/// The right hand side of an [NOT] IN expression
+enum InTarget {
+    Subquery(LogicalOperator),
+    List(Vec<Expression>),
+}
+
 fn expression_3(input: &str) -> ParserResult<Expression> {
     // Conceptually you can use between for boolean expressions but then the parsing
     // gets a little weird.
@@ -82,30 +100,85 @@ fn expression_3(input: &str) -> ParserResult<Expression> {
     // How would we parse that. you could also nest the betweens, ie
     // SELECT a between b between c and d and c between d and e
     // Again just crazy so we wont bother with these edge cases for now.
-    alt((
-        map(
-            tuple((
+    // The between/in tails are parsed after the first expression so we don't end up parsing
+    // it multiple times.
+    let between = map(
+        preceded(
+            kw("BETWEEN"),
+            cut(tuple((
+                ws_0,
                 expression_5,
                 ws_0,
-                kw("BETWEEN"),
-                cut(tuple((
-                    ws_0,
-                    expression_5,
-                    ws_0,
-                    kw("AND"),
-                    ws_0,
-                    expression_5,
-                ))),
-            )),
-            |(e1, _, _, (_, e2, _, _, _, e3))| {
-                Expression::FunctionCall(FunctionCall {
-                    function_name: "between".to_string(),
-                    args: vec![e1, e2, e3],
-                })
-            },
+                kw("AND"),
+                ws_0,
+                expression_5,
+            ))),
         ),
-        expression_5,
-    ))(input)
+        |(_, e2, _, _, _, e3)| Tail3::Between(e2, e3),
+    );
+    let in_list = delimited(
+        pair(tag("("), ws_0),
+        separated_list1(tuple((ws_0, tag(","), ws_0)), expression),
+        pair(ws_0, tag(")")),
+    );
+    let in_tail = map(
+        tuple((
+            opt(pair(kw("NOT"), ws_0)),
+            kw("IN"),
+            preceded(
+                ws_0,
+                cut(alt((
+                    map(subquery, InTarget::Subquery),
+                    map(in_list, InTarget::List),
+                ))),
+            ),
+        )),
+        |(not, _, target)| Tail3::In(not.is_some(), target),
+    );
+
+    map(
+        pair(expression_5, opt(preceded(ws_0, alt((between, in_tail))))),
+        |(e1, tail)| match tail {
+            Some(Tail3::Between(e2, e3)) => Expression::FunctionCall(FunctionCall {
+                function_name: "between".to_string(),
+                args: vec![e1, e2, e3],
+            }),
+            Some(Tail3::In(negated, InTarget::Subquery(query))) => {
+                Expression::Subquery(Box::new(Subquery {
+                    negated,
+                    operand: Some(e1),
+                    scalar: false,
+                    query,
+                }))
+            }
+            // An in list is just sugar for a chain of ORs, the planner will turn any long
+            // lists back into something more efficient.
+            Some(Tail3::In(negated, InTarget::List(list))) => {
+                let mut list = list.into_iter().map(|item| {
+                    Expression::FunctionCall(FunctionCall {
+                        function_name: "=".to_string(),
+                        args: vec![e1.clone(), item],
+                    })
+                });
+                let first = list.next().unwrap();
+                let ors = list.fold(first, |acc, eq| {
+                    Expression::FunctionCall(FunctionCall {
+                        function_name: "or".to_string(),
+                        args: vec![acc, eq],
+                    })
+                });
+                if negated {
+                    Expression::FunctionCall(FunctionCall {
+                        function_name: "not".to_string(),
+                        args: vec![ors],
+                    })
+                } else {
+                    ors
+                }
+            }
+            None => e1,
+        },
+    )(input)
 }
 
 fn expression_5(input: &str) -> ParserResult<Expression> {
@@ -177,24 +250,54 @@ fn expression_5(input: &str) -> ParserResult<Expression> {
 }
 
 fn expression_6(input: &str) -> ParserResult<Expression> {
-    infix_many((tag("+"), tag("-")), expression_7)(input)
+    infix(tag("|"), expression_7)(input)
 }
 
 fn expression_7(input: &str) -> ParserResult<Expression> {
-    infix_many((tag("*"), tag("/")), expression_8)(input)
+    infix(tag("&"), expression_8)(input)
 }
 
 fn expression_8(input: &str) -> ParserResult<Expression> {
-    infix_many((tag("->>"), tag("->")), expression_9)(input)
+    infix_many((tag("<<"), tag(">>")), expression_9)(input)
 }
 
 fn expression_9(input: &str) -> ParserResult<Expression> {
+    infix_many((tag("+"), tag("-")), expression_10)(input)
+}
+
+fn expression_10(input: &str) -> ParserResult<Expression> {
+    infix_many((tag("*"), tag("/")), expression_11)(input)
+}
+
+fn expression_11(input: &str) -> ParserResult<Expression> {
+    infix(tag("^"), expression_12)(input)
+}
+
+fn expression_12(input: &str) -> ParserResult<Expression> {
+    infix_many((tag("->>"), tag("->")), expression_13)(input)
+}
+
+fn expression_13(input: &str) -> ParserResult<Expression> {
+    alt((
+        map(preceded(pair(tag("~"), ws_0), expression_13), |expr| {
+            Expression::FunctionCall(FunctionCall {
+                function_name: "~".to_string(),
+                args: vec![expr],
+            })
+        }),
+        expression_14,
+    ))(input)
+}
+
+fn expression_14(input: &str) -> ParserResult<Expression> {
     alt((
-        count_star,
-        function_call,
+        exists,
+        window_function,
         cast,
         literal,
+        parameter,
         column_reference,
+        scalar_subquery,
         brackets,
     ))(input)
 }
@@ -248,13 +351,19 @@ fn infix<'a, Op: Fn(&'a str) -> ParserResult<&'a str>>(
 fn function_call(input: &str) -> ParserResult<Expression> {
     map(
         tuple((
+            opt(terminated(identifier_str, tag("."))),
             identifier_str,
             tuple((ws_0, tag("("), ws_0)),
             comma_sep_expressions,
             ws_0,
             tag(")"),
         )),
-        |(function_name, _, params, _, _)| {
+        |(namespace, function_name, _, params, _, _)| {
+            // Functions registered by embedders are namespaced, ie ext.my_func(...)
+            let function_name = match namespace {
+                Some(namespace) => format!("{}.{}", namespace, function_name),
+                None => function_name,
+            };
             Expression::FunctionCall(FunctionCall {
                 function_name,
                 args: params,
@@ -263,6 +372,69 @@ fn function_call(input: &str) -> ParserResult<Expression> {
     )(input)
 }
 
+/// A function call with an optional OVER clause making it a window function, ie
+/// sum(amount) OVER (PARTITION BY customer ORDER BY day ROWS BETWEEN 6 PRECEDING AND CURRENT ROW)
+fn window_function(input: &str) -> ParserResult<Expression> {
+    map(
+        pair(
+            alt((count_star, function_call)),
+            opt(preceded(
+                tuple((ws_0, kw("OVER"), ws_0, tag("("), ws_0)),
+                cut(tuple((
+                    opt(terminated(
+                        preceded(
+                            tuple((kw("PARTITION"), ws_0, kw("BY"), ws_0)),
+                            separated_list1(tuple((ws_0, tag(","), ws_0)), expression),
+                        ),
+                        ws_0,
+                    )),
+                    opt(terminated(order_clause, ws_0)),
+                    opt(terminated(window_frame, ws_0)),
+                    tag(")"),
+                ))),
+            )),
+        ),
+        |(function, over)| match over {
+            Some((partition_by, order_by, frame, _)) => {
+                Expression::WindowFunction(Box::new(WindowFunction {
+                    function,
+                    partition_by: partition_by.unwrap_or_default(),
+                    order_by: order_by.unwrap_or_default(),
+                    frame: frame.unwrap_or(WindowFrame::Range),
+                }))
+            }
+            None => function,
+        },
+    )(input)
+}
+
+/// ROWS UNBOUNDED PRECEDING | ROWS BETWEEN (n | UNBOUNDED) PRECEDING AND CURRENT ROW
+fn window_frame(input: &str) -> ParserResult<WindowFrame> {
+    let preceding = || {
+        terminated(
+            alt((
+                value(None, kw("UNBOUNDED")),
+                map(map_res(digit1, str::parse), Some),
+            )),
+            pair(ws_0, kw("PRECEDING")),
+        )
+    };
+    map(
+        preceded(
+            pair(kw("ROWS"), ws_0),
+            cut(alt((
+                delimited(
+                    pair(kw("BETWEEN"), ws_0),
+                    preceding(),
+                    tuple((ws_0, kw("AND"), ws_0, kw("CURRENT"), ws_0, kw("ROW"))),
+                ),
+                preceding(),
+            ))),
+        ),
+        WindowFrame::Rows,
+    )(input)
+}
+
 /// Due to some sql weirdness count(*) is a thing, the star doesn't
 /// really mean anything and its semantically equivalent to count()
 fn count_star(input: &str) -> ParserResult<Expression> {
@@ -278,24 +450,64 @@ fn count_star(input: &str) -> ParserResult<Expression> {
 }
 
 fn cast(input: &str) -> ParserResult<Expression> {
-    preceded(
-        kw("CAST"),
-        cut(map(
-            tuple((
-                tuple((ws_0, tag("("), ws_0)),
-                expression,
-                tuple((ws_0, kw("AS"), ws_0)),
-                datatype,
-                pair(ws_0, tag(")")),
-            )),
-            |(_, expr, _, datatype, _)| {
-                Expression::Cast(Cast {
-                    expr: Box::new(expr),
-                    datatype,
-                })
-            },
+    let (input, try_cast) = alt((value(true, kw("TRY_CAST")), value(false, kw("CAST"))))(input)?;
+    cut(map(
+        tuple((
+            tuple((ws_0, tag("("), ws_0)),
+            expression,
+            tuple((ws_0, kw("AS"), ws_0)),
+            datatype,
+            pair(ws_0, tag(")")),
         )),
-    )(input)
+        move |(_, expr, _, datatype, _)| {
+            Expression::Cast(Cast {
+                expr: Box::new(expr),
+                datatype,
+                try_cast,
+            })
+        },
+    ))(input)
+}
+
+/// EXISTS (SELECT ...), NOT EXISTS is handled by the usual not operator.
+fn exists(input: &str) -> ParserResult<Expression> {
+    map(preceded(pair(kw("EXISTS"), ws_0), subquery), |query| {
+        Expression::Subquery(Box::new(Subquery {
+            negated: false,
+            operand: None,
+            scalar: false,
+            query,
+        }))
+    })(input)
+}
+
+/// (SELECT ...) used as a value
+fn scalar_subquery(input: &str) -> ParserResult<Expression> {
+    map(subquery, |query| {
+        Expression::Subquery(Box::new(Subquery {
+            negated: false,
+            operand: None,
+            scalar: true,
+            query,
+        }))
+    })(input)
+}
+
+/// A ? placeholder for a prepared statement.
+/// As we can't count them as we go, the index is initially the position from the end of the
+/// input, they're then renumbered once the whole statement has been parsed.
+fn parameter(input: &str) -> ParserResult<Expression> {
+    map(tag("?"), |_| {
+        Expression::Parameter(Parameter {
+            index: input.len(),
+            datatype: DataType::Null,
+        })
+    })(input)
+}
+
+/// A bracketed select used as part of an expression
+fn subquery(input: &str) -> ParserResult<LogicalOperator> {
+    delimited(pair(tag("("), ws_0), select, cut(pair(ws_0, tag(")"))))(input)
 }
 
 fn brackets(input: &str) -> ParserResult<Expression> {
@@ -344,6 +556,7 @@ fn column_reference(input: &str) -> ParserResult<Expression> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ast::rel::logical::Project;
     use data::{DataType, Datum};
 
     #[test]
@@ -371,6 +584,14 @@ mod tests {
                 args: vec![Expression::from(1), Expression::from(2),]
             })
         );
+
+        assert_eq!(
+            expression("Ext.Foo(1)").unwrap().1,
+            Expression::FunctionCall(FunctionCall {
+                function_name: "ext.foo".to_string(),
+                args: vec![Expression::from(1)]
+            })
+        );
     }
 
     #[test]
@@ -414,6 +635,65 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_bitwise_expression() {
+        let call = |function_name: &str, args: Vec<Expression>| {
+            Expression::FunctionCall(FunctionCall {
+                function_name: function_name.to_string(),
+                args,
+            })
+        };
+        assert_eq!(
+            expression("1 | 2 & 3 << 1 + 1").unwrap().1,
+            // Should be (1 | (2 & (3 << (1 + 1))))
+            call(
+                "|",
+                vec![
+                    Expression::from(1),
+                    call(
+                        "&",
+                        vec![
+                            Expression::from(2),
+                            call(
+                                "<<",
+                                vec![
+                                    Expression::from(3),
+                                    call("+", vec![Expression::from(1), Expression::from(1)])
+                                ]
+                            )
+                        ]
+                    )
+                ]
+            )
+        );
+
+        assert_eq!(
+            expression("~1 ^ 2 * 3").unwrap().1,
+            // Should be ((~1 ^ 2) * 3)
+            call(
+                "*",
+                vec![
+                    call(
+                        "^",
+                        vec![call("~", vec![Expression::from(1)]), Expression::from(2)]
+                    ),
+                    Expression::from(3)
+                ]
+            )
+        );
+
+        assert_eq!(
+            expression("1 < 1 >> 2").unwrap().1,
+            call(
+                "<",
+                vec![
+                    Expression::from(1),
+                    call(">>", vec![Expression::from(1), Expression::from(2)])
+                ]
+            )
+        );
+    }
+
     #[test]
     fn test_named_expression() {
         let expression = Expression::Constant(Datum::Null, DataType::Null);
@@ -451,7 +731,17 @@ mod tests {
             expression("cast( null as decimal(1,2))").unwrap().1,
             Expression::Cast(Cast {
                 expr: Box::new(expr),
-                datatype: DataType::Decimal(1, 2)
+                datatype: DataType::Decimal(1, 2),
+                try_cast: false,
+            })
+        );
+
+        assert_eq!(
+            expression("TRY_CAST(\"a\" AS INT)").unwrap().1,
+            Expression::Cast(Cast {
+                expr: Box::new(Expression::from("a")),
+                datatype: DataType::Integer,
+                try_cast: true,
             })
         );
     }
@@ -609,6 +899,103 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_subquery_expressions() {
+        let query = LogicalOperator::Project(Project {
+            distinct: false,
+            expressions: vec![NamedExpression {
+                alias: None,
+                expression: Expression::from(1),
+            }],
+            source: Box::new(LogicalOperator::Single),
+        });
+
+        assert_eq!(
+            expression("EXISTS (select 1)").unwrap().1,
+            Expression::Subquery(Box::new(Subquery {
+                negated: false,
+                operand: None,
+                scalar: false,
+                query: query.clone(),
+            }))
+        );
+
+        assert_eq!(
+            expression("not exists(select 1)").unwrap().1,
+            Expression::FunctionCall(FunctionCall {
+                function_name: "not".to_string(),
+                args: vec![Expression::Subquery(Box::new(Subquery {
+                    negated: false,
+                    operand: None,
+                    scalar: false,
+                    query: query.clone(),
+                }))]
+            })
+        );
+
+        assert_eq!(
+            expression("2 not in (select 1)").unwrap().1,
+            Expression::Subquery(Box::new(Subquery {
+                negated: true,
+                operand: Some(Expression::from(2)),
+                scalar: false,
+                query: query.clone(),
+            }))
+        );
+
+        assert_eq!(
+            expression("( select 1 ) + 2").unwrap().1,
+            Expression::FunctionCall(FunctionCall {
+                function_name: "+".to_string(),
+                args: vec![
+                    Expression::Subquery(Box::new(Subquery {
+                        negated: false,
+                        operand: None,
+                        scalar: true,
+                        query,
+                    })),
+                    Expression::from(2)
+                ]
+            })
+        );
+        // Still just brackets
+        assert_eq!(
+            expression("(selected)").unwrap().1,
+            expression("selected").unwrap().1
+        );
+    }
+
+    #[test]
+    fn test_in_list_expressions() {
+        let eq = |item: i32| {
+            Expression::FunctionCall(FunctionCall {
+                function_name: "=".to_string(),
+                args: vec![Expression::from(2), Expression::from(item)],
+            })
+        };
+        let ors = Expression::FunctionCall(FunctionCall {
+            function_name: "or".to_string(),
+            args: vec![
+                Expression::FunctionCall(FunctionCall {
+                    function_name: "or".to_string(),
+                    args: vec![eq(1), eq(2)],
+                }),
+                eq(3),
+            ],
+        });
+
+        assert_eq!(expression("2 in (1)").unwrap().1, eq(1));
+        assert_eq!(expression("2 IN (1, 2,3)").unwrap().1, ors.clone());
+        assert_eq!(
+            expression("2 not in (1, 2, 3)").unwrap().1,
+            Expression::FunctionCall(FunctionCall {
+                function_name: "not".to_string(),
+                args: vec![ors],
+            })
+        );
+        assert!(expression("2 in ()").is_err());
+    }
+
     #[test]
     fn test_sort_expr() {
         let expr = Expression::ColumnReference(ColumnReference {
@@ -641,4 +1028,75 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_window_functions() {
+        let column = |alias: &str| {
+            Expression::ColumnReference(ColumnReference {
+                qualifier: None,
+                alias: alias.to_string(),
+                star: false,
+            })
+        };
+        let sum = Expression::FunctionCall(FunctionCall {
+            function_name: "sum".to_string(),
+            args: vec![column("amount")],
+        });
+
+        assert_eq!(
+            expression("sum(amount) OVER (PARTITION BY customer, region ORDER BY day DESC)")
+                .unwrap()
+                .1,
+            Expression::WindowFunction(Box::new(WindowFunction {
+                function: sum.clone(),
+                partition_by: vec![column("customer"), column("region")],
+                order_by: vec![SortExpression {
+                    ordering: SortOrder::Desc,
+                    expression: column("day"),
+                }],
+                frame: WindowFrame::Range,
+            }))
+        );
+
+        assert_eq!(
+            expression("count(*) over ()").unwrap().1,
+            Expression::WindowFunction(Box::new(WindowFunction {
+                function: Expression::FunctionCall(FunctionCall {
+                    function_name: "count".to_string(),
+                    args: vec![],
+                }),
+                partition_by: vec![],
+                order_by: vec![],
+                frame: WindowFrame::Range,
+            }))
+        );
+
+        assert_eq!(
+            expression("sum(amount) over (order by day rows between 2 preceding and current row)")
+                .unwrap()
+                .1,
+            Expression::WindowFunction(Box::new(WindowFunction {
+                function: sum.clone(),
+                partition_by: vec![],
+                order_by: vec![SortExpression {
+                    ordering: SortOrder::Asc,
+                    expression: column("day"),
+                }],
+                frame: WindowFrame::Rows(Some(2)),
+            }))
+        );
+
+        if let Expression::WindowFunction(window) =
+            expression("row_number() OVER (ROWS UNBOUNDED PRECEDING)")
+                .unwrap()
+                .1
+        {
+            assert_eq!(window.frame, WindowFrame::Rows(None));
+        } else {
+            panic!()
+        }
+
+        assert!(expression("sum(amount) OVER (PARTITION day)").is_err());
+        assert!(expression("sum(amount) OVER (ROWS 2 FOLLOWING)").is_err());
+    }
 }
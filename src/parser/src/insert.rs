@@ -1,41 +1,89 @@
-use crate::atoms::{kw, qualified_reference};
+use crate::atoms::{identifier_str, kw, qualified_reference, quoted_string};
 use crate::literals::literal;
-use crate::select::select;
+use crate::select::{select, serde_options};
 use crate::whitespace::ws_0;
 use crate::ParserResult;
 use ast::expr::Expression;
-use ast::rel::logical::{LogicalOperator, TableInsert, TableReference, Values};
+use ast::rel::logical::{FileCopy, LogicalOperator, TableInsert, TableReference, Values};
 use nom::branch::alt;
 use nom::bytes::complete::tag;
-use nom::combinator::{cut, map};
-use nom::multi::separated_list0;
-use nom::sequence::{pair, preceded, tuple};
+use nom::combinator::{cut, map, opt};
+use nom::multi::{separated_list0, separated_list1};
+use nom::sequence::{delimited, pair, preceded, tuple};
 
-/// Parses an insert statement
+/// Parses an insert statement, optionally naming the columns to insert into, ie
+/// INSERT INTO foo (a, c) SELECT ...
 pub fn insert(input: &str) -> ParserResult<LogicalOperator> {
     map(
         preceded(
             kw("INSERT"),
-            pair(
+            tuple((
                 cut(preceded(
                     tuple((ws_0, kw("INTO"), ws_0)),
                     qualified_reference,
                 )),
+                opt(preceded(ws_0, insert_columns)),
                 cut(preceded(ws_0, alt((select, values)))),
-            ),
+            )),
         ),
-        |((database, table_name), select)| {
+        |((database, table_name), columns, select)| {
             LogicalOperator::TableInsert(TableInsert {
                 table: Box::new(LogicalOperator::TableReference(TableReference {
                     database,
                     table: table_name,
+                    sample: None,
+                    as_of: None,
                 })),
+                columns: columns.unwrap_or_default(),
+                json_schemas: vec![],
                 source: Box::new(select),
             })
         },
     )(input)
 }
 
+/// Parses the list of columns being inserted into, ie "(a, c)"
+fn insert_columns(input: &str) -> ParserResult<Vec<String>> {
+    delimited(
+        pair(tag("("), ws_0),
+        separated_list1(tuple((ws_0, tag(","), ws_0)), identifier_str),
+        pair(ws_0, tag(")")),
+    )(input)
+}
+
+/// Parses a copy from a csv file into a table, ie
+/// COPY orders FROM 'data/orders.csv' WITH (delimiter = '|')
+pub fn copy(input: &str) -> ParserResult<LogicalOperator> {
+    map(
+        preceded(
+            kw("COPY"),
+            cut(tuple((
+                preceded(ws_0, qualified_reference),
+                preceded(tuple((ws_0, kw("FROM"), ws_0)), quoted_string),
+                opt(preceded(ws_0, serde_options)),
+            ))),
+        ),
+        |((database, table_name), path, serde_options)| {
+            LogicalOperator::TableInsert(TableInsert {
+                table: Box::new(LogicalOperator::TableReference(TableReference {
+                    database,
+                    table: table_name,
+                    sample: None,
+                    as_of: None,
+                })),
+                columns: vec![],
+                json_schemas: vec![],
+                source: Box::new(LogicalOperator::FileCopy(FileCopy {
+                    path,
+                    serde_options: serde_options.unwrap_or_default(),
+                    columns: vec![],
+                    dead_letters: None,
+                })),
+            })
+        },
+    )(input)
+}
+
 /// Parses a values clause.
 fn values(input: &str) -> ParserResult<LogicalOperator> {
     map(
@@ -73,7 +121,7 @@ fn values_row(input: &str) -> ParserResult<Vec<Expression>> {
 mod tests {
     use super::*;
     use ast::expr::{Expression, NamedExpression};
-    use ast::rel::logical::Project;
+    use ast::rel::logical::{Project, SerdeOptions};
 
     #[test]
     fn test_insert_from() {
@@ -82,8 +130,12 @@ mod tests {
             LogicalOperator::TableInsert(TableInsert {
                 table: Box::new(LogicalOperator::TableReference(TableReference {
                     database: None,
-                    table: "foo".to_string()
+                    table: "foo".to_string(),
+                    sample: None,
+                    as_of: None,
                 })),
+                columns: vec![],
+                json_schemas: vec![],
                 source: Box::new(LogicalOperator::Project(Project {
                     distinct: false,
                     expressions: vec![NamedExpression {
@@ -103,8 +155,12 @@ mod tests {
             LogicalOperator::TableInsert(TableInsert {
                 table: Box::new(LogicalOperator::TableReference(TableReference {
                     database: None,
-                    table: "foo".to_string()
+                    table: "foo".to_string(),
+                    sample: None,
+                    as_of: None,
                 })),
+                columns: vec![],
+                json_schemas: vec![],
                 source: Box::new(LogicalOperator::Values(Values {
                     fields: vec![],
                     data: vec![
@@ -115,4 +171,55 @@ mod tests {
             })
         );
     }
+
+    #[test]
+    fn test_copy() {
+        assert_eq!(
+            copy("copy db.foo from 'data/foo.csv' with (delimiter = '|')")
+                .unwrap()
+                .1,
+            LogicalOperator::TableInsert(TableInsert {
+                table: Box::new(LogicalOperator::TableReference(TableReference {
+                    database: Some("db".to_string()),
+                    table: "foo".to_string(),
+                    sample: None,
+                    as_of: None,
+                })),
+                columns: vec![],
+                json_schemas: vec![],
+                source: Box::new(LogicalOperator::FileCopy(FileCopy {
+                    path: "data/foo.csv".to_string(),
+                    serde_options: SerdeOptions {
+                        delimiter: b'|',
+                        ..SerdeOptions::default()
+                    },
+                    columns: vec![],
+                    dead_letters: None,
+                }))
+            })
+        );
+        assert!(copy("copy foo").is_err());
+    }
+
+    #[test]
+    fn test_insert_columns() {
+        assert_eq!(
+            insert("insert into foo ( a,C ) values (1, 2)").unwrap().1,
+            LogicalOperator::TableInsert(TableInsert {
+                table: Box::new(LogicalOperator::TableReference(TableReference {
+                    database: None,
+                    table: "foo".to_string(),
+                    sample: None,
+                    as_of: None,
+                })),
+                columns: vec!["a".to_string(), "c".to_string()],
+                json_schemas: vec![],
+                source: Box::new(LogicalOperator::Values(Values {
+                    fields: vec![],
+                    data: vec![vec![Expression::from(1), Expression::from(2)]]
+                }))
+            })
+        );
+        assert!(insert("insert into foo () values (1)").is_err());
+    }
 }
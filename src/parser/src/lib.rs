@@ -1,6 +1,6 @@
 use crate::whitespace::ws_0;
 use ast::expr::Expression;
-use ast::statement::Statement;
+use ast::statement::{ProcedureStatement, Statement};
 use nom::combinator::all_consuming;
 use nom::error::{convert_error, VerboseError};
 use nom::lib::std::fmt::{Display, Formatter};
@@ -15,6 +15,7 @@ mod drop;
 mod expression;
 mod insert;
 mod literals;
+mod procedure;
 mod select;
 mod show;
 mod statement;
@@ -28,7 +29,49 @@ type ParserResult<'a, T> = IResult<&'a str, T, VerboseError<&'a str>>;
 pub fn parse(input: &str) -> Result<Statement, ParseError> {
     let parser_result = all_consuming(delimited(ws_0, statement::statement, ws_0))(input);
 
-    parser_result.map(|(_, command)| command).map_err(|err| {
+    parser_result
+        .map(|(_, mut command)| {
+            number_parameters(&mut command);
+            command
+        })
+        .map_err(|err| {
+            match err {
+                nom::Err::Error(e) => ParseError::from(convert_error(input, e)),
+                nom::Err::Failure(e) => ParseError::from(convert_error(input, e)),
+                // We should only get an incomplete if we used the streaming parsers
+                nom::Err::Incomplete(_) => ParseError::from(String::from("Incomplete parsing")),
+            }
+        })
+}
+
+/// The parameters come out of the parser numbered by their distance from the end of the
+/// input, here we renumber them to be in order starting at zero.
+/// Some expressions get duplicated while parsing (ie ? IN (1, 2)) so we can have many
+/// parameters with the same position.
+fn number_parameters(statement: &mut Statement) {
+    let mut positions = vec![];
+    statement.visit_parameters_mut(&mut |expr| {
+        if let Expression::Parameter(parameter) = expr {
+            positions.push(parameter.index);
+        }
+    });
+    positions.sort_unstable_by(|a, b| b.cmp(a));
+    positions.dedup();
+    statement.visit_parameters_mut(&mut |expr| {
+        if let Expression::Parameter(parameter) = expr {
+            parameter.index = positions
+                .iter()
+                .position(|position| *position == parameter.index)
+                .unwrap()
+        }
+    });
+}
+
+/// Parses the body of a stored procedure, the statements between its BEGIN and END
+pub fn parse_procedure_body(input: &str) -> Result<Vec<ProcedureStatement>, ParseError> {
+    let parser_result = all_consuming(delimited(ws_0, procedure::procedure_body, ws_0))(input);
+
+    parser_result.map(|(_, body)| body).map_err(|err| {
         match err {
             nom::Err::Error(e) => ParseError::from(convert_error(input, e)),
             nom::Err::Failure(e) => ParseError::from(convert_error(input, e)),
@@ -74,8 +117,9 @@ impl Error for ParseError {}
 #[cfg(test)]
 mod tests {
     use super::*;
-    use ast::expr::{Expression, NamedExpression};
+    use ast::expr::{Expression, FunctionCall, NamedExpression, Parameter};
     use ast::rel::logical::{LogicalOperator, Project};
+    use data::DataType;
 
     #[test]
     fn test_statement_select() {
@@ -92,6 +136,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_statement_parameters() {
+        let parameter = |index| {
+            Expression::Parameter(Parameter {
+                index,
+                datatype: DataType::Null,
+            })
+        };
+        let eq = |a, b| {
+            Expression::FunctionCall(FunctionCall {
+                function_name: "=".to_string(),
+                args: vec![a, b],
+            })
+        };
+        assert_eq!(
+            parse("SELECT ? = 1 IN (?, 2)").unwrap(),
+            Statement::Query(LogicalOperator::Project(Project {
+                distinct: false,
+                expressions: vec![NamedExpression {
+                    expression: Expression::FunctionCall(FunctionCall {
+                        function_name: "or".to_string(),
+                        args: vec![
+                            eq(eq(parameter(0), Expression::from(1)), parameter(1)),
+                            eq(eq(parameter(0), Expression::from(1)), Expression::from(2)),
+                        ]
+                    }),
+                    alias: None
+                },],
+                source: Box::from(LogicalOperator::Single)
+            }))
+        );
+    }
+
     #[test]
     fn test_statement_err() {
         assert_eq!(
@@ -6,8 +6,9 @@ use data::DataType::Decimal;
 use data::{DataType, Datum, DECIMAL_MAX_PRECISION};
 use nom::branch::alt;
 use nom::bytes::complete::tag;
-use nom::combinator::{cut, map, value};
-use nom::sequence::{preceded, tuple};
+use nom::combinator::{cut, map, value, verify};
+use nom::multi::separated_list1;
+use nom::sequence::{delimited, preceded, tuple};
 
 pub fn literal(input: &str) -> ParserResult<Expression> {
     alt((
@@ -50,9 +51,30 @@ pub fn datatype(input: &str) -> ParserResult<DataType> {
         value(DataType::Json, kw("JSON")),
         value(DataType::Date, kw("DATE")),
         value(DataType::Timestamp, kw("TIMESTAMP")),
+        enum_datatype,
     ))(input)
 }
 
+/// ENUM('new', 'paid', 'void'), the labels have to be unique
+fn enum_datatype(input: &str) -> ParserResult<DataType> {
+    map(
+        delimited(
+            tuple((kw("ENUM"), ws_0, tag("("), ws_0)),
+            verify(
+                separated_list1(tuple((ws_0, tag(","), ws_0)), quoted_string),
+                |labels: &[String]| {
+                    labels
+                        .iter()
+                        .enumerate()
+                        .all(|(idx, label)| !labels[..idx].contains(label))
+                },
+            ),
+            tuple((ws_0, tag(")"))),
+        ),
+        |labels| DataType::new_enum(&labels),
+    )(input)
+}
+
 fn null_literal(input: &str) -> ParserResult<Expression> {
     value(
         Expression::Constant(Datum::Null, DataType::Null),
@@ -95,6 +117,7 @@ fn date_literal(input: &str) -> ParserResult<Expression> {
             Expression::Cast(Cast {
                 expr: Box::new(expr),
                 datatype: DataType::Date,
+                try_cast: false,
             })
         },
     )(input)
@@ -170,13 +193,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_enum_datatype_literals() {
+        let labels = vec!["new".to_string(), "it's".to_string()];
+        assert_eq!(
+            datatype(r#"enum ( 'new', "it's" )"#).unwrap().1,
+            DataType::new_enum(&labels)
+        );
+        // The type's display form parses back
+        assert_eq!(
+            datatype(&DataType::new_enum(&labels).to_string())
+                .unwrap()
+                .1,
+            DataType::new_enum(&labels)
+        );
+        assert!(datatype("enum('a', 'a')").is_err());
+        assert!(datatype("enum()").is_err());
+    }
+
     #[test]
     fn test_date_literals() {
         assert_eq!(
             literal(r#"date "2010-01-01""#).unwrap().1,
             Expression::Cast(Cast {
                 expr: Box::new(Expression::from("2010-01-01")),
-                datatype: DataType::Date
+                datatype: DataType::Date,
+                try_cast: false,
             })
         );
     }
@@ -0,0 +1,221 @@
+use crate::atoms::{and_recognise, kw, qualified_reference};
+use crate::expression::expression;
+use crate::statement::statement;
+use crate::whitespace::ws_0;
+use crate::ParserResult;
+use ast::statement::{CallProcedure, CreateProcedure, If, ProcedureStatement, Statement, While};
+use nom::branch::alt;
+use nom::bytes::complete::tag;
+use nom::combinator::{cut, map, opt, verify};
+use nom::multi::many0;
+use nom::sequence::{pair, preceded, terminated, tuple};
+
+/// PROCEDURE [db.]name[()] BEGIN statements END, the CREATE has already been parsed
+pub fn create_procedure(input: &str) -> ParserResult<Statement> {
+    map(
+        preceded(
+            pair(ws_0, kw("PROCEDURE")),
+            cut(tuple((
+                preceded(ws_0, qualified_reference),
+                opt(tuple((ws_0, tag("("), ws_0, tag(")")))),
+                tuple((ws_0, kw("BEGIN"), ws_0)),
+                and_recognise(procedure_body),
+                kw("END"),
+            ))),
+        ),
+        |((database, name), _, _, (body, body_sql), _)| {
+            Statement::CreateProcedure(CreateProcedure {
+                database,
+                name,
+                body,
+                body_sql: body_sql.trim_end().to_string(),
+            })
+        },
+    )(input)
+}
+
+/// CALL [db.]name[()]
+pub fn call(input: &str) -> ParserResult<Statement> {
+    map(
+        preceded(
+            kw("CALL"),
+            cut(terminated(
+                preceded(ws_0, qualified_reference),
+                opt(tuple((ws_0, tag("("), ws_0, tag(")")))),
+            )),
+        ),
+        |(database, name)| Statement::CallProcedure(CallProcedure { database, name }),
+    )(input)
+}
+
+/// The statements of a procedure's body, each one terminated with a semicolon
+pub fn procedure_body(input: &str) -> ParserResult<Vec<ProcedureStatement>> {
+    many0(terminated(
+        procedure_statement,
+        tuple((ws_0, tag(";"), ws_0)),
+    ))(input)
+}
+
+fn procedure_statement(input: &str) -> ParserResult<ProcedureStatement> {
+    alt((
+        if_,
+        while_,
+        // Procedures can't define other procedures, they can call them (or themselves) with
+        // the call depth limited by max_recursion_depth
+        map(
+            verify(statement, |statement| {
+                !matches!(statement, Statement::CreateProcedure(_))
+            }),
+            |statement| ProcedureStatement::Statement(Box::new(statement)),
+        ),
+    ))(input)
+}
+
+/// IF cond THEN ... [ELSEIF cond THEN ...] [ELSE ...] END IF
+fn if_(input: &str) -> ParserResult<ProcedureStatement> {
+    map(
+        preceded(
+            kw("IF"),
+            cut(tuple((
+                if_branch,
+                many0(preceded(kw("ELSEIF"), if_branch)),
+                opt(preceded(pair(kw("ELSE"), ws_0), procedure_body)),
+                tuple((kw("END"), ws_0, kw("IF"))),
+            ))),
+        ),
+        |(first, mut branches, otherwise, _)| {
+            branches.insert(0, first);
+            ProcedureStatement::If(If {
+                branches,
+                otherwise: otherwise.unwrap_or_default(),
+            })
+        },
+    )(input)
+}
+
+fn if_branch(input: &str) -> ParserResult<(ast::expr::Expression, Vec<ProcedureStatement>)> {
+    pair(
+        preceded(ws_0, expression),
+        preceded(tuple((ws_0, kw("THEN"), ws_0)), procedure_body),
+    )(input)
+}
+
+/// WHILE cond DO ... END WHILE
+fn while_(input: &str) -> ParserResult<ProcedureStatement> {
+    map(
+        preceded(
+            kw("WHILE"),
+            cut(tuple((
+                preceded(ws_0, expression),
+                preceded(tuple((ws_0, kw("DO"), ws_0)), procedure_body),
+                tuple((kw("END"), ws_0, kw("WHILE"))),
+            ))),
+        ),
+        |(condition, body, _)| ProcedureStatement::While(While { condition, body }),
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::expr::{ColumnReference, Expression, FunctionCall};
+    use ast::statement::SetUserVariable;
+
+    fn statement(sql: &str) -> ProcedureStatement {
+        ProcedureStatement::Statement(Box::new(crate::parse(sql).unwrap()))
+    }
+
+    fn user_variable(name: &str) -> Expression {
+        Expression::ColumnReference(ColumnReference {
+            qualifier: None,
+            alias: name.to_string(),
+            star: false,
+        })
+    }
+
+    #[test]
+    fn test_create_procedure() {
+        let sql = "PROCEDURE db.p() BEGIN
+            SET @i = 0;
+            WHILE @i < 3 DO
+                IF @i = 1 THEN
+                    SET @one = 1;
+                ELSEIF @i = 2 THEN
+                    SET @two = 2;
+                ELSE
+                    SET @other = 0;
+                END IF;
+                SET @i = @i + 1;
+            END WHILE;
+        END";
+        let (rest, procedure) = create_procedure(sql).unwrap();
+        assert_eq!(rest, "");
+        let create_procedure = if let Statement::CreateProcedure(create_procedure) = procedure {
+            create_procedure
+        } else {
+            panic!()
+        };
+        assert_eq!(create_procedure.database, Some("db".to_string()));
+        assert_eq!(create_procedure.name, "p");
+        assert!(create_procedure.body_sql.starts_with("SET @i = 0;"));
+        assert!(create_procedure.body_sql.ends_with("END WHILE;"));
+        assert_eq!(
+            create_procedure.body,
+            vec![
+                statement("SET @i = 0"),
+                ProcedureStatement::While(While {
+                    condition: Expression::FunctionCall(FunctionCall {
+                        function_name: "<".to_string(),
+                        args: vec![user_variable("@i"), Expression::from(3)]
+                    }),
+                    body: vec![
+                        ProcedureStatement::If(If {
+                            branches: vec![
+                                (
+                                    Expression::FunctionCall(FunctionCall {
+                                        function_name: "=".to_string(),
+                                        args: vec![user_variable("@i"), Expression::from(1)]
+                                    }),
+                                    vec![statement("SET @one = 1")]
+                                ),
+                                (
+                                    Expression::FunctionCall(FunctionCall {
+                                        function_name: "=".to_string(),
+                                        args: vec![user_variable("@i"), Expression::from(2)]
+                                    }),
+                                    vec![statement("SET @two = 2")]
+                                )
+                            ],
+                            otherwise: vec![statement("SET @other = 0")]
+                        }),
+                        statement("SET @i = @i + 1"),
+                    ]
+                })
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nested_procedures() {
+        assert_eq!(
+            procedure_body("CALL p;").unwrap().1,
+            vec![statement("CALL p")]
+        );
+        assert!(create_procedure("PROCEDURE p BEGIN CREATE PROCEDURE q BEGIN END; END").is_err());
+    }
+
+    #[test]
+    fn test_call() {
+        assert_eq!(
+            call("CALL db.p()").unwrap().1,
+            Statement::CallProcedure(CallProcedure {
+                database: Some("db".to_string()),
+                name: "p".to_string()
+            })
+        );
+        assert!(matches!(
+            statement("SET @x = 1"),
+            ProcedureStatement::Statement(statement) if matches!(*statement, Statement::SetUserVariable(SetUserVariable { .. }))
+        ));
+    }
+}
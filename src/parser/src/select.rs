@@ -1,17 +1,26 @@
-use crate::atoms::{as_clause, integer, kw, qualified_reference, quoted_string};
+use crate::atoms::{
+    as_clause, decimal, identifier_str, integer, kw, qualified_reference, quoted_string,
+};
 use crate::expression::{comma_sep_expressions, expression, named_expression, sort_expression};
+use crate::literals::datatype;
 use crate::whitespace::ws_0;
 use crate::ParserResult;
-use ast::expr::{Expression, NamedExpression, SortExpression};
+use ast::expr::{Cast, ColumnReference, Expression, FunctionCall, NamedExpression, SortExpression};
 use ast::rel::logical::{
-    FileScan, Filter, GroupBy, Join, JoinType, Limit, LogicalOperator, Project, SerdeOptions, Sort,
-    TableAlias, TableReference, UnionAll,
+    ChangeFormat, ErrorPolicy, FileFormat, FileScan, Filter, GroupBy, Join, JoinSide, JoinType,
+    Limit, LogicalOperator, Project, SampleMethod, SerdeOptions, Sort, TableAlias, TableReference,
+    TableSample, UnionAll,
 };
+use data::jsonpath_utils::JsonPathExpression;
+use data::rust_decimal::Decimal;
+use data::{DataType, LogicalTimestamp};
 use nom::branch::alt;
 use nom::bytes::complete::tag;
-use nom::combinator::{cut, map, opt, value};
+use nom::character::complete::{multispace0, multispace1};
+use nom::combinator::{cut, map, map_opt, map_res, opt, value, verify};
+use nom::error::context;
 use nom::multi::{many0, separated_list0, separated_list1};
-use nom::sequence::{delimited, pair, preceded, separated_pair, tuple};
+use nom::sequence::{delimited, pair, preceded, separated_pair, terminated, tuple};
 
 /// Parses a select statement, a select statement consists of potentially multiple
 /// select expressions unioned together
@@ -37,10 +46,11 @@ pub fn select(input: &str) -> ParserResult<LogicalOperator> {
 
 /// Parses a singular select expression
 fn select_expr(input: &str) -> ParserResult<LogicalOperator> {
-    map(
+    map_opt(
         preceded(
             kw("SELECT"),
             cut(tuple((
+                opt(preceded(multispace0, hints)),
                 preceded(ws_0, comma_sep_named_expressions),
                 opt(preceded(ws_0, from_clause)),
                 opt(preceded(ws_0, where_clause)),
@@ -49,13 +59,31 @@ fn select_expr(input: &str) -> ParserResult<LogicalOperator> {
                 opt(preceded(ws_0, limit_clause)),
             ))),
         ),
-        |(expressions, from_option, where_option, group_option, order_option, limit_option)| {
+        |(
+            hints,
+            expressions,
+            from_option,
+            where_option,
+            group_option,
+            order_option,
+            limit_option,
+        )| {
+            let hints = hints.unwrap_or_default();
             let mut query = from_option.unwrap_or(LogicalOperator::Single);
 
+            for hint in &hints {
+                if let Hint::HashJoin(probe, build) = hint {
+                    if !apply_hash_join_hint(&mut query, probe, build) {
+                        return None;
+                    }
+                }
+            }
+
             if let Some(predicate) = where_option {
                 query = LogicalOperator::Filter(Filter {
                     predicate,
                     source: Box::new(query),
+                    pinned: hints.contains(&Hint::NoPushdown),
                 });
             }
 
@@ -88,11 +116,78 @@ fn select_expr(input: &str) -> ParserResult<LogicalOperator> {
                 });
             }
 
-            query
+            Some(query)
         },
     )(input)
 }
 
+/// An optimizer hint, these come in a comment straight after the SELECT, ie
+/// SELECT /*+ HASH_JOIN(o c) NO_PUSHDOWN */ ...
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Hint {
+    // Join the first table to the second by building the hash table from the second
+    HashJoin(String, String),
+    // Leave the where clause where it is rather than pushing it down into the from clause
+    NoPushdown,
+}
+
+/// Parses a hint comment, unlike other comments hints that can't be parsed are errors rather
+/// than silently ignored
+fn hints(input: &str) -> ParserResult<Vec<Hint>> {
+    let separator = tuple((multispace0, opt(tag(",")), multispace0));
+    let hash_join = map(
+        preceded(
+            kw("HASH_JOIN"),
+            cut(delimited(
+                tuple((multispace0, tag("("), multispace0)),
+                separated_pair(identifier_str, multispace1, identifier_str),
+                pair(multispace0, tag(")")),
+            )),
+        ),
+        |(probe, build)| Hint::HashJoin(probe, build),
+    );
+    let hint = alt((hash_join, value(Hint::NoPushdown, kw("NO_PUSHDOWN"))));
+    preceded(
+        pair(tag("/*+"), multispace0),
+        cut(terminated(many0(terminated(hint, separator)), tag("*/"))),
+    )(input)
+}
+
+/// Marks which side of the join between the two tables to build the hash table from, returns
+/// false if there's no such join or it's an outer join that can't be built from that side, the
+/// hash table has to be built from the side whose unmatched rows aren't kept.
+fn apply_hash_join_hint(operator: &mut LogicalOperator, probe: &str, build: &str) -> bool {
+    if let LogicalOperator::Join(join) = operator {
+        let left = join_aliases(&join.left);
+        let right = join_aliases(&join.right);
+        if left.contains(&probe) && right.contains(&build) {
+            join.build_side = Some(JoinSide::Right);
+            return join.join_type != JoinType::RightOuter;
+        }
+        if left.contains(&build) && right.contains(&probe) {
+            join.build_side = Some(JoinSide::Left);
+            return join.join_type == JoinType::Inner || join.join_type == JoinType::RightOuter;
+        }
+        apply_hash_join_hint(&mut join.left, probe, build)
+            || apply_hash_join_hint(&mut join.right, probe, build)
+    } else {
+        false
+    }
+}
+
+/// The aliases of the tables and subqueries joined together by the operator
+fn join_aliases(operator: &LogicalOperator) -> Vec<&str> {
+    match operator {
+        LogicalOperator::Join(join) => {
+            let mut aliases = join_aliases(&join.left);
+            aliases.extend(join_aliases(&join.right));
+            aliases
+        }
+        LogicalOperator::TableAlias(table_alias) => vec![table_alias.alias.as_str()],
+        _ => vec![],
+    }
+}
+
 fn comma_sep_named_expressions(input: &str) -> ParserResult<Vec<NamedExpression>> {
     separated_list0(tuple((ws_0, tag(","), ws_0)), named_expression)(input)
 }
@@ -133,6 +228,7 @@ fn from_clause(input: &str) -> ParserResult<LogicalOperator> {
                     right: Box::new(right),
                     on: Expression::from(true),
                     join_type: JoinType::Inner,
+                    build_side: None,
                 })
             })
         },
@@ -157,6 +253,7 @@ fn join(input: &str) -> ParserResult<LogicalOperator> {
                         right: Box::new(right),
                         on: condition,
                         join_type,
+                        build_side: None,
                     })
                 })
         },
@@ -173,21 +270,96 @@ fn join_type(input: &str) -> ParserResult<JoinType> {
             JoinType::LeftOuter,
             tuple((kw("LEFT"), ws_0, opt(pair(kw("OUTER"), ws_0)), kw("JOIN"))),
         ),
+        value(
+            JoinType::RightOuter,
+            tuple((kw("RIGHT"), ws_0, opt(pair(kw("OUTER"), ws_0)), kw("JOIN"))),
+        ),
+        value(
+            JoinType::FullOuter,
+            tuple((kw("FULL"), ws_0, opt(pair(kw("OUTER"), ws_0)), kw("JOIN"))),
+        ),
     ))(input)
 }
 
 fn join_item(input: &str) -> ParserResult<LogicalOperator> {
-    map(
-        pair(unaliased_join_item, as_clause),
-        |(sub_query, alias_opt)| {
-            if let Some(alias) = alias_opt {
+    map_opt(
+        tuple((
+            unaliased_join_item,
+            opt(preceded(ws_0, as_of_clause)),
+            as_clause,
+            opt(preceded(ws_0, tablesample_clause)),
+        )),
+        |(sub_query, as_of_opt, alias_opt, sample_opt)| {
+            let mut item = if let Some(alias) = alias_opt {
                 LogicalOperator::TableAlias(TableAlias {
                     alias,
                     source: Box::from(sub_query),
                 })
             } else {
                 sub_query
+            };
+            // Only tables can be sampled or read as of a timestamp, so dig down through any
+            // aliases to find the table
+            if sample_opt.is_some() || as_of_opt.is_some() {
+                let mut table = &mut item;
+                while let LogicalOperator::TableAlias(table_alias) = table {
+                    table = &mut table_alias.source;
+                }
+                if let LogicalOperator::TableReference(table_reference) = table {
+                    table_reference.sample = sample_opt;
+                    table_reference.as_of = as_of_opt;
+                } else {
+                    return None;
+                }
             }
+            Some(item)
+        },
+    )(input)
+}
+
+/// Parse an as of clause, ie AS OF TIMESTAMP '2020-01-31 12:00:00'
+fn as_of_clause(input: &str) -> ParserResult<LogicalTimestamp> {
+    preceded(
+        tuple((kw("AS"), ws_0, kw("OF"), ws_0, kw("TIMESTAMP"))),
+        cut(preceded(
+            ws_0,
+            context(
+                "Invalid AS OF timestamp",
+                map_res(quoted_string, |timestamp| {
+                    LogicalTimestamp::parse(&timestamp)
+                }),
+            ),
+        )),
+    )(input)
+}
+
+/// Parse a tablesample clause, ie TABLESAMPLE BERNOULLI(10) REPEATABLE(42)
+fn tablesample_clause(input: &str) -> ParserResult<TableSample> {
+    let method = alt((
+        value(SampleMethod::Bernoulli, kw("BERNOULLI")),
+        value(SampleMethod::System, kw("SYSTEM")),
+    ));
+    let percentage = verify(
+        delimited(pair(tag("("), ws_0), decimal, pair(ws_0, tag(")"))),
+        |percentage: &Decimal| *percentage >= Decimal::from(0) && *percentage <= Decimal::from(100),
+    );
+    let repeatable = preceded(
+        pair(kw("REPEATABLE"), ws_0),
+        delimited(pair(tag("("), ws_0), integer, pair(ws_0, tag(")"))),
+    );
+    map(
+        preceded(
+            kw("TABLESAMPLE"),
+            cut(tuple((
+                preceded(ws_0, method),
+                preceded(ws_0, percentage),
+                opt(preceded(ws_0, repeatable)),
+            ))),
+        ),
+        |(method, percentage, seed)| TableSample {
+            method,
+            percentage,
+            seed,
         },
     )(input)
 }
@@ -259,6 +431,8 @@ fn table_reference_with_alias(input: &str) -> ParserResult<LogicalOperator> {
         let table_source = LogicalOperator::TableReference(TableReference {
             database,
             table: table.clone(),
+            sample: None,
+            as_of: None,
         });
         LogicalOperator::TableAlias(TableAlias {
             alias: table,
@@ -267,44 +441,201 @@ fn table_reference_with_alias(input: &str) -> ParserResult<LogicalOperator> {
     })(input)
 }
 
-/// Parse a file source
+/// Parse a file source, ie
+/// DIRECTORY "data/" WITH (format = "json") COLUMNS (city TEXT PATH "$.address.city")
 fn directory_source(input: &str) -> ParserResult<LogicalOperator> {
     map(
         preceded(
             kw("DIRECTORY"),
             cut(preceded(
                 ws_0,
-                pair(quoted_string, opt(preceded(ws_0, serde_options))),
+                tuple((
+                    quoted_string,
+                    opt(preceded(ws_0, serde_options)),
+                    opt(preceded(ws_0, file_columns)),
+                )),
             )),
         ),
-        |(directory, serde_options)| {
+        |(directory, serde_options, columns)| {
             LogicalOperator::FileScan(FileScan {
                 directory,
                 serde_options: serde_options.unwrap_or_default(),
+                columns: columns.unwrap_or_default(),
+                dead_letters: None,
+                source_offsets: None,
+                paused_sources: None,
             })
         },
     )(input)
 }
 
-fn serde_options(input: &str) -> ParserResult<SerdeOptions> {
+fn file_columns(input: &str) -> ParserResult<Vec<NamedExpression>> {
+    preceded(
+        pair(kw("COLUMNS"), ws_0),
+        cut(delimited(
+            pair(tag("("), ws_0),
+            separated_list1(
+                tuple((ws_0, tag(","), ws_0)),
+                map(
+                    tuple((
+                        identifier_str,
+                        preceded(ws_0, datatype),
+                        preceded(tuple((ws_0, kw("PATH"), ws_0)), json_path),
+                    )),
+                    |(alias, datatype, path)| file_column(alias, datatype, path),
+                ),
+            ),
+            pair(ws_0, tag(")")),
+        )),
+    )(input)
+}
+
+/// A quoted string that's also a valid json path
+pub fn json_path(input: &str) -> ParserResult<String> {
+    verify(quoted_string, |path: &str| {
+        JsonPathExpression::parse(path).is_some()
+    })(input)
+}
+
+/// The expression to pull a typed column out of a document, the same as writing
+/// cast(data->>path as datatype) by hand.
+fn file_column(alias: String, datatype: DataType, path: String) -> NamedExpression {
+    // Text and the date types want the unquoted string, anything else gets cast from the json.
+    let function_name = match datatype {
+        DataType::Text | DataType::Date | DataType::Timestamp => "->>",
+        _ => "->",
+    };
+    let extracted = Expression::FunctionCall(FunctionCall {
+        function_name: function_name.to_string(),
+        args: vec![
+            Expression::ColumnReference(ColumnReference {
+                qualifier: None,
+                alias: "data".to_string(),
+                star: false,
+            }),
+            Expression::from(path),
+        ],
+    });
+    let expression = match datatype {
+        DataType::Text | DataType::Json => extracted,
+        datatype => Expression::Cast(Cast {
+            expr: Box::new(extracted),
+            datatype,
+            try_cast: false,
+        }),
+    };
+    NamedExpression {
+        alias: Some(alias),
+        expression,
+    }
+}
+
+/// The options for reading files, ie WITH (delimiter = "|", format = "csv")
+pub fn serde_options(input: &str) -> ParserResult<SerdeOptions> {
     map(
         delimited(
             tuple((kw("WITH"), ws_0, tag("("), ws_0)),
-            delimiter_option,
+            separated_list1(tuple((ws_0, tag(","), ws_0)), serde_option),
             tuple((ws_0, tag(")"))),
         ),
-        |delimiter| SerdeOptions { delimiter },
+        |options| {
+            let mut serde_options = SerdeOptions::default();
+            for option in options {
+                match option {
+                    SerdeOption::Delimiter(delimiter) => serde_options.delimiter = delimiter,
+                    SerdeOption::Format(format) => serde_options.format = format,
+                    SerdeOption::OnError(on_error) => serde_options.on_error = on_error,
+                    SerdeOption::Resume(resume) => serde_options.resume = resume,
+                    SerdeOption::MaxRowsPerSecond(max) => {
+                        serde_options.max_rows_per_second = Some(max)
+                    }
+                    SerdeOption::MaxBatchRows(max) => serde_options.max_batch_rows = Some(max),
+                    SerdeOption::Changes(changes) => serde_options.changes = Some(changes),
+                    SerdeOption::Snapshot(snapshot) => serde_options.snapshot = Some(snapshot),
+                }
+            }
+            serde_options
+        },
     )(input)
 }
 
-fn delimiter_option(input: &str) -> ParserResult<u8> {
-    map(
-        preceded(
-            tuple((kw("DELIMITER"), ws_0, tag("="), ws_0)),
-            quoted_string,
+enum SerdeOption {
+    Delimiter(u8),
+    Format(FileFormat),
+    OnError(ErrorPolicy),
+    Resume(bool),
+    MaxRowsPerSecond(u64),
+    MaxBatchRows(u64),
+    Changes(ChangeFormat),
+    Snapshot(String),
+}
+
+fn serde_option(input: &str) -> ParserResult<SerdeOption> {
+    alt((
+        map(
+            preceded(
+                tuple((kw("DELIMITER"), ws_0, tag("="), ws_0)),
+                verify(quoted_string, |s: &str| !s.is_empty()),
+            ),
+            |s| SerdeOption::Delimiter(s.as_bytes()[0]),
         ),
-        |s| s.as_bytes()[0],
-    )(input)
+        map_opt(
+            preceded(tuple((kw("FORMAT"), ws_0, tag("="), ws_0)), quoted_string),
+            |s| match s.to_lowercase().as_str() {
+                "csv" => Some(SerdeOption::Format(FileFormat::Csv)),
+                "json" => Some(SerdeOption::Format(FileFormat::Json)),
+                "parquet" => Some(SerdeOption::Format(FileFormat::Parquet)),
+                _ => None,
+            },
+        ),
+        map_opt(
+            preceded(tuple((kw("ON_ERROR"), ws_0, tag("="), ws_0)), quoted_string),
+            |s| match s.to_lowercase().as_str() {
+                "fail" => Some(SerdeOption::OnError(ErrorPolicy::Fail)),
+                "skip" => Some(SerdeOption::OnError(ErrorPolicy::Skip)),
+                "dead_letter" => Some(SerdeOption::OnError(ErrorPolicy::DeadLetter)),
+                _ => None,
+            },
+        ),
+        map_opt(
+            preceded(tuple((kw("RESUME"), ws_0, tag("="), ws_0)), quoted_string),
+            |s| match s.to_lowercase().as_str() {
+                "true" => Some(SerdeOption::Resume(true)),
+                "false" => Some(SerdeOption::Resume(false)),
+                _ => None,
+            },
+        ),
+        map(
+            preceded(
+                tuple((kw("MAX_ROWS_PER_SECOND"), ws_0, tag("="), ws_0)),
+                positive_quoted_int,
+            ),
+            SerdeOption::MaxRowsPerSecond,
+        ),
+        map(
+            preceded(
+                tuple((kw("MAX_BATCH_ROWS"), ws_0, tag("="), ws_0)),
+                positive_quoted_int,
+            ),
+            SerdeOption::MaxBatchRows,
+        ),
+        map_opt(
+            preceded(tuple((kw("CHANGES"), ws_0, tag("="), ws_0)), quoted_string),
+            |s| match s.to_lowercase().as_str() {
+                "debezium" => Some(SerdeOption::Changes(ChangeFormat::Debezium)),
+                "maxwell" => Some(SerdeOption::Changes(ChangeFormat::Maxwell)),
+                _ => None,
+            },
+        ),
+        map(
+            preceded(tuple((kw("SNAPSHOT"), ws_0, tag("="), ws_0)), quoted_string),
+            SerdeOption::Snapshot,
+        ),
+    ))(input)
+}
+
+fn positive_quoted_int(input: &str) -> ParserResult<u64> {
+    map_opt(quoted_string, |s| s.trim().parse().ok().filter(|i| *i > 0))(input)
 }
 
 #[cfg(test)]
@@ -408,12 +739,50 @@ mod tests {
                 },],
                 source: Box::from(LogicalOperator::Filter(Filter {
                     predicate: Expression::from(true),
-                    source: Box::new(LogicalOperator::Single)
+                    source: Box::new(LogicalOperator::Single),
+                    pinned: false,
                 }))
             })
         );
     }
 
+    #[test]
+    fn test_hints() {
+        let query =
+            select("SELECT /*+ HASH_JOIN(a b), NO_PUSHDOWN */ 1 FROM a JOIN b ON 3 WHERE true")
+                .unwrap()
+                .1;
+        let filter = match query {
+            LogicalOperator::Project(Project { source, .. }) => match *source {
+                LogicalOperator::Filter(filter) => filter,
+                _ => panic!(),
+            },
+            _ => panic!(),
+        };
+        assert!(filter.pinned);
+        assert!(
+            matches!(*filter.source, LogicalOperator::Join(join) if join.build_side == Some(JoinSide::Right))
+        );
+
+        let query = select("SELECT /*+HASH_JOIN(c a)*/ 1 FROM a JOIN b ON 3 JOIN c ON 4")
+            .unwrap()
+            .1;
+        assert!(matches!(
+            query,
+            LogicalOperator::Project(Project { source, .. })
+                if matches!(*source, LogicalOperator::Join(ref join) if join.build_side == Some(JoinSide::Left))
+        ));
+
+        // Outer joins can only be built from the side whose unmatched rows aren't kept
+        assert!(select("SELECT /*+ HASH_JOIN(b a) */ 1 FROM a LEFT JOIN b ON 3").is_err());
+        assert!(select("SELECT /*+ HASH_JOIN(a b) */ 1 FROM a RIGHT JOIN b ON 3").is_err());
+        assert!(select("SELECT /*+ HASH_JOIN(b a) */ 1 FROM a RIGHT JOIN b ON 3").is_ok());
+        assert!(select("SELECT /*+ HASH_JOIN(a c) */ 1 FROM a JOIN b ON 3").is_err());
+        assert!(select("SELECT /*+ NO_SUCH_HINT */ 1").is_err());
+        // Without the + it's just a comment
+        assert!(select("SELECT /* NO_SUCH_HINT */ 1").is_ok());
+    }
+
     #[test]
     fn test_group_by() {
         assert_eq!(
@@ -448,18 +817,23 @@ mod tests {
                         alias: "a".to_string(),
                         source: Box::new(LogicalOperator::TableReference(TableReference {
                             database: None,
-                            table: "a".to_string()
+                            table: "a".to_string(),
+                            sample: None,
+                            as_of: None,
                         }))
                     })),
                     right: Box::new(LogicalOperator::TableAlias(TableAlias {
                         alias: "b".to_string(),
                         source: Box::new(LogicalOperator::TableReference(TableReference {
                             database: None,
-                            table: "b".to_string()
+                            table: "b".to_string(),
+                            sample: None,
+                            as_of: None,
                         }))
                     })),
                     on: Expression::from(true),
-                    join_type: JoinType::Inner
+                    join_type: JoinType::Inner,
+                    build_side: None,
                 }))
             })
         );
@@ -480,23 +854,49 @@ mod tests {
                         alias: "a".to_string(),
                         source: Box::new(LogicalOperator::TableReference(TableReference {
                             database: None,
-                            table: "a".to_string()
+                            table: "a".to_string(),
+                            sample: None,
+                            as_of: None,
                         }))
                     })),
                     right: Box::new(LogicalOperator::TableAlias(TableAlias {
                         alias: "b".to_string(),
                         source: Box::new(LogicalOperator::TableReference(TableReference {
                             database: None,
-                            table: "b".to_string()
+                            table: "b".to_string(),
+                            sample: None,
+                            as_of: None,
                         }))
                     })),
                     on: Expression::from(3),
-                    join_type: JoinType::Inner
+                    join_type: JoinType::Inner,
+                    build_side: None,
                 }))
             })
         );
     }
 
+    #[test]
+    fn test_outer_joins() {
+        for (sql, expected) in &[
+            ("LEFT JOIN", JoinType::LeftOuter),
+            ("left outer join", JoinType::LeftOuter),
+            ("RIGHT JOIN", JoinType::RightOuter),
+            ("RIGHT OUTER JOIN", JoinType::RightOuter),
+            ("FULL JOIN", JoinType::FullOuter),
+            ("full outer join", JoinType::FullOuter),
+        ] {
+            let query = select(&format!("SELECT 1 FROM a {} b ON 3", sql))
+                .unwrap()
+                .1;
+            assert!(matches!(
+                query,
+                LogicalOperator::Project(Project { source, .. })
+                    if matches!(*source, LogicalOperator::Join(ref join) if join.join_type == *expected)
+            ));
+        }
+    }
+
     #[test]
     fn test_order_by() {
         let project = LogicalOperator::Project(Project {
@@ -590,7 +990,9 @@ mod tests {
                 alias: "foo".to_string(),
                 source: Box::new(LogicalOperator::TableReference(TableReference {
                     database: None,
-                    table: "foo".to_string()
+                    table: "foo".to_string(),
+                    sample: None,
+                    as_of: None,
                 })),
             })
         );
@@ -601,10 +1003,98 @@ mod tests {
                 alias: "bar".to_string(),
                 source: Box::new(LogicalOperator::TableReference(TableReference {
                     database: Some("foo".to_string()),
-                    table: "bar".to_string()
+                    table: "bar".to_string(),
+                    sample: None,
+                    as_of: None,
+                })),
+            })
+        );
+    }
+
+    #[test]
+    fn test_tablesample() {
+        assert_eq!(
+            join_item("foo f TABLESAMPLE BERNOULLI (0.5) REPEATABLE(42)")
+                .unwrap()
+                .1,
+            LogicalOperator::TableAlias(TableAlias {
+                alias: "f".to_string(),
+                source: Box::new(LogicalOperator::TableAlias(TableAlias {
+                    alias: "foo".to_string(),
+                    source: Box::new(LogicalOperator::TableReference(TableReference {
+                        database: None,
+                        table: "foo".to_string(),
+                        sample: Some(TableSample {
+                            method: SampleMethod::Bernoulli,
+                            percentage: Decimal::new(5, 1),
+                            seed: Some(42)
+                        }),
+                        as_of: None,
+                    })),
+                })),
+            })
+        );
+
+        assert_eq!(
+            join_item("foo tablesample system(10)").unwrap().1,
+            LogicalOperator::TableAlias(TableAlias {
+                alias: "foo".to_string(),
+                source: Box::new(LogicalOperator::TableReference(TableReference {
+                    database: None,
+                    table: "foo".to_string(),
+                    sample: Some(TableSample {
+                        method: SampleMethod::System,
+                        percentage: Decimal::from(10),
+                        seed: None
+                    }),
+                    as_of: None,
                 })),
             })
         );
+
+        assert!(join_item("foo tablesample system(101)").is_err());
+        assert!(join_item("(select 1) tablesample system(10)").is_err());
+    }
+
+    #[test]
+    fn test_as_of() {
+        assert_eq!(
+            join_item("foo AS OF TIMESTAMP '2020-01-31 12:00:00' AS f")
+                .unwrap()
+                .1,
+            LogicalOperator::TableAlias(TableAlias {
+                alias: "f".to_string(),
+                source: Box::new(LogicalOperator::TableAlias(TableAlias {
+                    alias: "foo".to_string(),
+                    source: Box::new(LogicalOperator::TableReference(TableReference {
+                        database: None,
+                        table: "foo".to_string(),
+                        sample: None,
+                        as_of: Some(LogicalTimestamp::new(1_580_472_000_000)),
+                    })),
+                })),
+            })
+        );
+
+        // Still usable as an alias
+        assert_eq!(
+            join_item("foo as of").unwrap().1,
+            LogicalOperator::TableAlias(TableAlias {
+                alias: "of".to_string(),
+                source: Box::new(LogicalOperator::TableAlias(TableAlias {
+                    alias: "foo".to_string(),
+                    source: Box::new(LogicalOperator::TableReference(TableReference {
+                        database: None,
+                        table: "foo".to_string(),
+                        sample: None,
+                        as_of: None,
+                    })),
+                })),
+            })
+        );
+
+        assert!(join_item("foo as of timestamp 'yesterday'").is_err());
+        assert!(join_item("(select 1) as of timestamp '2020-01-31 12:00:00'").is_err());
     }
 
     #[test]
@@ -619,7 +1109,11 @@ mod tests {
                 }],
                 source: Box::new(LogicalOperator::FileScan(FileScan {
                     directory: "test".to_string(),
-                    serde_options: SerdeOptions::default()
+                    serde_options: SerdeOptions::default(),
+                    columns: vec![],
+                    dead_letters: None,
+                    source_offsets: None,
+                    paused_sources: None,
                 })),
             })
         );
@@ -639,9 +1133,128 @@ mod tests {
                 }],
                 source: Box::new(LogicalOperator::FileScan(FileScan {
                     directory: "test".to_string(),
-                    serde_options: SerdeOptions { delimiter: b'|' }
+                    serde_options: SerdeOptions {
+                        delimiter: b'|',
+                        format: FileFormat::Csv,
+                        on_error: ErrorPolicy::Fail,
+                        resume: false,
+                        max_rows_per_second: None,
+                        max_batch_rows: None,
+                        changes: None,
+                        snapshot: None
+                    },
+                    columns: vec![],
+                    dead_letters: None,
+                    source_offsets: None,
+                    paused_sources: None,
                 })),
             })
         );
+
+        assert_eq!(
+            serde_options(r#"WITH (format = 'JSON', delimiter = "\t", on_error = 'dead_letter', resume = 'true')"#)
+                .unwrap()
+                .1,
+            SerdeOptions {
+                delimiter: b'\t',
+                format: FileFormat::Json,
+                on_error: ErrorPolicy::DeadLetter,
+                resume: true,
+                max_rows_per_second: None,
+                max_batch_rows: None,
+                changes: None,
+                snapshot: None
+            }
+        );
+        assert_eq!(
+            serde_options(r#"WITH (max_rows_per_second = '1000', max_batch_rows = "50")"#)
+                .unwrap()
+                .1,
+            SerdeOptions {
+                max_rows_per_second: Some(1000),
+                max_batch_rows: Some(50),
+                ..SerdeOptions::default()
+            }
+        );
+        assert!(serde_options(r#"WITH (max_rows_per_second = '0')"#).is_err());
+        assert_eq!(
+            serde_options(r#"WITH (format = 'parquet')"#)
+                .unwrap()
+                .1
+                .format,
+            FileFormat::Parquet
+        );
+        assert_eq!(
+            serde_options(r#"WITH (changes = 'Debezium', snapshot = 'data/snapshot')"#)
+                .unwrap()
+                .1,
+            SerdeOptions {
+                changes: Some(ChangeFormat::Debezium),
+                snapshot: Some("data/snapshot".to_string()),
+                ..SerdeOptions::default()
+            }
+        );
+        assert_eq!(
+            serde_options(r#"WITH (changes = 'maxwell')"#)
+                .unwrap()
+                .1
+                .changes,
+            Some(ChangeFormat::Maxwell)
+        );
+        assert!(serde_options(r#"WITH (changes = 'canal')"#).is_err());
+        assert!(serde_options(r#"WITH (format = 'xml')"#).is_err());
+        assert!(serde_options(r#"WITH (on_error = 'ignore')"#).is_err());
+    }
+
+    #[test]
+    fn test_directory_src_columns() {
+        assert_eq!(
+            directory_source(r#"DIRECTORY "test" COLUMNS (city TEXT PATH '$.address.city', age INT PATH '$.age')"#)
+                .unwrap()
+                .1,
+            LogicalOperator::FileScan(FileScan {
+                directory: "test".to_string(),
+                serde_options: SerdeOptions::default(),
+                columns: vec![
+                    NamedExpression {
+                        expression: Expression::FunctionCall(FunctionCall {
+                            function_name: "->>".to_string(),
+                            args: vec![
+                                Expression::ColumnReference(ColumnReference {
+                                    qualifier: None,
+                                    alias: "data".to_string(),
+                                    star: false
+                                }),
+                                Expression::from("$.address.city")
+                            ]
+                        }),
+                        alias: Some("city".to_string()),
+                    },
+                    NamedExpression {
+                        expression: Expression::Cast(Cast {
+                            expr: Box::new(Expression::FunctionCall(FunctionCall {
+                                function_name: "->".to_string(),
+                                args: vec![
+                                    Expression::ColumnReference(ColumnReference {
+                                    qualifier: None,
+                                    alias: "data".to_string(),
+                                    star: false
+                                }),
+                                    Expression::from("$.age")
+                                ]
+                            })),
+                            datatype: DataType::Integer,
+                            try_cast: false,
+                        }),
+                        alias: Some("age".to_string()),
+                    },
+                ],
+                dead_letters: None,
+                    source_offsets: None,
+                    paused_sources: None,
+            })
+        );
+
+        assert!(directory_source(r#"DIRECTORY "test" COLUMNS (city TEXT PATH 'city')"#).is_err());
     }
 }
@@ -14,6 +14,11 @@ pub fn show(input: &str) -> ParserResult<Statement> {
             value(Statement::ShowFunctions, preceded(ws_0, kw("FUNCTIONS"))),
             value(Statement::ShowDatabases, preceded(ws_0, kw("DATABASES"))),
             value(Statement::ShowTables, preceded(ws_0, kw("TABLES"))),
+            value(Statement::ShowWarnings, preceded(ws_0, kw("WARNINGS"))),
+            value(
+                Statement::ShowProcesslist,
+                preceded(ws_0, kw("PROCESSLIST")),
+            ),
         ))),
     )(input)
 }
@@ -36,4 +41,17 @@ mod tests {
     fn test_show_databases() {
         assert_eq!(show("Show databases").unwrap().1, Statement::ShowDatabases);
     }
+
+    #[test]
+    fn test_show_warnings() {
+        assert_eq!(show("SHOW WARNINGS").unwrap().1, Statement::ShowWarnings);
+    }
+
+    #[test]
+    fn test_show_processlist() {
+        assert_eq!(
+            show("show processlist").unwrap().1,
+            Statement::ShowProcesslist
+        );
+    }
 }
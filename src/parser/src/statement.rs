@@ -1,17 +1,29 @@
-use crate::atoms::{identifier_str, kw, qualified_reference};
+use crate::atoms::{and_recognise, identifier_str, kw, qualified_reference, quoted_string};
 use crate::create::create;
 use crate::delete::delete;
 use crate::drop::drop_;
-use crate::insert::insert;
-use crate::select::select;
+use crate::expression::expression;
+use crate::insert::{copy, insert};
+use crate::literals::datatype;
+use crate::procedure::call;
+use crate::select::{select, serde_options};
 use crate::show::show;
 use crate::whitespace::ws_0;
 use crate::ParserResult;
-use ast::rel::logical::LogicalOperator;
-use ast::statement::{CompactTable, Explain, Statement};
+use ast::expr::{Expression, NamedExpression};
+use ast::rel::logical::{LogicalOperator, Project};
+use ast::statement::{
+    AlterSource, AlterTableColumnMask, AlterTableColumnSchema, AlterTableModifyColumn,
+    AlterTableStorageTier, AlterUser, AnalyzeTable, CompactTable, Explain, ExplainMaintenance,
+    FlushSink, Grant, GrantObject, InferSchema, RefreshMaterializedView, SetUserVariable,
+    SetVariable, Statement, StorageTier, DEFAULT_SAMPLE_SIZE,
+};
+use data::DataType;
 use nom::branch::alt;
-use nom::combinator::{cut, map};
-use nom::sequence::{preceded, tuple};
+use nom::bytes::complete::tag;
+use nom::character::complete::digit1;
+use nom::combinator::{cut, map, map_res, not, opt};
+use nom::sequence::{delimited, pair, preceded, separated_pair, terminated, tuple};
 
 pub fn statement(input: &str) -> ParserResult<Statement> {
     alt((
@@ -22,19 +34,57 @@ pub fn statement(input: &str) -> ParserResult<Statement> {
         create,
         drop_,
         compact,
+        analyze,
+        alter_source,
+        alter_table,
+        alter_user,
+        grant,
+        flush_sink,
+        refresh_materialized_view,
+        set,
+        infer_schema,
+        call,
+        kill,
+        transaction,
+        vacuum,
     ))(input)
 }
 
 /// The logical operator statements, these can be used both as a standalone
 /// statement and as input to the explain operator
 fn logical_operator(input: &str) -> ParserResult<LogicalOperator> {
-    alt((select, insert, delete))(input)
+    alt((select, insert, copy, delete))(input)
 }
 
 fn explain(input: &str) -> ParserResult<Statement> {
+    preceded(
+        kw("EXPLAIN"),
+        cut(preceded(
+            ws_0,
+            alt((
+                explain_maintenance,
+                map(
+                    pair(opt(pair(kw("TYPES"), ws_0)), logical_operator),
+                    |(types, query)| {
+                        Statement::Explain(Explain {
+                            operator: query,
+                            types: types.is_some(),
+                        })
+                    },
+                ),
+            )),
+        )),
+    )(input)
+}
+
+/// MAINTENANCE FOR VIEW [db.]name
+fn explain_maintenance(input: &str) -> ParserResult<Statement> {
     map(
-        preceded(kw("EXPLAIN"), cut(preceded(ws_0, logical_operator))),
-        |query| Statement::Explain(Explain { operator: query }),
+        preceded(
+            tuple((kw("MAINTENANCE"), ws_0, kw("FOR"), ws_0, kw("VIEW"))),
+            cut(preceded(ws_0, qualified_reference)),
+        ),
+        |(database, name)| Statement::ExplainMaintenance(ExplainMaintenance { database, name }),
     )(input)
 }
 
@@ -58,11 +108,449 @@ fn compact(input: &str) -> ParserResult<Statement> {
     )(input)
 }
 
+/// KILL [CONNECTION | QUERY] id, either way the connection's killed
+fn kill(input: &str) -> ParserResult<Statement> {
+    map(
+        preceded(
+            kw("KILL"),
+            cut(preceded(
+                tuple((ws_0, opt(pair(alt((kw("CONNECTION"), kw("QUERY"))), ws_0)))),
+                map_res(digit1, str::parse::<u32>),
+            )),
+        ),
+        Statement::Kill,
+    )(input)
+}
+
+/// BEGIN [WORK] | START TRANSACTION | COMMIT [WORK] | ROLLBACK [WORK]
+fn transaction(input: &str) -> ParserResult<Statement> {
+    let work = || opt(preceded(ws_0, kw("WORK")));
+    alt((
+        map(pair(kw("BEGIN"), work()), |_| Statement::Begin),
+        map(tuple((kw("START"), ws_0, kw("TRANSACTION"))), |_| {
+            Statement::Begin
+        }),
+        map(pair(kw("COMMIT"), work()), |_| Statement::Commit),
+        map(pair(kw("ROLLBACK"), work()), |_| Statement::Rollback),
+    ))(input)
+}
+
+/// VACUUM, there's no per table form as the retention window applies to every table
+fn vacuum(input: &str) -> ParserResult<Statement> {
+    map(kw("VACUUM"), |_| Statement::Vacuum)(input)
+}
+
+/// ANALYZE [TABLE] [db.]name
+fn analyze(input: &str) -> ParserResult<Statement> {
+    map(
+        preceded(
+            kw("ANALYZE"),
+            cut(preceded(
+                tuple((ws_0, opt(pair(kw("TABLE"), ws_0)))),
+                qualified_reference,
+            )),
+        ),
+        |(database, name)| Statement::AnalyzeTable(AnalyzeTable { database, name }),
+    )(input)
+}
+
+/// ALTER SOURCE [db.]name PAUSE|RESUME
+fn alter_source(input: &str) -> ParserResult<Statement> {
+    map(
+        preceded(
+            tuple((kw("ALTER"), ws_0, kw("SOURCE"))),
+            cut(tuple((
+                preceded(ws_0, qualified_reference),
+                preceded(
+                    ws_0,
+                    alt((map(kw("PAUSE"), |_| true), map(kw("RESUME"), |_| false))),
+                ),
+            ))),
+        ),
+        |((database, name), paused)| {
+            Statement::AlterSource(AlterSource {
+                database,
+                name,
+                paused,
+            })
+        },
+    )(input)
+}
+
+/// ALTER TABLE [db.]name SET STORAGE TIER HOT|COLD LOCATION 'path'
+/// ALTER TABLE [db.]name MODIFY [COLUMN] column datatype
+/// ALTER TABLE [db.]name ALTER [COLUMN] column SET MASK USING (expression) | DROP MASK
+/// ALTER TABLE [db.]name ALTER [COLUMN] column SET SCHEMA 'json schema' | DROP SCHEMA
+fn alter_table(input: &str) -> ParserResult<Statement> {
+    enum Alteration<'a> {
+        StorageTier(StorageTier),
+        ModifyColumn(String, DataType),
+        AlterColumn(String, ColumnAlteration<'a>),
+    }
+
+    enum ColumnAlteration<'a> {
+        Mask(Option<(Expression, &'a str)>),
+        Schema(Option<String>),
+    }
+
+    map(
+        preceded(
+            tuple((kw("ALTER"), ws_0, kw("TABLE"))),
+            cut(tuple((
+                preceded(ws_0, qualified_reference),
+                preceded(
+                    ws_0,
+                    alt((
+                        map(
+                            preceded(
+                                tuple((kw("SET"), ws_0, kw("STORAGE"), ws_0, kw("TIER"), ws_0)),
+                                alt((
+                                    map(kw("HOT"), |_| StorageTier::Hot),
+                                    map(
+                                        preceded(
+                                            tuple((kw("COLD"), ws_0, kw("LOCATION"), ws_0)),
+                                            quoted_string,
+                                        ),
+                                        StorageTier::Cold,
+                                    ),
+                                )),
+                            ),
+                            Alteration::StorageTier,
+                        ),
+                        map(
+                            preceded(
+                                tuple((kw("MODIFY"), opt(pair(ws_0, kw("COLUMN"))), ws_0)),
+                                separated_pair(identifier_str, ws_0, datatype),
+                            ),
+                            |(column, datatype)| Alteration::ModifyColumn(column, datatype),
+                        ),
+                        map(
+                            preceded(
+                                tuple((kw("ALTER"), opt(pair(ws_0, kw("COLUMN"))), ws_0)),
+                                separated_pair(
+                                    identifier_str,
+                                    ws_0,
+                                    alt((
+                                        map(
+                                            delimited(
+                                                tuple((
+                                                    kw("SET"),
+                                                    ws_0,
+                                                    kw("MASK"),
+                                                    ws_0,
+                                                    kw("USING"),
+                                                    ws_0,
+                                                    tag("("),
+                                                    ws_0,
+                                                )),
+                                                and_recognise(expression),
+                                                pair(ws_0, tag(")")),
+                                            ),
+                                            |mask| ColumnAlteration::Mask(Some(mask)),
+                                        ),
+                                        map(tuple((kw("DROP"), ws_0, kw("MASK"))), |_| {
+                                            ColumnAlteration::Mask(None)
+                                        }),
+                                        map(
+                                            preceded(
+                                                tuple((kw("SET"), ws_0, kw("SCHEMA"), ws_0)),
+                                                quoted_string,
+                                            ),
+                                            |schema| ColumnAlteration::Schema(Some(schema)),
+                                        ),
+                                        map(tuple((kw("DROP"), ws_0, kw("SCHEMA"))), |_| {
+                                            ColumnAlteration::Schema(None)
+                                        }),
+                                    )),
+                                ),
+                            ),
+                            |(column, alteration)| Alteration::AlterColumn(column, alteration),
+                        ),
+                    )),
+                ),
+            ))),
+        ),
+        |((database, name), alteration)| match alteration {
+            Alteration::StorageTier(tier) => {
+                Statement::AlterTableStorageTier(AlterTableStorageTier {
+                    database,
+                    name,
+                    tier,
+                })
+            }
+            Alteration::ModifyColumn(column, datatype) => {
+                Statement::AlterTableModifyColumn(AlterTableModifyColumn {
+                    database,
+                    name,
+                    column,
+                    datatype,
+                })
+            }
+            Alteration::AlterColumn(column, ColumnAlteration::Mask(mask)) => {
+                Statement::AlterTableColumnMask(AlterTableColumnMask {
+                    database,
+                    name,
+                    column,
+                    mask: mask.map(|(expression, sql)| (expression, sql.to_string())),
+                })
+            }
+            Alteration::AlterColumn(column, ColumnAlteration::Schema(schema)) => {
+                Statement::AlterTableColumnSchema(AlterTableColumnSchema {
+                    database,
+                    name,
+                    column,
+                    schema,
+                })
+            }
+        },
+    )(input)
+}
+
+/// ALTER USER name SET variable = value | ALTER USER name RESET variable
+fn alter_user(input: &str) -> ParserResult<Statement> {
+    map(
+        preceded(
+            tuple((kw("ALTER"), ws_0, kw("USER"))),
+            cut(tuple((
+                preceded(ws_0, alt((quoted_string, identifier_str))),
+                preceded(
+                    ws_0,
+                    alt((
+                        map(
+                            preceded(
+                                pair(kw("SET"), ws_0),
+                                separated_pair(
+                                    identifier_str,
+                                    tuple((ws_0, tag("="), ws_0)),
+                                    variable_value,
+                                ),
+                            ),
+                            |(name, value)| (name, Some(value)),
+                        ),
+                        map(preceded(pair(kw("RESET"), ws_0), identifier_str), |name| {
+                            (name, None)
+                        }),
+                    )),
+                ),
+            ))),
+        ),
+        |(user, (name, value))| Statement::AlterUser(AlterUser { user, name, value }),
+    )(input)
+}
+
+/// GRANT EXECUTE ON DIRECTORY 'path' TO user | REVOKE EXECUTE ON DIRECTORY 'path' FROM user
+fn grant(input: &str) -> ParserResult<Statement> {
+    let object = || {
+        map(
+            preceded(
+                tuple((kw("EXECUTE"), ws_0, kw("ON"), ws_0, kw("DIRECTORY"), ws_0)),
+                quoted_string,
+            ),
+            GrantObject::Directory,
+        )
+    };
+    let user = || preceded(ws_0, alt((quoted_string, identifier_str)));
+    map(
+        alt((
+            map(
+                preceded(
+                    kw("GRANT"),
+                    cut(separated_pair(
+                        preceded(ws_0, object()),
+                        pair(ws_0, kw("TO")),
+                        user(),
+                    )),
+                ),
+                |(object, user)| (false, object, user),
+            ),
+            map(
+                preceded(
+                    kw("REVOKE"),
+                    cut(separated_pair(
+                        preceded(ws_0, object()),
+                        pair(ws_0, kw("FROM")),
+                        user(),
+                    )),
+                ),
+                |(object, user)| (true, object, user),
+            ),
+        )),
+        |(revoke, object, user)| {
+            Statement::Grant(Grant {
+                revoke,
+                object,
+                user,
+            })
+        },
+    )(input)
+}
+
+/// FLUSH SINK [db.]name
+fn flush_sink(input: &str) -> ParserResult<Statement> {
+    map(
+        preceded(
+            tuple((kw("FLUSH"), ws_0, kw("SINK"))),
+            cut(preceded(ws_0, qualified_reference)),
+        ),
+        |(database, name)| Statement::FlushSink(FlushSink { database, name }),
+    )(input)
+}
+
+/// REFRESH MATERIALIZED VIEW [CONCURRENTLY] [db.]name
+fn refresh_materialized_view(input: &str) -> ParserResult<Statement> {
+    map(
+        preceded(
+            tuple((kw("REFRESH"), ws_0, kw("MATERIALIZED"), ws_0, kw("VIEW"))),
+            cut(tuple((
+                opt(preceded(ws_0, kw("CONCURRENTLY"))),
+                preceded(ws_0, qualified_reference),
+            ))),
+        ),
+        |(concurrently, (database, name))| {
+            Statement::RefreshMaterializedView(RefreshMaterializedView {
+                database,
+                name,
+                concurrently: concurrently.is_some(),
+            })
+        },
+    )(input)
+}
+
+/// Samples the files in a directory to work out their columns,
+/// ie INFER SCHEMA FROM 'data/' WITH (format = 'json') SAMPLE 100
+fn infer_schema(input: &str) -> ParserResult<Statement> {
+    map(
+        preceded(
+            tuple((kw("INFER"), ws_0, kw("SCHEMA"))),
+            cut(tuple((
+                preceded(tuple((ws_0, kw("FROM"), ws_0)), quoted_string),
+                opt(preceded(ws_0, serde_options)),
+                opt(preceded(tuple((ws_0, kw("SAMPLE"), ws_0)), sample_size)),
+            ))),
+        ),
+        |(directory, serde_options, sample_size)| {
+            Statement::InferSchema(InferSchema {
+                directory,
+                serde_options: serde_options.unwrap_or_default(),
+                sample_size: sample_size.unwrap_or(DEFAULT_SAMPLE_SIZE),
+            })
+        },
+    )(input)
+}
+
+pub fn sample_size(input: &str) -> ParserResult<usize> {
+    map_res(digit1, str::parse)(input)
+}
+
+/// Sets a session variable, ie SET lc_time = 'de_DE', or a user variable, ie SET @x = 1
+fn set(input: &str) -> ParserResult<Statement> {
+    preceded(
+        kw("SET"),
+        cut(preceded(
+            ws_0,
+            alt((set_user_variable, set_names, set_variable)),
+        )),
+    )(input)
+}
+
+/// NAMES charset [COLLATE collation], sets character_set_client, the collation is ignored
+fn set_names(input: &str) -> ParserResult<Statement> {
+    map(
+        terminated(
+            preceded(pair(kw("NAMES"), ws_0), variable_value),
+            opt(pair(tuple((ws_0, kw("COLLATE"), ws_0)), variable_value)),
+        ),
+        |value| {
+            Statement::SetVariable(SetVariable {
+                name: "character_set_client".to_string(),
+                value,
+            })
+        },
+    )(input)
+}
+
+fn set_variable(input: &str) -> ParserResult<Statement> {
+    map(
+        tuple((
+            identifier_str,
+            preceded(tuple((ws_0, tag("="), ws_0)), variable_value),
+        )),
+        |(name, value)| Statement::SetVariable(SetVariable { name, value }),
+    )(input)
+}
+
+/// @name = expr or @name = (SELECT ...)
+fn set_user_variable(input: &str) -> ParserResult<Statement> {
+    map(
+        tuple((
+            preceded(pair(tag("@"), not(tag("@"))), identifier_str),
+            preceded(
+                tuple((ws_0, tag("="), ws_0)),
+                alt((
+                    delimited(pair(tag("("), ws_0), select, pair(ws_0, tag(")"))),
+                    map(expression, |expression| {
+                        LogicalOperator::Project(Project {
+                            distinct: false,
+                            expressions: vec![NamedExpression {
+                                expression,
+                                alias: None,
+                            }],
+                            source: Box::from(LogicalOperator::Single),
+                        })
+                    }),
+                )),
+            ),
+        )),
+        |(name, query)| Statement::SetUserVariable(SetUserVariable { name, query }),
+    )(input)
+}
+
+fn variable_value(input: &str) -> ParserResult<String> {
+    alt((
+        quoted_string,
+        map(digit1, String::from),
+        // ON is a keyword so isn't a valid identifier
+        map(kw("ON"), |_| String::from("on")),
+        identifier_str,
+    ))(input)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use ast::expr::{Expression, NamedExpression};
-    use ast::rel::logical::{LogicalOperator, Project};
+    use ast::rel::logical::{ErrorPolicy, FileFormat, LogicalOperator, Project, SerdeOptions};
+
+    #[test]
+    fn test_infer_schema() {
+        assert_eq!(
+            statement(r#"INFER SCHEMA FROM 'data/' WITH (format = 'json') SAMPLE 10"#)
+                .unwrap()
+                .1,
+            Statement::InferSchema(InferSchema {
+                directory: "data/".to_string(),
+                serde_options: SerdeOptions {
+                    delimiter: b',',
+                    format: FileFormat::Json,
+                    on_error: ErrorPolicy::Fail,
+                    resume: false,
+                    max_rows_per_second: None,
+                    max_batch_rows: None,
+                    changes: None,
+                    snapshot: None
+                },
+                sample_size: 10
+            })
+        );
+        assert_eq!(
+            statement(r#"infer schema from "data/""#).unwrap().1,
+            Statement::InferSchema(InferSchema {
+                directory: "data/".to_string(),
+                serde_options: SerdeOptions::default(),
+                sample_size: DEFAULT_SAMPLE_SIZE
+            })
+        );
+    }
 
     #[test]
     fn test_statement_select() {
@@ -100,6 +588,22 @@ mod tests {
                     },],
                     source: Box::from(LogicalOperator::Single)
                 }),
+                types: false,
+            })
+        );
+
+        assert_eq!(
+            statement("EXPLAIN TYPES SELECT 1").unwrap().1,
+            Statement::Explain(Explain {
+                operator: LogicalOperator::Project(Project {
+                    distinct: false,
+                    expressions: vec![NamedExpression {
+                        expression: Expression::from(1),
+                        alias: None
+                    },],
+                    source: Box::from(LogicalOperator::Single)
+                }),
+                types: true,
             })
         );
     }
@@ -112,6 +616,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_kill() {
+        assert_eq!(statement("KILL 12").unwrap().1, Statement::Kill(12));
+        assert_eq!(
+            statement("kill connection 12").unwrap().1,
+            Statement::Kill(12)
+        );
+        assert_eq!(statement("KILL QUERY 3").unwrap().1, Statement::Kill(3));
+        assert!(statement("KILL foo").is_err());
+    }
+
+    #[test]
+    fn test_transaction() {
+        assert_eq!(statement("BEGIN").unwrap().1, Statement::Begin);
+        assert_eq!(statement("begin work").unwrap().1, Statement::Begin);
+        assert_eq!(statement("START TRANSACTION").unwrap().1, Statement::Begin);
+        assert_eq!(statement("COMMIT").unwrap().1, Statement::Commit);
+        assert_eq!(statement("rollback work").unwrap().1, Statement::Rollback);
+    }
+
+    #[test]
+    fn test_vacuum() {
+        assert_eq!(statement("VACUUM").unwrap().1, Statement::Vacuum);
+        assert_eq!(statement("vacuum").unwrap().1, Statement::Vacuum);
+    }
+
     #[test]
     fn test_compact() {
         assert_eq!(
@@ -122,4 +652,319 @@ mod tests {
             })
         );
     }
+
+    #[test]
+    fn test_alter_source() {
+        assert_eq!(
+            statement("ALTER SOURCE foo.events PAUSE").unwrap().1,
+            Statement::AlterSource(AlterSource {
+                database: Some("foo".to_string()),
+                name: "events".to_string(),
+                paused: true
+            })
+        );
+        assert_eq!(
+            statement("alter source events resume").unwrap().1,
+            Statement::AlterSource(AlterSource {
+                database: None,
+                name: "events".to_string(),
+                paused: false
+            })
+        );
+        assert!(statement("ALTER SOURCE events STOP").is_err());
+    }
+
+    #[test]
+    fn test_alter_table_storage_tier() {
+        assert_eq!(
+            statement("ALTER TABLE foo.events SET STORAGE TIER COLD LOCATION '/archive/events'")
+                .unwrap()
+                .1,
+            Statement::AlterTableStorageTier(AlterTableStorageTier {
+                database: Some("foo".to_string()),
+                name: "events".to_string(),
+                tier: StorageTier::Cold("/archive/events".to_string()),
+            })
+        );
+        assert_eq!(
+            statement("alter table events set storage tier hot")
+                .unwrap()
+                .1,
+            Statement::AlterTableStorageTier(AlterTableStorageTier {
+                database: None,
+                name: "events".to_string(),
+                tier: StorageTier::Hot,
+            })
+        );
+        assert!(statement("ALTER TABLE events SET STORAGE TIER COLD").is_err());
+    }
+
+    #[test]
+    fn test_alter_table_modify_column() {
+        assert_eq!(
+            statement("ALTER TABLE foo.events MODIFY COLUMN amount DECIMAL(12, 2)")
+                .unwrap()
+                .1,
+            Statement::AlterTableModifyColumn(AlterTableModifyColumn {
+                database: Some("foo".to_string()),
+                name: "events".to_string(),
+                column: "amount".to_string(),
+                datatype: DataType::Decimal(12, 2),
+            })
+        );
+        assert_eq!(
+            statement("alter table events modify id bigint").unwrap().1,
+            Statement::AlterTableModifyColumn(AlterTableModifyColumn {
+                database: None,
+                name: "events".to_string(),
+                column: "id".to_string(),
+                datatype: DataType::BigInt,
+            })
+        );
+        assert!(statement("ALTER TABLE events MODIFY COLUMN id").is_err());
+    }
+
+    #[test]
+    fn test_alter_table_column_mask() {
+        assert_eq!(
+            statement(
+                r#"ALTER TABLE foo.customers ALTER COLUMN card SET MASK USING (mask_text(card, 4))"#
+            )
+            .unwrap()
+            .1,
+            Statement::AlterTableColumnMask(AlterTableColumnMask {
+                database: Some("foo".to_string()),
+                name: "customers".to_string(),
+                column: "card".to_string(),
+                mask: Some((
+                    expression("mask_text(card, 4)").unwrap().1,
+                    "mask_text(card, 4)".to_string()
+                )),
+            })
+        );
+        assert_eq!(
+            statement("alter table customers alter card drop mask")
+                .unwrap()
+                .1,
+            Statement::AlterTableColumnMask(AlterTableColumnMask {
+                database: None,
+                name: "customers".to_string(),
+                column: "card".to_string(),
+                mask: None,
+            })
+        );
+        assert!(statement("ALTER TABLE customers ALTER COLUMN card SET MASK").is_err());
+    }
+
+    #[test]
+    fn test_alter_table_column_schema() {
+        assert_eq!(
+            statement(r#"ALTER TABLE orders ALTER COLUMN doc SET SCHEMA '{"type": "object"}'"#)
+                .unwrap()
+                .1,
+            Statement::AlterTableColumnSchema(AlterTableColumnSchema {
+                database: None,
+                name: "orders".to_string(),
+                column: "doc".to_string(),
+                schema: Some(r#"{"type": "object"}"#.to_string()),
+            })
+        );
+        assert_eq!(
+            statement("alter table foo.orders alter doc drop schema")
+                .unwrap()
+                .1,
+            Statement::AlterTableColumnSchema(AlterTableColumnSchema {
+                database: Some("foo".to_string()),
+                name: "orders".to_string(),
+                column: "doc".to_string(),
+                schema: None,
+            })
+        );
+        assert!(statement("ALTER TABLE orders ALTER COLUMN doc SET SCHEMA").is_err());
+    }
+
+    #[test]
+    fn test_alter_user() {
+        assert_eq!(
+            statement("ALTER USER 'Bob' SET time_zone = '+02:00'")
+                .unwrap()
+                .1,
+            Statement::AlterUser(AlterUser {
+                user: "Bob".to_string(),
+                name: "time_zone".to_string(),
+                value: Some("+02:00".to_string()),
+            })
+        );
+        assert_eq!(
+            statement("alter user bob reset time_zone").unwrap().1,
+            Statement::AlterUser(AlterUser {
+                user: "bob".to_string(),
+                name: "time_zone".to_string(),
+                value: None,
+            })
+        );
+        assert!(statement("ALTER USER bob SET time_zone").is_err());
+    }
+
+    #[test]
+    fn test_grant() {
+        assert_eq!(
+            statement("GRANT EXECUTE ON DIRECTORY 'data/' TO bob")
+                .unwrap()
+                .1,
+            Statement::Grant(Grant {
+                revoke: false,
+                object: GrantObject::Directory("data/".to_string()),
+                user: "bob".to_string(),
+            })
+        );
+        assert_eq!(
+            statement("revoke execute on directory \"data/\" from 'Bob'")
+                .unwrap()
+                .1,
+            Statement::Grant(Grant {
+                revoke: true,
+                object: GrantObject::Directory("data/".to_string()),
+                user: "Bob".to_string(),
+            })
+        );
+        assert!(statement("GRANT EXECUTE ON DIRECTORY 'data/' FROM bob").is_err());
+        assert!(statement("REVOKE EXECUTE ON DIRECTORY 'data/' TO bob").is_err());
+    }
+
+    #[test]
+    fn test_refresh_materialized_view() {
+        assert_eq!(
+            statement("REFRESH MATERIALIZED VIEW CONCURRENTLY foo.totals")
+                .unwrap()
+                .1,
+            Statement::RefreshMaterializedView(RefreshMaterializedView {
+                database: Some("foo".to_string()),
+                name: "totals".to_string(),
+                concurrently: true
+            })
+        );
+        assert_eq!(
+            statement("refresh materialized view totals").unwrap().1,
+            Statement::RefreshMaterializedView(RefreshMaterializedView {
+                database: None,
+                name: "totals".to_string(),
+                concurrently: false
+            })
+        );
+    }
+
+    #[test]
+    fn test_explain_maintenance() {
+        assert_eq!(
+            statement("EXPLAIN MAINTENANCE FOR VIEW foo.totals")
+                .unwrap()
+                .1,
+            Statement::ExplainMaintenance(ExplainMaintenance {
+                database: Some("foo".to_string()),
+                name: "totals".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_analyze() {
+        assert_eq!(
+            statement("ANALYZE TABLE foo.orders").unwrap().1,
+            Statement::AnalyzeTable(AnalyzeTable {
+                database: Some("foo".to_string()),
+                name: "orders".to_string()
+            })
+        );
+        assert_eq!(
+            statement("analyze orders").unwrap().1,
+            Statement::AnalyzeTable(AnalyzeTable {
+                database: None,
+                name: "orders".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_flush_sink() {
+        assert_eq!(
+            statement("FLUSH SINK orders_sink").unwrap().1,
+            Statement::FlushSink(FlushSink {
+                database: None,
+                name: "orders_sink".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_set() {
+        let expected = Statement::SetVariable(SetVariable {
+            name: "lc_time".to_string(),
+            value: "de_DE".to_string(),
+        });
+        assert_eq!(statement("SET lc_time = 'de_DE'").unwrap().1, expected);
+        // Unquoted values are identifiers so get lowercased
+        assert_eq!(
+            statement("set LC_TIME=de_DE").unwrap().1,
+            Statement::SetVariable(SetVariable {
+                name: "lc_time".to_string(),
+                value: "de_de".to_string(),
+            })
+        );
+        assert_eq!(
+            statement("SET sql_select_limit = 100").unwrap().1,
+            Statement::SetVariable(SetVariable {
+                name: "sql_select_limit".to_string(),
+                value: "100".to_string(),
+            })
+        );
+        assert_eq!(
+            statement("SET deterministic_order = ON").unwrap().1,
+            Statement::SetVariable(SetVariable {
+                name: "deterministic_order".to_string(),
+                value: "on".to_string(),
+            })
+        );
+        assert_eq!(
+            statement("SET NAMES utf8mb4 COLLATE utf8mb4_general_ci")
+                .unwrap()
+                .1,
+            Statement::SetVariable(SetVariable {
+                name: "character_set_client".to_string(),
+                value: "utf8mb4".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_set_user_variable() {
+        assert_eq!(
+            statement("SET @X = 1").unwrap().1,
+            Statement::SetUserVariable(SetUserVariable {
+                name: "x".to_string(),
+                query: LogicalOperator::Project(Project {
+                    distinct: false,
+                    expressions: vec![NamedExpression {
+                        expression: Expression::from(1),
+                        alias: None
+                    }],
+                    source: Box::from(LogicalOperator::Single)
+                }),
+            })
+        );
+        assert_eq!(
+            statement("SET @x = ( SELECT 1 )").unwrap().1,
+            Statement::SetUserVariable(SetUserVariable {
+                name: "x".to_string(),
+                query: LogicalOperator::Project(Project {
+                    distinct: false,
+                    expressions: vec![NamedExpression {
+                        expression: Expression::from(1),
+                        alias: None
+                    }],
+                    source: Box::from(LogicalOperator::Single)
+                }),
+            })
+        );
+    }
 }
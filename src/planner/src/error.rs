@@ -16,6 +16,39 @@ pub enum PlannerError {
     InsertMismatch(Vec<DataType>, Vec<DataType>),
     // function name, location name(ie where clause, sort expression)
     AggregateNotAllowed(&'static str, &'static str),
+    // Number of columns returned by an IN subquery
+    SubqueryColumnCount(usize),
+    // Reason the subquery couldn't be turned into a join
+    SubqueryNotSupported(&'static str),
+    // Name of the view that a tablesample clause was used on
+    TableSampleNotSupported(String),
+    // The operator between an insert and a resumable file source that reads all of its input
+    ResumeNotSupported(&'static str),
+    InsertIntoView,
+    // A column named by an insert that isn't in the table
+    InsertColumnNotFound(String),
+    // A column named more than once by an insert
+    InsertColumnRepeated(String),
+    // The table being deleted from, users that see masked columns can't delete the real rows
+    DeleteFromMaskedTable(String),
+    // The label and the enum type it was inserted into
+    UnknownEnumLabel(String, DataType),
+    // The user and the object they don't have EXECUTE on
+    PermissionDenied(String, String),
+    // The function used with an OVER clause
+    WindowFunctionNotSupported(String),
+    // Where the window function was used, ie where clause
+    WindowNotAllowed(&'static str),
+    // The views expanded, the first view is repeated at the end
+    ViewRecursion(Vec<String>),
+    // The view that would've been expanded and the max_recursion_depth
+    ViewDepthExceeded(String, u32),
+    // A statement that writes was run with the as_of session variable set
+    WriteAsOf,
+    // The file option COPY doesn't support, ie resume
+    CopyOptionNotSupported(&'static str),
+    // The type being cast from and the type being cast to, see the cast matrix
+    CastNotSupported(DataType, DataType),
 }
 
 impl From<FunctionResolutionError> for PlannerError {
@@ -87,6 +120,71 @@ impl Display for PlannerError {
             PlannerError::AggregateNotAllowed(function_name, location) => {
                 f.write_fmt(format_args!("Aggregate function {} found in {},\nAggregate functions can only be used in select clauses", function_name, location))
             }
+            PlannerError::SubqueryColumnCount(count) => f.write_fmt(format_args!(
+                "Subquery returns {} columns, expected 1",
+                count
+            )),
+            PlannerError::SubqueryNotSupported(reason) => {
+                f.write_fmt(format_args!("Subquery not supported - {}", reason))
+            }
+            PlannerError::TableSampleNotSupported(view) => f.write_fmt(format_args!(
+                "TABLESAMPLE can only be used on tables, {} is a view",
+                view
+            )),
+            PlannerError::ResumeNotSupported(operator) => f.write_fmt(format_args!(
+                "Resumable file sources can't be inserted through a {}, the source offsets wouldn't line up with the rows written",
+                operator
+            )),
+            PlannerError::InsertIntoView => {
+                f.write_str("Rows can only be inserted into tables, not views")
+            }
+            PlannerError::InsertColumnNotFound(column) => f.write_fmt(format_args!(
+                "Column {} isn't in the table being inserted into",
+                column
+            )),
+            PlannerError::InsertColumnRepeated(column) => f.write_fmt(format_args!(
+                "Column {} is named more than once in the insert",
+                column
+            )),
+            PlannerError::DeleteFromMaskedTable(table) => f.write_fmt(format_args!(
+                "Can't delete from {} as some of its columns are masked",
+                table
+            )),
+            PlannerError::UnknownEnumLabel(label, datatype) => f.write_fmt(format_args!(
+                "\"{}\" isn't one of the labels of {}",
+                label, datatype
+            )),
+            PlannerError::PermissionDenied(user, object) => f.write_fmt(format_args!(
+                "User {} doesn't have EXECUTE on {}",
+                user, object
+            )),
+            PlannerError::WindowFunctionNotSupported(function) => f.write_fmt(format_args!(
+                "{} can't be used as a window function, only row_number() and aggregates of plain expressions can",
+                function
+            )),
+            PlannerError::WindowNotAllowed(location) => f.write_fmt(format_args!(
+                "Window functions found in {},\nWindow functions can only be used in the select list of queries without aggregates",
+                location
+            )),
+            PlannerError::ViewRecursion(views) => f.write_fmt(format_args!(
+                "View {} refers back to itself - {}",
+                views.last().unwrap(),
+                views.join(" -> ")
+            )),
+            PlannerError::ViewDepthExceeded(view, depth) => f.write_fmt(format_args!(
+                "View {} is nested more than {} views deep, see max_recursion_depth",
+                view, depth
+            )),
+            PlannerError::WriteAsOf => {
+                f.write_str("Statements can only read while as_of is set, SET as_of = DEFAULT to write")
+            }
+            PlannerError::CopyOptionNotSupported(option) => f.write_fmt(format_args!(
+                "COPY doesn't support the {} option, read the file from a DIRECTORY source instead",
+                option
+            )),
+            PlannerError::CastNotSupported(from, to) => {
+                f.write_fmt(format_args!("Values of type {} can't be cast to {}", from, to))
+            }
         }
     }
 }
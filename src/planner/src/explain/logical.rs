@@ -1,6 +1,6 @@
 use crate::explain::ExplainNode;
 use ast::expr::{Expression, NamedExpression, SortExpression};
-use ast::rel::logical::LogicalOperator;
+use ast::rel::logical::{JoinType, LogicalOperator};
 use data::DataType;
 use std::borrow::Cow;
 
@@ -10,6 +10,7 @@ impl ExplainNode for LogicalOperator {
             LogicalOperator::Single => "SINGLE".to_string(),
             LogicalOperator::GroupBy(_) => "GROUP".to_string(),
             LogicalOperator::Project(_) => "PROJECT".to_string(),
+            LogicalOperator::Window(_) => "WINDOW".to_string(),
             LogicalOperator::Sort(_) => "SORT".to_string(),
             LogicalOperator::Values(_) => "VALUES".to_string(),
             LogicalOperator::ResolvedTable(_) | LogicalOperator::TableReference(_) => {
@@ -24,7 +25,14 @@ impl ExplainNode for LogicalOperator {
             LogicalOperator::TableInsert(_) => "INSERT".to_string(),
             LogicalOperator::NegateFreq(_) => "NEGATE".to_string(),
             LogicalOperator::FileScan(_) => "FILE_SCAN".to_string(),
-            LogicalOperator::Join(_) => "JOIN".to_string(),
+            LogicalOperator::FileCopy(_) => "FILE_COPY".to_string(),
+            LogicalOperator::Join(join) => match join.join_type {
+                JoinType::LeftSemi => "SEMI_JOIN".to_string(),
+                JoinType::LeftAnti => "ANTI_JOIN".to_string(),
+                JoinType::Inner | JoinType::LeftOuter | JoinType::RightOuter => "JOIN".to_string(),
+                JoinType::FullOuter => "FULL_JOIN".to_string(),
+                JoinType::LeftSingle => "SINGLE_JOIN".to_string(),
+            },
         }
     }
 
@@ -33,6 +41,9 @@ impl ExplainNode for LogicalOperator {
             LogicalOperator::TableAlias(table_alias) => table_alias.source.expressions(),
             LogicalOperator::Project(project) => &project.expressions,
             LogicalOperator::GroupBy(group_by) => &group_by.expressions,
+            LogicalOperator::Window(window) => &window.functions,
+            LogicalOperator::FileScan(file_scan) => &file_scan.columns,
+            LogicalOperator::FileCopy(file_copy) => &file_copy.columns,
             _ => &[],
         }
     }
@@ -41,7 +52,9 @@ impl ExplainNode for LogicalOperator {
         match self {
             LogicalOperator::TableAlias(table_alias) => table_alias.source.table_columns(),
             LogicalOperator::ResolvedTable(table) => Cow::from(&table.columns),
-            LogicalOperator::FileScan(_) => Cow::from(vec![("data".to_string(), DataType::Json)]),
+            LogicalOperator::FileScan(file_scan) if file_scan.columns.is_empty() => {
+                Cow::from(vec![("data".to_string(), DataType::Json)])
+            }
             _ => Cow::from(vec![]),
         }
     }
@@ -86,6 +99,7 @@ impl ExplainNode for LogicalOperator {
                 vec![("source".to_string(), project.source.as_ref())]
             }
             LogicalOperator::Sort(sort) => vec![("source".to_string(), sort.source.as_ref())],
+            LogicalOperator::Window(window) => vec![("source".to_string(), window.source.as_ref())],
             LogicalOperator::Join(join) => vec![
                 ("left".to_string(), join.left.as_ref()),
                 ("right".to_string(), join.right.as_ref()),
@@ -107,6 +121,7 @@ impl ExplainNode for LogicalOperator {
             | LogicalOperator::ResolvedTable(_)
             | LogicalOperator::Single
             | LogicalOperator::FileScan(_)
+            | LogicalOperator::FileCopy(_)
             | LogicalOperator::TableReference(_) => vec![],
         }
     }
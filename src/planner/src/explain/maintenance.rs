@@ -0,0 +1,230 @@
+use crate::Planner;
+use ast::expr::Expression;
+use ast::rel::logical::{LogicalOperator, Values};
+use data::DataType;
+
+/// A step on the path a change to an input takes up to the view's output.
+struct MaintenanceStep {
+    input: String,
+    operator: String,
+    state: String,
+    work_per_change: String,
+}
+
+impl Planner {
+    /// Explains how the view would be maintained incrementally, each input gets the chain of
+    /// operators a change to it passes through, along with the state each one needs to persist
+    /// and a rough idea of the work a single changed row causes.
+    /// The resultant plan has 4 columns: input, operator, state and work_per_change.
+    pub fn explain_maintenance(&self, operator: &LogicalOperator) -> LogicalOperator {
+        let mut steps = vec![];
+        let mut path = vec![];
+        visit_inputs(operator, &mut path, &mut steps);
+
+        let data = steps
+            .into_iter()
+            .map(|step| {
+                vec![
+                    Expression::from(step.input),
+                    Expression::from(step.operator),
+                    Expression::from(step.state),
+                    Expression::from(step.work_per_change),
+                ]
+            })
+            .collect();
+
+        LogicalOperator::Values(Values {
+            fields: vec![
+                (DataType::Text, String::from("input")),
+                (DataType::Text, String::from("operator")),
+                (DataType::Text, String::from("state")),
+                (DataType::Text, String::from("work_per_change")),
+            ],
+            data,
+        })
+    }
+}
+
+/// Walks down to each input, path holds the operators above the current one along with which
+/// of their children we came from.
+fn visit_inputs<'a>(
+    operator: &'a LogicalOperator,
+    path: &mut Vec<(&'a LogicalOperator, usize)>,
+    steps: &mut Vec<MaintenanceStep>,
+) {
+    let leaf = match operator {
+        LogicalOperator::ResolvedTable(_) => Some("TABLE"),
+        LogicalOperator::FileScan(_) => Some("FILE_SCAN"),
+        _ => None,
+    };
+    if let Some(leaf) = leaf {
+        let input = input_name(operator, path);
+        steps.push(MaintenanceStep {
+            input: input.clone(),
+            operator: leaf.to_string(),
+            state: String::new(),
+            work_per_change: "1 row".to_string(),
+        });
+        for (ancestor, child_idx) in path.iter().rev() {
+            if let Some((operator, state, work_per_change)) = maintenance_step(ancestor, *child_idx)
+            {
+                steps.push(MaintenanceStep {
+                    input: input.clone(),
+                    operator,
+                    state,
+                    work_per_change,
+                });
+            }
+        }
+        return;
+    }
+
+    for (idx, child) in children(operator).into_iter().enumerate() {
+        path.push((operator, idx));
+        visit_inputs(child, path, steps);
+        path.pop();
+    }
+}
+
+/// Describes the operator's part in maintaining the view when a row arrives from the child at
+/// child_idx, None for operators that don't take part ie aliases.
+fn maintenance_step(
+    operator: &LogicalOperator,
+    child_idx: usize,
+) -> Option<(String, String, String)> {
+    let stateless = |name: &str| Some((name.to_string(), String::new(), "1 row".to_string()));
+    match operator {
+        LogicalOperator::TableAlias(_) => None,
+        LogicalOperator::Project(_) => stateless("PROJECT"),
+        LogicalOperator::Filter(_) => stateless("FILTER"),
+        LogicalOperator::UnionAll(_) => stateless("UNION_ALL"),
+        LogicalOperator::NegateFreq(_) => stateless("NEGATE"),
+        LogicalOperator::Sort(_) => stateless("SORT"),
+        LogicalOperator::Join(join) => {
+            let (name, other) = if child_idx == 0 {
+                ("JOIN(left)", &join.right)
+            } else {
+                ("JOIN(right)", &join.left)
+            };
+            let other = input_names(other).join(", ");
+            Some((
+                name.to_string(),
+                format!("rows of {} keyed on the join condition", other),
+                format!("probe {} for the matching rows", other),
+            ))
+        }
+        LogicalOperator::GroupBy(group_by) => {
+            let state = if group_by.key_expressions.is_empty() {
+                "1 group".to_string()
+            } else {
+                format!(
+                    "1 row per group, keyed on {} expression(s)",
+                    group_by.key_expressions.len()
+                )
+            };
+            let mut non_retractable = vec![];
+            for named_expression in &group_by.expressions {
+                non_retractable_aggregates(&named_expression.expression, &mut non_retractable);
+            }
+            if non_retractable.is_empty() {
+                Some(("GROUP".to_string(), state, "update 1 group".to_string()))
+            } else {
+                Some((
+                    "GROUP".to_string(),
+                    format!("{} plus the input rows of each group", state),
+                    format!(
+                        "recompute 1 group on removals, {} can't be retracted",
+                        non_retractable.join(", ")
+                    ),
+                ))
+            }
+        }
+        LogicalOperator::Limit(limit) => Some((
+            "LIMIT".to_string(),
+            "all rows of its input".to_string(),
+            format!("recompute the top {} rows", limit.offset + limit.limit),
+        )),
+        LogicalOperator::Window(_) => Some((
+            "WINDOW".to_string(),
+            "all rows of its input".to_string(),
+            "recompute the changed row's partition".to_string(),
+        )),
+        LogicalOperator::TableInsert(_) => stateless("INSERT"),
+        LogicalOperator::Single
+        | LogicalOperator::Values(_)
+        | LogicalOperator::TableReference(_)
+        | LogicalOperator::ResolvedTable(_)
+        | LogicalOperator::FileScan(_)
+        | LogicalOperator::FileCopy(_) => None,
+    }
+}
+
+fn non_retractable_aggregates(expression: &Expression, names: &mut Vec<String>) {
+    if let Expression::CompiledAggregate(aggregate) = expression {
+        let name = aggregate.signature.name.to_string();
+        if !aggregate.function.supports_retract() && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    for child in expression.children() {
+        non_retractable_aggregates(child, names);
+    }
+}
+
+/// Names an input by the nearest alias above it, or its directory for file scans.
+fn input_name(operator: &LogicalOperator, path: &[(&LogicalOperator, usize)]) -> String {
+    if let LogicalOperator::FileScan(file_scan) = operator {
+        return format!("'{}'", file_scan.directory);
+    }
+    path.iter()
+        .rev()
+        .find_map(|(ancestor, _)| match ancestor {
+            LogicalOperator::TableAlias(table_alias) => Some(table_alias.alias.clone()),
+            _ => None,
+        })
+        .unwrap_or_else(|| "table".to_string())
+}
+
+/// The names of all the inputs under operator.
+fn input_names(operator: &LogicalOperator) -> Vec<String> {
+    let mut names = vec![];
+    collect_inputs(operator, &mut vec![], &mut names);
+    names
+}
+
+fn collect_inputs<'a>(
+    operator: &'a LogicalOperator,
+    path: &mut Vec<(&'a LogicalOperator, usize)>,
+    names: &mut Vec<String>,
+) {
+    if let LogicalOperator::ResolvedTable(_) | LogicalOperator::FileScan(_) = operator {
+        names.push(input_name(operator, path));
+    }
+    for child in children(operator) {
+        path.push((operator, 0));
+        collect_inputs(child, path, names);
+        path.pop();
+    }
+}
+
+fn children(operator: &LogicalOperator) -> Vec<&LogicalOperator> {
+    match operator {
+        LogicalOperator::Project(project) => vec![project.source.as_ref()],
+        LogicalOperator::GroupBy(group_by) => vec![group_by.source.as_ref()],
+        LogicalOperator::Filter(filter) => vec![filter.source.as_ref()],
+        LogicalOperator::Limit(limit) => vec![limit.source.as_ref()],
+        LogicalOperator::Sort(sort) => vec![sort.source.as_ref()],
+        LogicalOperator::Window(window) => vec![window.source.as_ref()],
+        LogicalOperator::TableAlias(table_alias) => vec![table_alias.source.as_ref()],
+        LogicalOperator::TableInsert(table_insert) => vec![table_insert.source.as_ref()],
+        LogicalOperator::UnionAll(union_all) => union_all.sources.iter().collect(),
+        LogicalOperator::NegateFreq(source) => vec![source.as_ref()],
+        LogicalOperator::Join(join) => vec![join.left.as_ref(), join.right.as_ref()],
+        LogicalOperator::Single
+        | LogicalOperator::Values(_)
+        | LogicalOperator::TableReference(_)
+        | LogicalOperator::ResolvedTable(_)
+        | LogicalOperator::FileScan(_)
+        | LogicalOperator::FileCopy(_) => vec![],
+    }
+}
@@ -1,8 +1,9 @@
 mod logical;
+mod maintenance;
 
 use crate::utils::expr::type_for_expression;
 use crate::Planner;
-use ast::expr::{Expression, NamedExpression, SortExpression};
+use ast::expr::{quote_function_name, Expression, NamedExpression, SortExpression};
 use ast::rel::logical::{LogicalOperator, Values};
 use data::DataType;
 use std::borrow::Cow;
@@ -42,46 +43,57 @@ impl Planner {
     /// expression_type - The resultant type of any expression
     /// expression - The expression itself.
     pub fn explain<N: ExplainNode>(&self, node: &N) -> LogicalOperator {
-        let mut lines = vec![];
-        let mut padding = Padding::default();
-        render_node(node, &mut lines, &mut padding);
-
-        let data = lines
-            .into_iter()
-            .map(|line| {
-                let idx_datum = line
-                    .column_idx
-                    .map(|idx| Expression::from(idx.to_string()))
-                    .unwrap_or_else(|| Expression::from(""));
-                let datatype_datum = line
-                    .expression_type
-                    .map(|dt| Expression::from(dt.to_string()))
-                    .unwrap_or_else(|| Expression::from(""));
-                let expr_datum = line
-                    .expression
-                    .map(Expression::from)
-                    .unwrap_or_else(|| Expression::from(""));
-                vec![
-                    Expression::from(line.tree),
-                    idx_datum,
-                    datatype_datum,
-                    expr_datum,
-                ]
-            })
-            .collect();
-
-        LogicalOperator::Values(Values {
-            fields: vec![
-                (DataType::Text, String::from("tree")),
-                (DataType::Text, String::from("col_idx")),
-                (DataType::Text, String::from("datatype")),
-                (DataType::Text, String::from("expression")),
-            ],
-            data,
-        })
+        explain_lines(node, false)
+    }
+
+    /// Like explain but every function call, column reference and constant in the expressions
+    /// is annotated with its type, pg_typeof style, to show where implicit casts were added and
+    /// what precision/scale decimal calculations end up with.
+    pub fn explain_types<N: ExplainNode>(&self, node: &N) -> LogicalOperator {
+        explain_lines(node, true)
     }
 }
 
+fn explain_lines<N: ExplainNode>(node: &N, types: bool) -> LogicalOperator {
+    let mut lines = vec![];
+    let mut padding = Padding::default();
+    render_node(node, &mut lines, &mut padding, types);
+
+    let data = lines
+        .into_iter()
+        .map(|line| {
+            let idx_datum = line
+                .column_idx
+                .map(|idx| Expression::from(idx.to_string()))
+                .unwrap_or_else(|| Expression::from(""));
+            let datatype_datum = line
+                .expression_type
+                .map(|dt| Expression::from(dt.to_string()))
+                .unwrap_or_else(|| Expression::from(""));
+            let expr_datum = line
+                .expression
+                .map(Expression::from)
+                .unwrap_or_else(|| Expression::from(""));
+            vec![
+                Expression::from(line.tree),
+                idx_datum,
+                datatype_datum,
+                expr_datum,
+            ]
+        })
+        .collect();
+
+    LogicalOperator::Values(Values {
+        fields: vec![
+            (DataType::Text, String::from("tree")),
+            (DataType::Text, String::from("col_idx")),
+            (DataType::Text, String::from("datatype")),
+            (DataType::Text, String::from("expression")),
+        ],
+        data,
+    })
+}
+
 /// A single explain line
 struct ExplainLine {
     tree: String,
@@ -125,7 +137,12 @@ impl ExplainLine {
     }
 }
 
-fn render_node<N: ExplainNode>(node: &N, lines: &mut Vec<ExplainLine>, padding: &mut Padding) {
+fn render_node<N: ExplainNode>(
+    node: &N,
+    lines: &mut Vec<ExplainLine>,
+    padding: &mut Padding,
+    types: bool,
+) {
     lines.push(ExplainLine::tree_only(padding, &node.node_name()));
     padding.push(" |");
     // output cols
@@ -138,7 +155,7 @@ fn render_node<N: ExplainNode>(node: &N, lines: &mut Vec<ExplainLine>, padding:
                 expr.alias.as_ref().unwrap(),
                 idx,
                 type_for_expression(&expr.expression),
-                expr.expression.to_string(),
+                render_expression(&expr.expression, types),
             ));
         }
         padding.pop();
@@ -172,7 +189,7 @@ fn render_node<N: ExplainNode>(node: &N, lines: &mut Vec<ExplainLine>, padding:
         lines.push(ExplainLine::expr_only(
             padding,
             type_for_expression(expr),
-            expr.to_string(),
+            render_expression(expr, types),
         ));
     }
 
@@ -183,7 +200,7 @@ fn render_node<N: ExplainNode>(node: &N, lines: &mut Vec<ExplainLine>, padding:
             lines.push(ExplainLine::expr_only(
                 padding,
                 type_for_expression(expr),
-                expr.to_string(),
+                render_expression(expr, types),
             ));
         }
     }
@@ -196,7 +213,11 @@ fn render_node<N: ExplainNode>(node: &N, lines: &mut Vec<ExplainLine>, padding:
             lines.push(ExplainLine::expr_only(
                 padding,
                 type_for_expression(&se.expression),
-                format!("{} ({})", &se.expression, se.ordering),
+                format!(
+                    "{} ({})",
+                    render_expression(&se.expression, types),
+                    se.ordering
+                ),
             ));
         }
         padding.pop();
@@ -209,13 +230,49 @@ fn render_node<N: ExplainNode>(node: &N, lines: &mut Vec<ExplainLine>, padding:
             &format!("{}:", source_name),
         ));
         padding.push("  ");
-        render_node(*source, lines, padding);
+        render_node(*source, lines, padding, types);
         padding.pop();
     }
 
     padding.pop()
 }
 
+fn render_expression(expr: &Expression, types: bool) -> String {
+    if types {
+        TypedExpression(expr).to_string()
+    } else {
+        expr.to_string()
+    }
+}
+
+/// Formats an expression with the type of each of its parts, ie
+/// `+`(to_bigint(<OFFSET 0>::INTEGER)::BIGINT, 1::BIGINT)::BIGINT
+struct TypedExpression<'a>(&'a Expression);
+
+impl Display for TypedExpression<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (name, args, ret) = match self.0 {
+            Expression::CompiledFunctionCall(function_call) => (
+                function_call.signature.name,
+                &function_call.args,
+                function_call.signature.ret,
+            ),
+            Expression::CompiledAggregate(function_call) => (
+                function_call.signature.name,
+                &function_call.args,
+                function_call.signature.ret,
+            ),
+            expr => return write!(f, "{}::{}", expr, type_for_expression(expr)),
+        };
+        let args = args
+            .iter()
+            .map(|arg| TypedExpression(arg).to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(f, "{}({})::{}", quote_function_name(name), args, ret)
+    }
+}
+
 /// A helper class to deal with pushing and popping padding
 #[derive(Default)]
 struct Padding {
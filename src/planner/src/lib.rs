@@ -1,6 +1,7 @@
 mod p1_validation;
 mod p2_optimization;
 mod p3_common_transforms;
+mod p4_delta_planning;
 mod p4_pit_planning;
 mod utils;
 
@@ -9,11 +10,14 @@ use data::{DataType, Session};
 mod error;
 mod explain;
 use crate::utils::logical::fields_for_operator;
-use ast::rel::logical::LogicalOperator;
+use ast::expr::Expression;
+use ast::rel::logical::{Limit, LogicalOperator, Sort};
 use catalog::Catalog;
 pub use error::*;
 use functions::registry::Registry;
-pub use p4_pit_planning::PointInTimePlan;
+pub use p2_optimization::OPTIMIZER_RULES;
+pub use p4_delta_planning::PartialAggregatePlan;
+pub use p4_pit_planning::{PointInTimePlan, READ_BARRIER_WAIT};
 use std::sync::RwLock;
 
 #[derive(Debug)]
@@ -41,13 +45,100 @@ impl Planner {
         &self,
         query: LogicalOperator,
         session: &Session,
+    ) -> Result<(Vec<Field>, LogicalOperator), PlannerError> {
+        let disabled_rules = session.disabled_optimizer_rules.read().unwrap().clone();
+        self.plan_common_with_rules(query, session, &|rule| !disabled_rules.contains(rule))
+    }
+
+    /// Like plan_common but only runs the optional optimizer rules that enabled returns true for
+    fn plan_common_with_rules(
+        &self,
+        query: LogicalOperator,
+        session: &Session,
+        enabled: &dyn Fn(&str) -> bool,
     ) -> Result<(Vec<Field>, LogicalOperator), PlannerError> {
         let query = self.validate(query, session)?;
-        let query = self.optimize(query, session)?;
+        let query = self.optimize_with_rules(query, session, enabled)?;
         let query = self.common_transforms(query, session)?;
         let fields = fields_for_operator(&query).collect();
         Ok((fields, query))
     }
+
+    /// Wraps a top level select in an implicit limit when the session has sql_select_limit set
+    /// and the query doesn't have a LIMIT of its own.
+    pub fn apply_select_limit(&self, query: LogicalOperator, session: &Session) -> LogicalOperator {
+        match (*session.sql_select_limit.read().unwrap(), query) {
+            (_, query @ LogicalOperator::Limit(_)) | (None, query) => query,
+            (Some(limit), query) => LogicalOperator::Limit(Limit {
+                offset: 0,
+                limit,
+                source: Box::new(query),
+            }),
+        }
+    }
+
+    /// Sorts the output of a top level select when the session has deterministic_order set.
+    /// The sort executor breaks ties using the full row encoding so a sort without any sort
+    /// expressions just orders by the whole row, for queries with an ORDER BY this also means
+    /// rows that tie on the sort expressions still come back in a stable order.
+    pub fn apply_deterministic_order(
+        &self,
+        query: LogicalOperator,
+        session: &Session,
+    ) -> LogicalOperator {
+        if !*session.deterministic_order.read().unwrap() {
+            return query;
+        }
+        match query {
+            LogicalOperator::Sort(_) => query,
+            LogicalOperator::Limit(Limit {
+                offset,
+                limit,
+                source,
+            }) => LogicalOperator::Limit(Limit {
+                offset,
+                limit,
+                source: Box::new(self.apply_deterministic_order(*source, session)),
+            }),
+            query => LogicalOperator::Sort(Sort {
+                sort_expressions: vec![],
+                source: Box::new(query),
+            }),
+        }
+    }
+}
+
+impl Planner {
+    /// Validates the query of a prepared statement, returns the fields the query will return
+    /// along with the types of its parameters.
+    pub fn plan_for_prepare(
+        &self,
+        query: LogicalOperator,
+        session: &Session,
+    ) -> Result<(Vec<Field>, Vec<DataType>), PlannerError> {
+        let mut query = self.validate(query, session)?;
+        let fields = fields_for_operator(&query).collect();
+
+        let mut parameter_types = vec![];
+        query.visit_parameters_mut(&mut |expr| {
+            if let Expression::Parameter(parameter) = expr {
+                if parameter_types.len() <= parameter.index {
+                    parameter_types.resize(parameter.index + 1, DataType::Null);
+                }
+                // The same parameter can end up in many places(ie ? IN (1, 2)), first one wins.
+                if parameter_types[parameter.index] == DataType::Null {
+                    parameter_types[parameter.index] = parameter.datatype;
+                }
+            }
+        });
+        // Anything we couldn't work out we'll let the client send as text
+        for datatype in &mut parameter_types {
+            if *datatype == DataType::Null {
+                *datatype = DataType::Text;
+            }
+        }
+        Ok((fields, parameter_types))
+    }
 }
 
 /// A Field is simply a column name and a type.
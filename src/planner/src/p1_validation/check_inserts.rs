@@ -1,14 +1,19 @@
 use crate::utils::logical::fields_for_operator;
 use crate::PlannerError;
-use ast::rel::logical::LogicalOperator;
+use ast::rel::logical::{FileFormat, LogicalOperator, SerdeOptions};
 
-/// Checks to make sure we're inserting rows with the right datatypes/length
+/// Checks to make sure we're inserting rows with the right datatypes/length.
+/// Resumable file sources are committed as the rows stream through, so they can't sit under
+/// anything that reads in all of its input first.
 pub(super) fn check_inserts(operator: &mut LogicalOperator) -> Result<(), PlannerError> {
     for child in operator.children_mut() {
         check_inserts(child)?;
     }
 
     if let LogicalOperator::TableInsert(table_insert) = operator {
+        if !matches!(*table_insert.table, LogicalOperator::ResolvedTable(_)) {
+            return Err(PlannerError::InsertIntoView);
+        }
         let table_fields: Vec<_> = fields_for_operator(&table_insert.table)
             .map(|f| f.data_type)
             .collect();
@@ -16,12 +21,62 @@ pub(super) fn check_inserts(operator: &mut LogicalOperator) -> Result<(), Planne
             .map(|f| f.data_type)
             .collect();
 
+        if let LogicalOperator::FileCopy(file_copy) = table_insert.source.as_ref() {
+            check_copy_options(&file_copy.serde_options)?;
+        }
         if table_fields != source_fields {
             Err(PlannerError::InsertMismatch(table_fields, source_fields))
         } else {
-            Ok(())
+            check_resumable_sources(&mut table_insert.source, None)
         }
     } else {
         Ok(())
     }
 }
+
+/// Copies parse a single csv file straight into the table, the options for following
+/// directories of files don't apply.
+fn check_copy_options(serde_options: &SerdeOptions) -> Result<(), PlannerError> {
+    let unsupported = if serde_options.format != FileFormat::Csv {
+        Some("FORMAT")
+    } else if serde_options.resume {
+        Some("RESUME")
+    } else if serde_options.max_rows_per_second.is_some() {
+        Some("MAX_ROWS_PER_SECOND")
+    } else if serde_options.changes.is_some() {
+        Some("CHANGES")
+    } else if serde_options.snapshot.is_some() {
+        Some("SNAPSHOT")
+    } else {
+        None
+    };
+    match unsupported {
+        Some(option) => Err(PlannerError::CopyOptionNotSupported(option)),
+        None => Ok(()),
+    }
+}
+
+fn check_resumable_sources(
+    operator: &mut LogicalOperator,
+    blocking: Option<&'static str>,
+) -> Result<(), PlannerError> {
+    if let LogicalOperator::FileScan(file_scan) = operator {
+        if file_scan.serde_options.resume {
+            if let Some(blocking) = blocking {
+                return Err(PlannerError::ResumeNotSupported(blocking));
+            }
+        }
+    }
+
+    let blocking = match operator {
+        LogicalOperator::GroupBy(_) => Some("GROUP BY"),
+        LogicalOperator::Project(project) if project.distinct => Some("DISTINCT"),
+        LogicalOperator::Sort(_) => Some("ORDER BY"),
+        LogicalOperator::Join(_) => Some("JOIN"),
+        _ => blocking,
+    };
+    for child in operator.children_mut() {
+        check_resumable_sources(child, blocking)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use crate::PlannerError;
+use ast::rel::logical::LogicalOperator;
+use catalog::{is_superuser, Catalog};
+use data::Session;
+use std::path::{Component, Path};
+
+/// Checks the session's user is allowed to read the directories the query's file sources scan,
+/// and the files it copies from.
+/// This runs before views are inlined so a view over files can be read by anyone that can read
+/// the view, it's the user creating the view that needs EXECUTE on the directory.
+pub(super) fn check_privileges(
+    catalog: &Catalog,
+    operator: &mut LogicalOperator,
+    session: &Session,
+) -> Result<(), PlannerError> {
+    let user = session.user.read().unwrap();
+    if is_superuser(&user) {
+        return Ok(());
+    }
+    let granted = granted_directories(catalog, &user)?;
+    check_file_scans(operator, &user, &granted)
+}
+
+/// Checks the session's user is allowed to use a directory outside of a query, ie to have a
+/// sink write into it.
+pub(super) fn check_directory(
+    catalog: &Catalog,
+    directory: &str,
+    session: &Session,
+) -> Result<(), PlannerError> {
+    let user = session.user.read().unwrap();
+    if is_superuser(&user) {
+        return Ok(());
+    }
+    let granted = granted_directories(catalog, &user)?;
+    if !directory_granted(directory, &granted) {
+        return Err(permission_denied(&user, directory));
+    }
+    Ok(())
+}
+
+fn granted_directories(catalog: &Catalog, user: &str) -> Result<Vec<String>, PlannerError> {
+    Ok(catalog
+        .privileges(user)?
+        .into_iter()
+        .filter(|privilege| {
+            privilege.privilege == "EXECUTE" && privilege.object_type == "DIRECTORY"
+        })
+        .map(|privilege| privilege.object)
+        .collect())
+}
+
+fn permission_denied(user: &str, directory: &str) -> PlannerError {
+    PlannerError::PermissionDenied(user.to_string(), format!("DIRECTORY \"{}\"", directory))
+}
+
+fn check_file_scans(
+    operator: &mut LogicalOperator,
+    user: &str,
+    granted: &[String],
+) -> Result<(), PlannerError> {
+    for child in operator.children_mut() {
+        check_file_scans(child, user, granted)?;
+    }
+
+    let directory = match operator {
+        LogicalOperator::FileScan(file_scan) => &file_scan.directory,
+        LogicalOperator::FileCopy(file_copy) => &file_copy.path,
+        _ => return Ok(()),
+    };
+    if !directory_granted(directory, granted) {
+        return Err(permission_denied(user, directory));
+    }
+    Ok(())
+}
+
+/// A directory is covered by a grant on it or on any of its parents, paths that climb back out
+/// with .. are never covered.
+fn directory_granted(directory: &str, granted: &[String]) -> bool {
+    let path = Path::new(directory);
+    if path.components().any(|c| c == Component::ParentDir) {
+        return false;
+    }
+    granted
+        .iter()
+        .any(|granted| path.starts_with(Path::new(granted)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_directory_granted() {
+        let granted = vec!["data/".to_string(), "/var/logs".to_string()];
+        assert!(directory_granted("data", &granted));
+        assert!(directory_granted("data/orders/", &granted));
+        assert!(directory_granted("/var/logs/app", &granted));
+        assert!(!directory_granted("database", &granted));
+        assert!(!directory_granted("data/../secrets", &granted));
+        assert!(!directory_granted("/var", &granted));
+        assert!(!directory_granted("data", &[]));
+    }
+}
@@ -0,0 +1,152 @@
+use crate::p1_validation::compile_functions_and_refs::compile_cast;
+use crate::utils::logical::fields_for_operator;
+use crate::PlannerError;
+use ast::expr::{CompiledColumnReference, Expression, NamedExpression};
+use ast::rel::logical::{LogicalOperator, Project, UnionAll};
+use data::{cast_kind, common_type, CastKind, DataType};
+use functions::registry::Registry;
+
+/// Lines up the types of the rows being inserted with the table's columns using the implicit
+/// casts from the cast matrix. Anything that still doesn't line up is left for check_inserts to
+/// reject. Union alls are lined up by coerce_union as their functions are compiled.
+pub(super) fn coerce_types(
+    operator: &mut LogicalOperator,
+    function_registry: &Registry,
+) -> Result<(), PlannerError> {
+    for child in operator.children_mut() {
+        coerce_types(child, function_registry)?;
+    }
+
+    if let LogicalOperator::TableInsert(table_insert) = operator {
+        let table_types: Vec<_> = fields_for_operator(&table_insert.table)
+            .map(|f| f.data_type)
+            .collect();
+        let source_types: Vec<_> = fields_for_operator(&table_insert.source)
+            .map(|f| f.data_type)
+            .collect();
+        let implicit = table_types.len() == source_types.len()
+            && source_types
+                .iter()
+                .zip(&table_types)
+                .all(|(from, to)| matches!(cast_kind(*from, *to), CastKind::Implicit(_)));
+        if implicit {
+            cast_source(&mut table_insert.source, &table_types, function_registry)?;
+        }
+    }
+    Ok(())
+}
+
+/// Lines up the types of the sources of a union all with each other using the implicit casts
+/// from the cast matrix, anything that still doesn't line up is left for check_unions to
+/// reject. This has to happen before the operators above the union are compiled against its
+/// fields so it's called from compile_functions.
+pub(super) fn coerce_union(
+    union_all: &mut UnionAll,
+    function_registry: &Registry,
+) -> Result<(), PlannerError> {
+    let mut sources = union_all.sources.iter();
+    let first_types = sources.next().map(source_types).unwrap_or_default();
+    let common_types = sources.try_fold(first_types, |types, source| {
+        let source_types = source_types(source);
+        if types.len() == source_types.len() {
+            types
+                .into_iter()
+                .zip(source_types)
+                .map(|(a, b)| common_type(a, b))
+                .collect()
+        } else {
+            None
+        }
+    });
+    if let Some(common_types) = common_types {
+        for source in union_all.sources.iter_mut() {
+            cast_source(source, &common_types, function_registry)?;
+        }
+    }
+    Ok(())
+}
+
+fn source_types(source: &LogicalOperator) -> Vec<DataType> {
+    fields_for_operator(source).map(|f| f.data_type).collect()
+}
+
+/// Wraps the source in a project casting its columns to the types, if they aren't already
+fn cast_source(
+    source: &mut LogicalOperator,
+    types: &[DataType],
+    function_registry: &Registry,
+) -> Result<(), PlannerError> {
+    if source_types(source) == types {
+        return Ok(());
+    }
+    let expressions = fields_for_operator(source)
+        .zip(types)
+        .enumerate()
+        .map(|(offset, (field, datatype))| {
+            let column = Expression::CompiledColumnReference(CompiledColumnReference {
+                offset,
+                datatype: field.data_type,
+            });
+            let expression = if field.data_type == *datatype {
+                column
+            } else {
+                compile_cast(column, *datatype, false, function_registry)?
+            };
+            Ok(NamedExpression {
+                alias: Some(field.alias),
+                expression,
+            })
+        })
+        .collect::<Result<_, PlannerError>>()?;
+    *source = LogicalOperator::Project(Project {
+        distinct: false,
+        expressions,
+        source: Box::new(std::mem::take(source)),
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::expr::CompiledFunctionCall;
+    use ast::rel::logical::Values;
+
+    fn values(datatype: DataType) -> LogicalOperator {
+        LogicalOperator::Values(Values {
+            fields: vec![(datatype, "a".to_string())],
+            data: vec![],
+        })
+    }
+
+    #[test]
+    fn test_coerce_unions() -> Result<(), PlannerError> {
+        let registry = Registry::default();
+        let mut union_all = UnionAll {
+            sources: vec![values(DataType::Integer), values(DataType::Decimal(4, 2))],
+        };
+        coerce_union(&mut union_all, &registry)?;
+
+        for source in &union_all.sources {
+            assert_eq!(source_types(source), vec![DataType::Decimal(12, 2)]);
+            if let LogicalOperator::Project(project) = source {
+                assert!(matches!(
+                    &project.expressions[0].expression,
+                    Expression::CompiledFunctionCall(CompiledFunctionCall { signature, .. })
+                        if signature.name == "to_decimal"
+                ));
+            } else {
+                panic!("Expected a project")
+            }
+        }
+
+        // Types that can't be lined up are left alone
+        let mut union_all = UnionAll {
+            sources: vec![values(DataType::Integer), values(DataType::Date)],
+        };
+        let expected = union_all.clone();
+        coerce_union(&mut union_all, &registry)?;
+        assert_eq!(union_all, expected);
+        Ok(())
+    }
+}
@@ -1,47 +1,109 @@
-use crate::utils::expr::{assemble_compound_function, type_for_expression};
-use crate::utils::logical::source_fields_for_operator;
+use crate::p1_validation::coerce_types::coerce_union;
+use crate::utils::expr::{
+    assemble_compound_function, contains_aggregate, contains_window, type_for_expression,
+};
+use crate::utils::logical::{fields_for_operator, source_fields_for_operator};
 use crate::{Field, FieldResolutionError, PlannerError};
 use ast::expr::*;
 use ast::rel::logical::LogicalOperator;
-use data::{DataType, Datum};
+use data::{cast_kind, CastKind, DataType, Datum};
 use functions::registry::Registry;
 use functions::{FunctionSignature, FunctionType};
+use std::iter::once;
 
+/// Used to run the validation phase over any subqueries, passed the subquery along with
+/// the fields from the enclosing scope.
+pub(super) type SubqueryValidator<'a> =
+    &'a dyn Fn(&mut LogicalOperator, &[Field]) -> Result<(), PlannerError>;
+
+/// Compiles functions and column references.
+/// outer_fields are the fields visible from any enclosing query (for correlated subqueries),
+/// references to these get compiled to offsets *past* the end of the operators source fields.
 pub(super) fn compile_functions(
     operator: &mut LogicalOperator,
+    outer_fields: &[Field],
     function_registry: &Registry,
+    validate_subquery: SubqueryValidator,
 ) -> Result<(), PlannerError> {
     for child in operator.children_mut() {
-        compile_functions(child, function_registry)?;
+        compile_functions(child, outer_fields, function_registry, validate_subquery)?;
+    }
+    if let LogicalOperator::UnionAll(union_all) = operator {
+        coerce_union(union_all, function_registry)?;
     }
 
     let source_fields: Vec<_> = source_fields_for_operator(operator).collect();
     for expr in operator.expressions_mut() {
-        compile_functions_in_expr(expr, &source_fields, function_registry)?;
+        compile_functions_in_expr(
+            expr,
+            &source_fields,
+            outer_fields,
+            function_registry,
+            validate_subquery,
+        )?;
     }
     Ok(())
 }
 
+/// Compiles a cast of the already compiled expression into a call to the cast function for
+/// the types. Casts the cast matrix forbids are rejected up front rather than failing to find
+/// a cast function.
+pub(super) fn compile_cast(
+    expr: Expression,
+    datatype: DataType,
+    try_cast: bool,
+    function_registry: &Registry,
+) -> Result<Expression, PlannerError> {
+    let expr_type = type_for_expression(&expr);
+    if cast_kind(expr_type, datatype) == CastKind::Forbidden {
+        return Err(PlannerError::CastNotSupported(expr_type, datatype));
+    }
+
+    let function_name = if try_cast {
+        datatype.try_cast_function()
+    } else {
+        datatype.cast_function()
+    };
+
+    let lookup_sig = FunctionSignature {
+        name: function_name,
+        args: vec![expr_type],
+        ret: datatype,
+    };
+
+    let (signature, function) = function_registry.resolve_function(&lookup_sig)?;
+    if let FunctionType::Scalar(function) = function {
+        Ok(Expression::CompiledFunctionCall(CompiledFunctionCall {
+            function,
+            args: Box::from(vec![expr]),
+            signature: Box::new(signature),
+        }))
+    } else {
+        panic!("Cast needs to be a scalar function")
+    }
+}
+
 fn compile_functions_in_expr(
     expression: &mut Expression,
     source_fields: &[Field],
+    outer_fields: &[Field],
     function_registry: &Registry,
+    validate_subquery: SubqueryValidator,
 ) -> Result<(), PlannerError> {
     match expression {
         Expression::FunctionCall(function_call) => {
             for arg in function_call.args.iter_mut() {
-                compile_functions_in_expr(arg, source_fields, function_registry)?;
+                compile_functions_in_expr(
+                    arg,
+                    source_fields,
+                    outer_fields,
+                    function_registry,
+                    validate_subquery,
+                )?;
             }
 
-            let arg_types = function_call.args.iter().map(type_for_expression).collect();
-
-            let lookup_sig = FunctionSignature {
-                name: &function_call.function_name,
-                args: arg_types,
-                ret: DataType::Null,
-            };
-
-            let (signature, function) = function_registry.resolve_function(&lookup_sig)?;
+            let (signature, function) =
+                resolve_function_with_parameters(function_call, function_registry)?;
 
             let mut args = Vec::new();
             std::mem::swap(&mut args, &mut function_call.args);
@@ -51,7 +113,6 @@ fn compile_functions_in_expr(
                     Expression::CompiledFunctionCall(CompiledFunctionCall {
                         function,
                         args: Box::from(args),
-                        expr_buffer: Box::from(vec![]),
                         signature: Box::new(signature),
                     })
                 }
@@ -59,7 +120,6 @@ fn compile_functions_in_expr(
                     Expression::CompiledAggregate(CompiledAggregate {
                         function,
                         args: Box::from(args),
-                        expr_buffer: Box::from(vec![]),
                         signature: Box::new(signature),
                     })
                 }
@@ -68,78 +128,136 @@ fn compile_functions_in_expr(
                     compile_functions_in_expr(
                         &mut exploded_expr,
                         source_fields,
+                        outer_fields,
                         function_registry,
+                        validate_subquery,
                     )?;
                     exploded_expr
                 }
             };
         }
         Expression::Cast(cast) => {
-            compile_functions_in_expr(&mut cast.expr, source_fields, function_registry)?;
-
-            let expr_type = type_for_expression(&cast.expr);
+            compile_functions_in_expr(
+                &mut cast.expr,
+                source_fields,
+                outer_fields,
+                function_registry,
+                validate_subquery,
+            )?;
 
-            let function_name = cast.datatype.cast_function();
-
-            let lookup_sig = FunctionSignature {
-                name: function_name,
-                args: vec![expr_type],
-                ret: cast.datatype,
-            };
+            // The parameter may as well be whatever we're casting to
+            if let Expression::Parameter(parameter) = cast.expr.as_mut() {
+                if parameter.datatype == DataType::Null {
+                    parameter.datatype = cast.datatype;
+                }
+            }
+            let expr = std::mem::replace(
+                cast.expr.as_mut(),
+                Expression::Constant(Datum::Null, DataType::Null),
+            );
+            *expression = compile_cast(expr, cast.datatype, cast.try_cast, function_registry)?;
+        }
+        Expression::ColumnReference(column_reference) => {
+            // Our own source fields take precedence, failing that we look to any
+            // enclosing queries.
+            let (offset, data_type) =
+                match resolve_column_reference(column_reference, source_fields) {
+                    Err(err @ FieldResolutionError::NotFound(..)) if !outer_fields.is_empty() => {
+                        match resolve_column_reference(column_reference, outer_fields) {
+                            Ok((idx, data_type)) => (source_fields.len() + idx, data_type),
+                            Err(FieldResolutionError::NotFound(..)) => return Err(err.into()),
+                            Err(outer_err) => return Err(outer_err.into()),
+                        }
+                    }
+                    result => result?,
+                };
+            *expression = Expression::CompiledColumnReference(CompiledColumnReference {
+                offset,
+                datatype: data_type,
+            })
+        }
+        Expression::Subquery(subquery) => {
+            if let Some(operand) = &mut subquery.operand {
+                compile_functions_in_expr(
+                    operand,
+                    source_fields,
+                    outer_fields,
+                    function_registry,
+                    validate_subquery,
+                )?;
+            }
 
-            let (signature, function) = function_registry.resolve_function(&lookup_sig)?;
+            // The subquery can see our source fields along with anything we can see.
+            let scope: Vec<_> = source_fields
+                .iter()
+                .chain(outer_fields.iter())
+                .cloned()
+                .collect();
+            validate_subquery(&mut subquery.query, &scope)?;
 
-            // Just an "empty" value to swap
-            let mut expr = Expression::Constant(Datum::Null, DataType::Null);
+            if subquery.scalar {
+                let column_count = fields_for_operator(&subquery.query).count();
+                if column_count != 1 {
+                    return Err(PlannerError::SubqueryColumnCount(column_count));
+                }
+            }
 
-            std::mem::swap(&mut expr, &mut cast.expr);
-            if let FunctionType::Scalar(function) = function {
-                *expression = Expression::CompiledFunctionCall(CompiledFunctionCall {
-                    function,
-                    args: Box::from(vec![expr]),
-                    expr_buffer: Box::from(vec![]),
-                    signature: Box::new(signature),
-                })
-            } else {
-                panic!("Cast needs to be a scalar function")
+            if let Some(operand) = &mut subquery.operand {
+                let fields: Vec<_> = fields_for_operator(&subquery.query).collect();
+                if fields.len() != 1 {
+                    return Err(PlannerError::SubqueryColumnCount(fields.len()));
+                }
+                if let Expression::Parameter(parameter) = operand {
+                    if parameter.datatype == DataType::Null {
+                        parameter.datatype = fields[0].data_type;
+                    }
+                }
+                // Make sure we'll actually be able to compare the operand to the subquery.
+                function_registry.resolve_function(&FunctionSignature {
+                    name: "=",
+                    args: vec![type_for_expression(operand), fields[0].data_type],
+                    ret: DataType::Null,
+                })?;
             }
         }
-        Expression::ColumnReference(column_reference) => {
-            let indexed_source_fields = source_fields.iter().enumerate();
-            let mut matching_fields: Vec<_> = if let Some(qualifier) = &column_reference.qualifier {
-                indexed_source_fields
-                    .filter(|(_idx, field)| {
-                        field.qualifier.as_ref() == Some(qualifier)
-                            && field.alias == column_reference.alias
-                    })
-                    .collect()
-            } else {
-                indexed_source_fields
-                    .filter(|(_idx, field)| field.alias == column_reference.alias)
-                    .collect()
-            };
 
-            if matching_fields.is_empty() {
-                return Err(FieldResolutionError::NotFound(
-                    ColumnReference::clone(column_reference),
-                    source_fields.to_vec(),
-                )
-                .into());
-            } else if matching_fields.len() > 1 {
-                return Err(FieldResolutionError::Ambiguous(
-                    ColumnReference::clone(column_reference),
-                    matching_fields
-                        .into_iter()
-                        .map(|(_idx, field)| field.clone())
-                        .collect(),
-                )
-                .into());
-            } else {
-                let (idx, field) = matching_fields.pop().unwrap();
-                *expression = Expression::CompiledColumnReference(CompiledColumnReference {
-                    offset: idx,
-                    datatype: field.data_type,
-                })
+        Expression::WindowFunction(window) => {
+            // row_number() is just a count of the rows so far, without counting the peers
+            if let Expression::FunctionCall(function_call) = &mut window.function {
+                if function_call.function_name.to_lowercase() == "row_number"
+                    && function_call.args.is_empty()
+                {
+                    function_call.function_name = "count".to_string();
+                    window.frame = WindowFrame::Rows(None);
+                }
+            }
+            let window = window.as_mut();
+            let exprs = once(&mut window.function)
+                .chain(window.partition_by.iter_mut())
+                .chain(window.order_by.iter_mut().map(|se| &mut se.expression));
+            for expr in exprs {
+                compile_functions_in_expr(
+                    expr,
+                    source_fields,
+                    outer_fields,
+                    function_registry,
+                    validate_subquery,
+                )?;
+            }
+
+            let nested = window
+                .function
+                .children()
+                .any(|arg| contains_aggregate(arg) || contains_window(arg))
+                || window
+                    .partition_by
+                    .iter()
+                    .chain(window.order_by.iter().map(|se| &se.expression))
+                    .any(|expr| contains_aggregate(expr) || contains_window(expr));
+            if !matches!(window.function, Expression::CompiledAggregate(_)) || nested {
+                return Err(PlannerError::WindowFunctionNotSupported(
+                    window.function.to_string(),
+                ));
             }
         }
 
@@ -147,11 +265,119 @@ fn compile_functions_in_expr(
         Expression::Constant(..)
         | Expression::CompiledFunctionCall(_)
         | Expression::CompiledAggregate(_)
-        | Expression::CompiledColumnReference(_) => {}
+        | Expression::CompiledColumnReference(_)
+        | Expression::Parameter(_) => {}
     }
     Ok(())
 }
 
+/// Resolves the function for the function call, filling in the types of any parameters
+/// passed straight in as args.
+/// We'll first try giving the parameters the same type as the other args, ie for `id = ?`
+/// we want the parameter to be the same type as id. If that doesn't work out we'll take
+/// whatever the function is expecting.
+fn resolve_function_with_parameters(
+    function_call: &mut FunctionCall,
+    function_registry: &Registry,
+) -> Result<(FunctionSignature<'static>, FunctionType), PlannerError> {
+    let untyped: Vec<_> = function_call
+        .args
+        .iter()
+        .map(|arg| matches!(arg, Expression::Parameter(parameter) if parameter.datatype == DataType::Null))
+        .collect();
+    let sibling_type = function_call
+        .args
+        .iter()
+        .zip(untyped.iter())
+        .filter(|(_arg, untyped)| !**untyped)
+        .map(|(arg, _untyped)| type_for_expression(arg))
+        .find(|datatype| *datatype != DataType::Null);
+
+    let arg_types: Vec<_> = function_call.args.iter().map(type_for_expression).collect();
+    let inferred = sibling_type
+        .filter(|_| untyped.contains(&true))
+        .and_then(|sibling_type| {
+            let inferred_types = arg_types
+                .iter()
+                .zip(untyped.iter())
+                .map(|(datatype, untyped)| if *untyped { sibling_type } else { *datatype })
+                .collect();
+            function_registry
+                .resolve_function(&FunctionSignature {
+                    name: &function_call.function_name,
+                    args: inferred_types,
+                    ret: DataType::Null,
+                })
+                .ok()
+        });
+    let (signature, function) = match inferred {
+        Some(resolved) => resolved,
+        None => function_registry.resolve_function(&FunctionSignature {
+            name: &function_call.function_name,
+            args: arg_types,
+            ret: DataType::Null,
+        })?,
+    };
+
+    for ((arg, untyped), datatype) in function_call
+        .args
+        .iter_mut()
+        .zip(untyped)
+        .zip(signature.args.iter())
+    {
+        if let (Expression::Parameter(parameter), true) = (arg, untyped) {
+            parameter.datatype = *datatype;
+        }
+    }
+    Ok((signature, function))
+}
+
+/// Compiles an expression assembled by the planner itself, ie function calls whose args
+/// have already been compiled.
+pub(crate) fn compile_expression(
+    expression: &mut Expression,
+    function_registry: &Registry,
+) -> Result<(), PlannerError> {
+    compile_functions_in_expr(expression, &[], &[], function_registry, &|_, _| Ok(()))
+}
+
+/// Finds the offset and type of the field a column reference refers to.
+fn resolve_column_reference(
+    column_reference: &ColumnReference,
+    fields: &[Field],
+) -> Result<(usize, DataType), FieldResolutionError> {
+    let indexed_fields = fields.iter().enumerate();
+    let mut matching_fields: Vec<_> = if let Some(qualifier) = &column_reference.qualifier {
+        indexed_fields
+            .filter(|(_idx, field)| {
+                field.qualifier.as_ref() == Some(qualifier) && field.alias == column_reference.alias
+            })
+            .collect()
+    } else {
+        indexed_fields
+            .filter(|(_idx, field)| field.alias == column_reference.alias)
+            .collect()
+    };
+
+    if matching_fields.is_empty() {
+        Err(FieldResolutionError::NotFound(
+            ColumnReference::clone(column_reference),
+            fields.to_vec(),
+        ))
+    } else if matching_fields.len() > 1 {
+        Err(FieldResolutionError::Ambiguous(
+            ColumnReference::clone(column_reference),
+            matching_fields
+                .into_iter()
+                .map(|(_idx, field)| field.clone())
+                .collect(),
+        ))
+    } else {
+        let (idx, field) = matching_fields.pop().unwrap();
+        Ok((idx, field.data_type))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -208,7 +434,6 @@ mod tests {
                         Expression::CompiledFunctionCall(CompiledFunctionCall {
                             function: &DummyFunct {},
                             args: Box::from(vec![Expression::from(2), Expression::from(3)]),
-                            expr_buffer: Box::from(vec![]),
                             signature: Box::new(FunctionSignature {
                                 name: "+",
                                 args: vec![DataType::Integer, DataType::Integer],
@@ -216,7 +441,6 @@ mod tests {
                             }),
                         }),
                     ]),
-                    expr_buffer: Box::from(vec![]),
                     signature: Box::new(FunctionSignature {
                         name: "+",
                         args: vec![DataType::Integer, DataType::Integer],
@@ -227,7 +451,7 @@ mod tests {
             source: Box::new(LogicalOperator::Single),
         });
 
-        compile_functions(&mut operator, &function_registry)?;
+        compile_functions(&mut operator, &[], &function_registry, &|_, _| Ok(()))?;
 
         assert_eq!(operator, expected);
 
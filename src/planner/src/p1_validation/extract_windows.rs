@@ -0,0 +1,223 @@
+use crate::utils::expr::{contains_aggregate, contains_window, type_for_expression};
+use crate::utils::logical::fieldnames_for_operator;
+use crate::PlannerError;
+use ast::expr::{CompiledColumnReference, Expression, NamedExpression, SortExpression};
+use ast::rel::logical::{LogicalOperator, Window};
+
+/// Pulls the window functions out of select lists and into window operators that sit between
+/// the project and its source, the project then just references the window operators' columns.
+/// Window functions sharing the same PARTITION BY and ORDER BY get evaluated by the same window
+/// operator, each different one gets a window operator of its own stacked on the last.
+pub(super) fn extract_windows(operator: &mut LogicalOperator) -> Result<(), PlannerError> {
+    for child in operator.children_mut() {
+        extract_windows(child)?;
+    }
+
+    match operator {
+        LogicalOperator::Project(project) => {
+            let has_windows = project
+                .expressions
+                .iter()
+                .any(|ne| contains_window(&ne.expression));
+            if !has_windows {
+                return Ok(());
+            }
+            if project
+                .expressions
+                .iter()
+                .any(|ne| contains_aggregate(&ne.expression))
+            {
+                return Err(PlannerError::WindowNotAllowed("queries with aggregates"));
+            }
+
+            let source_len = fieldnames_for_operator(&project.source).count();
+            let mut windows = vec![];
+            for named_expression in &mut project.expressions {
+                take_windows(&mut named_expression.expression, source_len, &mut windows);
+            }
+
+            // One operator per distinct partition by/order by, in the order they first appear
+            let mut specs: Vec<(&[Expression], &[SortExpression])> = vec![];
+            let mut spec_idxs = vec![];
+            for window in &windows {
+                if let Expression::WindowFunction(window) = window {
+                    let spec = (window.partition_by.as_slice(), window.order_by.as_slice());
+                    let idx = specs.iter().position(|s| *s == spec).unwrap_or_else(|| {
+                        specs.push(spec);
+                        specs.len() - 1
+                    });
+                    spec_idxs.push(idx);
+                }
+            }
+            let spec_count = specs.len();
+
+            // The offsets of each window function's column in the output of the last window
+            let mut offsets = vec![0; windows.len()];
+            let mut offset = source_len;
+            for spec_idx in 0..spec_count {
+                for (window_idx, _) in spec_idxs
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, s)| **s == spec_idx)
+                {
+                    offsets[window_idx] = offset;
+                    offset += 1;
+                }
+            }
+            for named_expression in &mut project.expressions {
+                reference_windows(&mut named_expression.expression, source_len, &offsets);
+            }
+
+            let mut functions: Vec<Vec<NamedExpression>> = vec![vec![]; spec_count];
+            for (window_idx, (window, spec_idx)) in windows.into_iter().zip(spec_idxs).enumerate() {
+                functions[spec_idx].push(NamedExpression {
+                    alias: Some(format!("window_{}", window_idx)),
+                    expression: window,
+                });
+            }
+            for functions in functions {
+                let source = std::mem::take(project.source.as_mut());
+                *project.source = LogicalOperator::Window(Window {
+                    functions,
+                    source: Box::new(source),
+                });
+            }
+        }
+        LogicalOperator::GroupBy(group_by) => {
+            for ne in &group_by.expressions {
+                throw_on_window(&ne.expression, "queries with aggregates")?;
+            }
+            for expr in &group_by.key_expressions {
+                throw_on_window(expr, "group by clause")?;
+            }
+        }
+        LogicalOperator::Filter(filter) => {
+            throw_on_window(&filter.predicate, "where clause")?;
+        }
+        LogicalOperator::Sort(sort) => {
+            for se in &sort.sort_expressions {
+                throw_on_window(&se.expression, "order by clause")?;
+            }
+        }
+        LogicalOperator::Join(join) => {
+            throw_on_window(&join.on, "join conditions")?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Swaps the window functions in the expression for placeholder references, the placeholder's
+/// offset is source_len plus the index of the window function in windows.
+fn take_windows(expression: &mut Expression, source_len: usize, windows: &mut Vec<Expression>) {
+    if let Expression::WindowFunction(window) = expression {
+        let datatype = type_for_expression(&window.function);
+        let placeholder = Expression::CompiledColumnReference(CompiledColumnReference {
+            offset: source_len + windows.len(),
+            datatype,
+        });
+        windows.push(std::mem::replace(expression, placeholder));
+    } else {
+        for child in expression.children_mut() {
+            take_windows(child, source_len, windows);
+        }
+    }
+}
+
+/// Points the placeholders from take_windows at the window operators' columns, anything
+/// referencing the project's source is left alone.
+fn reference_windows(expression: &mut Expression, source_len: usize, offsets: &[usize]) {
+    match expression {
+        Expression::CompiledColumnReference(column_ref) if column_ref.offset >= source_len => {
+            column_ref.offset = offsets[column_ref.offset - source_len];
+        }
+        _ => {
+            for child in expression.children_mut() {
+                reference_windows(child, source_len, offsets);
+            }
+        }
+    }
+}
+
+fn throw_on_window(expr: &Expression, location: &'static str) -> Result<(), PlannerError> {
+    if contains_window(expr) {
+        Err(PlannerError::WindowNotAllowed(location))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::logical::fields_for_operator;
+    use crate::{Planner, PlannerError};
+    use ast::expr::{CompiledColumnReference, Expression};
+    use ast::rel::logical::LogicalOperator;
+    use ast::statement::Statement;
+    use data::Session;
+
+    fn validate(sql: &str) -> Result<LogicalOperator, PlannerError> {
+        let planner = Planner::new_for_test();
+        let session = Session::new(1);
+        match parser::parse(sql).unwrap() {
+            Statement::Query(query) => planner.validate(query, &session),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_extract_windows() -> Result<(), PlannerError> {
+        let query = validate(
+            "SELECT row_number() OVER (ORDER BY b), a, sum(b) OVER (PARTITION BY a) + 1, \
+             count(*) OVER (ORDER BY b) \
+             FROM (SELECT 1 as a, 2 as b) t",
+        )?;
+        let project = if let LogicalOperator::Project(project) = query {
+            project
+        } else {
+            panic!()
+        };
+        // Two different specs so two windows, the one first seen at the bottom
+        let window = if let LogicalOperator::Window(window) = project.source.as_ref() {
+            window
+        } else {
+            panic!()
+        };
+        assert_eq!(window.functions.len(), 1);
+        let fields: Vec<_> = fields_for_operator(project.source.as_ref())
+            .map(|f| f.alias)
+            .collect();
+        assert_eq!(fields, vec!["a", "b", "window_0", "window_2", "window_1"]);
+
+        let offsets: Vec<_> = project
+            .expressions
+            .iter()
+            .map(|ne| match &ne.expression {
+                Expression::CompiledColumnReference(CompiledColumnReference { offset, .. }) => {
+                    *offset
+                }
+                Expression::CompiledFunctionCall(function_call) => match &function_call.args[0] {
+                    Expression::CompiledColumnReference(column_ref) => column_ref.offset,
+                    _ => panic!(),
+                },
+                _ => panic!(),
+            })
+            .collect();
+        assert_eq!(offsets, vec![2, 0, 4, 3]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_windows_not_allowed() {
+        for sql in &[
+            "SELECT count(*), row_number() OVER () FROM (SELECT 1 as a) t",
+            "SELECT a FROM (SELECT 1 as a) t GROUP BY row_number() OVER ()",
+            "SELECT a FROM (SELECT 1 as a) t WHERE row_number() OVER () = 1",
+            "SELECT a FROM (SELECT 1 as a) t ORDER BY row_number() OVER ()",
+            "SELECT sum(row_number() OVER ()) OVER () FROM (SELECT 1 as a) t",
+            "SELECT lower('a') OVER () FROM (SELECT 1 as a) t",
+        ] {
+            assert!(validate(sql).is_err(), "{}", sql);
+        }
+    }
+}
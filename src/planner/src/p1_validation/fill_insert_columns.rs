@@ -0,0 +1,107 @@
+use crate::utils::logical::fields_for_operator;
+use crate::PlannerError;
+use ast::expr::{CompiledColumnReference, Expression, NamedExpression};
+use ast::rel::logical::{LogicalOperator, Project};
+use data::{DataType, Datum};
+
+/// Where each of the columns named by an insert are in the table, erroring on columns that
+/// aren't in the table or are named twice. All the table's columns if none are named.
+pub(super) fn target_offsets(
+    table_columns: &[(String, DataType)],
+    columns: &[String],
+) -> Result<Vec<usize>, PlannerError> {
+    if columns.is_empty() {
+        return Ok((0..table_columns.len()).collect());
+    }
+    let mut offsets: Vec<usize> = Vec::with_capacity(columns.len());
+    for column in columns {
+        let offset = table_columns
+            .iter()
+            .position(|(alias, _)| alias == column)
+            .ok_or_else(|| PlannerError::InsertColumnNotFound(column.clone()))?;
+        if offsets.contains(&offset) {
+            return Err(PlannerError::InsertColumnRepeated(column.clone()));
+        }
+        offsets.push(offset);
+    }
+    Ok(offsets)
+}
+
+/// Inserts that name the columns they're inserting into have their source widened out to all
+/// of the table's columns, in the table's order, with nulls for the columns that weren't named.
+/// The named columns are left for coerce_types and check_inserts to type check as usual.
+pub(super) fn fill_insert_columns(operator: &mut LogicalOperator) -> Result<(), PlannerError> {
+    for child in operator.children_mut() {
+        fill_insert_columns(child)?;
+    }
+
+    if let LogicalOperator::TableInsert(table_insert) = operator {
+        if table_insert.columns.is_empty() {
+            return Ok(());
+        }
+        let table_columns = match table_insert.table.as_ref() {
+            LogicalOperator::ResolvedTable(table) => table.columns.clone(),
+            _ => return Err(PlannerError::InsertIntoView),
+        };
+        let offsets = target_offsets(&table_columns, &table_insert.columns)?;
+        let source_types: Vec<_> = fields_for_operator(&table_insert.source)
+            .map(|f| f.data_type)
+            .collect();
+        if source_types.len() != offsets.len() {
+            let target_types = offsets.iter().map(|idx| table_columns[*idx].1).collect();
+            return Err(PlannerError::InsertMismatch(target_types, source_types));
+        }
+
+        let expressions = table_columns
+            .into_iter()
+            .enumerate()
+            .map(|(idx, (alias, datatype))| {
+                let expression = match offsets.iter().position(|offset| *offset == idx) {
+                    Some(offset) => Expression::CompiledColumnReference(CompiledColumnReference {
+                        offset,
+                        datatype: source_types[offset],
+                    }),
+                    None => Expression::Constant(Datum::Null, datatype),
+                };
+                NamedExpression {
+                    alias: Some(alias),
+                    expression,
+                }
+            })
+            .collect();
+        table_insert.source = Box::new(LogicalOperator::Project(Project {
+            distinct: false,
+            expressions,
+            source: std::mem::take(&mut table_insert.source),
+        }));
+        table_insert.columns.clear();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_offsets() {
+        let table_columns = vec![
+            ("a".to_string(), DataType::Integer),
+            ("b".to_string(), DataType::Text),
+            ("c".to_string(), DataType::Date),
+        ];
+        assert_eq!(target_offsets(&table_columns, &[]).unwrap(), vec![0, 1, 2]);
+        assert_eq!(
+            target_offsets(&table_columns, &["c".to_string(), "a".to_string()]).unwrap(),
+            vec![2, 0]
+        );
+        assert!(matches!(
+            target_offsets(&table_columns, &["d".to_string()]),
+            Err(PlannerError::InsertColumnNotFound(_))
+        ));
+        assert!(matches!(
+            target_offsets(&table_columns, &["a".to_string(), "a".to_string()]),
+            Err(PlannerError::InsertColumnRepeated(_))
+        ));
+    }
+}
@@ -1,14 +1,18 @@
-use crate::{Planner, PlannerError};
+use crate::{Field, Planner, PlannerError};
 use ast::rel::logical::LogicalOperator;
 use data::Session;
 mod check_aggregates_usage;
 mod check_inserts;
 mod check_predicates;
+mod check_privileges;
 mod check_unions;
+mod coerce_types;
 mod column_aliases;
-mod compile_functions_and_refs;
+pub(crate) mod compile_functions_and_refs;
 mod convert_project_to_groupby;
 mod expand_stars;
+mod extract_windows;
+mod fill_insert_columns;
 mod resolve_tables;
 mod sub_in_special_vars;
 mod validate_values_types;
@@ -17,31 +21,66 @@ mod validate_values_types;
 /// doing all the catalog and function lookups and subbing them in.
 impl Planner {
     pub fn validate(
+        &self,
+        query: LogicalOperator,
+        session: &Session,
+    ) -> Result<LogicalOperator, PlannerError> {
+        self.validate_with_outer_fields(query, session, &[])
+    }
+
+    /// Checks the session's user has EXECUTE on a directory that's used outside of a query, ie
+    /// the file a sink writes to.
+    pub fn check_directory_privilege(
+        &self,
+        directory: &str,
+        session: &Session,
+    ) -> Result<(), PlannerError> {
+        let catalog = self.catalog.read().unwrap();
+        check_privileges::check_directory(&catalog, directory, session)
+    }
+
+    /// Validates a query that may reference fields from an enclosing query, ie the query
+    /// of a correlated subquery.
+    fn validate_with_outer_fields(
         &self,
         mut query: LogicalOperator,
         session: &Session,
+        outer_fields: &[Field],
     ) -> Result<LogicalOperator, PlannerError> {
         // NOTE if adding phases before the resolve table step you will need to
         // add them inside the resolve table where it inlines any views.
         // Populate column aliases
-        sub_in_special_vars::sub_in_special_vars(&mut query);
+        sub_in_special_vars::sub_in_special_vars(&mut query, session);
         column_aliases::normalize_column_aliases(&mut query);
         // Grab a read lock on the catalog and look up the tables
         {
             let catalog = self.catalog.read().unwrap();
+            check_privileges::check_privileges(&catalog, &mut query, session)?;
             resolve_tables::resolve_tables(&catalog, &mut query, session)?;
         }
         // Now that all the fields are there we can expand all the stars
         expand_stars::expand_stars(&mut query);
         validate_values_types::validate_values_types(&mut query)?;
-        compile_functions_and_refs::compile_functions(&mut query, &self.function_registry)?;
+        compile_functions_and_refs::compile_functions(
+            &mut query,
+            outer_fields,
+            &self.function_registry,
+            &|subquery, fields| {
+                *subquery =
+                    self.validate_with_outer_fields(std::mem::take(subquery), session, fields)?;
+                Ok(())
+            },
+        )?;
 
         // At this point the ast's are sane enough that we can ask expressions what types they
         // return etc.
+        extract_windows::extract_windows(&mut query)?;
         convert_project_to_groupby::project_to_groupby(&mut query);
         // Type checks etc
         check_aggregates_usage::check_for_aggregates(&mut query)?;
         check_predicates::check_predicates(&mut query)?;
+        fill_insert_columns::fill_insert_columns(&mut query)?;
+        coerce_types::coerce_types(&mut query, &self.function_registry)?;
         check_inserts::check_inserts(&mut query)?;
         check_unions::check_unions(&mut query)?;
 
@@ -1,43 +1,157 @@
 use crate::p1_validation::{column_aliases, sub_in_special_vars};
+use crate::utils::logical::fields_for_operator;
 use crate::PlannerError;
-use ast::rel::logical::{LogicalOperator, ResolvedTable};
+use ast::expr::{Cast, ColumnReference, Expression, FunctionCall, NamedExpression};
+use ast::rel::logical::{
+    ColumnSchema, ErrorPolicy, Filter, LogicalOperator, Project, ResolvedTable,
+};
 use ast::statement::Statement;
 use catalog::{Catalog, TableOrView};
-use data::Session;
+use data::json::OwnedJson;
+use data::{DataType, LogicalTimestamp, Session};
 
 pub(super) fn resolve_tables(
     catalog: &Catalog,
     operator: &mut LogicalOperator,
     session: &Session,
 ) -> Result<(), PlannerError> {
-    for child in operator.children_mut() {
-        resolve_tables(catalog, child, session)?;
+    resolve_tables_impl(catalog, operator, session, Access::Read, &mut vec![])
+}
+
+/// How a table's being used, policies and masks only apply to the rows being read
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Access {
+    Read,
+    // The table an insert writes into
+    Write,
+    // The rows a delete reads to retract, these have to be the real rows so can't be masked
+    Delete,
+}
+
+/// Views are the views being expanded that the operator's within, outermost first. Views that
+/// refer back to themselves would otherwise be expanded forever.
+fn resolve_tables_impl(
+    catalog: &Catalog,
+    operator: &mut LogicalOperator,
+    session: &Session,
+    access: Access,
+    views: &mut Vec<String>,
+) -> Result<(), PlannerError> {
+    if let LogicalOperator::TableInsert(table_insert) = operator {
+        let source_access = if let LogicalOperator::NegateFreq(_) = *table_insert.source {
+            Access::Delete
+        } else {
+            Access::Read
+        };
+        let table_name = if let LogicalOperator::TableReference(table_ref) = &*table_insert.table {
+            let database = table_ref
+                .database
+                .clone()
+                .unwrap_or_else(|| session.current_database.read().unwrap().to_string());
+            Some((database, table_ref.table.clone()))
+        } else {
+            None
+        };
+        resolve_tables_impl(
+            catalog,
+            &mut table_insert.table,
+            session,
+            Access::Write,
+            views,
+        )?;
+        resolve_tables_impl(
+            catalog,
+            &mut table_insert.source,
+            session,
+            source_access,
+            views,
+        )?;
+        if let (LogicalOperator::ResolvedTable(table), LogicalOperator::FileCopy(file_copy)) =
+            (table_insert.table.as_ref(), table_insert.source.as_mut())
+        {
+            file_copy.columns = copy_columns(&table.columns);
+        }
+        if let (LogicalOperator::ResolvedTable(table), Some((database, name))) =
+            (table_insert.table.as_ref(), table_name)
+        {
+            if source_access != Access::Delete {
+                table_insert.json_schemas =
+                    json_schemas(catalog, (&database, &name), &table.columns)?;
+            }
+        }
+    } else {
+        for child in operator.children_mut() {
+            resolve_tables_impl(catalog, child, session, access, views)?;
+        }
+    }
+
+    if let LogicalOperator::FileScan(file_scan) = operator {
+        if file_scan.serde_options.on_error == ErrorPolicy::DeadLetter {
+            file_scan.dead_letters = Some(catalog.dead_letters_table());
+        }
+        if file_scan.serde_options.resume {
+            file_scan.source_offsets = Some(catalog.source_offsets_table());
+        }
+        file_scan.paused_sources = Some(catalog.paused_sources_table());
+    }
+
+    if let LogicalOperator::FileCopy(file_copy) = operator {
+        if file_copy.serde_options.on_error == ErrorPolicy::DeadLetter {
+            file_copy.dead_letters = Some(catalog.dead_letters_table());
+        }
     }
 
     if let LogicalOperator::TableReference(table_ref) = operator {
-        // In a block to drop the lock as we need  to get write access to it further down for
-        // views
-        let item = {
-            let current_db = session.current_database.read().unwrap();
-            let database = table_ref.database.as_ref().unwrap_or(&current_db);
-            let table_name = &table_ref.table;
-
-            catalog.item(database, table_name)?
-        };
+        let database = table_ref
+            .database
+            .clone()
+            .unwrap_or_else(|| session.current_database.read().unwrap().to_string());
+        let item = catalog.item(&database, &table_ref.table)?;
         match item.item {
             TableOrView::Table(table) => {
-                *operator = LogicalOperator::ResolvedTable(ResolvedTable {
+                let resolved_table = LogicalOperator::ResolvedTable(ResolvedTable {
                     columns: item.columns,
                     table,
-                })
+                    sample: table_ref.sample.take(),
+                    as_of: table_ref.as_of,
+                });
+                *operator = if access == Access::Write {
+                    resolved_table
+                } else {
+                    apply_row_security(
+                        catalog,
+                        (&database, &table_ref.table),
+                        resolved_table,
+                        session,
+                        access,
+                    )?
+                };
+            }
+            TableOrView::View(_) if table_ref.sample.is_some() => {
+                return Err(PlannerError::TableSampleNotSupported(
+                    table_ref.table.clone(),
+                ));
             }
             TableOrView::View(view) => {
+                let view_name = format!("{}.{}", database, table_ref.table);
+                if views.contains(&view_name) {
+                    views.push(view_name);
+                    return Err(PlannerError::ViewRecursion(views.clone()));
+                }
+                let max_depth = *session.max_recursion_depth.read().unwrap();
+                if views.len() >= max_depth as usize {
+                    return Err(PlannerError::ViewDepthExceeded(view_name, max_depth));
+                }
                 if let Statement::Query(op) =
                     parser::parse(&view.sql).expect("Parse failed for view?")
                 {
+                    let as_of = table_ref.as_of;
                     *operator = op;
+                    if let Some(as_of) = as_of {
+                        set_as_of(operator, as_of);
+                    }
                     // Run the planner over the subbed-in sql up to the current phase
-                    sub_in_special_vars::sub_in_special_vars(operator);
+                    sub_in_special_vars::sub_in_special_vars(operator, session);
                     column_aliases::normalize_column_aliases(operator);
                     // Use a session with the "current" db being the same as the one the
                     let mut current_db = view.db_context;
@@ -47,14 +161,16 @@ pub(super) fn resolve_tables(
                             &mut session.current_database.write().unwrap(),
                         );
                     }
-                    for child in operator.children_mut() {
-                        resolve_tables(catalog, child, session)?;
-                    }
-                    // TODO on a failure this will leave the current db changed...
+                    views.push(view_name);
+                    let result = operator.children_mut().try_for_each(|child| {
+                        resolve_tables_impl(catalog, child, session, Access::Read, views)
+                    });
+                    views.pop();
                     std::mem::swap(
                         &mut current_db,
                         &mut session.current_database.write().unwrap(),
                     );
+                    result?;
                 } else {
                     panic!("Bogus view")
                 }
@@ -65,12 +181,147 @@ pub(super) fn resolve_tables(
     Ok(())
 }
 
+/// The columns a copy parses out of each record, the nth field cast to the type of the nth
+/// column of the table. They're try casts, the copy rejects records with fields that come out
+/// null without being empty.
+fn copy_columns(columns: &[(String, DataType)]) -> Vec<NamedExpression> {
+    columns
+        .iter()
+        .enumerate()
+        .map(|(idx, (alias, datatype))| {
+            let field = Expression::ColumnReference(ColumnReference {
+                qualifier: None,
+                alias: format!("field_{}", idx + 1),
+                star: false,
+            });
+            let expression = match datatype {
+                DataType::Text => field,
+                datatype => Expression::Cast(Cast {
+                    expr: Box::new(field),
+                    datatype: *datatype,
+                    try_cast: true,
+                }),
+            };
+            NamedExpression {
+                alias: Some(alias.clone()),
+                expression,
+            }
+        })
+        .collect()
+}
+
+/// The json schemas of the table's json columns that inserted rows are checked against
+fn json_schemas(
+    catalog: &Catalog,
+    (database, table): (&str, &str),
+    columns: &[(String, DataType)],
+) -> Result<Vec<ColumnSchema>, PlannerError> {
+    Ok(catalog
+        .column_schemas(database, table)?
+        .into_iter()
+        .filter_map(|(column, schema)| {
+            // Columns since modified to another type aren't checked
+            let offset = columns
+                .iter()
+                .position(|(alias, datatype)| *alias == column && *datatype == DataType::Json)?;
+            let schema = OwnedJson::parse(&schema)?;
+            Some(ColumnSchema {
+                column,
+                offset,
+                schema,
+            })
+        })
+        .collect())
+}
+
+/// A view read AS OF TIMESTAMP reads all of its tables as of the timestamp, unless they're
+/// read as of one of their own
+fn set_as_of(operator: &mut LogicalOperator, as_of: LogicalTimestamp) {
+    if let LogicalOperator::TableReference(table_ref) = operator {
+        table_ref.as_of.get_or_insert(as_of);
+    }
+    for child in operator.children_mut() {
+        set_as_of(child, as_of);
+    }
+}
+
+/// Users other than the table's owner only see the rows matching the predicates of all of the
+/// table's policies, they're AND-ed together in a filter over the table. Masked columns are
+/// swapped for their masks by a project over that.
+fn apply_row_security(
+    catalog: &Catalog,
+    (database, table): (&str, &str),
+    resolved_table: LogicalOperator,
+    session: &Session,
+    access: Access,
+) -> Result<LogicalOperator, PlannerError> {
+    let policies = catalog.policies(database, table)?;
+    let masks = catalog.column_masks(database, table)?;
+    if policies.is_empty() && masks.is_empty() {
+        return Ok(resolved_table);
+    }
+    let owner = catalog.table_owner(database, table)?;
+    if owner.as_deref() == Some(session.user.read().unwrap().as_str()) {
+        return Ok(resolved_table);
+    }
+    if !masks.is_empty() && access == Access::Delete {
+        return Err(PlannerError::DeleteFromMaskedTable(table.to_string()));
+    }
+
+    let mut operator = resolved_table;
+    let predicate = policies
+        .iter()
+        .map(|policy| parser::parse_expression(&policy.predicate).expect("Bogus policy"))
+        .fold(None, |predicate, policy_predicate| match predicate {
+            Some(predicate) => Some(Expression::FunctionCall(FunctionCall {
+                function_name: "and".to_string(),
+                args: vec![predicate, policy_predicate],
+            })),
+            None => Some(policy_predicate),
+        });
+    if let Some(predicate) = predicate {
+        operator = LogicalOperator::Filter(Filter {
+            predicate,
+            source: Box::new(operator),
+            pinned: false,
+        });
+    }
+    if !masks.is_empty() {
+        let expressions = fields_for_operator(&operator)
+            .map(|field| {
+                let mask = masks.iter().find(|(column, _mask)| *column == field.alias);
+                let expression = match mask {
+                    Some((_column, mask)) => {
+                        parser::parse_expression(mask).expect("Bogus column mask")
+                    }
+                    None => Expression::ColumnReference(ColumnReference {
+                        qualifier: None,
+                        alias: field.alias.clone(),
+                        star: false,
+                    }),
+                };
+                NamedExpression {
+                    alias: Some(field.alias),
+                    expression,
+                }
+            })
+            .collect();
+        operator = LogicalOperator::Project(Project {
+            distinct: false,
+            expressions,
+            source: Box::new(operator),
+        });
+    }
+    sub_in_special_vars::sub_in_special_vars(&mut operator, session);
+    Ok(operator)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utils::logical::fields_for_operator;
     use crate::Field;
-    use ast::rel::logical::TableReference;
+    use ast::rel::logical::{TableInsert, TableReference};
+    use catalog::Policy;
     use data::DataType;
 
     #[test]
@@ -80,6 +331,8 @@ mod tests {
         let mut operator = LogicalOperator::TableReference(TableReference {
             database: Some("incresql".to_string()),
             table: "databases".to_string(),
+            sample: None,
+            as_of: None,
         });
 
         resolve_tables(&catalog, &mut operator, &session)?;
@@ -105,6 +358,8 @@ mod tests {
         let mut operator = LogicalOperator::TableReference(TableReference {
             database: None,
             table: "databases".to_string(),
+            sample: None,
+            as_of: None,
         });
 
         resolve_tables(&catalog, &mut operator, &session)?;
@@ -121,4 +376,114 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_resolve_table_with_policies() -> Result<(), PlannerError> {
+        let mut catalog = Catalog::new_for_test().unwrap();
+        catalog.create_table(
+            "default",
+            "orders",
+            &[("tenant".to_string(), DataType::Text)],
+        )?;
+        catalog.set_table_owner("default", "orders", "admin")?;
+        for name in &["a", "b"] {
+            catalog.create_policy(&Policy {
+                database: "default".to_string(),
+                table: "orders".to_string(),
+                name: name.to_string(),
+                predicate: format!("tenant != \"{}\"", name),
+            })?;
+        }
+        let session = Session::new(1);
+        let table_ref = LogicalOperator::TableReference(TableReference {
+            database: None,
+            table: "orders".to_string(),
+            sample: None,
+            as_of: None,
+        });
+
+        // Both policies are applied to everyone else
+        *session.user.write().unwrap() = "bob".to_string();
+        let mut operator = table_ref.clone();
+        resolve_tables(&catalog, &mut operator, &session)?;
+        if let LogicalOperator::Filter(filter) = &operator {
+            assert_eq!(
+                filter.predicate,
+                parser::parse_expression(r#"tenant != "a" AND tenant != "b""#).unwrap()
+            );
+            assert!(matches!(*filter.source, LogicalOperator::ResolvedTable(_)));
+        } else {
+            panic!("Expected a filter, got {:?}", operator)
+        }
+
+        // But not the owner
+        *session.user.write().unwrap() = "admin".to_string();
+        let mut operator = table_ref;
+        resolve_tables(&catalog, &mut operator, &session)?;
+        assert!(matches!(operator, LogicalOperator::ResolvedTable(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_table_with_masks() -> Result<(), PlannerError> {
+        let mut catalog = Catalog::new_for_test().unwrap();
+        let columns = [
+            ("id".to_string(), DataType::Integer),
+            ("card".to_string(), DataType::Text),
+        ];
+        catalog.create_table("default", "customers", &columns)?;
+        catalog.set_table_owner("default", "customers", "admin")?;
+        catalog.set_column_mask("default", "customers", "card", Some("mask_text(card, 4)"))?;
+        let session = Session::new(1);
+        *session.user.write().unwrap() = "bob".to_string();
+        let table_ref = LogicalOperator::TableReference(TableReference {
+            database: None,
+            table: "customers".to_string(),
+            sample: None,
+            as_of: None,
+        });
+
+        let mut operator = table_ref.clone();
+        resolve_tables(&catalog, &mut operator, &session)?;
+        if let LogicalOperator::Project(project) = &operator {
+            assert_eq!(
+                project.expressions,
+                vec![
+                    NamedExpression {
+                        alias: Some("id".to_string()),
+                        expression: parser::parse_expression("id").unwrap(),
+                    },
+                    NamedExpression {
+                        alias: Some("card".to_string()),
+                        expression: parser::parse_expression("mask_text(card, 4)").unwrap(),
+                    }
+                ]
+            );
+        } else {
+            panic!("Expected a project, got {:?}", operator)
+        }
+
+        // Deletes have to see the real rows, inserts aren't masked
+        let mut delete = LogicalOperator::TableInsert(TableInsert {
+            table: Box::new(table_ref.clone()),
+            columns: vec![],
+            json_schemas: vec![],
+            source: Box::new(LogicalOperator::NegateFreq(Box::new(table_ref.clone()))),
+        });
+        assert!(matches!(
+            resolve_tables(&catalog, &mut delete, &session),
+            Err(PlannerError::DeleteFromMaskedTable(_))
+        ));
+        let mut insert = LogicalOperator::TableInsert(TableInsert {
+            table: Box::new(table_ref),
+            columns: vec![],
+            json_schemas: vec![],
+            source: Box::new(LogicalOperator::Single),
+        });
+        resolve_tables(&catalog, &mut insert, &session)?;
+        if let LogicalOperator::TableInsert(insert) = insert {
+            assert!(matches!(*insert.table, LogicalOperator::ResolvedTable(_)));
+        }
+        Ok(())
+    }
 }
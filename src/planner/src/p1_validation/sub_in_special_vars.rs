@@ -1,33 +1,50 @@
 use ast::expr::*;
 use ast::rel::logical::LogicalOperator;
-use data::{DataType, Datum};
+use data::{DataType, Datum, Session};
 
 /// Mysql uses some @@ magic variables that they can select.
-/// This is here to replace some of them with Constants
-pub(super) fn sub_in_special_vars(query: &mut LogicalOperator) {
+/// This is here to replace some of them with Constants, along with any @name user variables
+/// set with SET @name = ..., unset user variables are null.
+pub(super) fn sub_in_special_vars(query: &mut LogicalOperator, session: &Session) {
     for child in query.children_mut() {
-        sub_in_special_vars(child);
+        sub_in_special_vars(child, session);
     }
 
     for expression in query.expressions_mut() {
-        if let Expression::ColumnReference(ColumnReference {
-            qualifier: _,
-            alias,
-            star: _,
-        }) = expression
-        {
-            if alias.starts_with("@@") {
-                let constant = match alias.as_str() {
-                    "@@max_allowed_packet" => {
-                        Expression::Constant(Datum::from(0xffffff), DataType::Integer)
-                    }
-                    "@@socket" => Expression::Constant(Datum::from(""), DataType::Text),
-
-                    _ => continue,
-                };
-                *expression = constant;
+        sub_in_special_vars_for_expr(expression, session);
+    }
+}
+
+fn sub_in_special_vars_for_expr(expression: &mut Expression, session: &Session) {
+    if let Expression::ColumnReference(ColumnReference {
+        qualifier: _,
+        alias,
+        star: _,
+    }) = expression
+    {
+        let constant = if alias.starts_with("@@") {
+            match alias.as_str() {
+                "@@max_allowed_packet" => {
+                    Expression::Constant(Datum::from(0xffffff), DataType::Integer)
+                }
+                "@@socket" => Expression::Constant(Datum::from(""), DataType::Text),
+
+                _ => return,
+            }
+        } else if let Some(name) = alias.strip_prefix('@') {
+            match session.user_variables.read().unwrap().get(name) {
+                Some((datum, datatype)) => Expression::Constant(datum.clone(), *datatype),
+                None => Expression::Constant(Datum::Null, DataType::Null),
             }
-        }
+        } else {
+            return;
+        };
+        *expression = constant;
+        return;
+    }
+
+    for child in expression.children_mut() {
+        sub_in_special_vars_for_expr(child, session);
     }
 }
 
@@ -53,11 +70,52 @@ mod tests {
             source: Box::new(LogicalOperator::Single),
         });
 
-        sub_in_special_vars(&mut operator);
+        sub_in_special_vars(&mut operator, &Session::new(1));
 
         assert_eq!(
             operator.expressions_mut().next().unwrap(),
             &mut Expression::Constant(Datum::from(0xffffff), DataType::Integer)
         );
     }
+
+    #[test]
+    fn test_sub_in_user_vars() {
+        let user_var = |name: &str| {
+            Expression::ColumnReference(ColumnReference {
+                qualifier: None,
+                alias: name.to_string(),
+                star: false,
+            })
+        };
+        let mut operator = LogicalOperator::Project(Project {
+            distinct: false,
+            expressions: vec![NamedExpression {
+                alias: None,
+                expression: Expression::FunctionCall(FunctionCall {
+                    function_name: "+".to_string(),
+                    args: vec![user_var("@x"), user_var("@y")],
+                }),
+            }],
+            source: Box::new(LogicalOperator::Single),
+        });
+        let session = Session::new(1);
+        session
+            .user_variables
+            .write()
+            .unwrap()
+            .insert("x".to_string(), (Datum::from(1_i64), DataType::BigInt));
+
+        sub_in_special_vars(&mut operator, &session);
+
+        assert_eq!(
+            operator.expressions_mut().next().unwrap(),
+            &mut Expression::FunctionCall(FunctionCall {
+                function_name: "+".to_string(),
+                args: vec![
+                    Expression::Constant(Datum::from(1_i64), DataType::BigInt),
+                    Expression::Constant(Datum::Null, DataType::Null)
+                ],
+            })
+        );
+    }
 }
@@ -1,23 +1,34 @@
+use crate::p1_validation::fill_insert_columns::target_offsets;
 use crate::utils::expr::type_for_expression;
 use crate::PlannerError;
+use ast::expr::{Cast, Expression};
 use ast::rel::logical::{LogicalOperator, TableInsert};
-use data::DataType;
+use data::{cast_kind, CastKind, DataType, Datum};
 
 /// Walks "values" (ie insert .. values ()) and populates types in the header,
-/// has to happen fairly early on in the planning
+/// has to happen fairly early on in the planning. Inserts that name their columns only take
+/// the types of the columns named.
 pub(super) fn validate_values_types(query: &mut LogicalOperator) -> Result<(), PlannerError> {
     for child in query.children_mut() {
         validate_values_types(child)?;
     }
 
-    if let LogicalOperator::TableInsert(TableInsert { table, source }) = query {
+    if let LogicalOperator::TableInsert(TableInsert {
+        table,
+        source,
+        columns,
+        ..
+    }) = query
+    {
         if let (LogicalOperator::Values(values), LogicalOperator::ResolvedTable(resolved_tables)) =
             (source.as_mut(), table.as_mut())
         {
-            values.fields = resolved_tables
-                .columns
-                .iter()
-                .map(|(alias, dt)| (*dt, alias.clone()))
+            values.fields = target_offsets(&resolved_tables.columns, columns)?
+                .into_iter()
+                .map(|idx| {
+                    let (alias, dt) = &resolved_tables.columns[idx];
+                    (*dt, alias.clone())
+                })
                 .collect();
 
             let table_types: Vec<_> = values
@@ -25,15 +36,60 @@ pub(super) fn validate_values_types(query: &mut LogicalOperator) -> Result<(), P
                 .iter()
                 .map(|(datatype, _)| *datatype)
                 .collect();
-            for row in &values.data {
+            // Any parameters being inserted take the type of their column and any labels
+            // being inserted into enum columns are swapped for their codes
+            for row in &mut values.data {
+                for (expr, datatype) in row.iter_mut().zip(table_types.iter()) {
+                    match expr {
+                        Expression::Parameter(parameter) => parameter.datatype = *datatype,
+                        Expression::Constant(datum, constant_type @ DataType::Text) => {
+                            if let Some(labels) = datatype.enum_labels() {
+                                let label = datum.as_text();
+                                if let Some(idx) = labels.iter().position(|l| l == label) {
+                                    *datum = Datum::from(idx as i32);
+                                    *constant_type = *datatype;
+                                } else {
+                                    return Err(PlannerError::UnknownEnumLabel(
+                                        label.to_string(),
+                                        *datatype,
+                                    ));
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            // Values that can be implicitly cast to their column's type are, ie ints into
+            // bigint columns
+            for row in &mut values.data {
                 let row_types: Vec<_> = row.iter().map(type_for_expression).collect();
-                let is_match = row_types
-                    .iter()
-                    .zip(table_types.iter())
-                    .all(|(row, table)| row == table || *row == DataType::Null);
+                let is_match = row_types.len() == table_types.len()
+                    && row_types
+                        .iter()
+                        .zip(table_types.iter())
+                        .all(|(row, table)| {
+                            matches!(cast_kind(*row, *table), CastKind::Implicit(_))
+                        });
                 if !is_match {
                     return Err(PlannerError::InsertMismatch(table_types, row_types));
                 }
+                for ((expr, row_type), table_type) in
+                    row.iter_mut().zip(row_types).zip(table_types.iter())
+                {
+                    if row_type != *table_type && row_type != DataType::Null {
+                        let value = std::mem::replace(
+                            expr,
+                            Expression::Constant(Datum::Null, DataType::Null),
+                        );
+                        *expr = Expression::Cast(Cast {
+                            expr: Box::new(value),
+                            datatype: *table_type,
+                            try_cast: false,
+                        });
+                    }
+                }
             }
         }
     }
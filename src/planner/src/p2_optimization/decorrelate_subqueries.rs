@@ -0,0 +1,457 @@
+use crate::p1_validation::compile_functions_and_refs::compile_expression;
+use crate::utils::expr::{
+    combine_predicates, contains_subquery, decompose_predicate, min_max_column_deps_for_expression,
+};
+use crate::utils::logical::{
+    create_wrapping_project, fields_for_operator, source_fields_for_operator,
+};
+use crate::PlannerError;
+use ast::expr::{CompiledColumnReference, Expression, FunctionCall, Subquery};
+use ast::rel::logical::{Filter, Join, JoinType, LogicalOperator};
+use functions::registry::Registry;
+
+/// Rewrites EXISTS/IN subqueries in where clauses as semi(or anti for the NOT versions) joins.
+/// Any correlated predicates from the subquery's where clause are pulled up into the join
+/// condition, this means the subquery only has to be evaluated once and the join can be
+/// executed as a hash join rather than re-running the subquery for each row.
+/// ie
+/// SELECT * FROM a WHERE EXISTS (SELECT * FROM b WHERE b.id = a.id AND b.c > 1)
+/// becomes
+/// SELECT * FROM a LEFT SEMI JOIN (SELECT * FROM b WHERE b.c > 1) b ON b.id = a.id
+/// Scalar subqueries in the select list or where clause are rewritten the same way, just as
+/// single joins that null pad when there's no match and fail when there's more than one.
+pub(super) fn decorrelate_subqueries(
+    operator: &mut LogicalOperator,
+    function_registry: &Registry,
+) -> Result<(), PlannerError> {
+    for child in operator.children_mut() {
+        decorrelate_subqueries(child, function_registry)?;
+    }
+
+    if let LogicalOperator::Filter(filter) = operator {
+        let mut source = std::mem::take(filter.source.as_mut());
+        let mut remaining = vec![];
+
+        for predicate in decompose_predicate(std::mem::take(&mut filter.predicate)) {
+            match as_subquery(predicate) {
+                Ok(subquery) => source = join_subquery(source, subquery, function_registry)?,
+                Err(predicate) => remaining.push(predicate),
+            }
+        }
+
+        filter.predicate = combine_predicates(remaining, function_registry);
+        *filter.source = source;
+    }
+
+    // Scalar subqueries get joined onto the source of the operator using them.
+    let mut filter_output_len = None;
+    match operator {
+        LogicalOperator::Project(project) => {
+            for named_expression in &mut project.expressions {
+                join_scalar_subqueries(
+                    &mut named_expression.expression,
+                    &mut project.source,
+                    function_registry,
+                )?;
+            }
+        }
+        LogicalOperator::Filter(filter) if contains_subquery(&filter.predicate) => {
+            filter_output_len = Some(fields_for_operator(&filter.source).count());
+            join_scalar_subqueries(&mut filter.predicate, &mut filter.source, function_registry)?;
+        }
+        _ => {}
+    }
+
+    // Anything left over is somewhere we can't rewrite, ie under an OR or in an aggregate.
+    for expr in operator.expressions_mut() {
+        if contains_subquery(expr) {
+            return Err(PlannerError::SubqueryNotSupported(
+                "subqueries can only be used as where clause conditions or in the select list of queries without aggregates",
+            ));
+        }
+    }
+
+    // A filter passes the subqueries' columns through so they need dropping again.
+    if let Some(output_len) = filter_output_len {
+        let mut project = create_wrapping_project(std::mem::take(operator));
+        project.expressions.truncate(output_len);
+        *operator = LogicalOperator::Project(project);
+    }
+    Ok(())
+}
+
+/// Replaces any scalar subqueries in the expression with a reference to their value, each of
+/// them is joined onto the source.
+fn join_scalar_subqueries(
+    expression: &mut Expression,
+    source: &mut LogicalOperator,
+    function_registry: &Registry,
+) -> Result<(), PlannerError> {
+    if matches!(expression, Expression::Subquery(subquery) if subquery.scalar) {
+        if let Expression::Subquery(subquery) = std::mem::take(expression) {
+            let left = std::mem::take(source);
+            let (joined, value) = join_scalar_subquery(left, *subquery, function_registry)?;
+            *source = joined;
+            *expression = value;
+        }
+        return Ok(());
+    }
+    for expr in expression.children_mut() {
+        join_scalar_subqueries(expr, source, function_registry)?;
+    }
+    Ok(())
+}
+
+/// Returns the subquery if the predicate is a subquery or the negation of one, otherwise
+/// hands the predicate back.
+fn as_subquery(predicate: Expression) -> Result<Subquery, Expression> {
+    match predicate {
+        Expression::Subquery(subquery) => Ok(*subquery),
+        Expression::CompiledFunctionCall(function)
+            if function.signature.name == "not"
+                && matches!(function.args[0], Expression::Subquery(_)) =>
+        {
+            if let Some(Expression::Subquery(mut subquery)) = function.args.into_vec().pop() {
+                subquery.negated = !subquery.negated;
+                Ok(*subquery)
+            } else {
+                panic!()
+            }
+        }
+        predicate => Err(predicate),
+    }
+}
+
+/// Joins the subquery onto the left operator.
+fn join_subquery(
+    left: LogicalOperator,
+    subquery: Subquery,
+    function_registry: &Registry,
+) -> Result<LogicalOperator, PlannerError> {
+    let Subquery {
+        negated,
+        operand,
+        query,
+        ..
+    } = subquery;
+    let left_len = fields_for_operator(&left).count();
+    let Decorrelated {
+        right,
+        select_expr,
+        mut conditions,
+        inner_len,
+    } = decorrelate(query, left_len, function_registry)?;
+
+    if let Some(operand) = operand {
+        // We've checked during validation that there is exactly one column.
+        let select_expr = select_value(select_expr.unwrap(), inner_len, left_len)?;
+
+        let mut condition = Expression::FunctionCall(FunctionCall {
+            function_name: "=".to_string(),
+            args: vec![operand, select_expr],
+        });
+        if negated {
+            // NOT IN has to exclude rows if either side is null, treating null's as a match
+            // gets us the right semantics from the anti join.
+            condition = Expression::FunctionCall(FunctionCall {
+                function_name: "coalesce".to_string(),
+                args: vec![condition, Expression::from(true)],
+            });
+        }
+        compile_expression(&mut condition, function_registry)?;
+        conditions.push(condition);
+    }
+
+    Ok(LogicalOperator::Join(Join {
+        left: Box::new(left),
+        right: Box::new(right),
+        on: combine_predicates(conditions, function_registry),
+        join_type: if negated {
+            JoinType::LeftAnti
+        } else {
+            JoinType::LeftSemi
+        },
+        build_side: None,
+    }))
+}
+
+/// Joins the scalar subquery onto the left operator, returns the join along with the
+/// expression for the subquery's value from the join's point of view.
+fn join_scalar_subquery(
+    left: LogicalOperator,
+    subquery: Subquery,
+    function_registry: &Registry,
+) -> Result<(LogicalOperator, Expression), PlannerError> {
+    let left_len = fields_for_operator(&left).count();
+    let Decorrelated {
+        right,
+        select_expr,
+        conditions,
+        inner_len,
+    } = decorrelate(subquery.query, left_len, function_registry)?;
+    // We've checked during validation that there is exactly one column.
+    let value = select_value(select_expr.unwrap(), inner_len, left_len)?;
+
+    let join = LogicalOperator::Join(Join {
+        left: Box::new(left),
+        right: Box::new(right),
+        on: combine_predicates(conditions, function_registry),
+        join_type: JoinType::LeftSingle,
+        build_side: None,
+    });
+    Ok((join, value))
+}
+
+/// A subquery split up ready to be joined on.
+struct Decorrelated {
+    // What's left of the subquery once its select list and correlated predicates are removed
+    right: LogicalOperator,
+    // The first expression of the select list, still in the scope of the subquery
+    select_expr: Option<Expression>,
+    // The correlated predicates in the scope of the join
+    conditions: Vec<Expression>,
+    inner_len: usize,
+}
+
+/// Peels the select list and any correlated predicates off of the subquery.
+fn decorrelate(
+    mut query: LogicalOperator,
+    left_len: usize,
+    function_registry: &Registry,
+) -> Result<Decorrelated, PlannerError> {
+    // Any subqueries of the subquery first.
+    decorrelate_subqueries(&mut query, function_registry)?;
+
+    // Peel off the select list, for EXISTS we don't care about it and for IN we'll
+    // use it in the join condition directly.
+    let (body, select_expr) = match query {
+        LogicalOperator::Project(project) => {
+            let select_expr = project
+                .expressions
+                .into_iter()
+                .next()
+                .map(|ne| ne.expression);
+            (*project.source, select_expr)
+        }
+        query => {
+            let select_expr = fields_for_operator(&query).next().map(|field| {
+                Expression::CompiledColumnReference(CompiledColumnReference {
+                    offset: 0,
+                    datatype: field.data_type,
+                })
+            });
+            (query, select_expr)
+        }
+    };
+
+    // Split out any correlated predicates from the subquery's where clause.
+    let mut conditions = vec![];
+    let (mut right, inner_len) = match body {
+        LogicalOperator::Filter(filter) => {
+            let inner_len = fields_for_operator(&filter.source).count();
+            let mut local = vec![];
+            for mut predicate in decompose_predicate(filter.predicate) {
+                match min_max_column_deps_for_expression(&mut predicate) {
+                    Some((_min, max)) if max >= inner_len => conditions.push(predicate),
+                    _ => local.push(predicate),
+                }
+            }
+            let right = LogicalOperator::Filter(Filter {
+                predicate: combine_predicates(local, function_registry),
+                source: filter.source,
+                pinned: filter.pinned,
+            });
+            (right, inner_len)
+        }
+        body => {
+            let inner_len = fields_for_operator(&body).count();
+            (body, inner_len)
+        }
+    };
+
+    if has_outer_references(&mut right) {
+        return Err(PlannerError::SubqueryNotSupported(
+            "correlated fields can only be referenced from the subquery's where clause",
+        ));
+    }
+
+    for condition in &mut conditions {
+        remap_to_join(condition, inner_len, left_len)?;
+    }
+
+    Ok(Decorrelated {
+        right,
+        select_expr,
+        conditions,
+        inner_len,
+    })
+}
+
+/// Rewrites the subquery's select expression to the scope of the join, it can only be made
+/// up of the subquery's own fields.
+fn select_value(
+    mut select_expr: Expression,
+    inner_len: usize,
+    left_len: usize,
+) -> Result<Expression, PlannerError> {
+    if let Some((_min, max)) = min_max_column_deps_for_expression(&mut select_expr) {
+        if max >= inner_len {
+            return Err(PlannerError::SubqueryNotSupported(
+                "correlated fields can only be referenced from the subquery's where clause",
+            ));
+        }
+    }
+    remap_to_join(&mut select_expr, inner_len, left_len)?;
+    Ok(select_expr)
+}
+
+/// Returns true if any expressions in the operator tree reference fields from outside of
+/// their sources, ie fields from an enclosing query.
+fn has_outer_references(operator: &mut LogicalOperator) -> bool {
+    let source_len = source_fields_for_operator(operator).count();
+    let outer_reference = operator.expressions_mut().any(|expr| {
+        matches!(min_max_column_deps_for_expression(expr), Some((_min, max)) if max >= source_len)
+    });
+    outer_reference || operator.children_mut().any(has_outer_references)
+}
+
+/// Rewrites an expression from the scope of the subquery (the inner fields followed by the
+/// outer fields) to the scope of the join (the left/outer fields followed by the inner fields)
+fn remap_to_join(
+    expression: &mut Expression,
+    inner_len: usize,
+    left_len: usize,
+) -> Result<(), PlannerError> {
+    if let Expression::CompiledColumnReference(column_reference) = expression {
+        column_reference.offset = if column_reference.offset < inner_len {
+            column_reference.offset + left_len
+        } else if column_reference.offset - inner_len < left_len {
+            column_reference.offset - inner_len
+        } else {
+            return Err(PlannerError::SubqueryNotSupported(
+                "correlated fields can only come from the immediately enclosing query",
+            ));
+        };
+    }
+    for expr in expression.children_mut() {
+        remap_to_join(expr, inner_len, left_len)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Planner, PlannerError};
+    use data::Session;
+
+    fn decorrelate(sql: &str) -> Result<LogicalOperator, PlannerError> {
+        let planner = Planner::new_for_test();
+        let session = Session::new(1);
+        let query = if let ast::statement::Statement::Query(query) = parser::parse(sql).unwrap() {
+            query
+        } else {
+            panic!()
+        };
+        let mut query = planner.validate(query, &session)?;
+        decorrelate_subqueries(&mut query, &planner.function_registry)?;
+        Ok(query)
+    }
+
+    fn find_join(operator: &mut LogicalOperator) -> Option<&mut Join> {
+        if let LogicalOperator::Join(join) = operator {
+            Some(join)
+        } else {
+            operator.children_mut().find_map(find_join)
+        }
+    }
+
+    #[test]
+    fn test_decorrelate_exists() -> Result<(), PlannerError> {
+        let mut query = decorrelate(
+            "SELECT * FROM (SELECT 1 as a) o \
+             WHERE EXISTS (SELECT * FROM (SELECT 1 as b, 2 as c) i WHERE i.b = o.a AND i.c > 1)",
+        )?;
+        let join = find_join(&mut query).unwrap();
+
+        assert_eq!(join.join_type, JoinType::LeftSemi);
+        // The correlated predicate has been pulled up into the join with both sides
+        // referenced from the join's point of view, the other predicate stays put.
+        assert_eq!(join.on.to_string(), "`=`(<OFFSET 1>, <OFFSET 0>)");
+        assert!(
+            matches!(join.right.as_ref(), LogicalOperator::Filter(filter) if filter.predicate.to_string() == "`>`(<OFFSET 1>, 1)")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_decorrelate_not_in() -> Result<(), PlannerError> {
+        let mut query = decorrelate(
+            "SELECT * FROM (SELECT 1 as a) o WHERE a NOT IN (SELECT 2 as b) AND a > 0",
+        )?;
+
+        // The other predicate stays put in the filter
+        if let LogicalOperator::Project(project) = &query {
+            assert!(
+                matches!(project.source.as_ref(), LogicalOperator::Filter(filter) if filter.predicate.to_string() == "`>`(<OFFSET 0>, 0)")
+            );
+        } else {
+            panic!()
+        }
+        let join = find_join(&mut query).unwrap();
+        assert_eq!(join.join_type, JoinType::LeftAnti);
+        assert_eq!(join.on.to_string(), "coalesce(`=`(<OFFSET 0>, 2), TRUE)");
+        Ok(())
+    }
+
+    #[test]
+    fn test_decorrelate_scalar() -> Result<(), PlannerError> {
+        let mut query = decorrelate(
+            "SELECT a, (SELECT i.c FROM (SELECT 1 as b, 2 as c) i WHERE i.b = o.a) + 1 \
+             FROM (SELECT 1 as a) o",
+        )?;
+
+        // The subquery's value is read from the column after the outer query's
+        if let LogicalOperator::Project(project) = &query {
+            assert_eq!(
+                project.expressions[1].expression.to_string(),
+                "`+`(<OFFSET 2>, 1)"
+            );
+        } else {
+            panic!()
+        }
+        let join = find_join(&mut query).unwrap();
+        assert_eq!(join.join_type, JoinType::LeftSingle);
+        assert_eq!(join.on.to_string(), "`=`(<OFFSET 1>, <OFFSET 0>)");
+        Ok(())
+    }
+
+    #[test]
+    fn test_decorrelate_scalar_in_where() -> Result<(), PlannerError> {
+        let query = decorrelate("SELECT * FROM (SELECT 1 as a) o WHERE a > (SELECT 0)")?;
+
+        // The subquery's column is dropped again after the filter
+        assert_eq!(fields_for_operator(&query).count(), 1);
+        if let LogicalOperator::Project(project) = &query {
+            assert!(matches!(
+                project.source.as_ref(),
+                LogicalOperator::Project(_)
+            ));
+        } else {
+            panic!()
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_decorrelate_unsupported() {
+        assert!(matches!(
+            decorrelate("SELECT 1 WHERE 1 = 2 OR EXISTS(SELECT 1)"),
+            Err(PlannerError::SubqueryNotSupported(_))
+        ));
+        assert!(matches!(
+            decorrelate("SELECT count(*), (SELECT 1) FROM (SELECT 1 as a) o"),
+            Err(PlannerError::SubqueryNotSupported(_))
+        ));
+    }
+}
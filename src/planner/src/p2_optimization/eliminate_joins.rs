@@ -0,0 +1,249 @@
+use crate::utils::expr::{decompose_predicate, min_max_column_deps_for_expression};
+use crate::utils::logical::fieldnames_for_operator;
+use crate::utils::usage::walk_with_usage;
+use ast::expr::Expression;
+use ast::rel::logical::{Join, JoinType, LogicalOperator};
+
+/// Removes left outer joins where none of the right side's columns are used and where
+/// the join can't change the number of rows, ie the right side is unique on the join keys.
+/// Generated sql over view stacks tends to join in a lot of tables "just in case", this
+/// saves us from actually having to do the work.
+pub(super) fn eliminate_joins(query: &mut LogicalOperator) {
+    let used = vec![true; fieldnames_for_operator(query).count()];
+    walk_with_usage(query, used, &mut eliminate_join);
+}
+
+/// Swaps the join for its left side if it can be eliminated, returning the right side's
+/// columns that were removed.
+fn eliminate_join(operator: &mut LogicalOperator, used: &mut Vec<bool>) -> Vec<(usize, usize)> {
+    if let LogicalOperator::Join(join) = operator {
+        if can_eliminate(join, used) {
+            let left_len = fieldnames_for_operator(&join.left).count();
+            let right_len = fieldnames_for_operator(&join.right).count();
+            *operator = std::mem::take(join.left.as_mut());
+            used.truncate(left_len);
+            return vec![(left_len, right_len)];
+        }
+    }
+    vec![]
+}
+
+/// Returns true if the join is a left join where the right side isn't used and
+/// each left row can match at most one right row.
+fn can_eliminate(join: &Join, used: &[bool]) -> bool {
+    if join.join_type != JoinType::LeftOuter {
+        return false;
+    }
+    let left_len = fieldnames_for_operator(&join.left).count();
+    if used.iter().skip(left_len).any(|u| *u) {
+        return false;
+    }
+
+    // Find all the right columns that are fixed by the join condition, any other conditions
+    // can only reduce the matches so we don't care about them.
+    let mut fixed_columns = vec![];
+    for condition in decompose_predicate(join.on.clone()) {
+        if let Expression::CompiledFunctionCall(function) = condition {
+            if function.signature.name == "=" {
+                let (a, b) = (&function.args[0], &function.args[1]);
+                fixed_columns.extend(fixed_column(a, b, left_len));
+                fixed_columns.extend(fixed_column(b, a, left_len));
+            }
+        }
+    }
+
+    unique_keys(&join.right)
+        .iter()
+        .any(|key| key.iter().all(|column| fixed_columns.contains(column)))
+}
+
+/// For a condition of column = other, returns the right side column if other doesn't
+/// depend on the right side.
+fn fixed_column(column: &Expression, other: &Expression, left_len: usize) -> Option<usize> {
+    if let Expression::CompiledColumnReference(column_reference) = column {
+        let other_deps = min_max_column_deps_for_expression(&mut other.clone());
+        if column_reference.offset >= left_len
+            && other_deps.map(|(_min, max)| max < left_len).unwrap_or(true)
+        {
+            return Some(column_reference.offset - left_len);
+        }
+    }
+    None
+}
+
+/// Returns sets of columns for which each set is known to be unique for the operator.
+/// An empty set means the operator returns at most one row.
+fn unique_keys(operator: &LogicalOperator) -> Vec<Vec<usize>> {
+    match operator {
+        LogicalOperator::Single => vec![vec![]],
+        LogicalOperator::Values(values) if values.data.len() <= 1 => vec![vec![]],
+        LogicalOperator::Limit(limit) if (0..=1).contains(&limit.limit) => vec![vec![]],
+        LogicalOperator::GroupBy(group_by) => {
+            // The grouping keys have to be output directly for us to make use of them
+            let key: Option<Vec<_>> = group_by
+                .key_expressions
+                .iter()
+                .map(|key_expr| {
+                    group_by
+                        .expressions
+                        .iter()
+                        .position(|ne| &ne.expression == key_expr)
+                })
+                .collect();
+            key.into_iter().collect()
+        }
+        LogicalOperator::Project(project) => unique_keys(&project.source)
+            .into_iter()
+            .filter_map(|key| {
+                key.into_iter()
+                    .map(|column| {
+                        project.expressions.iter().position(|ne| {
+                            matches!(&ne.expression, Expression::CompiledColumnReference(column_reference) if column_reference.offset == column)
+                        })
+                    })
+                    .collect()
+            })
+            .collect(),
+        LogicalOperator::Filter(filter) => unique_keys(&filter.source),
+        LogicalOperator::Sort(sort) => unique_keys(&sort.source),
+        LogicalOperator::Limit(limit) => unique_keys(&limit.source),
+        LogicalOperator::TableAlias(table_alias) => unique_keys(&table_alias.source),
+        // Tables without a primary key are keyed on all their columns but can hold duplicates
+        LogicalOperator::ResolvedTable(resolved) => resolved
+            .table
+            .primary_key()
+            .map(<[usize]>::to_vec)
+            .into_iter()
+            .collect(),
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Planner, PlannerError};
+    use catalog::TableOptions;
+    use data::{DataType, Session, SortOrder};
+
+    fn eliminate(sql: &str) -> Result<LogicalOperator, PlannerError> {
+        eliminate_with_planner(&Planner::new_for_test(), sql)
+    }
+
+    fn eliminate_with_planner(
+        planner: &Planner,
+        sql: &str,
+    ) -> Result<LogicalOperator, PlannerError> {
+        let session = Session::new(1);
+        let query = if let ast::statement::Statement::Query(query) = parser::parse(sql).unwrap() {
+            query
+        } else {
+            panic!()
+        };
+        let mut query = planner.validate(query, &session)?;
+        eliminate_joins(&mut query);
+        Ok(query)
+    }
+
+    fn has_join(operator: &mut LogicalOperator) -> bool {
+        matches!(operator, LogicalOperator::Join(_)) || operator.children_mut().any(has_join)
+    }
+
+    #[test]
+    fn test_eliminate_unique_left_join() -> Result<(), PlannerError> {
+        let mut query = eliminate(
+            "SELECT a.x FROM (SELECT 1 as x) a \
+             LEFT JOIN (SELECT y, count() as c FROM (SELECT 1 as y) t GROUP BY y) b ON a.x = b.y",
+        )?;
+        assert!(!has_join(&mut query));
+        Ok(())
+    }
+
+    #[test]
+    fn test_eliminate_left_join_on_primary_key() -> Result<(), PlannerError> {
+        let planner = Planner::new_for_test();
+        {
+            let mut catalog = planner.catalog.write().unwrap();
+            let columns = vec![
+                ("id".to_string(), DataType::Integer),
+                ("name".to_string(), DataType::Text),
+            ];
+            let options = TableOptions {
+                primary_key: Some(vec![(0, SortOrder::Asc)]),
+                ..TableOptions::default()
+            };
+            catalog.create_table_with_options("default", "customers", &columns, &options)?;
+            catalog.create_table("default", "contacts", &columns)?;
+        }
+
+        let mut query = eliminate_with_planner(
+            &planner,
+            "SELECT a.x FROM (SELECT 1 as x) a LEFT JOIN customers c ON a.x = c.id",
+        )?;
+        assert!(!has_join(&mut query));
+
+        // Without a primary key the same row can be in the table more than once
+        let mut query = eliminate_with_planner(
+            &planner,
+            "SELECT a.x FROM (SELECT 1 as x) a LEFT JOIN contacts c ON a.x = c.id",
+        )?;
+        assert!(has_join(&mut query));
+
+        // Not joined on the key
+        let mut query = eliminate_with_planner(
+            &planner,
+            "SELECT a.x FROM (SELECT 'a' as x) a LEFT JOIN customers c ON a.x = c.name",
+        )?;
+        assert!(has_join(&mut query));
+        Ok(())
+    }
+
+    #[test]
+    fn test_keep_used_or_non_unique_joins() -> Result<(), PlannerError> {
+        // Right side columns are used
+        let mut query = eliminate(
+            "SELECT a.x, b.c FROM (SELECT 1 as x) a \
+             LEFT JOIN (SELECT y, count() as c FROM (SELECT 1 as y) t GROUP BY y) b ON a.x = b.y",
+        )?;
+        assert!(has_join(&mut query));
+
+        // Right side might match many rows
+        let mut query = eliminate(
+            "SELECT a.x FROM (SELECT 1 as x) a \
+             LEFT JOIN (SELECT 1 as y UNION ALL SELECT 1 as y) b ON a.x = b.y",
+        )?;
+        assert!(has_join(&mut query));
+
+        // Inner joins can filter rows
+        let mut query = eliminate(
+            "SELECT a.x FROM (SELECT 1 as x) a \
+             JOIN (SELECT y, count() as c FROM (SELECT 1 as y) t GROUP BY y) b ON a.x = b.y",
+        )?;
+        assert!(has_join(&mut query));
+        Ok(())
+    }
+
+    #[test]
+    fn test_eliminate_join_left_of_another_join() -> Result<(), PlannerError> {
+        // The columns of c move down once b's are removed from under the outer join
+        let mut query = eliminate(
+            "SELECT a.x, c.z FROM (SELECT 1 as x) a \
+             LEFT JOIN (SELECT y, count() as c FROM (SELECT 1 as y) t GROUP BY y) b ON a.x = b.y \
+             JOIN (SELECT 1 as z) c ON c.z = a.x",
+        )?;
+        fn outer_join(operator: &mut LogicalOperator) -> Option<&mut Join> {
+            if let LogicalOperator::Join(join) = operator {
+                Some(join)
+            } else {
+                operator.children_mut().find_map(outer_join)
+            }
+        }
+        let join = outer_join(&mut query).unwrap();
+        assert!(!has_join(&mut join.left));
+        assert_eq!(
+            min_max_column_deps_for_expression(&mut join.on),
+            Some((0, 1))
+        );
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 use ast::expr::Expression;
 use ast::rel::logical::LogicalOperator;
 use data::Session;
+use functions::{check_cast, Volatility};
 
 /// Simplifies expressions involving only constants
 pub(super) fn fold_constants(query: &mut LogicalOperator, session: &Session) {
@@ -22,10 +23,11 @@ fn fold_constants_for_expr(expr: &mut Expression, session: &Session) {
 
             // Rust fmt doesn't seem to agree with clippy lol
             #[allow(clippy::blocks_in_if_conditions)]
-            if function_call
-                .args
-                .iter()
-                .all(|expr| matches!(expr, Expression::Constant(..)))
+            if function_call.function.volatility() != Volatility::Volatile
+                && function_call
+                    .args
+                    .iter()
+                    .all(|expr| matches!(expr, Expression::Constant(..)))
             {
                 let function_input: Vec<_> = function_call
                     .args
@@ -44,6 +46,12 @@ fn fold_constants_for_expr(expr: &mut Expression, session: &Session) {
                     .function
                     .execute(session, &function_call.signature, &function_input)
                     .into_static();
+                check_cast(
+                    session,
+                    &function_call.signature,
+                    &function_input,
+                    &constant,
+                );
 
                 *expr = Expression::Constant(constant, function_call.signature.ret);
             }
@@ -54,19 +62,25 @@ fn fold_constants_for_expr(expr: &mut Expression, session: &Session) {
                 fold_constants_for_expr(arg, session);
             }
         }
+        Expression::WindowFunction(_) => {
+            for child in expr.children_mut() {
+                fold_constants_for_expr(child, session);
+            }
+        }
         Expression::CompiledColumnReference(_column_reference) => {
             // TODO once we have the source expr's bit done we can come back here and optimize folding up constants from a subquery
         }
 
-        // Already a constant
-        Expression::Constant(..) => {}
+        // Already a constant, or at least will be once bound
+        Expression::Constant(..) | Expression::Parameter(_) => {}
         // These should be gone by now.
-        Expression::Cast(_) | Expression::FunctionCall(_) | Expression::ColumnReference(_) => {
-            panic!(
-                "Hit {:?} in constant fold, this should be gone by now!",
-                expr
-            )
-        }
+        Expression::Cast(_)
+        | Expression::FunctionCall(_)
+        | Expression::ColumnReference(_)
+        | Expression::Subquery(_) => panic!(
+            "Hit {:?} in constant fold, this should be gone by now!",
+            expr
+        ),
     }
 }
 
@@ -102,11 +116,9 @@ mod tests {
                         Expression::CompiledFunctionCall(CompiledFunctionCall {
                             function: add_function.as_scalar(),
                             args: Box::from(vec![Expression::from(2), Expression::from(3)]),
-                            expr_buffer: Box::from(vec![]),
                             signature: Box::new(add_signature.clone()),
                         }),
                     ]),
-                    expr_buffer: Box::from(vec![]),
                     signature: Box::new(add_signature.clone()),
                 }),
             }],
@@ -126,4 +138,36 @@ mod tests {
 
         assert_eq!(operator, expected);
     }
+
+    #[test]
+    fn test_volatile_functions_not_folded() {
+        let session = Session::new(1);
+        let function_registry = Registry::default();
+        let random_signature = FunctionSignature {
+            name: "random",
+            args: vec![],
+            ret: DataType::Null,
+        };
+        let (random_signature, random_function) = function_registry
+            .resolve_function(&random_signature)
+            .unwrap();
+
+        let mut operator = LogicalOperator::Project(Project {
+            distinct: false,
+            expressions: vec![NamedExpression {
+                alias: None,
+                expression: Expression::CompiledFunctionCall(CompiledFunctionCall {
+                    function: random_function.as_scalar(),
+                    args: Box::from(vec![]),
+                    signature: Box::new(random_signature),
+                }),
+            }],
+            source: Box::new(LogicalOperator::Single),
+        });
+        let expected = operator.clone();
+
+        fold_constants(&mut operator, &session);
+
+        assert_eq!(operator, expected);
+    }
 }
@@ -0,0 +1,303 @@
+use crate::p1_validation::compile_functions_and_refs::compile_expression;
+use crate::utils::expr::{combine_predicates, decompose_predicate};
+use crate::utils::logical::fields_for_operator;
+use crate::PlannerError;
+use ast::expr::{CompiledColumnReference, CompiledFunctionCall, Expression, FunctionCall};
+use ast::rel::logical::{Filter, Join, JoinType, LogicalOperator, Values};
+use data::Datum;
+use functions::registry::Registry;
+
+/// The number of terms after which it's cheaper to hash the list than to compare
+/// against each term for every row.
+const IN_LIST_JOIN_THRESHOLD: usize = 16;
+
+/// Rewrites long chains of ORs comparing the same expression against constants (which is
+/// what an IN list is parsed as) into a semi join against an inline values relation.
+/// ie
+/// SELECT * FROM a WHERE a.id IN (1, 2, 3, ...)
+/// becomes
+/// SELECT * FROM a LEFT SEMI JOIN (VALUES (1), (2), (3), ...) v ON a.id = v.value
+/// The NOT IN version becomes an anti join instead, with rows where a.id is null filtered out
+/// beforehand. The join can then be executed as a hash join rather than having to evaluate
+/// every term of the chain for each row.
+pub(super) fn rewrite_in_lists(
+    operator: &mut LogicalOperator,
+    function_registry: &Registry,
+) -> Result<(), PlannerError> {
+    for child in operator.children_mut() {
+        rewrite_in_lists(child, function_registry)?;
+    }
+
+    if let LogicalOperator::Filter(filter) = operator {
+        let mut source = std::mem::take(filter.source.as_mut());
+        let mut remaining = vec![];
+        let mut rewritten = false;
+
+        for predicate in decompose_predicate(std::mem::take(&mut filter.predicate)) {
+            match as_in_list(predicate) {
+                Ok(in_list) => {
+                    source = join_in_list(source, in_list, function_registry)?;
+                    rewritten = true;
+                }
+                Err(predicate) => remaining.push(predicate),
+            }
+        }
+
+        // No point keeping the filter around if the lists were all it had
+        if rewritten && remaining.is_empty() {
+            *operator = source;
+        } else {
+            filter.predicate = combine_predicates(remaining, function_registry);
+            *filter.source = source;
+        }
+    }
+    Ok(())
+}
+
+/// A predicate that can be turned into a join, the comparison is the first of the
+/// equality checks and is used as a template for the join condition.
+struct InList {
+    negated: bool,
+    comparison: CompiledFunctionCall,
+    constant_idx: usize,
+    values: Vec<Expression>,
+}
+
+/// Returns the in list if the predicate is a long enough chain of equality checks, otherwise
+/// hands the predicate back.
+fn as_in_list(predicate: Expression) -> Result<InList, Expression> {
+    let (negated, ors) = match &predicate {
+        Expression::CompiledFunctionCall(function) if function.signature.name == "not" => {
+            (true, &function.args[0])
+        }
+        ors => (false, ors),
+    };
+
+    let mut terms = vec![];
+    decompose_disjunction(ors, &mut terms);
+    if terms.len() < IN_LIST_JOIN_THRESHOLD {
+        return Err(predicate);
+    }
+
+    let mut template: Option<(&CompiledFunctionCall, usize)> = None;
+    let mut values = vec![];
+    for term in terms {
+        let (comparison, constant_idx) = match as_comparison(term) {
+            Some(comparison) => comparison,
+            None => return Err(predicate),
+        };
+        if let Some((first, first_idx)) = template {
+            let operand_idx = 1 - constant_idx;
+            if comparison.signature != first.signature
+                || comparison.args[operand_idx] != first.args[1 - first_idx]
+            {
+                return Err(predicate);
+            }
+        } else {
+            template = Some((comparison, constant_idx));
+        }
+        values.push(comparison.args[constant_idx].clone());
+    }
+
+    // A null in a NOT IN list means no row can pass, there's nothing to gain from a join.
+    if negated
+        && values
+            .iter()
+            .any(|value| matches!(value, Expression::Constant(Datum::Null, _)))
+    {
+        return Err(predicate);
+    }
+
+    let (comparison, constant_idx) = template.unwrap();
+    let comparison = comparison.clone();
+    Ok(InList {
+        negated,
+        comparison,
+        constant_idx,
+        values,
+    })
+}
+
+/// Splits the expression at all the "ors"
+fn decompose_disjunction<'a>(expression: &'a Expression, terms: &mut Vec<&'a Expression>) {
+    match expression {
+        Expression::CompiledFunctionCall(function) if function.signature.name == "or" => {
+            for arg in function.args.iter() {
+                decompose_disjunction(arg, terms);
+            }
+        }
+        expression => terms.push(expression),
+    }
+}
+
+/// If the expression is a `x = <constant>` (or the other way around) returns the function
+/// along with which argument is the constant.
+fn as_comparison(expression: &Expression) -> Option<(&CompiledFunctionCall, usize)> {
+    if let Expression::CompiledFunctionCall(function) = expression {
+        if function.signature.name == "=" {
+            let is_constant = |idx: usize| matches!(function.args[idx], Expression::Constant(..));
+            return match (is_constant(0), is_constant(1)) {
+                (false, true) => Some((function, 1)),
+                (true, false) => Some((function, 0)),
+                _ => None,
+            };
+        }
+    }
+    None
+}
+
+/// Joins the in list's values onto the left operator.
+fn join_in_list(
+    left: LogicalOperator,
+    in_list: InList,
+    function_registry: &Registry,
+) -> Result<LogicalOperator, PlannerError> {
+    let InList {
+        negated,
+        mut comparison,
+        constant_idx,
+        values,
+    } = in_list;
+    let datatype = comparison.signature.args[constant_idx];
+    let left_len = fields_for_operator(&left).count();
+
+    let left = if negated {
+        // The anti join would keep rows where the operand is null as they never match,
+        // while NOT IN has to exclude them.
+        let mut predicate = Expression::FunctionCall(FunctionCall {
+            function_name: "not".to_string(),
+            args: vec![Expression::FunctionCall(FunctionCall {
+                function_name: "isnull".to_string(),
+                args: vec![comparison.args[1 - constant_idx].clone()],
+            })],
+        });
+        compile_expression(&mut predicate, function_registry)?;
+        LogicalOperator::Filter(Filter {
+            predicate,
+            source: Box::new(left),
+            pinned: false,
+        })
+    } else {
+        left
+    };
+
+    comparison.args[constant_idx] = Expression::CompiledColumnReference(CompiledColumnReference {
+        offset: left_len,
+        datatype,
+    });
+    let condition = Expression::CompiledFunctionCall(comparison);
+
+    let right = LogicalOperator::Values(Values {
+        fields: vec![(datatype, "value".to_string())],
+        data: values.into_iter().map(|value| vec![value]).collect(),
+    });
+
+    Ok(LogicalOperator::Join(Join {
+        left: Box::new(left),
+        right: Box::new(right),
+        on: condition,
+        join_type: if negated {
+            JoinType::LeftAnti
+        } else {
+            JoinType::LeftSemi
+        },
+        build_side: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Planner, PlannerError};
+    use data::Session;
+
+    fn rewrite(sql: &str) -> Result<LogicalOperator, PlannerError> {
+        let planner = Planner::new_for_test();
+        let session = Session::new(1);
+        let query = if let ast::statement::Statement::Query(query) = parser::parse(sql).unwrap() {
+            query
+        } else {
+            panic!()
+        };
+        let mut query = planner.validate(query, &session)?;
+        rewrite_in_lists(&mut query, &planner.function_registry)?;
+        Ok(query)
+    }
+
+    fn find_join(operator: &mut LogicalOperator) -> Option<&mut Join> {
+        if let LogicalOperator::Join(join) = operator {
+            Some(join)
+        } else {
+            operator.children_mut().find_map(find_join)
+        }
+    }
+
+    fn in_list(count: usize) -> String {
+        (0..count)
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    #[test]
+    fn test_rewrite_in_list() -> Result<(), PlannerError> {
+        let mut query = rewrite(&format!(
+            "SELECT * FROM (SELECT 1 as a, 2 as b) t WHERE a IN ({}) AND b > 1",
+            in_list(IN_LIST_JOIN_THRESHOLD)
+        ))?;
+
+        if let LogicalOperator::Project(project) = &query {
+            assert!(
+                matches!(project.source.as_ref(), LogicalOperator::Filter(filter) if filter.predicate.to_string() == "`>`(<OFFSET 1>, 1)")
+            );
+        } else {
+            panic!()
+        }
+        let join = find_join(&mut query).unwrap();
+        assert_eq!(join.join_type, JoinType::LeftSemi);
+        assert_eq!(join.on.to_string(), "`=`(<OFFSET 0>, <OFFSET 2>)");
+        if let LogicalOperator::Values(values) = join.right.as_ref() {
+            assert_eq!(values.data.len(), IN_LIST_JOIN_THRESHOLD);
+        } else {
+            panic!()
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_rewrite_not_in_list() -> Result<(), PlannerError> {
+        let mut query = rewrite(&format!(
+            "SELECT * FROM (SELECT 1 as a) t WHERE a NOT IN ({})",
+            in_list(IN_LIST_JOIN_THRESHOLD)
+        ))?;
+        let join = find_join(&mut query).unwrap();
+        assert_eq!(join.join_type, JoinType::LeftAnti);
+        assert_eq!(join.on.to_string(), "`=`(<OFFSET 0>, <OFFSET 1>)");
+        assert!(
+            matches!(join.left.as_ref(), LogicalOperator::Filter(filter) if filter.predicate.to_string() == "not(isnull(<OFFSET 0>))")
+        );
+
+        // A null in the list excludes every row, so it's left as it is
+        let mut query = rewrite(&format!(
+            "SELECT * FROM (SELECT 1 as a) t WHERE a NOT IN ({}, null)",
+            in_list(IN_LIST_JOIN_THRESHOLD)
+        ))?;
+        assert!(find_join(&mut query).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_leave_short_or_mixed_lists() -> Result<(), PlannerError> {
+        // Short lists are cheap enough to evaluate inline
+        let mut query = rewrite("SELECT * FROM (SELECT 1 as a) t WHERE a IN (1, 2, 3)")?;
+        assert!(find_join(&mut query).is_none());
+
+        // Terms comparing different expressions
+        let mut query = rewrite(&format!(
+            "SELECT * FROM (SELECT 1 as a, 2 as b) t WHERE a IN ({}) OR b = 1",
+            in_list(IN_LIST_JOIN_THRESHOLD)
+        ))?;
+        assert!(find_join(&mut query).is_none());
+        Ok(())
+    }
+}
@@ -3,21 +3,88 @@ use ast::rel::logical::LogicalOperator;
 use data::Session;
 
 pub(crate) mod collapse_projects;
+mod decorrelate_subqueries;
+mod eliminate_joins;
 mod fold_constants;
+mod in_list_joins;
+mod order_joins;
 mod predicate_pushdown;
+mod prune_file_columns;
+mod right_joins;
+mod select_indexes;
+
+/// The rules that can be switched off with SET optimizer_rules, handy for narrowing down which
+/// rule is to blame for a wrong result or a slow plan. Subqueries always have to be decorrelated
+/// for the query to be planned at all so that's not one of them.
+pub const OPTIMIZER_RULES: &[&str] = &[
+    "fold_constants",
+    "predicate_pushdown",
+    "select_indexes",
+    "in_list_joins",
+    "collapse_projects",
+    "eliminate_joins",
+    "prune_file_columns",
+    "order_joins",
+];
 
 impl Planner {
     /// Optimizes the query by rewriting parts of it to be more efficient.
     pub fn optimize(
+        &self,
+        query: LogicalOperator,
+        session: &Session,
+    ) -> Result<LogicalOperator, PlannerError> {
+        let disabled_rules = session.disabled_optimizer_rules.read().unwrap().clone();
+        self.optimize_with_rules(query, session, &|rule| !disabled_rules.contains(rule))
+    }
+
+    /// Optimizes the query only running the optional rules that enabled returns true for
+    pub(crate) fn optimize_with_rules(
         &self,
         mut query: LogicalOperator,
         session: &Session,
+        enabled: &dyn Fn(&str) -> bool,
     ) -> Result<LogicalOperator, PlannerError> {
-        fold_constants::fold_constants(&mut query, session);
-        predicate_pushdown::predicate_pushdown(&mut query, &self.function_registry);
+        // Subqueries have to be turned into joins before anything else can make sense of them
+        decorrelate_subqueries::decorrelate_subqueries(&mut query, &self.function_registry)?;
+        // Likewise right joins are only ever run as left joins
+        right_joins::rewrite_right_joins(&mut query);
+        if enabled("fold_constants") {
+            fold_constants::fold_constants(&mut query, session);
+        }
+        if enabled("predicate_pushdown") {
+            predicate_pushdown::predicate_pushdown(&mut query, &self.function_registry);
+        }
         // After pushing down the predicates it can open up some more options for constant folding
-        fold_constants::fold_constants(&mut query, session);
-        collapse_projects::collapse_projects(&mut query);
+        if enabled("fold_constants") {
+            fold_constants::fold_constants(&mut query, session);
+        }
+        // Also needs the predicates sitting right on top of the tables they filter
+        if enabled("select_indexes") {
+            select_indexes::select_indexes(&mut query);
+        }
+        // Done once the predicates are pushed down and folded so the lists sit right on top
+        // of what they're filtering and are made up of constants.
+        if enabled("in_list_joins") {
+            in_list_joins::rewrite_in_lists(&mut query, &self.function_registry)?;
+        }
+        if enabled("collapse_projects") {
+            collapse_projects::collapse_projects(&mut query);
+        }
+        // Removing joins renumbers the column references above the removed columns itself
+        // so it doesn't need any other cleanup after.
+        if enabled("eliminate_joins") {
+            eliminate_joins::eliminate_joins(&mut query);
+        }
+        // Only needs to know which columns are used, by now the projects are collapsed into
+        // the ones that actually use them.
+        if enabled("prune_file_columns") {
+            prune_file_columns::prune_file_columns(&mut query);
+        }
+        // With the joins that are left settled we can pick which way around to run them
+        if enabled("order_joins") {
+            order_joins::order_joins(&mut query, &self.catalog.read().unwrap());
+        }
         Ok(query)
     }
 }
@@ -0,0 +1,147 @@
+use crate::utils::logical::{create_wrapping_project, fieldnames_for_operator};
+use crate::utils::statistics::estimate_rows;
+use ast::expr::Expression;
+use ast::rel::logical::{JoinSide, JoinType, LogicalOperator};
+use catalog::Catalog;
+
+/// Puts the smaller input of inner joins on the right, the hash join reads its right input
+/// into a hash table before streaming the left input past it. The sizes are estimated from
+/// the stats gathered by ANALYZE, joins over anything that hasn't been analyzed are left in
+/// the order they were written. Joins with a HASH_JOIN hint are put whichever way around the
+/// hint says regardless of the stats.
+pub(super) fn order_joins(query: &mut LogicalOperator, catalog: &Catalog) {
+    for child in query.children_mut() {
+        order_joins(child, catalog);
+    }
+
+    if let LogicalOperator::Join(join) = query {
+        if join.join_type != JoinType::Inner {
+            return;
+        }
+        match (join.build_side, &join.left, &join.right) {
+            (Some(JoinSide::Left), _, _) => {}
+            (Some(JoinSide::Right), _, _) => return,
+            (None, left, right) => {
+                match (estimate_rows(left, catalog), estimate_rows(right, catalog)) {
+                    (Some(left), Some(right)) if right > left => {}
+                    _ => return,
+                }
+            }
+        }
+
+        let left_len = fieldnames_for_operator(&join.left).count();
+        let right_len = fieldnames_for_operator(&join.right).count();
+        std::mem::swap(&mut join.left, &mut join.right);
+        join.build_side = join.build_side.map(|_| JoinSide::Right);
+        swap_column_references(&mut join.on, left_len, right_len);
+
+        // Put the columns back in the order the operators above expect
+        let mut project = create_wrapping_project(std::mem::take(query));
+        project.expressions.rotate_left(right_len);
+        *query = LogicalOperator::Project(project);
+    }
+}
+
+/// Points references to the join's columns at where they end up once its inputs are swapped
+fn swap_column_references(expression: &mut Expression, left_len: usize, right_len: usize) {
+    if let Expression::CompiledColumnReference(column_reference) = expression {
+        if column_reference.offset < left_len {
+            column_reference.offset += right_len;
+        } else {
+            column_reference.offset -= left_len;
+        }
+    }
+    for expr in expression.children_mut() {
+        swap_column_references(expr, left_len, right_len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Planner, PlannerError};
+    use catalog::{ColumnStats, TableOrView};
+    use data::{DataType, Datum, Session};
+
+    /// Creates a table with stats saying it has the given number of rows
+    fn analyzed_table(planner: &Planner, name: &str, rows: i64) -> Result<(), PlannerError> {
+        let mut catalog = planner.catalog.write().unwrap();
+        let columns = vec![("id".to_string(), DataType::Integer)];
+        catalog.create_table("default", name, &columns)?;
+        let table_id = match catalog.item("default", name)?.item {
+            TableOrView::Table(table) => table.id(),
+            TableOrView::View(_) => panic!(),
+        };
+        let stats = ColumnStats::from_sorted_values(DataType::Integer, &[(Datum::from(1), rows)]);
+        catalog.set_column_stats("default", name, table_id, &[("id".to_string(), stats)])?;
+        Ok(())
+    }
+
+    fn order(planner: &Planner, sql: &str) -> Result<LogicalOperator, PlannerError> {
+        let session = Session::new(1);
+        let query = if let ast::statement::Statement::Query(query) = parser::parse(sql).unwrap() {
+            query
+        } else {
+            panic!()
+        };
+        let mut query = planner.validate(query, &session)?;
+        order_joins(&mut query, &planner.catalog.read().unwrap());
+        Ok(query)
+    }
+
+    /// The estimated rows of the first join's left and right inputs
+    fn join_inputs(planner: &Planner, operator: &mut LogicalOperator) -> (f64, f64) {
+        if let LogicalOperator::Join(join) = operator {
+            let catalog = planner.catalog.read().unwrap();
+            return (
+                estimate_rows(&join.left, &catalog).unwrap(),
+                estimate_rows(&join.right, &catalog).unwrap(),
+            );
+        }
+        operator
+            .children_mut()
+            .map(|child| join_inputs(planner, child))
+            .next()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_order_joins() -> Result<(), PlannerError> {
+        let planner = Planner::new_for_test();
+        analyzed_table(&planner, "small", 10)?;
+        analyzed_table(&planner, "big", 1000)?;
+
+        let mut query = order(
+            &planner,
+            "SELECT s.id, b.id FROM small s JOIN big b ON s.id = b.id",
+        )?;
+        assert_eq!(join_inputs(&planner, &mut query), (1000.0, 10.0));
+
+        let mut query = order(
+            &planner,
+            "SELECT s.id, b.id FROM big b JOIN small s ON s.id = b.id",
+        )?;
+        assert_eq!(join_inputs(&planner, &mut query), (1000.0, 10.0));
+
+        // Left joins have to stay the way around they are
+        let mut query = order(
+            &planner,
+            "SELECT s.id, b.id FROM small s LEFT JOIN big b ON s.id = b.id",
+        )?;
+        assert_eq!(join_inputs(&planner, &mut query), (10.0, 1000.0));
+
+        // Hints win out over the stats
+        let mut query = order(
+            &planner,
+            "SELECT /*+ HASH_JOIN(s b) */ s.id, b.id FROM small s JOIN big b ON s.id = b.id",
+        )?;
+        assert_eq!(join_inputs(&planner, &mut query), (10.0, 1000.0));
+
+        let mut query = order(
+            &planner,
+            "SELECT /*+ HASH_JOIN(b s) */ s.id, b.id FROM small s JOIN big b ON s.id = b.id",
+        )?;
+        assert_eq!(join_inputs(&planner, &mut query), (1000.0, 10.0));
+        Ok(())
+    }
+}
@@ -28,6 +28,15 @@ fn pushdown_predicates_from_above(
     // other wise we wrap ourselves in a filter operator with those predicates.
     // As we push all the predicates out of a filter, filters should actually be removed.
     match operator {
+        // NO_PUSHDOWN, anything coming from above stops here too
+        LogicalOperator::Filter(filter) if filter.pinned => {
+            if !predicates.is_empty() {
+                predicates.push(std::mem::take(&mut filter.predicate));
+                filter.predicate = combine_predicates(predicates, function_registry);
+            }
+            pushdown_predicates_from_above(filter.source.as_mut(), vec![], function_registry);
+        }
+
         LogicalOperator::Filter(filter) => {
             let predicate = std::mem::take(&mut filter.predicate);
             let predicates = decompose_predicate(predicate).collect();
@@ -111,7 +120,9 @@ fn pushdown_predicates_from_above(
                         _ => keep.push(predicate),
                     }
                 }
-            } else if join.join_type == JoinType::LeftOuter {
+            } else if join.join_type == JoinType::LeftOuter
+                || join.join_type == JoinType::LeftSingle
+            {
                 for mut predicate in predicates {
                     match min_max_column_deps_for_expression(&mut predicate) {
                         None => {
@@ -137,6 +148,27 @@ fn pushdown_predicates_from_above(
                         _ => keep.push(condition),
                     }
                 }
+            } else if join.join_type == JoinType::LeftSemi || join.join_type == JoinType::LeftAnti {
+                // Semi and anti joins only output the left side so anything from above
+                // can be pushed down the left side.
+                left.extend(predicates);
+
+                for mut condition in join_predicates {
+                    match min_max_column_deps_for_expression(&mut condition) {
+                        // Constants and right only conditions just filter what we're matching
+                        // against
+                        None => right.push(condition),
+                        Some((min, _max)) if min >= left_len => right.push(condition),
+                        // For semi joins a left only condition is just a filter on the left
+                        // side, for anti joins its the opposite.
+                        Some((_min, max))
+                            if max < left_len && join.join_type == JoinType::LeftSemi =>
+                        {
+                            left.push(condition)
+                        }
+                        _ => keep.push(condition),
+                    }
+                }
             } else {
                 // Default implementation to play it safe for newly added join types
                 keep.extend(join_predicates);
@@ -160,6 +192,7 @@ fn pushdown_predicates_from_above(
                 *operator = LogicalOperator::Filter(Filter {
                     predicate: combine_predicates(wrap, function_registry),
                     source: Box::new(source),
+                    pinned: false,
                 });
             }
         }
@@ -176,6 +209,7 @@ fn pushdown_predicates_from_above(
                 *operator = LogicalOperator::Filter(Filter {
                     predicate: combine_predicates(predicates, function_registry),
                     source: Box::new(source),
+                    pinned: false,
                 });
             }
 
@@ -0,0 +1,74 @@
+use crate::utils::expr::type_for_expression;
+use crate::utils::logical::fieldnames_for_operator;
+use crate::utils::usage::walk_with_usage;
+use ast::expr::Expression;
+use ast::rel::logical::LogicalOperator;
+use data::Datum;
+
+/// Swaps the columns of file scans that nothing above uses for nulls. The scan then never
+/// pulls them out of the documents and for parquet files never reads them at all.
+pub(super) fn prune_file_columns(query: &mut LogicalOperator) {
+    let used = vec![true; fieldnames_for_operator(query).count()];
+    walk_with_usage(query, used, &mut prune_file_scan);
+}
+
+fn prune_file_scan(operator: &mut LogicalOperator, used: &mut Vec<bool>) -> Vec<(usize, usize)> {
+    if let LogicalOperator::FileScan(file_scan) = operator {
+        for (column, used) in file_scan.columns.iter_mut().zip(used.iter()) {
+            if !used {
+                let datatype = type_for_expression(&column.expression);
+                column.expression = Expression::Constant(Datum::Null, datatype);
+            }
+        }
+    }
+    vec![]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p2_optimization::collapse_projects::collapse_projects;
+    use crate::{Planner, PlannerError};
+    use data::{DataType, Session};
+
+    fn file_scan(operator: &mut LogicalOperator) -> Option<&mut LogicalOperator> {
+        if matches!(operator, LogicalOperator::FileScan(_)) {
+            Some(operator)
+        } else {
+            operator.children_mut().find_map(file_scan)
+        }
+    }
+
+    #[test]
+    fn test_prune_file_columns() -> Result<(), PlannerError> {
+        let planner = Planner::new_for_test();
+        let session = Session::new(1);
+        let query = if let ast::statement::Statement::Query(query) = parser::parse(
+            "SELECT a FROM DIRECTORY 'data' WITH (format = 'parquet') \
+             COLUMNS (a INT PATH '$.a', b TEXT PATH '$.b', c INT PATH '$.c') WHERE c > 1",
+        )
+        .unwrap()
+        {
+            query
+        } else {
+            panic!()
+        };
+        let mut query = planner.validate(query, &session)?;
+        collapse_projects(&mut query);
+        prune_file_columns(&mut query);
+
+        if let Some(LogicalOperator::FileScan(file_scan)) = file_scan(&mut query) {
+            let pruned: Vec<_> = file_scan
+                .columns
+                .iter()
+                .map(|column| {
+                    column.expression == Expression::Constant(Datum::Null, DataType::Text)
+                })
+                .collect();
+            assert_eq!(pruned, vec![false, true, false]);
+        } else {
+            panic!("Expected a file scan")
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+use crate::utils::logical::{create_wrapping_project, fieldnames_for_operator};
+use ast::expr::Expression;
+use ast::rel::logical::{JoinSide, JoinType, LogicalOperator};
+
+/// Rewrites right outer joins as left outer joins with their inputs swapped, the rest of the
+/// planner and the executors then only ever have to deal with the left flavour. A project is
+/// wrapped around the join to put the columns back in the order the query expects.
+pub(super) fn rewrite_right_joins(query: &mut LogicalOperator) {
+    for child in query.children_mut() {
+        rewrite_right_joins(child);
+    }
+
+    if let LogicalOperator::Join(join) = query {
+        if join.join_type != JoinType::RightOuter {
+            return;
+        }
+        let left_len = fieldnames_for_operator(&join.left).count();
+        let right_len = fieldnames_for_operator(&join.right).count();
+        std::mem::swap(&mut join.left, &mut join.right);
+        swap_column_references(&mut join.on, left_len, right_len);
+        join.join_type = JoinType::LeftOuter;
+        join.build_side = join.build_side.map(|side| match side {
+            JoinSide::Left => JoinSide::Right,
+            JoinSide::Right => JoinSide::Left,
+        });
+
+        // The old left side's columns now come after the old right side's
+        let mut project = create_wrapping_project(std::mem::take(query));
+        project.expressions.rotate_left(right_len);
+        *query = LogicalOperator::Project(project);
+    }
+}
+
+/// Moves the references to the left side's columns after the right side's and the references
+/// to the right side's columns to the front.
+fn swap_column_references(expression: &mut Expression, left_len: usize, right_len: usize) {
+    if let Expression::CompiledColumnReference(column_ref) = expression {
+        column_ref.offset = if column_ref.offset < left_len {
+            column_ref.offset + right_len
+        } else {
+            column_ref.offset - left_len
+        };
+    }
+    for expr in expression.children_mut() {
+        swap_column_references(expr, left_len, right_len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::logical::fields_for_operator;
+    use crate::{Planner, PlannerError};
+    use ast::expr::CompiledColumnReference;
+    use ast::rel::logical::Project;
+    use data::Session;
+
+    fn rewrite(sql: &str) -> Result<LogicalOperator, PlannerError> {
+        let planner = Planner::new_for_test();
+        let session = Session::new(1);
+        let query = if let ast::statement::Statement::Query(query) = parser::parse(sql).unwrap() {
+            query
+        } else {
+            panic!()
+        };
+        let mut query = planner.validate(query, &session)?;
+        rewrite_right_joins(&mut query);
+        Ok(query)
+    }
+
+    fn offsets(project: &Project) -> Vec<usize> {
+        project
+            .expressions
+            .iter()
+            .map(|ne| match &ne.expression {
+                Expression::CompiledColumnReference(CompiledColumnReference { offset, .. }) => {
+                    *offset
+                }
+                _ => panic!(),
+            })
+            .collect()
+    }
+
+    fn column_references(expression: &Expression, offsets: &mut Vec<usize>) {
+        if let Expression::CompiledColumnReference(column_ref) = expression {
+            offsets.push(column_ref.offset);
+        }
+        for expr in expression.children() {
+            column_references(expr, offsets);
+        }
+    }
+
+    #[test]
+    fn test_rewrite_right_join() -> Result<(), PlannerError> {
+        let query = rewrite(
+            "SELECT * FROM (SELECT 1 as a, 2 as b) l \
+             RIGHT JOIN (SELECT 3 as c) r ON l.a = r.c AND l.b > 1",
+        )?;
+        let fields: Vec<_> = fields_for_operator(&query).map(|f| f.alias).collect();
+        assert_eq!(fields, vec!["a", "b", "c"]);
+
+        // SELECT * -> the wrapping project -> the join
+        let project = if let LogicalOperator::Project(project) = query {
+            project
+        } else {
+            panic!()
+        };
+        let wrapping = if let LogicalOperator::Project(wrapping) = *project.source {
+            wrapping
+        } else {
+            panic!()
+        };
+        assert_eq!(offsets(&wrapping), vec![1, 2, 0]);
+        let join = if let LogicalOperator::Join(join) = *wrapping.source {
+            join
+        } else {
+            panic!()
+        };
+        assert_eq!(join.join_type, JoinType::LeftOuter);
+        assert_eq!(fieldnames_for_operator(&join.left).count(), 1);
+        // l.a = r.c AND l.b > 1 now reads r.c's column 0 and l's columns 1 and 2
+        let mut referenced = vec![];
+        column_references(&join.on, &mut referenced);
+        referenced.sort_unstable();
+        assert_eq!(referenced, vec![0, 1, 2]);
+        Ok(())
+    }
+}
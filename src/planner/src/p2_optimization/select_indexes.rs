@@ -0,0 +1,116 @@
+use crate::utils::ranges::ranges_for_column;
+use ast::expr::Expression;
+use ast::rel::logical::{LogicalOperator, ResolvedTable};
+
+/// Swaps tables being filtered for one of their indexes when the predicate doesn't narrow down
+/// the table's own key but does narrow down the index's, the scan ranges are then worked out
+/// from the index's key. Indexes hold the same tuples as their table so nothing above the scan
+/// can tell the difference.
+pub(super) fn select_indexes(query: &mut LogicalOperator) {
+    for child in query.children_mut() {
+        select_indexes(child);
+    }
+
+    if let LogicalOperator::Filter(filter) = query {
+        if let Some(resolved) = resolved_table(&mut filter.source) {
+            select_index(&filter.predicate, resolved);
+        }
+    }
+}
+
+/// Returns the table under any aliases
+fn resolved_table(operator: &mut LogicalOperator) -> Option<&mut ResolvedTable> {
+    match operator {
+        LogicalOperator::ResolvedTable(resolved) => Some(resolved),
+        LogicalOperator::TableAlias(table_alias) => resolved_table(&mut table_alias.source),
+        _ => None,
+    }
+}
+
+fn select_index(predicate: &Expression, resolved: &mut ResolvedTable) {
+    // Only the first column of a key can narrow down a scan on its own
+    let restricted = |key: &[usize]| {
+        key.first().map_or(false, |column| {
+            !ranges_for_column(predicate, *column).is_all()
+        })
+    };
+    if resolved.table.null_bitmap() || restricted(resolved.table.key_columns()) {
+        return;
+    }
+    let index = resolved
+        .table
+        .indexes()
+        .iter()
+        .find(|index| restricted(index.key_columns()))
+        .cloned();
+    if let Some(index) = index {
+        resolved.table = index;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Planner;
+    use data::{DataType, Session, SortOrder};
+    use storage::{Storage, Table};
+
+    /// Returns the id of the table scanned for a two column table with an index on b
+    fn scanned(predicate: &str) -> u32 {
+        let storage = Storage::new_in_mem().unwrap();
+        let index = storage
+            .table(4, 2, vec![SortOrder::Asc; 2])
+            .with_primary_key(&[1, 0]);
+        let table = storage
+            .table(2, 2, vec![SortOrder::Asc; 2])
+            .with_indexes(vec![index]);
+        let planner = Planner::new_for_test();
+        let session = Session::new(1);
+        let sql = format!(
+            "SELECT * FROM (SELECT 1 as a, 2 as b) t WHERE {}",
+            predicate
+        );
+        let query = if let ast::statement::Statement::Query(query) = parser::parse(&sql).unwrap() {
+            query
+        } else {
+            panic!()
+        };
+        let query = planner.validate(query, &session).unwrap();
+        if let LogicalOperator::Project(project) = query {
+            if let LogicalOperator::Filter(mut filter) = *project.source {
+                *filter.source = resolved(table);
+                let mut query = LogicalOperator::Filter(filter);
+                select_indexes(&mut query);
+                if let LogicalOperator::Filter(filter) = query {
+                    if let LogicalOperator::ResolvedTable(resolved) = *filter.source {
+                        return resolved.table.id();
+                    }
+                }
+            }
+        }
+        panic!()
+    }
+
+    fn resolved(table: Table) -> LogicalOperator {
+        LogicalOperator::ResolvedTable(ResolvedTable {
+            columns: vec![
+                ("a".to_string(), DataType::Integer),
+                ("b".to_string(), DataType::Integer),
+            ],
+            table,
+            sample: None,
+            as_of: None,
+        })
+    }
+
+    #[test]
+    fn test_select_indexes() {
+        assert_eq!(scanned("b = 2"), 4);
+        assert_eq!(scanned("b > 2 AND a != b"), 4);
+        // The table's own key is just as good
+        assert_eq!(scanned("a = 1 AND b = 2"), 2);
+        assert_eq!(scanned("a = 1"), 2);
+        // Nothing to narrow the scan down with
+        assert_eq!(scanned("a = b"), 2);
+    }
+}
@@ -106,6 +106,12 @@ pub(crate) fn normalize_joins(
             );
         }
 
+        // The key columns are inserted at the front of both sides so the remaining
+        // conditions need their column references moving along to match.
+        for expr in non_equi_conditions.iter_mut() {
+            move_join_column_references(expr, left_len, equi_len);
+        }
+
         // Swap in the upstream projects.
         join.left = Box::from(LogicalOperator::Project(left_project));
         join.right = Box::from(LogicalOperator::Project(right_project));
@@ -131,10 +137,29 @@ pub(crate) fn normalize_joins(
         }
 
         // we now want to remove equi_join count columns at the start of the right side
-        for _ in 0..equi_len {
-            wrapping_project.expressions.remove(left_len);
+        if let LogicalOperator::Join(join) = wrapping_project.source.as_ref() {
+            if join.join_type.outputs_right() {
+                for _ in 0..equi_len {
+                    wrapping_project.expressions.remove(left_len);
+                }
+            }
         }
 
         *query = LogicalOperator::Project(wrapping_project)
     }
 }
+
+/// Moves the column references past the key columns inserted on each side of a join,
+/// references to the left side move by the number of keys and the right side by twice that.
+fn move_join_column_references(expression: &mut Expression, left_len: usize, equi_len: usize) {
+    if let Expression::CompiledColumnReference(column_ref) = expression {
+        column_ref.offset += if column_ref.offset < left_len {
+            equi_len
+        } else {
+            2 * equi_len
+        };
+    }
+    for expr in expression.children_mut() {
+        move_join_column_references(expr, left_len, equi_len);
+    }
+}
@@ -0,0 +1,146 @@
+use crate::p4_pit_planning::{build_operator, is_repeatable_expression, volatility};
+use crate::utils::logical::fields_for_operator;
+use crate::{Field, Planner, PlannerError, PointInTimePlan};
+use ast::rel::logical::LogicalOperator;
+use ast::rel::point_in_time::{PointInTimeOperator, TableChanges};
+use data::{LogicalTimestamp, Session};
+use functions::Volatility;
+
+mod partial_aggregate;
+pub use partial_aggregate::PartialAggregatePlan;
+
+impl Planner {
+    /// Plans a query that returns the change to the query's rows between from and to rather than
+    /// the rows themselves. This only works for linear queries, those made up of filters,
+    /// projects and union alls over tables, as for them the change to the rows is just the query
+    /// run over the changes to the tables. None is returned for any other query.
+    pub fn plan_for_delta(
+        &self,
+        query: LogicalOperator,
+        session: &Session,
+        from: LogicalTimestamp,
+        to: LogicalTimestamp,
+    ) -> Result<Option<PointInTimePlan>, PlannerError> {
+        let (fields, mut operator) = match self.plan_immutable(query, session)? {
+            Some(planned) => planned,
+            None => return Ok(None),
+        };
+        if !is_linear(&mut operator) {
+            return Ok(None);
+        }
+        let mut operator = build_operator(operator, &self.function_registry);
+        scan_changes(&mut operator, from, to);
+        Ok(Some(PointInTimePlan { fields, operator }))
+    }
+
+    /// Like plan_common but returns None if the query calls anything other than immutable
+    /// functions, the others, ie now(), could give different answers for the same row over time
+    /// so views calling them are recomputed as of each refresh instead. It's checked before the
+    /// query's optimized as stable functions get folded into constants.
+    fn plan_immutable(
+        &self,
+        query: LogicalOperator,
+        session: &Session,
+    ) -> Result<Option<(Vec<Field>, LogicalOperator)>, PlannerError> {
+        let mut query = self.validate(query, session)?;
+        if !is_immutable(&mut query) {
+            return Ok(None);
+        }
+        let query = self.optimize(query, session)?;
+        let query = self.common_transforms(query, session)?;
+        let fields = fields_for_operator(&query).collect();
+        Ok(Some((fields, query)))
+    }
+}
+
+/// Whether the query only calls immutable functions
+fn is_immutable(query: &mut LogicalOperator) -> bool {
+    query
+        .expressions_mut()
+        .all(|expr| volatility(expr) == Volatility::Immutable)
+        && query.children_mut().all(is_immutable)
+}
+
+/// Whether each change to the query's tables maps onto its own change to the query's rows,
+/// volatile functions don't count as they'd give different answers for the same row over time.
+fn is_linear(query: &mut LogicalOperator) -> bool {
+    let linear = match query {
+        LogicalOperator::Project(project) => !project.distinct,
+        LogicalOperator::ResolvedTable(table) => table.sample.is_none() && table.as_of.is_none(),
+        LogicalOperator::Filter(_)
+        | LogicalOperator::TableAlias(_)
+        | LogicalOperator::UnionAll(_) => true,
+        _ => false,
+    };
+    linear
+        && query
+            .expressions_mut()
+            .all(|expr| is_repeatable_expression(expr))
+        && query.children_mut().all(is_linear)
+}
+
+/// Swaps each table scan for a read of the table's changes
+fn scan_changes(operator: &mut PointInTimeOperator, from: LogicalTimestamp, to: LogicalTimestamp) {
+    if let PointInTimeOperator::TableScan(table_scan) = operator {
+        let table = table_scan.table.clone();
+        *operator = PointInTimeOperator::TableChanges(TableChanges { table, from, to });
+    }
+    for child in operator.children_mut() {
+        scan_changes(child, from, to);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::statement::Statement;
+
+    fn parse(sql: &str) -> LogicalOperator {
+        match parser::parse(sql).unwrap() {
+            Statement::Query(query) => query,
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_plan_for_delta() -> Result<(), PlannerError> {
+        let planner = Planner::new_for_test();
+        let session = Session::new(1);
+        let from = LogicalTimestamp::new(10);
+        let to = LogicalTimestamp::new(20);
+
+        let plan = planner
+            .plan_for_delta(
+                parse("SELECT name FROM incresql.databases WHERE name != 'default'"),
+                &session,
+                from,
+                to,
+            )?
+            .unwrap();
+        let mut operator = &plan.operator;
+        while let Some(child) = match operator {
+            PointInTimeOperator::Project(project) => Some(&project.source),
+            PointInTimeOperator::Filter(filter) => Some(&filter.source),
+            _ => None,
+        } {
+            operator = child;
+        }
+        assert!(matches!(
+            operator,
+            PointInTimeOperator::TableChanges(TableChanges { from: f, to: t, .. }) if *f == from && *t == to
+        ));
+
+        for sql in &[
+            "SELECT count(*) FROM incresql.databases",
+            "SELECT d.name FROM incresql.databases d JOIN incresql.databases e ON d.name = e.name",
+            "SELECT name FROM incresql.databases LIMIT 1",
+            "SELECT name, random() FROM incresql.databases",
+            "SELECT name FROM incresql.databases WHERE now() > cast('2020-01-01' as timestamp)",
+        ] {
+            assert!(planner
+                .plan_for_delta(parse(sql), &session, from, to)?
+                .is_none());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,164 @@
+use super::{is_linear, scan_changes};
+use crate::p4_pit_planning::{build_operator, is_repeatable_expression};
+use crate::utils::expr::move_column_references;
+use crate::utils::logical::fields_for_operator;
+use crate::{Field, Planner, PlannerError};
+use ast::expr::{CompiledColumnReference, Expression};
+use ast::rel::logical::{GroupBy, LogicalOperator};
+use ast::rel::point_in_time::{PointInTimeOperator, Project};
+use data::{LogicalTimestamp, Session};
+
+/// A group by over a union all split into a partial aggregate per source of the union and a
+/// final merge of the partials. Each source's plan returns the changes to its rows between
+/// from and to with the group's key in front of them, so a change to one source only touches
+/// that source's partial state for the group. The expressions are evaluated over those rows,
+/// merging every source's state for a group gives the state of the group as a whole.
+pub struct PartialAggregatePlan {
+    pub fields: Vec<Field>,
+    pub sources: Vec<PointInTimeOperator>,
+    pub key_len: usize,
+    pub expressions: Vec<Expression>,
+}
+
+impl Planner {
+    /// Plans a query that groups the rows of a union all so each of the union's sources can
+    /// keep its own partial aggregate states, see PartialAggregatePlan. None is returned unless
+    /// the query is a group by with keys over a union all of linear queries, with aggregates
+    /// that can all be retracted as the changes to the sources include deletes.
+    pub fn plan_for_partial_aggregate(
+        &self,
+        query: LogicalOperator,
+        session: &Session,
+        from: LogicalTimestamp,
+        to: LogicalTimestamp,
+    ) -> Result<Option<PartialAggregatePlan>, PlannerError> {
+        let (fields, operator) = match self.plan_immutable(query, session)? {
+            Some(planned) => planned,
+            None => return Ok(None),
+        };
+        let GroupBy {
+            expressions,
+            key_expressions,
+            source,
+        } = match operator {
+            LogicalOperator::GroupBy(group_by) if !group_by.key_expressions.is_empty() => group_by,
+            _ => return Ok(None),
+        };
+        let splittable = expressions
+            .iter()
+            .map(|ne| &ne.expression)
+            .chain(&key_expressions)
+            .all(|expr| is_repeatable_expression(expr) && is_retractable(expr));
+        let mut union_sources = match union_sources(*source) {
+            Some(sources) if splittable => sources,
+            _ => return Ok(None),
+        };
+        if !union_sources.iter_mut().all(is_linear) {
+            return Ok(None);
+        }
+
+        // Like a hash group each source's rows are prefixed with their key
+        let key_len = key_expressions.len();
+        let sources = union_sources
+            .into_iter()
+            .map(|source| {
+                let mut project_exprs = key_expressions.clone();
+                for (idx, field) in fields_for_operator(&source).enumerate() {
+                    project_exprs.push(Expression::CompiledColumnReference(
+                        CompiledColumnReference {
+                            offset: idx,
+                            datatype: field.data_type,
+                        },
+                    ));
+                }
+                let mut operator = build_operator(source, &self.function_registry);
+                scan_changes(&mut operator, from, to);
+                PointInTimeOperator::Project(Project {
+                    expressions: project_exprs,
+                    source: Box::new(operator),
+                })
+            })
+            .collect();
+        let expressions = expressions
+            .into_iter()
+            .map(|mut ne| {
+                move_column_references(&mut ne.expression, key_len as isize);
+                ne.expression
+            })
+            .collect();
+
+        Ok(Some(PartialAggregatePlan {
+            fields,
+            sources,
+            key_len,
+            expressions,
+        }))
+    }
+}
+
+/// The sources of the union all under any aliases
+fn union_sources(operator: LogicalOperator) -> Option<Vec<LogicalOperator>> {
+    match operator {
+        LogicalOperator::UnionAll(union_all) => Some(union_all.sources),
+        LogicalOperator::TableAlias(table_alias) => union_sources(*table_alias.source),
+        _ => None,
+    }
+}
+
+/// Whether each of the aggregates in the expression can have rows taken back out of it
+fn is_retractable(expression: &Expression) -> bool {
+    if let Expression::CompiledAggregate(aggregate) = expression {
+        if !aggregate.function.supports_retract() {
+            return false;
+        }
+    }
+    expression.children().all(is_retractable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::statement::Statement;
+
+    fn plan(sql: &str) -> Option<PartialAggregatePlan> {
+        let query = match parser::parse(sql).unwrap() {
+            Statement::Query(query) => query,
+            _ => panic!(),
+        };
+        Planner::new_for_test()
+            .plan_for_partial_aggregate(
+                query,
+                &Session::new(1),
+                LogicalTimestamp::new(10),
+                LogicalTimestamp::new(20),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_plan_for_partial_aggregate() {
+        let partial = plan(
+            "SELECT name, count(*), sum(1) FROM \
+             (SELECT name FROM incresql.databases UNION ALL SELECT name FROM incresql.tables) t \
+             GROUP BY name",
+        )
+        .unwrap();
+        assert_eq!(partial.sources.len(), 2);
+        assert_eq!(partial.key_len, 1);
+        assert_eq!(partial.fields.len(), 3);
+        assert_eq!(partial.expressions.len(), 3);
+
+        for sql in &[
+            // Not over a union
+            "SELECT name, count(*) FROM incresql.databases GROUP BY name",
+            // No keys
+            "SELECT count(*) FROM (SELECT name FROM incresql.databases UNION ALL SELECT name FROM incresql.tables) t",
+            // Can't be retracted
+            "SELECT name, any_value(name) FROM (SELECT name FROM incresql.databases UNION ALL SELECT name FROM incresql.tables) t GROUP BY name",
+            // One of the sources isn't linear
+            "SELECT name, count(*) FROM (SELECT name FROM incresql.databases UNION ALL SELECT name FROM incresql.tables LIMIT 1) t GROUP BY name",
+        ] {
+            assert!(plan(sql).is_none(), "{}", sql);
+        }
+    }
+}
@@ -4,9 +4,19 @@ use crate::{Field, Planner, PlannerError};
 use ast::expr::*;
 use ast::rel::logical::*;
 use ast::rel::point_in_time;
-use ast::rel::point_in_time::{Group, PointInTimeOperator};
-use data::{LogicalTimestamp, Session};
+use ast::rel::point_in_time::{Group, PointInTimeOperator, ScanRange};
+use data::{LogicalTimestamp, Session, SortOrder};
 use functions::registry::Registry;
+use functions::Volatility;
+use std::time::Duration;
+
+// Inserts are chunked into write batches of this many rows so we don't blow out our memory
+const DEFAULT_INSERT_BATCH_ROWS: usize = 10_000;
+
+/// How long reads will wait on in-flight writes before settling for an older read barrier
+pub const READ_BARRIER_WAIT: Duration = Duration::from_millis(100);
+
+mod scan_ranges;
 
 pub struct PointInTimePlan {
     pub fields: Vec<Field>,
@@ -22,12 +32,118 @@ impl Planner {
         session: &Session,
     ) -> Result<PointInTimePlan, PlannerError> {
         let (fields, operator) = self.plan_common(query, session)?;
-        let operator = build_operator(operator, &self.function_registry);
+        let mut operator = build_operator(operator, &self.function_registry);
+        if let Some(timestamp) = self.read_timestamp(session, &operator)? {
+            operator.set_scan_timestamps(timestamp);
+        }
         Ok(PointInTimePlan { fields, operator })
     }
+
+    /// Plans the query as normal and again without any of the optional optimizer rules, the
+    /// unoptimized plan should always return the same rows so can be used to catch optimizer
+    /// bugs. There's no unoptimized plan for queries that wouldn't return the same rows when
+    /// run twice, ie inserts, file reads or those calling random(). Both plans read as of the
+    /// same timestamp with read_barrier on, otherwise writes that land between the two runs
+    /// will show up as differences.
+    pub fn plan_for_validation(
+        &self,
+        query: LogicalOperator,
+        session: &Session,
+    ) -> Result<(PointInTimePlan, Option<PointInTimePlan>), PlannerError> {
+        let (fields, operator) = self.plan_common(query.clone(), session)?;
+        let mut optimized = PointInTimePlan {
+            fields,
+            operator: build_operator(operator, &self.function_registry),
+        };
+        let (fields, mut operator) = self.plan_common_with_rules(query, session, &|_rule| false)?;
+        let mut unoptimized = if is_repeatable(&mut operator) {
+            Some(PointInTimePlan {
+                fields,
+                operator: build_operator(operator, &self.function_registry),
+            })
+        } else {
+            None
+        };
+
+        if let Some(timestamp) = self.read_timestamp(session, &optimized.operator)? {
+            optimized.operator.set_scan_timestamps(timestamp);
+            if let Some(unoptimized) = &mut unoptimized {
+                unoptimized.operator.set_scan_timestamps(timestamp);
+            }
+        }
+        Ok((optimized, unoptimized))
+    }
+
+    /// The timestamp to read as of, the session's as_of if it's set otherwise a read barrier
+    /// when the session has read_barrier on. Nothing can be written while as_of is set.
+    fn read_timestamp(
+        &self,
+        session: &Session,
+        operator: &PointInTimeOperator,
+    ) -> Result<Option<LogicalTimestamp>, PlannerError> {
+        if let Some(as_of) = *session.as_of.read().unwrap() {
+            if writes(operator) {
+                return Err(PlannerError::WriteAsOf);
+            }
+            Ok(Some(scan_timestamp(as_of)))
+        } else if *session.read_barrier.read().unwrap() {
+            Ok(Some(
+                self.catalog.read().unwrap().read_barrier(READ_BARRIER_WAIT),
+            ))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// The timestamp to scan at to read as of a time, scans only see rows written before their
+/// timestamp and the rows written at the time should be seen too
+fn scan_timestamp(as_of: LogicalTimestamp) -> LogicalTimestamp {
+    LogicalTimestamp::new(as_of.ms + 1)
 }
 
-fn build_operator(query: LogicalOperator, function_registry: &Registry) -> PointInTimeOperator {
+/// Whether the plan writes to any tables
+fn writes(operator: &PointInTimeOperator) -> bool {
+    matches!(operator, PointInTimeOperator::TableInsert(_)) || operator.children().any(writes)
+}
+
+/// Whether running the query again would give the same rows without any side effects, queries
+/// that write, read files (which can record rejected rows) or call volatile functions don't.
+fn is_repeatable(query: &mut LogicalOperator) -> bool {
+    if let LogicalOperator::TableInsert(_)
+    | LogicalOperator::FileScan(_)
+    | LogicalOperator::FileCopy(_) = query
+    {
+        return false;
+    }
+    query
+        .expressions_mut()
+        .all(|expr| is_repeatable_expression(expr))
+        && query.children_mut().all(is_repeatable)
+}
+
+/// Whether the expression gives the same result each time it's evaluated within a statement
+pub(crate) fn is_repeatable_expression(expression: &Expression) -> bool {
+    volatility(expression) != Volatility::Volatile
+}
+
+/// The volatility of the most volatile function the expression calls
+pub(crate) fn volatility(expression: &Expression) -> Volatility {
+    let own = if let Expression::CompiledFunctionCall(function_call) = expression {
+        function_call.function.volatility()
+    } else {
+        Volatility::Immutable
+    };
+    expression
+        .children()
+        .map(volatility)
+        .fold(own, Volatility::max)
+}
+
+pub(crate) fn build_operator(
+    query: LogicalOperator,
+    function_registry: &Registry,
+) -> PointInTimeOperator {
     match query {
         LogicalOperator::Single => PointInTimeOperator::Single,
         LogicalOperator::Project(Project {
@@ -86,10 +202,18 @@ fn build_operator(query: LogicalOperator, function_registry: &Registry) -> Point
                 })
             }
         }
-        LogicalOperator::Filter(Filter { predicate, source }) => {
+        LogicalOperator::Filter(Filter {
+            predicate, source, ..
+        }) => {
+            let mut source = build_operator(*source, function_registry);
+            // Narrow down the parts of the table we need to read, the filter is still needed
+            // as the ranges may cover more than the predicate.
+            if let PointInTimeOperator::TableScan(table_scan) = &mut source {
+                table_scan.ranges = scan_ranges::scan_ranges(&predicate, &table_scan.table);
+            }
             PointInTimeOperator::Filter(point_in_time::Filter {
                 predicate,
-                source: Box::new(build_operator(*source, function_registry)),
+                source: Box::new(source),
             })
         }
         LogicalOperator::Limit(Limit {
@@ -108,6 +232,43 @@ fn build_operator(query: LogicalOperator, function_registry: &Registry) -> Point
             sort_expressions,
             source: Box::new(build_operator(*source, function_registry)),
         }),
+        LogicalOperator::Window(Window { functions, source }) => {
+            // The functions all share the same partition and order by, see extract_windows
+            let (partition_by, order_by) = match &functions[0].expression {
+                Expression::WindowFunction(window) => {
+                    (window.partition_by.clone(), window.order_by.clone())
+                }
+                _ => panic!("Expected a window function"),
+            };
+            let mut source = build_operator(*source, function_registry);
+            if !partition_by.is_empty() || !order_by.is_empty() {
+                let sort_expressions = partition_by
+                    .iter()
+                    .map(|expression| SortExpression {
+                        ordering: SortOrder::Asc,
+                        expression: expression.clone(),
+                    })
+                    .chain(order_by.iter().cloned())
+                    .collect();
+                source = PointInTimeOperator::Sort(point_in_time::Sort {
+                    sort_expressions,
+                    source: Box::new(source),
+                });
+            }
+            let functions = functions
+                .into_iter()
+                .map(|ne| match ne.expression {
+                    Expression::WindowFunction(window) => (window.function, window.frame),
+                    _ => panic!("Expected a window function"),
+                })
+                .collect();
+            PointInTimeOperator::Window(point_in_time::Window {
+                source: Box::new(source),
+                partition_by,
+                order_by: order_by.into_iter().map(|sort| sort.expression).collect(),
+                functions,
+            })
+        }
         LogicalOperator::Values(values) => {
             let data = values.data.into_iter().map(|row| {
                 row.into_iter().map(|expr| {
@@ -132,18 +293,44 @@ fn build_operator(query: LogicalOperator, function_registry: &Registry) -> Point
                     .collect(),
             })
         }
-        LogicalOperator::ResolvedTable(ResolvedTable { columns: _, table }) => {
+        LogicalOperator::ResolvedTable(ResolvedTable {
+            columns: _,
+            table,
+            sample,
+            as_of,
+        }) => {
             PointInTimeOperator::TableScan(point_in_time::TableScan {
                 table,
                 // Having a timestamp in the future gives us read after write within the same ms
                 // Rockdb already gives us atomic writes so I can't think of any downsides with this
-                timestamp: LogicalTimestamp::MAX,
+                timestamp: as_of.map_or(LogicalTimestamp::MAX, scan_timestamp),
+                ranges: vec![ScanRange::default()],
+                sample,
             })
         }
-        LogicalOperator::TableInsert(TableInsert { table, source }) => {
+        LogicalOperator::TableInsert(TableInsert {
+            table,
+            mut source,
+            json_schemas,
+            ..
+        }) => {
+            let mut source_offsets = None;
+            let mut batch_rows = DEFAULT_INSERT_BATCH_ROWS;
+            visit_file_scans(&mut source, &mut |file_scan| {
+                if file_scan.source_offsets.is_some() {
+                    source_offsets = file_scan.source_offsets.clone();
+                }
+                if let Some(max_batch_rows) = file_scan.serde_options.max_batch_rows {
+                    batch_rows = batch_rows.min(max_batch_rows as usize);
+                }
+            });
+            if let LogicalOperator::FileCopy(file_copy) = source.as_ref() {
+                if let Some(max_batch_rows) = file_copy.serde_options.max_batch_rows {
+                    batch_rows = batch_rows.min(max_batch_rows as usize);
+                }
+            }
             let actual_table =
-                if let LogicalOperator::ResolvedTable(ResolvedTable { columns: _, table }) = *table
-                {
+                if let LogicalOperator::ResolvedTable(ResolvedTable { table, .. }) = *table {
                     table
                 } else {
                     panic!("Can not insert into anything other than a resolved table")
@@ -152,6 +339,9 @@ fn build_operator(query: LogicalOperator, function_registry: &Registry) -> Point
             PointInTimeOperator::TableInsert(point_in_time::TableInsert {
                 table: actual_table,
                 source: Box::new(build_operator(*source, function_registry)),
+                source_offsets,
+                batch_rows,
+                json_schemas,
             })
         }
         LogicalOperator::NegateFreq(source) => {
@@ -164,6 +354,26 @@ fn build_operator(query: LogicalOperator, function_registry: &Registry) -> Point
             PointInTimeOperator::FileScan(point_in_time::FileScan {
                 directory: file_scan.directory,
                 serde_options: file_scan.serde_options,
+                columns: file_scan
+                    .columns
+                    .into_iter()
+                    .map(|ne| ne.expression)
+                    .collect(),
+                dead_letters: file_scan.dead_letters,
+                source_offsets: file_scan.source_offsets,
+                paused_sources: file_scan.paused_sources,
+            })
+        }
+        LogicalOperator::FileCopy(file_copy) => {
+            PointInTimeOperator::FileCopy(point_in_time::FileCopy {
+                path: file_copy.path,
+                serde_options: file_copy.serde_options,
+                columns: file_copy
+                    .columns
+                    .into_iter()
+                    .map(|ne| ne.expression)
+                    .collect(),
+                dead_letters: file_copy.dead_letters,
             })
         }
         LogicalOperator::Join(join) => {
@@ -197,6 +407,17 @@ fn build_operator(query: LogicalOperator, function_registry: &Registry) -> Point
     }
 }
 
+/// Inserts pick up the settings of the file sources feeding them, resuming sources have their
+/// offsets committed by the insert and the batch size is capped by max_batch_rows.
+fn visit_file_scans<F: FnMut(&FileScan)>(operator: &mut LogicalOperator, visitor: &mut F) {
+    if let LogicalOperator::FileScan(file_scan) = operator {
+        visitor(file_scan);
+    }
+    for child in operator.children_mut() {
+        visit_file_scans(child, visitor);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -230,4 +451,74 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_plan_for_validation() -> Result<(), PlannerError> {
+        let planner = Planner::new_for_test();
+        let session = Session::new(1);
+        let parse = |sql: &str| match parser::parse(sql).unwrap() {
+            ast::statement::Statement::Query(query) => query,
+            _ => panic!(),
+        };
+
+        let (optimized, unoptimized) =
+            planner.plan_for_validation(parse("SELECT 1 + 2"), &session)?;
+        assert_eq!(
+            optimized.operator,
+            PointInTimeOperator::Project(point_in_time::Project {
+                expressions: vec![Expression::from(3)],
+                source: Box::new(PointInTimeOperator::Single),
+            })
+        );
+        let unoptimized = unoptimized.unwrap();
+        assert_eq!(unoptimized.fields, optimized.fields);
+        assert_ne!(unoptimized.operator, optimized.operator);
+
+        let (_optimized, unoptimized) =
+            planner.plan_for_validation(parse("SELECT random()"), &session)?;
+        assert!(unoptimized.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_barrier() -> Result<(), PlannerError> {
+        let planner = Planner::new_for_test();
+        let session = Session::new(1);
+        let query = LogicalOperator::TableReference(TableReference {
+            database: Some("incresql".to_string()),
+            table: "databases".to_string(),
+            sample: None,
+            as_of: None,
+        });
+
+        let scan_timestamp = |operator: &PointInTimeOperator| match operator {
+            PointInTimeOperator::TableScan(table_scan) => table_scan.timestamp,
+            _ => panic!("Expected a table scan, got {:?}", operator),
+        };
+
+        let plan = planner.plan_for_point_in_time(query.clone(), &session)?;
+        assert_eq!(scan_timestamp(&plan.operator), LogicalTimestamp::MAX);
+
+        *session.read_barrier.write().unwrap() = true;
+        let before = LogicalTimestamp::now();
+        let plan = planner.plan_for_point_in_time(query.clone(), &session)?;
+        assert!(scan_timestamp(&plan.operator) < before);
+        assert!(scan_timestamp(&plan.operator).ms >= before.ms - 1);
+
+        // as_of takes precedence over the read barrier
+        *session.as_of.write().unwrap() = Some(LogicalTimestamp::new(123));
+        let plan = planner.plan_for_point_in_time(query, &session)?;
+        assert_eq!(scan_timestamp(&plan.operator), LogicalTimestamp::new(124));
+
+        // Tables read AS OF TIMESTAMP keep their own timestamp
+        let query = LogicalOperator::TableReference(TableReference {
+            database: Some("incresql".to_string()),
+            table: "databases".to_string(),
+            sample: None,
+            as_of: Some(LogicalTimestamp::new(456)),
+        });
+        let plan = planner.plan_for_point_in_time(query, &session)?;
+        assert_eq!(scan_timestamp(&plan.operator), LogicalTimestamp::new(457));
+        Ok(())
+    }
 }
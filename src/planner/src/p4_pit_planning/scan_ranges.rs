@@ -0,0 +1,202 @@
+use crate::utils::ranges::ranges_for_column;
+use ast::expr::Expression;
+use ast::rel::point_in_time::ScanRange;
+use data::Datum;
+use std::ops::Bound;
+use storage::Table;
+
+/// The most ranges we'll split a scan into, past this we'll settle for scanning a bit more
+/// than we need to.
+const MAX_SCAN_RANGES: usize = 64;
+
+/// Works out which ranges of the table need to be scanned for the predicate to be satisfied.
+/// The predicate is still applied after the scan so the ranges only need to cover every row
+/// that might match.
+/// Ranges are built up one primary key column at a time, as long as each column is restricted
+/// to a set of points we can keep narrowing down using the next column, ie
+/// a IN (1, 2) AND b > 5
+/// becomes the scans [1, 5]..[1] and [2, 5]..[2]
+/// Tables with null bitmaps aren't sorted by their primary key so they're always fully scanned.
+pub(super) fn scan_ranges(predicate: &Expression, table: &Table) -> Vec<ScanRange> {
+    // The from and to prefixes for each range
+    let mut ranges = vec![(vec![], vec![])];
+
+    let key = table.key_columns().iter().zip(table.pk());
+    for (idx, (column, sort_order)) in key.enumerate() {
+        let range_set = ranges_for_column(predicate, *column);
+        if range_set.intervals().is_empty() {
+            return vec![];
+        }
+        if table.null_bitmap()
+            || range_set.is_all()
+            || ranges.len() * range_set.intervals().len() > MAX_SCAN_RANGES
+        {
+            break;
+        }
+
+        // Descending keys are stored back to front
+        let intervals: Vec<_> = if sort_order.is_asc() {
+            range_set
+                .intervals()
+                .iter()
+                .map(|interval| (&interval.lower, &interval.upper))
+                .collect()
+        } else {
+            range_set
+                .intervals()
+                .iter()
+                .rev()
+                .map(|interval| (&interval.upper, &interval.lower))
+                .collect()
+        };
+
+        ranges = ranges
+            .into_iter()
+            .flat_map(|(from, to)| {
+                intervals.iter().map(move |(from_bound, to_bound)| {
+                    (extend(&from, from_bound), extend(&to, to_bound))
+                })
+            })
+            .collect();
+
+        // Once we've got a proper range for a column the later columns aren't sorted in
+        // any useful way.
+        let all_points = ranges
+            .iter()
+            .all(|(from, to)| from.len() == idx + 1 && from == to);
+        if !all_points {
+            break;
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|(from, to)| ScanRange {
+            from: Some(from).filter(|from| !from.is_empty()),
+            to: Some(to).filter(|to| !to.is_empty()),
+        })
+        .collect()
+}
+
+/// Appends the bound onto the prefix. The scan's bounds are always inclusive so any
+/// excluded values are left for the predicate to filter out.
+fn extend(prefix: &[Datum<'static>], bound: &Bound<Datum<'static>>) -> Vec<Datum<'static>> {
+    let mut prefix = prefix.to_vec();
+    match bound {
+        Bound::Included(datum) | Bound::Excluded(datum) => prefix.push(datum.clone()),
+        Bound::Unbounded => {}
+    }
+    prefix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Planner;
+    use ast::rel::logical::LogicalOperator;
+    use data::{Session, SortOrder};
+    use storage::Storage;
+
+    /// Returns the scan ranges for a two column table given the where clause
+    fn ranges(predicate: &str, pk: Vec<SortOrder>) -> Vec<ScanRange> {
+        ranges_for_table(predicate, Storage::new_in_mem().unwrap().table(2, 2, pk))
+    }
+
+    fn ranges_for_table(predicate: &str, table: Table) -> Vec<ScanRange> {
+        let planner = Planner::new_for_test();
+        let session = Session::new(1);
+        let sql = format!(
+            "SELECT * FROM (SELECT 1 as a, 2 as b) t WHERE {}",
+            predicate
+        );
+        let query = if let ast::statement::Statement::Query(query) = parser::parse(&sql).unwrap() {
+            query
+        } else {
+            panic!()
+        };
+        let query = planner.validate(query, &session).unwrap();
+        if let LogicalOperator::Project(project) = query {
+            if let LogicalOperator::Filter(filter) = *project.source {
+                return scan_ranges(&filter.predicate, &table);
+            }
+        }
+        panic!()
+    }
+
+    fn range(from: &[i32], to: &[i32]) -> ScanRange {
+        let to_datums = |values: &[i32]| {
+            Some(values.iter().map(|i| Datum::from(*i)).collect::<Vec<_>>())
+                .filter(|datums| !datums.is_empty())
+        };
+        ScanRange {
+            from: to_datums(from),
+            to: to_datums(to),
+        }
+    }
+
+    #[test]
+    fn test_scan_ranges() {
+        let asc = vec![SortOrder::Asc, SortOrder::Asc];
+        assert_eq!(ranges("TRUE", asc.clone()), vec![range(&[], &[])]);
+        assert_eq!(ranges("a = 1 AND a = 2", asc.clone()), vec![]);
+        assert_eq!(
+            ranges("a >= 1 AND a < 5", asc.clone()),
+            vec![range(&[1], &[5])]
+        );
+        assert_eq!(
+            ranges("a IN (1, 2) AND b > 5", asc.clone()),
+            vec![range(&[1, 5], &[1]), range(&[2, 5], &[2])]
+        );
+        assert_eq!(
+            ranges("a = 1 AND b BETWEEN 2 AND 3", asc.clone()),
+            vec![range(&[1, 2], &[1, 3])]
+        );
+        // Once a is a range we can't narrow it down any further with b
+        assert_eq!(
+            ranges("a > 1 AND b = 2", asc.clone()),
+            vec![range(&[1], &[])]
+        );
+        // Nothing on a means nothing for b either
+        assert_eq!(ranges("b = 2", asc), vec![range(&[], &[])]);
+    }
+
+    #[test]
+    fn test_scan_ranges_desc() {
+        let desc = vec![SortOrder::Desc, SortOrder::Asc];
+        assert_eq!(
+            ranges("a >= 1 AND a < 5", desc.clone()),
+            vec![range(&[5], &[1])]
+        );
+        assert_eq!(
+            ranges("a IN (1, 2)", desc),
+            vec![range(&[2], &[2]), range(&[1], &[1])]
+        );
+    }
+
+    #[test]
+    fn test_scan_ranges_null_bitmap() {
+        let table = Storage::new_in_mem()
+            .unwrap()
+            .table(2, 2, vec![SortOrder::Asc, SortOrder::Asc])
+            .with_null_bitmap();
+        assert_eq!(
+            ranges_for_table("a = 1 AND b = 2", table.clone()),
+            vec![range(&[], &[])]
+        );
+        assert_eq!(ranges_for_table("a = 1 AND a = 2", table), vec![]);
+    }
+
+    #[test]
+    fn test_scan_ranges_primary_key() {
+        // Keyed on b alone
+        let table = Storage::new_in_mem()
+            .unwrap()
+            .table(2, 2, vec![SortOrder::Asc])
+            .with_primary_key(&[1]);
+        assert_eq!(
+            ranges_for_table("a = 1 AND b = 2", table.clone()),
+            vec![range(&[2], &[2])]
+        );
+        assert_eq!(ranges_for_table("a = 1", table), vec![range(&[], &[])]);
+    }
+}
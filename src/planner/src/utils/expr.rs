@@ -1,3 +1,4 @@
+use crate::utils::logical::fields_for_operator;
 use ast::expr::{CompiledFunctionCall, Expression, FunctionCall};
 use data::DataType;
 use functions::registry::Registry;
@@ -13,6 +14,13 @@ pub(crate) fn type_for_expression(expr: &Expression) -> DataType {
         Expression::CompiledFunctionCall(function_call) => function_call.signature.ret,
         Expression::CompiledAggregate(function_call) => function_call.signature.ret,
         Expression::CompiledColumnReference(column_reference) => column_reference.datatype,
+        Expression::Subquery(subquery) if subquery.scalar => fields_for_operator(&subquery.query)
+            .next()
+            .map(|field| field.data_type)
+            .unwrap_or(DataType::Null),
+        Expression::Subquery(_) => DataType::Boolean,
+        Expression::Parameter(parameter) => parameter.datatype,
+        Expression::WindowFunction(window) => type_for_expression(&window.function),
 
         // These should be gone by now!
         Expression::FunctionCall(_) | Expression::ColumnReference(_) => {
@@ -21,12 +29,43 @@ pub(crate) fn type_for_expression(expr: &Expression) -> DataType {
     }
 }
 
-/// Returns true if the expression contains an aggregate anywhere in its expressions.
+/// Returns true if the expression contains an aggregate anywhere in its expressions, the
+/// aggregates of window functions don't count as they don't collapse the rows.
 pub(crate) fn contains_aggregate(expr: &Expression) -> bool {
-    if let Expression::CompiledAggregate(_) = expr {
+    match expr {
+        Expression::CompiledAggregate(_) => true,
+        Expression::WindowFunction(_) => false,
+        _ => expr.children().any(contains_aggregate),
+    }
+}
+
+/// Returns true if the expression contains a window function anywhere in its expressions.
+pub(crate) fn contains_window(expr: &Expression) -> bool {
+    if let Expression::WindowFunction(_) = expr {
+        true
+    } else {
+        expr.children().any(contains_window)
+    }
+}
+
+/// Returns true if the expression contains a subquery anywhere in its expressions.
+pub(crate) fn contains_subquery(expr: &Expression) -> bool {
+    if let Expression::Subquery(_) = expr {
         true
     } else {
-        expr.children().any(contains_aggregate)
+        expr.children().any(contains_subquery)
+    }
+}
+
+/// Marks any columns referenced by the expression as used
+pub(crate) fn mark_used(expression: &Expression, used: &mut [bool]) {
+    if let Expression::CompiledColumnReference(column_reference) = expression {
+        if let Some(u) = used.get_mut(column_reference.offset) {
+            *u = true;
+        }
+    }
+    for expr in expression.children() {
+        mark_used(expr, used);
     }
 }
 
@@ -109,7 +148,6 @@ pub(crate) fn combine_predicates<E: IntoIterator<Item = Expression>>(
             Expression::CompiledFunctionCall(CompiledFunctionCall {
                 function: and_function.as_scalar(),
                 args: Box::from([a, b]),
-                expr_buffer: Box::from(vec![]),
                 signature: Box::new(and_function_sig.clone()),
             })
         }),
@@ -10,16 +10,31 @@ pub(crate) fn fields_for_operator(
     operator: &LogicalOperator,
 ) -> Box<dyn Iterator<Item = Field> + '_> {
     match operator {
-        LogicalOperator::Project(_) | LogicalOperator::GroupBy(_) => {
-            Box::from(operator.named_expressions().map(|ne| Field {
+        LogicalOperator::FileScan(file_scan) if file_scan.columns.is_empty() => {
+            Box::from(once(Field {
                 qualifier: None,
-                alias: ne.alias.as_ref().unwrap().clone(),
-                data_type: type_for_expression(&ne.expression),
+                alias: "data".to_string(),
+                data_type: DataType::Json,
             }))
         }
+        LogicalOperator::Project(_)
+        | LogicalOperator::GroupBy(_)
+        | LogicalOperator::FileScan(_)
+        | LogicalOperator::FileCopy(_) => Box::from(operator.named_expressions().map(|ne| Field {
+            qualifier: None,
+            alias: ne.alias.as_ref().unwrap().clone(),
+            data_type: type_for_expression(&ne.expression),
+        })),
         LogicalOperator::Filter(filter) => fields_for_operator(&filter.source),
         LogicalOperator::Limit(limit) => fields_for_operator(&limit.source),
         LogicalOperator::Sort(sort) => fields_for_operator(&sort.source),
+        LogicalOperator::Window(window) => Box::from(fields_for_operator(&window.source).chain(
+            window.functions.iter().map(|ne| Field {
+                qualifier: None,
+                alias: ne.alias.as_ref().unwrap().clone(),
+                data_type: type_for_expression(&ne.expression),
+            }),
+        )),
         LogicalOperator::Values(values) => {
             Box::from(values.fields.iter().map(|(data_type, alias)| Field {
                 qualifier: None,
@@ -45,12 +60,10 @@ pub(crate) fn fields_for_operator(
         }
         LogicalOperator::NegateFreq(source) => fields_for_operator(source),
         LogicalOperator::Single | LogicalOperator::TableInsert(_) => Box::from(empty()),
-        LogicalOperator::FileScan(_) => Box::from(once(Field {
-            qualifier: None,
-            alias: "data".to_string(),
-            data_type: DataType::Json,
-        })),
         LogicalOperator::TableReference(_) => panic!(),
+        LogicalOperator::Join(join) if !join.join_type.outputs_right() => {
+            fields_for_operator(&join.left)
+        }
         LogicalOperator::Join(join) => {
             Box::from(fields_for_operator(&join.left).chain(fields_for_operator(&join.right)))
         }
@@ -63,7 +76,13 @@ pub(crate) fn fieldnames_for_operator(
     operator: &LogicalOperator,
 ) -> Box<dyn Iterator<Item = (Option<&str>, &str)> + '_> {
     match operator {
-        LogicalOperator::Project(_) | LogicalOperator::GroupBy(_) => Box::from(
+        LogicalOperator::FileScan(file_scan) if file_scan.columns.is_empty() => {
+            Box::from(once((None, "data")))
+        }
+        LogicalOperator::Project(_)
+        | LogicalOperator::GroupBy(_)
+        | LogicalOperator::FileScan(_)
+        | LogicalOperator::FileCopy(_) => Box::from(
             operator
                 .named_expressions()
                 .map(|ne| (None, ne.alias.as_ref().unwrap().as_str())),
@@ -71,6 +90,14 @@ pub(crate) fn fieldnames_for_operator(
         LogicalOperator::Filter(filter) => fieldnames_for_operator(&filter.source),
         LogicalOperator::Limit(limit) => fieldnames_for_operator(&limit.source),
         LogicalOperator::Sort(sort) => fieldnames_for_operator(&sort.source),
+        LogicalOperator::Window(window) => Box::from(
+            fieldnames_for_operator(&window.source).chain(
+                window
+                    .functions
+                    .iter()
+                    .map(|ne| (None, ne.alias.as_ref().unwrap().as_str())),
+            ),
+        ),
         LogicalOperator::Values(values) => Box::from(
             values
                 .fields
@@ -91,8 +118,10 @@ pub(crate) fn fieldnames_for_operator(
                 .map(|(alias, _datatype)| (None, alias.as_str())),
         ),
         LogicalOperator::NegateFreq(source) => fieldnames_for_operator(source),
-        LogicalOperator::FileScan(_) => Box::from(once((None, "data"))),
         LogicalOperator::Single | LogicalOperator::TableInsert(_) => Box::from(empty()),
+        LogicalOperator::Join(join) if !join.join_type.outputs_right() => {
+            fieldnames_for_operator(&join.left)
+        }
         LogicalOperator::Join(join) => Box::from(
             fieldnames_for_operator(&join.left).chain(fieldnames_for_operator(&join.right)),
         ),
@@ -112,18 +141,35 @@ pub(crate) fn source_fields_for_operator(
         LogicalOperator::Filter(filter) => fields_for_operator(&filter.source),
         LogicalOperator::Limit(limit) => fields_for_operator(&limit.source),
         LogicalOperator::Sort(sort) => fields_for_operator(&sort.source),
+        LogicalOperator::Window(window) => fields_for_operator(&window.source),
         LogicalOperator::TableAlias(table_alias) => fields_for_operator(&table_alias.source),
         LogicalOperator::UnionAll(union_all) => {
             fields_for_operator(union_all.sources.first().unwrap())
         }
         LogicalOperator::TableInsert(table_insert) => fields_for_operator(&table_insert.source),
         LogicalOperator::NegateFreq(source) => fields_for_operator(source),
-        // The on clause see's the columns the same as the operators above do.
-        LogicalOperator::Join(_) => fields_for_operator(operator),
+        // Any columns are pulled out of the document
+        LogicalOperator::FileScan(_) => Box::from(once(Field {
+            qualifier: None,
+            alias: "data".to_string(),
+            data_type: DataType::Json,
+        })),
+        // Each column is parsed out of the record's fields
+        LogicalOperator::FileCopy(file_copy) => {
+            Box::from((1..=file_copy.columns.len()).map(|idx| Field {
+                qualifier: None,
+                alias: format!("field_{}", idx),
+                data_type: DataType::Text,
+            }))
+        }
+        // The on clause see's both sides, even for semi/anti joins where the right side isn't
+        // output.
+        LogicalOperator::Join(join) => {
+            Box::from(fields_for_operator(&join.left).chain(fields_for_operator(&join.right)))
+        }
         LogicalOperator::Values(_)
         | LogicalOperator::Single
         | LogicalOperator::TableReference(_)
-        | LogicalOperator::FileScan(_)
         | LogicalOperator::ResolvedTable(_) => Box::from(empty()),
     }
 }
@@ -1,2 +1,5 @@
 pub(crate) mod expr;
 pub(crate) mod logical;
+pub(crate) mod ranges;
+pub(crate) mod statistics;
+pub(crate) mod usage;
@@ -0,0 +1,363 @@
+use ast::expr::Expression;
+use data::{DataType, Datum};
+use std::cmp::Ordering;
+use std::ops::Bound;
+
+/// A contiguous range of values for a column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Interval {
+    pub lower: Bound<Datum<'static>>,
+    pub upper: Bound<Datum<'static>>,
+}
+
+/// A set of disjoint intervals, kept in ascending order.
+/// When derived from a predicate the set is a superset of the values for which the predicate
+/// could be true, ie any value outside of the set is guaranteed to be filtered out. Nulls
+/// can never satisfy a comparison so they're never considered to be part of a set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RangeSet {
+    intervals: Vec<Interval>,
+}
+
+impl RangeSet {
+    /// The set containing every value
+    pub fn all() -> Self {
+        RangeSet {
+            intervals: vec![Interval {
+                lower: Bound::Unbounded,
+                upper: Bound::Unbounded,
+            }],
+        }
+    }
+
+    /// The set containing no values
+    pub fn empty() -> Self {
+        RangeSet { intervals: vec![] }
+    }
+
+    /// The set containing just the one value
+    pub fn point(datum: Datum<'static>) -> Self {
+        RangeSet::from_bounds(Bound::Included(datum.clone()), Bound::Included(datum))
+    }
+
+    /// The set containing the values between the two bounds
+    pub fn from_bounds(lower: Bound<Datum<'static>>, upper: Bound<Datum<'static>>) -> Self {
+        if is_empty(&lower, &upper) {
+            RangeSet::empty()
+        } else {
+            RangeSet {
+                intervals: vec![Interval { lower, upper }],
+            }
+        }
+    }
+
+    pub fn intervals(&self) -> &[Interval] {
+        &self.intervals
+    }
+
+    pub fn is_all(&self) -> bool {
+        self == &RangeSet::all()
+    }
+
+    pub fn intersect(&self, other: &RangeSet) -> RangeSet {
+        let mut intervals = vec![];
+        for a in &self.intervals {
+            for b in &other.intervals {
+                let lower = if cmp_lower(&a.lower, &b.lower) == Ordering::Greater {
+                    &a.lower
+                } else {
+                    &b.lower
+                };
+                let upper = if cmp_upper(&a.upper, &b.upper) == Ordering::Less {
+                    &a.upper
+                } else {
+                    &b.upper
+                };
+                if !is_empty(lower, upper) {
+                    intervals.push(Interval {
+                        lower: lower.clone(),
+                        upper: upper.clone(),
+                    });
+                }
+            }
+        }
+        RangeSet::normalized(intervals)
+    }
+
+    pub fn union(&self, other: &RangeSet) -> RangeSet {
+        let intervals = self
+            .intervals
+            .iter()
+            .chain(other.intervals.iter())
+            .cloned()
+            .collect();
+        RangeSet::normalized(intervals)
+    }
+
+    /// Sorts the intervals and merges any that overlap or touch.
+    fn normalized(mut intervals: Vec<Interval>) -> RangeSet {
+        intervals.sort_by(|a, b| cmp_lower(&a.lower, &b.lower));
+        let mut merged: Vec<Interval> = Vec::with_capacity(intervals.len());
+        for interval in intervals {
+            match merged.last_mut() {
+                Some(last) if !is_separated(&last.upper, &interval.lower) => {
+                    if cmp_upper(&interval.upper, &last.upper) == Ordering::Greater {
+                        last.upper = interval.upper;
+                    }
+                }
+                _ => merged.push(interval),
+            }
+        }
+        RangeSet { intervals: merged }
+    }
+}
+
+/// Orders two lower bounds, unbounded being the lowest.
+fn cmp_lower(a: &Bound<Datum>, b: &Bound<Datum>) -> Ordering {
+    match (a, b) {
+        (Bound::Unbounded, Bound::Unbounded) => Ordering::Equal,
+        (Bound::Unbounded, _) => Ordering::Less,
+        (_, Bound::Unbounded) => Ordering::Greater,
+        (Bound::Included(a), Bound::Excluded(b)) if a == b => Ordering::Less,
+        (Bound::Excluded(a), Bound::Included(b)) if a == b => Ordering::Greater,
+        (Bound::Included(a), Bound::Included(b))
+        | (Bound::Included(a), Bound::Excluded(b))
+        | (Bound::Excluded(a), Bound::Included(b))
+        | (Bound::Excluded(a), Bound::Excluded(b)) => a.cmp(b),
+    }
+}
+
+/// Orders two upper bounds, unbounded being the highest.
+fn cmp_upper(a: &Bound<Datum>, b: &Bound<Datum>) -> Ordering {
+    match (a, b) {
+        (Bound::Unbounded, Bound::Unbounded) => Ordering::Equal,
+        (Bound::Unbounded, _) => Ordering::Greater,
+        (_, Bound::Unbounded) => Ordering::Less,
+        (Bound::Included(a), Bound::Excluded(b)) if a == b => Ordering::Greater,
+        (Bound::Excluded(a), Bound::Included(b)) if a == b => Ordering::Less,
+        (Bound::Included(a), Bound::Included(b))
+        | (Bound::Included(a), Bound::Excluded(b))
+        | (Bound::Excluded(a), Bound::Included(b))
+        | (Bound::Excluded(a), Bound::Excluded(b)) => a.cmp(b),
+    }
+}
+
+/// Returns true if no value can lie between the lower and upper bounds.
+fn is_empty(lower: &Bound<Datum>, upper: &Bound<Datum>) -> bool {
+    match (lower, upper) {
+        (Bound::Unbounded, _) | (_, Bound::Unbounded) => false,
+        (Bound::Included(l), Bound::Included(u)) => l > u,
+        (Bound::Included(l), Bound::Excluded(u))
+        | (Bound::Excluded(l), Bound::Included(u))
+        | (Bound::Excluded(l), Bound::Excluded(u)) => l >= u,
+    }
+}
+
+/// Returns true if there is a gap between an upper bound and a following lower bound, ie
+/// the two intervals can't be merged.
+fn is_separated(upper: &Bound<Datum>, lower: &Bound<Datum>) -> bool {
+    match (upper, lower) {
+        (Bound::Unbounded, _) | (_, Bound::Unbounded) => false,
+        (Bound::Excluded(u), Bound::Excluded(l)) => u <= l,
+        (Bound::Included(u), Bound::Included(l))
+        | (Bound::Included(u), Bound::Excluded(l))
+        | (Bound::Excluded(u), Bound::Included(l)) => u < l,
+    }
+}
+
+/// Works out the range of values the column can take for the predicate to be true.
+/// Only direct comparisons between the column and constants of the same type are taken
+/// into account, anything else is assumed to allow any value.
+pub(crate) fn ranges_for_column(predicate: &Expression, column: usize) -> RangeSet {
+    match predicate {
+        Expression::Constant(Datum::Boolean(true), _) => RangeSet::all(),
+        Expression::Constant(Datum::Boolean(false), _) | Expression::Constant(Datum::Null, _) => {
+            RangeSet::empty()
+        }
+        Expression::CompiledFunctionCall(function) => match function.signature.name {
+            "and" => function
+                .args
+                .iter()
+                .map(|arg| ranges_for_column(arg, column))
+                .fold(RangeSet::all(), |a, b| a.intersect(&b)),
+            "or" => function
+                .args
+                .iter()
+                .map(|arg| ranges_for_column(arg, column))
+                .fold(RangeSet::empty(), |a, b| a.union(&b)),
+            "=" | "!=" | "<" | "<=" | ">" | ">=" => {
+                match as_column_comparison(&function.args[0], &function.args[1], column) {
+                    // Flip the operator around if the column is on the right
+                    Some((datum, flipped)) => match (function.signature.name, flipped) {
+                        (_, _) if datum.is_null() => RangeSet::empty(),
+                        ("=", _) => RangeSet::point(datum),
+                        ("!=", _) => {
+                            RangeSet::from_bounds(Bound::Unbounded, Bound::Excluded(datum.clone()))
+                                .union(&RangeSet::from_bounds(
+                                    Bound::Excluded(datum),
+                                    Bound::Unbounded,
+                                ))
+                        }
+                        ("<", false) | (">", true) => {
+                            RangeSet::from_bounds(Bound::Unbounded, Bound::Excluded(datum))
+                        }
+                        ("<=", false) | (">=", true) => {
+                            RangeSet::from_bounds(Bound::Unbounded, Bound::Included(datum))
+                        }
+                        (">", false) | ("<", true) => {
+                            RangeSet::from_bounds(Bound::Excluded(datum), Bound::Unbounded)
+                        }
+                        _ => RangeSet::from_bounds(Bound::Included(datum), Bound::Unbounded),
+                    },
+                    None => RangeSet::all(),
+                }
+            }
+            "between" => {
+                let args = &function.args;
+                let lower = as_column_comparison(&args[0], &args[1], column);
+                let upper = as_column_comparison(&args[0], &args[2], column);
+                match (lower, upper) {
+                    (Some((lower, _)), Some((upper, _))) if lower.is_null() || upper.is_null() => {
+                        RangeSet::empty()
+                    }
+                    (Some((lower, _)), Some((upper, _))) => {
+                        RangeSet::from_bounds(Bound::Included(lower), Bound::Included(upper))
+                    }
+                    _ => RangeSet::all(),
+                }
+            }
+            _ => RangeSet::all(),
+        },
+        _ => RangeSet::all(),
+    }
+}
+
+/// If the args are a comparison between the column and a constant, returns the constant and
+/// whether the column was on the right hand side.
+fn as_column_comparison(
+    left: &Expression,
+    right: &Expression,
+    column: usize,
+) -> Option<(Datum<'static>, bool)> {
+    match (left, right) {
+        (
+            Expression::CompiledColumnReference(column_reference),
+            Expression::Constant(datum, datatype),
+        ) if column_reference.offset == column
+            && comparable(column_reference.datatype, *datatype) =>
+        {
+            Some((datum.clone(), false))
+        }
+        (
+            Expression::Constant(datum, datatype),
+            Expression::CompiledColumnReference(column_reference),
+        ) if column_reference.offset == column
+            && comparable(column_reference.datatype, *datatype) =>
+        {
+            Some((datum.clone(), true))
+        }
+        _ => None,
+    }
+}
+
+/// Datums only have a meaningful ordering against datums of the same type.
+fn comparable(column_type: DataType, constant_type: DataType) -> bool {
+    constant_type == DataType::Null
+        || (column_type == constant_type
+            && matches!(
+                column_type,
+                DataType::Boolean
+                    | DataType::Integer
+                    | DataType::BigInt
+                    | DataType::Decimal(..)
+                    | DataType::Text
+                    | DataType::Date
+                    | DataType::Timestamp
+            ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Planner;
+    use ast::rel::logical::LogicalOperator;
+    use data::Session;
+
+    /// Returns the ranges for the first column of the where clause
+    fn ranges(sql: &str) -> RangeSet {
+        let planner = Planner::new_for_test();
+        let session = Session::new(1);
+        let query = if let ast::statement::Statement::Query(query) = parser::parse(sql).unwrap() {
+            query
+        } else {
+            panic!()
+        };
+        let query = planner.validate(query, &session).unwrap();
+        if let LogicalOperator::Project(project) = query {
+            if let LogicalOperator::Filter(filter) = *project.source {
+                return ranges_for_column(&filter.predicate, 0);
+            }
+        }
+        panic!()
+    }
+
+    fn bounds(lower: Bound<i32>, upper: Bound<i32>) -> RangeSet {
+        let to_datum = |bound| match bound {
+            Bound::Included(i) => Bound::Included(Datum::from(i)),
+            Bound::Excluded(i) => Bound::Excluded(Datum::from(i)),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        RangeSet::from_bounds(to_datum(lower), to_datum(upper))
+    }
+
+    #[test]
+    fn test_range_set_operations() {
+        let a = bounds(Bound::Included(1), Bound::Excluded(5));
+        let b = bounds(Bound::Included(3), Bound::Included(8));
+        let c = bounds(Bound::Excluded(8), Bound::Unbounded);
+
+        assert_eq!(
+            a.intersect(&b),
+            bounds(Bound::Included(3), Bound::Excluded(5))
+        );
+        assert_eq!(a.union(&b), bounds(Bound::Included(1), Bound::Included(8)));
+        // Touching intervals get merged
+        assert_eq!(b.union(&c), bounds(Bound::Included(3), Bound::Unbounded));
+        assert_eq!(a.intersect(&c), RangeSet::empty());
+        assert_eq!(a.union(&c).intervals().len(), 2);
+        assert_eq!(
+            bounds(Bound::Excluded(1), Bound::Excluded(1)),
+            RangeSet::empty()
+        );
+        assert!(RangeSet::empty().union(&RangeSet::all()).is_all());
+    }
+
+    #[test]
+    fn test_ranges_for_column() {
+        let from = "SELECT * FROM (SELECT 1 as a, 2 as b) t WHERE";
+        assert_eq!(
+            ranges(&format!("{} a >= 1 AND 5 > a", from)),
+            bounds(Bound::Included(1), Bound::Excluded(5))
+        );
+        assert_eq!(
+            ranges(&format!("{} a BETWEEN 1 AND 5 AND a > 3", from)),
+            bounds(Bound::Excluded(3), Bound::Included(5))
+        );
+        assert_eq!(
+            ranges(&format!("{} a IN (1, 3, 2) AND a != 2", from)),
+            RangeSet::point(Datum::from(1)).union(&RangeSet::point(Datum::from(3)))
+        );
+        assert_eq!(
+            ranges(&format!("{} (a < 1 OR a > 5) AND a < 3", from)),
+            bounds(Bound::Unbounded, Bound::Excluded(1))
+        );
+        assert_eq!(
+            ranges(&format!("{} a = 1 AND a = 2", from)),
+            RangeSet::empty()
+        );
+        // Things we can't make sense of shouldn't restrict the range
+        assert!(ranges(&format!("{} a = 1 OR b = 2", from)).is_all());
+        assert!(ranges(&format!("{} a + 1 = 2", from)).is_all());
+        assert!(ranges(&format!("{} NOT (a = 2)", from)).is_all());
+    }
+}
@@ -0,0 +1,235 @@
+use crate::utils::expr::decompose_predicate;
+use crate::utils::ranges::{ranges_for_column, RangeSet};
+use ast::expr::Expression;
+use ast::rel::logical::{JoinType, LogicalOperator, ResolvedTable};
+use catalog::{Catalog, ColumnStats};
+use data::rust_decimal::prelude::ToPrimitive;
+use data::Datum;
+use std::ops::Bound;
+
+/// The fraction of rows a predicate is assumed to let through when there's no stats to go on
+const DEFAULT_SELECTIVITY: f64 = 0.25;
+
+/// Estimates how many rows the operator returns from the stats gathered by ANALYZE, None if
+/// it reads anything that hasn't been analyzed. Joins are assumed to be along foreign keys so
+/// return as many rows as their larger input.
+pub(crate) fn estimate_rows(operator: &LogicalOperator, catalog: &Catalog) -> Option<f64> {
+    match operator {
+        LogicalOperator::ResolvedTable(table) => {
+            let rows = table_stats(table, catalog)
+                .into_iter()
+                .flatten()
+                .map(|stats| stats.rows as f64)
+                .next()?;
+            match &table.sample {
+                Some(sample) => Some(rows * sample.percentage.to_f64()? / 100.0),
+                None => Some(rows),
+            }
+        }
+        LogicalOperator::Filter(filter) => {
+            let rows = estimate_rows(&filter.source, catalog)?;
+            let stats = column_stats(&filter.source, catalog);
+            Some(rows * selectivity(&filter.predicate, &stats))
+        }
+        LogicalOperator::GroupBy(group_by) if group_by.key_expressions.is_empty() => Some(1.0),
+        LogicalOperator::GroupBy(group_by) => estimate_rows(&group_by.source, catalog),
+        LogicalOperator::Limit(limit) => {
+            estimate_rows(&limit.source, catalog).map(|rows| rows.min(limit.limit as f64))
+        }
+        LogicalOperator::Join(join) => {
+            let left = estimate_rows(&join.left, catalog)?;
+            let right = estimate_rows(&join.right, catalog)?;
+            match join.join_type {
+                JoinType::Inner | JoinType::LeftOuter | JoinType::RightOuter => {
+                    Some(left.max(right))
+                }
+                // Every row from both sides can make it out unmatched
+                JoinType::FullOuter => Some(left + right),
+                JoinType::LeftSemi | JoinType::LeftAnti | JoinType::LeftSingle => Some(left),
+            }
+        }
+        LogicalOperator::UnionAll(union_all) => union_all
+            .sources
+            .iter()
+            .map(|source| estimate_rows(source, catalog))
+            .sum(),
+        LogicalOperator::Project(project) => estimate_rows(&project.source, catalog),
+        LogicalOperator::Sort(sort) => estimate_rows(&sort.source, catalog),
+        LogicalOperator::TableAlias(table_alias) => estimate_rows(&table_alias.source, catalog),
+        LogicalOperator::NegateFreq(source) => estimate_rows(source, catalog),
+        _ => None,
+    }
+}
+
+/// The stats for each of the operator's output columns, only tables (and filters and aliases
+/// over them) pass their columns' stats on.
+fn column_stats(operator: &LogicalOperator, catalog: &Catalog) -> Vec<Option<ColumnStats>> {
+    match operator {
+        LogicalOperator::ResolvedTable(table) => table_stats(table, catalog),
+        LogicalOperator::Filter(filter) => column_stats(&filter.source, catalog),
+        LogicalOperator::TableAlias(table_alias) => column_stats(&table_alias.source, catalog),
+        _ => vec![],
+    }
+}
+
+fn table_stats(table: &ResolvedTable, catalog: &Catalog) -> Vec<Option<ColumnStats>> {
+    catalog
+        .column_stats(table.table.id(), &table.columns)
+        .unwrap_or_default()
+}
+
+/// Estimates the fraction of rows the predicate is true for, the predicate's conjuncts are
+/// assumed to be independent of each other.
+pub(crate) fn selectivity(predicate: &Expression, stats: &[Option<ColumnStats>]) -> f64 {
+    decompose_predicate(predicate.clone())
+        .map(|conjunct| conjunct_selectivity(&conjunct, stats))
+        .product()
+}
+
+fn conjunct_selectivity(conjunct: &Expression, stats: &[Option<ColumnStats>]) -> f64 {
+    if let Some((column, negated)) = null_check(conjunct) {
+        if let Some(Some(column_stats)) = stats.get(column) {
+            let null_fraction = column_stats.null_fraction.to_f64().unwrap_or_default();
+            return if negated {
+                1.0 - null_fraction
+            } else {
+                null_fraction
+            };
+        }
+    }
+
+    // Comparisons against constants narrow the column down to a set of ranges which we can
+    // look up in the histogram
+    for (column, column_stats) in stats.iter().enumerate() {
+        if let Some(column_stats) = column_stats {
+            let ranges = ranges_for_column(conjunct, column);
+            if !ranges.is_all() {
+                return range_selectivity(column_stats, &ranges);
+            }
+        }
+    }
+    DEFAULT_SELECTIVITY
+}
+
+/// If the expression is "column IS [NOT] NULL" returns the column and whether it's negated
+fn null_check(expression: &Expression) -> Option<(usize, bool)> {
+    if let Expression::CompiledFunctionCall(function) = expression {
+        match (function.signature.name, &function.args[..]) {
+            ("isnull", [Expression::CompiledColumnReference(column_reference)]) => {
+                return Some((column_reference.offset, false))
+            }
+            ("not", [inner]) => return null_check(inner).map(|(column, _)| (column, true)),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The fraction of the column's rows that fall in the ranges, points are assumed to hold an
+/// even share of the rows while ranges are measured in histogram buckets.
+fn range_selectivity(stats: &ColumnStats, ranges: &RangeSet) -> f64 {
+    let non_null = 1.0 - stats.null_fraction.to_f64().unwrap_or_default();
+    let histogram = &stats.histogram;
+    if histogram.len() < 2 {
+        return non_null * DEFAULT_SELECTIVITY;
+    }
+    let (min, max) = (&histogram[0], &histogram[histogram.len() - 1]);
+
+    let fraction: f64 = ranges
+        .intervals()
+        .iter()
+        .map(|interval| match (&interval.lower, &interval.upper) {
+            (Bound::Included(lower), Bound::Included(upper)) if lower == upper => {
+                if lower < min || lower > max {
+                    0.0
+                } else {
+                    1.0 / stats.distinct.max(1) as f64
+                }
+            }
+            (lower, upper) => (histogram_position(histogram, upper, 1.0)
+                - histogram_position(histogram, lower, 0.0))
+            .max(0.0),
+        })
+        .sum();
+    non_null * fraction.min(1.0)
+}
+
+/// Roughly how far through the column's values the bound sits, values within a bucket are
+/// placed in the middle of it.
+fn histogram_position(
+    histogram: &[Datum<'static>],
+    bound: &Bound<Datum<'static>>,
+    unbounded: f64,
+) -> f64 {
+    match bound {
+        Bound::Unbounded => unbounded,
+        Bound::Included(datum) | Bound::Excluded(datum) => {
+            let below = histogram.iter().filter(|bucket| *bucket < datum).count();
+            if below == 0 {
+                0.0
+            } else if below == histogram.len() {
+                1.0
+            } else {
+                (below as f64 - 0.5) / (histogram.len() - 1) as f64
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Planner;
+    use data::{DataType, Session};
+
+    /// Returns the predicate of the where clause
+    fn predicate(sql: &str) -> Expression {
+        let planner = Planner::new_for_test();
+        let session = Session::new(1);
+        let query = if let ast::statement::Statement::Query(query) = parser::parse(sql).unwrap() {
+            query
+        } else {
+            panic!()
+        };
+        let query = planner.validate(query, &session).unwrap();
+        if let LogicalOperator::Project(project) = query {
+            if let LogicalOperator::Filter(filter) = *project.source {
+                return filter.predicate;
+            }
+        }
+        panic!()
+    }
+
+    #[test]
+    fn test_selectivity() {
+        // 1 to 100 with another 25 rows of nulls
+        let mut values: Vec<_> = (1..=100).map(|i| (Datum::from(i), 1)).collect();
+        values.insert(0, (Datum::Null, 25));
+        let stats = vec![Some(ColumnStats::from_sorted_values(
+            DataType::Integer,
+            &values,
+        ))];
+        let estimate = |condition: &str| {
+            let sql = format!("SELECT * FROM (SELECT 1 as a) t WHERE {}", condition);
+            selectivity(&predicate(&sql), &stats)
+        };
+
+        assert_eq!(estimate("a IS NULL"), 0.2);
+        assert_eq!(estimate("a IS NOT NULL"), 0.8);
+        assert!((estimate("a = 5") - 0.008).abs() < 0.0001);
+        assert_eq!(estimate("a = 500"), 0.0);
+        let half = estimate("a < 50");
+        assert!(half > 0.3 && half < 0.5, "{}", half);
+        let small = estimate("a < 3");
+        assert!(small < 0.05, "{}", small);
+        assert!((estimate("a = 5 AND a IS NOT NULL") - 0.0064).abs() < 0.0001);
+        // Without any stats we fall back to a guess
+        assert_eq!(
+            selectivity(
+                &predicate("SELECT * FROM (SELECT 1 as a) t WHERE a = 5"),
+                &[None]
+            ),
+            DEFAULT_SELECTIVITY
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use crate::utils::expr::{mark_used, move_column_references};
+use crate::utils::logical::fieldnames_for_operator;
+use ast::expr::Expression;
+use ast::rel::logical::LogicalOperator;
+
+/// Walks down the tree keeping track of which of the operator's output columns are
+/// actually used by the operators above. Visit is called with each operator and its used
+/// columns on the way down, it can swap the operator for one with fewer output columns by
+/// returning the columns it removed as (offset, count) ranges. Returns the columns removed from
+/// the operator's output, the column references above are moved down past them.
+pub(crate) fn walk_with_usage<F>(
+    operator: &mut LogicalOperator,
+    mut used: Vec<bool>,
+    visit: &mut F,
+) -> Vec<(usize, usize)>
+where
+    F: FnMut(&mut LogicalOperator, &mut Vec<bool>) -> Vec<(usize, usize)>,
+{
+    let mut removed = visit(operator, &mut used);
+    if !removed.is_empty() {
+        removed.extend(walk_with_usage(operator, used, visit));
+        return removed;
+    }
+
+    match operator {
+        LogicalOperator::Project(project) => {
+            let mut used = vec![false; fieldnames_for_operator(&project.source).count()];
+            for ne in &project.expressions {
+                mark_used(&ne.expression, &mut used);
+            }
+            let removed = walk_with_usage(&mut project.source, used, visit);
+            for ne in &mut project.expressions {
+                remove_columns(&mut ne.expression, &removed);
+            }
+            vec![]
+        }
+        LogicalOperator::GroupBy(group_by) => {
+            let mut used = vec![false; fieldnames_for_operator(&group_by.source).count()];
+            for ne in &group_by.expressions {
+                mark_used(&ne.expression, &mut used);
+            }
+            for expr in &group_by.key_expressions {
+                mark_used(expr, &mut used);
+            }
+            let removed = walk_with_usage(&mut group_by.source, used, visit);
+            for ne in &mut group_by.expressions {
+                remove_columns(&mut ne.expression, &removed);
+            }
+            for expr in &mut group_by.key_expressions {
+                remove_columns(expr, &removed);
+            }
+            vec![]
+        }
+        LogicalOperator::Filter(filter) => {
+            mark_used(&filter.predicate, &mut used);
+            let removed = walk_with_usage(&mut filter.source, used, visit);
+            remove_columns(&mut filter.predicate, &removed);
+            removed
+        }
+        LogicalOperator::Sort(sort) => {
+            for se in &sort.sort_expressions {
+                mark_used(&se.expression, &mut used);
+            }
+            let removed = walk_with_usage(&mut sort.source, used, visit);
+            for se in &mut sort.sort_expressions {
+                remove_columns(&mut se.expression, &removed);
+            }
+            removed
+        }
+        LogicalOperator::Limit(limit) => walk_with_usage(&mut limit.source, used, visit),
+        LogicalOperator::TableAlias(table_alias) => {
+            walk_with_usage(&mut table_alias.source, used, visit)
+        }
+        LogicalOperator::NegateFreq(source) => walk_with_usage(source, used, visit),
+        LogicalOperator::Join(join) => {
+            let output_len = used.len();
+            let left_len = fieldnames_for_operator(&join.left).count();
+            let right_len = fieldnames_for_operator(&join.right).count();
+            // Semi/anti joins don't output the right side but the join condition will still
+            // reference it.
+            used.resize(left_len + right_len, false);
+            mark_used(&join.on, &mut used);
+            let right_used = used.split_off(left_len);
+            let mut removed = walk_with_usage(&mut join.left, used, visit);
+            removed.extend(
+                walk_with_usage(&mut join.right, right_used, visit)
+                    .into_iter()
+                    .map(|(offset, count)| (offset + left_len, count)),
+            );
+            remove_columns(&mut join.on, &removed);
+            removed.retain(|(offset, _count)| *offset < output_len);
+            removed
+        }
+        // For anything else we'll play it safe and assume everything is used.
+        _ => {
+            for child in operator.children_mut() {
+                let used = vec![true; fieldnames_for_operator(child).count()];
+                walk_with_usage(child, used, visit);
+            }
+            vec![]
+        }
+    }
+}
+
+/// Moves the column references down past the removed columns, none of the references point
+/// at the removed columns themselves as they weren't used.
+fn remove_columns(expression: &mut Expression, removed: &[(usize, usize)]) {
+    if let Expression::CompiledColumnReference(column_reference) = expression {
+        let offset = column_reference.offset;
+        let removed_before: usize = removed
+            .iter()
+            .filter(|(removed_offset, _count)| *removed_offset < offset)
+            .map(|(_offset, count)| count)
+            .sum();
+        move_column_references(expression, -(removed_before as isize));
+    } else {
+        for expr in expression.children_mut() {
+            remove_columns(expr, removed);
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use crate::workload::WorkloadGuard;
+use crate::QueryError;
+use data::{Datum, Priority, Session, TupleIter};
+use executor::point_in_time::BoxedExecutor;
+use executor::ExecutionError;
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+/// How many statements can run at once and how many more can queue waiting for their turn,
+/// statements arriving when the queue's full are turned away.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct AdmissionLimits {
+    // None means there's no limit and nothing ever queues
+    pub max_concurrent: Option<usize>,
+    pub max_queued: usize,
+}
+
+impl Default for AdmissionLimits {
+    fn default() -> Self {
+        AdmissionLimits {
+            max_concurrent: None,
+            max_queued: 64,
+        }
+    }
+}
+
+// How often queued statements check whether their connection's been killed
+const KILL_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Hands out permits to run statements, a statement holds its permit until its results have
+/// been read.
+#[derive(Debug)]
+pub(crate) struct Admission {
+    state: Mutex<AdmissionState>,
+    changed: Condvar,
+}
+
+#[derive(Debug)]
+struct AdmissionState {
+    limits: AdmissionLimits,
+    running: usize,
+    // The queued statements' tickets, highest priority first then in the order they arrived
+    queue: BTreeSet<(Reverse<Priority>, u64)>,
+    next_ticket: u64,
+}
+
+impl AdmissionState {
+    fn has_capacity(&self) -> bool {
+        match self.limits.max_concurrent {
+            Some(max_concurrent) => self.running < max_concurrent,
+            None => true,
+        }
+    }
+}
+
+impl Admission {
+    pub(crate) fn new(limits: AdmissionLimits) -> Self {
+        Admission {
+            state: Mutex::new(AdmissionState {
+                limits,
+                running: 0,
+                queue: BTreeSet::new(),
+                next_ticket: 0,
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    pub(crate) fn set_limits(&self, limits: AdmissionLimits) {
+        self.state.lock().unwrap().limits = limits;
+        self.changed.notify_all();
+    }
+
+    /// Waits for a permit to run a statement for the session, statements already queued
+    /// with the same or a higher priority go first.
+    pub(crate) fn admit(
+        self: &Arc<Self>,
+        session: &Session,
+    ) -> Result<AdmissionPermit, QueryError> {
+        let mut state = self.state.lock().unwrap();
+        if state.queue.is_empty() && state.has_capacity() {
+            state.running += 1;
+            return Ok(AdmissionPermit {
+                admission: Arc::clone(self),
+            });
+        }
+        if state.queue.len() >= state.limits.max_queued {
+            return Err(QueryError::AdmissionQueueFull(state.limits.max_queued));
+        }
+
+        let ticket = (
+            Reverse(*session.priority.read().unwrap()),
+            state.next_ticket,
+        );
+        state.next_ticket += 1;
+        state.queue.insert(ticket);
+        loop {
+            if state.queue.iter().next() == Some(&ticket) && state.has_capacity() {
+                state.queue.remove(&ticket);
+                state.running += 1;
+                // There may be room for the next in line too
+                self.changed.notify_all();
+                return Ok(AdmissionPermit {
+                    admission: Arc::clone(self),
+                });
+            }
+            if session.kill_flag.load(Ordering::Relaxed) {
+                state.queue.remove(&ticket);
+                self.changed.notify_all();
+                return Err(QueryError::ExecutionError(ExecutionError::Killed));
+            }
+            state = self
+                .changed
+                .wait_timeout(state, KILL_CHECK_INTERVAL)
+                .unwrap()
+                .0;
+        }
+    }
+}
+
+/// Allows a statement to run, the slot is given up when this is dropped.
+#[derive(Debug)]
+pub(crate) struct AdmissionPermit {
+    admission: Arc<Admission>,
+}
+
+impl Drop for AdmissionPermit {
+    fn drop(&mut self) {
+        self.admission.state.lock().unwrap().running -= 1;
+        self.admission.changed.notify_all();
+    }
+}
+
+/// Wraps the statement's executor so its permit is held, and its time accounted for, until the
+/// results have been read. Any cast that failed under strict_casts, or decimal that overflowed,
+/// fails the statement.
+pub(crate) fn admitted(
+    executor: BoxedExecutor,
+    session: Arc<Session>,
+    permit: AdmissionPermit,
+    workload: WorkloadGuard,
+) -> BoxedExecutor {
+    Box::from(AdmittedExecutor {
+        executor,
+        session,
+        _permit: permit,
+        _workload: workload,
+    })
+}
+
+struct AdmittedExecutor {
+    executor: BoxedExecutor,
+    session: Arc<Session>,
+    _permit: AdmissionPermit,
+    _workload: WorkloadGuard,
+}
+
+impl TupleIter for AdmittedExecutor {
+    type E = ExecutionError;
+
+    fn advance(&mut self) -> Result<(), Self::E> {
+        self.executor.advance()?;
+        if let Some(err) = self.session.take_cast_error() {
+            return Err(ExecutionError::CastError(err));
+        }
+        Ok(())
+    }
+
+    fn get(&self) -> Option<(&[Datum], i64)> {
+        self.executor.get()
+    }
+
+    fn column_count(&self) -> usize {
+        self.executor.column_count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    fn limited(max_concurrent: usize, max_queued: usize) -> Arc<Admission> {
+        Arc::new(Admission::new(AdmissionLimits {
+            max_concurrent: Some(max_concurrent),
+            max_queued,
+        }))
+    }
+
+    fn wait_for_queue(admission: &Admission, len: usize) {
+        while admission.state.lock().unwrap().queue.len() != len {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_unlimited() -> Result<(), QueryError> {
+        let admission = Arc::new(Admission::new(AdmissionLimits::default()));
+        let session = Session::new(1);
+        let _permits = (0..100)
+            .map(|_| admission.admit(&session))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_queue_full() -> Result<(), QueryError> {
+        let admission = limited(1, 0);
+        let session = Session::new(1);
+        let permit = admission.admit(&session)?;
+        assert!(matches!(
+            admission.admit(&session),
+            Err(QueryError::AdmissionQueueFull(0))
+        ));
+        std::mem::drop(permit);
+        admission.admit(&session)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_priority_order() -> Result<(), QueryError> {
+        let admission = limited(1, 10);
+        let permit = admission.admit(&Session::new(1))?;
+
+        let (sender, receiver) = channel();
+        let mut threads = vec![];
+        for (queued, priority) in [Priority::Low, Priority::Normal, Priority::High]
+            .iter()
+            .enumerate()
+        {
+            let queued_admission = Arc::clone(&admission);
+            let sender = sender.clone();
+            let priority = *priority;
+            threads.push(thread::spawn(move || {
+                let session = Session::new(2);
+                *session.priority.write().unwrap() = priority;
+                let _permit = queued_admission.admit(&session).unwrap();
+                sender.send(priority).unwrap();
+            }));
+            wait_for_queue(&admission, queued + 1);
+        }
+
+        std::mem::drop(permit);
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<_>>(),
+            vec![Priority::High, Priority::Normal, Priority::Low]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_killed_while_queued() -> Result<(), QueryError> {
+        let admission = limited(1, 10);
+        let _permit = admission.admit(&Session::new(1))?;
+        let session = Session::new(2);
+        session.kill_flag.store(true, Ordering::Relaxed);
+        assert!(matches!(
+            admission.admit(&session),
+            Err(QueryError::ExecutionError(ExecutionError::Killed))
+        ));
+        assert!(admission.state.lock().unwrap().queue.is_empty());
+        Ok(())
+    }
+}
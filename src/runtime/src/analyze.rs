@@ -0,0 +1,75 @@
+use crate::workload::Scheduler;
+use crate::QueryError;
+use catalog::{CatalogError, ColumnStats, TableOrView};
+use data::{Datum, LogicalTimestamp, TupleIter};
+use planner::Planner;
+use std::collections::BTreeMap;
+
+/// Reads through the table working out each column's null fraction, distinct values and
+/// histogram, replacing any stats gathered for it before.
+pub(crate) fn analyze_table(
+    planner: &Planner,
+    scheduler: &Scheduler,
+    database: &str,
+    name: &str,
+) -> Result<(), QueryError> {
+    let item = planner.catalog.read().unwrap().item(database, name)?;
+    let table = match item.item {
+        TableOrView::Table(table) => table,
+        TableOrView::View(_) => {
+            return Err(CatalogError::NotATable(database.to_string(), name.to_string()).into())
+        }
+    };
+
+    // Each column's distinct values and how many times they appear, kept sorted for the
+    // histograms
+    let mut values: Vec<BTreeMap<Datum<'static>, i64>> = vec![BTreeMap::new(); item.columns.len()];
+    let mut yielder = scheduler.yielder();
+    let mut iter = table.full_scan(LogicalTimestamp::MAX);
+    while let Some((tuple, freq)) = iter.next()? {
+        yielder.tick();
+        for (column_values, datum) in values.iter_mut().zip(tuple) {
+            *column_values.entry(datum.as_static()).or_insert(0) += freq;
+        }
+    }
+
+    let stats: Vec<_> = item
+        .columns
+        .iter()
+        .zip(values)
+        .map(|((alias, datatype), column_values)| {
+            let column_values: Vec<_> = column_values.into_iter().collect();
+            (
+                alias.clone(),
+                ColumnStats::from_sorted_values(*datatype, &column_values),
+            )
+        })
+        .collect();
+    planner
+        .catalog
+        .write()
+        .unwrap()
+        .set_column_stats(database, name, table.id(), &stats)?;
+    Ok(())
+}
+
+/// Re-analyzes the tables that have had more than threshold_percent of their rows changed
+/// since they were last analyzed, tables that have never been analyzed are left alone.
+pub(crate) fn auto_analyze(
+    planner: &Planner,
+    scheduler: &Scheduler,
+    threshold_percent: u32,
+) -> Result<(), QueryError> {
+    if threshold_percent == 0 {
+        return Ok(());
+    }
+    let stale = planner
+        .catalog
+        .read()
+        .unwrap()
+        .stale_tables(threshold_percent)?;
+    for (database, name) in stale {
+        analyze_table(planner, scheduler, &database, &name)?;
+    }
+    Ok(())
+}
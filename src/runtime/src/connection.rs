@@ -1,13 +1,46 @@
+use crate::admission::admitted;
+use crate::analyze::{analyze_table, auto_analyze};
+use crate::infer_schema::{
+    external_table_sql, given_columns, keyed_columns, InferredColumn, SchemaInferrer,
+};
+use crate::materialized_view::{refresh_materialized_view, view_query};
+use crate::procedure::run_procedure;
+use crate::schema_migration::modify_column;
+use crate::sink::flush_sink;
+use crate::storage_tier::{move_to_cold, move_to_hot};
 use crate::{QueryError, Runtime};
-use ast::expr::Expression;
-use ast::rel::logical::{LogicalOperator, Values};
-use ast::statement::Statement;
-use catalog::TableOrView;
-use data::{empty_tuple_iter, DataType, Session};
-use executor::point_in_time::{build_executor, BoxedExecutor};
-use parser::parse;
-use planner::Field;
-use std::sync::Arc;
+use ast::expr::{Cast, Expression, NamedExpression};
+use ast::rel::logical::{
+    FileFormat, FileScan, Filter, Limit, LogicalOperator, Project, SerdeOptions, TableReference,
+    Values,
+};
+use ast::rel::point_in_time::PointInTimeOperator;
+use ast::statement::{
+    AlterTableColumnMask, AlterTableColumnSchema, ExternalColumns, Grant, GrantObject, SinkTarget,
+    Statement, StorageTier,
+};
+use catalog::{
+    corrections_table_name, is_superuser, CatalogError, Event, LateData, Policy, Privilege, Sink,
+    StateTtl, TableOptions, TableOrView, WorkloadClass,
+};
+use data::chrono::FixedOffset;
+use data::json::OwnedJson;
+use data::json_schema::check_schema;
+use data::{
+    empty_tuple_iter, is_utf8_charset, DataType, Datum, DecimalOverflow, InvalidUtf8, Locale,
+    LogicalTimestamp, Priority, Session, Warning, DEFAULT_MAX_INTERMEDIATE_ROWS,
+    DEFAULT_MAX_RECURSION_DEPTH, DEFAULT_MAX_VALUE_BYTES, WARN_GENERIC,
+};
+use executor::point_in_time::{
+    build_compared_executor, build_executor, build_transaction_executor,
+    build_type_checked_executor, parquet_schema, BoxedExecutor,
+};
+use parser::{parse, parse_procedure_body};
+use planner::{Field, PointInTimePlan, OPTIMIZER_RULES};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use storage::Transaction;
 
 /// Represents a connection to the database.  Note this is the logical connection, not the physical
 /// tcp connection.
@@ -16,21 +49,205 @@ pub struct Connection<'a> {
     pub connection_id: u32,
     pub session: Arc<Session>,
     pub runtime: &'a Runtime,
+    // Set between BEGIN and COMMIT/ROLLBACK
+    pub(crate) transaction: Mutex<Option<Transaction>>,
+    // The number of procedure calls in progress, ie procedures calling procedures
+    pub(crate) procedure_depth: AtomicU32,
 }
 
 impl Drop for Connection<'_> {
     fn drop(&mut self) {
+        let session = &self.session;
+        self.runtime.fire_hooks(|hook| hook.on_disconnect(session));
         self.runtime.remove_connection(self.connection_id);
     }
 }
 
+/// The fields and rows from executing a statement.
+pub type QueryResult = Result<(Vec<Field>, BoxedExecutor), QueryError>;
+
+/// A statement that's been parsed and validated ahead of time, to be executed later with
+/// its parameters bound.
+#[derive(Debug)]
+pub struct PreparedStatement {
+    pub fields: Vec<Field>,
+    pub parameter_types: Vec<DataType>,
+    sql: String,
+    statement: Statement,
+}
+
 impl Connection<'_> {
-    pub fn execute_statement(
-        &self,
-        query: &str,
-    ) -> Result<(Vec<Field>, BoxedExecutor), QueryError> {
+    /// Logs the user in, their stored session defaults are applied before the runtime's
+    /// on_connect hooks are told about the connection.
+    pub fn login(&self, user: &str) -> Result<(), QueryError> {
+        *self.session.user.write().unwrap() = user.to_string();
+        let defaults = self
+            .runtime
+            .planner
+            .catalog
+            .read()
+            .unwrap()
+            .user_defaults(user)?;
+        for (name, value) in defaults {
+            set_variable(&self.session, &name, &value)?;
+        }
+        let session = &self.session;
+        self.runtime.fire_hooks(|hook| hook.on_connect(session));
+        Ok(())
+    }
+
+    pub fn execute_statement(&self, query: &str) -> QueryResult {
+        self.statement_received(query);
+        let parse_tree = parse(query)?;
+        self.execute_admitted(parse_tree)
+    }
+
+    /// Kills the connection, users can kill their own connections, only root can kill anyone's.
+    /// The same connections are visible to SHOW PROCESSLIST.
+    pub fn kill(&self, connection_id: u32) -> Result<(), QueryError> {
+        let user = self.session.user.read().unwrap().clone();
+        let session = self
+            .runtime
+            .sessions()
+            .into_iter()
+            .find(|session| session.connection_id == connection_id)
+            .ok_or(QueryError::UnknownConnection(connection_id))?;
+        if !is_superuser(&user) && *session.user.read().unwrap() != user {
+            return Err(QueryError::KillNotAllowed(connection_id));
+        }
+        self.runtime.kill_connection(connection_id);
+        Ok(())
+    }
+
+    /// Policies and masks restrict what everyone but the table's owner sees, so only the owner
+    /// (or root) can change them. Tables from before owners were recorded are root's.
+    fn check_table_owner(&self, database: &str, table: &str) -> Result<(), QueryError> {
+        let user = self.session.user.read().unwrap().clone();
+        if is_superuser(&user) {
+            return Ok(());
+        }
+        let owner = self
+            .runtime
+            .planner
+            .catalog
+            .read()
+            .unwrap()
+            .table_owner(database, table)?;
+        if owner.as_deref() != Some(user.as_str()) {
+            return Err(QueryError::NotTableOwner(
+                user,
+                database.to_string(),
+                table.to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Records the statement for SHOW PROCESSLIST and tells the hooks about it
+    fn statement_received(&self, sql: &str) {
+        *self.session.statement.write().unwrap() = sql.to_string();
+        let session = &self.session;
+        self.runtime
+            .fire_hooks(|hook| hook.on_statement(session, sql));
+    }
+
+    /// Runs sql on behalf of another statement, ie SHOW TABLES, so hooks only see the original
+    fn execute_sql(&self, query: &str) -> QueryResult {
         let parse_tree = parse(query)?;
+        self.execute_parsed_statement(parse_tree)
+    }
 
+    /// Parses the statement and works out the types of its parameters, for anything other
+    /// than a query we won't know the fields until it's run.
+    pub fn prepare_statement(&self, query: &str) -> Result<PreparedStatement, QueryError> {
+        let statement = parse(query)?;
+        let (fields, parameter_types) = if let Statement::Query(query) = &statement {
+            self.runtime
+                .planner
+                .plan_for_prepare(query.clone(), &self.session)?
+        } else {
+            let mut parameter_count = 0;
+            statement
+                .clone()
+                .visit_parameters_mut(&mut |_| parameter_count += 1);
+            if parameter_count != 0 {
+                return Err(QueryError::ParametersNotSupported);
+            }
+            (vec![], vec![])
+        };
+
+        Ok(PreparedStatement {
+            fields,
+            parameter_types,
+            sql: query.to_string(),
+            statement,
+        })
+    }
+
+    /// Executes a prepared statement, the parameters are converted to the types inferred
+    /// when the statement was prepared.
+    pub fn execute_prepared_statement(
+        &self,
+        prepared_statement: &PreparedStatement,
+        parameters: &[(Datum<'static>, DataType)],
+    ) -> QueryResult {
+        let parameter_types = &prepared_statement.parameter_types;
+        if parameters.len() != parameter_types.len() {
+            return Err(QueryError::ParameterCountMismatch(
+                parameter_types.len(),
+                parameters.len(),
+            ));
+        }
+
+        self.statement_received(&prepared_statement.sql);
+        let mut statement = prepared_statement.statement.clone();
+        statement.visit_parameters_mut(&mut |expr| {
+            if let Expression::Parameter(parameter) = expr {
+                let (datum, datatype) = &parameters[parameter.index];
+                let target_type = parameter_types[parameter.index];
+                *expr = if datum.is_null() || *datatype == target_type {
+                    Expression::Constant(datum.clone(), target_type)
+                } else {
+                    Expression::Cast(Cast {
+                        expr: Box::new(Expression::Constant(datum.clone(), *datatype)),
+                        datatype: target_type,
+                        try_cast: false,
+                    })
+                };
+            }
+        });
+        self.execute_admitted(statement)
+    }
+
+    /// Runs the statement once it's admitted, the statement holds its admission and is
+    /// accounted to its workload class until its results have been read.
+    fn execute_admitted(&self, statement: Statement) -> QueryResult {
+        let class = match statement {
+            Statement::CreateMaterializedView(_)
+            | Statement::RefreshMaterializedView(_)
+            | Statement::CreateSink(_)
+            | Statement::FlushSink(_)
+            | Statement::AlterTableModifyColumn(_)
+            | Statement::AnalyzeTable(_)
+            | Statement::Vacuum
+            | Statement::CallProcedure(_) => WorkloadClass::Maintenance,
+            _ => WorkloadClass::Interactive,
+        };
+        let permit = self.runtime.admission.admit(&self.session)?;
+        let workload = self.runtime.scheduler.start(class);
+        let (fields, executor) = self.execute_parsed_statement(statement)?;
+        Ok((
+            fields,
+            admitted(executor, Arc::clone(&self.session), permit, workload),
+        ))
+    }
+
+    fn execute_parsed_statement(&self, parse_tree: Statement) -> QueryResult {
+        // Show warnings reports on the previous statement so has to run before we reset them
+        if let Statement::ShowWarnings = parse_tree {
+            return self.show_warnings();
+        }
+        self.session.start_statement();
         // For almost everything we'll rewrite into some kinda logical operator
         let logical_operator = match parse_tree {
             Statement::ShowFunctions => {
@@ -48,24 +265,212 @@ impl Connection<'_> {
                 })
             }
             Statement::ShowDatabases => {
-                return self.execute_statement("SELECT name as database FROM incresql.databases")
+                return self.execute_sql("SELECT name as database FROM incresql.databases")
             }
+            Statement::ShowWarnings => unreachable!(),
+            Statement::ShowProcesslist => self.show_processlist(),
             Statement::ShowTables => {
-                return self.execute_statement(
+                return self.execute_sql(
                     "SELECT name as table FROM incresql.tables WHERE database_name = database()",
                 );
             }
+            Statement::Kill(connection_id) => {
+                self.kill(connection_id)?;
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::Begin => {
+                // Like mysql a BEGIN in the middle of a transaction commits it first
+                let mut transaction = self.transaction.lock().unwrap();
+                if let Some(transaction) = transaction.take() {
+                    transaction.commit()?;
+                }
+                *transaction = Some(Transaction::default());
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::Commit => {
+                if let Some(transaction) = self.transaction.lock().unwrap().take() {
+                    transaction.commit()?;
+                }
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::Rollback => {
+                self.transaction.lock().unwrap().take();
+                return Ok((vec![], empty_tuple_iter()));
+            }
             Statement::UseDatabase(database) => {
                 *self.session.current_database.write().unwrap() = database;
                 return Ok((vec![], empty_tuple_iter()));
             }
-            Statement::Query(logical_operator) => logical_operator,
+            Statement::SetVariable(variable) => {
+                set_variable(&self.session, &variable.name, &variable.value)?;
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::SetUserVariable(set_user_variable) => {
+                let name = set_user_variable.name;
+                let (fields, mut executor) =
+                    self.execute_parsed_statement(Statement::Query(set_user_variable.query))?;
+                if fields.len() != 1 {
+                    return Err(QueryError::UserVariableNotScalar(name));
+                }
+                // No rows leaves the variable null, like mysql
+                let value = match executor.next()? {
+                    Some((tuple, 1)) => tuple[0].as_static(),
+                    Some(_) => return Err(QueryError::UserVariableNotScalar(name)),
+                    None => Datum::Null,
+                };
+                if executor.next()?.is_some() {
+                    return Err(QueryError::UserVariableNotScalar(name));
+                }
+                self.session
+                    .user_variables
+                    .write()
+                    .unwrap()
+                    .insert(name, (value, fields[0].data_type));
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::AlterUser(alter_user) => {
+                let current_user = self.session.user.read().unwrap().clone();
+                if !is_superuser(&current_user) && alter_user.user != current_user {
+                    return Err(QueryError::AlterUserNotAllowed(current_user));
+                }
+                // Check the value against a scratch session so bad defaults are caught now
+                // rather than when the user next logs in
+                if let Some(value) = &alter_user.value {
+                    set_variable(&Session::new(0), &alter_user.name, value)?;
+                }
+                let mut catalog = self.runtime.planner.catalog.write().unwrap();
+                catalog.set_user_default(
+                    &alter_user.user,
+                    &alter_user.name.to_lowercase(),
+                    alter_user.value.as_deref(),
+                )?;
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::Grant(Grant {
+                revoke,
+                object,
+                user,
+            }) => {
+                let current_user = self.session.user.read().unwrap().clone();
+                if !is_superuser(&current_user) {
+                    return Err(QueryError::GrantNotAllowed(current_user));
+                }
+                let (object_type, object) = match object {
+                    GrantObject::Directory(directory) => ("DIRECTORY", directory),
+                };
+                let privilege = Privilege {
+                    user,
+                    privilege: "EXECUTE".to_string(),
+                    object_type: object_type.to_string(),
+                    object,
+                };
+                let mut catalog = self.runtime.planner.catalog.write().unwrap();
+                if revoke {
+                    catalog.revoke_privilege(&privilege)?;
+                } else {
+                    catalog.grant_privilege(&privilege)?;
+                }
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::Query(logical_operator) => {
+                self.auto_analyze();
+                let planner = &self.runtime.planner;
+                let logical_operator =
+                    planner.apply_deterministic_order(logical_operator, &self.session);
+                let logical_operator = planner.apply_select_limit(logical_operator, &self.session);
+                if self.session.sample_plan_validation() {
+                    return self.execute_validated(logical_operator);
+                }
+                logical_operator
+            }
             Statement::Explain(explain) => {
+                self.auto_analyze();
                 let (_fields, operator) = self
                     .runtime
                     .planner
                     .plan_common(explain.operator, &self.session)?;
-                self.runtime.planner.explain(&operator)
+                if explain.types {
+                    self.runtime.planner.explain_types(&operator)
+                } else {
+                    self.runtime.planner.explain(&operator)
+                }
+            }
+            Statement::ExplainMaintenance(explain_maintenance) => {
+                let database = explain_maintenance
+                    .database
+                    .unwrap_or_else(|| self.session.current_database.read().unwrap().to_string());
+                let name = explain_maintenance.name;
+                let (item, materialized_view, state_ttl, maintained_at) = {
+                    let catalog = self.runtime.planner.catalog.read().unwrap();
+                    (
+                        catalog.item(&database, &name)?,
+                        catalog.materialized_view(&database, &name).ok(),
+                        catalog.state_ttl(&database, &name)?,
+                        catalog.view_maintained_at(&database, &name)?,
+                    )
+                };
+                let planner = &self.runtime.planner;
+                match (item.item, materialized_view) {
+                    (TableOrView::View(_), _) => {
+                        let query = LogicalOperator::TableReference(TableReference {
+                            database: Some(database),
+                            table: name,
+                            sample: None,
+                            as_of: None,
+                        });
+                        let (_fields, operator) = planner.plan_common(query, &self.session)?;
+                        planner.explain_maintenance(&operator)
+                    }
+                    (TableOrView::Table(_), Some((view, _table))) => {
+                        let (view_session, query) = view_query(&self.session, view)?;
+                        let (_fields, operator) = planner.plan_common(query, &view_session)?;
+                        let mut explain = planner.explain_maintenance(&operator);
+                        // Views that aren't maintained incrementally are recomputed on refresh
+                        let (state, work_per_refresh) = match state_ttl {
+                            _ if maintained_at.is_some() => (
+                                "all rows of the view".to_string(),
+                                "apply the changes to its tables since the last refresh"
+                                    .to_string(),
+                            ),
+                            Some(state_ttl) if state_ttl.late_data == LateData::Drop => (
+                                format!(
+                                    "rows of the view with {} within {}ms of the latest",
+                                    state_ttl.time_column, state_ttl.ttl_ms
+                                ),
+                                "recompute the open rows on refresh".to_string(),
+                            ),
+                            Some(state_ttl) if state_ttl.late_data == LateData::Corrections => (
+                                "all rows of the view".to_string(),
+                                format!(
+                                    "recompute the whole view on refresh, late changes to rows \
+                                     with {} over {}ms behind the latest go to {}",
+                                    state_ttl.time_column,
+                                    state_ttl.ttl_ms,
+                                    corrections_table_name(&name)
+                                ),
+                            ),
+                            _ => (
+                                "all rows of the view".to_string(),
+                                "recompute the whole view on refresh".to_string(),
+                            ),
+                        };
+                        if let LogicalOperator::Values(values) = &mut explain {
+                            values.data.insert(
+                                0,
+                                vec![
+                                    Expression::from(name),
+                                    Expression::from("REFRESH"),
+                                    Expression::from(state),
+                                    Expression::from(work_per_refresh),
+                                ],
+                            );
+                        }
+                        explain
+                    }
+                    (TableOrView::Table(_), None) => {
+                        return Err(QueryError::NotAView(database, name))
+                    }
+                }
             }
             Statement::CreateDatabase(create_database) => {
                 let mut catalog = self.runtime.planner.catalog.write().unwrap();
@@ -83,7 +488,44 @@ impl Connection<'_> {
                     .database
                     .unwrap_or_else(|| self.session.current_database.read().unwrap().to_string());
 
-                catalog.create_table(&database, &create_table.name, &create_table.columns)?;
+                let mut primary_key = None;
+                if let Some(key) = &create_table.primary_key {
+                    let (name, columns) = (&create_table.name, &create_table.columns);
+                    let mut key_columns: Vec<(usize, _)> = Vec::with_capacity(key.len());
+                    for (column, sort_order) in key {
+                        let column_idx = columns
+                            .iter()
+                            .position(|(alias, _)| alias == column)
+                            .ok_or_else(|| {
+                                QueryError::ColumnNotFound(
+                                    database.clone(),
+                                    name.clone(),
+                                    column.clone(),
+                                )
+                            })?;
+                        if key_columns.iter().any(|(idx, _)| *idx == column_idx) {
+                            return Err(QueryError::DuplicateKeyColumn(column.clone()));
+                        }
+                        key_columns.push((column_idx, *sort_order));
+                    }
+                    primary_key = Some(key_columns);
+                }
+                let options = TableOptions {
+                    checksums: create_table.checksums,
+                    null_bitmap: create_table.null_bitmap,
+                    primary_key,
+                };
+                catalog.create_table_with_options(
+                    &database,
+                    &create_table.name,
+                    &create_table.columns,
+                    &options,
+                )?;
+                catalog.set_table_owner(
+                    &database,
+                    &create_table.name,
+                    &self.session.user.read().unwrap(),
+                )?;
                 return Ok((vec![], empty_tuple_iter()));
             }
             Statement::CreateView(create_view) => {
@@ -110,6 +552,147 @@ impl Connection<'_> {
                 )?;
                 return Ok((vec![], empty_tuple_iter()));
             }
+            Statement::CreateMaterializedView(create_view) => {
+                // Views whose rows don't need to close can be maintained from the changes to
+                // their tables as long as their query is linear, or aggregates over a union all
+                // of linear queries in which case each source keeps partial states
+                let planner = &self.runtime.planner;
+                let no_state_ttl = create_view.state_ttl.is_none();
+                let linear = no_state_ttl
+                    && planner
+                        .plan_for_delta(
+                            create_view.query.clone(),
+                            &self.session,
+                            LogicalTimestamp::default(),
+                            LogicalTimestamp::default(),
+                        )?
+                        .is_some();
+                let partial_states = no_state_ttl
+                    && !linear
+                    && planner
+                        .plan_for_partial_aggregate(
+                            create_view.query.clone(),
+                            &self.session,
+                            LogicalTimestamp::default(),
+                            LogicalTimestamp::default(),
+                        )?
+                        .is_some();
+                let incremental = linear || partial_states;
+                let (fields, _operator) = self
+                    .runtime
+                    .planner
+                    .plan_common(create_view.query, &self.session)?;
+                let columns: Vec<_> = fields.into_iter().map(|f| (f.alias, f.data_type)).collect();
+
+                let state_ttl = create_view.state_ttl.map(|state_ttl| StateTtl {
+                    ttl_ms: state_ttl.ttl_ms,
+                    time_column: state_ttl.time_column,
+                    late_data: match state_ttl.late_data {
+                        ast::statement::LateData::Drop => LateData::Drop,
+                        ast::statement::LateData::Corrections => LateData::Corrections,
+                        ast::statement::LateData::Retract => LateData::Retract,
+                    },
+                });
+                if let Some(state_ttl) = &state_ttl {
+                    let is_timestamp = columns.iter().any(|(alias, datatype)| {
+                        alias == &state_ttl.time_column && *datatype == DataType::Timestamp
+                    });
+                    if !is_timestamp {
+                        return Err(QueryError::InvalidTimeColumn(state_ttl.time_column.clone()));
+                    }
+                }
+
+                let current_db = self.session.current_database.read().unwrap().to_string();
+                let database = create_view.database.unwrap_or_else(|| current_db.clone());
+                {
+                    let mut catalog = self.runtime.planner.catalog.write().unwrap();
+                    catalog.create_materialized_view(
+                        &database,
+                        &create_view.name,
+                        &columns,
+                        &create_view.sql,
+                        &current_db,
+                        state_ttl.as_ref(),
+                        partial_states,
+                    )?;
+                    catalog.set_table_owner(
+                        &database,
+                        &create_view.name,
+                        &self.session.user.read().unwrap(),
+                    )?;
+                    // Maintained as of the beginning of time so the first refresh fills it
+                    if incremental {
+                        catalog.set_view_maintained_at(
+                            &database,
+                            &create_view.name,
+                            LogicalTimestamp::default(),
+                        )?;
+                    }
+                }
+                self.refresh_materialized_view(&database, &create_view.name)?;
+                if !incremental {
+                    self.session.add_warning(Warning::new(
+                        WARN_GENERIC,
+                        format!(
+                            "Materialized view {}.{} can't be maintained incrementally, \
+                             use REFRESH MATERIALIZED VIEW to update it",
+                            database, create_view.name
+                        ),
+                    ));
+                }
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::RefreshMaterializedView(refresh) => {
+                let database = refresh
+                    .database
+                    .unwrap_or_else(|| self.session.current_database.read().unwrap().to_string());
+                self.refresh_materialized_view(&database, &refresh.name)?;
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::CreateExternalTable(create_external_table) => {
+                let serde_options = &create_external_table.serde_options;
+                let columns = match &create_external_table.columns {
+                    ExternalColumns::Given(columns) => given_columns(serde_options, columns),
+                    ExternalColumns::Auto(sample_size) => self.infer_columns(
+                        &create_external_table.directory,
+                        serde_options,
+                        *sample_size,
+                    )?,
+                };
+                let name = match &create_external_table.database {
+                    Some(database) => format!("`{}`.`{}`", database, create_external_table.name),
+                    None => format!("`{}`", create_external_table.name),
+                };
+                let sql =
+                    external_table_sql(&create_external_table.directory, serde_options, &columns);
+                return self.execute_sql(&format!("CREATE VIEW {} AS {}", name, sql));
+            }
+            Statement::InferSchema(infer_schema) => {
+                let columns = self.infer_columns(
+                    &infer_schema.directory,
+                    &infer_schema.serde_options,
+                    infer_schema.sample_size,
+                )?;
+                let data = columns
+                    .into_iter()
+                    .map(|column| {
+                        vec![
+                            Expression::from(column.name),
+                            Expression::from(column.datatype.to_string()),
+                            Expression::from(column.path),
+                        ]
+                    })
+                    .collect();
+
+                LogicalOperator::Values(Values {
+                    fields: vec![
+                        (DataType::Text, String::from("column_name")),
+                        (DataType::Text, String::from("data_type")),
+                        (DataType::Text, String::from("path")),
+                    ],
+                    data,
+                })
+            }
             Statement::CompactTable(compact_table) => {
                 let database = compact_table
                     .database
@@ -120,8 +703,338 @@ impl Connection<'_> {
                     catalog.item(&database, &compact_table.name)?
                 };
                 if let TableOrView::Table(table) = item.item {
-                    table.force_rocks_compaction();
+                    table.force_compaction()?;
+                }
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::Vacuum => {
+                self.runtime.vacuum()?;
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::AnalyzeTable(analyze) => {
+                let database = analyze
+                    .database
+                    .unwrap_or_else(|| self.session.current_database.read().unwrap().to_string());
+                analyze_table(
+                    &self.runtime.planner,
+                    &self.runtime.scheduler,
+                    &database,
+                    &analyze.name,
+                )?;
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::AlterSource(alter_source) => {
+                let database = alter_source
+                    .database
+                    .unwrap_or_else(|| self.session.current_database.read().unwrap().to_string());
+                let query = LogicalOperator::TableReference(TableReference {
+                    database: Some(database.clone()),
+                    table: alter_source.name.clone(),
+                    sample: None,
+                    as_of: None,
+                });
+                let (_fields, mut operator) =
+                    self.runtime.planner.plan_common(query, &self.session)?;
+                let mut directories = vec![];
+                file_source_directories(&mut operator, &mut directories);
+                if directories.is_empty() {
+                    return Err(QueryError::NotAFileSource(database, alter_source.name));
+                }
+
+                let mut catalog = self.runtime.planner.catalog.write().unwrap();
+                for directory in directories {
+                    catalog.set_source_paused(&directory, alter_source.paused)?;
+                }
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::AlterTableStorageTier(alter_table) => {
+                let database = alter_table
+                    .database
+                    .unwrap_or_else(|| self.session.current_database.read().unwrap().to_string());
+                let planner = &self.runtime.planner;
+                match alter_table.tier {
+                    StorageTier::Hot => {
+                        move_to_hot(planner, &self.session, &database, &alter_table.name)?
+                    }
+                    StorageTier::Cold(location) => {
+                        planner.check_directory_privilege(&location, &self.session)?;
+                        move_to_cold(planner, &database, &alter_table.name, &location)?
+                    }
+                }
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::AlterTableModifyColumn(alter_table) => {
+                let database = alter_table
+                    .database
+                    .unwrap_or_else(|| self.session.current_database.read().unwrap().to_string());
+                modify_column(
+                    &self.runtime.planner,
+                    &self.runtime.scheduler,
+                    &self.session,
+                    &database,
+                    &alter_table.name,
+                    &alter_table.column,
+                    alter_table.datatype,
+                )?;
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::AlterTableColumnMask(AlterTableColumnMask {
+                database,
+                name,
+                column,
+                mask,
+            }) => {
+                let database = database
+                    .unwrap_or_else(|| self.session.current_database.read().unwrap().to_string());
+                self.check_table_owner(&database, &name)?;
+                let item = self
+                    .runtime
+                    .planner
+                    .catalog
+                    .read()
+                    .unwrap()
+                    .item(&database, &name)?;
+                if let TableOrView::View(_) = item.item {
+                    return Err(CatalogError::NotATable(database, name).into());
+                }
+                if !item.columns.iter().any(|(alias, _)| *alias == column) {
+                    return Err(QueryError::ColumnNotFound(database, name, column));
+                }
+                let mask_sql = match mask {
+                    Some((mask, mask_sql)) => {
+                        // Plan the mask over the table now rather than failing every query later
+                        let query = LogicalOperator::Project(Project {
+                            distinct: false,
+                            expressions: vec![NamedExpression {
+                                alias: None,
+                                expression: mask,
+                            }],
+                            source: Box::new(LogicalOperator::TableReference(TableReference {
+                                database: Some(database.clone()),
+                                table: name.clone(),
+                                sample: None,
+                                as_of: None,
+                            })),
+                        });
+                        self.runtime.planner.plan_common(query, &self.session)?;
+                        Some(mask_sql)
+                    }
+                    None => None,
+                };
+                let mut catalog = self.runtime.planner.catalog.write().unwrap();
+                catalog.set_column_mask(&database, &name, &column, mask_sql.as_deref())?;
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::AlterTableColumnSchema(AlterTableColumnSchema {
+                database,
+                name,
+                column,
+                schema,
+            }) => {
+                let database = database
+                    .unwrap_or_else(|| self.session.current_database.read().unwrap().to_string());
+                let item = self
+                    .runtime
+                    .planner
+                    .catalog
+                    .read()
+                    .unwrap()
+                    .item(&database, &name)?;
+                if let TableOrView::View(_) = item.item {
+                    return Err(CatalogError::NotATable(database, name).into());
+                }
+                match item.columns.iter().find(|(alias, _)| *alias == column) {
+                    None => return Err(QueryError::ColumnNotFound(database, name, column)),
+                    Some((_, DataType::Json)) => {}
+                    Some(_) => return Err(QueryError::NotAJsonColumn(database, name, column)),
+                }
+                if let Some(schema) = &schema {
+                    let json = OwnedJson::parse(schema).ok_or_else(|| {
+                        QueryError::InvalidJsonSchema("not valid json".to_string())
+                    })?;
+                    check_schema(json.as_json()).map_err(QueryError::InvalidJsonSchema)?;
+                }
+                let mut catalog = self.runtime.planner.catalog.write().unwrap();
+                catalog.set_column_schema(&database, &name, &column, schema.as_deref())?;
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::CreateSink(create_sink) => {
+                let path = match create_sink.target {
+                    SinkTarget::File(path) => path,
+                    SinkTarget::Kafka(_) => return Err(QueryError::KafkaSinksNotSupported),
+                };
+                self.runtime
+                    .planner
+                    .check_directory_privilege(&path, &self.session)?;
+                let current_db = self.session.current_database.read().unwrap().to_string();
+                let mut sink = Sink {
+                    database: create_sink.database.unwrap_or_else(|| current_db.clone()),
+                    name: create_sink.name,
+                    source_database: create_sink.source_database.unwrap_or(current_db),
+                    source: create_sink.source,
+                    path,
+                    emitted_at: None,
+                };
+                let _flushing = self.runtime.sink_flushes.lock().unwrap();
+                // Check the source exists before registering the sink
+                self.runtime
+                    .planner
+                    .catalog
+                    .read()
+                    .unwrap()
+                    .item(&sink.source_database, &sink.source)?;
+                self.runtime
+                    .planner
+                    .catalog
+                    .write()
+                    .unwrap()
+                    .create_sink(&sink)?;
+                // The first flush writes out the source's current rows
+                self.flush_sink(&mut sink)?;
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::FlushSink(flush_sink) => {
+                let database = flush_sink
+                    .database
+                    .unwrap_or_else(|| self.session.current_database.read().unwrap().to_string());
+                let _flushing = self.runtime.sink_flushes.lock().unwrap();
+                let mut sink = {
+                    let catalog = self.runtime.planner.catalog.read().unwrap();
+                    catalog.sink(&database, &flush_sink.name)?
+                };
+                self.flush_sink(&mut sink)?;
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::CreateProcedure(create_procedure) => {
+                let database = create_procedure
+                    .database
+                    .unwrap_or_else(|| self.session.current_database.read().unwrap().to_string());
+                let mut catalog = self.runtime.planner.catalog.write().unwrap();
+                catalog.create_procedure(
+                    &database,
+                    &create_procedure.name,
+                    &create_procedure.body_sql,
+                )?;
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::CallProcedure(call_procedure) => {
+                let database = call_procedure
+                    .database
+                    .unwrap_or_else(|| self.session.current_database.read().unwrap().to_string());
+                let sql = {
+                    let catalog = self.runtime.planner.catalog.read().unwrap();
+                    catalog.procedure(&database, &call_procedure.name)?
+                };
+                let body = parse_procedure_body(&sql)?;
+                let max_depth = *self.session.max_recursion_depth.read().unwrap();
+                if self.procedure_depth.load(Ordering::Relaxed) >= max_depth {
+                    return Err(QueryError::ProcedureDepthExceeded(
+                        database,
+                        call_procedure.name,
+                        max_depth,
+                    ));
                 }
+                // Like views the body is resolved against the procedure's own database
+                let current_db = std::mem::replace(
+                    &mut *self.session.current_database.write().unwrap(),
+                    database,
+                );
+                self.procedure_depth.fetch_add(1, Ordering::Relaxed);
+                let result = run_procedure(&body, &self.session, &mut |statement| {
+                    self.execute_parsed_statement(statement)
+                });
+                self.procedure_depth.fetch_sub(1, Ordering::Relaxed);
+                *self.session.current_database.write().unwrap() = current_db;
+                result?;
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::DropProcedure(drop_procedure) => {
+                let database = drop_procedure
+                    .database
+                    .unwrap_or_else(|| self.session.current_database.read().unwrap().to_string());
+                let mut catalog = self.runtime.planner.catalog.write().unwrap();
+                catalog.drop_procedure(&database, &drop_procedure.name)?;
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::CreateEvent(create_event) => {
+                let database = create_event
+                    .database
+                    .unwrap_or_else(|| self.session.current_database.read().unwrap().to_string());
+                // The first run is every_ms from now
+                let last_run_ms = self.runtime.clock().now().ms as i64;
+                let mut catalog = self.runtime.planner.catalog.write().unwrap();
+                catalog.create_event(&Event {
+                    database,
+                    name: create_event.name,
+                    every_ms: create_event.every_ms,
+                    sql: create_event.sql,
+                    last_run_ms,
+                    last_error: None,
+                })?;
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::DropEvent(drop_event) => {
+                let database = drop_event
+                    .database
+                    .unwrap_or_else(|| self.session.current_database.read().unwrap().to_string());
+                let mut catalog = self.runtime.planner.catalog.write().unwrap();
+                catalog.drop_event(&database, &drop_event.name)?;
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::CreatePolicy(create_policy) => {
+                let database = create_policy
+                    .database
+                    .unwrap_or_else(|| self.session.current_database.read().unwrap().to_string());
+                self.check_table_owner(&database, &create_policy.table)?;
+                // Plan the predicate over the table now rather than failing every query later
+                let query = LogicalOperator::Filter(Filter {
+                    predicate: create_policy.predicate,
+                    source: Box::new(LogicalOperator::TableReference(TableReference {
+                        database: Some(database.clone()),
+                        table: create_policy.table.clone(),
+                        sample: None,
+                        as_of: None,
+                    })),
+                    pinned: false,
+                });
+                self.runtime.planner.plan_common(query, &self.session)?;
+                let mut catalog = self.runtime.planner.catalog.write().unwrap();
+                catalog.create_policy(&Policy {
+                    database,
+                    table: create_policy.table,
+                    name: create_policy.name,
+                    predicate: create_policy.predicate_sql,
+                })?;
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::DropPolicy(drop_policy) => {
+                let database = drop_policy
+                    .database
+                    .unwrap_or_else(|| self.session.current_database.read().unwrap().to_string());
+                self.check_table_owner(&database, &drop_policy.table)?;
+                let mut catalog = self.runtime.planner.catalog.write().unwrap();
+                catalog.drop_policy(&database, &drop_policy.table, &drop_policy.name)?;
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::CreateIndex(create_index) => {
+                let database = create_index
+                    .database
+                    .unwrap_or_else(|| self.session.current_database.read().unwrap().to_string());
+                let mut catalog = self.runtime.planner.catalog.write().unwrap();
+                catalog.create_index(
+                    &database,
+                    &create_index.table,
+                    &create_index.name,
+                    &create_index.column,
+                )?;
+                return Ok((vec![], empty_tuple_iter()));
+            }
+            Statement::DropSink(drop_sink) => {
+                let database = drop_sink
+                    .database
+                    .unwrap_or_else(|| self.session.current_database.read().unwrap().to_string());
+                let mut catalog = self.runtime.planner.catalog.write().unwrap();
+                catalog.drop_sink(&database, &drop_sink.name)?;
                 return Ok((vec![], empty_tuple_iter()));
             }
             Statement::DropTable(drop_table) => {
@@ -139,16 +1052,416 @@ impl Connection<'_> {
             .runtime
             .planner
             .plan_for_point_in_time(logical_operator, &self.session)?;
+        let executor = self.build_executor(&plan.operator);
+        Ok((plan.fields, executor))
+    }
+
+    /// Whether there's a transaction open, ie BEGIN without a COMMIT or ROLLBACK yet
+    pub fn in_transaction(&self) -> bool {
+        self.transaction.lock().unwrap().is_some()
+    }
+
+    /// Builds the executor for the plan, within the transaction if there's one open
+    fn build_executor(&self, plan: &PointInTimeOperator) -> BoxedExecutor {
+        match &*self.transaction.lock().unwrap() {
+            Some(transaction) => build_transaction_executor(&self.session, plan, transaction),
+            None => build_executor(&self.session, plan),
+        }
+    }
+
+    /// Runs the query with its rows checked against the types the planner expects and against
+    /// the rows of an unoptimized plan for the same query.
+    fn execute_validated(&self, query: LogicalOperator) -> QueryResult {
+        let (optimized, unoptimized) = self
+            .runtime
+            .planner
+            .plan_for_validation(query, &self.session)?;
+        let checked = |plan: &PointInTimePlan| {
+            build_type_checked_executor(
+                self.build_executor(&plan.operator),
+                plan.fields.iter().map(|field| field.data_type).collect(),
+            )
+        };
+        let mut executor = checked(&optimized);
+        if let Some(unoptimized) = &unoptimized {
+            executor = build_compared_executor(executor, checked(unoptimized));
+        }
+        Ok((optimized.fields, executor))
+    }
+
+    fn refresh_materialized_view(&self, database: &str, name: &str) -> Result<(), QueryError> {
+        let _refreshing = self.runtime.view_refreshes.lock().unwrap();
+        let runtime = self.runtime;
+        refresh_materialized_view(
+            &runtime.planner,
+            &runtime.scheduler,
+            &self.session,
+            database,
+            name,
+        )
+    }
+
+    /// Brings the stats of tables that have changed a lot since they were analyzed up to date
+    /// before a query's planned, a failure only costs us a worse plan so is left as a warning.
+    fn auto_analyze(&self) {
+        let runtime = self.runtime;
+        // Someone's already on it
+        let _analyzing = match runtime.auto_analyzes.try_lock() {
+            Ok(analyzing) => analyzing,
+            Err(_) => return,
+        };
+        let threshold = runtime.auto_analyze_threshold.load(Ordering::Relaxed);
+        if let Err(err) = auto_analyze(&runtime.planner, &runtime.scheduler, threshold) {
+            self.session.add_warning(Warning::new(
+                WARN_GENERIC,
+                format!("Automatic ANALYZE failed: {}", err),
+            ));
+        }
+    }
+
+    /// Lists the open connections with what their clients reported about themselves, users
+    /// other than superusers only see their own connections.
+    fn show_processlist(&self) -> LogicalOperator {
+        let user = self.session.user.read().unwrap().clone();
+        let text = |value: Option<String>| match value {
+            Some(value) => Expression::from(value),
+            None => Expression::Constant(Datum::Null, DataType::Text),
+        };
+        let data = self
+            .runtime
+            .sessions()
+            .into_iter()
+            .filter(|session| is_superuser(&user) || *session.user.read().unwrap() == user)
+            .map(|session| {
+                vec![
+                    Expression::from(session.connection_id as i64),
+                    Expression::from(session.user.read().unwrap().clone()),
+                    Expression::from(session.current_database.read().unwrap().clone()),
+                    text(session.client_attribute("program_name")),
+                    text(session.client_attribute("_client_name")),
+                    text(session.client_attribute("_client_version")),
+                    Expression::from(session.statement.read().unwrap().clone()),
+                ]
+            })
+            .collect();
+
+        LogicalOperator::Values(Values {
+            fields: vec![
+                (DataType::BigInt, String::from("Id")),
+                (DataType::Text, String::from("User")),
+                (DataType::Text, String::from("db")),
+                (DataType::Text, String::from("Program")),
+                (DataType::Text, String::from("Client")),
+                (DataType::Text, String::from("Client_version")),
+                (DataType::Text, String::from("Info")),
+            ],
+            data,
+        })
+    }
+
+    /// Lists the warnings raised by the last statement, mysql style.
+    fn show_warnings(&self) -> QueryResult {
+        let data = self
+            .session
+            .warnings()
+            .into_iter()
+            .map(|warning| {
+                vec![
+                    Expression::from(warning.level.name()),
+                    Expression::from(warning.code as i32),
+                    Expression::from(warning.message),
+                ]
+            })
+            .collect();
+
+        let query = LogicalOperator::Values(Values {
+            fields: vec![
+                (DataType::Text, String::from("Level")),
+                (DataType::Integer, String::from("Code")),
+                (DataType::Text, String::from("Message")),
+            ],
+            data,
+        });
+        let plan = self
+            .runtime
+            .planner
+            .plan_for_point_in_time(query, &self.session)?;
         let executor = build_executor(&self.session, &plan.operator);
         Ok((plan.fields, executor))
     }
 
+    /// Writes out the sink's pending changes and records how far it's got.
+    fn flush_sink(&self, sink: &mut Sink) -> Result<(), QueryError> {
+        flush_sink(
+            &self.runtime.planner,
+            &self.runtime.scheduler,
+            &self.session,
+            sink,
+        )?;
+        let mut catalog = self.runtime.planner.catalog.write().unwrap();
+        catalog.update_sink(sink)?;
+        Ok(())
+    }
+
+    /// Reads up to sample_size documents from the directory to work out their columns.
+    /// Parquet files come with their own schema so there's no need to sample them.
+    fn infer_columns(
+        &self,
+        directory: &str,
+        serde_options: &SerdeOptions,
+        sample_size: usize,
+    ) -> Result<Vec<InferredColumn>, QueryError> {
+        if serde_options.format == FileFormat::Parquet {
+            // The schema's read straight from the files rather than through a file scan
+            self.runtime
+                .planner
+                .check_directory_privilege(directory, &self.session)?;
+            return Ok(keyed_columns(&parquet_schema(directory)?));
+        }
+        let query = LogicalOperator::Limit(Limit {
+            offset: 0,
+            limit: sample_size as i64,
+            source: Box::new(LogicalOperator::FileScan(FileScan {
+                directory: directory.to_string(),
+                serde_options: serde_options.clone(),
+                columns: vec![],
+                dead_letters: None,
+                source_offsets: None,
+                paused_sources: None,
+            })),
+        });
+        let plan = self
+            .runtime
+            .planner
+            .plan_for_point_in_time(query, &self.session)?;
+        let mut executor = build_executor(&self.session, &plan.operator);
+        let mut inferrer = SchemaInferrer::new(serde_options);
+        while let Some((tuple, _freq)) = executor.next()? {
+            if let Some(document) = tuple[0].as_maybe_json() {
+                inferrer.observe(document);
+            }
+        }
+        Ok(inferrer.columns())
+    }
+
     pub fn change_database(&self, database: &str) -> Result<(), QueryError> {
         *self.session.current_database.write().unwrap() = String::from(database);
         Ok(())
     }
 }
 
+/// Sets the named session variable, erroring if it's unknown or the value isn't valid for it
+fn set_variable(session: &Session, name: &str, value: &str) -> Result<(), QueryError> {
+    match name.to_lowercase().as_str() {
+        "lc_time" => {
+            let locale = Locale::from_name(value).ok_or_else(|| {
+                QueryError::InvalidVariableValue(name.to_string(), value.to_string())
+            })?;
+            *session.lc_time.write().unwrap() = locale;
+        }
+        "time_zone" => {
+            let time_zone = parse_time_zone(value).ok_or_else(|| {
+                QueryError::InvalidVariableValue(name.to_string(), value.to_string())
+            })?;
+            *session.time_zone.write().unwrap() = time_zone;
+        }
+        "sql_select_limit" => {
+            // Like mysql anything too big to be a real limit is the same as no limit.
+            let limit = if value.eq_ignore_ascii_case("default") {
+                None
+            } else {
+                let limit: u64 = value.parse().map_err(|_| {
+                    QueryError::InvalidVariableValue(name.to_string(), value.to_string())
+                })?;
+                Some(limit).filter(|limit| *limit <= i64::MAX as u64)
+            };
+            *session.sql_select_limit.write().unwrap() = limit.map(|limit| limit as i64);
+        }
+        "seed" => {
+            let seed = if value.eq_ignore_ascii_case("default") {
+                None
+            } else {
+                Some(value.parse().map_err(|_| {
+                    QueryError::InvalidVariableValue(name.to_string(), value.to_string())
+                })?)
+            };
+            *session.seed.write().unwrap() = seed;
+        }
+        "deterministic_order" => {
+            *session.deterministic_order.write().unwrap() = parse_switch(name, value)?;
+        }
+        "priority" => {
+            let priority = Priority::from_name(value).ok_or_else(|| {
+                QueryError::InvalidVariableValue(name.to_string(), value.to_string())
+            })?;
+            *session.priority.write().unwrap() = priority;
+        }
+        "read_barrier" => {
+            *session.read_barrier.write().unwrap() = parse_switch(name, value)?;
+        }
+        "as_of" => {
+            let as_of = if value.eq_ignore_ascii_case("default") {
+                None
+            } else {
+                Some(LogicalTimestamp::parse(value).map_err(|_| {
+                    QueryError::InvalidVariableValue(name.to_string(), value.to_string())
+                })?)
+            };
+            *session.as_of.write().unwrap() = as_of;
+        }
+        "strict_casts" => {
+            *session.strict_casts.write().unwrap() = parse_switch(name, value)?;
+        }
+        "decimal_overflow" => {
+            let policy = DecimalOverflow::from_name(value).ok_or_else(|| {
+                QueryError::InvalidVariableValue(name.to_string(), value.to_string())
+            })?;
+            *session.decimal_overflow.write().unwrap() = policy;
+        }
+        "invalid_utf8" => {
+            let policy = InvalidUtf8::from_name(value).ok_or_else(|| {
+                QueryError::InvalidVariableValue(name.to_string(), value.to_string())
+            })?;
+            *session.invalid_utf8.write().unwrap() = policy;
+        }
+        "character_set_client" => {
+            // Text is only ever utf8 so this is here for clients that set it on connecting,
+            // anything else would be misread
+            if !is_utf8_charset(value) {
+                return Err(QueryError::InvalidVariableValue(
+                    name.to_string(),
+                    value.to_string(),
+                ));
+            }
+        }
+        "plan_validation" => {
+            // The percentage of queries to validate
+            let percent = if value.eq_ignore_ascii_case("default") {
+                0
+            } else {
+                value
+                    .parse()
+                    .ok()
+                    .filter(|percent| *percent <= 100)
+                    .ok_or_else(|| {
+                        QueryError::InvalidVariableValue(name.to_string(), value.to_string())
+                    })?
+            };
+            *session.plan_validation.write().unwrap() = percent;
+        }
+        "max_recursion_depth" => {
+            let depth = if value.eq_ignore_ascii_case("default") {
+                DEFAULT_MAX_RECURSION_DEPTH
+            } else {
+                value
+                    .parse()
+                    .ok()
+                    .filter(|depth| *depth > 0)
+                    .ok_or_else(|| {
+                        QueryError::InvalidVariableValue(name.to_string(), value.to_string())
+                    })?
+            };
+            *session.max_recursion_depth.write().unwrap() = depth;
+        }
+        "max_intermediate_rows" => {
+            let rows = if value.eq_ignore_ascii_case("default") {
+                DEFAULT_MAX_INTERMEDIATE_ROWS
+            } else {
+                value.parse().ok().filter(|rows| *rows > 0).ok_or_else(|| {
+                    QueryError::InvalidVariableValue(name.to_string(), value.to_string())
+                })?
+            };
+            *session.max_intermediate_rows.write().unwrap() = rows;
+        }
+        "max_value_bytes" => {
+            let bytes = if value.eq_ignore_ascii_case("default") {
+                DEFAULT_MAX_VALUE_BYTES
+            } else {
+                value
+                    .parse()
+                    .ok()
+                    .filter(|bytes| *bytes > 0)
+                    .ok_or_else(|| {
+                        QueryError::InvalidVariableValue(name.to_string(), value.to_string())
+                    })?
+            };
+            *session.max_value_bytes.write().unwrap() = bytes;
+        }
+        "optimizer_rules" => {
+            let mut disabled = session.disabled_optimizer_rules.read().unwrap().clone();
+            update_optimizer_rules(&mut disabled, value).ok_or_else(|| {
+                QueryError::InvalidVariableValue(name.to_string(), value.to_string())
+            })?;
+            *session.disabled_optimizer_rules.write().unwrap() = disabled;
+        }
+        _ => return Err(QueryError::UnknownVariable(name.to_string())),
+    }
+    Ok(())
+}
+
+/// Applies a comma separated list of rules to switch on (+rule) or off (-rule) to the disabled
+/// rules, default switches them all back on.
+fn update_optimizer_rules(disabled: &mut HashSet<String>, value: &str) -> Option<()> {
+    if value.eq_ignore_ascii_case("default") {
+        disabled.clear();
+        return Some(());
+    }
+    for change in value.split(',') {
+        let change = change.trim().to_lowercase();
+        let rule = change.get(1..)?;
+        if !OPTIMIZER_RULES.contains(&rule) {
+            return None;
+        }
+        match change.get(..1)? {
+            "+" => disabled.remove(rule),
+            "-" => disabled.insert(rule.to_string()),
+            _ => return None,
+        };
+    }
+    Some(())
+}
+
+/// Parses a time zone given as either UTC or an offset, ie +02:00
+fn parse_time_zone(value: &str) -> Option<FixedOffset> {
+    if value.eq_ignore_ascii_case("utc") {
+        return FixedOffset::east_opt(0);
+    }
+    let sign = match value.get(..1)? {
+        "+" => 1,
+        "-" => -1,
+        _ => return None,
+    };
+    let mut parts = value[1..].splitn(2, ':');
+    let hours: i32 = parts.next()?.parse().ok()?;
+    let minutes: i32 = parts.next().unwrap_or("0").parse().ok()?;
+    if minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// The directories of all the file scans within the operator
+/// Parses the value of an on/off session variable
+fn parse_switch(name: &str, value: &str) -> Result<bool, QueryError> {
+    match value.to_lowercase().as_str() {
+        "on" | "true" | "1" => Ok(true),
+        "off" | "false" | "0" | "default" => Ok(false),
+        _ => Err(QueryError::InvalidVariableValue(
+            name.to_string(),
+            value.to_string(),
+        )),
+    }
+}
+
+fn file_source_directories(operator: &mut LogicalOperator, directories: &mut Vec<String>) {
+    if let LogicalOperator::FileScan(file_scan) = operator {
+        directories.push(file_scan.directory.clone());
+    }
+    for child in operator.children_mut() {
+        file_source_directories(child, directories);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -187,6 +1500,141 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_prepare_and_execute_statement() -> Result<(), QueryError> {
+        let runtime = Runtime::new_for_test();
+        let connection = runtime.new_connection();
+        let prepared = connection.prepare_statement("select ? + 1, ?")?;
+        assert_eq!(
+            prepared.parameter_types,
+            vec![DataType::Integer, DataType::Text]
+        );
+        assert_eq!(prepared.fields.len(), 2);
+
+        // Parameters are cast to the inferred types
+        let (_fields, mut executor) = connection.execute_prepared_statement(
+            &prepared,
+            &[
+                (Datum::from("2".to_string()), DataType::Text),
+                (Datum::Null, DataType::Null),
+            ],
+        )?;
+        assert_eq!(
+            executor.next()?,
+            Some(([Datum::from(3), Datum::Null].as_ref(), 1))
+        );
+
+        assert!(matches!(
+            connection.execute_prepared_statement(&prepared, &[]),
+            Err(QueryError::ParameterCountMismatch(2, 0))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_prepare_unsupported_statement() {
+        let runtime = Runtime::new_for_test();
+        let connection = runtime.new_connection();
+        assert!(matches!(
+            connection.prepare_statement("create view foo as select ?"),
+            Err(QueryError::ParametersNotSupported)
+        ));
+    }
+
+    #[test]
+    fn test_set_variable() -> Result<(), QueryError> {
+        let runtime = Runtime::new_for_test();
+        let connection = runtime.new_connection();
+        connection.execute_statement("set lc_time = 'de_DE'")?;
+        assert_eq!(connection.session.lc_time.read().unwrap().name, "de_DE");
+
+        assert!(matches!(
+            connection.execute_statement("set lc_time = 'xx_XX'"),
+            Err(QueryError::InvalidVariableValue(..))
+        ));
+        connection.execute_statement("set time_zone = '-05:30'")?;
+        assert_eq!(
+            connection
+                .session
+                .time_zone
+                .read()
+                .unwrap()
+                .local_minus_utc(),
+            -(5 * 3600 + 30 * 60)
+        );
+        assert!(matches!(
+            connection.execute_statement("set time_zone = 'nowhere'"),
+            Err(QueryError::InvalidVariableValue(..))
+        ));
+
+        connection.execute_statement("set sql_select_limit = 10")?;
+        assert_eq!(
+            *connection.session.sql_select_limit.read().unwrap(),
+            Some(10)
+        );
+        connection.execute_statement("set sql_select_limit = 18446744073709551615")?;
+        assert_eq!(*connection.session.sql_select_limit.read().unwrap(), None);
+        connection.execute_statement("set sql_select_limit = 10")?;
+        connection.execute_statement("set sql_select_limit = DEFAULT")?;
+        assert_eq!(*connection.session.sql_select_limit.read().unwrap(), None);
+        assert!(matches!(
+            connection.execute_statement("set sql_select_limit = 'lots'"),
+            Err(QueryError::InvalidVariableValue(..))
+        ));
+
+        connection.execute_statement("set seed = 42")?;
+        assert_eq!(*connection.session.seed.read().unwrap(), Some(42));
+        connection.execute_statement("set seed = default")?;
+        assert_eq!(*connection.session.seed.read().unwrap(), None);
+        assert!(matches!(
+            connection.execute_statement("set seed = 'abc'"),
+            Err(QueryError::InvalidVariableValue(..))
+        ));
+
+        connection.execute_statement("set deterministic_order = on")?;
+        assert!(*connection.session.deterministic_order.read().unwrap());
+        connection.execute_statement("set deterministic_order = 0")?;
+        assert!(!*connection.session.deterministic_order.read().unwrap());
+        assert!(matches!(
+            connection.execute_statement("set deterministic_order = 'maybe'"),
+            Err(QueryError::InvalidVariableValue(..))
+        ));
+
+        connection.execute_statement("set priority = high")?;
+        assert_eq!(*connection.session.priority.read().unwrap(), Priority::High);
+        assert!(matches!(
+            connection.execute_statement("set priority = 'urgent'"),
+            Err(QueryError::InvalidVariableValue(..))
+        ));
+
+        connection.execute_statement("set max_recursion_depth = 4")?;
+        assert_eq!(*connection.session.max_recursion_depth.read().unwrap(), 4);
+        connection.execute_statement("set max_recursion_depth = default")?;
+        assert_eq!(
+            *connection.session.max_recursion_depth.read().unwrap(),
+            DEFAULT_MAX_RECURSION_DEPTH
+        );
+        assert!(matches!(
+            connection.execute_statement("set max_recursion_depth = 0"),
+            Err(QueryError::InvalidVariableValue(..))
+        ));
+        connection.execute_statement("set max_intermediate_rows = 1000")?;
+        assert_eq!(
+            *connection.session.max_intermediate_rows.read().unwrap(),
+            1000
+        );
+        assert!(matches!(
+            connection.execute_statement("set max_intermediate_rows = 'lots'"),
+            Err(QueryError::InvalidVariableValue(..))
+        ));
+
+        assert!(matches!(
+            connection.execute_statement("set foo = 'bar'"),
+            Err(QueryError::UnknownVariable(..))
+        ));
+        Ok(())
+    }
+
     #[test]
     fn test_change_database() -> Result<(), QueryError> {
         let runtime = Runtime::new_for_test();
@@ -198,4 +1646,58 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_login() -> Result<(), QueryError> {
+        let runtime = Runtime::new_for_test();
+        let connection = runtime.new_connection();
+        connection.execute_statement("alter user 'bob' set sql_select_limit = 10")?;
+        connection.execute_statement("alter user bob set time_zone = '+01:00'")?;
+        connection.execute_statement("alter user bob reset time_zone")?;
+        assert!(matches!(
+            connection.execute_statement("alter user bob set seed = 'abc'"),
+            Err(QueryError::InvalidVariableValue(..))
+        ));
+
+        let bobs_connection = runtime.new_connection();
+        bobs_connection.login("bob")?;
+        assert_eq!(*bobs_connection.session.user.read().unwrap(), "bob");
+        assert_eq!(
+            *bobs_connection.session.sql_select_limit.read().unwrap(),
+            Some(10)
+        );
+        assert_eq!(
+            *bobs_connection.session.time_zone.read().unwrap(),
+            FixedOffset::east_opt(0).unwrap()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_kill() -> Result<(), QueryError> {
+        let runtime = Runtime::new_for_test();
+        let bobs_connection = runtime.new_connection();
+        bobs_connection.login("bob")?;
+        let alices_connection = runtime.new_connection();
+        alices_connection.login("alice")?;
+
+        assert!(matches!(
+            alices_connection.execute_statement(&format!("KILL {}", bobs_connection.connection_id)),
+            Err(QueryError::KillNotAllowed(_))
+        ));
+        assert!(matches!(
+            alices_connection.execute_statement("KILL 9999"),
+            Err(QueryError::UnknownConnection(9999))
+        ));
+        assert!(!bobs_connection.session.kill_flag.load(Ordering::Relaxed));
+
+        let root_connection = runtime.new_connection();
+        root_connection.execute_statement(&format!(
+            "KILL CONNECTION {}",
+            bobs_connection.connection_id
+        ))?;
+        assert!(bobs_connection.session.kill_flag.load(Ordering::Relaxed));
+        assert!(!alices_connection.session.kill_flag.load(Ordering::Relaxed));
+        Ok(())
+    }
 }
@@ -1,14 +1,60 @@
 use catalog::CatalogError;
 use executor::ExecutionError;
+use functions::registry::FunctionResolutionError;
 use parser::ParseError;
-use planner::PlannerError;
+use planner::{FieldResolutionError, PlannerError};
 use std::fmt::{Debug, Display, Formatter};
+use storage::StorageError;
 
 pub enum QueryError {
     ParseError(ParseError),
     PlannerError(PlannerError),
     ExecutionError(ExecutionError),
     CatalogError(CatalogError),
+    StorageError(StorageError),
+    // Expected number of parameters, given number of parameters
+    ParameterCountMismatch(usize, usize),
+    ParametersNotSupported,
+    UnknownVariable(String),
+    // Variable name, value
+    InvalidVariableValue(String, String),
+    // Database and name of a table/view that doesn't read from any files
+    NotAFileSource(String, String),
+    NotAView(String, String),
+    // A state ttl's time column that isn't a timestamp column of the view
+    InvalidTimeColumn(String),
+    KafkaSinksNotSupported,
+    // Path of the sink's file and the io error
+    SinkWriteError(String, String),
+    ObjectStorageNotSupported(String),
+    // Location of the cold table's files and the io error
+    StorageTierWriteError(String, String),
+    // The max number of statements that can queue for admission
+    AdmissionQueueFull(usize),
+    // Database, table and the column that isn't in it
+    ColumnNotFound(String, String, String),
+    // A user variable set to a query giving more than one row or column
+    UserVariableNotScalar(String),
+    // The user that tried to grant or revoke a privilege without being a superuser
+    GrantNotAllowed(String),
+    // A column listed more than once in a table's primary key
+    DuplicateKeyColumn(String),
+    // The id given to KILL that isn't an open connection
+    UnknownConnection(u32),
+    // The id given to KILL of another user's connection
+    KillNotAllowed(u32),
+    // The user that tried to change another user's defaults without being a superuser
+    AlterUserNotAllowed(String),
+    // Database, name of the procedure and the max_recursion_depth
+    ProcedureDepthExceeded(String, String, u32),
+    // Database, name of the view being refreshed and the max_intermediate_rows
+    IntermediateRowsExceeded(String, String, u64),
+    // Database, table and the column given a json schema that isn't a json column
+    NotAJsonColumn(String, String, String),
+    // Why the json schema can't be used
+    InvalidJsonSchema(String),
+    // The user, database and table of a change only the table's owner can make
+    NotTableOwner(String, String, String),
 }
 
 impl Display for QueryError {
@@ -18,6 +64,96 @@ impl Display for QueryError {
             QueryError::PlannerError(err) => Display::fmt(err, f),
             QueryError::ExecutionError(err) => Display::fmt(err, f),
             QueryError::CatalogError(err) => Display::fmt(err, f),
+            QueryError::StorageError(err) => Display::fmt(err, f),
+            QueryError::ParameterCountMismatch(expected, actual) => f.write_fmt(format_args!(
+                "Statement expects {} parameters but {} were given",
+                expected, actual
+            )),
+            QueryError::ParametersNotSupported => {
+                f.write_str("Parameters are only supported in queries")
+            }
+            QueryError::UnknownVariable(name) => {
+                f.write_fmt(format_args!("Unknown session variable {}", name))
+            }
+            QueryError::InvalidVariableValue(name, value) => f.write_fmt(format_args!(
+                "Invalid value {} for session variable {}",
+                value, name
+            )),
+            QueryError::NotAFileSource(database, name) => f.write_fmt(format_args!(
+                "{}.{} doesn't read from any file sources",
+                database, name
+            )),
+            QueryError::NotAView(database, name) => {
+                f.write_fmt(format_args!("{}.{} is not a view", database, name))
+            }
+            QueryError::InvalidTimeColumn(column) => f.write_fmt(format_args!(
+                "State ttl time column {} must be a timestamp column of the view",
+                column
+            )),
+            QueryError::KafkaSinksNotSupported => f.write_str(
+                "Kafka sinks aren't supported by this build, use INTO FILE('path') instead",
+            ),
+            QueryError::SinkWriteError(path, err) => {
+                f.write_fmt(format_args!("Error writing to sink file {}: {}", path, err))
+            }
+            QueryError::ObjectStorageNotSupported(location) => f.write_fmt(format_args!(
+                "Object storage locations like {} aren't supported by this build, use a local directory instead",
+                location
+            )),
+            QueryError::StorageTierWriteError(location, err) => f.write_fmt(format_args!(
+                "Error writing cold table files to {}: {}",
+                location, err
+            )),
+            QueryError::AdmissionQueueFull(max_queued) => f.write_fmt(format_args!(
+                "Too many statements running, all {} queue slots are taken, try again later",
+                max_queued
+            )),
+            QueryError::ColumnNotFound(database, name, column) => f.write_fmt(format_args!(
+                "Column {} not found in {}.{}",
+                column, database, name
+            )),
+            QueryError::UserVariableNotScalar(name) => f.write_fmt(format_args!(
+                "User variable @{} can only be set to a single value, the query gave more than one row or column",
+                name
+            )),
+            QueryError::GrantNotAllowed(user) => f.write_fmt(format_args!(
+                "User {} can't grant or revoke privileges, only root can",
+                user
+            )),
+            QueryError::UnknownConnection(connection_id) => {
+                f.write_fmt(format_args!("Unknown connection id: {}", connection_id))
+            }
+            QueryError::KillNotAllowed(connection_id) => f.write_fmt(format_args!(
+                "Connection {} belongs to another user, only root can kill it",
+                connection_id
+            )),
+            QueryError::AlterUserNotAllowed(user) => f.write_fmt(format_args!(
+                "User {} can only change their own defaults, only root can change another user's",
+                user
+            )),
+            QueryError::DuplicateKeyColumn(column) => f.write_fmt(format_args!(
+                "Column {} appears more than once in the primary key",
+                column
+            )),
+            QueryError::ProcedureDepthExceeded(database, name, depth) => f.write_fmt(format_args!(
+                "Procedure {}.{} nests procedure calls more than {} deep, see max_recursion_depth",
+                database, name, depth
+            )),
+            QueryError::IntermediateRowsExceeded(database, name, rows) => f.write_fmt(format_args!(
+                "Refreshing {}.{} needs more than {} rows in memory, see max_intermediate_rows",
+                database, name, rows
+            )),
+            QueryError::NotAJsonColumn(database, name, column) => f.write_fmt(format_args!(
+                "Column {} of {}.{} isn't a json column, only json columns can have a json schema",
+                column, database, name
+            )),
+            QueryError::InvalidJsonSchema(reason) => {
+                f.write_fmt(format_args!("Invalid json schema - {}", reason))
+            }
+            QueryError::NotTableOwner(user, database, table) => f.write_fmt(format_args!(
+                "User {} doesn't own {}.{}, only its owner or root can change its security",
+                user, database, table
+            )),
         }
     }
 }
@@ -51,3 +187,205 @@ impl From<CatalogError> for QueryError {
         QueryError::CatalogError(catalog_error)
     }
 }
+
+impl From<StorageError> for QueryError {
+    fn from(storage_error: StorageError) -> Self {
+        QueryError::StorageError(storage_error)
+    }
+}
+
+/// The error number and SQLSTATE sent to clients along with an error's message.
+/// The numbers are MySQL's own so client libraries can tell errors apart just as they would
+/// against MySQL, ie a missing table is always 1146/42S02 whichever part of incresql noticed.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ErrorCode {
+    pub code: u16,
+    pub sql_state: &'static str,
+}
+
+const fn error_code(code: u16, sql_state: &'static str) -> ErrorCode {
+    ErrorCode { code, sql_state }
+}
+
+const ER_DB_CREATE_EXISTS: ErrorCode = error_code(1007, "HY000");
+const ER_ERROR_ON_READ: ErrorCode = error_code(1024, "HY000");
+const ER_ERROR_ON_WRITE: ErrorCode = error_code(1026, "HY000");
+const ER_GET_ERRNO: ErrorCode = error_code(1030, "HY000");
+const ER_OUT_OF_RESOURCES: ErrorCode = error_code(1041, "HY000");
+const ER_BAD_DB_ERROR: ErrorCode = error_code(1049, "42000");
+const ER_TABLE_EXISTS_ERROR: ErrorCode = error_code(1050, "42S01");
+const ER_NON_UNIQ_ERROR: ErrorCode = error_code(1052, "23000");
+const ER_BAD_FIELD_ERROR: ErrorCode = error_code(1054, "42S22");
+const ER_DUP_FIELDNAME: ErrorCode = error_code(1060, "42S21");
+const ER_DUP_KEYNAME: ErrorCode = error_code(1061, "42000");
+const ER_DUP_ENTRY: ErrorCode = error_code(1062, "23000");
+const ER_WRONG_FIELD_SPEC: ErrorCode = error_code(1063, "42000");
+const ER_PARSE_ERROR: ErrorCode = error_code(1064, "42000");
+const ER_UNKNOWN_ERROR: ErrorCode = error_code(1105, "HY000");
+const ER_NO_SUCH_THREAD: ErrorCode = error_code(1094, "HY000");
+const ER_KILL_DENIED_ERROR: ErrorCode = error_code(1095, "HY000");
+const ER_FIELD_SPECIFIED_TWICE: ErrorCode = error_code(1110, "42000");
+const ER_INVALID_GROUP_FUNC_USE: ErrorCode = error_code(1111, "HY000");
+const ER_WRONG_VALUE_COUNT_ON_ROW: ErrorCode = error_code(1136, "21S01");
+const ER_NONEXISTING_GRANT: ErrorCode = error_code(1141, "42000");
+const ER_TABLEACCESS_DENIED_ERROR: ErrorCode = error_code(1142, "42000");
+const ER_NO_SUCH_TABLE: ErrorCode = error_code(1146, "42S02");
+const ER_UNKNOWN_SYSTEM_VARIABLE: ErrorCode = error_code(1193, "HY000");
+const ER_WRONG_ARGUMENTS: ErrorCode = error_code(1210, "HY000");
+const ER_WRONG_USAGE: ErrorCode = error_code(1221, "HY000");
+const ER_WRONG_NUMBER_OF_COLUMNS_IN_SELECT: ErrorCode = error_code(1222, "21000");
+const ER_SPECIFIC_ACCESS_DENIED_ERROR: ErrorCode = error_code(1227, "42000");
+const ER_WRONG_VALUE_FOR_VAR: ErrorCode = error_code(1231, "42000");
+const ER_NOT_SUPPORTED_YET: ErrorCode = error_code(1235, "42000");
+const ER_OPERAND_COLUMNS: ErrorCode = error_code(1241, "21000");
+const ER_SUBQUERY_NO_1_ROW: ErrorCode = error_code(1242, "21000");
+const ER_NON_UPDATABLE_TABLE: ErrorCode = error_code(1288, "HY000");
+const ER_TRUNCATED_WRONG_VALUE: ErrorCode = error_code(1292, "22007");
+const ER_UNSUPPORTED_PS: ErrorCode = error_code(1295, "HY000");
+const ER_SP_ALREADY_EXISTS: ErrorCode = error_code(1304, "42000");
+const ER_SP_DOES_NOT_EXIST: ErrorCode = error_code(1305, "42000");
+const ER_QUERY_INTERRUPTED: ErrorCode = error_code(1317, "70100");
+const ER_WRONG_OBJECT: ErrorCode = error_code(1347, "HY000");
+const ER_TRUNCATED_WRONG_VALUE_FOR_FIELD: ErrorCode = error_code(1366, "HY000");
+const ER_PROCACCESS_DENIED_ERROR: ErrorCode = error_code(1370, "42000");
+const ER_DATA_TOO_LONG: ErrorCode = error_code(1406, "22001");
+const ER_NON_INSERTABLE_TABLE: ErrorCode = error_code(1471, "HY000");
+const ER_SP_RECURSION_LIMIT: ErrorCode = error_code(1456, "HY000");
+const ER_VIEW_RECURSIVE: ErrorCode = error_code(1462, "HY000");
+const ER_EVENT_ALREADY_EXISTS: ErrorCode = error_code(1537, "HY000");
+const ER_EVENT_DOES_NOT_EXIST: ErrorCode = error_code(1539, "HY000");
+const ER_WRONG_PARAMETERS_TO_NATIVE_FCT: ErrorCode = error_code(1583, "42000");
+const ER_TOO_MANY_CONCURRENT_TRXS: ErrorCode = error_code(1637, "HY000");
+const ER_CANT_EXECUTE_IN_READ_ONLY_TRANSACTION: ErrorCode = error_code(1792, "25006");
+const ER_INTERNAL_ERROR: ErrorCode = error_code(1815, "HY000");
+const ER_INVALID_JSON_TEXT: ErrorCode = error_code(3140, "22032");
+const ER_WINDOW_INVALID_WINDOW_FUNC_USE: ErrorCode = error_code(3593, "HY000");
+const ER_CHECK_CONSTRAINT_VIOLATED: ErrorCode = error_code(3819, "HY000");
+
+impl QueryError {
+    /// The error code for this error, errors wrapping errors from other parts of incresql get
+    /// the code of the underlying error.
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            QueryError::ParseError(_) => ER_PARSE_ERROR,
+            QueryError::PlannerError(err) => planner_error_code(err),
+            QueryError::ExecutionError(err) => execution_error_code(err),
+            QueryError::CatalogError(err) => catalog_error_code(err),
+            QueryError::StorageError(err) => storage_error_code(err),
+            QueryError::ParameterCountMismatch(..) => ER_WRONG_ARGUMENTS,
+            QueryError::ParametersNotSupported => ER_UNSUPPORTED_PS,
+            QueryError::UnknownVariable(_) => ER_UNKNOWN_SYSTEM_VARIABLE,
+            QueryError::InvalidVariableValue(..) => ER_WRONG_VALUE_FOR_VAR,
+            QueryError::NotAFileSource(..) | QueryError::NotAView(..) => ER_WRONG_OBJECT,
+            QueryError::InvalidTimeColumn(_) => ER_WRONG_USAGE,
+            QueryError::KafkaSinksNotSupported | QueryError::ObjectStorageNotSupported(_) => {
+                ER_NOT_SUPPORTED_YET
+            }
+            QueryError::SinkWriteError(..) | QueryError::StorageTierWriteError(..) => {
+                ER_ERROR_ON_WRITE
+            }
+            QueryError::AdmissionQueueFull(_) => ER_TOO_MANY_CONCURRENT_TRXS,
+            QueryError::ColumnNotFound(..) => ER_BAD_FIELD_ERROR,
+            QueryError::UserVariableNotScalar(_) => ER_SUBQUERY_NO_1_ROW,
+            QueryError::GrantNotAllowed(_) => ER_SPECIFIC_ACCESS_DENIED_ERROR,
+            QueryError::DuplicateKeyColumn(_) => ER_DUP_FIELDNAME,
+            QueryError::UnknownConnection(_) => ER_NO_SUCH_THREAD,
+            QueryError::KillNotAllowed(_) => ER_KILL_DENIED_ERROR,
+            QueryError::AlterUserNotAllowed(_) => ER_SPECIFIC_ACCESS_DENIED_ERROR,
+            QueryError::ProcedureDepthExceeded(..) => ER_SP_RECURSION_LIMIT,
+            QueryError::IntermediateRowsExceeded(..) => ER_OUT_OF_RESOURCES,
+            QueryError::NotAJsonColumn(..) => ER_WRONG_FIELD_SPEC,
+            QueryError::InvalidJsonSchema(_) => ER_INVALID_JSON_TEXT,
+            QueryError::NotTableOwner(..) => ER_TABLEACCESS_DENIED_ERROR,
+        }
+    }
+}
+
+fn planner_error_code(err: &PlannerError) -> ErrorCode {
+    match err {
+        PlannerError::FunctionResolutionError(FunctionResolutionError::FunctionNotFound(_)) => {
+            ER_SP_DOES_NOT_EXIST
+        }
+        PlannerError::FunctionResolutionError(
+            FunctionResolutionError::MatchingSignatureNotFound(..),
+        ) => ER_WRONG_PARAMETERS_TO_NATIVE_FCT,
+        PlannerError::FieldResolutionError(FieldResolutionError::Ambiguous(..)) => {
+            ER_NON_UNIQ_ERROR
+        }
+        PlannerError::FieldResolutionError(FieldResolutionError::NotFound(..)) => {
+            ER_BAD_FIELD_ERROR
+        }
+        PlannerError::CatalogError(err) => catalog_error_code(err),
+        PlannerError::PredicateNotBoolean(..) | PlannerError::CastNotSupported(..) => {
+            ER_WRONG_ARGUMENTS
+        }
+        PlannerError::UnionAllMismatch(..) => ER_WRONG_NUMBER_OF_COLUMNS_IN_SELECT,
+        PlannerError::InsertMismatch(..) => ER_WRONG_VALUE_COUNT_ON_ROW,
+        PlannerError::AggregateNotAllowed(..) => ER_INVALID_GROUP_FUNC_USE,
+        PlannerError::SubqueryColumnCount(_) => ER_OPERAND_COLUMNS,
+        PlannerError::SubqueryNotSupported(_)
+        | PlannerError::TableSampleNotSupported(_)
+        | PlannerError::ResumeNotSupported(_)
+        | PlannerError::WindowFunctionNotSupported(_)
+        | PlannerError::CopyOptionNotSupported(_) => ER_NOT_SUPPORTED_YET,
+        PlannerError::InsertIntoView => ER_NON_INSERTABLE_TABLE,
+        PlannerError::InsertColumnNotFound(_) => ER_BAD_FIELD_ERROR,
+        PlannerError::InsertColumnRepeated(_) => ER_FIELD_SPECIFIED_TWICE,
+        PlannerError::DeleteFromMaskedTable(_) => ER_NON_UPDATABLE_TABLE,
+        PlannerError::UnknownEnumLabel(..) => ER_TRUNCATED_WRONG_VALUE_FOR_FIELD,
+        PlannerError::PermissionDenied(..) => ER_PROCACCESS_DENIED_ERROR,
+        PlannerError::WindowNotAllowed(_) => ER_WINDOW_INVALID_WINDOW_FUNC_USE,
+        PlannerError::ViewRecursion(_) | PlannerError::ViewDepthExceeded(..) => ER_VIEW_RECURSIVE,
+        PlannerError::WriteAsOf => ER_CANT_EXECUTE_IN_READ_ONLY_TRANSACTION,
+    }
+}
+
+fn execution_error_code(err: &ExecutionError) -> ErrorCode {
+    match err {
+        ExecutionError::StorageError(err) => storage_error_code(err),
+        ExecutionError::IOError(_) | ExecutionError::DecodingError(_) => ER_ERROR_ON_READ,
+        ExecutionError::ValidationError(_) => ER_INTERNAL_ERROR,
+        ExecutionError::CastError(_) => ER_TRUNCATED_WRONG_VALUE,
+        ExecutionError::SubqueryRowCount => ER_SUBQUERY_NO_1_ROW,
+        ExecutionError::ValueTooLarge(..) => ER_DATA_TOO_LONG,
+        ExecutionError::JsonSchemaViolation(..) => ER_CHECK_CONSTRAINT_VIOLATED,
+        ExecutionError::Killed => ER_QUERY_INTERRUPTED,
+    }
+}
+
+fn catalog_error_code(err: &CatalogError) -> ErrorCode {
+    match err {
+        CatalogError::StorageError(err) => storage_error_code(err),
+        CatalogError::TableAlreadyExists(..) => ER_TABLE_EXISTS_ERROR,
+        CatalogError::TableNotFound(..) => ER_NO_SUCH_TABLE,
+        CatalogError::DatabaseAlreadyExists(_) => ER_DB_CREATE_EXISTS,
+        CatalogError::DatabaseNotFound(_) => ER_BAD_DB_ERROR,
+        CatalogError::ProcedureAlreadyExists(..) => ER_SP_ALREADY_EXISTS,
+        CatalogError::ProcedureNotFound(..) => ER_SP_DOES_NOT_EXIST,
+        CatalogError::EventAlreadyExists(..) => ER_EVENT_ALREADY_EXISTS,
+        CatalogError::EventNotFound(..) => ER_EVENT_DOES_NOT_EXIST,
+        CatalogError::PrivilegeNotGranted(..) => ER_NONEXISTING_GRANT,
+        CatalogError::ColumnNotFound(..) => ER_BAD_FIELD_ERROR,
+        CatalogError::IndexAlreadyExists(..) => ER_DUP_KEYNAME,
+        CatalogError::NotAMaterializedView(..)
+        | CatalogError::NotATable(..)
+        | CatalogError::NotAColdTable(..) => ER_WRONG_OBJECT,
+        // MySQL has nothing like these, they get the catch all code
+        CatalogError::DatabaseNotEmpty(_)
+        | CatalogError::SinkAlreadyExists(..)
+        | CatalogError::SinkNotFound(..)
+        | CatalogError::PolicyAlreadyExists(..)
+        | CatalogError::PolicyNotFound(..)
+        | CatalogError::ColumnMaskNotFound(..)
+        | CatalogError::ColumnSchemaNotFound(..) => ER_UNKNOWN_ERROR,
+    }
+}
+
+fn storage_error_code(err: &StorageError) -> ErrorCode {
+    match err {
+        StorageError::RocksDbError(_)
+        | StorageError::EncryptionError(_)
+        | StorageError::UnsupportedFormat(_) => ER_GET_ERRNO,
+        StorageError::DuplicateKey => ER_DUP_ENTRY,
+    }
+}
@@ -0,0 +1,38 @@
+use crate::{QueryError, Runtime};
+use std::time::Duration;
+
+/// How often the event scheduler looks for events that are due
+pub const EVENT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Runs each of the events that are due, ie every_ms has passed since they last ran. An event
+/// that's missed several runs (say the server was down) is only run the once, it then waits
+/// every_ms from now. Events run one after another, each on a connection of its own with the
+/// event's database as the current database, a failing event is recorded in its last_error
+/// rather than stopping the others.
+pub(crate) fn run_due_events(runtime: &Runtime) -> Result<(), QueryError> {
+    let now = runtime.clock().now().ms as i64;
+    let events = runtime.planner.catalog.read().unwrap().events()?;
+    for event in events {
+        if now < event.last_run_ms.saturating_add(event.every_ms) {
+            continue;
+        }
+        let connection = runtime.new_connection();
+        *connection.session.current_database.write().unwrap() = event.database.clone();
+        let result =
+            connection
+                .execute_statement(&event.sql)
+                .and_then(|(_fields, mut executor)| {
+                    while executor.next()?.is_some() {}
+                    Ok(())
+                });
+
+        let mut catalog = runtime.planner.catalog.write().unwrap();
+        // The event could've been dropped while it was running
+        if let Ok(mut event) = catalog.event(&event.database, &event.name) {
+            event.last_run_ms = now;
+            event.last_error = result.err().map(|err| err.to_string());
+            catalog.update_event(&event)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use data::chrono::Utc;
+use data::Session;
+use std::fmt::{Debug, Formatter};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::Mutex;
+
+/// Hooks into the lifecycle of every connection made to the runtime, ie for auditing or
+/// metrics. The hooks are called synchronously so should be quick, all of them default to
+/// doing nothing.
+pub trait ConnectionHook: Debug + Send + Sync {
+    /// Called once the connection's user is known and their session defaults applied
+    fn on_connect(&self, _session: &Session) {}
+
+    /// Called as the connection is dropped
+    fn on_disconnect(&self, _session: &Session) {}
+
+    /// Called before each statement is run, including prepared statements as they're executed
+    fn on_statement(&self, _session: &Session, _sql: &str) {}
+}
+
+/// Writes a line to the log for each statement run, tagged with the connection and what its
+/// client reported about itself so a bad query can be traced back to the service that sent it.
+pub struct QueryLog {
+    out: Mutex<Box<dyn Write + Send>>,
+}
+
+impl QueryLog {
+    pub fn new(out: Box<dyn Write + Send>) -> Self {
+        QueryLog {
+            out: Mutex::new(out),
+        }
+    }
+
+    /// A query log appending to the file at path
+    pub fn to_file(path: &str) -> Result<Self, std::io::Error> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(QueryLog::new(Box::new(file)))
+    }
+}
+
+impl Debug for QueryLog {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("QueryLog")
+    }
+}
+
+impl ConnectionHook for QueryLog {
+    fn on_statement(&self, session: &Session, sql: &str) {
+        let program = session
+            .client_attribute("program_name")
+            .or_else(|| session.client_attribute("_client_name"))
+            .unwrap_or_default();
+        let line = format!(
+            "{} id={} user={} program={} {}\n",
+            Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+            session.connection_id,
+            session.user.read().unwrap(),
+            program,
+            sql.replace('\n', " ")
+        );
+        // Losing a log line isn't worth failing the statement over
+        let mut out = self.out.lock().unwrap();
+        let _ = out.write_all(line.as_bytes()).and_then(|_| out.flush());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_query_log() {
+        let buffer = SharedBuffer::default();
+        let log = QueryLog::new(Box::new(buffer.clone()));
+        let session = Session::new(7);
+        *session.user.write().unwrap() = "bob".to_string();
+        log.on_statement(&session, "select 1");
+        session
+            .client_attributes
+            .write()
+            .unwrap()
+            .insert("_client_name".to_string(), "libmysql".to_string());
+        log.on_statement(&session, "select\n2");
+        session
+            .client_attributes
+            .write()
+            .unwrap()
+            .insert("program_name".to_string(), "orders".to_string());
+        log.on_statement(&session, "select 3");
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<_> = output
+            .lines()
+            .map(|line| &line[line.find(' ').unwrap() + 1..])
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "id=7 user=bob program= select 1",
+                "id=7 user=bob program=libmysql select 2",
+                "id=7 user=bob program=orders select 3",
+            ]
+        );
+    }
+}
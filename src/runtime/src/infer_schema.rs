@@ -0,0 +1,477 @@
+use ast::rel::logical::{ChangeFormat, ErrorPolicy, FileFormat, SerdeOptions};
+use ast::statement::ExternalColumn;
+use data::chrono::{NaiveDate, NaiveDateTime};
+use data::json::{Json, JsonType};
+use data::rust_decimal::prelude::ToPrimitive;
+use data::rust_decimal::Decimal;
+use data::{DataType, DECIMAL_MAX_PRECISION, DECIMAL_MAX_SCALE};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// A column found by sampling the documents in a file source.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct InferredColumn {
+    pub name: String,
+    pub datatype: DataType,
+    // The json path used to pull the column out of each document
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum InferredType {
+    Null,
+    Boolean,
+    Integer,
+    BigInt,
+    // Scale
+    Decimal(u32),
+    Date,
+    Timestamp,
+    Text,
+    Object,
+    Json,
+}
+
+impl InferredType {
+    /// Works out a type that can hold values of both types, falling back to text or json
+    /// for anything incompatible.
+    fn merge(self, other: InferredType) -> InferredType {
+        use InferredType::*;
+        match (self, other) {
+            (Null, t) | (t, Null) => t,
+            (a, b) if a == b => a,
+            (Decimal(a), Decimal(b)) => Decimal(a.max(b)),
+            (Decimal(s), Integer) | (Decimal(s), BigInt) => Decimal(s),
+            (Integer, Decimal(s)) | (BigInt, Decimal(s)) => Decimal(s),
+            (Integer, BigInt) | (BigInt, Integer) => BigInt,
+            (Object, _) | (_, Object) | (Json, _) | (_, Json) => Json,
+            _ => Text,
+        }
+    }
+
+    fn datatype(self) -> DataType {
+        match self {
+            InferredType::Boolean => DataType::Boolean,
+            InferredType::Integer => DataType::Integer,
+            InferredType::BigInt => DataType::BigInt,
+            InferredType::Decimal(scale) => {
+                DataType::Decimal(DECIMAL_MAX_PRECISION, (scale as u8).min(DECIMAL_MAX_SCALE))
+            }
+            InferredType::Date => DataType::Date,
+            InferredType::Timestamp => DataType::Timestamp,
+            // Nothing but nulls, text is as good a guess as any
+            InferredType::Null | InferredType::Text => DataType::Text,
+            InferredType::Object | InferredType::Json => DataType::Json,
+        }
+    }
+}
+
+/// Builds up a schema from sampled documents, nested objects are flattened out into a column
+/// per leaf while arrays are kept as json. The exception is an array at the top level which
+/// is what we get from csv files.
+#[derive(Debug)]
+pub struct SchemaInferrer {
+    // Csv values are always strings so we look at what's in them to guess a type
+    infer_from_strings: bool,
+    // In the order they were first seen
+    paths: Vec<(Vec<Segment>, InferredType)>,
+    path_idxs: HashMap<Vec<Segment>, usize>,
+}
+
+impl SchemaInferrer {
+    pub fn new(serde_options: &SerdeOptions) -> Self {
+        SchemaInferrer {
+            infer_from_strings: serde_options.format == FileFormat::Csv,
+            paths: vec![],
+            path_idxs: HashMap::new(),
+        }
+    }
+
+    pub fn observe(&mut self, document: Json) {
+        self.observe_at(document, &mut vec![]);
+    }
+
+    fn observe_at(&mut self, json: Json, path: &mut Vec<Segment>) {
+        let inferred_type = match json.json_type() {
+            JsonType::Null => InferredType::Null,
+            JsonType::Boolean => InferredType::Boolean,
+            JsonType::Number => number_type(json.get_number().unwrap()),
+            JsonType::String => self.string_type(json.get_string().unwrap()),
+            JsonType::Object => {
+                for (key, value) in json.iter_object().unwrap() {
+                    path.push(Segment::Key(key.to_string()));
+                    self.observe_at(value, path);
+                    path.pop();
+                }
+                InferredType::Object
+            }
+            JsonType::Array if path.is_empty() => {
+                for (idx, value) in json.iter_array().unwrap().enumerate() {
+                    path.push(Segment::Index(idx));
+                    self.observe_at(value, path);
+                    path.pop();
+                }
+                return;
+            }
+            JsonType::Array => InferredType::Json,
+        };
+
+        if let Some(idx) = self.path_idxs.get(path.as_slice()) {
+            let (_, existing) = &mut self.paths[*idx];
+            *existing = existing.merge(inferred_type);
+        } else {
+            self.path_idxs.insert(path.clone(), self.paths.len());
+            self.paths.push((path.clone(), inferred_type));
+        }
+    }
+
+    fn string_type(&self, s: &str) -> InferredType {
+        if NaiveDate::from_str(s).is_ok() {
+            InferredType::Date
+        } else if NaiveDateTime::from_str(s).is_ok() {
+            InferredType::Timestamp
+        } else if !self.infer_from_strings {
+            InferredType::Text
+        } else if s.is_empty() {
+            InferredType::Null
+        } else if s.eq_ignore_ascii_case("true") || s.eq_ignore_ascii_case("false") {
+            InferredType::Boolean
+        } else if let Ok(d) = Decimal::from_str(s) {
+            number_type(d)
+        } else {
+            InferredType::Text
+        }
+    }
+
+    /// The columns found so far, objects are flattened into their leaves unless we've seen
+    /// something other than an object at the same path.
+    pub fn columns(&self) -> Vec<InferredColumn> {
+        let mut columns: Vec<InferredColumn> = vec![];
+        for (path, inferred_type) in &self.paths {
+            let under_object = (1..path.len()).all(|len| {
+                let idx = self.path_idxs[&path[..len]];
+                self.paths[idx].1 == InferredType::Object
+            });
+            let has_children = self
+                .paths
+                .iter()
+                .any(|(other, _)| other.len() > path.len() && other.starts_with(path));
+            if !under_object || (*inferred_type == InferredType::Object && has_children) {
+                continue;
+            }
+
+            let mut name = column_name(path);
+            let mut suffix = 1;
+            while columns.iter().any(|column| column.name == name) {
+                suffix += 1;
+                name = format!("{}_{}", column_name(path), suffix);
+            }
+            columns.push(InferredColumn {
+                name,
+                datatype: inferred_type.datatype(),
+                path: json_path(path),
+            });
+        }
+        columns
+    }
+}
+
+fn number_type(d: Decimal) -> InferredType {
+    if d.scale() != 0 {
+        InferredType::Decimal(d.scale())
+    } else if d.to_i32().is_some() {
+        InferredType::Integer
+    } else if d.to_i64().is_some() {
+        InferredType::BigInt
+    } else {
+        InferredType::Decimal(0)
+    }
+}
+
+fn column_name(path: &[Segment]) -> String {
+    if path.is_empty() {
+        return "value".to_string();
+    }
+    path.iter()
+        .map(|segment| match segment {
+            Segment::Key(key) => key
+                .chars()
+                .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                .collect(),
+            Segment::Index(idx) => format!("c{}", idx + 1),
+        })
+        .collect::<Vec<String>>()
+        .join("_")
+        .to_lowercase()
+}
+
+fn json_path(path: &[Segment]) -> String {
+    let mut json_path = "$".to_string();
+    for segment in path {
+        match segment {
+            Segment::Key(key) if is_simple_key(key) => {
+                json_path.push('.');
+                json_path.push_str(key);
+            }
+            Segment::Key(key) => {
+                let escaped = key.replace('\\', "\\\\").replace('"', "\\\"");
+                json_path.push_str(&format!("[\"{}\"]", escaped));
+            }
+            Segment::Index(idx) => json_path.push_str(&format!("[{}]", idx)),
+        }
+    }
+    json_path
+}
+
+fn is_simple_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// The query for an external table, the file scan pulls each column out of the document
+/// and casts it to its type.
+pub fn external_table_sql(
+    directory: &str,
+    serde_options: &SerdeOptions,
+    columns: &[InferredColumn],
+) -> String {
+    let columns: Vec<_> = columns
+        .iter()
+        .map(|column| {
+            format!(
+                "`{}` {} PATH {}",
+                column.name.replace('`', "_"),
+                column.datatype,
+                quote(&column.path)
+            )
+        })
+        .collect();
+
+    let format = match serde_options.format {
+        FileFormat::Csv => "csv",
+        FileFormat::Json => "json",
+        FileFormat::Parquet => "parquet",
+    };
+    let on_error = match serde_options.on_error {
+        ErrorPolicy::Fail => "fail",
+        ErrorPolicy::Skip => "skip",
+        ErrorPolicy::DeadLetter => "dead_letter",
+    };
+    let mut options = vec![
+        format!("format = '{}'", format),
+        format!(
+            "delimiter = {}",
+            quote(&(serde_options.delimiter as char).to_string())
+        ),
+        format!("on_error = '{}'", on_error),
+        format!("resume = '{}'", serde_options.resume),
+    ];
+    if let Some(max_rows_per_second) = serde_options.max_rows_per_second {
+        options.push(format!("max_rows_per_second = '{}'", max_rows_per_second));
+    }
+    if let Some(max_batch_rows) = serde_options.max_batch_rows {
+        options.push(format!("max_batch_rows = '{}'", max_batch_rows));
+    }
+    match serde_options.changes {
+        Some(ChangeFormat::Debezium) => options.push("changes = 'debezium'".to_string()),
+        Some(ChangeFormat::Maxwell) => options.push("changes = 'maxwell'".to_string()),
+        None => {}
+    }
+    if let Some(snapshot) = &serde_options.snapshot {
+        options.push(format!("snapshot = {}", quote(snapshot)));
+    }
+    format!(
+        "SELECT * FROM DIRECTORY {} WITH ({}) COLUMNS ({})",
+        quote(directory),
+        options.join(", "),
+        columns.join(", ")
+    )
+}
+
+/// Columns given up front without a path are looked up by name for json and parquet or by
+/// position for csv, dotted names are flattened with underscores as with inferred columns.
+pub fn given_columns(
+    serde_options: &SerdeOptions,
+    columns: &[ExternalColumn],
+) -> Vec<InferredColumn> {
+    columns
+        .iter()
+        .enumerate()
+        .map(|(idx, column)| {
+            let path = column.path.clone().unwrap_or_else(|| {
+                let segments: Vec<_> = match serde_options.format {
+                    FileFormat::Csv => vec![Segment::Index(idx)],
+                    FileFormat::Json | FileFormat::Parquet => column
+                        .name
+                        .split('.')
+                        .map(|key| Segment::Key(key.to_string()))
+                        .collect(),
+                };
+                json_path(&segments)
+            });
+            InferredColumn {
+                name: column.name.replace('.', "_"),
+                datatype: column.datatype,
+                path,
+            }
+        })
+        .collect()
+}
+
+/// Columns for reading back json documents written out with one key per column.
+pub fn keyed_columns(columns: &[(String, DataType)]) -> Vec<InferredColumn> {
+    columns
+        .iter()
+        .map(|(name, datatype)| InferredColumn {
+            name: name.clone(),
+            datatype: *datatype,
+            path: json_path(&[Segment::Key(name.clone())]),
+        })
+        .collect()
+}
+
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::json::OwnedJson;
+
+    fn infer(serde_options: &SerdeOptions, documents: &[&str]) -> Vec<(String, DataType, String)> {
+        let mut inferrer = SchemaInferrer::new(serde_options);
+        for document in documents {
+            inferrer.observe(OwnedJson::parse(document).unwrap().as_json());
+        }
+        inferrer
+            .columns()
+            .into_iter()
+            .map(|column| (column.name, column.datatype, column.path))
+            .collect()
+    }
+
+    fn column(name: &str, datatype: DataType, path: &str) -> (String, DataType, String) {
+        (name.to_string(), datatype, path.to_string())
+    }
+
+    #[test]
+    fn test_infer_json() {
+        let json = SerdeOptions {
+            delimiter: b',',
+            format: FileFormat::Json,
+            on_error: ErrorPolicy::Fail,
+            resume: false,
+            max_rows_per_second: None,
+            max_batch_rows: None,
+            changes: None,
+            snapshot: None,
+        };
+        assert_eq!(
+            infer(
+                &json,
+                &[
+                    r#"{"a": 1, "b": {"c": "x", "d": 1.25}, "e": [1], "f": null, "g": 1}"#,
+                    r#"{"a": 3000000000, "b": {"c": "2020-01-01", "d": 1}, "g": {"h": 1}}"#,
+                    r#"{"a": null, "my key": "2020-01-01", "b": {"e": {}}}"#,
+                ]
+            ),
+            vec![
+                column("a", DataType::BigInt, "$.a"),
+                column("b_c", DataType::Text, "$.b.c"),
+                column("b_d", DataType::Decimal(28, 2), "$.b.d"),
+                column("e", DataType::Json, "$.e"),
+                column("f", DataType::Text, "$.f"),
+                column("g", DataType::Json, "$.g"),
+                column("my_key", DataType::Date, r#"$["my key"]"#),
+                column("b_e", DataType::Json, "$.b.e"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_infer_csv() {
+        assert_eq!(
+            infer(
+                &SerdeOptions::default(),
+                &[
+                    r#"["1", "abc", "1.5", "true", "2020-01-01T10:00:00", ""]"#,
+                    r#"["2", "", "2", "FALSE", "2020-01-02T10:00:00", "3"]"#,
+                ]
+            ),
+            vec![
+                column("c1", DataType::Integer, "$[0]"),
+                column("c2", DataType::Text, "$[1]"),
+                column("c3", DataType::Decimal(28, 1), "$[2]"),
+                column("c4", DataType::Boolean, "$[3]"),
+                column("c5", DataType::Timestamp, "$[4]"),
+                column("c6", DataType::Integer, "$[5]"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_external_table_sql() {
+        let json = SerdeOptions {
+            delimiter: b',',
+            format: FileFormat::Json,
+            on_error: ErrorPolicy::Fail,
+            resume: false,
+            max_rows_per_second: None,
+            max_batch_rows: None,
+            changes: None,
+            snapshot: None,
+        };
+        let column = |name: &str, datatype, path: Option<&str>| ExternalColumn {
+            name: name.to_string(),
+            datatype,
+            path: path.map(str::to_string),
+        };
+        let columns = given_columns(
+            &json,
+            &[
+                column("a", DataType::Integer, None),
+                column("b.c", DataType::Text, None),
+                column("d", DataType::Date, Some("$.e[0]")),
+            ],
+        );
+        assert_eq!(
+            external_table_sql("it's/", &json, &columns),
+            r#"SELECT * FROM DIRECTORY 'it\'s/' WITH (format = 'json', delimiter = ',', on_error = 'fail', resume = 'false') COLUMNS (`a` INTEGER PATH '$.a', `b_c` TEXT PATH '$.b.c', `d` DATE PATH '$.e[0]')"#
+        );
+
+        let columns = given_columns(
+            &SerdeOptions::default(),
+            &[column("a.b", DataType::Text, None)],
+        );
+        assert_eq!(columns[0].path, "$[0]");
+
+        let throttled = SerdeOptions {
+            max_rows_per_second: Some(100),
+            max_batch_rows: Some(10),
+            ..SerdeOptions::default()
+        };
+        assert_eq!(
+            external_table_sql("data", &throttled, &columns),
+            r#"SELECT * FROM DIRECTORY 'data' WITH (format = 'csv', delimiter = ',', on_error = 'fail', resume = 'false', max_rows_per_second = '100', max_batch_rows = '10') COLUMNS (`a_b` TEXT PATH '$[0]')"#
+        );
+
+        let changes = SerdeOptions {
+            format: FileFormat::Json,
+            changes: Some(ChangeFormat::Debezium),
+            snapshot: Some("snapshot".to_string()),
+            ..SerdeOptions::default()
+        };
+        assert_eq!(
+            external_table_sql("changes", &changes, &columns),
+            r#"SELECT * FROM DIRECTORY 'changes' WITH (format = 'json', delimiter = ',', on_error = 'fail', resume = 'false', changes = 'debezium', snapshot = 'snapshot') COLUMNS (`a_b` TEXT PATH '$[0]')"#
+        );
+    }
+}
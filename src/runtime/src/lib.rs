@@ -1,18 +1,42 @@
+mod admission;
+mod analyze;
 pub mod connection;
 mod error;
+mod events;
+pub mod hooks;
+pub mod infer_schema;
+mod materialized_view;
+mod procedure;
+mod schema_migration;
+mod sink;
+pub mod sqllogictest;
+mod storage_tier;
+mod workload;
 
-pub use error::QueryError;
+pub use admission::AdmissionLimits;
+pub use error::{ErrorCode, QueryError};
+pub use events::EVENT_POLL_INTERVAL;
+pub use storage::{CompactionStyle, EncryptionKey, StorageOptions};
 
+use crate::admission::Admission;
 use crate::connection::Connection;
+use crate::hooks::ConnectionHook;
+use crate::workload::Scheduler;
 use catalog::Catalog;
-use data::Session;
-use functions::registry::Registry;
+use data::{Clock, Session};
+use functions::registry::{RegistrationError, Registry};
+use functions::FunctionDefinition;
 use planner::Planner;
 use std::collections::HashMap;
 use std::error::Error;
-use std::sync::atomic::Ordering;
-use std::sync::{Arc, RwLock, Weak};
-use storage::Storage;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::thread;
+use std::time::Duration;
+use storage::{Storage, Vacuum, VACUUM_INTERVAL};
+
+/// The default percentage of a table's rows that have to change before it's re-analyzed
+pub const DEFAULT_AUTO_ANALYZE_THRESHOLD: u32 = 20;
 
 /// Wraps all the runtime services of incresql.
 /// connections are created from a runtime and then sql can then be run against a connection.
@@ -20,6 +44,26 @@ use storage::Storage;
 pub struct Runtime {
     connections_state: RwLock<ConnectionsState>,
     planner: Planner,
+    // Held while a sink is flushed so the same changes aren't written out twice
+    sink_flushes: Mutex<()>,
+    // Held while a materialized view is refreshed so refreshes don't both apply the same changes
+    view_refreshes: Mutex<()>,
+    // Held while stale tables are re-analyzed, other connections skip the check rather than
+    // wait on it
+    auto_analyzes: Mutex<()>,
+    // The percentage of an analyzed table's rows that have to change before it's analyzed
+    // again, 0 turns it off
+    auto_analyze_threshold: AtomicU32,
+    hooks: RwLock<Vec<Arc<dyn ConnectionHook>>>,
+    // Limits how many statements run at once so bursts of heavy queries queue rather than
+    // starving everything else
+    admission: Arc<Admission>,
+    // Shares the cpu between interactive statements and maintenance
+    scheduler: Arc<Scheduler>,
+    // How long old versions of rows are kept and how far they've been vacuumed
+    vacuum: Vacuum,
+    // Held while old versions are vacuumed so a VACUUM doesn't run over the background one
+    vacuums: Mutex<()>,
 }
 
 #[derive(Debug)]
@@ -35,9 +79,29 @@ impl Runtime {
         Runtime::new_with_storage(storage)
     }
 
+    /// Create a new runtime with its storage tuned/encrypted by the options, when keys are being
+    /// rotated everything is compacted up front to re-encrypt it with the current key.
+    pub fn new_with_options(
+        db_path: &str,
+        options: &StorageOptions,
+    ) -> Result<Runtime, Box<dyn Error>> {
+        let storage = Storage::new_with_options(db_path, options)?;
+        let runtime = Runtime::new_with_storage(storage)?;
+        if options.encryption_key.is_some() && !options.previous_encryption_keys.is_empty() {
+            runtime
+                .planner
+                .catalog
+                .read()
+                .unwrap()
+                .compact_all_tables()?;
+        }
+        Ok(runtime)
+    }
+
     fn new_with_storage(storage: Storage) -> Result<Runtime, Box<dyn Error>> {
         let function_registry = Registry::new(true);
         let catalog = Catalog::new(storage)?;
+        let scheduler = Arc::new(Scheduler::new(catalog.workload_stats()));
         let planner = Planner::new(function_registry, catalog);
 
         let connections_state = RwLock::from(ConnectionsState {
@@ -48,6 +112,15 @@ impl Runtime {
         Ok(Runtime {
             connections_state,
             planner,
+            sink_flushes: Mutex::new(()),
+            view_refreshes: Mutex::new(()),
+            auto_analyzes: Mutex::new(()),
+            auto_analyze_threshold: AtomicU32::new(DEFAULT_AUTO_ANALYZE_THRESHOLD),
+            hooks: RwLock::default(),
+            admission: Arc::new(Admission::new(AdmissionLimits::default())),
+            scheduler,
+            vacuum: Vacuum::default(),
+            vacuums: Mutex::new(()),
         })
     }
 
@@ -55,6 +128,17 @@ impl Runtime {
     pub fn new_for_test() -> Runtime {
         Runtime::new_with_storage(Storage::new_in_mem().unwrap()).unwrap()
     }
+
+    /// Creates a new runtime with in-memory storage where the time only moves when the clock's
+    /// advanced, writes are timestamped and now() evaluated by it.
+    pub fn new_for_test_with_clock(clock: Clock) -> Runtime {
+        Runtime::new_with_storage(Storage::new_in_mem_with_clock(clock).unwrap()).unwrap()
+    }
+
+    /// The clock writes are timestamped by
+    pub fn clock(&self) -> Clock {
+        self.planner.catalog.read().unwrap().clock().clone()
+    }
 }
 
 impl Runtime {
@@ -63,11 +147,13 @@ impl Runtime {
         let mut connection_state = self.connections_state.write().unwrap();
         connection_state.connection_id_counter += 1;
         let connection_id = connection_state.connection_id_counter;
-        let session = Arc::new(Session::new(connection_id));
+        let session = Arc::new(Session::new_with_clock(connection_id, self.clock()));
         let connection = Arc::from(Connection {
             connection_id,
             session,
             runtime: &self,
+            transaction: Mutex::default(),
+            procedure_depth: AtomicU32::default(),
         });
 
         connection_state.connections.insert(
@@ -78,6 +164,100 @@ impl Runtime {
         connection
     }
 
+    /// Changes how many statements can run and queue at once, statements already running or
+    /// queued are unaffected
+    pub fn set_admission_limits(&self, limits: AdmissionLimits) {
+        self.admission.set_limits(limits);
+    }
+
+    /// Sets the percentage of time refreshes and sink flushes get while interactive statements
+    /// are running, they pause between batches of rows to keep to it
+    pub fn set_maintenance_share(&self, share: u8) {
+        self.scheduler.set_maintenance_share(share);
+    }
+
+    /// Sets the percentage of an analyzed table's rows that have to be written to before the
+    /// table's analyzed again ahead of the next query, 0 turns automatic analyzes off
+    pub fn set_auto_analyze_threshold(&self, percent: u32) {
+        self.auto_analyze_threshold
+            .store(percent, Ordering::Relaxed);
+    }
+
+    /// Runs the events that are due, returning once they've all run
+    pub fn run_due_events(&self) -> Result<(), QueryError> {
+        events::run_due_events(self)
+    }
+
+    /// Runs events as they come due, this method doesn't return
+    pub fn run_event_scheduler(&self) {
+        loop {
+            if let Err(err) = self.run_due_events() {
+                eprintln!("Error running events\n {:?}", err);
+            }
+            thread::sleep(EVENT_POLL_INTERVAL);
+        }
+    }
+
+    /// Sets how long old versions of rows are kept for, reads as of further back than this may
+    /// find rows missing once they've been vacuumed
+    pub fn set_vacuum_retention(&self, retention: Duration) {
+        self.vacuum.set_retention(retention);
+    }
+
+    /// Removes the old versions of rows that have fallen out of the retention window from every
+    /// table, returning the number of versions removed. Versions incremental views and sinks
+    /// are yet to read changes from are kept.
+    pub fn vacuum(&self) -> Result<u64, QueryError> {
+        let _vacuuming = self.vacuums.lock().unwrap();
+        let (watermark, tables) = {
+            let catalog = self.planner.catalog.read().unwrap();
+            let watermark = self
+                .vacuum
+                .watermark(catalog.clock().now(), catalog.oldest_needed_changes()?);
+            (watermark, catalog.storage_tables()?)
+        };
+        let mut removed = 0;
+        for table in &tables {
+            removed += table.vacuum(watermark)?;
+        }
+        self.vacuum.vacuumed(watermark);
+        Ok(removed)
+    }
+
+    /// Vacuums every VACUUM_INTERVAL, this method doesn't return
+    pub fn run_vacuum_scheduler(&self) {
+        loop {
+            thread::sleep(VACUUM_INTERVAL);
+            if let Err(err) = self.vacuum() {
+                eprintln!("Error vacuuming\n {:?}", err);
+            }
+        }
+    }
+
+    /// Registers an embedder's scalar or aggregate function to be called from sql as
+    /// namespace.name, done as the runtime's set up before any connections are made.
+    pub fn register_function(
+        &mut self,
+        namespace: &str,
+        function_definition: FunctionDefinition,
+    ) -> Result<(), RegistrationError> {
+        self.planner
+            .function_registry
+            .register_extension_function(namespace, function_definition)
+    }
+
+    /// Registers a hook to be called as connections connect, run statements and disconnect
+    pub fn add_hook(&self, hook: Arc<dyn ConnectionHook>) {
+        self.hooks.write().unwrap().push(hook);
+    }
+
+    /// Calls f with each of the registered hooks
+    fn fire_hooks<F: Fn(&dyn ConnectionHook)>(&self, f: F) {
+        for hook in self.hooks.read().unwrap().iter() {
+            f(hook.as_ref());
+        }
+    }
+
     /// Marks the connection_id passed as killed, its then up to the executors to bail out.
     pub fn kill_connection(&self, connection_id: u32) {
         let mut connection_state = self.connections_state.write().unwrap();
@@ -91,6 +271,19 @@ impl Runtime {
             });
     }
 
+    /// The sessions of the open connections, ordered by connection id
+    fn sessions(&self) -> Vec<Arc<Session>> {
+        let connection_state = self.connections_state.read().unwrap();
+        let mut sessions: Vec<_> = connection_state
+            .connections
+            .values()
+            .filter_map(|connection| connection.upgrade())
+            .map(|connection| connection.session.clone())
+            .collect();
+        sessions.sort_by_key(|session| session.connection_id);
+        sessions
+    }
+
     /// Used by connections when they're dropped to clean up any state
     fn remove_connection(&self, connection_id: u32) {
         let mut connection_state = self.connections_state.write().unwrap();
@@ -101,6 +294,8 @@ impl Runtime {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use data::{DataType, Datum};
+    use functions::{Function, FunctionSignature, FunctionType};
 
     #[test]
     fn test_new_connection() {
@@ -150,4 +345,94 @@ mod tests {
             0
         );
     }
+
+    #[derive(Debug)]
+    struct Double {}
+
+    impl Function for Double {
+        fn execute<'a>(
+            &self,
+            _session: &Session,
+            _signature: &FunctionSignature,
+            args: &'a [Datum<'a>],
+        ) -> Datum<'a> {
+            match args[0].as_maybe_integer() {
+                Some(i) => Datum::from(i * 2),
+                None => Datum::Null,
+            }
+        }
+    }
+
+    #[test]
+    fn test_register_function() -> Result<(), QueryError> {
+        let mut runtime = Runtime::new_for_test();
+        let definition = || {
+            FunctionDefinition::new(
+                "double",
+                vec![DataType::Integer],
+                DataType::Integer,
+                FunctionType::Scalar(&Double {}),
+            )
+        };
+        runtime.register_function("ext", definition()).unwrap();
+        assert_eq!(
+            runtime.register_function("ext", definition()),
+            Err(RegistrationError::DuplicateFunction(
+                "ext.double".to_string(),
+                vec![DataType::Integer]
+            ))
+        );
+
+        let connection = runtime.new_connection();
+        let (_, mut executor) = connection.execute_statement("select ext.double(21)")?;
+        assert_eq!(executor.next()?, Some(([Datum::from(42)].as_ref(), 1)));
+        Ok(())
+    }
+
+    #[derive(Debug, Default)]
+    struct RecordingHook {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl ConnectionHook for RecordingHook {
+        fn on_connect(&self, session: &Session) {
+            let user = session.user.read().unwrap();
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("connect {}", user));
+        }
+
+        fn on_disconnect(&self, session: &Session) {
+            let user = session.user.read().unwrap();
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("disconnect {}", user));
+        }
+
+        fn on_statement(&self, _session: &Session, sql: &str) {
+            self.events.lock().unwrap().push(sql.to_string());
+        }
+    }
+
+    #[test]
+    fn test_hooks() -> Result<(), QueryError> {
+        let runtime = Runtime::new_for_test();
+        let hook = Arc::new(RecordingHook::default());
+        runtime.add_hook(hook.clone());
+
+        let connection = runtime.new_connection();
+        connection.login("bob")?;
+        connection.execute_statement("show tables")?;
+        let prepared = connection.prepare_statement("select 1")?;
+        connection.execute_prepared_statement(&prepared, &[])?;
+        std::mem::drop(connection);
+
+        assert_eq!(
+            *hook.events.lock().unwrap(),
+            vec!["connect bob", "show tables", "select 1", "disconnect bob"]
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,469 @@
+use crate::workload::Scheduler;
+use crate::QueryError;
+use ast::expr::{ColumnReference, Expression, FunctionCall};
+use ast::rel::logical::{Filter, LogicalOperator, TableAlias};
+use ast::statement::Statement;
+use catalog::{
+    corrections_table_name, partial_states_table_name, CatalogItem, LateData, TableOrView, View,
+};
+use data::chrono::Duration;
+use data::encoding_tuple::encode_tuple;
+use data::{DataType, Datum, LogicalTimestamp, Session, TupleIter};
+use executor::aggregate_expression::{AggregateExpression, EvalAggregateRow};
+use executor::point_in_time::build_executor;
+use executor::{ExecutionError, ExprArena};
+use planner::{Planner, READ_BARRIER_WAIT};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use storage::Table;
+
+/// Recomputes a materialized view as of the read barrier and writes the difference from its
+/// current rows into its table in one batch, so readers see either the old rows or the new.
+/// Only writing the difference keeps the table's history meaningful for sinks etc.
+/// Views with a state ttl close the rows whose time was more than the ttl behind the latest
+/// time in the view as of the last refresh, late changes to closed rows are either dropped,
+/// kept in the view's corrections table or applied like any other change (retracting the
+/// closed row and re-emitting it).
+/// Closed rows are only recomputed when late changes aren't dropped.
+/// Incrementally maintained views only apply the changes to their tables since they were last
+/// refreshed, see maintain_materialized_view.
+/// Refreshing is maintenance so gives way to interactive statements between batches of rows.
+pub(crate) fn refresh_materialized_view(
+    planner: &Planner,
+    scheduler: &Scheduler,
+    session: &Session,
+    database: &str,
+    name: &str,
+) -> Result<(), QueryError> {
+    let (view, table, columns, state_ttl, maintained_at, owner) = {
+        let catalog = planner.catalog.read().unwrap();
+        let (view, table) = catalog.materialized_view(database, name)?;
+        let columns = catalog.item(database, name)?.columns;
+        (
+            view,
+            table,
+            columns,
+            catalog.state_ttl(database, name)?,
+            catalog.view_maintained_at(database, name)?,
+            catalog.table_owner(database, name)?,
+        )
+    };
+    let (view_session, mut query) = view_query(session, view)?;
+    // The view holds the rows its owner can see, whoever refreshes it
+    if let Some(owner) = owner {
+        *view_session.user.write().unwrap() = owner;
+    }
+    if let Some(maintained_at) = maintained_at {
+        if maintain_materialized_view(
+            planner,
+            scheduler,
+            (&view_session, database, name),
+            query.clone(),
+            &table,
+            maintained_at,
+        )? {
+            return Ok(());
+        }
+    }
+    let corrections_table = match &state_ttl {
+        Some(state_ttl) if state_ttl.late_data == LateData::Corrections => {
+            let catalog = planner.catalog.read().unwrap();
+            match catalog.item(database, &corrections_table_name(name))?.item {
+                TableOrView::Table(table) => Some(table),
+                TableOrView::View(_) => None,
+            }
+        }
+        _ => None,
+    };
+    let time_idx = state_ttl.as_ref().and_then(|state_ttl| {
+        columns
+            .iter()
+            .position(|(alias, _)| alias == &state_ttl.time_column)
+    });
+    let row_time = |tuple: &[Datum]| time_idx.and_then(|idx| tuple[idx].as_maybe_timestamp());
+
+    let mut current = vec![];
+    let mut latest = None;
+    let mut current_iter = table.full_scan(LogicalTimestamp::MAX);
+    while let Some((tuple, freq)) = current_iter.next()? {
+        check_intermediate_rows(session, (database, name), current.len())?;
+        latest = latest.max(row_time(tuple));
+        current.push((tuple.iter().map(Datum::as_static).collect::<Vec<_>>(), freq));
+    }
+
+    let open_from = match (&state_ttl, latest) {
+        (Some(state_ttl), Some(latest)) if state_ttl.late_data != LateData::Retract => {
+            Some(latest - Duration::milliseconds(state_ttl.ttl_ms))
+        }
+        _ => None,
+    };
+    // Rows without a time never close
+    let closed = |tuple: &[Datum]| match (row_time(tuple), open_from) {
+        (Some(time), Some(open_from)) => time < open_from,
+        _ => false,
+    };
+
+    // Closed rows can't change when late changes are dropped so there's no need to recompute them
+    if let (Some(state_ttl), Some(open_from)) = (&state_ttl, open_from) {
+        if state_ttl.late_data == LateData::Drop {
+            query = LogicalOperator::Filter(Filter {
+                predicate: Expression::FunctionCall(FunctionCall {
+                    function_name: ">=".to_string(),
+                    args: vec![
+                        Expression::ColumnReference(ColumnReference {
+                            qualifier: None,
+                            alias: state_ttl.time_column.clone(),
+                            star: false,
+                        }),
+                        Expression::Constant(Datum::from(open_from), DataType::Timestamp),
+                    ],
+                }),
+                source: Box::new(LogicalOperator::TableAlias(TableAlias {
+                    alias: name.to_string(),
+                    source: Box::new(query),
+                })),
+                pinned: false,
+            });
+        }
+    }
+    let mut plan = planner.plan_for_point_in_time(query, &view_session)?;
+    let timestamp = planner
+        .catalog
+        .read()
+        .unwrap()
+        .read_barrier(READ_BARRIER_WAIT);
+    plan.operator.set_scan_timestamps(timestamp);
+
+    let mut changes: HashMap<Vec<Datum<'static>>, i64> = HashMap::new();
+    let mut late_changes: HashMap<Vec<Datum<'static>>, i64> = HashMap::new();
+    let mut yielder = scheduler.yielder();
+    let mut executor = build_executor(&view_session, &plan.operator);
+    while let Some((tuple, freq)) = executor.next()? {
+        yielder.tick();
+        let rows = current.len() + changes.len() + late_changes.len();
+        check_intermediate_rows(session, (database, name), rows)?;
+        let changes = if closed(tuple) {
+            &mut late_changes
+        } else {
+            &mut changes
+        };
+        let tuple = tuple.iter().map(Datum::as_static).collect();
+        *changes.entry(tuple).or_insert(0) += freq;
+    }
+    for (tuple, freq) in current {
+        let changes = if closed(&tuple) {
+            &mut late_changes
+        } else {
+            &mut changes
+        };
+        *changes.entry(tuple).or_insert(0) -= freq;
+    }
+
+    // The corrections table holds the up to date version of the closed rows late changes hit
+    let mut corrections = HashMap::new();
+    if let Some(corrections_table) = &corrections_table {
+        for (tuple, freq) in late_changes.into_iter().filter(|(_tuple, freq)| *freq > 0) {
+            corrections.insert(tuple, freq);
+        }
+        let mut current_iter = corrections_table.full_scan(LogicalTimestamp::MAX);
+        while let Some((tuple, freq)) = current_iter.next()? {
+            let tuple = tuple.iter().map(Datum::as_static).collect();
+            *corrections.entry(tuple).or_insert(0) -= freq;
+        }
+    }
+
+    table.atomic_write::<_, ExecutionError>(|batch| {
+        let now = table.clock().now();
+        for (tuple, freq) in changes.iter().filter(|(_tuple, freq)| **freq != 0) {
+            batch.write_tuple(&table, tuple, now, *freq)?;
+        }
+        if let Some(corrections_table) = &corrections_table {
+            for (tuple, freq) in corrections.iter().filter(|(_tuple, freq)| **freq != 0) {
+                batch.write_tuple(corrections_table, tuple, now, *freq)?;
+            }
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
+
+/// Brings an incrementally maintained view up to date by running its query over the changes to
+/// its tables since it was last maintained, so the work done is down to what's changed rather
+/// than the size of the view. The changes to the view's rows are written in the same batch as
+/// the new maintained at timestamp.
+/// Views aggregating over a union all are maintained through their partial states instead, see
+/// maintain_partial_aggregate.
+/// Returns false if the view's query can no longer be planned incrementally, ie one of its
+/// tables has been swapped for a view, in which case it's left for a full refresh.
+fn maintain_materialized_view(
+    planner: &Planner,
+    scheduler: &Scheduler,
+    (view_session, database, name): (&Arc<Session>, &str, &str),
+    query: LogicalOperator,
+    table: &Table,
+    maintained_at: LogicalTimestamp,
+) -> Result<bool, QueryError> {
+    let timestamp = planner
+        .catalog
+        .read()
+        .unwrap()
+        .read_barrier(READ_BARRIER_WAIT);
+    if timestamp <= maintained_at {
+        return Ok(true);
+    }
+    let plan =
+        match planner.plan_for_delta(query.clone(), view_session, maintained_at, timestamp)? {
+            Some(plan) => plan,
+            None => {
+                return maintain_partial_aggregate(
+                    planner,
+                    scheduler,
+                    (view_session, database, name),
+                    query,
+                    table,
+                    (maintained_at, timestamp),
+                )
+            }
+        };
+
+    let mut changes: HashMap<Vec<Datum<'static>>, i64> = HashMap::new();
+    let mut yielder = scheduler.yielder();
+    let mut executor = build_executor(view_session, &plan.operator);
+    while let Some((tuple, freq)) = executor.next()? {
+        yielder.tick();
+        check_intermediate_rows(view_session, (database, name), changes.len())?;
+        let tuple = tuple.iter().map(Datum::as_static).collect();
+        *changes.entry(tuple).or_insert(0) += freq;
+    }
+
+    let catalog = planner.catalog.read().unwrap();
+    table.atomic_write::<_, ExecutionError>(|batch| {
+        let now = table.clock().now();
+        for (tuple, freq) in changes.iter().filter(|(_tuple, freq)| **freq != 0) {
+            batch.write_tuple(table, tuple, now, *freq)?;
+        }
+        catalog.write_view_maintained_at(batch, database, name, timestamp);
+        Ok(())
+    })?;
+    Ok(true)
+}
+
+/// Maintains a view that aggregates over a union all from the partial aggregate states each of
+/// the union's sources keeps per group in the view's partial states table. Only the states of
+/// the sources that changed are updated, the view's row for each group they touched is then
+/// recomputed by merging the group's states from every source. The new states are written in
+/// the same batch as the changes to the view's rows.
+/// Returns false if the query can't be split this way or the view doesn't have a partial states
+/// table to keep the states in.
+fn maintain_partial_aggregate(
+    planner: &Planner,
+    scheduler: &Scheduler,
+    (view_session, database, name): (&Arc<Session>, &str, &str),
+    query: LogicalOperator,
+    table: &Table,
+    (maintained_at, timestamp): (LogicalTimestamp, LogicalTimestamp),
+) -> Result<bool, QueryError> {
+    let states_table = {
+        let catalog = planner.catalog.read().unwrap();
+        match catalog.item(database, &partial_states_table_name(name)) {
+            Ok(CatalogItem {
+                item: TableOrView::Table(states_table),
+                ..
+            }) => states_table,
+            _ => return Ok(false),
+        }
+    };
+    let plan =
+        match planner.plan_for_partial_aggregate(query, view_session, maintained_at, timestamp)? {
+            Some(plan) => plan,
+            None => return Ok(false),
+        };
+    let expressions: Vec<_> = plan
+        .expressions
+        .iter()
+        .map(AggregateExpression::from)
+        .collect();
+
+    // The state of each source for each group touched, as it was and as it is now
+    let mut states: HashMap<(i32, Vec<Datum<'static>>), (PartialState, PartialState)> =
+        HashMap::new();
+    let mut arena = ExprArena::default();
+    let mut yielder = scheduler.yielder();
+    for (source, source_plan) in plan.sources.iter().enumerate() {
+        let source = source as i32;
+        let mut executor = build_executor(view_session, source_plan);
+        while let Some((tuple, freq)) = executor.next()? {
+            yielder.tick();
+            check_intermediate_rows(view_session, (database, name), states.len())?;
+            let key: Vec<_> = tuple[..plan.key_len].iter().map(Datum::as_static).collect();
+            let (_before, after) = match states.entry((source, key)) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let state = read_partial_state(&states_table, &expressions, entry.key())?;
+                    entry.insert((state.clone(), state))
+                }
+            };
+            after.rows += freq;
+            expressions.apply(view_session, tuple, freq, &mut after.state, &mut arena);
+        }
+    }
+
+    // Each touched group's row is retracted and replaced with the merge of its new states, the
+    // sources that didn't touch the group have the same state before and after
+    let keys: HashSet<_> = states.keys().map(|(_source, key)| key.clone()).collect();
+    let mut changes: HashMap<Vec<Datum<'static>>, i64> = HashMap::new();
+    for key in keys {
+        let mut before = vec![];
+        let mut after = vec![];
+        for source in 0..plan.sources.len() as i32 {
+            let source_key = (source, key.clone());
+            match states.get(&source_key) {
+                Some((source_before, source_after)) => {
+                    before.push(source_before.clone());
+                    after.push(source_after.clone());
+                }
+                None => {
+                    let state = read_partial_state(&states_table, &expressions, &source_key)?;
+                    before.push(state.clone());
+                    after.push(state);
+                }
+            }
+        }
+        if let Some(row) = merged_row(view_session, &expressions, &before) {
+            *changes.entry(row).or_insert(0) -= 1;
+        }
+        if let Some(row) = merged_row(view_session, &expressions, &after) {
+            *changes.entry(row).or_insert(0) += 1;
+        }
+    }
+
+    let catalog = planner.catalog.read().unwrap();
+    table.atomic_write::<_, ExecutionError>(|batch| {
+        let now = table.clock().now();
+        for (tuple, freq) in changes.iter().filter(|(_tuple, freq)| **freq != 0) {
+            batch.write_tuple(table, tuple, now, *freq)?;
+        }
+        let mut key_buf = vec![];
+        let mut state_buf = vec![];
+        for ((source, key), (_before, after)) in &states {
+            key_buf.clear();
+            encode_tuple(key, &mut key_buf);
+            let pk = [Datum::from(*source), Datum::ByteARef(&key_buf)];
+            if after.rows == 0 {
+                batch.system_delete_tuple(&states_table, &pk);
+            } else {
+                state_buf.clear();
+                expressions.serialize_state(&after.state, &mut state_buf);
+                let tuple = [
+                    pk[0].ref_clone(),
+                    pk[1].ref_clone(),
+                    Datum::from(after.rows),
+                    Datum::ByteARef(&state_buf),
+                ];
+                batch.system_write_tuple(&states_table, &tuple, 1);
+            }
+        }
+        catalog.write_view_maintained_at(batch, database, name, timestamp);
+        Ok(())
+    })?;
+    Ok(true)
+}
+
+/// Refreshes hold the rows of a view that are changing in memory until they're written, this
+/// puts a limit on how many so a view whose query blows up (ie a join missing its condition)
+/// errors rather than running out of memory.
+fn check_intermediate_rows(
+    session: &Session,
+    (database, name): (&str, &str),
+    rows: usize,
+) -> Result<(), QueryError> {
+    let max_rows = *session.max_intermediate_rows.read().unwrap();
+    if rows as u64 >= max_rows {
+        Err(QueryError::IntermediateRowsExceeded(
+            database.to_string(),
+            name.to_string(),
+            max_rows,
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// A source's aggregate state for a group along with how many of the source's rows are in it
+#[derive(Debug, Clone, Default)]
+struct PartialState {
+    rows: i64,
+    state: Vec<Datum<'static>>,
+}
+
+/// Reads the source's state for the group from the partial states table, groups the source
+/// doesn't have any rows in get a freshly initialized state.
+fn read_partial_state(
+    states_table: &Table,
+    expressions: &[AggregateExpression],
+    (source, key): &(i32, Vec<Datum<'static>>),
+) -> Result<PartialState, QueryError> {
+    let mut key_buf = vec![];
+    encode_tuple(key, &mut key_buf);
+    let pk = [Datum::from(*source), Datum::ByteARef(&key_buf)];
+    let mut state = expressions.initialize();
+    let mut lookup_buf = vec![];
+    let mut value = vec![];
+    let rows = match states_table.system_point_lookup(&pk, &mut lookup_buf, &mut value)? {
+        Some(_) => {
+            expressions
+                .deserialize_state(value[1].as_bytea(), &mut state)
+                .map_err(ExecutionError::from)?;
+            value[0].as_bigint()
+        }
+        None => 0,
+    };
+    Ok(PartialState { rows, state })
+}
+
+/// Merges the sources' states for a group into the group's row, None if none of the sources
+/// have any rows in the group
+fn merged_row(
+    session: &Session,
+    expressions: &[AggregateExpression],
+    states: &[PartialState],
+) -> Option<Vec<Datum<'static>>> {
+    let mut rows = 0;
+    let mut merged = expressions.initialize();
+    for partial in states {
+        rows += partial.rows;
+        expressions.merge(&partial.state, &mut merged);
+    }
+    if rows == 0 {
+        return None;
+    }
+    let mut arena = ExprArena::default();
+    let mut row = vec![Datum::Null; expressions.len()];
+    expressions.finalize(session, &merged, &mut row, &mut arena);
+    Some(row.iter().map(Datum::as_static).collect())
+}
+
+/// Parses the materialized view's query, it has to be planned with the returned session as
+/// its sql is relative to the database it was created from. The session is for the same user
+/// so the same policies apply.
+pub fn view_query(
+    session: &Session,
+    view: View,
+) -> Result<(Arc<Session>, LogicalOperator), QueryError> {
+    let view_session = Arc::new(Session::new_with_clock(
+        session.connection_id,
+        session.clock.clone(),
+    ));
+    *view_session.current_database.write().unwrap() = view.db_context;
+    *view_session.user.write().unwrap() = session.user.read().unwrap().clone();
+    *view_session.max_recursion_depth.write().unwrap() =
+        *session.max_recursion_depth.read().unwrap();
+    *view_session.max_intermediate_rows.write().unwrap() =
+        *session.max_intermediate_rows.read().unwrap();
+    *view_session.max_value_bytes.write().unwrap() = *session.max_value_bytes.read().unwrap();
+    match parser::parse(&view.sql)? {
+        Statement::Query(query) => Ok((view_session, query)),
+        _ => panic!("Bogus materialized view"),
+    }
+}
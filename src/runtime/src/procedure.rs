@@ -0,0 +1,77 @@
+use crate::connection::QueryResult;
+use crate::QueryError;
+use ast::expr::{Cast, Expression, NamedExpression};
+use ast::rel::logical::{LogicalOperator, Project};
+use ast::statement::{ProcedureStatement, Statement};
+use data::{DataType, Session};
+use executor::ExecutionError;
+use std::sync::atomic::Ordering;
+
+/// Runs the body of a procedure, statements are run one after another with their results read
+/// to the end and thrown away, anything a procedure wants to keep around goes in user variables.
+/// Each statement (and each condition) is run through execute as if it were run on its own.
+pub(crate) fn run_procedure<F>(
+    body: &[ProcedureStatement],
+    session: &Session,
+    execute: &mut F,
+) -> Result<(), QueryError>
+where
+    F: FnMut(Statement) -> QueryResult,
+{
+    for procedure_statement in body {
+        // Loops can run for as long as they like so give kill a chance to stop them
+        if session.kill_flag.load(Ordering::Relaxed) {
+            return Err(QueryError::from(ExecutionError::Killed));
+        }
+        match procedure_statement {
+            ProcedureStatement::Statement(statement) => {
+                let (_fields, mut executor) = execute(Statement::clone(statement))?;
+                while executor.next()?.is_some() {}
+            }
+            ProcedureStatement::If(if_) => {
+                let mut branch = &if_.otherwise;
+                for (condition, body) in &if_.branches {
+                    if is_true(condition, execute)? {
+                        branch = body;
+                        break;
+                    }
+                }
+                run_procedure(branch, session, execute)?;
+            }
+            ProcedureStatement::While(while_) => {
+                while is_true(&while_.condition, execute)? {
+                    run_procedure(&while_.body, session, execute)?;
+                    if session.kill_flag.load(Ordering::Relaxed) {
+                        return Err(QueryError::from(ExecutionError::Killed));
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Evaluates the condition as a SELECT CAST(condition AS BOOLEAN), null counts as false.
+fn is_true<F>(condition: &Expression, execute: &mut F) -> Result<bool, QueryError>
+where
+    F: FnMut(Statement) -> QueryResult,
+{
+    let query = LogicalOperator::Project(Project {
+        distinct: false,
+        expressions: vec![NamedExpression {
+            alias: None,
+            expression: Expression::Cast(Cast {
+                expr: Box::new(condition.clone()),
+                datatype: DataType::Boolean,
+                try_cast: false,
+            }),
+        }],
+        source: Box::new(LogicalOperator::Single),
+    });
+    let (_fields, mut executor) = execute(Statement::Query(query))?;
+    let is_true = match executor.next()? {
+        Some((tuple, _freq)) => tuple[0].as_maybe_boolean() == Some(true),
+        None => false,
+    };
+    Ok(is_true)
+}
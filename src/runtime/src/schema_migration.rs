@@ -0,0 +1,240 @@
+use crate::workload::Scheduler;
+use crate::QueryError;
+use ast::expr::{Cast, ColumnReference, Expression, NamedExpression};
+use ast::rel::logical::{LogicalOperator, Project, TableReference};
+use catalog::{CatalogError, MigrationStatus, SchemaMigration, TableOptions, TableOrView};
+use data::{DataType, Datum, LogicalTimestamp, Session, TupleIter};
+use executor::point_in_time::build_executor;
+use executor::ExecutionError;
+use planner::{Planner, READ_BARRIER_WAIT};
+use std::collections::HashMap;
+use std::sync::Arc;
+use storage::Table;
+
+/// Rows are copied into the shadow table in batches of this many, progress is recorded after
+/// each batch
+const BACKFILL_BATCH_ROWS: usize = 1024;
+
+/// The name of the table a column type change is backfilled into before it's swapped in
+pub fn shadow_table_name(name: &str) -> String {
+    format!("{}__shadow", name)
+}
+
+/// Changes the type of a table's column. Datums are encoded differently for each type so the
+/// rows can't be changed in place, instead they're copied into a shadow table with the column
+/// cast to its new type which is then swapped in for the table, progress is kept in
+/// incresql.schema_migrations as the backfill goes. Rows written during the backfill are caught
+/// up on before the swap, though anything written between the catch up and the swap is lost
+/// so writes are best paused for the duration.
+pub(crate) fn modify_column(
+    planner: &Planner,
+    scheduler: &Scheduler,
+    session: &Arc<Session>,
+    database: &str,
+    name: &str,
+    column: &str,
+    datatype: DataType,
+) -> Result<(), QueryError> {
+    let item = planner.catalog.read().unwrap().item(database, name)?;
+    let table = match item.item {
+        TableOrView::Table(table) => table,
+        TableOrView::View(_) => {
+            return Err(CatalogError::NotATable(database.to_string(), name.to_string()).into())
+        }
+    };
+    let column_idx = item
+        .columns
+        .iter()
+        .position(|(alias, _)| alias == column)
+        .ok_or_else(|| {
+            QueryError::ColumnNotFound(database.to_string(), name.to_string(), column.to_string())
+        })?;
+    if item.columns[column_idx].1 == datatype {
+        return Ok(());
+    }
+
+    let mut rows_total = 0;
+    let mut iter = table.full_scan(LogicalTimestamp::MAX);
+    while iter.next()?.is_some() {
+        rows_total += 1;
+    }
+    let mut migration = SchemaMigration {
+        database: database.to_string(),
+        table: name.to_string(),
+        column: column.to_string(),
+        datatype,
+        rows_total,
+        rows_copied: 0,
+        status: MigrationStatus::Backfilling,
+    };
+
+    let mut columns = item.columns.clone();
+    columns[column_idx].1 = datatype;
+    let shadow_name = shadow_table_name(name);
+    let shadow = {
+        let mut catalog = planner.catalog.write().unwrap();
+        let options = TableOptions {
+            checksums: table.checksums(),
+            null_bitmap: table.null_bitmap(),
+            primary_key: table.primary_key().map(|key_columns| {
+                key_columns
+                    .iter()
+                    .copied()
+                    .zip(table.pk().iter().copied())
+                    .collect()
+            }),
+        };
+        catalog.create_table_with_options(database, &shadow_name, &columns, &options)?;
+        catalog.update_schema_migration(&migration)?;
+        match catalog.item(database, &shadow_name)?.item {
+            TableOrView::Table(table) => table,
+            TableOrView::View(_) => unreachable!(),
+        }
+    };
+
+    let query = cast_query(database, name, &item.columns, column_idx, datatype);
+    let result = backfill(planner, scheduler, session, &query, &shadow, &mut migration)
+        .and_then(|timestamp| catch_up(planner, session, &query, &shadow, timestamp))
+        .map(|caught_up| migration.rows_copied += caught_up);
+    let mut catalog = planner.catalog.write().unwrap();
+    match result {
+        Ok(()) => {
+            catalog.swap_in_shadow_table(database, name, &shadow_name)?;
+            migration.status = MigrationStatus::Done;
+            catalog.update_schema_migration(&migration)?;
+            Ok(())
+        }
+        Err(err) => {
+            catalog.drop_table(database, &shadow_name)?;
+            migration.status = MigrationStatus::Failed;
+            catalog.update_schema_migration(&migration)?;
+            Err(err)
+        }
+    }
+}
+
+/// Selects all of the table's columns with the one being changed cast to its new type
+fn cast_query(
+    database: &str,
+    name: &str,
+    columns: &[(String, DataType)],
+    column_idx: usize,
+    datatype: DataType,
+) -> LogicalOperator {
+    let expressions = columns
+        .iter()
+        .enumerate()
+        .map(|(idx, (alias, _))| {
+            let mut expression = Expression::ColumnReference(ColumnReference {
+                qualifier: None,
+                alias: alias.clone(),
+                star: false,
+            });
+            if idx == column_idx {
+                expression = Expression::Cast(Cast {
+                    expr: Box::new(expression),
+                    datatype,
+                    try_cast: false,
+                });
+            }
+            NamedExpression {
+                alias: Some(alias.clone()),
+                expression,
+            }
+        })
+        .collect();
+    LogicalOperator::Project(Project {
+        distinct: false,
+        expressions,
+        source: Box::new(LogicalOperator::TableReference(TableReference {
+            database: Some(database.to_string()),
+            table: name.to_string(),
+            sample: None,
+            as_of: None,
+        })),
+    })
+}
+
+/// Copies the table's rows as of the read barrier into the shadow table, returning the
+/// timestamp they were read at.
+fn backfill(
+    planner: &Planner,
+    scheduler: &Scheduler,
+    session: &Arc<Session>,
+    query: &LogicalOperator,
+    shadow: &Table,
+    migration: &mut SchemaMigration,
+) -> Result<LogicalTimestamp, QueryError> {
+    let timestamp = planner
+        .catalog
+        .read()
+        .unwrap()
+        .read_barrier(READ_BARRIER_WAIT);
+    let mut plan = planner.plan_for_point_in_time(query.clone(), session)?;
+    plan.operator.set_scan_timestamps(timestamp);
+
+    let mut yielder = scheduler.yielder();
+    let mut batch = vec![];
+    let mut executor = build_executor(session, &plan.operator);
+    loop {
+        let next = executor.next()?;
+        if let Some((tuple, freq)) = next {
+            yielder.tick();
+            batch.push((tuple.iter().map(Datum::as_static).collect::<Vec<_>>(), freq));
+        }
+        if batch.len() == BACKFILL_BATCH_ROWS || (next.is_none() && !batch.is_empty()) {
+            migration.rows_copied += batch.len() as i64;
+            write_rows(shadow, batch.drain(..))?;
+            planner
+                .catalog
+                .write()
+                .unwrap()
+                .update_schema_migration(migration)?;
+        }
+        if next.is_none() {
+            return Ok(timestamp);
+        }
+    }
+}
+
+/// Applies the changes made to the table since the backfill read it to the shadow table, the
+/// table's read as it is now rather than as of a read barrier so nothing's left behind.
+/// Returns the number of rows added to the shadow table.
+fn catch_up(
+    planner: &Planner,
+    session: &Arc<Session>,
+    query: &LogicalOperator,
+    shadow: &Table,
+    backfilled_at: LogicalTimestamp,
+) -> Result<i64, QueryError> {
+    let mut changes: HashMap<Vec<Datum<'static>>, i64> = HashMap::new();
+    for (timestamp, sign) in &[(backfilled_at, -1), (LogicalTimestamp::MAX, 1)] {
+        let mut plan = planner.plan_for_point_in_time(query.clone(), session)?;
+        plan.operator.set_scan_timestamps(*timestamp);
+        let mut executor = build_executor(session, &plan.operator);
+        while let Some((tuple, freq)) = executor.next()? {
+            let tuple = tuple.iter().map(Datum::as_static).collect();
+            *changes.entry(tuple).or_insert(0) += freq * sign;
+        }
+    }
+    let added = changes.values().filter(|freq| **freq > 0).count() as i64;
+    write_rows(
+        shadow,
+        changes.into_iter().filter(|(_tuple, freq)| *freq != 0),
+    )?;
+    Ok(added)
+}
+
+fn write_rows<I: Iterator<Item = (Vec<Datum<'static>>, i64)>>(
+    table: &Table,
+    rows: I,
+) -> Result<(), QueryError> {
+    table.atomic_write::<_, ExecutionError>(|batch| {
+        let now = table.clock().now();
+        for (tuple, freq) in rows {
+            batch.write_tuple(table, &tuple, now, freq)?;
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
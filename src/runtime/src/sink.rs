@@ -0,0 +1,121 @@
+use crate::workload::Scheduler;
+use crate::QueryError;
+use ast::rel::logical::{LogicalOperator, TableReference};
+use catalog::Sink;
+use data::json::JsonBuilder;
+use data::{DataType, Datum, LogicalTimestamp, Session};
+use executor::point_in_time::build_executor;
+use planner::{Field, Planner, READ_BARRIER_WAIT};
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::Arc;
+
+/// Writes the changes made to the sink's source since it was last flushed out to its file.
+/// The changes are worked out by reading the source as of the last flush and as of now and
+/// diffing the two, each row that's come or gone is written as a debezium style json event
+/// so the file can be read back in with CHANGES = 'debezium'.
+pub(crate) fn flush_sink(
+    planner: &Planner,
+    scheduler: &Scheduler,
+    session: &Arc<Session>,
+    sink: &mut Sink,
+) -> Result<(), QueryError> {
+    let mut timestamp = planner
+        .catalog
+        .read()
+        .unwrap()
+        .read_barrier(READ_BARRIER_WAIT);
+    let mut changes = BTreeMap::new();
+    if let Some(emitted_at) = sink.emitted_at {
+        timestamp = LogicalTimestamp::new(timestamp.ms.max(emitted_at.ms));
+        read_source(
+            planner,
+            scheduler,
+            session,
+            sink,
+            emitted_at,
+            -1,
+            &mut changes,
+        )?;
+    }
+    read_source(
+        planner,
+        scheduler,
+        session,
+        sink,
+        timestamp,
+        1,
+        &mut changes,
+    )?;
+
+    // Removals go first so consumers keyed on a primary key see updates in the right order
+    let mut events = String::new();
+    for (row, freq) in changes.iter().filter(|(_row, freq)| **freq < 0) {
+        for _ in 0..-freq {
+            events.push_str(&format!("{{\"op\":\"d\",\"before\":{}}}\n", row));
+        }
+    }
+    for (row, freq) in changes.iter().filter(|(_row, freq)| **freq > 0) {
+        for _ in 0..*freq {
+            events.push_str(&format!("{{\"op\":\"c\",\"after\":{}}}\n", row));
+        }
+    }
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&sink.path)
+        .and_then(|mut file| file.write_all(events.as_bytes()))
+        .map_err(|err| QueryError::SinkWriteError(sink.path.clone(), err.to_string()))?;
+    sink.emitted_at = Some(timestamp);
+    Ok(())
+}
+
+/// Reads the source as of the timestamp adding the rows into changes, keyed by their json.
+fn read_source(
+    planner: &Planner,
+    scheduler: &Scheduler,
+    session: &Arc<Session>,
+    sink: &Sink,
+    timestamp: LogicalTimestamp,
+    sign: i64,
+    changes: &mut BTreeMap<String, i64>,
+) -> Result<(), QueryError> {
+    let query = LogicalOperator::TableReference(TableReference {
+        database: Some(sink.source_database.clone()),
+        table: sink.source.clone(),
+        sample: None,
+        as_of: None,
+    });
+    let mut plan = planner.plan_for_point_in_time(query, session)?;
+    plan.operator.set_scan_timestamps(timestamp);
+
+    let mut yielder = scheduler.yielder();
+    let mut executor = build_executor(session, &plan.operator);
+    while let Some((tuple, freq)) = executor.next()? {
+        yielder.tick();
+        *changes.entry(row_json(&plan.fields, tuple)).or_insert(0) += freq * sign;
+    }
+    changes.retain(|_row, freq| *freq != 0);
+    Ok(())
+}
+
+/// Renders the row as a json object, types without a json equivalent are written as strings.
+pub(crate) fn row_json(fields: &[Field], tuple: &[Datum]) -> String {
+    let json = JsonBuilder::default().object(|object| {
+        for (field, datum) in fields.iter().zip(tuple) {
+            let key = field.alias.as_str();
+            match (datum, field.data_type) {
+                (Datum::Null, _) => object.push_null(key),
+                (_, DataType::Boolean) => object.push_bool(key, datum.as_boolean()),
+                (_, DataType::Integer) => object.push_int(key, datum.as_integer() as i64),
+                (_, DataType::BigInt) => object.push_int(key, datum.as_bigint()),
+                (_, DataType::Decimal(..)) => object.push_decimal(key, datum.as_decimal()),
+                (_, DataType::Json) => object.push_json(key, datum.as_json()),
+                (_, datatype) => object.push_string(key, &datum.typed_with(datatype).to_string()),
+            }
+        }
+    });
+    Datum::from(json).typed_with(DataType::Json).to_string()
+}
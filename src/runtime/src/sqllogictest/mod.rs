@@ -0,0 +1,527 @@
+//! Support for running sqllogictest scripts against incresql.
+//! The format is documented at https://www.sqlite.org/sqllogictest/doc/trunk/about.wiki,
+//! scripts are a series of statement and query records separated by blank lines, the expected
+//! output of a query can either be written one value per line, one row per line or as a hash
+//! of all the values once there's more than hash-threshold values.
+mod script;
+
+use crate::connection::Connection;
+use crate::QueryError;
+use data::rust_decimal::Decimal;
+use data::{DataType, Datum};
+pub use script::*;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter};
+use std::path::Path;
+
+pub enum SqlLogicTestError {
+    // The script itself is malformed
+    ScriptError {
+        line: usize,
+        message: String,
+    },
+    UnexpectedError {
+        line: usize,
+        sql: String,
+        error: String,
+    },
+    UnexpectedSuccess {
+        line: usize,
+        sql: String,
+    },
+    // We did get an error, just not the one expected
+    WrongError {
+        line: usize,
+        sql: String,
+        expected: String,
+        error: String,
+    },
+    ColumnCountMismatch {
+        line: usize,
+        sql: String,
+        expected: usize,
+        actual: usize,
+    },
+    ResultMismatch {
+        line: usize,
+        sql: String,
+        expected: Vec<String>,
+        actual: Vec<String>,
+    },
+    IoError(std::io::Error),
+}
+
+impl Display for SqlLogicTestError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SqlLogicTestError::ScriptError { line, message } => {
+                f.write_fmt(format_args!("Invalid script @ line {}: {}", line, message))
+            }
+            SqlLogicTestError::UnexpectedError { line, sql, error } => f.write_fmt(format_args!(
+                "Unexpected error @ line {}: {}\n{}",
+                line, error, sql
+            )),
+            SqlLogicTestError::UnexpectedSuccess { line, sql } => f.write_fmt(format_args!(
+                "Expected an error but statement succeeded @ line {}\n{}",
+                line, sql
+            )),
+            SqlLogicTestError::WrongError {
+                line,
+                sql,
+                expected,
+                error,
+            } => f.write_fmt(format_args!(
+                "Expected an error containing \"{}\" but got \"{}\" @ line {}\n{}",
+                expected, error, line, sql
+            )),
+            SqlLogicTestError::ColumnCountMismatch {
+                line,
+                sql,
+                expected,
+                actual,
+            } => f.write_fmt(format_args!(
+                "Expected {} columns but got {} @ line {}\n{}",
+                expected, actual, line, sql
+            )),
+            SqlLogicTestError::ResultMismatch {
+                line,
+                sql,
+                expected,
+                actual,
+            } => f.write_fmt(format_args!(
+                "Results don't match @ line {}\n{}\n\nexpected:\n{}\n\nactual:\n{}",
+                line,
+                sql,
+                expected.join("\n"),
+                actual.join("\n")
+            )),
+            SqlLogicTestError::IoError(err) => Display::fmt(err, f),
+        }
+    }
+}
+
+impl Debug for SqlLogicTestError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl From<std::io::Error> for SqlLogicTestError {
+    fn from(err: std::io::Error) -> Self {
+        SqlLogicTestError::IoError(err)
+    }
+}
+
+/// The number of records that were run vs skipped due to skipif/onlyif.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct RunSummary {
+    pub executed: usize,
+    pub skipped: usize,
+}
+
+/// Runs sqllogictest scripts against a connection, the hash threshold and any labelled
+/// results carry over from one script to the next.
+#[derive(Debug)]
+pub struct SqlLogicTestRunner<'a> {
+    connection: &'a Connection<'a>,
+    hash_threshold: usize,
+    labels: HashMap<String, String>,
+}
+
+impl<'a> SqlLogicTestRunner<'a> {
+    pub fn new(connection: &'a Connection<'a>) -> Self {
+        SqlLogicTestRunner {
+            connection,
+            hash_threshold: 0,
+            labels: HashMap::new(),
+        }
+    }
+
+    pub fn run_file<P: AsRef<Path>>(&mut self, path: P) -> Result<RunSummary, SqlLogicTestError> {
+        let script = std::fs::read_to_string(path)?;
+        self.run_script(&script)
+    }
+
+    /// Runs all the records in the script, stopping at the first failure.
+    pub fn run_script(&mut self, script: &str) -> Result<RunSummary, SqlLogicTestError> {
+        let mut summary = RunSummary::default();
+        for record in parse_script(script)? {
+            match record {
+                Record::HashThreshold(hash_threshold) => self.hash_threshold = hash_threshold,
+                Record::Halt { conditions } => {
+                    if conditions.iter().all(Condition::applies) {
+                        break;
+                    }
+                }
+                Record::Statement {
+                    line,
+                    conditions,
+                    sql,
+                    expected,
+                } => {
+                    if conditions.iter().all(Condition::applies) {
+                        self.run_statement(line, sql, expected)?;
+                        summary.executed += 1;
+                    } else {
+                        summary.skipped += 1;
+                    }
+                }
+                Record::Query {
+                    line,
+                    conditions,
+                    sql,
+                    column_types,
+                    sort_mode,
+                    label,
+                    expected,
+                } => {
+                    if conditions.iter().all(Condition::applies) {
+                        let (column_count, rows) = match self.execute(&sql, &column_types) {
+                            Ok(result) => result,
+                            Err(error) => {
+                                let error = error.to_string();
+                                return Err(SqlLogicTestError::UnexpectedError {
+                                    line,
+                                    sql,
+                                    error,
+                                });
+                            }
+                        };
+                        if column_count != column_types.len() {
+                            return Err(SqlLogicTestError::ColumnCountMismatch {
+                                line,
+                                sql,
+                                expected: column_types.len(),
+                                actual: column_count,
+                            });
+                        }
+                        self.check_results(line, sql, rows, sort_mode, label, expected)?;
+                        summary.executed += 1;
+                    } else {
+                        summary.skipped += 1;
+                    }
+                }
+            }
+        }
+        Ok(summary)
+    }
+
+    fn run_statement(
+        &self,
+        line: usize,
+        sql: String,
+        expected: StatementExpectation,
+    ) -> Result<(), SqlLogicTestError> {
+        match (self.execute(&sql, ""), expected) {
+            (Ok(_), StatementExpectation::Ok) | (Err(_), StatementExpectation::Error(None)) => {
+                Ok(())
+            }
+            (Ok(_), StatementExpectation::Error(_)) => {
+                Err(SqlLogicTestError::UnexpectedSuccess { line, sql })
+            }
+            (Err(error), StatementExpectation::Ok) => Err(SqlLogicTestError::UnexpectedError {
+                line,
+                sql,
+                error: error.to_string(),
+            }),
+            (Err(error), StatementExpectation::Error(Some(expected))) => {
+                let error = error.to_string();
+                if error.contains(&expected) {
+                    Ok(())
+                } else {
+                    Err(SqlLogicTestError::WrongError {
+                        line,
+                        sql,
+                        expected,
+                        error,
+                    })
+                }
+            }
+        }
+    }
+
+    /// Runs the sql to completion formatting each value, statements are lazy so even if
+    /// we don't care about the output we still have to drain the executor.
+    /// Returns the number of columns along with the rows.
+    fn execute(
+        &self,
+        sql: &str,
+        column_types: &str,
+    ) -> Result<(usize, Vec<Vec<String>>), QueryError> {
+        let (fields, mut executor) = self.connection.execute_statement(sql)?;
+        let column_types: Vec<_> = column_types.chars().collect();
+        let mut rows = vec![];
+        while let Some((tuple, freq)) = executor.next()? {
+            let row: Vec<_> = tuple
+                .iter()
+                .zip(fields.iter())
+                .enumerate()
+                .map(|(idx, (datum, field))| {
+                    let column_type = column_types.get(idx).copied().unwrap_or('T');
+                    format_value(datum, field.data_type, column_type)
+                })
+                .collect();
+            for _ in 0..freq {
+                rows.push(row.clone());
+            }
+        }
+        Ok((fields.len(), rows))
+    }
+
+    fn check_results(
+        &mut self,
+        line: usize,
+        sql: String,
+        mut rows: Vec<Vec<String>>,
+        sort_mode: SortMode,
+        label: Option<String>,
+        expected: Vec<String>,
+    ) -> Result<(), SqlLogicTestError> {
+        if sort_mode == SortMode::RowSort {
+            rows.sort();
+        }
+        let mut values: Vec<_> = rows.iter().flatten().cloned().collect();
+        if sort_mode == SortMode::ValueSort {
+            values.sort();
+        }
+        let hash = hash_values(&values);
+
+        if let Some(label) = label {
+            if let Some(labelled_hash) = self.labels.get(&label) {
+                if *labelled_hash != hash {
+                    return Err(SqlLogicTestError::ResultMismatch {
+                        line,
+                        sql,
+                        expected: vec![labelled_hash.clone()],
+                        actual: vec![hash],
+                    });
+                }
+            } else {
+                self.labels.insert(label, hash.clone());
+            }
+        }
+
+        let expects_hash = expected.len() == 1 && expected[0].contains(" values hashing to ");
+        let actual =
+            if expects_hash || (self.hash_threshold > 0 && values.len() > self.hash_threshold) {
+                vec![hash]
+            } else if expected.len() == values.len() || sort_mode == SortMode::ValueSort {
+                values
+            } else {
+                rows.iter().map(|row| row.join(" ")).collect()
+            };
+
+        // Rows written out on a single line may not be spaced exactly as we'd space them.
+        let expected_normalized: Vec<_> = expected
+            .iter()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+        if actual == expected || actual == expected_normalized {
+            Ok(())
+        } else {
+            Err(SqlLogicTestError::ResultMismatch {
+                line,
+                sql,
+                expected,
+                actual,
+            })
+        }
+    }
+}
+
+/// Formats a value the way sqllogictest expects, I columns are integers, R columns have 3
+/// decimal places and text has empty strings and unprintable characters replaced.
+fn format_value(datum: &Datum, datatype: DataType, column_type: char) -> String {
+    if datum.is_null() {
+        return "NULL".to_string();
+    }
+    let as_decimal = match datatype {
+        DataType::Integer => datum.as_maybe_integer().map(Decimal::from),
+        DataType::BigInt => datum.as_maybe_bigint().map(Decimal::from),
+        DataType::Decimal(..) => datum.as_maybe_decimal(),
+        DataType::Boolean => datum.as_maybe_boolean().map(|b| Decimal::from(b as i32)),
+        _ => None,
+    };
+
+    match (column_type, as_decimal) {
+        ('I', Some(decimal)) => decimal.trunc().to_string(),
+        ('R', Some(decimal)) => {
+            let rounded = decimal.round_dp(3).to_string();
+            let mut parts = rounded.splitn(2, '.');
+            let integer_part = parts.next().unwrap();
+            let fraction_part = parts.next().unwrap_or("");
+            format!("{}.{:0<3}", integer_part, fraction_part)
+        }
+        _ => {
+            let text = datum.typed_with(datatype).to_string();
+            if text.is_empty() {
+                "(empty)".to_string()
+            } else {
+                text.chars()
+                    .map(|c| if (' '..='~').contains(&c) { c } else { '@' })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// The md5 of all the values each followed by a newline, same as the reference implementation.
+fn hash_values(values: &[String]) -> String {
+    let mut context = md5::Context::new();
+    for value in values {
+        context.consume(value.as_bytes());
+        context.consume(b"\n");
+    }
+    format!("{} values hashing to {:x}", values.len(), context.compute())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Runtime;
+
+    #[test]
+    fn test_format_value() {
+        assert_eq!(format_value(&Datum::Null, DataType::Integer, 'I'), "NULL");
+        assert_eq!(format_value(&Datum::from(12), DataType::Integer, 'I'), "12");
+        assert_eq!(
+            format_value(&Datum::from(12), DataType::Integer, 'R'),
+            "12.000"
+        );
+        assert_eq!(
+            format_value(
+                &Datum::from(Decimal::new(-12345, 4)),
+                DataType::Decimal(10, 4),
+                'R'
+            ),
+            "-1.234"
+        );
+        assert_eq!(
+            format_value(
+                &Datum::from(Decimal::new(-19, 1)),
+                DataType::Decimal(10, 1),
+                'I'
+            ),
+            "-1"
+        );
+        assert_eq!(
+            format_value(&Datum::from(""), DataType::Text, 'T'),
+            "(empty)"
+        );
+        assert_eq!(
+            format_value(&Datum::from("a\tb"), DataType::Text, 'T'),
+            "a@b"
+        );
+    }
+
+    #[test]
+    fn test_hash_values() {
+        // echo -n -e "1\n2\n" | md5sum
+        assert_eq!(
+            hash_values(&["1".to_string(), "2".to_string()]),
+            "2 values hashing to 6ddb4095eb719e2a9f0a3f95677d24e0"
+        );
+    }
+
+    #[test]
+    fn test_run_script() -> Result<(), SqlLogicTestError> {
+        let runtime = Runtime::new_for_test();
+        let connection = runtime.new_connection();
+        let mut runner = SqlLogicTestRunner::new(&connection);
+        let summary = runner.run_script(
+            r#"
+statement ok
+CREATE TABLE t1(a INT, b TEXT)
+
+statement ok
+INSERT INTO t1 VALUES (2, "b"), (1, "a"), (3, "")
+
+statement error t2 not found
+SELECT * FROM t2
+
+onlyif sqlite
+statement ok
+some sqlite specific sql
+
+query IT rowsort
+SELECT a, b FROM t1
+----
+1
+a
+2
+b
+3
+(empty)
+
+query IT rowsort
+SELECT a, b FROM t1
+----
+1 a
+2 b
+3 (empty)
+
+query I valuesort label-a
+SELECT a FROM t1
+----
+3 values hashing to c0710d6b4f15dfa88f600b0e6b624077
+
+query I valuesort label-a
+SELECT a + 0 FROM t1
+----
+1
+2
+3
+"#,
+        )?;
+        assert_eq!(
+            summary,
+            RunSummary {
+                executed: 7,
+                skipped: 1
+            }
+        );
+
+        assert!(matches!(
+            runner.run_script("query I nosort\nSELECT 1\n----\n2\n"),
+            Err(SqlLogicTestError::ResultMismatch { line: 1, .. })
+        ));
+        assert!(matches!(
+            runner.run_script("query I valuesort label-a\nSELECT 4\n----\n4\n"),
+            Err(SqlLogicTestError::ResultMismatch { line: 1, .. })
+        ));
+        assert!(matches!(
+            runner.run_script("query II\nSELECT 1\n----\n1\n"),
+            Err(SqlLogicTestError::ColumnCountMismatch { line: 1, .. })
+        ));
+        assert!(matches!(
+            runner.run_script("statement error\nSELECT 1"),
+            Err(SqlLogicTestError::UnexpectedSuccess { line: 1, .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_threshold() -> Result<(), SqlLogicTestError> {
+        let runtime = Runtime::new_for_test();
+        let connection = runtime.new_connection();
+        let mut runner = SqlLogicTestRunner::new(&connection);
+        runner.run_script(
+            r#"
+hash-threshold 1
+
+query II
+SELECT 1, 2
+----
+2 values hashing to 6ddb4095eb719e2a9f0a3f95677d24e0
+
+halt
+
+query I
+SELECT 1
+----
+2
+"#,
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+use crate::sqllogictest::SqlLogicTestError;
+
+/// The name we match against in skipif/onlyif conditions.
+pub const ENGINE_NAME: &str = "incresql";
+
+type Lines<'a> = std::iter::Enumerate<std::str::Lines<'a>>;
+
+/// A single record from a sqllogictest script, records are separated by blank lines.
+#[derive(Debug, Eq, PartialEq)]
+pub enum Record {
+    Statement {
+        line: usize,
+        conditions: Vec<Condition>,
+        sql: String,
+        expected: StatementExpectation,
+    },
+    Query {
+        line: usize,
+        conditions: Vec<Condition>,
+        sql: String,
+        column_types: String,
+        sort_mode: SortMode,
+        label: Option<String>,
+        expected: Vec<String>,
+    },
+    HashThreshold(usize),
+    Halt {
+        conditions: Vec<Condition>,
+    },
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum StatementExpectation {
+    Ok,
+    // An optional snippet that the error message should contain
+    Error(Option<String>),
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum SortMode {
+    NoSort,
+    RowSort,
+    ValueSort,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Condition {
+    SkipIf(String),
+    OnlyIf(String),
+}
+
+impl Condition {
+    /// Returns true if a record with this condition should be run by us.
+    pub fn applies(&self) -> bool {
+        match self {
+            Condition::SkipIf(engine) => !engine.eq_ignore_ascii_case(ENGINE_NAME),
+            Condition::OnlyIf(engine) => engine.eq_ignore_ascii_case(ENGINE_NAME),
+        }
+    }
+}
+
+/// Parses a sqllogictest script into its records.
+pub fn parse_script(script: &str) -> Result<Vec<Record>, SqlLogicTestError> {
+    let mut records = vec![];
+    let mut lines = script.lines().enumerate();
+
+    while let Some((idx, line)) = lines.next() {
+        let line_number = idx + 1;
+        let line = line.trim_end();
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut conditions = vec![];
+        let mut words: Vec<&str> = line.split_whitespace().collect();
+        loop {
+            let condition = match words.as_slice() {
+                ["skipif", engine, ..] => Condition::SkipIf(engine.to_string()),
+                ["onlyif", engine, ..] => Condition::OnlyIf(engine.to_string()),
+                _ => break,
+            };
+            conditions.push(condition);
+            match lines.next() {
+                Some((_, line)) => words = line.split_whitespace().collect(),
+                None => return Err(parse_error(line_number, "condition without a record")),
+            }
+        }
+
+        let record = match words.as_slice() {
+            ["statement", "ok"] => Record::Statement {
+                line: line_number,
+                conditions,
+                sql: read_sql(&mut lines).0,
+                expected: StatementExpectation::Ok,
+            },
+            ["statement", "error", message @ ..] => Record::Statement {
+                line: line_number,
+                conditions,
+                sql: read_sql(&mut lines).0,
+                expected: StatementExpectation::Error(if message.is_empty() {
+                    None
+                } else {
+                    Some(message.join(" "))
+                }),
+            },
+            ["query", column_types, rest @ ..] => {
+                let sort_mode = match rest.first() {
+                    None | Some(&"nosort") => SortMode::NoSort,
+                    Some(&"rowsort") => SortMode::RowSort,
+                    Some(&"valuesort") => SortMode::ValueSort,
+                    Some(sort_mode) => {
+                        return Err(parse_error(
+                            line_number,
+                            &format!("unknown sort mode {}", sort_mode),
+                        ))
+                    }
+                };
+                let label = rest.get(1).map(|label| label.to_string());
+                let (sql, has_results) = read_sql(&mut lines);
+                let expected = if has_results {
+                    read_results(&mut lines)
+                } else {
+                    vec![]
+                };
+                Record::Query {
+                    line: line_number,
+                    conditions,
+                    sql,
+                    column_types: column_types.to_string(),
+                    sort_mode,
+                    label,
+                    expected,
+                }
+            }
+            ["hash-threshold", threshold] => {
+                let threshold = threshold
+                    .parse()
+                    .map_err(|_| parse_error(line_number, "invalid hash-threshold"))?;
+                Record::HashThreshold(threshold)
+            }
+            ["halt"] => Record::Halt { conditions },
+            _ => {
+                let message = format!("unknown record {}", words.join(" "));
+                return Err(parse_error(line_number, &message));
+            }
+        };
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Reads the sql for a record, stopping at either a blank line or a ---- separator,
+/// returns the sql and whether we hit the separator.
+fn read_sql(lines: &mut Lines) -> (String, bool) {
+    let mut sql_lines = vec![];
+    for (_, line) in lines.by_ref() {
+        if line.trim().is_empty() {
+            return (sql_lines.join("\n"), false);
+        } else if line.trim_end() == "----" {
+            return (sql_lines.join("\n"), true);
+        }
+        sql_lines.push(line);
+    }
+    (sql_lines.join("\n"), false)
+}
+
+fn read_results(lines: &mut Lines) -> Vec<String> {
+    let mut results = vec![];
+    for (_, line) in lines.by_ref() {
+        if line.trim().is_empty() {
+            break;
+        }
+        results.push(line.trim().to_string());
+    }
+    results
+}
+
+fn parse_error(line: usize, message: &str) -> SqlLogicTestError {
+    SqlLogicTestError::ScriptError {
+        line,
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_script() -> Result<(), SqlLogicTestError> {
+        let script = "
+# A comment
+hash-threshold 8
+
+statement ok
+CREATE TABLE t1(a INT, b TEXT)
+
+skipif incresql
+statement error no such table
+SELECT * FROM
+  t2
+
+query IT rowsort label-1
+SELECT a, b FROM t1
+----
+1
+abc
+";
+        assert_eq!(
+            parse_script(script)?,
+            vec![
+                Record::HashThreshold(8),
+                Record::Statement {
+                    line: 5,
+                    conditions: vec![],
+                    sql: "CREATE TABLE t1(a INT, b TEXT)".to_string(),
+                    expected: StatementExpectation::Ok
+                },
+                Record::Statement {
+                    line: 8,
+                    conditions: vec![Condition::SkipIf("incresql".to_string())],
+                    sql: "SELECT * FROM\n  t2".to_string(),
+                    expected: StatementExpectation::Error(Some("no such table".to_string()))
+                },
+                Record::Query {
+                    line: 13,
+                    conditions: vec![],
+                    sql: "SELECT a, b FROM t1".to_string(),
+                    column_types: "IT".to_string(),
+                    sort_mode: SortMode::RowSort,
+                    label: Some("label-1".to_string()),
+                    expected: vec!["1".to_string(), "abc".to_string()]
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_script_errors() {
+        assert!(matches!(
+            parse_script("query I wibble\nselect 1"),
+            Err(SqlLogicTestError::ScriptError { line: 1, .. })
+        ));
+        assert!(matches!(
+            parse_script("\nfoo bar"),
+            Err(SqlLogicTestError::ScriptError { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_conditions() {
+        assert!(Condition::OnlyIf("incresql".to_string()).applies());
+        assert!(!Condition::OnlyIf("sqlite".to_string()).applies());
+        assert!(Condition::SkipIf("mysql".to_string()).applies());
+        assert!(!Condition::SkipIf("incresql".to_string()).applies());
+    }
+}
@@ -0,0 +1,125 @@
+use crate::infer_schema::{external_table_sql, keyed_columns};
+use crate::materialized_view::view_query;
+use crate::sink::row_json;
+use crate::QueryError;
+use ast::rel::logical::{FileFormat, SerdeOptions};
+use catalog::{CatalogError, TableOrView};
+use data::{Datum, LogicalTimestamp, Session, TupleIter};
+use executor::point_in_time::build_executor;
+use executor::ExecutionError;
+use planner::{Field, Planner, READ_BARRIER_WAIT};
+use std::path::Path;
+
+/// Writes a table's rows out to a json file in the location and swaps the table for a cold
+/// table that reads them back through a file scan, freeing up the space they took in rocksdb.
+/// The location must be empty so the cold table doesn't pick up anyone else's files.
+pub fn move_to_cold(
+    planner: &Planner,
+    database: &str,
+    name: &str,
+    location: &str,
+) -> Result<(), QueryError> {
+    if location.contains("://") {
+        return Err(QueryError::ObjectStorageNotSupported(location.to_string()));
+    }
+    let item = planner.catalog.read().unwrap().item(database, name)?;
+    let table = match item.item {
+        TableOrView::Table(table) => table,
+        TableOrView::View(_) => {
+            return Err(CatalogError::NotATable(database.to_string(), name.to_string()).into())
+        }
+    };
+    let write_error = |err: std::io::Error| {
+        QueryError::StorageTierWriteError(location.to_string(), err.to_string())
+    };
+    std::fs::create_dir_all(location).map_err(write_error)?;
+    if std::fs::read_dir(location)
+        .map_err(write_error)?
+        .next()
+        .is_some()
+    {
+        return Err(QueryError::StorageTierWriteError(
+            location.to_string(),
+            "the directory isn't empty".to_string(),
+        ));
+    }
+
+    let fields: Vec<_> = item
+        .columns
+        .iter()
+        .map(|(alias, data_type)| Field {
+            qualifier: None,
+            alias: alias.clone(),
+            data_type: *data_type,
+        })
+        .collect();
+    let mut rows = String::new();
+    let mut iter = table.full_scan(LogicalTimestamp::MAX);
+    while let Some((tuple, freq)) = iter.next()? {
+        // Nulls are left out as a json null would read back into a text column as 'null'
+        let (fields, tuple): (Vec<_>, Vec<_>) = fields
+            .iter()
+            .cloned()
+            .zip(tuple.iter().cloned())
+            .filter(|(_field, datum)| !datum.is_null())
+            .unzip();
+        let row = row_json(&fields, &tuple);
+        for _ in 0..freq {
+            rows.push_str(&row);
+            rows.push('\n');
+        }
+    }
+    std::fs::write(Path::new(location).join(format!("{}.json", name)), rows)
+        .map_err(write_error)?;
+
+    let serde_options = SerdeOptions {
+        format: FileFormat::Json,
+        ..SerdeOptions::default()
+    };
+    let sql = external_table_sql(location, &serde_options, &keyed_columns(&item.columns));
+    planner
+        .catalog
+        .write()
+        .unwrap()
+        .move_table_to_cold(database, name, &sql)?;
+    Ok(())
+}
+
+/// Reads a cold table's rows back out of its files into a fresh table in rocksdb. The files are
+/// left where they are.
+pub fn move_to_hot(
+    planner: &Planner,
+    session: &Session,
+    database: &str,
+    name: &str,
+) -> Result<(), QueryError> {
+    let view = planner.catalog.read().unwrap().cold_table(database, name)?;
+    let (view_session, query) = view_query(session, view)?;
+    let mut plan = planner.plan_for_point_in_time(query, &view_session)?;
+    let timestamp = planner
+        .catalog
+        .read()
+        .unwrap()
+        .read_barrier(READ_BARRIER_WAIT);
+    plan.operator.set_scan_timestamps(timestamp);
+
+    let mut rows = vec![];
+    let mut executor = build_executor(&view_session, &plan.operator);
+    while let Some((tuple, freq)) = executor.next()? {
+        rows.push((tuple.iter().map(Datum::as_static).collect::<Vec<_>>(), freq));
+    }
+
+    let table = planner
+        .catalog
+        .write()
+        .unwrap()
+        .move_table_to_hot(database, name)?;
+    table.atomic_write::<_, ExecutionError>(|batch| {
+        let now = table.clock().now();
+        for (tuple, freq) in &rows {
+            batch.write_tuple(&table, tuple, now, *freq)?;
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
@@ -0,0 +1,158 @@
+use catalog::{WorkloadClass, WorkloadStats};
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::Instant;
+
+/// The rows maintenance processes between checking whether it should give way
+const MAINTENANCE_BATCH_ROWS: usize = 1024;
+const DEFAULT_MAINTENANCE_SHARE: u8 = 25;
+
+/// Shares the cpu between interactive statements and maintenance. While any interactive
+/// statements are running, maintenance pauses between batches for long enough that it only
+/// gets its share of the time.
+#[derive(Debug)]
+pub(crate) struct Scheduler {
+    stats: Arc<WorkloadStats>,
+    interactive_running: AtomicUsize,
+    // Percentage of the time maintenance gets while interactive statements are running
+    maintenance_share: AtomicU8,
+}
+
+impl Scheduler {
+    pub(crate) fn new(stats: Arc<WorkloadStats>) -> Self {
+        Scheduler {
+            stats,
+            interactive_running: AtomicUsize::new(0),
+            maintenance_share: AtomicU8::new(DEFAULT_MAINTENANCE_SHARE),
+        }
+    }
+
+    /// Sets the percentage of time maintenance gets when it's competing with interactive
+    /// statements, 100 means maintenance never gives way.
+    pub(crate) fn set_maintenance_share(&self, share: u8) {
+        let share = match share {
+            0 => 1,
+            1..=100 => share,
+            _ => 100,
+        };
+        self.maintenance_share.store(share, Ordering::Relaxed);
+    }
+
+    /// Accounts for a statement of the class until the returned guard is dropped
+    pub(crate) fn start(self: &Arc<Self>, class: WorkloadClass) -> WorkloadGuard {
+        self.stats.record_statement(class);
+        if class == WorkloadClass::Interactive {
+            self.interactive_running.fetch_add(1, Ordering::Relaxed);
+        }
+        WorkloadGuard {
+            scheduler: Arc::clone(self),
+            class,
+            started: Instant::now(),
+        }
+    }
+
+    /// Returns a yielder for maintenance to tick as it processes each row
+    pub(crate) fn yielder(&self) -> Yielder<'_> {
+        Yielder {
+            scheduler: self,
+            rows: 0,
+            batch_started: Instant::now(),
+        }
+    }
+}
+
+/// Held while a statement runs, its elapsed time is recorded against its class when dropped.
+#[derive(Debug)]
+pub(crate) struct WorkloadGuard {
+    scheduler: Arc<Scheduler>,
+    class: WorkloadClass,
+    started: Instant,
+}
+
+impl Drop for WorkloadGuard {
+    fn drop(&mut self) {
+        let elapsed = self.started.elapsed().as_micros() as u64;
+        self.scheduler.stats.record_elapsed(self.class, elapsed);
+        if self.class == WorkloadClass::Interactive {
+            self.scheduler
+                .interactive_running
+                .fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Pauses maintenance between batches of rows while interactive statements are running.
+pub(crate) struct Yielder<'a> {
+    scheduler: &'a Scheduler,
+    rows: usize,
+    batch_started: Instant,
+}
+
+impl Yielder<'_> {
+    pub(crate) fn tick(&mut self) {
+        self.rows += 1;
+        if self.rows == MAINTENANCE_BATCH_ROWS {
+            self.rows = 0;
+            self.yield_now();
+        }
+    }
+
+    fn yield_now(&mut self) {
+        let scheduler = self.scheduler;
+        if scheduler.interactive_running.load(Ordering::Relaxed) > 0 {
+            let share = scheduler.maintenance_share.load(Ordering::Relaxed) as u32;
+            let pause = self.batch_started.elapsed() * (100 - share) / share;
+            sleep(pause);
+            scheduler
+                .stats
+                .record_yielded(WorkloadClass::Maintenance, pause.as_micros() as u64);
+        }
+        self.batch_started = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_start() {
+        let stats = Arc::new(WorkloadStats::default());
+        let scheduler = Arc::new(Scheduler::new(Arc::clone(&stats)));
+        let guard = scheduler.start(WorkloadClass::Interactive);
+        assert_eq!(scheduler.interactive_running.load(Ordering::Relaxed), 1);
+        std::mem::drop(guard);
+        assert_eq!(scheduler.interactive_running.load(Ordering::Relaxed), 0);
+
+        let _guard = scheduler.start(WorkloadClass::Maintenance);
+        assert_eq!(scheduler.interactive_running.load(Ordering::Relaxed), 0);
+        let statements: Vec<_> = stats
+            .totals()
+            .iter()
+            .map(|totals| totals.statements)
+            .collect();
+        assert_eq!(statements, vec![1, 1]);
+    }
+
+    #[test]
+    fn test_yield_to_interactive() {
+        let stats = Arc::new(WorkloadStats::default());
+        let scheduler = Arc::new(Scheduler::new(Arc::clone(&stats)));
+        scheduler.set_maintenance_share(50);
+        let yielded = || stats.totals()[1].yielded_micros;
+
+        // Nothing to give way to
+        let mut yielder = scheduler.yielder();
+        sleep(Duration::from_millis(5));
+        yielder.yield_now();
+        assert_eq!(yielded(), 0);
+
+        let _interactive = scheduler.start(WorkloadClass::Interactive);
+        sleep(Duration::from_millis(5));
+        yielder.yield_now();
+        // With a 50% share maintenance pauses for as long as its batch took
+        assert!(yielded() >= 5000);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::mysql::MysqlConnection;
+use crate::postgres::PostgresConnection;
 use runtime::Runtime;
 use scoped_threadpool::Pool;
 use std::net::TcpListener;
@@ -8,20 +9,58 @@ use std::panic::{catch_unwind, AssertUnwindSafe};
 // detection, we'll just make mysql public to get around it even though there's probably no use for
 // it outside of the server
 pub mod mysql;
+pub mod postgres;
 
-/// Implements a tcp server that accepts mysql connections
+/// Implements a tcp server that accepts mysql connections, and optionally postgres ones too
 pub struct Server {
     runtime: Runtime,
 }
 
+/// The wire protocol spoken by the clients of a listener
+#[derive(Debug, Copy, Clone)]
+enum Protocol {
+    Mysql,
+    Postgres,
+}
+
 impl Server {
     pub fn new(runtime: Runtime) -> Self {
         Server { runtime }
     }
 
-    /// Starts listening for mysql connections. This method doesn't normally terminate.
-    pub fn listen(&mut self, addr: &str) -> Result<(), std::io::Error> {
+    /// Starts listening for mysql connections, and postgres ones if given an address for them,
+    /// events are run in the background as they come due and old versions of rows vacuumed.
+    /// This method doesn't normally terminate.
+    pub fn listen(
+        &mut self,
+        addr: &str,
+        postgres_addr: Option<&str>,
+    ) -> Result<(), std::io::Error> {
         let listener = TcpListener::bind(addr)?;
+        let postgres_listener = postgres_addr.map(TcpListener::bind).transpose()?;
+        let mut background_pool = Pool::new(3);
+        let server = &*self;
+        background_pool.scoped(|scope| {
+            scope.execute(move || server.runtime.run_event_scheduler());
+            scope.execute(move || server.runtime.run_vacuum_scheduler());
+            if let Some(postgres_listener) = postgres_listener {
+                scope.execute(move || {
+                    if let Err(err) =
+                        server.accept_connections(postgres_listener, Protocol::Postgres)
+                    {
+                        eprintln!("Stopped accepting postgres connections\n {:?}", err);
+                    }
+                });
+            }
+            server.accept_connections(listener, Protocol::Mysql)
+        })
+    }
+
+    fn accept_connections(
+        &self,
+        listener: TcpListener,
+        protocol: Protocol,
+    ) -> Result<(), std::io::Error> {
         let mut pool = Pool::new(500);
 
         loop {
@@ -31,8 +70,15 @@ impl Server {
                     let connection_id = connection.connection_id;
                     scope.execute(move || {
                         if let Err(err) = catch_unwind(AssertUnwindSafe(|| {
-                            let mut mysql_connection = MysqlConnection::new(stream, connection);
-                            if let Err(err) = mysql_connection.connect() {
+                            let result = match protocol {
+                                Protocol::Mysql => {
+                                    MysqlConnection::new(stream, connection).connect()
+                                }
+                                Protocol::Postgres => {
+                                    PostgresConnection::new(stream, connection).connect()
+                                }
+                            };
+                            if let Err(err) = result {
                                 eprintln!("IO Error for {}\n {:?}", connection_id, err);
                             }
                         })) {
@@ -0,0 +1,212 @@
+use crate::mysql::protocol_base::{read_int_1, read_int_3, write_int_3};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use std::cmp::min;
+use std::io::{Error, ErrorKind, Read, Write};
+use std::num::Wrapping;
+
+/// Payloads smaller than this aren't worth compressing and get sent as is
+const MIN_COMPRESS_LENGTH: usize = 50;
+
+/// Outgoing packets are buffered up to this many bytes before being compressed, the rows of a
+/// result set compress much better together than they do one at a time.
+const COMPRESS_BATCH_BYTES: usize = 64 * 1024;
+
+/// The most a single compressed packet can hold once uncompressed
+const MAX_PAYLOAD_LENGTH: usize = 0xffffff;
+
+/// The compression algorithm negotiated with the client
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Compression {
+    Zlib,
+    Zstd(i32),
+}
+
+/// https://dev.mysql.com/doc/dev/mysql-server/8.0.12/page_protocol_basic_compression.html
+/// Wraps the client's stream, until compression is started everything passes straight through.
+/// After that the normal packets are sent and received inside compressed packets, which have a
+/// header of their own, the compressed length, a sequence id and the uncompressed length (0 if
+/// the payload wasn't compressed).
+/// Writes are buffered, flush has to be called once a response is complete.
+pub struct CompressedStream<S> {
+    inner: S,
+    compression: Option<Compression>,
+    sequence_id: Wrapping<u8>,
+    read_buf: Vec<u8>,
+    read_pos: usize,
+    write_buf: Vec<u8>,
+}
+
+impl<S> CompressedStream<S> {
+    pub fn new(inner: S) -> Self {
+        CompressedStream {
+            inner,
+            compression: None,
+            sequence_id: Wrapping(0),
+            read_buf: vec![],
+            read_pos: 0,
+            write_buf: vec![],
+        }
+    }
+
+    /// Everything from here on in is compressed, this happens once the handshake completes
+    pub fn start_compression(&mut self, compression: Compression) {
+        self.compression = Some(compression);
+    }
+}
+
+impl<S: Read> CompressedStream<S> {
+    /// Reads the next compressed packet into the read buffer
+    fn read_compressed_packet(&mut self, compression: Compression) -> Result<(), Error> {
+        let mut header = [0_u8; 7];
+        self.inner.read_exact(&mut header)?;
+        let mut compressed_length = 0_u32;
+        let mut sequence_id = 0_u8;
+        let mut uncompressed_length = 0_u32;
+        let rem = read_int_3(&mut compressed_length, &header);
+        let rem = read_int_1(&mut sequence_id, rem);
+        read_int_3(&mut uncompressed_length, rem);
+        self.sequence_id = Wrapping(sequence_id) + Wrapping(1);
+
+        let mut payload = vec![0; compressed_length as usize];
+        self.inner.read_exact(&mut payload)?;
+        self.read_pos = 0;
+        if uncompressed_length == 0 {
+            self.read_buf = payload;
+        } else {
+            self.read_buf.clear();
+            match compression {
+                Compression::Zlib => {
+                    ZlibDecoder::new(payload.as_slice()).read_to_end(&mut self.read_buf)?;
+                }
+                Compression::Zstd(_) => {
+                    self.read_buf = zstd::decode_all(payload.as_slice())?;
+                }
+            }
+            if self.read_buf.len() != uncompressed_length as usize {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "Compressed packet didn't match its uncompressed length",
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S: Write> CompressedStream<S> {
+    /// Sends everything buffered so far as compressed packets
+    fn write_compressed_packets(&mut self, compression: Compression) -> Result<(), Error> {
+        let mut header = Vec::with_capacity(7);
+        for chunk in self.write_buf.chunks(MAX_PAYLOAD_LENGTH) {
+            let compressed = if chunk.len() < MIN_COMPRESS_LENGTH {
+                None
+            } else {
+                Some(compress(compression, chunk)?).filter(|payload| payload.len() < chunk.len())
+            };
+            // Anything that didn't shrink goes as is with an uncompressed length of 0
+            let (payload, uncompressed_length) = match &compressed {
+                Some(payload) => (payload.as_slice(), chunk.len()),
+                None => (chunk, 0),
+            };
+
+            header.clear();
+            write_int_3(payload.len() as u32, &mut header);
+            header.push(self.sequence_id.0);
+            write_int_3(uncompressed_length as u32, &mut header);
+            self.inner.write_all(&header)?;
+            self.inner.write_all(payload)?;
+            self.sequence_id += Wrapping(1_u8);
+        }
+        self.write_buf.clear();
+        Ok(())
+    }
+}
+
+fn compress(compression: Compression, data: &[u8]) -> Result<Vec<u8>, Error> {
+    match compression {
+        Compression::Zlib => {
+            let mut encoder = ZlibEncoder::new(vec![], flate2::Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+        Compression::Zstd(level) => zstd::encode_all(data, level),
+    }
+}
+
+impl<S: Read> Read for CompressedStream<S> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let compression = match self.compression {
+            Some(compression) => compression,
+            None => return self.inner.read(buf),
+        };
+        while self.read_pos == self.read_buf.len() {
+            self.read_compressed_packet(compression)?;
+        }
+        let len = min(buf.len(), self.read_buf.len() - self.read_pos);
+        buf[..len].copy_from_slice(&self.read_buf[self.read_pos..(self.read_pos + len)]);
+        self.read_pos += len;
+        Ok(len)
+    }
+}
+
+impl<S: Write> Write for CompressedStream<S> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let compression = match self.compression {
+            Some(compression) => compression,
+            None => return self.inner.write(buf),
+        };
+        self.write_buf.extend_from_slice(buf);
+        if self.write_buf.len() >= COMPRESS_BATCH_BYTES {
+            self.write_compressed_packets(compression)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        if let Some(compression) = self.compression {
+            self.write_compressed_packets(compression)?;
+        }
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_compressed_stream() -> Result<(), Error> {
+        for compression in &[Compression::Zlib, Compression::Zstd(3)] {
+            let mut writer = CompressedStream::new(vec![]);
+            writer.write_all(b"before")?;
+            writer.start_compression(*compression);
+            // One packet too small to bother compressing, then one large enough
+            writer.write_all(b"small")?;
+            writer.flush()?;
+            let large = "[{\"a\": 1}, {\"b\": 2}]".repeat(100);
+            writer.write_all(large.as_bytes())?;
+            writer.flush()?;
+
+            let written = writer.inner;
+            assert_eq!(&written[..6], b"before");
+            // compressed length 5, sequence id 0 and 0 for the uncompressed length
+            assert_eq!(&written[6..13], &[5, 0, 0, 0, 0, 0, 0]);
+            assert!(written.len() < 6 + 7 + 5 + 7 + large.len() / 10);
+
+            let mut reader = CompressedStream::new(Cursor::new(written));
+            let mut before = [0_u8; 6];
+            reader.read_exact(&mut before)?;
+            reader.start_compression(*compression);
+            let mut rest = vec![0_u8; 5 + large.len()];
+            reader.read_exact(&mut rest)?;
+            assert_eq!(&before, b"before");
+            assert_eq!(&rest[..5], b"small");
+            assert_eq!(&rest[5..], large.as_bytes());
+            // Replies carry on from the client's sequence id
+            assert_eq!(reader.sequence_id, Wrapping(2));
+        }
+        Ok(())
+    }
+}
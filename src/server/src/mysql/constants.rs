@@ -72,6 +72,12 @@ pub const CAPABILITY_CLIENT_SESSION_TRACK: u32 = 0x00800000;
 /// Can send OK after a Text Resultset.
 pub const CAPABILITY_CLIENT_DEPRECATE_EOF: u32 = 0x01000000;
 
+/// Compression protocol extended to support zstd compression method.
+pub const CAPABILITY_CLIENT_ZSTD_COMPRESSION_ALGORITHM: u32 = 0x04000000;
+
+/// The zstd level used when the client doesn't ask for one.
+pub const DEFAULT_ZSTD_COMPRESSION_LEVEL: u8 = 3;
+
 pub const CHARSET_UTF8_GENERAL_CI: u8 = 33;
 
 pub const STATUS_FLAG_AUTOCOMMIT: u16 = 2;
@@ -93,6 +99,7 @@ pub const MYSQL_TYPE_DATETIME: u8 = 0x0c;
 pub const MYSQL_TYPE_YEAR: u8 = 0x0d;
 pub const MYSQL_TYPE_VARCHAR: u8 = 0x0f;
 pub const MYSQL_TYPE_BIT: u8 = 0x10;
+pub const MYSQL_TYPE_JSON: u8 = 0xf5;
 pub const MYSQL_TYPE_NEWDECIMAL: u8 = 0xf6;
 pub const MYSQL_TYPE_ENUM: u8 = 0xf7;
 pub const MYSQL_TYPE_SET: u8 = 0xf8;
@@ -141,8 +148,26 @@ pub const MYSQL_ER_NO_TABLES_USED: MyError<'static> = MyError {
     sql_state: "HY000",
 };
 
+pub const MYSQL_ER_UNKNOWN_STMT_HANDLER: MyError<'static> = MyError {
+    code: 1243,
+    msg: "Unknown prepared statement handler",
+    sql_state: "HY000",
+};
+
+pub const MYSQL_ER_INVALID_CHARACTER_STRING: MyError<'static> = MyError {
+    code: 1300,
+    msg: "Invalid utf8 character string",
+    sql_state: "HY000",
+};
+
 pub const MYSQL_ER_QUERY_INTERRUPTED: MyError<'static> = MyError {
     code: 1317,
     msg: "Query execution was interrupted",
     sql_state: "70100",
 };
+
+pub const MYSQL_ER_MALFORMED_PACKET: MyError<'static> = MyError {
+    code: 1835,
+    msg: "Malformed communication packet",
+    sql_state: "HY000",
+};
@@ -1,37 +1,61 @@
+use crate::mysql::compression::{CompressedStream, Compression};
 use crate::mysql::constants::*;
 use crate::mysql::packets::*;
 use crate::mysql::protocol_base::{read_int_1, read_int_3, write_int_3};
-use runtime::connection::Connection;
+use runtime::connection::{Connection, PreparedStatement, QueryResult};
 use runtime::QueryError;
-use std::cmp::min;
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::io::{Read, Write};
 use std::net::TcpStream;
 use std::num::Wrapping;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
+mod compression;
 mod constants;
 mod packets;
 mod protocol_base;
 
+/// Outgoing packets are buffered up to this many bytes before being written to the stream, this
+/// way the rows of a result set go out in batches rather than a write per row.
+const WRITE_BATCH_BYTES: usize = 64 * 1024;
+
+/// The largest payload a single packet can hold, anything bigger is split over several packets
+const MAX_PAYLOAD_LENGTH: usize = 0xffffff;
+
 pub struct MysqlConnection<'a> {
-    stream: TcpStream,
-    packet_header_buf: Vec<u8>,
+    stream: CompressedStream<TcpStream>,
+    packet_header_buf: [u8; 4],
     packet_buf: Vec<u8>,
+    write_buf: Vec<u8>,
     capabilities: u32,
     sequence_id: Wrapping<u8>,
     connection: Arc<Connection<'a>>,
+    prepared_statements: HashMap<u32, MysqlPreparedStatement>,
+    next_statement_id: Wrapping<u32>,
+}
+
+/// A prepared statement along with the parameter types last sent by the client, the client
+/// only resends them when they change.
+struct MysqlPreparedStatement {
+    prepared_statement: PreparedStatement,
+    parameter_types: Vec<(u8, bool)>,
 }
 
 impl<'a> MysqlConnection<'a> {
     pub fn new(stream: TcpStream, connection: Arc<Connection<'a>>) -> Self {
         MysqlConnection {
-            stream,
-            packet_header_buf: Vec::new(),
+            stream: CompressedStream::new(stream),
+            packet_header_buf: [0; 4],
             packet_buf: Vec::new(),
+            write_buf: Vec::new(),
             capabilities: 0,
             sequence_id: Wrapping(0),
             connection,
+            prepared_statements: HashMap::new(),
+            next_statement_id: Wrapping(1),
         }
     }
 
@@ -46,11 +70,25 @@ impl<'a> MysqlConnection<'a> {
                         break;
                     }
                     CommandPacket::ComPing => {
-                        self.send_packet(|buf| write_ok_packet(false, 0, capabilities, buf))?;
+                        self.send_packet(|buf| write_ok_packet(false, 0, 0, capabilities, buf))?;
+                    }
+                    CommandPacket::ComProcessKill(com_process_kill) => {
+                        match self.connection.kill(com_process_kill.connection_id) {
+                            Ok(()) => self.send_packet(|buf| {
+                                write_ok_packet(false, 0, 0, capabilities, buf)
+                            })?,
+                            Err(err) => self.send_query_error(err)?,
+                        }
                     }
                     CommandPacket::ComInitDb(com_init_db) => {
-                        if self.connection.change_database(&com_init_db.schema).is_ok() {
-                            self.send_packet(|buf| write_ok_packet(false, 0, capabilities, buf))?;
+                        let schema = match self.decode_text(&com_init_db.schema)? {
+                            Some(schema) => schema,
+                            None => continue,
+                        };
+                        if self.connection.change_database(&schema).is_ok() {
+                            self.send_packet(|buf| {
+                                write_ok_packet(false, 0, 0, capabilities, buf)
+                            })?;
                         } else {
                             self.send_packet(|buf| {
                                 write_err_packet_from_err(&MYSQL_ER_BAD_DB_ERROR, capabilities, buf)
@@ -58,7 +96,41 @@ impl<'a> MysqlConnection<'a> {
                         }
                     }
                     CommandPacket::ComQuery(com_query) => {
-                        self.process_query_command(&com_query.query)?;
+                        if let Some(query) = self.decode_text(&com_query.query)? {
+                            self.process_query_command(&query)?;
+                        }
+                    }
+                    CommandPacket::ComStmtPrepare(com_stmt_prepare) => {
+                        if let Some(query) = self.decode_text(&com_stmt_prepare.query)? {
+                            self.process_prepare_command(&query)?;
+                        }
+                    }
+                    CommandPacket::ComStmtExecute(com_stmt_execute) => {
+                        self.process_execute_command(&com_stmt_execute)?;
+                    }
+                    CommandPacket::ComStmtClose(com_stmt_close) => {
+                        // No response is sent for a close
+                        self.prepared_statements
+                            .remove(&com_stmt_close.statement_id);
+                    }
+                    CommandPacket::ComStmtReset(com_stmt_reset) => {
+                        // We don't support sending long data so there's nothing to reset
+                        if self
+                            .prepared_statements
+                            .contains_key(&com_stmt_reset.statement_id)
+                        {
+                            self.send_packet(|buf| {
+                                write_ok_packet(false, 0, 0, capabilities, buf)
+                            })?;
+                        } else {
+                            self.send_packet(|buf| {
+                                write_err_packet_from_err(
+                                    &MYSQL_ER_UNKNOWN_STMT_HANDLER,
+                                    capabilities,
+                                    buf,
+                                )
+                            })?;
+                        }
                     }
                     CommandPacket::ComUnknown => {
                         self.send_packet(|buf| {
@@ -72,89 +144,193 @@ impl<'a> MysqlConnection<'a> {
                 },
                 Err(io_error) => return Err(io_error),
             }
+            // A killed connection is closed once it's answered the command it was on
+            if self.connection.session.kill_flag.load(Ordering::Relaxed) {
+                break;
+            }
         }
 
         Ok(())
     }
 
-    fn process_query_command(&mut self, query: &str) -> Result<(), std::io::Error> {
+    /// Decodes text sent by the client with the session's invalid_utf8 policy, if it's rejected
+    /// the error is sent back and None returned.
+    fn decode_text<'b>(&mut self, bytes: &'b [u8]) -> Result<Option<Cow<'b, str>>, std::io::Error> {
         let capabilities = self.capabilities;
-        match self.connection.execute_statement(query) {
-            Ok((fields, mut executor)) => {
-                if !fields.is_empty() {
-                    self.send_packet(|buf| {
-                        write_resultset_packet(fields.len(), capabilities, buf)
-                    })?;
-                    for field in &fields {
-                        self.send_packet(|buf| {
-                            write_column_packet(
-                                "",
-                                &field.alias,
-                                field.data_type,
-                                capabilities,
-                                buf,
-                            )
-                        })?;
-                    }
+        match self.connection.session.decode_utf8(bytes) {
+            Ok(text) => Ok(Some(text)),
+            Err(offset) => {
+                let msg = format!("Invalid utf8 character string at byte {}", offset);
+                let my_err = MyError {
+                    msg: &msg,
+                    ..MYSQL_ER_INVALID_CHARACTER_STRING
+                };
+                self.send_packet(|buf| write_err_packet_from_err(&my_err, capabilities, buf))?;
+                Ok(None)
+            }
+        }
+    }
 
-                    if (capabilities & CAPABILITY_CLIENT_DEPRECATE_EOF) == 0 {
-                        self.send_packet(|buf| write_eof_packet(capabilities, buf))?;
-                    }
-                }
-                let datatypes: Vec<_> = fields.iter().map(|f| f.data_type).collect();
-                loop {
-                    match executor.next() {
-                        Ok(Some((tuple, freq))) => {
-                            if !fields.is_empty() {
-                                for _ in 0..freq {
-                                    self.send_packet(|buf| {
-                                        write_tuple_packet(tuple, &datatypes, buf)
-                                    })?;
-                                }
-                            }
-                        }
-                        Ok(None) => break,
-                        Err(err) => {
-                            let my_err = MyError {
-                                msg: &err.to_string(),
-                                sql_state: "HY000",
-                                code: 1,
-                            };
-                            self.send_packet(|buf| {
-                                write_err_packet_from_err(&my_err, capabilities, buf)
-                            })?;
-                            return Ok(());
-                        }
-                    }
-                }
+    fn process_query_command(&mut self, query: &str) -> Result<(), std::io::Error> {
+        let result = self.connection.execute_statement(query);
+        self.send_query_result(result, false)
+    }
 
-                if fields.is_empty() {
-                    self.send_packet(|buf| write_ok_packet(false, 0, capabilities, buf))?;
-                } else if (capabilities & CAPABILITY_CLIENT_DEPRECATE_EOF) == 0 {
-                    self.send_packet(|buf| write_eof_packet(capabilities, buf))?;
-                } else {
-                    self.send_packet(|buf| write_ok_packet(true, 0, capabilities, buf))?;
-                }
+    fn process_prepare_command(&mut self, query: &str) -> Result<(), std::io::Error> {
+        let capabilities = self.capabilities;
+        let prepared_statement = match self.connection.prepare_statement(query) {
+            Ok(prepared_statement) => prepared_statement,
+            Err(err) => return self.send_query_error(err),
+        };
+        let statement_id = self.next_statement_id.0;
+        self.next_statement_id += Wrapping(1);
+
+        let fields = &prepared_statement.fields;
+        let parameter_types = &prepared_statement.parameter_types;
+        self.send_packet(|buf| {
+            write_stmt_prepare_ok_packet(statement_id, fields.len(), parameter_types.len(), buf)
+        })?;
+        if !parameter_types.is_empty() {
+            for parameter_type in parameter_types {
+                self.send_packet(|buf| {
+                    write_column_packet("", "?", *parameter_type, capabilities, buf)
+                })?;
             }
-            Err(QueryError::ParseError(parse_error)) => {
-                let err = MyError {
-                    msg: &parse_error.to_string(),
-                    ..MYSQL_ER_PARSE_ERROR
-                };
-                self.send_packet(|buf| write_err_packet_from_err(&err, capabilities, buf))?;
+            if (capabilities & CAPABILITY_CLIENT_DEPRECATE_EOF) == 0 {
+                self.send_packet(|buf| write_eof_packet(0, capabilities, buf))?;
+            }
+        }
+        if !fields.is_empty() {
+            for field in fields {
+                self.send_packet(|buf| {
+                    write_column_packet("", &field.alias, field.data_type, capabilities, buf)
+                })?;
+            }
+            if (capabilities & CAPABILITY_CLIENT_DEPRECATE_EOF) == 0 {
+                self.send_packet(|buf| write_eof_packet(0, capabilities, buf))?;
+            }
+        }
+
+        self.prepared_statements.insert(
+            statement_id,
+            MysqlPreparedStatement {
+                prepared_statement,
+                parameter_types: vec![],
+            },
+        );
+        Ok(())
+    }
+
+    fn process_execute_command(
+        &mut self,
+        com_stmt_execute: &ComStmtExecutePacket,
+    ) -> Result<(), std::io::Error> {
+        let capabilities = self.capabilities;
+        let statement = match self
+            .prepared_statements
+            .get_mut(&com_stmt_execute.statement_id)
+        {
+            Some(statement) => statement,
+            None => {
+                return self.send_packet(|buf| {
+                    write_err_packet_from_err(&MYSQL_ER_UNKNOWN_STMT_HANDLER, capabilities, buf)
+                })
             }
+        };
+
+        let parameters = read_execute_parameters(
+            statement.prepared_statement.parameter_types.len(),
+            &mut statement.parameter_types,
+            *self.connection.session.invalid_utf8.read().unwrap(),
+            &com_stmt_execute.parameter_bytes,
+        );
+        let result = match parameters {
+            Ok(parameters) => self
+                .connection
+                .execute_prepared_statement(&statement.prepared_statement, &parameters),
             Err(err) => {
                 let my_err = MyError {
                     msg: &err.to_string(),
-                    sql_state: "HY000",
-                    code: 1,
+                    ..MYSQL_ER_MALFORMED_PACKET
                 };
-                self.send_packet(|buf| write_err_packet_from_err(&my_err, capabilities, buf))?;
+                return self
+                    .send_packet(|buf| write_err_packet_from_err(&my_err, capabilities, buf));
+            }
+        };
+        self.send_query_result(result, true)
+    }
+
+    /// Sends the results back to the client, prepared statements get their results back in the
+    /// binary format, everything else gets the text format.
+    fn send_query_result(
+        &mut self,
+        result: QueryResult,
+        binary: bool,
+    ) -> Result<(), std::io::Error> {
+        let capabilities = self.capabilities;
+        let (fields, mut executor) = match result {
+            Ok(result) => result,
+            Err(err) => return self.send_query_error(err),
+        };
+
+        if !fields.is_empty() {
+            self.send_packet(|buf| write_resultset_packet(fields.len(), capabilities, buf))?;
+            for field in &fields {
+                self.send_packet(|buf| {
+                    write_column_packet("", &field.alias, field.data_type, capabilities, buf)
+                })?;
+            }
+
+            if (capabilities & CAPABILITY_CLIENT_DEPRECATE_EOF) == 0 {
+                self.send_packet(|buf| write_eof_packet(0, capabilities, buf))?;
+            }
+        }
+        let datatypes: Vec<_> = fields.iter().map(|f| f.data_type).collect();
+        loop {
+            match executor.next() {
+                Ok(Some((tuple, freq))) => {
+                    if !fields.is_empty() {
+                        for _ in 0..freq {
+                            if binary {
+                                self.send_packet(|buf| {
+                                    write_binary_tuple_packet(tuple, &datatypes, buf)
+                                })?;
+                            } else {
+                                self.send_packet(|buf| write_tuple_packet(tuple, &datatypes, buf))?;
+                            }
+                        }
+                    }
+                }
+                Ok(None) => break,
+                Err(err) => return self.send_query_error(QueryError::from(err)),
             }
         }
+
+        // Includes warnings past the ones kept for SHOW WARNINGS
+        let warnings = self.connection.session.warning_count().min(u16::MAX as u64) as u16;
+        if fields.is_empty() {
+            self.send_packet(|buf| write_ok_packet(false, 0, warnings, capabilities, buf))?;
+        } else if (capabilities & CAPABILITY_CLIENT_DEPRECATE_EOF) == 0 {
+            self.send_packet(|buf| write_eof_packet(warnings, capabilities, buf))?;
+        } else {
+            self.send_packet(|buf| write_ok_packet(true, 0, warnings, capabilities, buf))?;
+        }
         Ok(())
     }
 
+    /// Sends the error with its code and SQLSTATE so clients can tell errors apart without
+    /// having to pick through the message.
+    fn send_query_error(&mut self, err: QueryError) -> Result<(), std::io::Error> {
+        let capabilities = self.capabilities;
+        let error_code = err.error_code();
+        let my_err = MyError {
+            msg: &err.to_string(),
+            sql_state: error_code.sql_state,
+            code: error_code.code,
+        };
+        self.send_packet(|buf| write_err_packet_from_err(&my_err, capabilities, buf))
+    }
+
     /// Set up the initial handshake with the server
     fn handshake(&mut self) -> Result<(), std::io::Error> {
         // Note that these handshake packets in the connection phase don't have the command byte.
@@ -166,7 +342,9 @@ impl<'a> MysqlConnection<'a> {
         let handshake_response = self.receive_packet::<HandshakeResponsePacket>()?;
         let capabilities = handshake_response.client_flags;
         self.capabilities = capabilities;
-        *self.connection.session.user.write().unwrap() = handshake_response.username;
+        let username = handshake_response.username;
+        *self.connection.session.client_attributes.write().unwrap() =
+            handshake_response.client_connection_attrs;
         if !handshake_response.database.is_empty() {
             *self.connection.session.current_database.write().unwrap() =
                 handshake_response.database;
@@ -178,41 +356,79 @@ impl<'a> MysqlConnection<'a> {
         // Get back the user's password
         let _auth_response = self.receive_packet::<AuthSwitchResponsePacket>()?;
 
+        // Apply the user's session defaults, if they're broken say so rather than carry on
+        if let Err(err) = self.connection.login(&username) {
+            self.send_query_error(err)?;
+            return self.flush_packets();
+        }
+
         // Reply with Ok.
-        self.send_packet(|buf| write_ok_packet(false, 0, capabilities, buf))
+        self.send_packet(|buf| write_ok_packet(false, 0, 0, capabilities, buf))?;
+        self.flush_packets()?;
+
+        // Compression kicks in once the handshake is done, for clients that asked for it.
+        if (capabilities & CAPABILITY_CLIENT_ZSTD_COMPRESSION_ALGORITHM) != 0 {
+            let level = handshake_response.zstd_compression_level as i32;
+            self.stream.start_compression(Compression::Zstd(level));
+        } else if (capabilities & CAPABILITY_CLIENT_COMPRESS) != 0 {
+            self.stream.start_compression(Compression::Zlib);
+        }
+        Ok(())
     }
 
     /// https://dev.mysql.com/doc/dev/mysql-server/8.0.12/page_protocol_basic_packets.html#sect_protocol_basic_packets_packet
-    /// Takes a closure as input, that closure must populate the bytes for the packet being sent
+    /// Takes a closure as input, that closure must populate the bytes for the packet being sent.
+    /// The packet is written straight into the write buffer, it's only sent on once the buffer
+    /// fills up or flush_packets is called.
     fn send_packet<F: Fn(&mut Vec<u8>)>(&mut self, f: F) -> Result<(), std::io::Error> {
-        self.packet_buf.clear();
-        f(&mut self.packet_buf);
-
-        let mut payload_length = self.packet_buf.len() as u32;
-        let mut payload_byte = self.packet_buf.as_slice();
+        // The header gets filled in once we know how long the payload is
+        let header_start = self.write_buf.len();
+        self.write_buf.extend_from_slice(&[0; 4]);
+        f(&mut self.write_buf);
+        let payload_length = self.write_buf.len() - header_start - 4;
 
-        while payload_length > 0 {
-            let packet_length = min(payload_length, 0xffffff) as u32;
-            self.packet_header_buf.clear();
-
-            write_int_3(packet_length, &mut self.packet_header_buf);
-            self.packet_header_buf.push(self.sequence_id.0);
-            self.stream.write_all(&self.packet_header_buf)?;
-            self.stream
-                .write_all(&payload_byte[..(packet_length as usize)])?;
-
-            payload_length -= packet_length;
-            payload_byte = &payload_byte[(packet_length as usize)..];
+        if payload_length < MAX_PAYLOAD_LENGTH {
+            let length_bytes = (payload_length as u32).to_le_bytes();
+            self.write_buf[header_start..(header_start + 3)].copy_from_slice(&length_bytes[..3]);
+            self.write_buf[header_start + 3] = self.sequence_id.0;
             self.sequence_id += Wrapping(1u8);
+        } else {
+            // A full packet always has to be followed by another, even if that's empty
+            let payload = self.write_buf.split_off(header_start + 4);
+            self.write_buf.truncate(header_start);
+            let mut chunks: Vec<&[u8]> = payload.chunks(MAX_PAYLOAD_LENGTH).collect();
+            if payload_length % MAX_PAYLOAD_LENGTH == 0 {
+                chunks.push(&[]);
+            }
+            for chunk in chunks {
+                write_int_3(chunk.len() as u32, &mut self.write_buf);
+                self.write_buf.push(self.sequence_id.0);
+                self.write_buf.extend_from_slice(chunk);
+                self.sequence_id += Wrapping(1u8);
+            }
+        }
+
+        if self.write_buf.len() >= WRITE_BATCH_BYTES {
+            self.stream.write_all(&self.write_buf)?;
+            self.write_buf.clear();
         }
-        self.stream.flush()?;
         Ok(())
     }
 
+    /// Sends on everything buffered up by send_packet
+    fn flush_packets(&mut self) -> Result<(), std::io::Error> {
+        self.stream.write_all(&self.write_buf)?;
+        self.write_buf.clear();
+        // The compression layer may be buffering too
+        self.stream.flush()
+    }
+
     fn receive_packet<P: ClientPacket>(&mut self) -> Result<P, std::io::Error> {
-        self.stream
-            .read_exact(self.packet_header_buf.as_mut_slice())?;
-        let mut header_bytes = self.packet_header_buf.as_slice();
+        // Whatever we've sent may still be sitting in our buffers, it all has to go out before we
+        // wait on the client.
+        self.flush_packets()?;
+        self.stream.read_exact(&mut self.packet_header_buf)?;
+        let mut header_bytes = &self.packet_header_buf[..];
         let mut packet_length = 0_u32;
         header_bytes = read_int_3(&mut packet_length, header_bytes);
 
@@ -235,6 +451,11 @@ enum CommandPacket {
     ComInitDb(ComInitDbPacket),
     ComQuery(ComQueryPacket),
     ComPing,
+    ComProcessKill(ComProcessKillPacket),
+    ComStmtPrepare(ComStmtPreparePacket),
+    ComStmtExecute(ComStmtExecutePacket),
+    ComStmtClose(ComStmtPacket),
+    ComStmtReset(ComStmtPacket),
     ComUnknown,
 }
 
@@ -244,7 +465,12 @@ impl ClientPacket for CommandPacket {
             0x01 => CommandPacket::ComQuit,
             0x02 => CommandPacket::ComInitDb(ComInitDbPacket::read(&buffer[1..])?),
             0x03 => CommandPacket::ComQuery(ComQueryPacket::read(&buffer[1..])?),
+            0x0C => CommandPacket::ComProcessKill(ComProcessKillPacket::read(&buffer[1..])?),
             0x0E => CommandPacket::ComPing,
+            0x16 => CommandPacket::ComStmtPrepare(ComStmtPreparePacket::read(&buffer[1..])?),
+            0x17 => CommandPacket::ComStmtExecute(ComStmtExecutePacket::read(&buffer[1..])?),
+            0x19 => CommandPacket::ComStmtClose(ComStmtPacket::read(&buffer[1..])?),
+            0x1A => CommandPacket::ComStmtReset(ComStmtPacket::read(&buffer[1..])?),
             _ => CommandPacket::ComUnknown,
         };
 
@@ -265,9 +491,23 @@ mod tests {
         assert_eq!(
             packet,
             CommandPacket::ComInitDb(ComInitDbPacket {
-                schema: "test".to_string()
+                schema: b"test".to_vec()
             })
         );
+
+        let buf = [0x19, 0x01, 0x00, 0x00, 0x00].as_ref();
+        let packet = CommandPacket::read(buf)?;
+        assert_eq!(
+            packet,
+            CommandPacket::ComStmtClose(ComStmtPacket { statement_id: 1 })
+        );
+
+        let buf = [0x0C, 0x2A, 0x00, 0x00, 0x00].as_ref();
+        let packet = CommandPacket::read(buf)?;
+        assert_eq!(
+            packet,
+            CommandPacket::ComProcessKill(ComProcessKillPacket { connection_id: 42 })
+        );
         Ok(())
     }
 
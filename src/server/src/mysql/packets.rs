@@ -1,8 +1,12 @@
 use crate::mysql::constants::*;
 use crate::mysql::protocol_base::*;
-use data::{DataType, Datum};
+use data::chrono::{Datelike, NaiveDate, Timelike};
+use data::json::Json;
+use data::rust_decimal::Decimal;
+use data::{DataType, Datum, InvalidUtf8};
 use std::collections::HashMap;
 use std::fmt::Debug;
+use std::io::{ErrorKind, Write};
 
 pub trait ClientPacket
 where
@@ -16,12 +20,14 @@ const SERVER_SUPPORTED_CAPABILITIES: u32 = CAPABILITY_CLIENT_LONG_PASSWORD
     | CAPABILITY_CLIENT_LONG_FLAG
     | CAPABILITY_CLIENT_CONNECT_WITH_DB
     | CAPABILITY_CLIENT_NO_SCHEMA
+    | CAPABILITY_CLIENT_COMPRESS
     | CAPABILITY_CLIENT_PROTOCOL_41
     | CAPABILITY_CLIENT_SECURE_CONNECTION
     | CAPABILITY_CLIENT_CONNECT_ATTRS
     | CAPABILITY_CLIENT_PLUGIN_AUTH
     | CAPABILITY_CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA
-    | CAPABILITY_CLIENT_DEPRECATE_EOF;
+    | CAPABILITY_CLIENT_DEPRECATE_EOF
+    | CAPABILITY_CLIENT_ZSTD_COMPRESSION_ALGORITHM;
 
 /// https://dev.mysql.com/doc/dev/mysql-server/8.0.12/page_protocol_connection_phase_packets_protocol_handshake_v10.html
 pub fn write_handshake_packet(connection_id: u32, buffer: &mut Vec<u8>) {
@@ -68,6 +74,7 @@ pub struct HandshakeResponsePacket {
     pub database: String,
     pub client_plugin_name: String,
     pub client_connection_attrs: HashMap<String, String>,
+    pub zstd_compression_level: u8,
 }
 
 impl ClientPacket for HandshakeResponsePacket {
@@ -104,14 +111,26 @@ impl ClientPacket for HandshakeResponsePacket {
             if !buffer.is_empty() && (packet.client_flags & CAPABILITY_CLIENT_CONNECT_ATTRS) != 0 {
                 let mut len_kvs = 0_u64;
                 buffer = read_enc_int(&mut len_kvs, buffer);
+                let (mut kvs, rem) = buffer.split_at(len_kvs as usize);
 
-                while !buffer.is_empty() {
+                while !kvs.is_empty() {
                     let mut key = String::new();
                     let mut value = String::new();
-                    buffer = read_enc_string(&mut key, buffer);
-                    buffer = read_enc_string(&mut value, buffer);
+                    kvs = read_enc_string(&mut key, kvs);
+                    kvs = read_enc_string(&mut value, kvs);
                     packet.client_connection_attrs.insert(key, value);
                 }
+                buffer = rem;
+            }
+
+            if (packet.client_flags & CAPABILITY_CLIENT_ZSTD_COMPRESSION_ALGORITHM) != 0 {
+                packet.zstd_compression_level = if buffer.is_empty() {
+                    DEFAULT_ZSTD_COMPRESSION_LEVEL
+                } else {
+                    let mut level = 0;
+                    buffer = read_int_1(&mut level, buffer);
+                    level
+                };
             }
         } else {
             packet.client_flags = lower_capibilities as u32 & SERVER_SUPPORTED_CAPABILITIES;
@@ -156,32 +175,241 @@ impl ClientPacket for AuthSwitchResponsePacket {
     }
 }
 
+/// The text of the schema and query packets is left as bytes to be decoded with the session's
+/// invalid_utf8 policy.
 #[derive(Default, Debug, PartialEq)]
 pub struct ComInitDbPacket {
-    pub schema: String,
+    pub schema: Vec<u8>,
 }
 
 impl ClientPacket for ComInitDbPacket {
     fn read(buffer: &[u8]) -> Result<Self, std::io::Error> {
         let mut packet = Self::default();
-        read_eof_string(&mut packet.schema, buffer);
+        read_eof_bytestring(&mut packet.schema, buffer);
         Ok(packet)
     }
 }
 
 #[derive(Default, Debug, PartialEq)]
 pub struct ComQueryPacket {
-    pub query: String,
+    pub query: Vec<u8>,
 }
 
 impl ClientPacket for ComQueryPacket {
     fn read(buffer: &[u8]) -> Result<Self, std::io::Error> {
         let mut packet = Self::default();
-        read_eof_string(&mut packet.query, buffer);
+        read_eof_bytestring(&mut packet.query, buffer);
+        Ok(packet)
+    }
+}
+
+#[derive(Default, Debug, PartialEq)]
+pub struct ComStmtPreparePacket {
+    pub query: Vec<u8>,
+}
+
+impl ClientPacket for ComStmtPreparePacket {
+    fn read(buffer: &[u8]) -> Result<Self, std::io::Error> {
+        let mut packet = Self::default();
+        read_eof_bytestring(&mut packet.query, buffer);
+        Ok(packet)
+    }
+}
+
+/// https://dev.mysql.com/doc/dev/mysql-server/8.0.12/page_protocol_com_stmt_execute.html
+/// The parameter values can only be decoded once we know how many parameters the statement
+/// has, so they're left as raw bytes, see read_execute_parameters.
+#[derive(Default, Debug, PartialEq)]
+pub struct ComStmtExecutePacket {
+    pub statement_id: u32,
+    pub flags: u8,
+    pub iteration_count: u32,
+    pub parameter_bytes: Vec<u8>,
+}
+
+impl ClientPacket for ComStmtExecutePacket {
+    fn read(mut buffer: &[u8]) -> Result<Self, std::io::Error> {
+        let mut packet = Self::default();
+        buffer = read_int_4(&mut packet.statement_id, buffer);
+        buffer = read_int_1(&mut packet.flags, buffer);
+        buffer = read_int_4(&mut packet.iteration_count, buffer);
+        read_eof_bytestring(&mut packet.parameter_bytes, buffer);
+        Ok(packet)
+    }
+}
+
+/// Used for both COM_STMT_CLOSE and COM_STMT_RESET
+#[derive(Default, Debug, PartialEq)]
+pub struct ComStmtPacket {
+    pub statement_id: u32,
+}
+
+impl ClientPacket for ComStmtPacket {
+    fn read(buffer: &[u8]) -> Result<Self, std::io::Error> {
+        let mut packet = Self::default();
+        read_int_4(&mut packet.statement_id, buffer);
+        Ok(packet)
+    }
+}
+
+#[derive(Default, Debug, PartialEq)]
+pub struct ComProcessKillPacket {
+    pub connection_id: u32,
+}
+
+impl ClientPacket for ComProcessKillPacket {
+    fn read(buffer: &[u8]) -> Result<Self, std::io::Error> {
+        let mut packet = Self::default();
+        read_int_4(&mut packet.connection_id, buffer);
         Ok(packet)
     }
 }
 
+/// Decodes the parameter values from a COM_STMT_EXECUTE packet. The client only sends the
+/// parameter types when they change so the types from the previous execution are passed in
+/// and updated as needed. Each type is the mysql type along with whether it's unsigned.
+/// Text parameters are decoded with the session's invalid_utf8 policy.
+pub fn read_execute_parameters(
+    parameter_count: usize,
+    parameter_types: &mut Vec<(u8, bool)>,
+    invalid_utf8: InvalidUtf8,
+    mut buffer: &[u8],
+) -> Result<Vec<(Datum<'static>, DataType)>, std::io::Error> {
+    if parameter_count == 0 {
+        return Ok(vec![]);
+    }
+    let invalid = |msg: &str| std::io::Error::new(ErrorKind::InvalidData, msg);
+
+    let null_bitmap = &buffer[..((parameter_count + 7) / 8)];
+    buffer = &buffer[null_bitmap.len()..];
+    let mut new_params_bound_flag = 0;
+    buffer = read_int_1(&mut new_params_bound_flag, buffer);
+    if new_params_bound_flag == 1 {
+        parameter_types.clear();
+        for _ in 0..parameter_count {
+            let mut parameter_type = 0;
+            let mut flags = 0;
+            buffer = read_int_1(&mut parameter_type, buffer);
+            buffer = read_int_1(&mut flags, buffer);
+            parameter_types.push((parameter_type, (flags & 0x80) != 0));
+        }
+    }
+    if parameter_types.len() != parameter_count {
+        return Err(invalid("Parameter types not sent"));
+    }
+
+    let mut parameters = Vec::with_capacity(parameter_count);
+    for (idx, (parameter_type, unsigned)) in parameter_types.iter().enumerate() {
+        if (null_bitmap[idx / 8] & (1 << (idx % 8))) != 0 {
+            parameters.push((Datum::Null, DataType::Null));
+            continue;
+        }
+
+        let parameter = match *parameter_type {
+            MYSQL_TYPE_NULL => (Datum::Null, DataType::Null),
+            MYSQL_TYPE_TINY => {
+                let mut i = 0;
+                buffer = read_int_1(&mut i, buffer);
+                let i = if *unsigned { i as i32 } else { i as i8 as i32 };
+                (Datum::from(i), DataType::Integer)
+            }
+            MYSQL_TYPE_SHORT | MYSQL_TYPE_YEAR => {
+                let mut i = 0;
+                buffer = read_int_2(&mut i, buffer);
+                let i = if *unsigned { i as i32 } else { i as i16 as i32 };
+                (Datum::from(i), DataType::Integer)
+            }
+            MYSQL_TYPE_LONG | MYSQL_TYPE_INT24 => {
+                let mut i = 0;
+                buffer = read_int_4(&mut i, buffer);
+                if *unsigned {
+                    (Datum::from(i as i64), DataType::BigInt)
+                } else {
+                    (Datum::from(i as i32), DataType::Integer)
+                }
+            }
+            MYSQL_TYPE_LONGLONG => {
+                let mut i = 0;
+                buffer = read_int_8(&mut i, buffer);
+                if *unsigned && i > i64::MAX as u64 {
+                    (Datum::from(Decimal::from(i)), DataType::Decimal(20, 0))
+                } else {
+                    (Datum::from(i as i64), DataType::BigInt)
+                }
+            }
+            // We don't have any floating point types so these get passed through as text to be
+            // cast to whatever's needed.
+            MYSQL_TYPE_FLOAT => {
+                let mut i = 0;
+                buffer = read_int_4(&mut i, buffer);
+                (Datum::from(f32::from_bits(i).to_string()), DataType::Text)
+            }
+            MYSQL_TYPE_DOUBLE => {
+                let mut i = 0;
+                buffer = read_int_8(&mut i, buffer);
+                (Datum::from(f64::from_bits(i).to_string()), DataType::Text)
+            }
+            MYSQL_TYPE_DATE | MYSQL_TYPE_DATETIME | MYSQL_TYPE_TIMESTAMP => {
+                let mut len = 0;
+                let mut year = 0;
+                let (mut month, mut day, mut hour, mut minute, mut second) = (0, 0, 0, 0, 0);
+                let mut micros = 0;
+                buffer = read_int_1(&mut len, buffer);
+                if len >= 4 {
+                    buffer = read_int_2(&mut year, buffer);
+                    buffer = read_int_1(&mut month, buffer);
+                    buffer = read_int_1(&mut day, buffer);
+                }
+                if len >= 7 {
+                    buffer = read_int_1(&mut hour, buffer);
+                    buffer = read_int_1(&mut minute, buffer);
+                    buffer = read_int_1(&mut second, buffer);
+                }
+                if len >= 11 {
+                    buffer = read_int_4(&mut micros, buffer);
+                }
+                let date = NaiveDate::from_ymd_opt(year as i32, month as u32, day as u32)
+                    .ok_or_else(|| invalid("Invalid date parameter"))?;
+                if *parameter_type == MYSQL_TYPE_DATE {
+                    (Datum::from(date), DataType::Date)
+                } else {
+                    let timestamp = date
+                        .and_hms_micro_opt(hour as u32, minute as u32, second as u32, micros)
+                        .ok_or_else(|| invalid("Invalid timestamp parameter"))?;
+                    (Datum::from(timestamp), DataType::Timestamp)
+                }
+            }
+            MYSQL_TYPE_TINY_BLOB
+            | MYSQL_TYPE_MEDIUM_BLOB
+            | MYSQL_TYPE_LONG_BLOB
+            | MYSQL_TYPE_BLOB => {
+                let mut bytes = vec![];
+                buffer = read_enc_bytestring(&mut bytes, buffer);
+                (Datum::from(bytes), DataType::ByteA)
+            }
+            MYSQL_TYPE_VARCHAR
+            | MYSQL_TYPE_VAR_STRING
+            | MYSQL_TYPE_STRING
+            | MYSQL_TYPE_DECIMAL
+            | MYSQL_TYPE_NEWDECIMAL
+            | MYSQL_TYPE_JSON
+            | MYSQL_TYPE_ENUM
+            | MYSQL_TYPE_SET => {
+                let mut bytes = vec![];
+                buffer = read_enc_bytestring(&mut bytes, buffer);
+                let text = invalid_utf8
+                    .decode(&bytes)
+                    .map_err(|_| invalid("Invalid utf8 parameter"))?
+                    .into_owned();
+                (Datum::from(text), DataType::Text)
+            }
+            _ => return Err(invalid("Unsupported parameter type")),
+        };
+        parameters.push(parameter);
+    }
+    Ok(parameters)
+}
+
 pub fn write_err_packet(
     error_code: u16,
     error_message: &str,
@@ -210,19 +438,108 @@ pub fn write_tuple_packet(tuple: &[Datum], types: &[DataType], buffer: &mut Vec<
         match value {
             Datum::Null => buffer.push(0xFB),
             Datum::Boolean(b) => write_enc_string(if *b { "1" } else { "0" }, buffer),
-            // TODO We could keep a buffer and write into that, then calc the length and copy across
-            // to avoid format allocating strings...
-            _ => write_enc_string(format!("{}", value.typed_with(types[idx])), buffer),
+            _ => write_enc_datum(value, types[idx], buffer),
+        }
+    }
+}
+
+/// Writes the datum as a length encoded string, formatted straight into the buffer.
+/// The common types skip the Display machinery altogether, everything else is still formatted
+/// via TypedDatum's Display but without going through an intermediate String.
+fn write_enc_datum(value: &Datum, datatype: DataType, buffer: &mut Vec<u8>) {
+    write_enc_string_with(
+        |buffer| match (value, datatype, value.as_maybe_bytea()) {
+            (Datum::Integer(i), DataType::Integer, _) => {
+                itoa::write(buffer, *i).unwrap();
+            }
+            (Datum::BigInt(i), DataType::BigInt, _) => {
+                itoa::write(buffer, *i).unwrap();
+            }
+            (_, DataType::Text, Some(bytes)) => buffer.extend_from_slice(bytes),
+            (_, DataType::Json, Some(bytes)) => {
+                serde_json::to_writer(buffer, &Json::from_bytes(bytes)).unwrap()
+            }
+            _ => write!(buffer, "{}", value.typed_with(datatype)).unwrap(),
+        },
+        buffer,
+    )
+}
+
+/// https://dev.mysql.com/doc/dev/mysql-server/8.0.12/page_protocol_binary_resultset.html
+pub fn write_binary_tuple_packet(tuple: &[Datum], types: &[DataType], buffer: &mut Vec<u8>) {
+    let header = 0;
+    // The first 2 bits of the null bitmap are reserved for rows.
+    let null_bitmap_offset = 2;
+
+    write_int_1(header, buffer);
+    let null_bitmap_start = buffer.len();
+    buffer.resize(
+        null_bitmap_start + (tuple.len() + 7 + null_bitmap_offset) / 8,
+        0,
+    );
+    for (idx, value) in tuple.iter().enumerate() {
+        if value.is_null() {
+            let bit = idx + null_bitmap_offset;
+            buffer[null_bitmap_start + bit / 8] |= 1 << (bit % 8);
+            continue;
+        }
+        match types[idx] {
+            DataType::Boolean => write_int_1(value.as_boolean() as u8, buffer),
+            DataType::Integer => write_int_4(value.as_integer() as u32, buffer),
+            DataType::BigInt => write_int_8(value.as_bigint() as u64, buffer),
+            DataType::Date => {
+                let date = value.as_date();
+                write_int_1(4, buffer);
+                write_int_2(date.year() as u16, buffer);
+                write_int_1(date.month() as u8, buffer);
+                write_int_1(date.day() as u8, buffer);
+            }
+            DataType::Timestamp => {
+                let timestamp = value.as_timestamp();
+                write_int_1(11, buffer);
+                write_int_2(timestamp.year() as u16, buffer);
+                write_int_1(timestamp.month() as u8, buffer);
+                write_int_1(timestamp.day() as u8, buffer);
+                write_int_1(timestamp.hour() as u8, buffer);
+                write_int_1(timestamp.minute() as u8, buffer);
+                write_int_1(timestamp.second() as u8, buffer);
+                write_int_4(timestamp.nanosecond() / 1000, buffer);
+            }
+            _ => write_enc_datum(value, types[idx], buffer),
         }
     }
 }
 
+/// https://dev.mysql.com/doc/dev/mysql-server/8.0.12/page_protocol_com_stmt_prepare.html
+pub fn write_stmt_prepare_ok_packet(
+    statement_id: u32,
+    column_count: usize,
+    parameter_count: usize,
+    buffer: &mut Vec<u8>,
+) {
+    let status = 0;
+    let filler = 0;
+    let warnings = 0;
+
+    write_int_1(status, buffer);
+    write_int_4(statement_id, buffer);
+    write_int_2(column_count as u16, buffer);
+    write_int_2(parameter_count as u16, buffer);
+    write_int_1(filler, buffer);
+    write_int_2(warnings, buffer);
+}
+
 /// https://dev.mysql.com/doc/dev/mysql-server/8.0.12/page_protocol_basic_ok_packet.html
-pub fn write_ok_packet(eof: bool, affected_rows: u64, capabilities: u32, buffer: &mut Vec<u8>) {
+pub fn write_ok_packet(
+    eof: bool,
+    affected_rows: u64,
+    warnings: u16,
+    capabilities: u32,
+    buffer: &mut Vec<u8>,
+) {
     let header = if eof { 0xFE } else { 0 };
     let last_insert_id = 0;
     let status_flags = STATUS_FLAG_AUTOCOMMIT;
-    let warnings = 0;
     let info = "";
 
     write_int_1(header, buffer);
@@ -242,9 +559,8 @@ pub fn write_ok_packet(eof: bool, affected_rows: u64, capabilities: u32, buffer:
     }
 }
 
-pub fn write_eof_packet(capabilities: u32, buffer: &mut Vec<u8>) {
+pub fn write_eof_packet(warnings: u16, capabilities: u32, buffer: &mut Vec<u8>) {
     let header = 0xFE;
-    let warnings = 0;
     let status_flags = STATUS_FLAG_AUTOCOMMIT; // Autocommit
 
     write_int_1(header, buffer);
@@ -284,7 +600,7 @@ pub fn write_column_packet(
 
     let column_type = match data_type {
         DataType::Null => MYSQL_TYPE_NULL,
-        DataType::Text | DataType::Json | DataType::JsonPath => {
+        DataType::Text | DataType::Json | DataType::JsonPath | DataType::Enum(_) => {
             decimals = 0x1f;
             MYSQL_TYPE_VAR_STRING
         }
@@ -353,7 +669,7 @@ mod tests {
             buf.as_slice(),
             &[
                 10_u8, 56, 46, 48, 46, 48, 45, 105, 110, 99, 114, 101, 115, 113, 108, 0, 1, 0, 0,
-                0, 1, 2, 3, 4, 5, 6, 7, 0, 0, 31, 130, 33, 0, 0, 56, 1, 20, 0, 0, 0, 0, 0, 0, 0, 0,
+                0, 1, 2, 3, 4, 5, 6, 7, 0, 0, 63, 130, 33, 0, 0, 56, 5, 20, 0, 0, 0, 0, 0, 0, 0, 0,
                 0, 0, 1, 1, 2, 3, 4, 5, 6, 7, 8, 9, 1, 1, 0, 109, 121, 115, 113, 108, 95, 110, 97,
                 116, 105, 118, 101, 95, 112, 97, 115, 115, 119, 111, 114, 100, 0
             ] as &[u8]
@@ -412,6 +728,33 @@ mod tests {
             "5.6.6-m9"
         );
         assert_eq!(packet.client_connection_attrs["_platform"], "x86_64");
+
+        // A client asking for zstd sends the level it wants after the attributes
+        let mut raw_packet = vec![];
+        write_int_4(
+            CAPABILITY_CLIENT_PROTOCOL_41
+                | CAPABILITY_CLIENT_PLUGIN_AUTH
+                | CAPABILITY_CLIENT_CONNECT_ATTRS
+                | CAPABILITY_CLIENT_COMPRESS
+                | CAPABILITY_CLIENT_ZSTD_COMPRESSION_ALGORITHM,
+            &mut raw_packet,
+        );
+        write_int_4(0x1000000, &mut raw_packet);
+        write_int_1(CHARSET_UTF8_GENERAL_CI, &mut raw_packet);
+        raw_packet.extend_from_slice(&[0; 23]);
+        write_null_string("root", &mut raw_packet);
+        write_int_1(0, &mut raw_packet);
+        write_null_string("mysql_native_password", &mut raw_packet);
+        let mut attrs = vec![];
+        write_enc_string("foo", &mut attrs);
+        write_enc_string("bar", &mut attrs);
+        write_enc_string(attrs, &mut raw_packet);
+        write_int_1(7, &mut raw_packet);
+        let packet = HandshakeResponsePacket::read(raw_packet.as_ref())?;
+        assert_eq!(packet.username, "root");
+        assert_eq!(packet.client_connection_attrs["foo"], "bar");
+        assert_ne!(packet.client_flags & CAPABILITY_CLIENT_COMPRESS, 0);
+        assert_eq!(packet.zstd_compression_level, 7);
         Ok(())
     }
 
@@ -441,7 +784,7 @@ mod tests {
     fn test_com_init_db_packet() -> Result<(), Box<dyn Error>> {
         let raw_packet = "abcdef".as_bytes();
         let packet = ComInitDbPacket::read(raw_packet)?;
-        assert_eq!(packet.schema, "abcdef");
+        assert_eq!(packet.schema, b"abcdef");
         Ok(())
     }
 
@@ -449,10 +792,119 @@ mod tests {
     fn test_com_query_packet() -> Result<(), Box<dyn Error>> {
         let raw_packet = "Select foo from bar".as_bytes();
         let packet = ComQueryPacket::read(raw_packet)?;
-        assert_eq!(packet.query, "Select foo from bar");
+        assert_eq!(packet.query, b"Select foo from bar");
+
+        // Left as is for the session to decode
+        let packet = ComQueryPacket::read(b"Select '\xff'")?;
+        assert_eq!(packet.query, b"Select '\xff'");
+        Ok(())
+    }
+
+    #[test]
+    fn test_com_stmt_execute_packet() -> Result<(), Box<dyn Error>> {
+        // Sample packet from https://dev.mysql.com/doc/dev/mysql-server/8.0.12/page_protocol_com_stmt_execute.html
+        // with the command byte removed
+        let raw_packet = [
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x01, 0x0f, 0x00, 0x03,
+            0x66, 0x6f, 0x6f,
+        ];
+        let packet = ComStmtExecutePacket::read(raw_packet.as_ref())?;
+        assert_eq!(packet.statement_id, 1);
+        assert_eq!(packet.iteration_count, 1);
+
+        let mut parameter_types = vec![];
+        let parameters = read_execute_parameters(
+            1,
+            &mut parameter_types,
+            InvalidUtf8::Error,
+            &packet.parameter_bytes,
+        )?;
+        assert_eq!(
+            parameters,
+            vec![(Datum::from("foo".to_string()), DataType::Text)]
+        );
+
+        // Invalid utf8 text is rejected or replaced depending on the policy
+        let bytes = [0x00, 0x01, 0x0f, 0x00, 0x02, 0x66, 0xff];
+        let mut parameter_types = vec![];
+        assert!(
+            read_execute_parameters(1, &mut parameter_types, InvalidUtf8::Error, &bytes).is_err()
+        );
+        let parameters =
+            read_execute_parameters(1, &mut parameter_types, InvalidUtf8::Replace, &bytes)?;
+        assert_eq!(
+            parameters,
+            vec![(Datum::from("f\u{FFFD}".to_string()), DataType::Text)]
+        );
+
+        // Types aren't resent, null bitmap is set for the 2nd param
+        let mut parameter_types = vec![(MYSQL_TYPE_LONG, false), (MYSQL_TYPE_LONG, false)];
+        let parameters = read_execute_parameters(
+            2,
+            &mut parameter_types,
+            InvalidUtf8::Error,
+            &[0x02, 0x00, 0xff, 0xff, 0xff, 0xff],
+        )?;
+        assert_eq!(
+            parameters,
+            vec![
+                (Datum::from(-1), DataType::Integer),
+                (Datum::Null, DataType::Null)
+            ]
+        );
         Ok(())
     }
 
+    #[test]
+    fn test_binary_tuple_packet() {
+        let mut buf = vec![];
+        write_binary_tuple_packet(
+            &[Datum::Null, Datum::from(1), Datum::from("a")],
+            &[DataType::Integer, DataType::Integer, DataType::Text],
+            &mut buf,
+        );
+        assert_eq!(buf.as_slice(), [0, 4, 1, 0, 0, 0, 1, 97].as_ref());
+    }
+
+    #[test]
+    fn test_tuple_packet() {
+        let mut buf = vec![];
+        write_tuple_packet(
+            &[
+                Datum::Null,
+                Datum::from(-12),
+                Datum::from(true),
+                Datum::from("a"),
+                Datum::from(12345678901_i64),
+                Datum::from(Decimal::new(1234, 2)),
+            ],
+            &[
+                DataType::Integer,
+                DataType::Integer,
+                DataType::Boolean,
+                DataType::Text,
+                DataType::BigInt,
+                DataType::Decimal(4, 2),
+            ],
+            &mut buf,
+        );
+        let mut expected = vec![0xFB, 3, b'-', b'1', b'2', 1, b'1', 1, b'a', 11];
+        expected.extend_from_slice(b"12345678901");
+        expected.push(5);
+        expected.extend_from_slice(b"12.34");
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn test_stmt_prepare_ok_packet() {
+        let mut buf = vec![];
+        write_stmt_prepare_ok_packet(1, 2, 3, &mut buf);
+        assert_eq!(
+            buf.as_slice(),
+            [0, 1, 0, 0, 0, 2, 0, 3, 0, 0, 0, 0].as_ref()
+        );
+    }
+
     #[test]
     fn test_err_packet() {
         let mut buf = vec![];
@@ -496,7 +948,7 @@ mod tests {
     #[test]
     fn test_ok_packet() {
         let mut buf = vec![];
-        write_ok_packet(false, 0, SERVER_SUPPORTED_CAPABILITIES, &mut buf);
+        write_ok_packet(false, 0, 0, SERVER_SUPPORTED_CAPABILITIES, &mut buf);
         // Expected response from https://dev.mysql.com/doc/dev/mysql-server/8.0.12/page_protocol_basic_ok_packet.html
         assert_eq!(
             buf.as_slice(),
@@ -507,7 +959,7 @@ mod tests {
     #[test]
     fn test_eof_packet() {
         let mut buf = vec![];
-        write_eof_packet(SERVER_SUPPORTED_CAPABILITIES, &mut buf);
+        write_eof_packet(0, SERVER_SUPPORTED_CAPABILITIES, &mut buf);
         // Expected response from https://dev.mysql.com/doc/dev/mysql-server/8.0.12/page_protocol_basic_eof_packet.html
         assert_eq!(buf.as_slice(), [0xfe, 0x00, 0x00, 0x02, 0x00].as_ref());
     }
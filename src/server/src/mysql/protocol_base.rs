@@ -42,6 +42,15 @@ pub fn read_int_4<'a>(i: &mut u32, buffer: &'a [u8]) -> &'a [u8] {
     &buffer[4..]
 }
 
+pub fn write_int_8(i: u64, buffer: &mut Vec<u8>) {
+    buffer.extend_from_slice(&i.to_le_bytes());
+}
+
+pub fn read_int_8<'a>(i: &mut u64, buffer: &'a [u8]) -> &'a [u8] {
+    *i = u64::from_le_bytes(AsRef::<[u8]>::as_ref(&buffer[..8]).try_into().unwrap());
+    &buffer[8..]
+}
+
 pub fn write_enc_int(i: u64, buffer: &mut Vec<u8>) {
     if i < 251 {
         buffer.push(i as u8);
@@ -89,8 +98,17 @@ pub fn write_null_string<S: AsRef<[u8]>>(s: S, buffer: &mut Vec<u8>) {
     buffer.push(0);
 }
 
+/// Reads a null terminated string, any invalid utf8 is replaced. Text that's run against the
+/// session is read as bytes and decoded with the session's invalid_utf8 policy instead.
 pub fn read_null_string<'a>(s: &mut String, buffer: &'a [u8]) -> &'a [u8] {
-    read_null_bytestring(unsafe { s.as_mut_vec() }, buffer)
+    let len = buffer.iter().position(|b| *b == 0).unwrap_or(buffer.len());
+    set_lossy(s, &buffer[..len]);
+    &buffer[(len + 1)..]
+}
+
+fn set_lossy(s: &mut String, bytes: &[u8]) {
+    s.clear();
+    s.push_str(&String::from_utf8_lossy(bytes));
 }
 
 pub fn read_null_bytestring<'a>(s: &mut Vec<u8>, buffer: &'a [u8]) -> &'a [u8] {
@@ -111,13 +129,6 @@ pub fn write_eof_string<S: AsRef<[u8]>>(s: S, buffer: &mut Vec<u8>) {
     buffer.extend_from_slice(s.as_ref());
 }
 
-pub fn read_eof_string<'a>(s: &mut String, buffer: &'a [u8]) -> &'a [u8] {
-    let vec = unsafe { s.as_mut_vec() };
-    vec.clear();
-    vec.extend_from_slice(buffer);
-    &[]
-}
-
 pub fn read_eof_bytestring<'a>(s: &mut Vec<u8>, buffer: &'a [u8]) -> &'a [u8] {
     s.clear();
     s.extend_from_slice(buffer);
@@ -129,6 +140,24 @@ pub fn write_enc_string<S: AsRef<[u8]>>(s: S, buffer: &mut Vec<u8>) {
     write_eof_string(s, buffer);
 }
 
+/// Writes a length encoded string whose contents are written into the buffer by f, this saves
+/// having to format values into a String first just to find out their length.
+/// Space for a 1 byte length is left up front, it's only for the rare long value that the
+/// contents need shifting along to make room for a longer length.
+pub fn write_enc_string_with<F: FnOnce(&mut Vec<u8>)>(f: F, buffer: &mut Vec<u8>) {
+    let start = buffer.len();
+    buffer.push(0);
+    f(buffer);
+    let length = buffer.len() - start - 1;
+    if length < 251 {
+        buffer[start] = length as u8;
+    } else {
+        let mut length_bytes = Vec::with_capacity(9);
+        write_enc_int(length as u64, &mut length_bytes);
+        buffer.splice(start..(start + 1), length_bytes);
+    }
+}
+
 pub fn read_enc_string<'a>(s: &mut String, buffer: &'a [u8]) -> &'a [u8] {
     let mut length = 0;
     let rem = read_enc_int(&mut length, buffer);
@@ -144,7 +173,8 @@ pub fn read_enc_bytestring<'a>(s: &mut Vec<u8>, buffer: &'a [u8]) -> &'a [u8] {
 }
 
 pub fn read_fixed_length_string<'a>(s: &mut String, length: usize, buffer: &'a [u8]) -> &'a [u8] {
-    read_fixed_length_bytestring(unsafe { s.as_mut_vec() }, length, buffer)
+    set_lossy(s, &buffer[..length]);
+    &buffer[length..]
 }
 
 pub fn read_fixed_length_bytestring<'a>(
@@ -201,6 +231,16 @@ mod tests {
         assert!(rem.is_empty());
     }
 
+    #[test]
+    fn test_int_8() {
+        let mut buf = vec![];
+        write_int_8(12345678901234567890, &mut buf);
+        let mut i = 0;
+        let rem = read_int_8(&mut i, &buf);
+        assert_eq!(i, 12345678901234567890);
+        assert!(rem.is_empty());
+    }
+
     #[test]
     fn test_enc_int() {
         let mut buf = vec![];
@@ -231,6 +271,11 @@ mod tests {
         rem = read_null_bytestring(&mut w, rem);
         assert_eq!(h, "hello");
         assert_eq!(w, "world".as_bytes());
+        assert!(rem.is_empty());
+
+        // Invalid utf8 is replaced rather than making its way into the string
+        let rem = read_null_string(&mut h, b"he\xffllo\0");
+        assert_eq!(h, "he\u{FFFD}llo");
         assert!(rem.is_empty())
     }
 
@@ -238,11 +283,7 @@ mod tests {
     fn test_eof_string() {
         let mut buf = vec![];
         write_eof_string("hello", &mut buf);
-        let mut h = String::new();
-        let rem = read_eof_string(&mut h, &buf);
-        assert_eq!(h, "hello");
         assert_eq!(buf.len(), "hello".len());
-        assert!(rem.is_empty());
 
         let mut h2 = Vec::new();
         let rem = read_eof_bytestring(&mut h2, &buf);
@@ -263,6 +304,23 @@ mod tests {
         assert!(rem.is_empty())
     }
 
+    #[test]
+    fn test_enc_string_with() {
+        let long = "a".repeat(300);
+        let mut buf = vec![];
+        write_enc_string_with(|buf| buf.extend_from_slice(b"hello"), &mut buf);
+        write_enc_string_with(|buf| buf.extend_from_slice(long.as_bytes()), &mut buf);
+        write_enc_string_with(|_| {}, &mut buf);
+        let (mut h, mut l, mut e) = (String::new(), String::new(), String::new());
+        let mut rem = read_enc_string(&mut h, &buf);
+        rem = read_enc_string(&mut l, rem);
+        rem = read_enc_string(&mut e, rem);
+        assert_eq!(h, "hello");
+        assert_eq!(l, long);
+        assert_eq!(e, "");
+        assert!(rem.is_empty())
+    }
+
     #[test]
     fn test_fixed_length_string() {
         let buf = "helloworld".as_bytes();
@@ -0,0 +1,267 @@
+use data::json::Json;
+use data::{DataType, Datum};
+use std::io::Write;
+
+// https://www.postgresql.org/docs/current/protocol-message-formats.html
+// Everything is big endian, each message (other than the startup ones) is a type byte followed
+// by an int32 length that includes itself but not the type byte.
+
+pub const PROTOCOL_VERSION_3: i32 = 196_608;
+pub const CANCEL_REQUEST_CODE: i32 = 80_877_102;
+pub const SSL_REQUEST_CODE: i32 = 80_877_103;
+pub const GSSENC_REQUEST_CODE: i32 = 80_877_104;
+
+/// The oid postgres knows the type by, types postgres doesn't have are sent as text
+pub fn type_oid(datatype: DataType) -> i32 {
+    match datatype {
+        DataType::Boolean => 16,
+        DataType::ByteA => 17,
+        DataType::BigInt => 20,
+        DataType::Integer => 23,
+        DataType::Json => 114,
+        DataType::Date => 1082,
+        DataType::Timestamp => 1114,
+        DataType::Decimal(..) => 1700,
+        DataType::JsonPath => 4072,
+        DataType::Null | DataType::Text | DataType::Enum(_) => 25,
+    }
+}
+
+/// The size of the type's values in bytes, -1 for variable length types
+fn type_size(datatype: DataType) -> i16 {
+    match datatype {
+        DataType::Boolean => 1,
+        DataType::Integer | DataType::Date => 4,
+        DataType::BigInt | DataType::Timestamp => 8,
+        _ => -1,
+    }
+}
+
+/// The type modifier, only numeric has one, it packs the precision and scale
+fn type_modifier(datatype: DataType) -> i32 {
+    match datatype {
+        DataType::Decimal(p, s) => (((p as i32) << 16) | s as i32) + 4,
+        _ => -1,
+    }
+}
+
+/// Writes the message type and leaves room for the length, returns where the length goes for
+/// end_message to fill in.
+fn start_message(tag: u8, buffer: &mut Vec<u8>) -> usize {
+    buffer.push(tag);
+    let length_start = buffer.len();
+    buffer.extend_from_slice(&[0; 4]);
+    length_start
+}
+
+fn end_message(length_start: usize, buffer: &mut Vec<u8>) {
+    let length = (buffer.len() - length_start) as i32;
+    buffer[length_start..(length_start + 4)].copy_from_slice(&length.to_be_bytes());
+}
+
+fn write_cstring(s: &str, buffer: &mut Vec<u8>) {
+    buffer.extend_from_slice(s.as_bytes());
+    buffer.push(0);
+}
+
+/// Reads a null terminated string, returning it and the rest of the buffer. A missing
+/// terminator takes the rest of the buffer.
+pub fn read_cstring(buffer: &[u8]) -> (String, &[u8]) {
+    let end = buffer.iter().position(|b| *b == 0).unwrap_or(buffer.len());
+    let s = String::from_utf8_lossy(&buffer[..end]).to_string();
+    (s, &buffer[(end + 1).min(buffer.len())..])
+}
+
+/// Reads the name/value pairs of a startup message (after the protocol version), the list ends
+/// with an empty name.
+pub fn read_startup_parameters(mut buffer: &[u8]) -> Vec<(String, String)> {
+    let mut parameters = vec![];
+    loop {
+        let (name, rest) = read_cstring(buffer);
+        if name.is_empty() {
+            return parameters;
+        }
+        let (value, rest) = read_cstring(rest);
+        parameters.push((name, value));
+        buffer = rest;
+    }
+}
+
+pub fn write_authentication_ok(buffer: &mut Vec<u8>) {
+    let length_start = start_message(b'R', buffer);
+    buffer.extend_from_slice(&0_i32.to_be_bytes());
+    end_message(length_start, buffer);
+}
+
+pub fn write_parameter_status(name: &str, value: &str, buffer: &mut Vec<u8>) {
+    let length_start = start_message(b'S', buffer);
+    write_cstring(name, buffer);
+    write_cstring(value, buffer);
+    end_message(length_start, buffer);
+}
+
+/// The status is idle or in a transaction block, we've no failed transaction state as statements
+/// that fail in a transaction don't abort it.
+pub fn write_ready_for_query(in_transaction: bool, buffer: &mut Vec<u8>) {
+    let length_start = start_message(b'Z', buffer);
+    buffer.push(if in_transaction { b'T' } else { b'I' });
+    end_message(length_start, buffer);
+}
+
+/// Describes the columns of the rows to come, all of them in the text format
+pub fn write_row_description(fields: &[(&str, DataType)], buffer: &mut Vec<u8>) {
+    let length_start = start_message(b'T', buffer);
+    buffer.extend_from_slice(&(fields.len() as i16).to_be_bytes());
+    for (name, datatype) in fields {
+        write_cstring(name, buffer);
+        // Table oid and column attribute number, we don't have either
+        buffer.extend_from_slice(&0_i32.to_be_bytes());
+        buffer.extend_from_slice(&0_i16.to_be_bytes());
+        buffer.extend_from_slice(&type_oid(*datatype).to_be_bytes());
+        buffer.extend_from_slice(&type_size(*datatype).to_be_bytes());
+        buffer.extend_from_slice(&type_modifier(*datatype).to_be_bytes());
+        // Text format
+        buffer.extend_from_slice(&0_i16.to_be_bytes());
+    }
+    end_message(length_start, buffer);
+}
+
+pub fn write_data_row(tuple: &[Datum], types: &[DataType], buffer: &mut Vec<u8>) {
+    let length_start = start_message(b'D', buffer);
+    buffer.extend_from_slice(&(tuple.len() as i16).to_be_bytes());
+    for (value, datatype) in tuple.iter().zip(types) {
+        if value.is_null() {
+            buffer.extend_from_slice(&(-1_i32).to_be_bytes());
+        } else {
+            let value_start = buffer.len();
+            buffer.extend_from_slice(&[0; 4]);
+            write_text_datum(value, *datatype, buffer);
+            let value_length = (buffer.len() - value_start - 4) as i32;
+            buffer[value_start..(value_start + 4)].copy_from_slice(&value_length.to_be_bytes());
+        }
+    }
+    end_message(length_start, buffer);
+}
+
+/// Writes the value the way postgres would in the text format
+fn write_text_datum(value: &Datum, datatype: DataType, buffer: &mut Vec<u8>) {
+    match (value, datatype, value.as_maybe_bytea()) {
+        (Datum::Boolean(b), _, _) => buffer.push(if *b { b't' } else { b'f' }),
+        (Datum::Integer(i), DataType::Integer, _) => {
+            itoa::write(buffer, *i).unwrap();
+        }
+        (Datum::BigInt(i), DataType::BigInt, _) => {
+            itoa::write(buffer, *i).unwrap();
+        }
+        (_, DataType::Text, Some(bytes)) => buffer.extend_from_slice(bytes),
+        (_, DataType::Json, Some(bytes)) => {
+            serde_json::to_writer(buffer, &Json::from_bytes(bytes)).unwrap()
+        }
+        (_, DataType::ByteA, Some(bytes)) => {
+            buffer.extend_from_slice(b"\\x");
+            for b in bytes {
+                write!(buffer, "{:02x}", b).unwrap();
+            }
+        }
+        _ => write!(buffer, "{}", value.typed_with(datatype)).unwrap(),
+    }
+}
+
+pub fn write_command_complete(tag: &str, buffer: &mut Vec<u8>) {
+    let length_start = start_message(b'C', buffer);
+    write_cstring(tag, buffer);
+    end_message(length_start, buffer);
+}
+
+pub fn write_empty_query_response(buffer: &mut Vec<u8>) {
+    let length_start = start_message(b'I', buffer);
+    end_message(length_start, buffer);
+}
+
+pub fn write_error_response(sql_state: &str, message: &str, buffer: &mut Vec<u8>) {
+    let length_start = start_message(b'E', buffer);
+    for (field, value) in &[
+        (b'S', "ERROR"),
+        (b'V', "ERROR"),
+        (b'C', sql_state),
+        (b'M', message),
+    ] {
+        buffer.push(*field);
+        write_cstring(value, buffer);
+    }
+    buffer.push(0);
+    end_message(length_start, buffer);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::rust_decimal::Decimal;
+
+    #[test]
+    fn test_read_startup_parameters() {
+        let parameters = read_startup_parameters(b"user\0root\0database\0default\0\0");
+        assert_eq!(
+            parameters,
+            vec![
+                ("user".to_string(), "root".to_string()),
+                ("database".to_string(), "default".to_string())
+            ]
+        );
+        assert_eq!(
+            read_startup_parameters(b"user\0ro"),
+            vec![("user".to_string(), "ro".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_write_row_description() {
+        let mut buffer = vec![];
+        write_row_description(
+            &[("a", DataType::Integer), ("b", DataType::Decimal(10, 2))],
+            &mut buffer,
+        );
+        let mut expected = vec![b'T', 0, 0, 0, 46, 0, 2];
+        expected.extend_from_slice(b"a\0\0\0\0\0\0\0\0\0\0\x17\0\x04\xff\xff\xff\xff\0\0");
+        expected.extend_from_slice(b"b\0\0\0\0\0\0\0\0\0\x06\xa4\xff\xff\0\x0a\0\x06\0\0");
+        assert_eq!(buffer, expected);
+    }
+
+    #[test]
+    fn test_write_data_row() {
+        let mut buffer = vec![];
+        write_data_row(
+            &[
+                Datum::from(42),
+                Datum::Null,
+                Datum::from(true),
+                Datum::from(vec![0_u8, 255]),
+                Datum::from(Decimal::new(1234, 2)),
+            ],
+            &[
+                DataType::Integer,
+                DataType::Text,
+                DataType::Boolean,
+                DataType::ByteA,
+                DataType::Decimal(10, 2),
+            ],
+            &mut buffer,
+        );
+        let mut expected = vec![b'D', 0, 0, 0, 40, 0, 5];
+        expected.extend_from_slice(b"\0\0\0\x0242");
+        expected.extend_from_slice(b"\xff\xff\xff\xff");
+        expected.extend_from_slice(b"\0\0\0\x01t");
+        expected.extend_from_slice(b"\0\0\0\x06\\x00ff");
+        expected.extend_from_slice(b"\0\0\0\x0512.34");
+        assert_eq!(buffer, expected);
+    }
+
+    #[test]
+    fn test_write_error_response() {
+        let mut buffer = vec![];
+        write_error_response("42P01", "oops", &mut buffer);
+        let mut expected = vec![b'E', 0, 0, 0, 32];
+        expected.extend_from_slice(b"SERROR\0VERROR\0C42P01\0Moops\0\0");
+        assert_eq!(buffer, expected);
+    }
+}
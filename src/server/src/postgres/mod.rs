@@ -0,0 +1,309 @@
+use crate::postgres::messages::*;
+use runtime::connection::{Connection, QueryResult};
+use runtime::QueryError;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+mod messages;
+
+/// Outgoing messages are buffered up to this many bytes before being written to the stream, this
+/// way the rows of a result set go out in batches rather than a write per row.
+const WRITE_BATCH_BYTES: usize = 64 * 1024;
+
+/// Startup messages are small, anything claiming to be bigger than this isn't one
+const MAX_STARTUP_LENGTH: usize = 10_000;
+
+/// The same cap postgres puts on messages, so a bad length can't have us allocate without bound
+const MAX_MESSAGE_LENGTH: usize = 1 << 30;
+
+/// Speaks the simple query flavour of the postgres protocol, enough for psql and most drivers to
+/// run sql and read back the results in the text format.
+pub struct PostgresConnection<'a> {
+    stream: TcpStream,
+    read_buf: Vec<u8>,
+    write_buf: Vec<u8>,
+    connection: Arc<Connection<'a>>,
+}
+
+impl<'a> PostgresConnection<'a> {
+    pub fn new(stream: TcpStream, connection: Arc<Connection<'a>>) -> Self {
+        PostgresConnection {
+            stream,
+            read_buf: Vec::new(),
+            write_buf: Vec::new(),
+            connection,
+        }
+    }
+
+    pub fn connect(&mut self) -> Result<(), std::io::Error> {
+        if !self.startup()? {
+            return self.flush_messages();
+        }
+        // After an error in the extended protocol everything up to the next sync is ignored
+        let mut skip_until_sync = false;
+
+        loop {
+            match self.receive_message()? {
+                b'Q' => {
+                    let (query, _) = read_cstring(&self.read_buf);
+                    self.process_query(&query)?;
+                }
+                b'X' => break,
+                b'S' => {
+                    skip_until_sync = false;
+                    self.send_ready_for_query()?;
+                }
+                // Flush, our buffer is always flushed before we wait on the client anyway
+                b'H' => {}
+                b'P' | b'B' | b'D' | b'E' | b'C' | b'F' => {
+                    if !skip_until_sync {
+                        skip_until_sync = true;
+                        self.send_message(|buf| {
+                            write_error_response(
+                                "0A000",
+                                "Only the simple query protocol is supported",
+                                buf,
+                            )
+                        })?;
+                    }
+                }
+                tag => {
+                    let message = format!("Unknown message type {:?}", tag as char);
+                    self.send_message(|buf| write_error_response("08P01", &message, buf))?;
+                    self.send_ready_for_query()?;
+                }
+            }
+            // A killed connection is closed once it's answered the message it was on
+            if self.connection.session.kill_flag.load(Ordering::Relaxed) {
+                break;
+            }
+        }
+
+        self.flush_messages()
+    }
+
+    /// Reads the startup message, turning down requests for encryption along the way, then logs
+    /// the user in. There's no password check, the same as for mysql connections. Returns false
+    /// if the connection should be closed.
+    fn startup(&mut self) -> Result<bool, std::io::Error> {
+        let parameters = loop {
+            self.receive_startup_message()?;
+            let mut code = [0; 4];
+            code.copy_from_slice(&self.read_buf[..4]);
+            match i32::from_be_bytes(code) {
+                SSL_REQUEST_CODE | GSSENC_REQUEST_CODE => {
+                    self.stream.write_all(b"N")?;
+                }
+                PROTOCOL_VERSION_3 => break read_startup_parameters(&self.read_buf[4..]),
+                // We don't hand out cancel keys so there's nothing to cancel
+                CANCEL_REQUEST_CODE => return Ok(false),
+                _ => {
+                    self.send_message(|buf| {
+                        write_error_response("08P01", "Unsupported frontend protocol", buf)
+                    })?;
+                    return Ok(false);
+                }
+            }
+        };
+
+        let mut username = String::new();
+        for (name, value) in parameters {
+            match name.as_str() {
+                "user" => username = value,
+                "database" if !value.is_empty() => {
+                    *self.connection.session.current_database.write().unwrap() = value;
+                }
+                "application_name" => {
+                    self.connection
+                        .session
+                        .client_attributes
+                        .write()
+                        .unwrap()
+                        .insert("program_name".to_string(), value);
+                }
+                _ => {}
+            }
+        }
+
+        // Apply the user's session defaults, if they're broken say so rather than carry on
+        if let Err(err) = self.connection.login(&username) {
+            self.send_query_error(err)?;
+            return Ok(false);
+        }
+
+        self.send_message(write_authentication_ok)?;
+        for (name, value) in &[
+            ("server_version", "12.0"),
+            ("server_encoding", "UTF8"),
+            ("client_encoding", "UTF8"),
+            ("DateStyle", "ISO, MDY"),
+            ("integer_datetimes", "on"),
+            ("standard_conforming_strings", "on"),
+        ] {
+            self.send_message(|buf| write_parameter_status(name, value, buf))?;
+        }
+        self.send_ready_for_query()?;
+        Ok(true)
+    }
+
+    fn process_query(&mut self, query: &str) -> Result<(), std::io::Error> {
+        if query.trim().trim_end_matches(';').trim().is_empty() {
+            self.send_message(write_empty_query_response)?;
+        } else {
+            let result = self.connection.execute_statement(query);
+            self.send_query_result(query, result)?;
+        }
+        self.send_ready_for_query()
+    }
+
+    fn send_ready_for_query(&mut self) -> Result<(), std::io::Error> {
+        let in_transaction = self.connection.in_transaction();
+        self.send_message(|buf| write_ready_for_query(in_transaction, buf))
+    }
+
+    /// Sends back the rows, if there are any, followed by the command complete message.
+    fn send_query_result(
+        &mut self,
+        query: &str,
+        result: QueryResult,
+    ) -> Result<(), std::io::Error> {
+        let (fields, mut executor) = match result {
+            Ok(result) => result,
+            Err(err) => return self.send_query_error(err),
+        };
+
+        if !fields.is_empty() {
+            let description: Vec<_> = fields
+                .iter()
+                .map(|field| (field.alias.as_str(), field.data_type))
+                .collect();
+            self.send_message(|buf| write_row_description(&description, buf))?;
+        }
+        let datatypes: Vec<_> = fields.iter().map(|f| f.data_type).collect();
+        let mut rows = 0;
+        loop {
+            match executor.next() {
+                Ok(Some((tuple, freq))) => {
+                    if !fields.is_empty() {
+                        for _ in 0..freq {
+                            self.send_message(|buf| write_data_row(tuple, &datatypes, buf))?;
+                        }
+                        rows += freq as u64;
+                    }
+                }
+                Ok(None) => break,
+                Err(err) => return self.send_query_error(QueryError::from(err)),
+            }
+        }
+
+        let tag = command_tag(query, !fields.is_empty(), rows);
+        self.send_message(|buf| write_command_complete(&tag, buf))
+    }
+
+    /// Sends the error with its SQLSTATE, postgres has no catch all like mysql's HY000 so those
+    /// go out as internal_error.
+    fn send_query_error(&mut self, err: QueryError) -> Result<(), std::io::Error> {
+        let sql_state = match err.error_code().sql_state {
+            "HY000" => "XX000",
+            sql_state => sql_state,
+        };
+        let message = err.to_string();
+        self.send_message(|buf| write_error_response(sql_state, &message, buf))
+    }
+
+    /// Takes a closure as input, that closure must write the message being sent. The message is
+    /// written straight into the write buffer, it's only sent on once the buffer fills up or
+    /// flush_messages is called.
+    fn send_message<F: Fn(&mut Vec<u8>)>(&mut self, f: F) -> Result<(), std::io::Error> {
+        f(&mut self.write_buf);
+        if self.write_buf.len() >= WRITE_BATCH_BYTES {
+            self.flush_messages()?;
+        }
+        Ok(())
+    }
+
+    /// Sends on everything buffered up by send_message
+    fn flush_messages(&mut self) -> Result<(), std::io::Error> {
+        self.stream.write_all(&self.write_buf)?;
+        self.write_buf.clear();
+        Ok(())
+    }
+
+    /// Reads the next message into the read buffer, returning its type
+    fn receive_message(&mut self) -> Result<u8, std::io::Error> {
+        // Whatever we've sent may still be sitting in our buffer, it all has to go out before we
+        // wait on the client.
+        self.flush_messages()?;
+        let mut header = [0; 5];
+        self.stream.read_exact(&mut header)?;
+        let mut length = [0; 4];
+        length.copy_from_slice(&header[1..]);
+        self.read_body(i32::from_be_bytes(length), MAX_MESSAGE_LENGTH)?;
+        Ok(header[0])
+    }
+
+    /// Reads a startup message into the read buffer, these have no type, just the length
+    fn receive_startup_message(&mut self) -> Result<(), std::io::Error> {
+        self.flush_messages()?;
+        let mut length = [0; 4];
+        self.stream.read_exact(&mut length)?;
+        self.read_body(i32::from_be_bytes(length), MAX_STARTUP_LENGTH)?;
+        if self.read_buf.len() < 4 {
+            return Err(invalid_data(
+                "Startup message is missing its protocol version",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Reads the rest of a message given the length from its header, which counts itself
+    fn read_body(&mut self, length: i32, max_length: usize) -> Result<(), std::io::Error> {
+        if length < 4 || length as usize > max_length {
+            return Err(invalid_data("Invalid message length"));
+        }
+        self.read_buf.resize(length as usize - 4, 0);
+        self.stream.read_exact(&mut self.read_buf)
+    }
+}
+
+fn invalid_data(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+/// The tag for the command complete message, clients mostly only look at the command but some
+/// parse a row count out of inserts and deletes. We don't keep counts for those so they're 0.
+fn command_tag(query: &str, returns_rows: bool, rows: u64) -> String {
+    if returns_rows {
+        return format!("SELECT {}", rows);
+    }
+    let command = query
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches(';')
+        .to_uppercase();
+    match command.as_str() {
+        "INSERT" => "INSERT 0 0".to_string(),
+        "DELETE" => "DELETE 0".to_string(),
+        "START" => "BEGIN".to_string(),
+        _ => command,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_tag() {
+        assert_eq!(command_tag("select 1", true, 3), "SELECT 3");
+        assert_eq!(command_tag("  create table t (a int)", false, 0), "CREATE");
+        assert_eq!(
+            command_tag("insert into t values (1)", false, 0),
+            "INSERT 0 0"
+        );
+        assert_eq!(command_tag("commit;", false, 0), "COMMIT");
+    }
+}
@@ -0,0 +1,571 @@
+use crate::engine::{KeyValueIter, StorageEngine, StorageStatistics, WriteBatch, WriteOp};
+use crate::StorageError;
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use data::encoding_core::VARINT_SIGNED_ZERO_ENC;
+use rand::RngCore;
+use std::convert::{TryFrom, TryInto};
+use std::sync::{Arc, RwLock};
+
+// Header byte for encrypted values that don't start with a zero frequency
+const ENCRYPTED_MARKER: u8 = 0xEC;
+// Header byte, key id, nonce
+const HEADER_LEN: usize = 1 + 4 + NONCE_LEN;
+const NONCE_LEN: usize = 24;
+// Poly1305 tag on the end of each encrypted value
+const TAG_LEN: usize = 16;
+
+/// Keys from this prefix up hold the database's own metadata (its format version and whether
+/// it's encrypted) rather than table data, they're kept in the clear so they can be checked
+/// before anything's decrypted.
+const CLEAR_PREFIX_START: u32 = 0xffff_fff0;
+/// Present once every value in the database has been encrypted
+const ENCRYPTED_KEY: &[u8] = b"\xff\xff\xff\xffencrypted";
+
+/// The most values looked at while holding up writes to re-encrypt them
+const REWRITE_BATCH_SIZE: usize = 1024;
+
+/// A 256 bit key used to encrypt values at rest.
+#[derive(Clone)]
+pub struct EncryptionKey {
+    id: u32,
+    key: [u8; 32],
+}
+
+impl EncryptionKey {
+    pub fn new(key: [u8; 32]) -> Self {
+        // The id is the start of the tag the key gives an empty value under the all zero nonce,
+        // which the random nonces will never land on, so values can be matched up with the key
+        // they were written with without storing the key.
+        let tag = XChaCha20Poly1305::new(&Key::from(key))
+            .encrypt(&XNonce::default(), b"".as_ref())
+            .unwrap();
+        EncryptionKey {
+            id: u32::from_be_bytes(tag[..4].try_into().unwrap()),
+            key,
+        }
+    }
+
+    /// Parses a key from 64 hex characters, surrounding whitespace is ignored
+    pub fn from_hex(hex: &str) -> Result<Self, StorageError> {
+        let hex = hex.trim();
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err(StorageError::EncryptionError(
+                "Encryption keys must be 64 hex characters".to_string(),
+            ));
+        }
+        let mut key = [0_u8; 32];
+        for (idx, byte) in key.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[idx * 2..idx * 2 + 2], 16).map_err(|_| {
+                StorageError::EncryptionError("Encryption keys must be hex encoded".to_string())
+            })?;
+        }
+        Ok(EncryptionKey::new(key))
+    }
+
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(&Key::from(self.key))
+    }
+}
+
+/// Wraps another storage engine encrypting values with XChaCha20-Poly1305 before they're handed
+/// down to it. Keys are left in the clear as they need to keep their order for scans, so the
+/// primary key columns of tables and indexed columns aren't encrypted.
+/// Each value is encrypted under a random 192 bit nonce with its key and header authenticated
+/// along with it, so values that have been tampered with or moved to another key fail to read
+/// rather than decrypting to garbage.
+/// Values written with one of the previous keys can still be read, compacting a range
+/// re-encrypts them with the current key first so the old keys can be retired once everything
+/// has been compacted.
+/// Values starting with a zero frequency keep that first byte in the clear so rocksdb's
+/// compaction filter can still drop them.
+pub struct EncryptedEngine {
+    inner: Arc<dyn StorageEngine>,
+    current: EncryptionKey,
+    previous: Vec<EncryptionKey>,
+    // Held exclusively while re-encrypting so we don't write back a stale value over a newer one
+    rewrite_lock: RwLock<()>,
+}
+
+impl EncryptedEngine {
+    /// Opens the engine over inner. A database that already has data written without
+    /// encryption is only opened when encrypt_existing is set, in which case all of its values
+    /// are encrypted before this returns. The encryption picks up where it left off if it's
+    /// interrupted.
+    pub fn open(
+        inner: Arc<dyn StorageEngine>,
+        current: EncryptionKey,
+        previous: Vec<EncryptionKey>,
+        encrypt_existing: bool,
+    ) -> Result<Self, StorageError> {
+        let engine = EncryptedEngine {
+            inner,
+            current,
+            previous,
+            rewrite_lock: RwLock::default(),
+        };
+        if !is_encrypted(engine.inner.as_ref())? {
+            if !engine.inner.is_empty()? {
+                if !encrypt_existing {
+                    return Err(StorageError::EncryptionError(
+                        "The database has data that was written without encryption, it has to be opened with encrypt existing data set to encrypt it".to_string(),
+                    ));
+                }
+                engine.encrypt_existing()?;
+            }
+            let mut batch = WriteBatch::default();
+            batch.put(ENCRYPTED_KEY, []);
+            engine.inner.write(batch)?;
+        }
+        Ok(engine)
+    }
+
+    fn encrypt(&self, key: &[u8], value: &[u8]) -> Result<Vec<u8>, StorageError> {
+        let mut encrypted = Vec::with_capacity(HEADER_LEN + value.len() + TAG_LEN);
+        encrypted.push(if value.first() == Some(&VARINT_SIGNED_ZERO_ENC) {
+            VARINT_SIGNED_ZERO_ENC
+        } else {
+            ENCRYPTED_MARKER
+        });
+        encrypted.extend_from_slice(&self.current.id.to_be_bytes());
+        let mut nonce = [0_u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        encrypted.extend_from_slice(&nonce);
+        let aad = associated_data(key, &encrypted);
+        let ciphertext = self
+            .current
+            .cipher()
+            .encrypt(
+                &XNonce::from(nonce),
+                Payload {
+                    msg: value,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| StorageError::EncryptionError("Couldn't encrypt value".to_string()))?;
+        encrypted.extend_from_slice(&ciphertext);
+        Ok(encrypted)
+    }
+
+    fn decrypt(&self, key: &[u8], value: &[u8]) -> Result<Vec<u8>, StorageError> {
+        let encryption_key = self.key_for(value)?;
+        encryption_key
+            .cipher()
+            .decrypt(
+                &XNonce::from(<[u8; NONCE_LEN]>::try_from(&value[5..HEADER_LEN]).unwrap()),
+                Payload {
+                    msg: &value[HEADER_LEN..],
+                    aad: &associated_data(key, value),
+                },
+            )
+            .map_err(|_| {
+                StorageError::EncryptionError(
+                    "Found a value that fails its authentication check, it's been corrupted or tampered with"
+                        .to_string(),
+                )
+            })
+    }
+
+    fn key_for(&self, value: &[u8]) -> Result<&EncryptionKey, StorageError> {
+        if value.len() < HEADER_LEN + TAG_LEN {
+            return Err(StorageError::EncryptionError(
+                "Found a value that isn't encrypted".to_string(),
+            ));
+        }
+        let id = u32::from_be_bytes(value[1..5].try_into().unwrap());
+        std::iter::once(&self.current)
+            .chain(&self.previous)
+            .find(|key| key.id == id)
+            .ok_or_else(|| {
+                StorageError::EncryptionError(format!(
+                    "Found a value encrypted with an unknown key ({:08x})",
+                    id
+                ))
+            })
+    }
+
+    /// Encrypts every value in the database that doesn't already decrypt with one of our keys,
+    /// which can only be the ones written before encryption was turned on.
+    fn encrypt_existing(&self) -> Result<(), StorageError> {
+        let to = CLEAR_PREFIX_START.to_be_bytes();
+        self.rewrite_range(&[], &to, |key, value| match self.decrypt(key, value) {
+            Ok(_) => Ok(None),
+            Err(_) => self.encrypt(key, value).map(Some),
+        })
+    }
+
+    /// Re-encrypts the values in from..to that aren't encrypted with the current key
+    fn reencrypt_range(&self, from: &[u8], to: &[u8]) -> Result<(), StorageError> {
+        self.rewrite_range(from, to, |key, value| {
+            if self.key_for(value)?.id == self.current.id {
+                Ok(None)
+            } else {
+                self.encrypt(key, &self.decrypt(key, value)?).map(Some)
+            }
+        })
+    }
+
+    /// Rewrites the values in from..to that rewrite returns a new value for. Writes are only
+    /// held up for REWRITE_BATCH_SIZE values at a time, each batch is read afresh once we hold
+    /// the lock so we never write back a value that's been changed since.
+    fn rewrite_range<F>(&self, from: &[u8], to: &[u8], rewrite: F) -> Result<(), StorageError>
+    where
+        F: Fn(&[u8], &[u8]) -> Result<Option<Vec<u8>>, StorageError>,
+    {
+        let mut start = match self.inner.first_key_from(from)? {
+            Some(key) if key.as_slice() < to => key,
+            _ => return Ok(()),
+        };
+        loop {
+            // Iterators don't leave the prefix they're seeked to
+            let prefix = prefix_of(&start);
+            let upper_bound = match prefix.checked_add(1) {
+                Some(next) if &next.to_be_bytes()[..] < to => next.to_be_bytes().to_vec(),
+                _ => to.to_vec(),
+            };
+
+            let _rewriting = self.rewrite_lock.write().unwrap();
+            let mut batch = WriteBatch::default();
+            let mut iter = self.inner.prefix_iter(upper_bound.clone());
+            iter.seek(&start);
+            let mut seen = 0;
+            while iter.valid() && seen < REWRITE_BATCH_SIZE {
+                let key = iter.key().unwrap();
+                if let Some(value) = rewrite(key, iter.value().unwrap())? {
+                    batch.put(key, value);
+                }
+                seen += 1;
+                iter.next();
+            }
+            iter.status()?;
+            let next = iter.key().map(<[u8]>::to_vec);
+            self.inner.write(batch)?;
+
+            start = match next {
+                Some(key) => key,
+                None => match self.inner.first_key_from(&upper_bound)? {
+                    Some(key) if key.as_slice() < to => key,
+                    _ => return Ok(()),
+                },
+            };
+        }
+    }
+}
+
+/// Returns true once the database's values have all been encrypted
+pub(crate) fn is_encrypted(engine: &dyn StorageEngine) -> Result<bool, StorageError> {
+    Ok(engine.get(ENCRYPTED_KEY)?.is_some())
+}
+
+/// Each value is bound to its key and to the marker and key id at the front of its header
+fn associated_data(key: &[u8], header: &[u8]) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(key.len() + 5);
+    aad.extend_from_slice(key);
+    aad.extend_from_slice(&header[..5]);
+    aad
+}
+
+fn prefix_of(key: &[u8]) -> u32 {
+    let mut prefix = [0_u8; 4];
+    let len = key.len().min(4);
+    prefix[..len].copy_from_slice(&key[..len]);
+    u32::from_be_bytes(prefix)
+}
+
+fn is_clear(key: &[u8]) -> bool {
+    prefix_of(key) >= CLEAR_PREFIX_START
+}
+
+impl StorageEngine for EncryptedEngine {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        match self.inner.get(key)? {
+            Some(value) if is_clear(key) => Ok(Some(value)),
+            Some(value) => Ok(Some(self.decrypt(key, &value)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn prefix_iter(&self, upper_bound: Vec<u8>) -> Box<dyn KeyValueIter + '_> {
+        Box::new(EncryptedIter {
+            engine: self,
+            iter: self.inner.prefix_iter(upper_bound),
+            value: None,
+            error: None,
+        })
+    }
+
+    fn is_empty(&self) -> Result<bool, StorageError> {
+        // Our own marker isn't data
+        match self.inner.first_key_from(&[])? {
+            Some(key) if key == ENCRYPTED_KEY => {
+                let mut after = key;
+                after.push(0);
+                Ok(self.inner.first_key_from(&after)?.is_none())
+            }
+            key => Ok(key.is_none()),
+        }
+    }
+
+    fn first_key_from(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        self.inner.first_key_from(key)
+    }
+
+    fn write(&self, batch: WriteBatch) -> Result<(), StorageError> {
+        let mut encrypted = WriteBatch::default();
+        for op in batch.ops {
+            encrypted.ops.push(match op {
+                WriteOp::Put(key, value) if !is_clear(&key) => {
+                    let value = self.encrypt(&key, &value)?;
+                    WriteOp::Put(key, value)
+                }
+                op => op,
+            });
+        }
+        let _writing = self.rewrite_lock.read().unwrap();
+        self.inner.write(encrypted)
+    }
+
+    fn compact_range(&self, from: &[u8], to: &[u8]) -> Result<(), StorageError> {
+        self.reencrypt_range(from, to)?;
+        self.inner.compact_range(from, to)
+    }
+
+    fn checkpoint(&self, path: &str) -> Result<(), StorageError> {
+        self.inner.checkpoint(path)
+    }
+
+    fn statistics(&self) -> StorageStatistics {
+        self.inner.statistics()
+    }
+}
+
+/// Decrypts each value as the iterator lands on it, an undecryptable value ends the iteration
+/// with the error left in the status.
+struct EncryptedIter<'a> {
+    engine: &'a EncryptedEngine,
+    iter: Box<dyn KeyValueIter + 'a>,
+    value: Option<Vec<u8>>,
+    error: Option<StorageError>,
+}
+
+impl EncryptedIter<'_> {
+    fn decrypt_current(&mut self) {
+        self.value = match (self.iter.key(), self.iter.value()) {
+            (Some(key), Some(value)) if is_clear(key) => Some(value.to_vec()),
+            (Some(key), Some(value)) => match self.engine.decrypt(key, value) {
+                Ok(value) => Some(value),
+                Err(err) => {
+                    self.error = Some(err);
+                    None
+                }
+            },
+            _ => None,
+        };
+    }
+}
+
+impl KeyValueIter for EncryptedIter<'_> {
+    fn seek(&mut self, key: &[u8]) {
+        self.error = None;
+        self.iter.seek(key);
+        self.decrypt_current();
+    }
+
+    fn valid(&self) -> bool {
+        self.error.is_none() && self.iter.valid()
+    }
+
+    fn next(&mut self) {
+        self.iter.next();
+        self.decrypt_current();
+    }
+
+    fn key(&self) -> Option<&[u8]> {
+        self.iter.key()
+    }
+
+    fn value(&self) -> Option<&[u8]> {
+        self.value.as_deref()
+    }
+
+    fn status(&self) -> Result<(), StorageError> {
+        match &self.error {
+            Some(err) => Err(err.clone()),
+            None => self.iter.status(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::MemEngine;
+
+    fn key(byte: u8) -> EncryptionKey {
+        EncryptionKey::new([byte; 32])
+    }
+
+    #[test]
+    fn test_from_hex() {
+        let hex = "00".repeat(31) + "ff";
+        let mut expected = [0_u8; 32];
+        expected[31] = 0xff;
+        assert_eq!(EncryptionKey::from_hex(&hex).unwrap().key, expected);
+        assert!(EncryptionKey::from_hex("abcd").is_err());
+        assert!(EncryptionKey::from_hex(&"zz".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<(), StorageError> {
+        let inner = Arc::new(MemEngine::default());
+        let engine = EncryptedEngine::open(inner.clone(), key(1), vec![], false)?;
+        let mut batch = WriteBatch::default();
+        batch.put([0, 0, 0, 2, 1], b"hello");
+        batch.put([0, 0, 0, 3, 1], [VARINT_SIGNED_ZERO_ENC]);
+        engine.write(batch)?;
+
+        assert_eq!(engine.get(&[0, 0, 0, 2, 1])?, Some(b"hello".to_vec()));
+        let stored = inner.get(&[0, 0, 0, 2, 1])?.unwrap();
+        assert_eq!(stored[0], ENCRYPTED_MARKER);
+        assert!(!stored.windows(5).any(|window| window == b"hello"));
+        // Zeroed frequencies stay recognisable to the compaction filter
+        assert_eq!(
+            inner.get(&[0, 0, 0, 3, 1])?.unwrap()[0],
+            VARINT_SIGNED_ZERO_ENC
+        );
+
+        let mut iter = engine.prefix_iter(vec![0, 0, 0, 3]);
+        iter.seek(&[0, 0, 0, 2]);
+        assert_eq!(iter.value(), Some(b"hello".as_ref()));
+        iter.next();
+        assert!(!iter.valid());
+        iter.status()?;
+
+        // Without the key the values can't be read
+        let other = EncryptedEngine::open(inner, key(2), vec![], false)?;
+        assert!(other.get(&[0, 0, 0, 2, 1]).is_err());
+        let mut iter = other.prefix_iter(vec![0, 0, 0, 3]);
+        iter.seek(&[0, 0, 0, 2]);
+        assert!(!iter.valid());
+        assert!(iter.status().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_tampering() -> Result<(), StorageError> {
+        let inner = Arc::new(MemEngine::default());
+        let engine = EncryptedEngine::open(inner.clone(), key(1), vec![], false)?;
+        let mut batch = WriteBatch::default();
+        batch.put([0, 0, 0, 2, 1], b"alice");
+        batch.put([0, 0, 0, 2, 2], b"bob");
+        engine.write(batch)?;
+
+        // A flipped bit
+        let mut stored = inner.get(&[0, 0, 0, 2, 1])?.unwrap();
+        let last = stored.len() - 1;
+        stored[last] ^= 1;
+        let mut batch = WriteBatch::default();
+        batch.put([0, 0, 0, 2, 1], &stored);
+        inner.write(batch)?;
+        assert!(engine.get(&[0, 0, 0, 2, 1]).is_err());
+
+        // A value moved over to another key
+        let stored = inner.get(&[0, 0, 0, 2, 2])?.unwrap();
+        let mut batch = WriteBatch::default();
+        batch.put([0, 0, 0, 2, 3], &stored);
+        inner.write(batch)?;
+        assert_eq!(engine.get(&[0, 0, 0, 2, 2])?, Some(b"bob".to_vec()));
+        assert!(engine.get(&[0, 0, 0, 2, 3]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_encrypt_existing() -> Result<(), StorageError> {
+        let inner = Arc::new(MemEngine::default());
+        let mut batch = WriteBatch::default();
+        batch.put([0, 0, 0, 2, 1], b"system");
+        batch.put([0, 0, 4, 2, 1], b"user");
+        batch.put(b"\xff\xff\xff\xfeformat_version", [1]);
+        inner.write(batch)?;
+
+        // Plaintext isn't mistaken for ciphertext
+        assert!(EncryptedEngine::open(inner.clone(), key(1), vec![], false).is_err());
+        assert!(!is_encrypted(inner.as_ref())?);
+
+        let engine = EncryptedEngine::open(inner.clone(), key(1), vec![], true)?;
+        assert!(is_encrypted(inner.as_ref())?);
+        assert_eq!(engine.get(&[0, 0, 0, 2, 1])?, Some(b"system".to_vec()));
+        assert_eq!(engine.get(&[0, 0, 4, 2, 1])?, Some(b"user".to_vec()));
+        assert_ne!(inner.get(&[0, 0, 4, 2, 1])?, Some(b"user".to_vec()));
+        // Metadata stays in the clear
+        assert_eq!(inner.get(b"\xff\xff\xff\xfeformat_version")?, Some(vec![1]));
+
+        // Once encrypted it opens without needing to encrypt anything
+        let engine = EncryptedEngine::open(inner, key(1), vec![], false)?;
+        assert_eq!(engine.get(&[0, 0, 4, 2, 1])?, Some(b"user".to_vec()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_encrypt_existing_resumes() -> Result<(), StorageError> {
+        // An earlier attempt got as far as encrypting the first value
+        let inner = Arc::new(MemEngine::default());
+        let mut batch = WriteBatch::default();
+        batch.put([0, 0, 4, 2, 2], b"plain");
+        inner.write(batch)?;
+        let engine = EncryptedEngine {
+            inner: inner.clone(),
+            current: key(1),
+            previous: vec![],
+            rewrite_lock: RwLock::default(),
+        };
+        let mut batch = WriteBatch::default();
+        batch.put(
+            [0, 0, 4, 2, 1],
+            engine.encrypt(&[0, 0, 4, 2, 1], b"encrypted")?,
+        );
+        inner.write(batch)?;
+
+        let engine = EncryptedEngine::open(inner, key(1), vec![], true)?;
+        assert_eq!(engine.get(&[0, 0, 4, 2, 1])?, Some(b"encrypted".to_vec()));
+        assert_eq!(engine.get(&[0, 0, 4, 2, 2])?, Some(b"plain".to_vec()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_rotation() -> Result<(), StorageError> {
+        let inner = Arc::new(MemEngine::default());
+        let engine = EncryptedEngine::open(inner.clone(), key(1), vec![], false)?;
+        let mut batch = WriteBatch::default();
+        batch.put([0, 0, 0, 2, 1], b"index");
+        batch.put([0, 0, 0, 3, 1], b"log");
+        batch.put([0, 0, 0, 4, 1], b"other table");
+        // Enough rows to take a few batches
+        for idx in 0..(REWRITE_BATCH_SIZE as u32 * 3) {
+            let mut key = vec![0, 0, 0, 2];
+            key.extend_from_slice(&idx.to_be_bytes());
+            batch.put(key, b"row");
+        }
+        engine.write(batch)?;
+
+        let rotated = EncryptedEngine::open(inner.clone(), key(2), vec![key(1)], false)?;
+        assert_eq!(rotated.get(&[0, 0, 0, 2, 1])?, Some(b"index".to_vec()));
+
+        rotated.compact_range(&[0, 0, 0, 2], &[0, 0, 0, 4])?;
+        let retired = EncryptedEngine::open(inner, key(2), vec![], false)?;
+        assert_eq!(retired.get(&[0, 0, 0, 2, 1])?, Some(b"index".to_vec()));
+        assert_eq!(retired.get(&[0, 0, 0, 3, 1])?, Some(b"log".to_vec()));
+        let mut iter = retired.prefix_iter(vec![0, 0, 0, 3]);
+        iter.seek(&[0, 0, 0, 2]);
+        let mut rows = 0;
+        while iter.valid() {
+            rows += 1;
+            iter.next();
+        }
+        iter.status()?;
+        assert_eq!(rows, REWRITE_BATCH_SIZE * 3 + 1);
+        // Outside of the compacted range so still on the old key
+        assert!(retired.get(&[0, 0, 0, 4, 1]).is_err());
+        Ok(())
+    }
+}
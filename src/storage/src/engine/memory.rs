@@ -0,0 +1,176 @@
+use crate::engine::{KeyValueIter, RocksEngine, StorageEngine, WriteBatch, WriteOp};
+use crate::StorageError;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// An in memory storage engine for tests, everything lives in a btree map.
+#[derive(Default)]
+pub struct MemEngine {
+    data: RwLock<BTreeMap<Vec<u8>, Vec<u8>>>,
+}
+
+impl StorageEngine for MemEngine {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.data.read().unwrap().get(key).cloned())
+    }
+
+    fn prefix_iter(&self, upper_bound: Vec<u8>) -> Box<dyn KeyValueIter + '_> {
+        Box::new(MemIter {
+            engine: self,
+            upper_bound,
+            entries: vec![],
+            position: 0,
+        })
+    }
+
+    fn is_empty(&self) -> Result<bool, StorageError> {
+        Ok(self.data.read().unwrap().is_empty())
+    }
+
+    fn first_key_from(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        let data = self.data.read().unwrap();
+        Ok(data
+            .range(key.to_vec()..)
+            .next()
+            .map(|(key, _)| key.clone()))
+    }
+
+    fn write(&self, batch: WriteBatch) -> Result<(), StorageError> {
+        let mut data = self.data.write().unwrap();
+        for op in batch.ops {
+            match op {
+                WriteOp::Put(key, value) => {
+                    data.insert(key, value);
+                }
+                WriteOp::Delete(key) => {
+                    data.remove(&key);
+                }
+                WriteOp::DeleteRange(from, to) => {
+                    let keys: Vec<_> = data.range(from..to).map(|(key, _)| key.clone()).collect();
+                    for key in keys {
+                        data.remove(&key);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn compact_range(&self, _from: &[u8], _to: &[u8]) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    fn checkpoint(&self, path: &str) -> Result<(), StorageError> {
+        let mut batch = WriteBatch::default();
+        for (key, value) in self.data.read().unwrap().iter() {
+            batch.put(key, value);
+        }
+        RocksEngine::open(path)?.write(batch)
+    }
+}
+
+/// Copies out the entries from the seek position up to the upper bound when seeked, so it
+/// doesn't hold the lock while it's being iterated over.
+struct MemIter<'a> {
+    engine: &'a MemEngine,
+    upper_bound: Vec<u8>,
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+    position: usize,
+}
+
+impl KeyValueIter for MemIter<'_> {
+    fn seek(&mut self, key: &[u8]) {
+        let prefix = &key[..key.len().min(4)];
+        self.entries = self
+            .engine
+            .data
+            .read()
+            .unwrap()
+            .range(key.to_vec()..)
+            .take_while(|(entry_key, _)| {
+                entry_key.as_slice() < self.upper_bound.as_slice() && entry_key.starts_with(prefix)
+            })
+            .map(|(entry_key, value)| (entry_key.clone(), value.clone()))
+            .collect();
+        self.position = 0;
+    }
+
+    fn valid(&self) -> bool {
+        self.position < self.entries.len()
+    }
+
+    fn next(&mut self) {
+        self.position += 1;
+    }
+
+    fn key(&self) -> Option<&[u8]> {
+        self.entries
+            .get(self.position)
+            .map(|(key, _value)| key.as_slice())
+    }
+
+    fn value(&self) -> Option<&[u8]> {
+        self.entries
+            .get(self.position)
+            .map(|(_key, value)| value.as_slice())
+    }
+
+    fn status(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(iter: &mut dyn KeyValueIter) -> Vec<Vec<u8>> {
+        let mut keys = vec![];
+        while iter.valid() {
+            keys.push(iter.key().unwrap().to_vec());
+            iter.next();
+        }
+        keys
+    }
+
+    #[test]
+    fn test_prefix_iter() -> Result<(), StorageError> {
+        let engine = MemEngine::default();
+        let mut batch = WriteBatch::default();
+        for key in &[
+            [0, 0, 0, 2, 1],
+            [0, 0, 0, 2, 2],
+            [0, 0, 0, 2, 3],
+            [0, 0, 0, 4, 1],
+        ] {
+            batch.put(key, [1]);
+        }
+        engine.write(batch)?;
+
+        // Stops at the upper bound
+        let mut iter = engine.prefix_iter(vec![0, 0, 0, 2, 3]);
+        iter.seek(&[0, 0, 0, 2, 2]);
+        assert_eq!(entries(iter.as_mut()), vec![vec![0, 0, 0, 2, 2]]);
+
+        // Doesn't cross into the next prefix
+        let mut iter = engine.prefix_iter(vec![0, 0, 0, 5]);
+        iter.seek(&[0, 0, 0, 2]);
+        assert_eq!(
+            entries(iter.as_mut()),
+            vec![
+                vec![0, 0, 0, 2, 1],
+                vec![0, 0, 0, 2, 2],
+                vec![0, 0, 0, 2, 3]
+            ]
+        );
+
+        let mut batch = WriteBatch::default();
+        batch.delete_range([0, 0, 0, 2, 1], [0, 0, 0, 2, 3]);
+        batch.delete([0, 0, 0, 4, 1]);
+        engine.write(batch)?;
+        assert_eq!(engine.get(&[0, 0, 0, 2, 2])?, None);
+        assert_eq!(engine.get(&[0, 0, 0, 2, 3])?, Some(vec![1]));
+        assert_eq!(engine.get(&[0, 0, 0, 4, 1])?, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+use crate::StorageError;
+
+mod encrypted;
+mod memory;
+mod overlay;
+mod rocks;
+pub(crate) use encrypted::is_encrypted;
+pub use encrypted::{EncryptedEngine, EncryptionKey};
+pub use memory::MemEngine;
+pub(crate) use overlay::OverlayEngine;
+pub use rocks::RocksEngine;
+
+/// The key value store underneath the storage layer, tables are laid out over it as described in
+/// storage.rs. Keys are grouped by their 4 byte (table id) prefix, iterators never cross from one
+/// prefix to the next.
+pub trait StorageEngine: Send + Sync {
+    /// Returns the value for the key
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Returns an iterator over the keys that share a prefix with the key it's seeked to and
+    /// are less than the upper bound
+    fn prefix_iter(&self, upper_bound: Vec<u8>) -> Box<dyn KeyValueIter + '_>;
+
+    /// Returns true if nothing at all has been written to the engine
+    fn is_empty(&self) -> Result<bool, StorageError>;
+
+    /// Returns the first key >= key, unlike prefix_iter this crosses prefixes so it can be used
+    /// to find the prefixes that are in use
+    fn first_key_from(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Applies all of the batch's writes or none of them
+    fn write(&self, batch: WriteBatch) -> Result<(), StorageError>;
+
+    /// Compacts the keys from..to, blocking until it's done
+    fn compact_range(&self, from: &[u8], to: &[u8]) -> Result<(), StorageError>;
+
+    /// Writes a consistent copy of everything stored out to path, it can then be opened with
+    /// Storage::new_with_path
+    fn checkpoint(&self, path: &str) -> Result<(), StorageError>;
+
+    /// A reading of how the engine's doing, engines without the stats leave them zeroed
+    fn statistics(&self) -> StorageStatistics {
+        StorageStatistics::default()
+    }
+}
+
+/// Counters and gauges from the storage engine for operators tuning it.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct StorageStatistics {
+    pub block_cache_hits: u64,
+    pub block_cache_misses: u64,
+    // Total time writes have been held up waiting on flushes/compactions
+    pub write_stall_micros: u64,
+    pub compaction_pending: bool,
+    pub pending_compaction_bytes: u64,
+    pub memtable_bytes: u64,
+    pub live_data_bytes: u64,
+}
+
+/// A positioned iterator over keys and values, same semantics as a rocksdb raw iterator.
+pub trait KeyValueIter {
+    /// Positions the iterator at the first key >= key
+    fn seek(&mut self, key: &[u8]);
+
+    fn valid(&self) -> bool;
+
+    fn next(&mut self);
+
+    fn key(&self) -> Option<&[u8]>;
+
+    fn value(&self) -> Option<&[u8]>;
+
+    /// Returns any error the iterator ran into, only meaningful once it's no longer valid
+    fn status(&self) -> Result<(), StorageError>;
+}
+
+pub(crate) enum WriteOp {
+    Put(Vec<u8>, Vec<u8>),
+    Delete(Vec<u8>),
+    DeleteRange(Vec<u8>, Vec<u8>),
+}
+
+/// A batch of writes to be applied atomically by the storage engine.
+#[derive(Default)]
+pub struct WriteBatch {
+    pub(crate) ops: Vec<WriteOp>,
+}
+
+impl WriteBatch {
+    pub fn put<K: AsRef<[u8]>, V: AsRef<[u8]>>(&mut self, key: K, value: V) {
+        self.ops
+            .push(WriteOp::Put(key.as_ref().to_vec(), value.as_ref().to_vec()));
+    }
+
+    pub fn delete<K: AsRef<[u8]>>(&mut self, key: K) {
+        self.ops.push(WriteOp::Delete(key.as_ref().to_vec()));
+    }
+
+    /// Deletes the keys from..to, to is exclusive
+    pub fn delete_range<F: AsRef<[u8]>, T: AsRef<[u8]>>(&mut self, from: F, to: T) {
+        self.ops.push(WriteOp::DeleteRange(
+            from.as_ref().to_vec(),
+            to.as_ref().to_vec(),
+        ));
+    }
+}
@@ -0,0 +1,292 @@
+use crate::engine::{KeyValueIter, StorageEngine, StorageStatistics, WriteBatch, WriteOp};
+use crate::StorageError;
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::sync::{Arc, RwLock};
+
+/// Keeps the writes made to it in memory on top of another engine, reads see the writes as if
+/// they'd been applied to the engine underneath. Nothing's ever written through to it, used to
+/// let a transaction read back its own writes before they're committed.
+pub struct OverlayEngine {
+    base: Arc<dyn StorageEngine>,
+    // The values written over the base engine's, None for deletes
+    writes: RwLock<BTreeMap<Vec<u8>, Option<Vec<u8>>>>,
+}
+
+impl OverlayEngine {
+    pub fn new(base: Arc<dyn StorageEngine>) -> Self {
+        OverlayEngine {
+            base,
+            writes: RwLock::default(),
+        }
+    }
+
+    /// Lists the base engine's keys from..to, prefix by prefix as its iterators don't cross
+    /// from one prefix to the next
+    fn base_keys(&self, from: &[u8], to: &[u8]) -> Result<Vec<Vec<u8>>, StorageError> {
+        let mut keys = vec![];
+        let mut seek = from.to_vec();
+        while seek.as_slice() < to && seek.len() >= 4 {
+            let mut iter = self.base.prefix_iter(to.to_vec());
+            iter.seek(&seek);
+            while let Some(key) = iter.key() {
+                keys.push(key.to_vec());
+                iter.next();
+            }
+            iter.status()?;
+            let prefix = u32::from_be_bytes(seek[..4].try_into().unwrap());
+            match prefix.checked_add(1) {
+                Some(next) => seek = next.to_be_bytes().to_vec(),
+                None => break,
+            }
+        }
+        Ok(keys)
+    }
+}
+
+impl StorageEngine for OverlayEngine {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        match self.writes.read().unwrap().get(key) {
+            Some(value) => Ok(value.clone()),
+            None => self.base.get(key),
+        }
+    }
+
+    fn prefix_iter(&self, upper_bound: Vec<u8>) -> Box<dyn KeyValueIter + '_> {
+        Box::new(OverlayIter {
+            engine: self,
+            base: self.base.prefix_iter(upper_bound.clone()),
+            upper_bound,
+            entries: vec![],
+            position: 0,
+            from_overlay: false,
+        })
+    }
+
+    fn is_empty(&self) -> Result<bool, StorageError> {
+        Ok(self.writes.read().unwrap().is_empty() && self.base.is_empty()?)
+    }
+
+    fn first_key_from(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        let writes = self.writes.read().unwrap();
+        let written = writes
+            .range(key.to_vec()..)
+            .find(|(_key, value)| value.is_some())
+            .map(|(key, _value)| key.clone());
+        // Skip past the base engine's keys that have been deleted over the top of it
+        let mut base = self.base.first_key_from(key)?;
+        while let Some(key) = &base {
+            if writes.get(key) != Some(&None) {
+                break;
+            }
+            let mut next = key.clone();
+            next.push(0);
+            base = self.base.first_key_from(&next)?;
+        }
+        Ok(match (written, base) {
+            (Some(written), Some(base)) => Some(written.min(base)),
+            (written, base) => written.or(base),
+        })
+    }
+
+    fn write(&self, batch: WriteBatch) -> Result<(), StorageError> {
+        let mut writes = self.writes.write().unwrap();
+        for op in batch.ops {
+            match op {
+                WriteOp::Put(key, value) => {
+                    writes.insert(key, Some(value));
+                }
+                WriteOp::Delete(key) => {
+                    writes.insert(key, None);
+                }
+                WriteOp::DeleteRange(from, to) => {
+                    for key in self.base_keys(&from, &to)? {
+                        writes.insert(key, None);
+                    }
+                    for (_key, value) in writes.range_mut(from..to) {
+                        *value = None;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn compact_range(&self, from: &[u8], to: &[u8]) -> Result<(), StorageError> {
+        self.base.compact_range(from, to)
+    }
+
+    fn checkpoint(&self, path: &str) -> Result<(), StorageError> {
+        self.base.checkpoint(path)
+    }
+
+    fn statistics(&self) -> StorageStatistics {
+        self.base.statistics()
+    }
+}
+
+/// Merges the base engine's iterator with the overlay's writes, the overlay's entries are
+/// copied out when seeked like MemEngine's.
+struct OverlayIter<'a> {
+    engine: &'a OverlayEngine,
+    base: Box<dyn KeyValueIter + 'a>,
+    upper_bound: Vec<u8>,
+    entries: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+    position: usize,
+    // Whether the current key is the overlay's rather than the base engine's
+    from_overlay: bool,
+}
+
+impl OverlayIter<'_> {
+    /// Moves onto whichever of the two iterators has the lower key, skipping over the base
+    /// engine's keys the overlay has written over and the overlay's deletes.
+    fn settle(&mut self) {
+        loop {
+            let (overlay_first, same_key) = match (self.entries.get(self.position), self.base.key())
+            {
+                (Some((key, _)), Some(base_key)) => {
+                    (key.as_slice() <= base_key, key.as_slice() == base_key)
+                }
+                (Some(_), None) => (true, false),
+                (None, _) => (false, false),
+            };
+            if !overlay_first {
+                self.from_overlay = false;
+                return;
+            }
+            if same_key {
+                self.base.next();
+            }
+            if self.entries[self.position].1.is_some() {
+                self.from_overlay = true;
+                return;
+            }
+            self.position += 1;
+        }
+    }
+}
+
+impl KeyValueIter for OverlayIter<'_> {
+    fn seek(&mut self, key: &[u8]) {
+        let prefix = &key[..key.len().min(4)];
+        let upper_bound = self.upper_bound.as_slice();
+        self.entries = self
+            .engine
+            .writes
+            .read()
+            .unwrap()
+            .range(key.to_vec()..)
+            .take_while(|(entry_key, _)| {
+                entry_key.as_slice() < upper_bound && entry_key.starts_with(prefix)
+            })
+            .map(|(entry_key, value)| (entry_key.clone(), value.clone()))
+            .collect();
+        self.position = 0;
+        self.base.seek(key);
+        self.settle();
+    }
+
+    fn valid(&self) -> bool {
+        if self.from_overlay {
+            self.position < self.entries.len()
+        } else {
+            self.base.valid()
+        }
+    }
+
+    fn next(&mut self) {
+        if self.from_overlay {
+            self.position += 1;
+        } else {
+            self.base.next();
+        }
+        self.settle();
+    }
+
+    fn key(&self) -> Option<&[u8]> {
+        if self.from_overlay {
+            self.entries
+                .get(self.position)
+                .map(|(key, _value)| key.as_slice())
+        } else {
+            self.base.key()
+        }
+    }
+
+    fn value(&self) -> Option<&[u8]> {
+        if self.from_overlay {
+            self.entries
+                .get(self.position)
+                .and_then(|(_key, value)| value.as_deref())
+        } else {
+            self.base.value()
+        }
+    }
+
+    fn status(&self) -> Result<(), StorageError> {
+        self.base.status()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::MemEngine;
+
+    fn entries(iter: &mut dyn KeyValueIter) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut entries = vec![];
+        while iter.valid() {
+            entries.push((iter.key().unwrap().to_vec(), iter.value().unwrap().to_vec()));
+            iter.next();
+        }
+        entries
+    }
+
+    #[test]
+    fn test_overlay() -> Result<(), StorageError> {
+        let base = Arc::new(MemEngine::default());
+        let mut batch = WriteBatch::default();
+        for key in &[[0, 0, 0, 2, 1], [0, 0, 0, 2, 3], [0, 0, 0, 2, 5]] {
+            batch.put(key, [1]);
+        }
+        base.write(batch)?;
+
+        let overlay = OverlayEngine::new(base.clone());
+        let mut batch = WriteBatch::default();
+        batch.put([0, 0, 0, 2, 2], [2]);
+        batch.put([0, 0, 0, 2, 3], [2]);
+        batch.delete([0, 0, 0, 2, 5]);
+        batch.put([0, 0, 0, 4, 1], [2]);
+        overlay.write(batch)?;
+
+        assert_eq!(overlay.get(&[0, 0, 0, 2, 3])?, Some(vec![2]));
+        assert_eq!(overlay.get(&[0, 0, 0, 2, 5])?, None);
+        assert_eq!(overlay.get(&[0, 0, 0, 2, 1])?, Some(vec![1]));
+        // Deleted keys are skipped over when crossing prefixes
+        assert_eq!(
+            overlay.first_key_from(&[0, 0, 0, 2, 4])?,
+            Some(vec![0, 0, 0, 4, 1])
+        );
+        // Nothing's written through
+        assert_eq!(base.get(&[0, 0, 0, 2, 3])?, Some(vec![1]));
+
+        let mut iter = overlay.prefix_iter(vec![0, 0, 0, 5]);
+        iter.seek(&[0, 0, 0, 2]);
+        assert_eq!(
+            entries(iter.as_mut()),
+            vec![
+                (vec![0, 0, 0, 2, 1], vec![1]),
+                (vec![0, 0, 0, 2, 2], vec![2]),
+                (vec![0, 0, 0, 2, 3], vec![2]),
+            ]
+        );
+
+        let mut batch = WriteBatch::default();
+        batch.delete_range([0, 0, 0, 2, 0], [0, 0, 0, 2, 3]);
+        overlay.write(batch)?;
+        let mut iter = overlay.prefix_iter(vec![0, 0, 0, 5]);
+        iter.seek(&[0, 0, 0, 2]);
+        assert_eq!(entries(iter.as_mut()), vec![(vec![0, 0, 0, 2, 3], vec![2])]);
+        Ok(())
+    }
+}
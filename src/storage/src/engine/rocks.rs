@@ -0,0 +1,515 @@
+use crate::engine::{KeyValueIter, StorageEngine, StorageStatistics, WriteBatch, WriteOp};
+use crate::options::{CompactionStyle, StorageOptions};
+use crate::{StorageError, SYSTEM_TABLE_ID_LIMIT};
+use data::encoding_core::{SortableEncoding, VARINT_SIGNED_ZERO_ENC};
+use data::SortOrder;
+use rocksdb::checkpoint::Checkpoint;
+use rocksdb::compaction_filter::Decision;
+use rocksdb::{
+    BlockBasedOptions, ColumnFamily, ColumnFamilyDescriptor, DBCompactionStyle, DBCompressionType,
+    DBRawIterator, MergeOperands, Options, ReadOptions, SliceTransform, WriteOptions, DB,
+    DEFAULT_COLUMN_FAMILY_NAME,
+};
+
+/// The column family the system tables live in
+const SYSTEM_COLUMN_FAMILY: &str = "system";
+/// The system tables are small and read on every query as they're planned, they get their own
+/// block cache so they don't get evicted by scans over user tables.
+const SYSTEM_BLOCK_CACHE_SIZE: usize = 8 * 1024 * 1024;
+const SYSTEM_WRITE_BUFFER_SIZE: usize = 4 * 1024 * 1024;
+
+/// The rocksdb storage engine.
+/// Tables with ids below SYSTEM_TABLE_ID_LIMIT are kept in their own column family so that
+/// compactions of big user tables don't slow down catalog lookups.
+pub struct RocksEngine {
+    db: DB,
+    // Kept for the statistics, which live on the options
+    options: Options,
+}
+
+impl RocksEngine {
+    /// Opens (or creates) the rocks db with data stored in the given path
+    pub fn open(path: &str) -> Result<Self, StorageError> {
+        RocksEngine::open_with_options(path, &StorageOptions::default())
+    }
+
+    /// Opens (or creates) the rocks db tuned with the given options
+    pub fn open_with_options(
+        path: &str,
+        storage_options: &StorageOptions,
+    ) -> Result<Self, StorageError> {
+        let options = RocksEngine::options(storage_options);
+        // Databases from before the system column family existed have their system tables
+        // sitting in the default column family, they get moved over once it's been created.
+        let migrate_system_tables = DB::list_cf(&options, path)
+            .map(|column_families| !column_families.iter().any(|cf| cf == SYSTEM_COLUMN_FAMILY))
+            .unwrap_or(false);
+        let db = DB::open_cf_descriptors(
+            &options,
+            path,
+            vec![
+                ColumnFamilyDescriptor::new(DEFAULT_COLUMN_FAMILY_NAME, options.clone()),
+                ColumnFamilyDescriptor::new(SYSTEM_COLUMN_FAMILY, RocksEngine::system_options()),
+            ],
+        )?;
+        let engine = RocksEngine { db, options };
+        if migrate_system_tables {
+            engine.migrate_system_tables()?;
+        }
+        Ok(engine)
+    }
+
+    /// Options common to both column families
+    fn base_options() -> Options {
+        let mut options = Options::default();
+        // These options are non-negotiable
+        options.set_prefix_extractor(SliceTransform::create_fixed_prefix(
+            std::mem::size_of::<u32>(),
+        ));
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+        options.set_merge_operator("frequency_merge", frequency_merge, Some(frequency_merge));
+        options.set_compaction_filter("compaction_filter", compaction_filter);
+        options.set_compression_type(DBCompressionType::Lz4);
+        options
+    }
+
+    /// Return the our rocks db options, these are also the options of the default column family
+    /// where the user tables live
+    fn options(storage_options: &StorageOptions) -> Options {
+        let mut options = RocksEngine::base_options();
+        let mut block_options = BlockBasedOptions::default();
+
+        // These options are "tunable"
+        block_options.set_bloom_filter(10, false);
+        block_options.set_lru_cache(storage_options.block_cache_size);
+        options.set_block_based_table_factory(&block_options);
+        options.increase_parallelism(4);
+        options.set_write_buffer_size(storage_options.write_buffer_size);
+        options.set_max_write_buffer_number(storage_options.max_write_buffer_number);
+        options.set_max_background_jobs(storage_options.max_background_jobs);
+        options.set_compaction_style(match storage_options.compaction_style {
+            CompactionStyle::Level => DBCompactionStyle::Level,
+            CompactionStyle::Universal => DBCompactionStyle::Universal,
+            CompactionStyle::Fifo => DBCompactionStyle::Fifo,
+        });
+        options.enable_statistics();
+        options
+    }
+
+    /// The options of the system column family, tuned for a small amount of data that's mostly
+    /// point looked up.
+    fn system_options() -> Options {
+        let mut options = RocksEngine::base_options();
+        let mut block_options = BlockBasedOptions::default();
+        block_options.set_bloom_filter(10, false);
+        block_options.set_lru_cache(SYSTEM_BLOCK_CACHE_SIZE);
+        options.set_block_based_table_factory(&block_options);
+        options.set_write_buffer_size(SYSTEM_WRITE_BUFFER_SIZE);
+        options.set_compaction_style(DBCompactionStyle::Level);
+        options
+    }
+
+    /// Moves the system tables out of the default column family into the system one
+    fn migrate_system_tables(&self) -> Result<(), StorageError> {
+        let default = self.db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+        let system = self.db.cf_handle(SYSTEM_COLUMN_FAMILY).unwrap();
+        let upper_bound = SYSTEM_TABLE_ID_LIMIT.to_be_bytes();
+        let mut iter_options = ReadOptions::default();
+        // We're crossing prefixes so we need the total order
+        iter_options.set_total_order_seek(true);
+        iter_options.set_iterate_upper_bound(upper_bound.to_vec());
+        let mut iter = self.db.raw_iterator_cf_opt(default, iter_options);
+        iter.seek([0_u8; 4]);
+
+        let mut write_batch = rocksdb::WriteBatch::default();
+        while iter.valid() {
+            write_batch.put_cf(system, iter.key().unwrap(), iter.value().unwrap());
+            iter.next();
+        }
+        iter.status()?;
+        write_batch.delete_range_cf(default, [0_u8; 4], upper_bound);
+        let mut write_options = WriteOptions::new();
+        write_options.set_sync(true);
+        self.db.write_opt(write_batch, &write_options)?;
+        Ok(())
+    }
+
+    /// The column family the key belongs in
+    fn column_family(&self, key: &[u8]) -> &ColumnFamily {
+        let name = if is_system_key(key) {
+            SYSTEM_COLUMN_FAMILY
+        } else {
+            DEFAULT_COLUMN_FAMILY_NAME
+        };
+        self.db.cf_handle(name).unwrap()
+    }
+
+    /// Sums the property across the column families
+    fn int_property(&self, name: &str) -> u64 {
+        [DEFAULT_COLUMN_FAMILY_NAME, SYSTEM_COLUMN_FAMILY]
+            .iter()
+            .filter_map(|cf| {
+                self.db
+                    .property_int_value_cf(self.db.cf_handle(cf)?, name)
+                    .ok()
+                    .flatten()
+            })
+            .sum()
+    }
+}
+
+/// True for keys belonging to the system tables, keys shorter than a table id are padded out
+/// with zeros.
+fn is_system_key(key: &[u8]) -> bool {
+    let mut prefix = [0_u8; 4];
+    let len = key.len().min(4);
+    prefix[..len].copy_from_slice(&key[..len]);
+    u32::from_be_bytes(prefix) < SYSTEM_TABLE_ID_LIMIT
+}
+
+impl StorageEngine for RocksEngine {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self
+            .db
+            .get_pinned_cf(self.column_family(key), key)?
+            .map(|value| value.as_ref().to_vec()))
+    }
+
+    fn prefix_iter(&self, upper_bound: Vec<u8>) -> Box<dyn KeyValueIter + '_> {
+        let mut iter_options = ReadOptions::default();
+        iter_options.set_prefix_same_as_start(true);
+        // Iterators never leave the prefix they're seeked to, so the upper bound's prefix tells
+        // us which column family to iterate over
+        let column_family = self.column_family(&upper_bound);
+        iter_options.set_iterate_upper_bound(upper_bound);
+        Box::new(RocksIter {
+            iter: self.db.raw_iterator_cf_opt(column_family, iter_options),
+        })
+    }
+
+    fn is_empty(&self) -> Result<bool, StorageError> {
+        for name in &[DEFAULT_COLUMN_FAMILY_NAME, SYSTEM_COLUMN_FAMILY] {
+            let mut iter_options = ReadOptions::default();
+            // We're crossing prefixes so we need the total order
+            iter_options.set_total_order_seek(true);
+            let mut iter = self
+                .db
+                .raw_iterator_cf_opt(self.db.cf_handle(name).unwrap(), iter_options);
+            iter.seek_to_first();
+            if iter.valid() {
+                return Ok(false);
+            }
+            iter.status()?;
+        }
+        Ok(true)
+    }
+
+    fn first_key_from(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        // The system tables sort before everything in the default column family
+        for name in &[SYSTEM_COLUMN_FAMILY, DEFAULT_COLUMN_FAMILY_NAME] {
+            let mut iter_options = ReadOptions::default();
+            // We're crossing prefixes so we need the total order
+            iter_options.set_total_order_seek(true);
+            let mut iter = self
+                .db
+                .raw_iterator_cf_opt(self.db.cf_handle(name).unwrap(), iter_options);
+            iter.seek(key);
+            if let Some(key) = iter.key() {
+                return Ok(Some(key.to_vec()));
+            }
+            iter.status()?;
+        }
+        Ok(None)
+    }
+
+    fn write(&self, batch: WriteBatch) -> Result<(), StorageError> {
+        let mut write_batch = rocksdb::WriteBatch::default();
+        for op in batch.ops {
+            match op {
+                WriteOp::Put(key, value) => {
+                    write_batch.put_cf(self.column_family(&key), key, value)
+                }
+                WriteOp::Delete(key) => write_batch.delete_cf(self.column_family(&key), key),
+                // Ranges are only ever deleted within a table
+                WriteOp::DeleteRange(from, to) => {
+                    write_batch.delete_range_cf(self.column_family(&from), from, to)
+                }
+            }
+        }
+        let mut write_options = WriteOptions::new();
+        write_options.set_sync(true);
+        write_options.set_low_pri(true);
+        self.db.write_opt(write_batch, &write_options)?;
+        Ok(())
+    }
+
+    fn compact_range(&self, from: &[u8], to: &[u8]) -> Result<(), StorageError> {
+        self.db
+            .compact_range_cf(self.column_family(from), Some(from), Some(to));
+        Ok(())
+    }
+
+    fn checkpoint(&self, path: &str) -> Result<(), StorageError> {
+        Checkpoint::new(&self.db)?.create_checkpoint(path)?;
+        Ok(())
+    }
+
+    fn statistics(&self) -> StorageStatistics {
+        let tickers = self.options.get_statistics().unwrap_or_default();
+        StorageStatistics {
+            block_cache_hits: ticker(&tickers, "rocksdb.block.cache.hit"),
+            block_cache_misses: ticker(&tickers, "rocksdb.block.cache.miss"),
+            write_stall_micros: ticker(&tickers, "rocksdb.stall.micros"),
+            compaction_pending: self.int_property("rocksdb.compaction-pending") != 0,
+            pending_compaction_bytes: self
+                .int_property("rocksdb.estimate-pending-compaction-bytes"),
+            memtable_bytes: self.int_property("rocksdb.cur-size-all-mem-tables"),
+            live_data_bytes: self.int_property("rocksdb.estimate-live-data-size"),
+        }
+    }
+}
+
+/// Pulls a ticker's count out of rocksdb's statistics dump, where each ticker is on its own line
+/// as "<name> COUNT : <count>".
+fn ticker(statistics: &str, name: &str) -> u64 {
+    statistics
+        .lines()
+        .find_map(|line| {
+            let mut parts = line.split_whitespace();
+            if parts.next() == Some(name) && parts.next() == Some("COUNT") {
+                parts.nth(1).and_then(|count| count.parse().ok())
+            } else {
+                None
+            }
+        })
+        .unwrap_or(0)
+}
+
+struct RocksIter<'a> {
+    iter: DBRawIterator<'a>,
+}
+
+impl KeyValueIter for RocksIter<'_> {
+    fn seek(&mut self, key: &[u8]) {
+        self.iter.seek(key);
+    }
+
+    fn valid(&self) -> bool {
+        self.iter.valid()
+    }
+
+    fn next(&mut self) {
+        self.iter.next();
+    }
+
+    fn key(&self) -> Option<&[u8]> {
+        self.iter.key()
+    }
+
+    fn value(&self) -> Option<&[u8]> {
+        self.iter.value()
+    }
+
+    fn status(&self) -> Result<(), StorageError> {
+        Ok(self.iter.status()?)
+    }
+}
+
+/// The rocksdb merge filter, merges frequencies but only in the log sections.
+fn frequency_merge(
+    key: &[u8],
+    existing_value: Option<&[u8]>,
+    operand_list: &mut MergeOperands,
+) -> Option<Vec<u8>> {
+    // Indirection to allow testing since MergeOperands can't be constructed by us..
+    frequency_merge_impl(key, existing_value, operand_list)
+}
+
+fn frequency_merge_impl<'a, I: Iterator<Item = &'a [u8]> + 'a>(
+    key: &[u8],
+    existing_value: Option<&[u8]>,
+    operand_list: I,
+) -> Option<Vec<u8>> {
+    // fourth byte is even for index, odd for logs
+    if key[3] & 1 != 1 {
+        panic!("Merge called for index section")
+    }
+
+    let mut count = 0_i64;
+    let mut temp = 0_i64;
+
+    if let Some(bytes) = existing_value {
+        count.read_sortable_bytes(SortOrder::Asc, bytes);
+    }
+
+    for operand in operand_list {
+        temp.read_sortable_bytes(SortOrder::Asc, operand);
+        count += temp;
+    }
+    let mut ret = Vec::with_capacity(4);
+    count.write_sortable_bytes(SortOrder::Asc, &mut ret);
+
+    Some(ret)
+}
+
+/// Used in conjunction with the frequency_merge filter to remove 0'd out freq's from the log
+/// section during a compaction.  We'll also use the filter during reads to prevent reading in these
+/// records then for consistency.
+// TODO We might not want to use this as we may end up with 0's in our index section but no log
+// with which to vacuum them with.
+fn compaction_filter(_level: u32, key: &[u8], value: &[u8]) -> Decision {
+    // fourth byte is even for index, odd for logs
+    if key[3] & 1 == 1 {
+        // We only need to check the first byte for 0
+        if value[0] == VARINT_SIGNED_ZERO_ENC {
+            return Decision::Remove;
+        }
+    }
+
+    Decision::Keep
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decision doesn't impl eq or debug etc, this is just to make the compactionfilter output
+    /// testable
+    trait DecisionExtra {
+        fn is_keep(&self) -> bool;
+    }
+
+    impl DecisionExtra for Decision {
+        fn is_keep(&self) -> bool {
+            if let Decision::Keep = self {
+                true
+            } else {
+                false
+            }
+        }
+    }
+
+    #[test]
+    fn test_is_system_key() {
+        assert!(is_system_key(&[0, 0, 0, 4, 1, 2]));
+        assert!(is_system_key(&[0, 0, 3, 255]));
+        assert!(is_system_key(&[0, 0, 3]));
+        assert!(!is_system_key(&[0, 0, 4, 0]));
+        assert!(!is_system_key(&[12, 34, 56, 78, 1]));
+    }
+
+    #[test]
+    fn test_migrate_system_tables() -> Result<(), StorageError> {
+        let path = std::env::temp_dir().join(format!("incresql-migrate-cf-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        let system_key = [0_u8, 0, 0, 4, 1];
+        let user_key = [0_u8, 0, 4, 0, 1];
+        {
+            // A database from before the system column family
+            let db = DB::open(&RocksEngine::options(&StorageOptions::default()), path)?;
+            db.put(system_key, [1])?;
+            db.put(user_key, [2])?;
+        }
+
+        let engine = RocksEngine::open(path)?;
+        assert_eq!(engine.get(&system_key)?, Some(vec![1]));
+        assert_eq!(engine.get(&user_key)?, Some(vec![2]));
+        let default = engine.db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+        let system = engine.db.cf_handle(SYSTEM_COLUMN_FAMILY).unwrap();
+        assert!(engine.db.get_pinned_cf(default, system_key)?.is_none());
+        assert!(engine.db.get_pinned_cf(system, system_key)?.is_some());
+        assert!(engine.db.get_pinned_cf(system, user_key)?.is_none());
+
+        let mut iter = engine.prefix_iter(vec![0, 0, 0, 5]);
+        iter.seek(&[0, 0, 0, 4]);
+        assert_eq!(iter.key(), Some(system_key.as_ref()));
+        drop(iter);
+        drop(engine);
+        let _ = std::fs::remove_dir_all(path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ticker() {
+        let statistics = "rocksdb.block.cache.miss COUNT : 12\nrocksdb.block.cache.hit COUNT : 34\nrocksdb.db.get.micros P50 : 1.0 P95 : 2.0\n";
+        assert_eq!(ticker(statistics, "rocksdb.block.cache.hit"), 34);
+        assert_eq!(ticker(statistics, "rocksdb.block.cache.miss"), 12);
+        assert_eq!(ticker(statistics, "rocksdb.stall.micros"), 0);
+    }
+
+    #[test]
+    fn test_frequency_merge_impl_from_put() {
+        // put=2,   1, 5, -4 are our deltas
+        let prefix = [0_u8, 2, 3, 9];
+
+        let mut put_buf = vec![];
+        2_i64.write_sortable_bytes(SortOrder::Asc, &mut put_buf);
+
+        let delta_bufs: Vec<_> = [1_i64, 5, -4]
+            .as_ref()
+            .iter()
+            .map(|i| {
+                let mut buf = vec![];
+                i.write_sortable_bytes(SortOrder::Asc, &mut buf);
+                buf
+            })
+            .collect();
+        let operands = delta_bufs.iter().map(|buf| buf.as_ref());
+
+        let mut expected_buf = vec![];
+        4_i64.write_sortable_bytes(SortOrder::Asc, &mut expected_buf);
+
+        assert_eq!(
+            frequency_merge_impl(&prefix, Some(&put_buf), operands),
+            Some(expected_buf)
+        );
+    }
+
+    #[test]
+    fn test_frequency_merge_impl_just_diffs() {
+        // 1, 5, -4 are our deltas
+        let prefix = [0_u8, 2, 3, 9];
+
+        let delta_bufs: Vec<_> = [1_i64, 5, -4]
+            .as_ref()
+            .iter()
+            .map(|i| {
+                let mut buf = vec![];
+                i.write_sortable_bytes(SortOrder::Asc, &mut buf);
+                buf
+            })
+            .collect();
+        let operands = delta_bufs.iter().map(|buf| buf.as_ref());
+
+        let mut expected_buf = vec![];
+        2_i64.write_sortable_bytes(SortOrder::Asc, &mut expected_buf);
+
+        assert_eq!(
+            frequency_merge_impl(&prefix, None, operands),
+            Some(expected_buf)
+        );
+    }
+
+    #[test]
+    fn test_compaction_filter() {
+        // PIT section - keep everything, don't even look!
+        assert!(compaction_filter(0, &[0, 0, 0, 0, 0, 2, 3, 4], &[]).is_keep());
+        assert!(
+            compaction_filter(0, &[0, 0, 0, 0, 0, 2, 3, 4], &[VARINT_SIGNED_ZERO_ENC]).is_keep()
+        );
+        // Log section - drop zeros
+        assert!(!compaction_filter(
+            0,
+            &[0, 0, 0, 1, 1, 2, 3, 4],
+            &[VARINT_SIGNED_ZERO_ENC, 1, 2, 3]
+        )
+        .is_keep());
+        assert!(compaction_filter(
+            0,
+            &[0, 0, 0, 1, 1, 2, 3, 4],
+            &[VARINT_SIGNED_ZERO_ENC + 1, 1, 2, 3]
+        )
+        .is_keep());
+    }
+}
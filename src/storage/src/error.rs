@@ -2,15 +2,26 @@ use rocksdb::Error;
 use std::fmt::{Display, Formatter};
 
 /// An error from the storage layer
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Clone)]
 pub enum StorageError {
     RocksDbError(String),
+    EncryptionError(String),
+    // A value or database written in a newer format than we know how to read
+    UnsupportedFormat(u8),
+    // A row written to a table with a primary key clashing with a different row's key
+    DuplicateKey,
 }
 
 impl Display for StorageError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             StorageError::RocksDbError(err) => f.write_str(err),
+            StorageError::EncryptionError(err) => f.write_str(err),
+            StorageError::UnsupportedFormat(version) => f.write_fmt(format_args!(
+                "Storage format version {} is newer than this version of incresql supports",
+                version
+            )),
+            StorageError::DuplicateKey => f.write_str("Duplicate entry for key 'PRIMARY'"),
         }
     }
 }
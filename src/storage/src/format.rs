@@ -0,0 +1,107 @@
+use crate::engine::{StorageEngine, WriteBatch};
+use crate::StorageError;
+
+/// The version of the key/value layout described in storage.rs that we write, bumped whenever
+/// it changes. Older versions have to stay readable so existing databases can be opened.
+///  0 - Databases from before the format was recorded, values aren't tagged
+///  1 - Each table value starts with the version it was written in
+pub const FORMAT_VERSION: u8 = 1;
+
+/// Where the database's format version is kept, the prefix is past any table id the catalog
+/// will hand out and even so it's never treated as a log section.
+const FORMAT_VERSION_KEY: &[u8] = b"\xff\xff\xff\xfeformat_version";
+
+/// Reads the format the engine's data was written in. New databases are stamped with the
+/// current format while databases with data but no format recorded predate the versioning.
+pub(crate) fn read_format_version(engine: &dyn StorageEngine) -> Result<u8, StorageError> {
+    match engine.get(FORMAT_VERSION_KEY)? {
+        Some(value) => match value.first() {
+            Some(version) if *version <= FORMAT_VERSION => Ok(*version),
+            Some(version) => Err(StorageError::UnsupportedFormat(*version)),
+            None => Err(StorageError::UnsupportedFormat(0)),
+        },
+        None if engine.is_empty()? => {
+            let mut batch = WriteBatch::default();
+            batch.put(FORMAT_VERSION_KEY, [FORMAT_VERSION]);
+            engine.write(batch)?;
+            Ok(FORMAT_VERSION)
+        }
+        None => Ok(0),
+    }
+}
+
+/// Starts a new value for a database in the given format, tagging it with the version its
+/// written in when the format has tags.
+pub(crate) fn start_value(format: u8, value_buf: &mut Vec<u8>) {
+    value_buf.clear();
+    if format >= 1 {
+        value_buf.push(format);
+    }
+}
+
+/// Splits a value into the version it was written in and the rest of the value. Values
+/// written in a newer version than we understand are an error rather than being misread.
+pub(crate) fn decode_value(format: u8, value: &[u8]) -> Result<(u8, &[u8]), StorageError> {
+    if format == 0 {
+        return Ok((0, value));
+    }
+    match value.split_first() {
+        Some((version, rest)) if *version >= 1 && *version <= FORMAT_VERSION => {
+            Ok((*version, rest))
+        }
+        Some((version, _rest)) => Err(StorageError::UnsupportedFormat(*version)),
+        None => Err(StorageError::UnsupportedFormat(0)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemEngine;
+
+    #[test]
+    fn test_read_format_version() -> Result<(), StorageError> {
+        // New databases get the current version
+        let engine = MemEngine::default();
+        assert_eq!(read_format_version(&engine)?, FORMAT_VERSION);
+        assert_eq!(read_format_version(&engine)?, FORMAT_VERSION);
+
+        // Ones with data but no version are from before versioning
+        let engine = MemEngine::default();
+        let mut batch = WriteBatch::default();
+        batch.put([0, 0, 0, 2, 1], [2]);
+        engine.write(batch)?;
+        assert_eq!(read_format_version(&engine)?, 0);
+
+        let engine = MemEngine::default();
+        let mut batch = WriteBatch::default();
+        batch.put(FORMAT_VERSION_KEY, [FORMAT_VERSION + 1]);
+        engine.write(batch)?;
+        assert_eq!(
+            read_format_version(&engine),
+            Err(StorageError::UnsupportedFormat(FORMAT_VERSION + 1))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_values() -> Result<(), StorageError> {
+        let mut value = vec![9, 9];
+        start_value(FORMAT_VERSION, &mut value);
+        value.extend_from_slice(&[1, 2, 3]);
+        assert_eq!(
+            decode_value(FORMAT_VERSION, &value)?,
+            (FORMAT_VERSION, [1, 2, 3].as_ref())
+        );
+
+        start_value(0, &mut value);
+        value.extend_from_slice(&[1, 2, 3]);
+        assert_eq!(decode_value(0, &value)?, (0, [1, 2, 3].as_ref()));
+
+        assert_eq!(
+            decode_value(FORMAT_VERSION, &[FORMAT_VERSION + 1, 1]),
+            Err(StorageError::UnsupportedFormat(FORMAT_VERSION + 1))
+        );
+        Ok(())
+    }
+}
@@ -1,7 +1,22 @@
+mod engine;
 mod error;
+mod format;
+mod modifications;
+mod options;
 mod storage;
 mod table;
+mod transaction;
+mod vacuum;
+mod write_tracker;
 
-pub use crate::storage::Storage;
-pub use crate::table::Table;
+pub use crate::engine::{
+    EncryptedEngine, EncryptionKey, KeyValueIter, MemEngine, RocksEngine, StorageEngine,
+    StorageStatistics, WriteBatch,
+};
+pub use crate::format::FORMAT_VERSION;
+pub use crate::options::{CompactionStyle, StorageOptions};
+pub use crate::storage::{Storage, SYSTEM_TABLE_ID_LIMIT};
+pub use crate::table::{Table, Writer};
+pub use crate::transaction::Transaction;
+pub use crate::vacuum::{Vacuum, DEFAULT_VACUUM_RETENTION, VACUUM_INTERVAL};
 pub use error::StorageError;
@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Counts the rows written to each table since the count was last reset, kept in memory only
+/// so the counts start again from zero on restart. Writes made with system_write_tuple aren't
+/// counted.
+#[derive(Debug, Default)]
+pub(crate) struct Modifications {
+    counts: Mutex<HashMap<u32, u64>>,
+}
+
+impl Modifications {
+    /// Adds the rows of a committed write batch, keyed by table id
+    pub(crate) fn add(&self, written: &HashMap<u32, u64>) {
+        if written.is_empty() {
+            return;
+        }
+        let mut counts = self.counts.lock().unwrap();
+        for (table_id, rows) in written {
+            *counts.entry(*table_id).or_insert(0) += rows;
+        }
+    }
+
+    pub(crate) fn get(&self, table_id: u32) -> u64 {
+        self.counts
+            .lock()
+            .unwrap()
+            .get(&table_id)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub(crate) fn reset(&self, table_id: u32) {
+        self.counts.lock().unwrap().remove(&table_id);
+    }
+}
@@ -0,0 +1,107 @@
+use crate::engine::EncryptionKey;
+use std::str::FromStr;
+
+/// How rocksdb is tuned and whether it's encrypted, the defaults suit a mixed workload while
+/// the ingest profile trades memory and read amplification for write throughput.
+#[derive(Clone)]
+pub struct StorageOptions {
+    pub block_cache_size: usize,
+    // Size of each memtable
+    pub write_buffer_size: usize,
+    pub max_write_buffer_number: i32,
+    pub max_background_jobs: i32,
+    pub compaction_style: CompactionStyle,
+    pub encryption_key: Option<EncryptionKey>,
+    // Keys being rotated out, values written with them can still be read
+    pub previous_encryption_keys: Vec<EncryptionKey>,
+    // Whether a database written without encryption is encrypted when it's opened with a key,
+    // otherwise it fails to open
+    pub encrypt_existing_data: bool,
+}
+
+const MB: usize = 1024 * 1024;
+
+impl Default for StorageOptions {
+    fn default() -> Self {
+        StorageOptions {
+            block_cache_size: 8 * MB,
+            write_buffer_size: 64 * MB,
+            max_write_buffer_number: 2,
+            max_background_jobs: 2,
+            compaction_style: CompactionStyle::Level,
+            encryption_key: None,
+            previous_encryption_keys: vec![],
+            encrypt_existing_data: false,
+        }
+    }
+}
+
+impl StorageOptions {
+    /// Returns the options for the named profile, "default" or "ingest"
+    pub fn profile(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "default" => Some(StorageOptions::default()),
+            "ingest" => Some(StorageOptions {
+                block_cache_size: 64 * MB,
+                write_buffer_size: 256 * MB,
+                max_write_buffer_number: 4,
+                max_background_jobs: 8,
+                compaction_style: CompactionStyle::Universal,
+                ..StorageOptions::default()
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CompactionStyle {
+    Level,
+    Universal,
+    Fifo,
+}
+
+impl FromStr for CompactionStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "level" => Ok(CompactionStyle::Level),
+            "universal" => Ok(CompactionStyle::Universal),
+            "fifo" => Ok(CompactionStyle::Fifo),
+            _ => Err(format!(
+                "Unknown compaction style {}, expected level, universal or fifo",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile() {
+        assert_eq!(
+            StorageOptions::profile("INGEST").unwrap().compaction_style,
+            CompactionStyle::Universal
+        );
+        assert_eq!(
+            StorageOptions::profile("default")
+                .unwrap()
+                .write_buffer_size,
+            64 * MB
+        );
+        assert!(StorageOptions::profile("fast").is_none());
+    }
+
+    #[test]
+    fn test_compaction_style_from_str() {
+        assert_eq!(
+            CompactionStyle::from_str("Universal"),
+            Ok(CompactionStyle::Universal)
+        );
+        assert!(CompactionStyle::from_str("tiered").is_err());
+    }
+}
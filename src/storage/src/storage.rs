@@ -1,20 +1,33 @@
+use crate::engine::{
+    is_encrypted, EncryptedEngine, MemEngine, RocksEngine, StorageEngine, StorageStatistics,
+};
 use crate::error::StorageError;
+use crate::format::read_format_version;
+use crate::modifications::Modifications;
+use crate::options::StorageOptions;
 use crate::table::Table;
-use data::encoding_core::{SortableEncoding, VARINT_SIGNED_ZERO_ENC};
-use data::SortOrder;
-use rocksdb::compaction_filter::Decision;
-use rocksdb::{
-    BlockBasedOptions, DBCompressionType, Env, MergeOperands, Options, SliceTransform, DB,
-};
+use crate::write_tracker::WriteTracker;
+use data::{Clock, LogicalTimestamp, SortOrder};
 use std::fmt::{Debug, Formatter};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Table ids below this are reserved for the catalog's system tables, engines may store them
+/// apart from the user tables.
+pub const SYSTEM_TABLE_ID_LIMIT: u32 = 1024;
 
 /// The storage subsystem, used to manage low-level storage of tables and atomicity
-/// via rockdb's write batch operations.
+/// via the storage engine's write batches.
 /// Adding/Removing tables etc should happen via the catalog, at this abstraction level a table has
 /// no name, its just referenced via a u32
 pub struct Storage {
-    db: Arc<DB>,
+    engine: Arc<dyn StorageEngine>,
+    // The format version the engine's data is laid out in
+    format: u8,
+    writes: Arc<WriteTracker>,
+    modifications: Arc<Modifications>,
+    // The table rows that fail their checksums are moved into
+    quarantine: Arc<RwLock<Option<u32>>>,
 }
 
 impl Debug for Storage {
@@ -61,211 +74,240 @@ impl Debug for Storage {
 //
 // Prefixes will be written as big endian, meaning that the fourth byte in the key should signal
 // if we're in the log or indexes sections.
+//
+// Values are prefixed with the format version they were written in (see format.rs), the
+// database's format version is kept under its own key outside of the tables. Keys can't carry
+// their own version as they have to sort, so a change to the key layout needs a new database
+// format version and its tables rewritten.
+//
+// The <tuple-pk> and <tuple-rest> are both written as their datum count followed by the datums,
+// see data::encoding_tuple, so they can be read back with read_tuple without knowing the
+// table's columns.
+//
+// Pruning...
+// Scans are only ever pruned by their ranges over the <tuple-pk>, rocksdb already skips the ssts
+// that fall outside of them. We don't keep zone maps (min/max of the columns per sst) as the
+// rocksdb bindings, and rocksdb's c api under them, don't expose table properties collectors to
+// build them with.
 
 impl Storage {
     /// Crates a new storage engine(rocks db) with data stored in the given path
     pub fn new_with_path(path: &str) -> Result<Self, StorageError> {
-        let options = Storage::options();
-        let db = Arc::from(DB::open(&options, path)?);
+        Storage::new_with_engine(Arc::new(RocksEngine::open(path)?))
+    }
 
-        Ok(Storage { db })
+    /// Like new_with_path but with rocksdb tuned by the options, values are encrypted when
+    /// there's an encryption key.
+    pub fn new_with_options(path: &str, options: &StorageOptions) -> Result<Self, StorageError> {
+        let engine = RocksEngine::open_with_options(path, options)?;
+        match &options.encryption_key {
+            Some(key) => Storage::new_with_engine(Arc::new(EncryptedEngine::open(
+                Arc::new(engine),
+                key.clone(),
+                options.previous_encryption_keys.clone(),
+                options.encrypt_existing_data,
+            )?)),
+            None if is_encrypted(&engine)? => Err(StorageError::EncryptionError(
+                "The database is encrypted, it can't be opened without its encryption key"
+                    .to_string(),
+            )),
+            None => Storage::new_with_engine(Arc::new(engine)),
+        }
     }
 
     /// Creates a new in memory backed storage.
     /// to be used for testing etc
     pub fn new_in_mem() -> Result<Self, StorageError> {
-        let mut options = Storage::options();
-        let env = Env::mem_env()?;
-        options.set_env(&env);
-        // TODO memory leak here, looking at the c api it looks like we should own the env
-        // and lend it to the db for it's whole lifetime.
-        std::mem::forget(env);
-        let db = Arc::from(DB::open(&options, "")?);
-        Ok(Storage { db })
+        Storage::new_with_engine(Arc::new(MemEngine::default()))
+    }
+
+    /// Creates a new in memory backed storage whose writes are timestamped by the clock, tests
+    /// can pass a virtual clock to control when rows become visible.
+    pub fn new_in_mem_with_clock(clock: Clock) -> Result<Self, StorageError> {
+        Storage::new_with_engine_and_clock(Arc::new(MemEngine::default()), clock)
+    }
+
+    /// Creates a new storage over the given storage engine
+    pub fn new_with_engine(engine: Arc<dyn StorageEngine>) -> Result<Self, StorageError> {
+        Storage::new_with_engine_and_clock(engine, Clock::system())
+    }
+
+    /// Creates a new storage over the given storage engine, taking the time from the clock.
+    /// Fails if the engine's data was written in a newer format than we can read.
+    pub fn new_with_engine_and_clock(
+        engine: Arc<dyn StorageEngine>,
+        clock: Clock,
+    ) -> Result<Self, StorageError> {
+        let format = read_format_version(engine.as_ref())?;
+        Ok(Storage {
+            engine,
+            format,
+            writes: Arc::new(WriteTracker::new(clock)),
+            modifications: Arc::default(),
+            quarantine: Arc::default(),
+        })
     }
 
     /// Returns the table for the given id and primary key info.
     pub fn table(&self, id: u32, length: usize, pk: Vec<SortOrder>) -> Table {
         assert_eq!(id & 1, 0, "Not a valid table id");
-        Table::new(Arc::clone(&self.db), id, length, pk)
+        Table::new(
+            Arc::clone(&self.engine),
+            Arc::clone(&self.writes),
+            Arc::clone(&self.modifications),
+            Arc::clone(&self.quarantine),
+            id,
+            length,
+            pk,
+        )
+        .with_format(self.format)
     }
 
-    /// Return the our default rocks db options
-    fn options() -> Options {
-        let mut options = Options::default();
-        let mut block_options = BlockBasedOptions::default();
-        // These options are non-negotiable
-        options.set_prefix_extractor(SliceTransform::create_fixed_prefix(
-            std::mem::size_of::<u32>(),
-        ));
-        options.create_if_missing(true);
-        options.set_merge_operator("frequency_merge", frequency_merge, Some(frequency_merge));
-        options.set_compaction_filter("compaction_filter", compaction_filter);
-
-        // These options are "tunable"
-        block_options.set_bloom_filter(10, false);
-        options.set_block_based_table_factory(&block_options);
-        options.increase_parallelism(4);
-        options.set_compression_type(DBCompressionType::Lz4);
-        options
+    /// The format version the data's laid out in, see format.rs
+    pub fn format_version(&self) -> u8 {
+        self.format
     }
-}
-
-/// The rocksdb merge filter, merges frequencies but only in the log sections.
-fn frequency_merge(
-    key: &[u8],
-    existing_value: Option<&[u8]>,
-    operand_list: &mut MergeOperands,
-) -> Option<Vec<u8>> {
-    // Indirection to allow testing since MergeOperands can't be constructed by us..
-    frequency_merge_impl(key, existing_value, operand_list)
-}
 
-fn frequency_merge_impl<'a, I: Iterator<Item = &'a [u8]> + 'a>(
-    key: &[u8],
-    existing_value: Option<&[u8]>,
-    operand_list: I,
-) -> Option<Vec<u8>> {
-    // fourth byte is even for index, odd for logs
-    if key[3] & 1 != 1 {
-        panic!("Merge called for index section")
+    /// Sets the table that rows failing their checksums are moved into, it's written as
+    /// table_id:bigint(pk), key:bytea(pk), value:bytea, detected_at:timestamp
+    pub fn set_quarantine_table(&self, id: u32) {
+        *self.quarantine.write().unwrap() = Some(id);
     }
 
-    let mut count = 0_i64;
-    let mut temp = 0_i64;
+    /// Returns a timestamp that reads can be made at and get a consistent view across tables,
+    /// everything written up to it has been committed. This will be just behind the current time
+    /// unless there's slow writes in flight, we'll wait up to wait for them to finish.
+    pub fn read_barrier(&self, wait: Duration) -> LogicalTimestamp {
+        self.writes.read_barrier(wait)
+    }
 
-    if let Some(bytes) = existing_value {
-        count.read_sortable_bytes(SortOrder::Asc, bytes);
+    /// The clock writes are timestamped by
+    pub fn clock(&self) -> &Clock {
+        self.writes.clock()
     }
 
-    for operand in operand_list {
-        temp.read_sortable_bytes(SortOrder::Asc, operand);
-        count += temp;
+    /// Returns the number of rows written to the table since startup or since the count was
+    /// last reset
+    pub fn modifications(&self, table_id: u32) -> u64 {
+        self.modifications.get(table_id)
     }
-    let mut ret = Vec::with_capacity(4);
-    count.write_sortable_bytes(SortOrder::Asc, &mut ret);
 
-    Some(ret)
-}
+    /// Starts the table's count of rows written again from zero
+    pub fn reset_modifications(&self, table_id: u32) {
+        self.modifications.reset(table_id)
+    }
 
-/// Used in conjunction with the frequency_merge filter to remove 0'd out freq's from the log
-/// section during a compaction.  We'll also use the filter during reads to prevent reading in these
-/// records then for consistency.
-// TODO We might not want to use this as we may end up with 0's in our index section but no log
-// with which to vacuum them with.
-fn compaction_filter(_level: u32, key: &[u8], value: &[u8]) -> Decision {
-    // fourth byte is even for index, odd for logs
-    if key[3] & 1 == 1 {
-        // We only need to check the first byte for 0
-        if value[0] == VARINT_SIGNED_ZERO_ENC {
-            return Decision::Remove;
-        }
+    /// Writes a consistent copy of all the tables out to path, the copy can be opened with
+    /// new_with_path.
+    pub fn checkpoint(&self, path: &str) -> Result<(), StorageError> {
+        self.engine.checkpoint(path)
     }
 
-    Decision::Keep
+    /// Returns the storage engine's current statistics
+    pub fn statistics(&self) -> StorageStatistics {
+        self.engine.statistics()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-
-    /// Decision doesn't impl eq or debug etc, this is just to make the compactionfilter output
-    /// testable
-    trait DecisionExtra {
-        fn is_keep(&self) -> bool;
-    }
-
-    impl DecisionExtra for Decision {
-        fn is_keep(&self) -> bool {
-            if let Decision::Keep = self {
-                true
-            } else {
-                false
-            }
-        }
-    }
+    use crate::format::FORMAT_VERSION;
+    use data::{Datum, TupleIter};
 
     #[test]
-    fn test_frequency_merge_impl_from_put() {
-        // put=2,   1, 5, -4 are our deltas
-        let prefix = [0_u8, 2, 3, 9];
-
-        let mut put_buf = vec![];
-        2_i64.write_sortable_bytes(SortOrder::Asc, &mut put_buf);
-
-        let delta_bufs: Vec<_> = [1_i64, 5, -4]
-            .as_ref()
-            .iter()
-            .map(|i| {
-                let mut buf = vec![];
-                i.write_sortable_bytes(SortOrder::Asc, &mut buf);
-                buf
-            })
-            .collect();
-        let operands = delta_bufs.iter().map(|buf| buf.as_ref());
-
-        let mut expected_buf = vec![];
-        4_i64.write_sortable_bytes(SortOrder::Asc, &mut expected_buf);
+    fn test_get_table() -> Result<(), StorageError> {
+        let storage = Storage::new_in_mem()?;
+        let table = storage.table(1234, 0, vec![]);
 
-        assert_eq!(
-            frequency_merge_impl(&prefix, Some(&put_buf), operands),
-            Some(expected_buf)
-        );
+        assert_eq!(table.id(), 1234);
+        Ok(())
     }
 
     #[test]
-    fn test_frequency_merge_impl_just_diffs() {
-        // 1, 5, -4 are our deltas
-        let prefix = [0_u8, 2, 3, 9];
-
-        let delta_bufs: Vec<_> = [1_i64, 5, -4]
-            .as_ref()
-            .iter()
-            .map(|i| {
-                let mut buf = vec![];
-                i.write_sortable_bytes(SortOrder::Asc, &mut buf);
-                buf
-            })
-            .collect();
-        let operands = delta_bufs.iter().map(|buf| buf.as_ref());
-
-        let mut expected_buf = vec![];
-        2_i64.write_sortable_bytes(SortOrder::Asc, &mut expected_buf);
-
-        assert_eq!(
-            frequency_merge_impl(&prefix, None, operands),
-            Some(expected_buf)
-        );
+    fn test_modifications() -> Result<(), StorageError> {
+        let storage = Storage::new_in_mem()?;
+        let table = storage.table(1234, 1, vec![SortOrder::Asc]);
+        table.atomic_write::<_, StorageError>(|writer| {
+            writer.write_tuple(&table, &[Datum::from(1)], LogicalTimestamp::new(10), 2)?;
+            writer.write_tuple(&table, &[Datum::from(2)], LogicalTimestamp::new(10), -1)?;
+            writer.system_write_tuple(&table, &[Datum::from(3)], 1);
+            Ok(())
+        })?;
+        assert_eq!(storage.modifications(1234), 3);
+        assert_eq!(storage.modifications(1236), 0);
+
+        // Failed writes don't count
+        let _ = table.atomic_write::<_, StorageError>(|writer| {
+            writer.write_tuple(&table, &[Datum::from(1)], LogicalTimestamp::new(10), 1)?;
+            Err(StorageError::RocksDbError("test".to_string()))
+        });
+        assert_eq!(storage.modifications(1234), 3);
+
+        storage.reset_modifications(1234);
+        assert_eq!(storage.modifications(1234), 0);
+        Ok(())
     }
 
     #[test]
-    fn test_compaction_filter() {
-        // PIT section - keep everything, don't even look!
-        assert!(compaction_filter(0, &[0, 0, 0, 0, 0, 2, 3, 4], &[]).is_keep());
-        assert!(
-            compaction_filter(0, &[0, 0, 0, 0, 0, 2, 3, 4], &[VARINT_SIGNED_ZERO_ENC]).is_keep()
-        );
-        // Log section - drop zeros
-        assert!(!compaction_filter(
-            0,
-            &[0, 0, 0, 1, 1, 2, 3, 4],
-            &[VARINT_SIGNED_ZERO_ENC, 1, 2, 3]
+    fn test_format_versions() -> Result<(), StorageError> {
+        let engine: Arc<dyn StorageEngine> = Arc::new(MemEngine::default());
+        let storage = Storage::new_with_engine(Arc::clone(&engine))?;
+        assert_eq!(storage.format_version(), FORMAT_VERSION);
+
+        // A database from before versioning, its values aren't tagged
+        let engine: Arc<dyn StorageEngine> = Arc::new(MemEngine::default());
+        let table = Table::new(
+            Arc::clone(&engine),
+            Arc::default(),
+            Arc::default(),
+            Arc::default(),
+            1234,
+            2,
+            vec![SortOrder::Asc],
         )
-        .is_keep());
-        assert!(compaction_filter(
-            0,
-            &[0, 0, 0, 1, 1, 2, 3, 4],
-            &[VARINT_SIGNED_ZERO_ENC + 1, 1, 2, 3]
-        )
-        .is_keep());
+        .with_format(0);
+        let tuple = [Datum::from(1), Datum::from("abc")];
+        table.atomic_write::<_, StorageError>(|writer| {
+            writer.write_tuple(&table, &tuple, LogicalTimestamp::new(10), 1)
+        })?;
+
+        let storage = Storage::new_with_engine(engine)?;
+        assert_eq!(storage.format_version(), 0);
+        let table = storage.table(1234, 2, vec![SortOrder::Asc]);
+        table.atomic_write::<_, StorageError>(|writer| {
+            writer.write_tuple(&table, &tuple, LogicalTimestamp::new(20), 1)
+        })?;
+        let mut iter = table.full_scan(LogicalTimestamp::MAX);
+        assert_eq!(iter.next()?, Some((tuple.as_ref(), 2)));
+        assert_eq!(iter.next()?, None);
+        let mut iter = table.full_scan(LogicalTimestamp::new(15));
+        assert_eq!(iter.next()?, Some((tuple.as_ref(), 1)));
+        Ok(())
     }
 
     #[test]
-    fn test_get_table() -> Result<(), StorageError> {
+    fn test_checkpoint() -> Result<(), StorageError> {
         let storage = Storage::new_in_mem()?;
-        let table = storage.table(1234, 0, vec![]);
-
-        assert_eq!(table.id(), 1234);
+        let table = storage.table(1234, 1, vec![SortOrder::Asc]);
+        let tuple = [Datum::from(1)];
+        table.atomic_write::<_, StorageError>(|writer| {
+            writer.write_tuple(&table, &tuple, LogicalTimestamp::new(10), 1)
+        })?;
+
+        let path = std::env::temp_dir().join(format!("incresql-checkpoint-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        storage.checkpoint(path)?;
+
+        let checkpoint = Storage::new_with_path(path)?;
+        let table = checkpoint.table(1234, 1, vec![SortOrder::Asc]);
+        let mut iter = table.full_scan(LogicalTimestamp::MAX);
+        assert_eq!(iter.next()?, Some((tuple.as_ref(), 1)));
+        assert_eq!(iter.next()?, None);
+        drop(iter);
+        drop(table);
+        drop(checkpoint);
+        let _ = std::fs::remove_dir_all(path);
         Ok(())
     }
 }
@@ -1,28 +1,61 @@
+use crate::engine::{KeyValueIter, StorageEngine, WriteBatch};
+use crate::format::{decode_value, start_value, FORMAT_VERSION};
+use crate::modifications::Modifications;
+use crate::transaction::{TransactionState, TransactionWrite};
+use crate::write_tracker::WriteTracker;
 use crate::StorageError;
 use data::encoding_core::SortableEncoding;
-use data::{Datum, LogicalTimestamp, SortOrder, TupleIter};
-use rocksdb::prelude::*;
-use rocksdb::{DBRawIterator, WriteBatch, WriteBatchWithIndex};
+use data::encoding_tuple::{null_bitmap_len, write_sparse_tuple, write_tuple};
+use data::{Clock, Datum, LogicalTimestamp, SortOrder, TupleIter};
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::fmt::{Debug, Formatter};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
 /// A Table is at this level is a collection of rows, identified by an id.
 /// We'll expose all of these tables by id in some special schema but in general not all of these
 /// are "tables" from the users perspective, some may be indexes..
 /// The primary key isn't exactly a primary key as freq doesn't always = 1. It's more to give KV
 /// semantics(and the performance that comes with it) for system/streaming state tables.
-/// It's not really defined what it means for a user table as of yet, At least for a start we'll
-/// consider all columns of a user table to be primary, if we wanted to expose it at the user level
-/// then we'd have to detect when the tuple-rest didn't match and throw an error.
+/// By default the leading columns of the tuple make up the pk. Tables with a primary key picked by
+/// the user (see with_primary_key) can be keyed on any of their columns and writing a row for a
+/// pk that already has a live row is an error, even if the rows are the same.
 #[derive(Clone)]
 pub struct Table {
-    db: Arc<DB>,
+    engine: Arc<dyn StorageEngine>,
+    writes: Arc<WriteTracker>,
+    modifications: Arc<Modifications>,
     id: u32,
     length: usize,
     pk: Vec<SortOrder>,
+    // The format version of the storage the table lives in
+    format: u8,
+    // Whether values carry a checksum of their key and value
+    checksums: bool,
+    // Whether tuples are written with a null bitmap rather than a byte per null
+    null_bitmap: bool,
+    // The columns of the tuple making up the pk, in pk order
+    key_columns: Vec<usize>,
+    // Whether the pk was picked by the user, rows then clash rather than adding up
+    primary_key: bool,
+    // The column of the tuple each stored column comes from, set when the key columns aren't
+    // the leading columns of the tuple. Rows are stored pk first then the rest of the columns
+    layout: Option<Vec<usize>>,
+    // Copies of the table keyed on other columns, written alongside the table
+    indexes: Vec<Table>,
+    // The table corrupt values are quarantined into, if one's been set up
+    quarantine: Arc<RwLock<Option<u32>>>,
+    // Set for the tables of a transaction, see Transaction::table
+    transaction: Option<Arc<TransactionState>>,
 }
 
+/// The length of the crc32 appended to the values of tables with checksums
+const CHECKSUM_LEN: usize = 4;
+
+/// The number of versions vacuum deletes per write batch
+const VACUUM_BATCH_SIZE: usize = 1000;
+
 impl PartialEq for Table {
     fn eq(&self, other: &Self) -> bool {
         self.id == other.id
@@ -39,9 +72,125 @@ impl Debug for Table {
 impl Table {
     /// Creates a new table. The pk represents the number of columns in the pk and their sort
     /// orders
-    pub(crate) fn new(db: Arc<DB>, id: u32, length: usize, pk: Vec<SortOrder>) -> Self {
+    pub(crate) fn new(
+        engine: Arc<dyn StorageEngine>,
+        writes: Arc<WriteTracker>,
+        modifications: Arc<Modifications>,
+        quarantine: Arc<RwLock<Option<u32>>>,
+        id: u32,
+        length: usize,
+        pk: Vec<SortOrder>,
+    ) -> Self {
         assert!(length >= pk.len());
-        Table { db, id, length, pk }
+        let key_columns = (0..pk.len()).collect();
+        Table {
+            engine,
+            writes,
+            modifications,
+            id,
+            length,
+            pk,
+            format: FORMAT_VERSION,
+            checksums: false,
+            null_bitmap: false,
+            key_columns,
+            primary_key: false,
+            layout: None,
+            indexes: vec![],
+            quarantine,
+            transaction: None,
+        }
+    }
+
+    /// Returns the table reading and writing values in the storage's format version
+    pub(crate) fn with_format(mut self, format: u8) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Returns the table with checksums turned on, each value written gets a crc32 of its key
+    /// and value appended which is checked as it's read back. Rows that fail the check are
+    /// treated as missing, they're moved out into the quarantine table so the table stays
+    /// readable and writable.
+    /// Turning checksums on or off for a table that already has rows isn't supported.
+    pub fn with_checksums(mut self) -> Self {
+        self.checksums = true;
+        self
+    }
+
+    /// Returns true if the table's values carry checksums.
+    pub fn checksums(&self) -> bool {
+        self.checksums
+    }
+
+    /// Returns the table with its tuples written as a bitmap of which columns are null followed
+    /// by only the columns that aren't, for wide tables that are mostly nulls. The keys no longer
+    /// sort by the primary key's columns so the table can only be fully scanned.
+    /// Changing the encoding of a table that already has rows isn't supported.
+    pub fn with_null_bitmap(mut self) -> Self {
+        self.null_bitmap = true;
+        self
+    }
+
+    /// Returns true if the table's tuples are written with null bitmaps.
+    pub fn null_bitmap(&self) -> bool {
+        self.null_bitmap
+    }
+
+    /// Returns the table keyed on the given columns of its tuples, one per sort order of the pk.
+    /// The rest of the columns are stored in the value. Writing a row whose key matches an
+    /// existing row with different values in the rest of its columns is an error rather than
+    /// the freqs adding up.
+    /// Changing the key of a table that already has rows isn't supported.
+    pub fn with_primary_key(mut self, key_columns: &[usize]) -> Self {
+        assert_eq!(key_columns.len(), self.pk.len());
+        let mut layout = key_columns.to_vec();
+        layout.extend((0..self.length).filter(|column| !key_columns.contains(column)));
+        assert_eq!(layout.len(), self.length);
+
+        self.key_columns = key_columns.to_vec();
+        self.primary_key = true;
+        self.layout = if layout
+            .iter()
+            .enumerate()
+            .all(|(idx, column)| idx == *column)
+        {
+            None
+        } else {
+            Some(layout)
+        };
+        self
+    }
+
+    /// Returns the columns of the tuple making up the primary key if it was picked by the user.
+    pub fn primary_key(&self) -> Option<&[usize]> {
+        if self.primary_key {
+            Some(&self.key_columns)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the columns of the tuple making up the pk, in pk order. Range scans are over
+    /// these columns.
+    pub fn key_columns(&self) -> &[usize] {
+        &self.key_columns
+    }
+
+    /// Returns the table with secondary indexes, every tuple written to the table is written
+    /// to each index in the same batch. An index holds the same tuples as the table, just keyed
+    /// differently (see with_primary_key), so it can be scanned in place of the table.
+    pub fn with_indexes(mut self, indexes: Vec<Table>) -> Self {
+        for index in &indexes {
+            assert_eq!(index.length, self.length);
+        }
+        self.indexes = indexes;
+        self
+    }
+
+    /// Returns the table's secondary indexes.
+    pub fn indexes(&self) -> &[Table] {
+        &self.indexes
     }
 
     /// Returns the id of the table.
@@ -49,14 +198,90 @@ impl Table {
         self.id
     }
 
-    /// Forces a rocks db compaction of the table, we'll expose this out in sql as it may be useful
+    /// The clock rows written to the table should be timestamped by
+    pub fn clock(&self) -> &Clock {
+        self.writes.clock()
+    }
+
+    /// Returns the number of columns in the table.
+    pub fn column_count(&self) -> usize {
+        self.length
+    }
+
+    /// Returns the sort orders of the primary key columns.
+    pub fn pk(&self) -> &[SortOrder] {
+        &self.pk
+    }
+
+    /// Forces a compaction of the table, we'll expose this out in sql as it may be useful
     /// after bulk loads or for benchmark tests as it blocks until compaction is done
-    pub fn force_rocks_compaction(&self) {
+    pub fn force_compaction(&self) -> Result<(), StorageError> {
         // id + 2 as id->id+1 is the index portion of the table and id+1->id+2 is the log portion
-        self.db.compact_range(
-            Some(self.id.to_be_bytes()),
-            Some((self.id + 2).to_be_bytes()),
-        );
+        self.engine
+            .compact_range(&self.id.to_be_bytes(), &(self.id + 2).to_be_bytes())
+    }
+
+    /// Removes the old versions of rows that no read as of the watermark or later can see, ie
+    /// those older than the version of each row that was current as of the watermark, which is
+    /// removed too if the row had been deleted by then. The latest version of a row is always
+    /// kept even once deleted as a write may be bringing the row back while we're vacuuming.
+    /// Indexes are tables of their own and are vacuumed separately.
+    /// Returns the number of versions removed.
+    pub fn vacuum(&self, watermark: LogicalTimestamp) -> Result<u64, StorageError> {
+        let mut iter = self
+            .engine
+            .prefix_iter((self.id + 1).to_be_bytes().to_vec());
+        iter.seek(&self.id.to_be_bytes());
+        let mut key_buffer: Vec<Datum<'static>> = right_size_new_to(self.length);
+        let mut batch = WriteBatch::default();
+        let mut removed = 0;
+        // Whether the row's version as of the watermark has been passed, everything after it
+        // is older
+        let mut passed_watermark = false;
+
+        while iter.valid() {
+            let key = iter.key().unwrap();
+            if let Some(value_buf) = self.read_value(key, iter.value().unwrap())? {
+                // Chop prefix
+                let mut key_buf = &key[4..];
+                let mut pk_len = 0_u64;
+                key_buf = pk_len.read_sortable_bytes(SortOrder::Asc, key_buf);
+                key_buf = read_datums(
+                    self.null_bitmap,
+                    key_buf,
+                    &mut key_buffer[..(pk_len as usize)],
+                );
+
+                if key_buf[0] == 0 {
+                    // "Header" record, the start of the next row
+                    let timestamp = u64::from_le_bytes(value_buf[..8].try_into().unwrap());
+                    passed_watermark = timestamp < watermark.ms;
+                } else if passed_watermark {
+                    batch.delete(key);
+                    removed += 1;
+                } else {
+                    let timestamp = u64::MAX - u64::from_be_bytes(key_buf[..8].try_into().unwrap());
+                    if timestamp < watermark.ms {
+                        passed_watermark = true;
+                        let mut freq = 0_i64;
+                        freq.read_sortable_bytes(SortOrder::Asc, value_buf);
+                        // Reads skip rows with no versions as of them just as they do deleted ones
+                        if freq == 0 {
+                            batch.delete(key);
+                            removed += 1;
+                        }
+                    }
+                }
+            }
+
+            if batch.ops.len() >= VACUUM_BATCH_SIZE {
+                self.engine.write(std::mem::take(&mut batch))?;
+            }
+            iter.next();
+        }
+        iter.status()?;
+        self.engine.write(batch)?;
+        Ok(removed)
     }
 
     /// Performs an atomic write, This semantically is done at the storage level so writes to any
@@ -65,31 +290,61 @@ impl Table {
     where
         F: FnOnce(&mut Writer) -> Result<(), E>,
     {
+        let _in_flight = self.writes.start_write();
         let mut writer = Writer::new();
         batch(&mut writer)?;
-        let mut write_options = WriteOptions::new();
-        write_options.set_sync(true);
-        write_options.set_low_pri(true);
-        self.db
-            .write_opt(writer.write_batch, &write_options)
-            .map_err(StorageError::from)?;
+        self.engine.write(writer.write_batch)?;
+        match &self.transaction {
+            // The rows only count as modified once they're committed
+            Some(transaction) => transaction
+                .writes
+                .lock()
+                .unwrap()
+                .append(&mut writer.transaction_writes),
+            None => self.modifications.add(&writer.modified),
+        }
         Ok(())
     }
 
-    /// Lower level atomic write without read after write support, used to work around some
-    /// unsupported rocks db functionality
+    pub(crate) fn engine(&self) -> &Arc<dyn StorageEngine> {
+        &self.engine
+    }
+
+    /// The table read and written through the transaction's overlay, along with its indexes
+    pub(crate) fn in_transaction(&self, transaction: &Arc<TransactionState>) -> Table {
+        let mut table = self.clone();
+        table.engine = Arc::clone(&transaction.overlay);
+        table.transaction = Some(Arc::clone(transaction));
+        table.indexes = self
+            .indexes
+            .iter()
+            .map(|index| index.in_transaction(transaction))
+            .collect();
+        table
+    }
+
+    /// The table a table of a transaction is over, the engine is the one under the overlay
+    pub(crate) fn outside_transaction(&self, engine: &Arc<dyn StorageEngine>) -> Table {
+        let mut table = self.clone();
+        table.engine = Arc::clone(engine);
+        table.transaction = None;
+        table.indexes = self
+            .indexes
+            .iter()
+            .map(|index| index.outside_transaction(engine))
+            .collect();
+        table
+    }
+
+    /// Lower level atomic write straight onto the write batch without read after write support,
+    /// used for range deletes which the writer can't index
     pub fn atomic_write_without_index<F, E: From<StorageError>>(&self, batch: F) -> Result<(), E>
     where
         F: FnOnce(&mut WriteBatch) -> Result<(), E>,
     {
         let mut write_batch = WriteBatch::default();
         batch(&mut write_batch)?;
-        let mut write_options = WriteOptions::new();
-        write_options.set_sync(true);
-        write_options.set_low_pri(true);
-        self.db
-            .write_opt(write_batch, &write_options)
-            .map_err(StorageError::from)?;
+        self.engine.write(write_batch)?;
         Ok(())
     }
 
@@ -106,7 +361,11 @@ impl Table {
     ) -> Result<Option<i64>, StorageError> {
         write_index_header_key(self, pk, key_buf);
 
-        if let Some(value_slice) = self.db.get_pinned(key_buf)? {
+        if let Some(value_slice) = self.engine.get(key_buf)? {
+            let value_slice = match self.read_value(key_buf, &value_slice)? {
+                Some(value_slice) => value_slice,
+                None => return Ok(None),
+            };
             rest_tuple.clear();
 
             let mut tuple_rest_len = 0_u64;
@@ -116,9 +375,7 @@ impl Table {
             // Not the tuple
             value_buf = tuple_rest_len.read_sortable_bytes(SortOrder::Asc, value_buf);
             rest_tuple.extend((0..tuple_rest_len).map(|_| Datum::default()));
-            for datum in rest_tuple {
-                value_buf = datum.from_sortable_bytes(value_buf);
-            }
+            read_datums(self.null_bitmap, value_buf, rest_tuple);
             Ok(Some(freq))
         } else {
             Ok(None)
@@ -139,24 +396,31 @@ impl Table {
     /// The from:to must be ordered as per the pk ordering.
     /// ie if the first col is sorted desc then the correct call here would be
     /// from: 5 to: 1.
+    /// Tables with null bitmaps don't sort by their pk so can only be scanned without a range.
     pub fn range_scan(
         &self,
         from: Option<&[Datum]>,
         to: Option<&[Datum]>,
         timestamp: LogicalTimestamp,
     ) -> impl TupleIter<E = StorageError> + '_ {
-        let mut iter_options = ReadOptions::default();
-        iter_options.set_prefix_same_as_start(true);
+        self.index_iter(from, to, timestamp)
+    }
 
-        if let Some(to_datum) = to {
+    fn index_iter(
+        &self,
+        from: Option<&[Datum]>,
+        to: Option<&[Datum]>,
+        timestamp: LogicalTimestamp,
+    ) -> IndexIter<'_> {
+        let upper_bound = if let Some(to_datum) = to {
             let mut buf = vec![];
             write_range_key(self, to_datum, &mut buf, true);
-            iter_options.set_iterate_upper_bound(buf);
+            buf
         } else {
-            iter_options.set_iterate_upper_bound((self.id + 1).to_be_bytes());
-        }
+            (self.id + 1).to_be_bytes().to_vec()
+        };
 
-        let mut iter = self.db.raw_iterator_opt(iter_options);
+        let mut iter = self.engine.prefix_iter(upper_bound);
 
         // Seek to start.
         if let Some(from_datum) = from {
@@ -167,31 +431,161 @@ impl Table {
             iter.seek(&self.id.to_be_bytes());
         }
 
-        IndexIter::new(iter, timestamp, self.length)
+        IndexIter::new(self, iter, timestamp)
+    }
+
+    /// Range scan of the table for SYSTEM sampling, the rows are split into blocks of
+    /// neighbouring keys and keep_block is called as each block's reached to decide whether to
+    /// return its rows. Skipped blocks are seeked past without being read.
+    pub fn sampled_range_scan<'a>(
+        &'a self,
+        from: Option<&[Datum]>,
+        to: Option<&[Datum]>,
+        timestamp: LogicalTimestamp,
+        keep_block: Box<dyn FnMut() -> bool + 'a>,
+    ) -> impl TupleIter<E = StorageError> + 'a {
+        let mut iter = self.index_iter(from, to, timestamp);
+        iter.sampler = Some(BlockSampler {
+            keep_block,
+            block_end: vec![],
+        });
+        iter
+    }
+
+    /// Scans for the changes made to the table between the two timestamps, each row whose freq
+    /// changed is returned with the change, ie its freq as of to less its freq as of from.
+    /// Rows that haven't been written to since from are skipped over without being read.
+    pub fn changes_scan(
+        &self,
+        from: LogicalTimestamp,
+        to: LogicalTimestamp,
+    ) -> impl TupleIter<E = StorageError> + '_ {
+        let mut iter = self
+            .engine
+            .prefix_iter((self.id + 1).to_be_bytes().to_vec());
+        iter.seek(&self.id.to_be_bytes());
+        ChangesIter::new(self, iter, from, to)
+    }
+
+    /// Strips the checksum and format version off the value, if the checksum doesn't match the
+    /// row is quarantined and None is returned. Values written in a format we can't read are
+    /// an error.
+    fn read_value<'v>(
+        &self,
+        key: &[u8],
+        value: &'v [u8],
+    ) -> Result<Option<&'v [u8]>, StorageError> {
+        let value = if !self.checksums {
+            value
+        } else {
+            match split_checksum(key, value) {
+                Some(payload) => payload,
+                None => {
+                    self.quarantine_row(key, value)?;
+                    return Ok(None);
+                }
+            }
+        };
+        // Every version so far lays the rest of the value out the same way
+        let (_version, payload) = decode_value(self.format, value)?;
+        Ok(Some(payload))
+    }
+
+    /// Moves a corrupt row out into the quarantine table, recording its raw key and value.
+    /// Without a quarantine table the row is left where it is and just skipped over.
+    fn quarantine_row(&self, key: &[u8], value: &[u8]) -> Result<(), StorageError> {
+        let quarantine_id = match *self.quarantine.read().unwrap() {
+            Some(id) => id,
+            None => return Ok(()),
+        };
+        let quarantine = Table::new(
+            Arc::clone(&self.engine),
+            Arc::clone(&self.writes),
+            Arc::clone(&self.modifications),
+            Arc::clone(&self.quarantine),
+            quarantine_id,
+            4,
+            vec![SortOrder::Asc, SortOrder::Asc],
+        )
+        .with_format(self.format);
+        let tuple = [
+            Datum::from(self.id as i64),
+            Datum::from(key.to_vec()),
+            Datum::from(value.to_vec()),
+            Datum::from(self.clock().now().ms as i64),
+        ];
+        quarantine.atomic_write(|batch| {
+            batch.system_write_tuple(&quarantine, &tuple, 1);
+            batch.delete(key);
+            Ok(())
+        })
     }
 }
 
+/// Returns the value without its checksum if the checksum matches
+fn split_checksum<'v>(key: &[u8], value: &'v [u8]) -> Option<&'v [u8]> {
+    if value.len() >= CHECKSUM_LEN {
+        let (payload, checksum) = value.split_at(value.len() - CHECKSUM_LEN);
+        if checksum == checksum_of(key, payload).to_le_bytes() {
+            return Some(payload);
+        }
+    }
+    None
+}
+
+/// The crc32 of a row's key followed by its value, covering the key means values can't be
+/// swapped between rows unnoticed.
+fn checksum_of(key: &[u8], value: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(key);
+    hasher.update(value);
+    hasher.finalize()
+}
+
 /// TupleIter implementation for iterating over the index section of tables
 struct IndexIter<'a> {
-    iter: DBRawIterator<'a>,
+    table: &'a Table,
+    iter: Box<dyn KeyValueIter + 'a>,
     timestamp: LogicalTimestamp,
-    /// Rocks db iters start already positioned on the first item
+    /// Engine iters start already positioned on the first item
     /// so we want the first call to advance to not advance the underlying
     /// rocksdb iter
     first: bool,
     tuple_buffer: Vec<Datum<'static>>,
+    /// For tables whose key columns aren't the leading columns, the row in the tuple's order
+    row_buffer: Vec<Datum<'static>>,
     freq: Option<i64>,
+    sampler: Option<BlockSampler<'a>>,
+}
+
+/// Decides which blocks of rows a SYSTEM sampled scan returns.
+struct BlockSampler<'a> {
+    keep_block: Box<dyn FnMut() -> bool + 'a>,
+    /// The exclusive end of the block the iter's in, every key up to it has been kept
+    block_end: Vec<u8>,
 }
 
 impl<'a> IndexIter<'a> {
-    fn new(iter: DBRawIterator<'a>, timestamp: LogicalTimestamp, column_count: usize) -> Self {
-        let tuple_buffer = right_size_new_to(column_count);
+    fn new(
+        table: &'a Table,
+        iter: Box<dyn KeyValueIter + 'a>,
+        timestamp: LogicalTimestamp,
+    ) -> Self {
+        let tuple_buffer = right_size_new_to(table.length);
+        let row_buffer = if table.layout.is_some() {
+            right_size_new_to(table.length)
+        } else {
+            vec![]
+        };
         IndexIter {
+            table,
             iter,
             timestamp,
             first: true,
             tuple_buffer,
+            row_buffer,
             freq: None,
+            sampler: None,
         }
     }
 }
@@ -213,17 +607,41 @@ impl TupleIter for IndexIter<'_> {
 
             if self.iter.valid() {
                 // key = <prefix as u32 be>:<tuple-pk as sorted>:<0>
-                // value = <timestamp as u64 le><freq as i64 varint><tuple-rest as sorted>
+                // value = <version><timestamp as u64 le><freq as i64 varint><tuple-rest as sorted>
+
+                let key = self.iter.key().unwrap();
+
+                // The first key past the end of a block is always the header of a new row
+                if let Some(sampler) = &mut self.sampler {
+                    if key >= sampler.block_end.as_slice() {
+                        sampler.block_end = sample_block_end(self.table, key);
+                        if !(sampler.keep_block)() {
+                            self.iter.seek(&sampler.block_end);
+                            self.first = true;
+                            continue;
+                        }
+                    }
+                }
 
+                let mut value_buf = match self.table.read_value(key, self.iter.value().unwrap())? {
+                    Some(value_buf) => value_buf,
+                    None => {
+                        // We can't tell which version of the row this was, so rather than
+                        // risk returning an older one we skip the row altogether
+                        seek_next_header = true;
+                        continue;
+                    }
+                };
                 // Chop prefix
-                let mut key_buf = &self.iter.key().unwrap()[4..];
-                let mut value_buf = self.iter.value().unwrap();
+                let mut key_buf = &key[4..];
                 // Tuple Pk
                 let mut tuple_pk_len = 0_u64;
                 key_buf = tuple_pk_len.read_sortable_bytes(SortOrder::Asc, &key_buf);
-                for idx in 0..tuple_pk_len {
-                    key_buf = self.tuple_buffer[idx as usize].from_sortable_bytes(key_buf);
-                }
+                key_buf = read_datums(
+                    self.table.null_bitmap,
+                    key_buf,
+                    &mut self.tuple_buffer[..(tuple_pk_len as usize)],
+                );
 
                 // Timestamp
                 let mut tuple_timestamp = LogicalTimestamp::default();
@@ -261,9 +679,16 @@ impl TupleIter for IndexIter<'_> {
                 // non-pk part of the tuple
                 let mut datum_count = 0_u64;
                 value_buf = datum_count.read_sortable_bytes(SortOrder::Asc, value_buf);
-                for idx in 0..datum_count {
-                    value_buf = self.tuple_buffer[(tuple_pk_len + idx) as usize]
-                        .from_sortable_bytes(value_buf);
+                read_datums(
+                    self.table.null_bitmap,
+                    value_buf,
+                    &mut self.tuple_buffer
+                        [(tuple_pk_len as usize)..((tuple_pk_len + datum_count) as usize)],
+                );
+                if let Some(layout) = &self.table.layout {
+                    for (idx, column) in layout.iter().enumerate() {
+                        self.row_buffer[*column] = std::mem::take(&mut self.tuple_buffer[idx]);
+                    }
                 }
                 break;
             } else {
@@ -275,6 +700,197 @@ impl TupleIter for IndexIter<'_> {
         Ok(())
     }
 
+    fn get(&self) -> Option<(&[Datum<'_>], i64)> {
+        if let Some(freq) = self.freq {
+            if self.table.layout.is_some() {
+                Some((&self.row_buffer, freq))
+            } else {
+                Some((&self.tuple_buffer, freq))
+            }
+        } else {
+            None
+        }
+    }
+
+    fn column_count(&self) -> usize {
+        self.tuple_buffer.len()
+    }
+}
+
+/// Walks every version of each row working out its freq as of from and as of to. Versions
+/// are stored newest first after the row's header so once the version as of from's been found
+/// the rest of the row can be skipped.
+struct ChangesIter<'a> {
+    table: &'a Table,
+    iter: Box<dyn KeyValueIter + 'a>,
+    from: LogicalTimestamp,
+    to: LogicalTimestamp,
+    /// When true the next call to advance starts from the current record rather than moving
+    /// on, the iter starts out positioned on the first record and a row's only known to be
+    /// complete once the next row's header has been read
+    first: bool,
+    /// Each record's pk is read into here
+    key_buffer: Vec<Datum<'static>>,
+    /// The row being returned, it's an owned copy as it's returned after the iter has moved on
+    tuple_buffer: Vec<Datum<'static>>,
+    freq: Option<i64>,
+    /// For tables with a primary key, the rest of the row's tuple as of to. If the row's
+    /// values were different as of from then the old row's retracted after the new one's
+    /// returned
+    to_rest: Vec<u8>,
+    retraction_buffer: Vec<Datum<'static>>,
+    retraction: Option<i64>,
+}
+
+impl<'a> ChangesIter<'a> {
+    fn new(
+        table: &'a Table,
+        iter: Box<dyn KeyValueIter + 'a>,
+        from: LogicalTimestamp,
+        to: LogicalTimestamp,
+    ) -> Self {
+        ChangesIter {
+            table,
+            iter,
+            from,
+            to,
+            first: true,
+            key_buffer: right_size_new_to(table.length),
+            tuple_buffer: right_size_new_to(table.length),
+            freq: None,
+            to_rest: vec![],
+            retraction_buffer: right_size_new_to(table.length),
+            retraction: None,
+        }
+    }
+}
+
+impl TupleIter for ChangesIter<'_> {
+    type E = StorageError;
+
+    fn advance(&mut self) -> Result<(), StorageError> {
+        // The freqs as of to and as of from of the row we're part way through, None while
+        // skipping over a row that hasn't changed
+        let mut row: Option<(Option<i64>, Option<i64>)> = None;
+        let change = |row: Option<(Option<i64>, Option<i64>)>| {
+            row.map(|(to, from)| to.unwrap_or(0) - from.unwrap_or(0))
+                .filter(|change| *change != 0)
+        };
+
+        if let Some(freq) = self.retraction.take() {
+            std::mem::swap(&mut self.tuple_buffer, &mut self.retraction_buffer);
+            self.freq = Some(freq);
+            return Ok(());
+        }
+
+        loop {
+            if self.first {
+                self.first = false;
+            } else {
+                self.iter.next();
+            }
+
+            if !self.iter.valid() {
+                self.iter.status()?;
+                self.freq = change(row);
+                // Leaves the iter where it is so the next call ends the scan
+                self.first = true;
+                return Ok(());
+            }
+
+            let key = self.iter.key().unwrap();
+            let mut value_buf = match self.table.read_value(key, self.iter.value().unwrap())? {
+                Some(value_buf) => value_buf,
+                None => continue,
+            };
+            // Chop prefix
+            let mut key_buf = &key[4..];
+            let mut pk_len = 0_u64;
+            key_buf = pk_len.read_sortable_bytes(SortOrder::Asc, key_buf);
+            key_buf = read_datums(
+                self.table.null_bitmap,
+                key_buf,
+                &mut self.key_buffer[..(pk_len as usize)],
+            );
+
+            let timestamp = if key_buf[0] == 0 {
+                // "Header" record, the start of the next row
+                if let Some(freq) = change(row) {
+                    self.freq = Some(freq);
+                    self.first = true;
+                    return Ok(());
+                }
+                let timestamp = u64::from_le_bytes(value_buf[..8].as_ref().try_into().unwrap());
+                value_buf = &value_buf[8..];
+                if timestamp < self.from.ms {
+                    // Not written to since from
+                    row = None;
+                    continue;
+                }
+                row = Some((None, None));
+                timestamp
+            } else if row.is_some() {
+                u64::MAX - u64::from_be_bytes(key_buf[..8].as_ref().try_into().unwrap())
+            } else {
+                continue;
+            };
+
+            let (to_freq, from_freq) = row.as_mut().unwrap();
+            if timestamp >= self.to.ms || from_freq.is_some() {
+                continue;
+            }
+            let mut freq = 0_i64;
+            value_buf = freq.read_sortable_bytes(SortOrder::Asc, value_buf);
+            if to_freq.is_none() {
+                *to_freq = Some(freq);
+                if self.table.primary_key {
+                    self.to_rest.clear();
+                    self.to_rest.extend_from_slice(value_buf);
+                }
+                if freq != 0 {
+                    read_row(
+                        self.table,
+                        pk_len as usize,
+                        value_buf,
+                        &mut self.key_buffer,
+                        &mut self.tuple_buffer,
+                    );
+                }
+            }
+            if timestamp < self.from.ms {
+                // Deleted rows are returned as they were as of from
+                if *to_freq == Some(0) && freq != 0 {
+                    read_row(
+                        self.table,
+                        pk_len as usize,
+                        value_buf,
+                        &mut self.key_buffer,
+                        &mut self.tuple_buffer,
+                    );
+                }
+                if self.table.primary_key
+                    && *to_freq != Some(0)
+                    && freq != 0
+                    && value_buf != self.to_rest.as_slice()
+                {
+                    // The key's row was replaced by one with different values, the new row's
+                    // returned with its whole freq then the old one's retracted
+                    read_row(
+                        self.table,
+                        pk_len as usize,
+                        value_buf,
+                        &mut self.key_buffer,
+                        &mut self.retraction_buffer,
+                    );
+                    self.retraction = Some(-freq);
+                    row = Some((*to_freq, Some(0)));
+                } else {
+                    row = Some((*to_freq, Some(freq)));
+                }
+            }
+        }
+    }
+
     fn get(&self) -> Option<(&[Datum<'_>], i64)> {
         if let Some(freq) = self.freq {
             Some((&self.tuple_buffer, freq))
@@ -288,10 +904,38 @@ impl TupleIter for IndexIter<'_> {
     }
 }
 
+/// Copies a row out of one of its versions, value_buf starting at the rest of the tuple and
+/// key_buffer already holding the pk
+fn read_row(
+    table: &Table,
+    pk_len: usize,
+    value_buf: &[u8],
+    key_buffer: &mut [Datum],
+    tuple_buffer: &mut [Datum<'static>],
+) {
+    let mut datum_count = 0_u64;
+    let value_buf = datum_count.read_sortable_bytes(SortOrder::Asc, value_buf);
+    read_datums(
+        table.null_bitmap,
+        value_buf,
+        &mut key_buffer[pk_len..(pk_len + datum_count as usize)],
+    );
+    for (idx, datum) in key_buffer.iter().enumerate() {
+        let column = table.layout.as_ref().map_or(idx, |layout| layout[idx]);
+        tuple_buffer[column] = datum.as_static();
+    }
+}
+
 /// Abstraction through which all writes happens, allows some degree of
-/// read after write functionality which is not offered by rocksdb.
+/// read after write functionality which is not offered by the storage engines.
 pub struct Writer {
-    write_batch: WriteBatchWithIndex,
+    write_batch: WriteBatch,
+    // The values written so far in this batch, None for deletes
+    written: HashMap<Vec<u8>, Option<Vec<u8>>>,
+    // The number of rows written to each table, by table id
+    modified: HashMap<u32, u64>,
+    // The rows written to tables of a transaction, to be written again when it commits
+    transaction_writes: Vec<TransactionWrite>,
     key_buf: Vec<u8>,
     value_buf: Vec<u8>,
 }
@@ -299,32 +943,164 @@ pub struct Writer {
 impl Writer {
     fn new() -> Self {
         Writer {
-            write_batch: WriteBatchWithIndex::default(),
+            write_batch: WriteBatch::default(),
+            written: HashMap::new(),
+            modified: HashMap::new(),
+            transaction_writes: vec![],
             key_buf: Vec::with_capacity(64),
             value_buf: Vec::with_capacity(64),
         }
     }
 
-    /// Writes the tuple into the table
+    /// Reads the value for key as of the writes in this batch.
+    fn get(&self, table: &Table, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        match self.written.get(key) {
+            Some(value) => Ok(value.clone()),
+            None => table.engine.get(key),
+        }
+    }
+
+    fn put(&mut self, key: &[u8], value: &[u8]) {
+        self.write_batch.put(key, value);
+        self.written.insert(key.to_vec(), Some(value.to_vec()));
+    }
+
+    fn delete(&mut self, key: &[u8]) {
+        self.write_batch.delete(key);
+        self.written.insert(key.to_vec(), None);
+    }
+
+    /// Writes the tuple into the table, and its indexes
     pub fn write_tuple(
         &mut self,
         table: &Table,
         tuple: &[Datum],
         timestamp: LogicalTimestamp,
-        mut freq: i64,
+        freq: i64,
+    ) -> Result<(), StorageError> {
+        self.write_row(table, tuple, timestamp, freq)?;
+        for index in &table.indexes {
+            self.write_row(index, tuple, timestamp, freq)?;
+        }
+        self.log_transaction_write(table, tuple, freq, false);
+        Ok(())
+    }
+
+    fn log_transaction_write(&mut self, table: &Table, tuple: &[Datum], freq: i64, system: bool) {
+        if table.transaction.is_some() {
+            self.transaction_writes.push(TransactionWrite {
+                table: table.clone(),
+                tuple: tuple.iter().map(Datum::as_static).collect(),
+                freq,
+                system,
+            });
+        }
+    }
+
+    /// Writes a batch of tuples into the table, and its indexes, all with the one timestamp.
+    /// The rows are written in key order so the table's existing rows are found by walking it
+    /// with a single iterator rather than looking each of them up, which is what makes bulk
+    /// loads quick. Rows repeated within the batch add up just as if written one by one.
+    pub fn write_tuples<'a>(
+        &mut self,
+        table: &Table,
+        tuples: &'a [(Vec<Datum<'a>>, i64)],
+        timestamp: LogicalTimestamp,
+    ) -> Result<(), StorageError> {
+        let mut rows: Vec<_> = tuples
+            .iter()
+            .map(|(tuple, freq)| {
+                assert_eq!(tuple.len(), table.length);
+                let tuple = stored_tuple(table, tuple);
+                let mut key = vec![];
+                write_index_header_key(table, &tuple, &mut key);
+                (key, tuple, *freq)
+            })
+            .collect();
+        // A stable sort so repeated rows are still written in the order they came in
+        rows.sort_by(|(key, ..), (other, ..)| key.cmp(other));
+
+        let mut iter = table
+            .engine
+            .prefix_iter((table.id + 1).to_be_bytes().to_vec());
+        let mut seeked = false;
+        for (key, tuple, freq) in rows {
+            let existing = match self.written.get(&key) {
+                Some(value) => value.clone(),
+                None => {
+                    // The keys only go up so the iterator only ever moves forward, once it's
+                    // run off the end of the table none of the keys left exist
+                    if !seeked || iter.key().map_or(false, |current| current < key.as_slice()) {
+                        iter.seek(&key);
+                        seeked = true;
+                    }
+                    iter.status()?;
+                    match iter.key() {
+                        Some(current) if current == key.as_slice() => {
+                            iter.value().map(<[u8]>::to_vec)
+                        }
+                        _ => None,
+                    }
+                }
+            };
+            self.key_buf = key;
+            self.write_stored_row(table, &tuple, timestamp, freq, existing)?;
+        }
+
+        for (tuple, freq) in tuples {
+            for index in &table.indexes {
+                self.write_row(index, tuple, timestamp, *freq)?;
+            }
+            self.log_transaction_write(table, tuple, *freq, false);
+        }
+        Ok(())
+    }
+
+    fn write_row(
+        &mut self,
+        table: &Table,
+        tuple: &[Datum],
+        timestamp: LogicalTimestamp,
+        freq: i64,
     ) -> Result<(), StorageError> {
         assert_eq!(tuple.len(), table.length);
+        let tuple = stored_tuple(table, tuple);
         // create rocksdb key
-        write_index_header_key(table, tuple, &mut self.key_buf);
+        write_index_header_key(table, &tuple, &mut self.key_buf);
+        let existing = self.get(table, &self.key_buf)?;
+        self.write_stored_row(table, &tuple, timestamp, freq, existing)
+    }
 
-        // TODO investigate holding onto slice as rocksdb may reuse it if we pass it back in.
-        if let Some(value_bytes) = self.write_batch.get(&table.db, &self.key_buf)? {
+    /// Writes the tuple, already laid out as it's stored, under the key in the key buffer. Any
+    /// existing value for the key is bumped down from the header into the row's history.
+    fn write_stored_row(
+        &mut self,
+        table: &Table,
+        tuple: &[Datum],
+        timestamp: LogicalTimestamp,
+        mut freq: i64,
+        existing: Option<Vec<u8>>,
+    ) -> Result<(), StorageError> {
+        if table.primary_key && freq > 1 {
+            return Err(StorageError::DuplicateKey);
+        }
+        *self.modified.entry(table.id).or_insert(0) += freq.abs() as u64;
+        let existing = match existing {
+            Some(value_bytes) => table
+                .read_value(&self.key_buf, &value_bytes)?
+                .map(<[u8]>::to_vec),
+            None => None,
+        };
+        if let Some(value_bytes) = existing {
             // There's an existing record..
             // We need to bump it down from the header.
-            let last_timestamp = u64::from_le_bytes(value_bytes.as_ref()[..8].try_into().unwrap());
+            let last_timestamp = u64::from_le_bytes(value_bytes[..8].try_into().unwrap());
             // We need to update the freqs here.
             let mut last_freq = 0_i64;
-            last_freq.read_sortable_bytes(SortOrder::Asc, &value_bytes.as_ref()[8..]);
+            last_freq.read_sortable_bytes(SortOrder::Asc, &value_bytes[8..]);
+            if table.primary_key && freq > 0 && last_freq != 0 {
+                return Err(StorageError::DuplicateKey);
+            }
             freq += last_freq;
 
             if last_timestamp != timestamp.ms {
@@ -332,8 +1108,15 @@ impl Writer {
                 self.key_buf
                     .extend_from_slice(&(u64::MAX - last_timestamp).to_be_bytes());
 
-                self.write_batch
-                    .put(&self.key_buf, &value_bytes.as_ref()[8..]);
+                let key = std::mem::take(&mut self.key_buf);
+                let mut value = vec![];
+                start_value(table.format, &mut value);
+                value.extend_from_slice(&value_bytes[8..]);
+                if table.checksums {
+                    append_checksum(&key, &mut value);
+                }
+                self.put(&key, &value);
+                self.key_buf = key;
 
                 // Restore the key
                 self.key_buf.truncate(self.key_buf.len() - 8);
@@ -341,8 +1124,11 @@ impl Writer {
             }
         }
         write_index_header_value(table, tuple, timestamp, freq, &mut self.value_buf);
+        if table.checksums {
+            append_checksum(&self.key_buf, &mut self.value_buf);
+        }
 
-        self.write_batch.put(&self.key_buf, &self.value_buf);
+        self.put_buffers();
         Ok(())
     }
 
@@ -352,6 +1138,7 @@ impl Writer {
     /// Will overwrite the latest version of a tuple for the same primary key
     pub fn system_write_tuple(&mut self, table: &Table, tuple: &[Datum], freq: i64) {
         self.write_index_header(table, tuple, LogicalTimestamp::default(), freq);
+        self.log_transaction_write(table, tuple, freq, true);
     }
 
     /// Deletes tuples but should only be used for tuples written with system_write_tuple.
@@ -359,7 +1146,9 @@ impl Writer {
     /// according to that pk...
     pub fn system_delete_tuple(&mut self, table: &Table, pk: &[Datum]) {
         write_index_header_key(table, pk, &mut self.key_buf);
-        self.write_batch.delete(&self.key_buf);
+        let key = std::mem::take(&mut self.key_buf);
+        self.delete(&key);
+        self.key_buf = key;
     }
 
     fn write_index_header(
@@ -369,10 +1158,29 @@ impl Writer {
         timestamp: LogicalTimestamp,
         freq: i64,
     ) {
-        write_index_header_key(table, tuple, &mut self.key_buf);
-        write_index_header_value(table, tuple, timestamp, freq, &mut self.value_buf);
+        let tuple = stored_tuple(table, tuple);
+        write_index_header_key(table, &tuple, &mut self.key_buf);
+        write_index_header_value(table, &tuple, timestamp, freq, &mut self.value_buf);
+        if table.checksums {
+            append_checksum(&self.key_buf, &mut self.value_buf);
+        }
+
+        self.put_buffers();
+    }
 
+    /// Puts the key and value that have been written into the buffers
+    fn put_buffers(&mut self) {
         self.write_batch.put(&self.key_buf, &self.value_buf);
+        self.written
+            .insert(self.key_buf.clone(), Some(self.value_buf.clone()));
+    }
+}
+
+/// The tuple with its columns in the order they're stored in, pk first
+fn stored_tuple<'a>(table: &Table, tuple: &'a [Datum<'a>]) -> Cow<'a, [Datum<'a>]> {
+    match &table.layout {
+        Some(layout) => Cow::Owned(layout.iter().map(|idx| tuple[*idx].ref_clone()).collect()),
+        None => Cow::Borrowed(tuple),
     }
 }
 
@@ -391,10 +1199,14 @@ fn write_range_key(table: &Table, tuple: &[Datum], key_buf: &mut Vec<u8>, end: b
     key_buf.extend_from_slice(&table.id.to_be_bytes());
 
     // Tuple-PK
-    (table.pk.len() as u64).write_sortable_bytes(SortOrder::Asc, key_buf);
-
-    for (sort_order, datum) in table.pk.iter().zip(tuple) {
-        datum.as_sortable_bytes(*sort_order, key_buf);
+    if table.null_bitmap {
+        let pk_len = table.pk.len().min(tuple.len());
+        write_sparse_tuple(&tuple[..pk_len], &table.pk, key_buf);
+    } else {
+        (table.pk.len() as u64).write_sortable_bytes(SortOrder::Asc, key_buf);
+        for (sort_order, datum) in table.pk.iter().zip(tuple) {
+            datum.as_sortable_bytes(*sort_order, key_buf);
+        }
     }
     if end {
         key_buf.push(255);
@@ -411,8 +1223,8 @@ fn write_index_header_value(
     value_buf: &mut Vec<u8>,
 ) {
     // Index header:
-    // value = <timestamp as u64 le><freq as i64 varint><tuple-rest as sorted>
-    value_buf.clear();
+    // value = <version><timestamp as u64 le><freq as i64 varint><tuple-rest as sorted>
+    start_value(table.format, value_buf);
 
     ////////// VALUE
     // Actual Timestamp
@@ -421,12 +1233,77 @@ fn write_index_header_value(
     // Freq
     freq.write_sortable_bytes(SortOrder::Asc, value_buf);
 
-    // Tuple-rest
+    write_tuple_rest(table, tuple, value_buf);
+}
+
+/// Writes the non-pk part of the tuple
+fn write_tuple_rest(table: &Table, tuple: &[Datum], value_buf: &mut Vec<u8>) {
     let rest = &tuple[(table.pk.len())..];
-    (rest.len() as u64).write_sortable_bytes(SortOrder::Asc, value_buf);
-    for datum in rest {
-        datum.as_sortable_bytes(SortOrder::Asc, value_buf);
+    if table.null_bitmap {
+        write_sparse_tuple(rest, &[], value_buf);
+    } else {
+        write_tuple(rest, value_buf);
+    }
+}
+
+/// Reads the datums following a tuple's datum count into tuple, for tables with null bitmaps
+/// the bitmap's read first and only the datums that aren't null are read from the buffer.
+fn read_datums<'a>(null_bitmap: bool, mut buffer: &'a [u8], tuple: &mut [Datum]) -> &'a [u8] {
+    if !null_bitmap {
+        for datum in tuple {
+            buffer = datum.from_sortable_bytes(buffer);
+        }
+        return buffer;
+    }
+    let (bitmap, mut rem) = buffer.split_at(null_bitmap_len(tuple.len()));
+    for (idx, datum) in tuple.iter_mut().enumerate() {
+        if bitmap[idx / 8] & (1 << (idx % 8)) != 0 {
+            *datum = Datum::Null;
+        } else {
+            rem = datum.from_sortable_bytes(rem);
+        }
+    }
+    rem
+}
+
+/// The exclusive end of the SYSTEM sampling block the row's header key is in. A block is the
+/// rows whose first key column encodes to the same bytes bar the last, ie runs of up to 256
+/// integers. Datums that encode to just their type and one more byte, such as booleans and
+/// small integers, get a block per value.
+fn sample_block_end(table: &Table, key: &[u8]) -> Vec<u8> {
+    let mut pk_len = 0_u64;
+    let mut rem = pk_len.read_sortable_bytes(SortOrder::Asc, &key[4..]);
+    if table.null_bitmap {
+        let (bitmap, datums) = rem.split_at(null_bitmap_len(pk_len as usize));
+        rem = datums;
+        if bitmap[0] & 1 != 0 {
+            return prefix_end(&key[..(key.len() - rem.len())]);
+        }
+    }
+    let start = key.len() - rem.len();
+    let end = key.len() - Datum::Null.from_sortable_bytes(rem).len();
+    if end - start > 2 {
+        prefix_end(&key[..(end - 1)])
+    } else {
+        prefix_end(&key[..end])
+    }
+}
+
+/// The smallest key greater than every key starting with prefix
+fn prefix_end(prefix: &[u8]) -> Vec<u8> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last != 255 {
+            end.push(last + 1);
+            break;
+        }
     }
+    end
+}
+
+fn append_checksum(key: &[u8], value_buf: &mut Vec<u8>) {
+    let checksum = checksum_of(key, value_buf);
+    value_buf.extend_from_slice(&checksum.to_le_bytes());
 }
 
 fn right_size_new_to<T: Default>(size: usize) -> Vec<T> {
@@ -437,14 +1314,45 @@ fn right_size_new_to<T: Default>(size: usize) -> Vec<T> {
 mod tests {
     use super::*;
     use crate::{Storage, StorageError};
+    use data::encoding_tuple::read_tuple;
     use data::{Datum, LogicalTimestamp, SortOrder};
 
     /// Hard to functionally test this, so this is more just a smoke test that anything else!
     #[test]
-    fn test_force_rocks_compaction() -> Result<(), StorageError> {
+    fn test_force_compaction() -> Result<(), StorageError> {
         let storage = Storage::new_in_mem()?;
         let table = storage.table(1234, 2, vec![SortOrder::Asc]);
-        table.force_rocks_compaction();
+        table.force_compaction()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_rows_readable_without_context() -> Result<(), StorageError> {
+        let storage = Storage::new_in_mem()?;
+        let table = storage.table(1234, 3, vec![SortOrder::Asc, SortOrder::Desc]);
+        let tuple = vec![Datum::from(1), Datum::from("abc"), Datum::from(true)];
+        table.atomic_write::<_, StorageError>(|writer| {
+            writer.write_tuple(&table, &tuple, LogicalTimestamp::new(10), 2)?;
+            Ok(())
+        })?;
+
+        let mut key = vec![];
+        write_index_header_key(&table, &tuple, &mut key);
+        let value = table.engine.get(&key)?.unwrap();
+
+        // key = <prefix>:<tuple-pk>:<0>
+        let (pk, rem) = read_tuple(&key[4..]).unwrap();
+        assert_eq!(pk, &tuple[..2]);
+        assert_eq!(rem, &[0]);
+
+        // value = <version><timestamp><freq><tuple-rest>
+        assert_eq!(value[0], FORMAT_VERSION);
+        let mut freq = 0_i64;
+        let rem = freq.read_sortable_bytes(SortOrder::Asc, &value[9..]);
+        assert_eq!(freq, 2);
+        let (rest, rem) = read_tuple(rem).unwrap();
+        assert_eq!(rest, &tuple[2..]);
+        assert!(rem.is_empty());
         Ok(())
     }
 
@@ -533,6 +1441,390 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_write_tuples() -> Result<(), StorageError> {
+        let storage = Storage::new_in_mem()?;
+        let table = storage.table(1234, 1, vec![SortOrder::Asc]);
+        let a = vec![Datum::from(1)];
+        let b = vec![Datum::from(2)];
+        let c = vec![Datum::from(3)];
+
+        table.atomic_write::<_, StorageError>(|writer| {
+            writer.write_tuple(&table, &b, LogicalTimestamp::new(10), 1)?;
+            Ok(())
+        })?;
+        // Rows before, on and after the existing row, with a repeat
+        let tuples = vec![
+            (c.clone(), 1),
+            (a.clone(), 1),
+            (b.clone(), 2),
+            (c.clone(), 1),
+        ];
+        table.atomic_write::<_, StorageError>(|writer| {
+            writer.write_tuples(&table, &tuples, LogicalTimestamp::new(20))
+        })?;
+
+        let rows = |timestamp| -> Result<Vec<(Vec<Datum<'static>>, i64)>, StorageError> {
+            let mut iter = table.full_scan(LogicalTimestamp::new(timestamp));
+            let mut rows = vec![];
+            while let Some((tuple, freq)) = iter.next()? {
+                rows.push((tuple.iter().map(Datum::as_static).collect(), freq));
+            }
+            Ok(rows)
+        };
+
+        assert_eq!(rows(15)?, vec![(b.clone(), 1)]);
+        assert_eq!(rows(25)?, vec![(a, 1), (b, 3), (c, 2)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_changes_scan() -> Result<(), StorageError> {
+        let storage = Storage::new_in_mem()?;
+        let table = storage.table(1234, 2, vec![SortOrder::Asc, SortOrder::Asc]);
+        let a = vec![Datum::from(1), Datum::from("a".to_string())];
+        let b = vec![Datum::from(2), Datum::from("b".to_string())];
+        let c = vec![Datum::from(3), Datum::from("c".to_string())];
+
+        table.atomic_write::<_, StorageError>(|writer| {
+            writer.write_tuple(&table, &a, LogicalTimestamp::new(10), 1)?;
+            writer.write_tuple(&table, &b, LogicalTimestamp::new(10), 2)?;
+            writer.write_tuple(&table, &b, LogicalTimestamp::new(20), -2)?;
+            writer.write_tuple(&table, &c, LogicalTimestamp::new(20), 1)?;
+            writer.write_tuple(&table, &c, LogicalTimestamp::new(30), 1)?;
+            Ok(())
+        })?;
+
+        let changes = |from, to| -> Result<Vec<(Vec<Datum<'static>>, i64)>, StorageError> {
+            let mut iter =
+                table.changes_scan(LogicalTimestamp::new(from), LogicalTimestamp::new(to));
+            let mut changes = vec![];
+            while let Some((tuple, freq)) = iter.next()? {
+                changes.push((tuple.iter().map(Datum::as_static).collect(), freq));
+            }
+            Ok(changes)
+        };
+
+        assert_eq!(changes(0, 15)?, vec![(a.clone(), 1), (b.clone(), 2)]);
+        assert_eq!(changes(15, 25)?, vec![(b.clone(), -2), (c.clone(), 1)]);
+        assert_eq!(changes(25, 35)?, vec![(c.clone(), 1)]);
+        assert_eq!(changes(0, 35)?, vec![(a, 1), (c, 2)]);
+        assert_eq!(changes(35, 40)?, vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_vacuum() -> Result<(), StorageError> {
+        let storage = Storage::new_in_mem()?;
+        let table = storage.table(1234, 1, vec![SortOrder::Asc]);
+        let a = [Datum::from(1)];
+        let b = [Datum::from(2)];
+        let c = [Datum::from(3)];
+
+        table.atomic_write::<_, StorageError>(|writer| {
+            writer.write_tuple(&table, &a, LogicalTimestamp::new(10), 1)?;
+            writer.write_tuple(&table, &a, LogicalTimestamp::new(20), 1)?;
+            writer.write_tuple(&table, &a, LogicalTimestamp::new(30), 1)?;
+            writer.write_tuple(&table, &b, LogicalTimestamp::new(10), 1)?;
+            writer.write_tuple(&table, &b, LogicalTimestamp::new(20), -1)?;
+            writer.write_tuple(&table, &c, LogicalTimestamp::new(10), 1)?;
+            writer.write_tuple(&table, &c, LogicalTimestamp::new(20), -1)?;
+            writer.write_tuple(&table, &c, LogicalTimestamp::new(30), 1)?;
+            Ok(())
+        })?;
+
+        let rows = |timestamp| -> Result<Vec<(Vec<Datum<'static>>, i64)>, StorageError> {
+            let mut iter = table.full_scan(LogicalTimestamp::new(timestamp));
+            let mut rows = vec![];
+            while let Some((tuple, freq)) = iter.next()? {
+                rows.push((tuple.iter().map(Datum::as_static).collect(), freq));
+            }
+            Ok(rows)
+        };
+
+        // a's and c's versions from 10, b's from 10 and c's deleted version from 20
+        assert_eq!(table.vacuum(LogicalTimestamp::new(25))?, 4);
+        assert_eq!(table.vacuum(LogicalTimestamp::new(25))?, 0);
+
+        // Reads as of the watermark or later are unchanged
+        assert_eq!(rows(25)?, vec![(a.to_vec(), 2)]);
+        assert_eq!(rows(35)?, vec![(a.to_vec(), 3), (c.to_vec(), 1)]);
+        // Before it the history's gone
+        assert_eq!(rows(15)?, vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_primary_key() -> Result<(), StorageError> {
+        let storage = Storage::new_in_mem()?;
+        // Keyed on the last column descending, the others are stored in the value
+        let table = storage
+            .table(1234, 3, vec![SortOrder::Desc])
+            .with_primary_key(&[2]);
+        assert_eq!(table.key_columns(), &[2]);
+        let a = vec![
+            Datum::from(1),
+            Datum::from("a".to_string()),
+            Datum::from(10),
+        ];
+        let b = vec![
+            Datum::from(2),
+            Datum::from("b".to_string()),
+            Datum::from(20),
+        ];
+        let b2 = vec![
+            Datum::from(3),
+            Datum::from("b".to_string()),
+            Datum::from(20),
+        ];
+
+        table.atomic_write::<_, StorageError>(|writer| {
+            writer.write_tuple(&table, &a, LogicalTimestamp::new(10), 1)?;
+            writer.write_tuple(&table, &b, LogicalTimestamp::new(10), 1)?;
+            Ok(())
+        })?;
+
+        let mut iter = table.full_scan(LogicalTimestamp::new(15));
+        assert_eq!(iter.next()?, Some((b.as_ref(), 1)));
+        assert_eq!(iter.next()?, Some((a.as_ref(), 1)));
+        assert_eq!(iter.next()?, None);
+
+        let from = [Datum::from(10)];
+        let mut iter = table.range_scan(Some(&from), Some(&from), LogicalTimestamp::new(15));
+        assert_eq!(iter.next()?, Some((a.as_ref(), 1)));
+        assert_eq!(iter.next()?, None);
+
+        // A different row for the same key clashes, even in the same batch
+        let result = table.atomic_write::<_, StorageError>(|writer| {
+            writer.write_tuple(&table, &b2, LogicalTimestamp::new(20), 1)
+        });
+        assert_eq!(result, Err(StorageError::DuplicateKey));
+        let result = table.atomic_write::<_, StorageError>(|writer| {
+            writer.write_tuple(&table, &a, LogicalTimestamp::new(20), 1)?;
+            writer.write_tuple(&table, &a, LogicalTimestamp::new(20), -1)?;
+            writer.write_tuple(&table, &b2, LogicalTimestamp::new(20), 1)
+        });
+        assert_eq!(result, Err(StorageError::DuplicateKey));
+
+        // As does the same row written again, the key would no longer be unique
+        let result = table.atomic_write::<_, StorageError>(|writer| {
+            writer.write_tuple(&table, &a, LogicalTimestamp::new(20), 1)
+        });
+        assert_eq!(result, Err(StorageError::DuplicateKey));
+        let c = vec![
+            Datum::from(4),
+            Datum::from("c".to_string()),
+            Datum::from(30),
+        ];
+        let result = table.atomic_write::<_, StorageError>(|writer| {
+            writer.write_tuple(&table, &c, LogicalTimestamp::new(20), 2)
+        });
+        assert_eq!(result, Err(StorageError::DuplicateKey));
+
+        // Once the old row's deleted the key's free again
+        table.atomic_write::<_, StorageError>(|writer| {
+            writer.write_tuple(&table, &b, LogicalTimestamp::new(20), -1)?;
+            writer.write_tuple(&table, &b2, LogicalTimestamp::new(20), 1)?;
+            Ok(())
+        })?;
+
+        let mut iter = table.full_scan(LogicalTimestamp::new(25));
+        assert_eq!(iter.next()?, Some((b2.as_ref(), 1)));
+        assert_eq!(iter.next()?, Some((a.as_ref(), 1)));
+        assert_eq!(iter.next()?, None);
+
+        // The key's row changed even though its freq didn't
+        let mut iter = table.changes_scan(LogicalTimestamp::new(15), LogicalTimestamp::new(25));
+        assert_eq!(iter.next()?, Some((b2.as_ref(), 1)));
+        assert_eq!(iter.next()?, Some((b.as_ref(), -1)));
+        assert_eq!(iter.next()?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_indexes() -> Result<(), StorageError> {
+        let storage = Storage::new_in_mem()?;
+        // Indexed on the second column
+        let index = storage
+            .table(1236, 2, vec![SortOrder::Asc; 2])
+            .with_primary_key(&[1, 0]);
+        let table = storage
+            .table(1234, 2, vec![SortOrder::Asc; 2])
+            .with_indexes(vec![index.clone()]);
+        let a = vec![Datum::from(1), Datum::from(20)];
+        let b = vec![Datum::from(2), Datum::from(10)];
+
+        table.atomic_write::<_, StorageError>(|writer| {
+            writer.write_tuple(&table, &a, LogicalTimestamp::new(10), 1)?;
+            writer.write_tuple(&table, &b, LogicalTimestamp::new(10), 1)?;
+            writer.write_tuple(&table, &b, LogicalTimestamp::new(10), -1)?;
+            writer.write_tuple(&table, &b, LogicalTimestamp::new(10), 1)?;
+            Ok(())
+        })?;
+
+        let mut iter = index.full_scan(LogicalTimestamp::new(15));
+        assert_eq!(iter.next()?, Some((b.as_ref(), 1)));
+        assert_eq!(iter.next()?, Some((a.as_ref(), 1)));
+        assert_eq!(iter.next()?, None);
+
+        let from = [Datum::from(20)];
+        let mut iter = index.range_scan(Some(&from), Some(&from), LogicalTimestamp::new(15));
+        assert_eq!(iter.next()?, Some((a.as_ref(), 1)));
+        assert_eq!(iter.next()?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sampled_range_scan() -> Result<(), StorageError> {
+        let storage = Storage::new_in_mem()?;
+        let table = storage.table(1234, 1, vec![SortOrder::Asc]);
+        table.atomic_write::<_, StorageError>(|writer| {
+            for i in 0..1000 {
+                writer.write_tuple(&table, &[Datum::from(i)], LogicalTimestamp::new(10), 1)?;
+            }
+            Ok(())
+        })?;
+
+        // Keep every other block, the blocks are each of 0..=148, 149..=255 then every 256
+        let blocks = std::cell::Cell::new(0);
+        let mut iter = table.sampled_range_scan(
+            None,
+            None,
+            LogicalTimestamp::new(15),
+            Box::new(|| {
+                blocks.set(blocks.get() + 1);
+                blocks.get() % 2 == 1
+            }),
+        );
+        let mut kept = vec![];
+        while let Some((tuple, freq)) = iter.next()? {
+            assert_eq!(freq, 1);
+            kept.push(tuple[0].as_integer());
+        }
+        let expected: Vec<_> = (0..149)
+            .step_by(2)
+            .chain(256..512)
+            .chain(768..1000)
+            .collect();
+        assert_eq!(kept, expected);
+        assert_eq!(blocks.get(), 153);
+        Ok(())
+    }
+
+    #[test]
+    fn test_checksums() -> Result<(), StorageError> {
+        let storage = Storage::new_in_mem()?;
+        storage.set_quarantine_table(100);
+        let table = storage
+            .table(1234, 2, vec![SortOrder::Asc])
+            .with_checksums();
+        let tuple1 = vec![Datum::from(1), Datum::from("abc".to_string())];
+        let tuple2 = vec![Datum::from(2), Datum::from("efg".to_string())];
+
+        table.atomic_write::<_, StorageError>(|writer| {
+            writer.write_tuple(&table, &tuple1, LogicalTimestamp::new(10), 1)?;
+            writer.write_tuple(&table, &tuple1, LogicalTimestamp::new(20), 1)?;
+            writer.write_tuple(&table, &tuple2, LogicalTimestamp::new(10), 1)?;
+            Ok(())
+        })?;
+
+        let mut iter = table.full_scan(LogicalTimestamp::new(15));
+        assert_eq!(iter.next()?, Some((tuple1.as_ref(), 1)));
+        assert_eq!(iter.next()?, Some((tuple2.as_ref(), 1)));
+        assert_eq!(iter.next()?, None);
+
+        // Flip a bit in the first row's latest value
+        let mut key = vec![];
+        write_index_header_key(&table, &tuple1, &mut key);
+        let mut value = table.engine.get(&key)?.unwrap();
+        value[9] ^= 1;
+        let mut batch = WriteBatch::default();
+        batch.put(&key, &value);
+        table.engine.write(batch)?;
+
+        let mut iter = table.full_scan(LogicalTimestamp::MAX);
+        assert_eq!(iter.next()?, Some((tuple2.as_ref(), 1)));
+        assert_eq!(iter.next()?, None);
+
+        // It's been moved out into the quarantine table
+        let quarantine = storage.table(100, 4, vec![SortOrder::Asc, SortOrder::Asc]);
+        let mut iter = quarantine.full_scan(LogicalTimestamp::MAX);
+        let (row, _freq) = iter.next()?.unwrap();
+        assert_eq!(row[0], Datum::from(1234_i64));
+        assert_eq!(row[1], Datum::from(key.clone()));
+        assert_eq!(row[2], Datum::from(value));
+        assert_eq!(iter.next()?, None);
+
+        // and the row can be written again
+        let mut key_buf = vec![];
+        let mut rest = vec![];
+        assert_eq!(
+            table.system_point_lookup(&tuple1, &mut key_buf, &mut rest)?,
+            None
+        );
+        table.atomic_write::<_, StorageError>(|writer| {
+            writer.write_tuple(&table, &tuple1, LogicalTimestamp::new(30), 1)
+        })?;
+        assert_eq!(
+            table.system_point_lookup(&tuple1, &mut key_buf, &mut rest)?,
+            Some(1)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_null_bitmap() -> Result<(), StorageError> {
+        let storage = Storage::new_in_mem()?;
+        let table = storage
+            .table(1234, 2, vec![SortOrder::Asc, SortOrder::Desc])
+            .with_null_bitmap();
+        let system_table = storage
+            .table(1236, 3, vec![SortOrder::Asc])
+            .with_null_bitmap();
+        let tuple1 = vec![Datum::Null, Datum::from(1)];
+        let tuple2 = vec![Datum::from(2), Datum::Null];
+        let tuple3 = vec![Datum::Null, Datum::Null];
+        let system_tuple = vec![Datum::from(1), Datum::Null, Datum::from("abc".to_string())];
+
+        table.atomic_write::<_, StorageError>(|writer| {
+            writer.write_tuple(&table, &tuple1, LogicalTimestamp::new(10), 1)?;
+            writer.write_tuple(&table, &tuple1, LogicalTimestamp::new(20), 1)?;
+            writer.write_tuple(&table, &tuple2, LogicalTimestamp::new(10), 2)?;
+            writer.write_tuple(&table, &tuple3, LogicalTimestamp::new(10), 1)?;
+            writer.write_tuple(&system_table, &system_tuple, LogicalTimestamp::new(10), 1)?;
+            Ok(())
+        })?;
+
+        // The keys don't sort by the columns, but each row still comes back once
+        let mut iter = table.full_scan(LogicalTimestamp::new(15));
+        let mut rows = vec![];
+        while let Some((tuple, freq)) = iter.next()? {
+            rows.push((tuple.iter().map(Datum::as_static).collect::<Vec<_>>(), freq));
+        }
+        assert_eq!(rows.len(), 3);
+        assert!(rows.contains(&(tuple1.clone(), 1)));
+        assert!(rows.contains(&(tuple2.clone(), 2)));
+        assert!(rows.contains(&(tuple3.clone(), 1)));
+
+        let mut iter = table.full_scan(LogicalTimestamp::MAX);
+        let mut freq = 0;
+        while let Some((tuple, f)) = iter.next()? {
+            if tuple == tuple1.as_slice() {
+                freq = f;
+            }
+        }
+        assert_eq!(freq, 2);
+
+        let mut key_buf = vec![];
+        let mut rest = vec![];
+        assert_eq!(
+            system_table.system_point_lookup(&system_tuple[..1], &mut key_buf, &mut rest)?,
+            Some(1)
+        );
+        assert_eq!(rest, &system_tuple[1..]);
+        Ok(())
+    }
+
     #[test]
     fn test_right_size_new_to() {
         let to: Vec<bool> = right_size_new_to(5);
@@ -0,0 +1,134 @@
+use crate::engine::{OverlayEngine, StorageEngine};
+use crate::{StorageError, Table};
+use data::Datum;
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+/// Writes made between BEGIN and COMMIT. The tables of a transaction (see Transaction::table)
+/// write to an overlay of the storage engine rather than the engine itself, so reads made in the
+/// transaction see its writes while no one else does. The rows written are logged too so COMMIT
+/// can write them all to the real tables in one batch at one timestamp. Dropping the transaction
+/// without committing it rolls it back.
+#[derive(Default)]
+pub struct Transaction {
+    // Set up from the first table used in the transaction
+    state: Mutex<Option<Arc<TransactionState>>>,
+}
+
+pub(crate) struct TransactionState {
+    pub(crate) base: Arc<dyn StorageEngine>,
+    pub(crate) overlay: Arc<dyn StorageEngine>,
+    pub(crate) writes: Mutex<Vec<TransactionWrite>>,
+}
+
+/// A row written in a transaction, the table is the transaction's
+pub(crate) struct TransactionWrite {
+    pub(crate) table: Table,
+    pub(crate) tuple: Vec<Datum<'static>>,
+    pub(crate) freq: i64,
+    // Written with system_write_tuple
+    pub(crate) system: bool,
+}
+
+impl Debug for Transaction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Transaction")
+    }
+}
+
+impl Transaction {
+    /// The table as seen from within the transaction, writes to it are made in the transaction
+    /// and reads see them.
+    pub fn table(&self, table: &Table) -> Table {
+        let mut state = self.state.lock().unwrap();
+        let state = state.get_or_insert_with(|| {
+            let base = Arc::clone(table.engine());
+            Arc::new(TransactionState {
+                overlay: Arc::new(OverlayEngine::new(Arc::clone(&base))),
+                base,
+                writes: Mutex::default(),
+            })
+        });
+        table.in_transaction(state)
+    }
+
+    /// Writes everything written in the transaction to the real tables as one batch, the rows
+    /// all get the same timestamp.
+    pub fn commit(self) -> Result<(), StorageError> {
+        let state = match self.state.into_inner().unwrap() {
+            Some(state) => state,
+            None => return Ok(()),
+        };
+        let writes = std::mem::take(&mut *state.writes.lock().unwrap());
+        let mut tables: HashMap<u32, Table> = HashMap::new();
+        for write in &writes {
+            tables
+                .entry(write.table.id())
+                .or_insert_with(|| write.table.outside_transaction(&state.base));
+        }
+        let first = match writes.first() {
+            Some(write) => &tables[&write.table.id()],
+            None => return Ok(()),
+        };
+        let timestamp = first.clock().now();
+        first.atomic_write::<_, StorageError>(|batch| {
+            for write in &writes {
+                let table = &tables[&write.table.id()];
+                if write.system {
+                    batch.system_write_tuple(table, &write.tuple, write.freq);
+                } else {
+                    batch.write_tuple(table, &write.tuple, timestamp, write.freq)?;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Storage;
+    use data::{LogicalTimestamp, SortOrder, TupleIter};
+
+    fn rows(table: &Table) -> Result<Vec<(i32, i64)>, StorageError> {
+        let mut iter = table.full_scan(LogicalTimestamp::MAX);
+        let mut rows = vec![];
+        while let Some((tuple, freq)) = iter.next()? {
+            rows.push((tuple[0].as_integer(), freq));
+        }
+        Ok(rows)
+    }
+
+    fn write(table: &Table, value: i32, freq: i64) -> Result<(), StorageError> {
+        table.atomic_write(|batch| {
+            batch.write_tuple(table, &[Datum::from(value)], table.clock().now(), freq)
+        })
+    }
+
+    #[test]
+    fn test_transaction() -> Result<(), StorageError> {
+        let storage = Storage::new_in_mem()?;
+        let table = storage.table(2, 1, vec![SortOrder::Asc]);
+        write(&table, 1, 1)?;
+
+        let transaction = Transaction::default();
+        let in_transaction = transaction.table(&table);
+        write(&in_transaction, 2, 1)?;
+        write(&in_transaction, 1, -1)?;
+        // Only the transaction sees its writes
+        assert_eq!(rows(&in_transaction)?, vec![(2, 1)]);
+        assert_eq!(rows(&table)?, vec![(1, 1)]);
+
+        transaction.commit()?;
+        assert_eq!(rows(&table)?, vec![(2, 1)]);
+
+        // Rolled back
+        let transaction = Transaction::default();
+        write(&transaction.table(&table), 3, 1)?;
+        drop(transaction);
+        assert_eq!(rows(&table)?, vec![(2, 1)]);
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+use data::LogicalTimestamp;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// How long the old versions of rows are kept by default
+pub const DEFAULT_VACUUM_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How often old versions are vacuumed away in the background
+pub const VACUUM_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Rows are written as new versions rather than in place, the old versions are kept for the
+/// retention window so reads as of a time within it (AS OF, incremental view maintenance, sinks)
+/// see the rows as they were then. Vacuuming removes the versions that have fallen out of the
+/// window (see Table::vacuum), reads as of before the watermark vacuumed to may find rows missing.
+#[derive(Debug)]
+pub struct Vacuum {
+    retention_ms: AtomicU64,
+    // The latest watermark the tables have been vacuumed to
+    vacuumed_to: AtomicU64,
+}
+
+impl Default for Vacuum {
+    fn default() -> Self {
+        Vacuum {
+            retention_ms: AtomicU64::new(DEFAULT_VACUUM_RETENTION.as_millis() as u64),
+            vacuumed_to: AtomicU64::default(),
+        }
+    }
+}
+
+impl Vacuum {
+    /// How long old versions of rows are kept for
+    pub fn retention(&self) -> Duration {
+        Duration::from_millis(self.retention_ms.load(Ordering::Relaxed))
+    }
+
+    /// Changes how long old versions are kept for, it takes effect from the next vacuum
+    pub fn set_retention(&self, retention: Duration) {
+        self.retention_ms
+            .store(retention.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// The watermark to vacuum to at now, the start of the retention window. It's held back to
+    /// the oldest timestamp changes still have to be read from, if there is one, so incremental
+    /// views and sinks that have fallen behind can catch up.
+    pub fn watermark(
+        &self,
+        now: LogicalTimestamp,
+        oldest_needed: Option<LogicalTimestamp>,
+    ) -> LogicalTimestamp {
+        let ms = now
+            .ms
+            .saturating_sub(self.retention_ms.load(Ordering::Relaxed));
+        LogicalTimestamp::new(oldest_needed.map_or(ms, |needed| ms.min(needed.ms)))
+    }
+
+    /// Records that the tables have been vacuumed to the watermark
+    pub fn vacuumed(&self, watermark: LogicalTimestamp) {
+        self.vacuumed_to.fetch_max(watermark.ms, Ordering::Relaxed);
+    }
+
+    /// The latest watermark the tables have been vacuumed to
+    pub fn vacuumed_to(&self) -> LogicalTimestamp {
+        LogicalTimestamp::new(self.vacuumed_to.load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watermark() {
+        let vacuum = Vacuum::default();
+        vacuum.set_retention(Duration::from_millis(100));
+        let now = LogicalTimestamp::new(1000);
+        assert_eq!(vacuum.watermark(now, None), LogicalTimestamp::new(900));
+        assert_eq!(
+            vacuum.watermark(now, Some(LogicalTimestamp::new(500))),
+            LogicalTimestamp::new(500)
+        );
+        assert_eq!(
+            vacuum.watermark(LogicalTimestamp::new(10), None),
+            LogicalTimestamp::new(0)
+        );
+
+        // Never moves backwards
+        vacuum.vacuumed(LogicalTimestamp::new(900));
+        vacuum.vacuumed(LogicalTimestamp::new(500));
+        assert_eq!(vacuum.vacuumed_to(), LogicalTimestamp::new(900));
+    }
+}
@@ -0,0 +1,118 @@
+use data::{Clock, LogicalTimestamp};
+use std::collections::BTreeMap;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// Keeps track of the writes that are yet to be committed, keyed by the time they started.
+/// Rows are written with timestamps taken as they're written so a write that started at t can
+/// still land rows at or after t, until it commits reads at t or later might miss some of them.
+#[derive(Debug, Default)]
+pub(crate) struct WriteTracker {
+    // Where write start times and read barriers are taken from
+    clock: Clock,
+    // Start ms -> number of writes started in that ms
+    writes: Mutex<BTreeMap<u64, usize>>,
+    finished: Condvar,
+}
+
+/// Marks the write as finished when dropped.
+pub(crate) struct WriteGuard<'a> {
+    tracker: &'a WriteTracker,
+    start: u64,
+}
+
+impl Drop for WriteGuard<'_> {
+    fn drop(&mut self) {
+        let mut writes = self.tracker.writes.lock().unwrap();
+        if let Some(count) = writes.get_mut(&self.start) {
+            *count -= 1;
+            if *count == 0 {
+                writes.remove(&self.start);
+            }
+        }
+        self.tracker.finished.notify_all();
+    }
+}
+
+impl WriteTracker {
+    pub(crate) fn new(clock: Clock) -> Self {
+        WriteTracker {
+            clock,
+            writes: Mutex::default(),
+            finished: Condvar::default(),
+        }
+    }
+
+    pub(crate) fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    pub(crate) fn start_write(&self) -> WriteGuard<'_> {
+        let start = self.clock.now().ms;
+        *self.writes.lock().unwrap().entry(start).or_insert(0) += 1;
+        WriteGuard {
+            tracker: self,
+            start,
+        }
+    }
+
+    /// Returns the latest timestamp that every write is known to be committed up to, ie the
+    /// previous ms unless there's writes from before then still in flight. We'll wait up to
+    /// wait for those to finish before settling for a timestamp from before they started.
+    pub(crate) fn read_barrier(&self, wait: Duration) -> LogicalTimestamp {
+        let target = self.clock.now().ms.saturating_sub(1);
+        let deadline = Instant::now() + wait;
+        let mut writes = self.writes.lock().unwrap();
+        loop {
+            match writes.keys().next() {
+                Some(start) if *start <= target => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return LogicalTimestamp::new(start.saturating_sub(1));
+                    }
+                    writes = self
+                        .finished
+                        .wait_timeout(writes, deadline - now)
+                        .unwrap()
+                        .0;
+                }
+                _ => return LogicalTimestamp::new(target),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_barrier() {
+        let tracker = WriteTracker::default();
+        let before = LogicalTimestamp::now().ms;
+        assert!(tracker.read_barrier(Duration::from_millis(0)).ms >= before - 1);
+
+        // A write that doesn't finish in time holds the barrier back to before it started
+        let guard = tracker.start_write();
+        std::thread::sleep(Duration::from_millis(5));
+        let barrier = tracker.read_barrier(Duration::from_millis(1));
+        assert_eq!(barrier.ms, guard.start - 1);
+
+        drop(guard);
+        assert!(tracker.read_barrier(Duration::from_millis(0)).ms > barrier.ms);
+    }
+
+    #[test]
+    fn test_read_barrier_virtual_clock() {
+        let clock = Clock::new_virtual(100);
+        let tracker = WriteTracker::new(clock.clone());
+        assert_eq!(tracker.read_barrier(Duration::from_millis(0)).ms, 99);
+
+        let guard = tracker.start_write();
+        clock.advance(10);
+        assert_eq!(tracker.read_barrier(Duration::from_millis(0)).ms, 99);
+
+        drop(guard);
+        assert_eq!(tracker.read_barrier(Duration::from_millis(0)).ms, 109);
+    }
+}
@@ -0,0 +1,115 @@
+use crate::runner::*;
+
+#[test]
+fn as_of_timestamp() {
+    with_virtual_clock(1_000_000, |clock, connection| {
+        connection.query(r#"CREATE TABLE orders (id INT)"#, "");
+        connection.query(r#"INSERT INTO orders VALUES (1)"#, "");
+        clock.advance(1000);
+        connection.query(r#"DELETE FROM orders WHERE id = 1"#, "");
+        connection.query(r#"INSERT INTO orders VALUES (2)"#, "");
+        connection.query(r#"CREATE VIEW order_ids AS SELECT id FROM orders"#, "");
+
+        connection.query(r#"SELECT id FROM orders"#, "|2|");
+        connection.query(
+            r#"SELECT id FROM orders AS OF TIMESTAMP '1970-01-01 00:16:40'"#,
+            "|1|",
+        );
+        connection.query(
+            r#"SELECT id FROM orders AS OF TIMESTAMP '1970-01-01 00:16:39.999'"#,
+            "",
+        );
+        connection.query(
+            r#"SELECT o.id FROM orders AS OF TIMESTAMP '1970-01-01 00:16:40' o"#,
+            "|1|",
+        );
+        // The tables in a view are read as of the timestamp too
+        connection.query(
+            r#"SELECT id FROM order_ids AS OF TIMESTAMP '1970-01-01 00:16:40'"#,
+            "|1|",
+        );
+        // Then and now side by side
+        connection.query(
+            r#"SELECT old.id, cur.id FROM orders AS OF TIMESTAMP '1970-01-01 00:16:40' old JOIN orders cur ON old.id + 1 = cur.id"#,
+            "|1|2|",
+        );
+        // Restoring the deleted row from history
+        connection.query(
+            r#"INSERT INTO orders SELECT id FROM orders AS OF TIMESTAMP '1970-01-01 00:16:40'"#,
+            "",
+        );
+        connection.query(
+            r#"SELECT id FROM orders ORDER BY id"#,
+            "
+            |1|
+            |2|
+            ",
+        );
+    });
+}
+
+#[test]
+fn as_of_session_variable() {
+    with_virtual_clock(1_000_000, |clock, connection| {
+        connection.query(r#"CREATE TABLE orders (id INT)"#, "");
+        connection.query(r#"INSERT INTO orders VALUES (1)"#, "");
+        clock.advance(1000);
+        connection.query(r#"INSERT INTO orders VALUES (2)"#, "");
+
+        connection.query(r#"SET as_of = '1970-01-01 00:16:40'"#, "");
+        connection.query(r#"SELECT id FROM orders"#, "|1|");
+        assert!(matches!(
+            connection.execute_statement("INSERT INTO orders VALUES (3)"),
+            // ER_CANT_EXECUTE_IN_READ_ONLY_TRANSACTION
+            Err(err) if err.error_code().code == 1792
+        ));
+        assert!(connection
+            .execute_statement("SET as_of = 'yesterday'")
+            .is_err());
+
+        connection.query(r#"SET as_of = DEFAULT"#, "");
+        connection.query(
+            r#"SELECT id FROM orders ORDER BY id"#,
+            "
+            |1|
+            |2|
+            ",
+        );
+    });
+}
+
+#[test]
+fn as_of_after_vacuum() {
+    with_virtual_clock(1_000_000, |clock, connection| {
+        connection.query(r#"CREATE TABLE orders (id INT)"#, "");
+        connection.query(r#"INSERT INTO orders VALUES (1)"#, "");
+        clock.advance(1000);
+        connection.query(r#"DELETE FROM orders WHERE id = 1"#, "");
+        connection.query(r#"INSERT INTO orders VALUES (2)"#, "");
+        // Past the default retention of a day
+        clock.advance(25 * 60 * 60 * 1000);
+        connection.query(r#"INSERT INTO orders VALUES (3)"#, "");
+        connection.query(
+            r#"SELECT id FROM orders AS OF TIMESTAMP '1970-01-01 00:16:40'"#,
+            "|1|",
+        );
+
+        connection.query(r#"VACUUM"#, "");
+        connection.query(
+            r#"SELECT id FROM orders AS OF TIMESTAMP '1970-01-01 00:16:40'"#,
+            "",
+        );
+        // Within the retention window
+        connection.query(
+            r#"SELECT id FROM orders AS OF TIMESTAMP '1970-01-02 01:16:40'"#,
+            "|2|",
+        );
+        connection.query(
+            r#"SELECT id FROM orders ORDER BY id"#,
+            "
+            |2|
+            |3|
+            ",
+        );
+    });
+}
@@ -0,0 +1,27 @@
+use crate::runner::*;
+
+#[test]
+fn bitwise_operators() {
+    with_connection(|connection| {
+        connection.query(
+            r#"SELECT 12 & 10, 12 | 10, 12 ^ 10, ~0"#,
+            "\
+        |8|14|6|-1|
+        ",
+        );
+
+        connection.query(
+            r#"SELECT 1 << 4, -16 >> 2, cast(1 as bigint) << 40, 1 | 2 << 2"#,
+            "\
+        |16|-4|1099511627776|9|
+        ",
+        );
+
+        connection.query(
+            r#"SELECT 1 & null, ~null"#,
+            "\
+        |NULL|NULL|
+        ",
+        );
+    });
+}
@@ -0,0 +1,13 @@
+use crate::runner::{with_connection, TestQuery};
+
+#[test]
+fn select_in_list() {
+    with_connection(|connection| {
+        connection.query(
+            r#"SELECT 1 in (1, 2), 3 in (1, 2), 3 not in (1, 2), 3 in (1, null)"#,
+            "
+        |TRUE|FALSE|TRUE|NULL|
+        ",
+        );
+    });
+}
@@ -1,4 +1,4 @@
-use crate::runner::query;
+use crate::runner::*;
 
 #[test]
 fn select_limit_no_offset() {
@@ -65,3 +65,37 @@ fn select_limit_with_offset() {
         ",
     );
 }
+
+#[test]
+fn sql_select_limit() {
+    with_connection(|connection| {
+        connection.query(r#"SET sql_select_limit = 2"#, "");
+        connection.query(
+            r#"SELECT 1 UNION ALL SELECT 2 UNION ALL SELECT 3"#,
+            "
+        |1|
+        |2|
+        ",
+        );
+
+        // An explicit limit wins
+        connection.query(
+            r#"SELECT foo FROM (SELECT 1 as foo UNION ALL SELECT 2 UNION ALL SELECT 3) LIMIT 3"#,
+            "
+        |1|
+        |2|
+        |3|
+        ",
+        );
+
+        connection.query(r#"SET sql_select_limit = DEFAULT"#, "");
+        connection.query(
+            r#"SELECT 1 UNION ALL SELECT 2 UNION ALL SELECT 3"#,
+            "
+        |1|
+        |2|
+        |3|
+        ",
+        );
+    });
+}
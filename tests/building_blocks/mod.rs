@@ -1,11 +1,16 @@
 mod aliases;
+mod as_of;
 mod between;
+mod bitwise;
 mod boolean_logic;
 mod eq_ne;
+mod in_list;
 mod limit;
 mod literals;
 mod order_by;
 mod predicates;
 mod star;
 mod tables;
+mod tablesample;
 mod unions;
+mod user_variables;
@@ -41,3 +41,46 @@ fn select_order_by() {
         );
     });
 }
+
+#[test]
+fn deterministic_order() {
+    with_connection(|connection| {
+        connection.query(r#"SET deterministic_order = ON"#, "");
+        connection.query(r#"Create table test (c1 INT, c2 TEXT)"#, "");
+        connection.query(
+            r#"INSERT INTO test VALUES (3, "c"), (1, "b"), (2, "a"), (1, "a"), (3, "a")"#,
+            "",
+        );
+
+        // Hash grouping comes back in any order without the flag
+        connection.query(
+            r#"SELECT c1, count(*) FROM test GROUP BY c1"#,
+            "
+            |1|2|
+            |2|1|
+            |3|2|
+        ",
+        );
+
+        // Ties are broken using the rest of the row
+        connection.query(
+            r#"SELECT c2, c1 FROM test ORDER BY c2 DESC"#,
+            "
+            |c|3|
+            |b|1|
+            |a|1|
+            |a|2|
+            |a|3|
+        ",
+        );
+
+        // The order is applied before the limit
+        connection.query(
+            r#"SELECT c1, c2 FROM test LIMIT 1, 2"#,
+            "
+            |1|b|
+            |2|a|
+        ",
+        );
+    });
+}
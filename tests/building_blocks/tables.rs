@@ -1,4 +1,4 @@
-use crate::runner::query;
+use crate::runner::*;
 
 #[test]
 fn select_from_table() {
@@ -38,3 +38,49 @@ fn select_from_table() {
         ",
     );
 }
+
+#[test]
+fn read_barrier() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE orders (id INT, customer_id INT)"#, "");
+        connection.query(r#"CREATE TABLE customers (id INT, name TEXT)"#, "");
+        connection.query(r#"INSERT INTO customers VALUES (1, "alice")"#, "");
+        connection.query(r#"INSERT INTO orders VALUES (10, 1)"#, "");
+        connection.query(r#"SET read_barrier = ON"#, "");
+        // Reads just behind the current time so give the writes a chance to settle
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        connection.query(
+            r#"SELECT orders.id, customers.name FROM orders JOIN customers ON orders.customer_id = customers.id"#,
+            "
+            |10|alice|
+            ",
+        );
+    });
+}
+
+#[test]
+fn read_barrier_virtual_clock() {
+    with_virtual_clock(1_000_000, |clock, connection| {
+        connection.query(r#"CREATE TABLE orders (id INT)"#, "");
+        connection.query(r#"SET read_barrier = ON"#, "");
+        connection.query(r#"INSERT INTO orders VALUES (10)"#, "");
+        // Reads are made before the previous ms so the row's hidden until the clock's moved on
+        // past the ms after the one it was written in
+        connection.query(r#"SELECT id FROM orders"#, "");
+        clock.advance(1);
+        connection.query(r#"SELECT id FROM orders"#, "");
+        clock.advance(1);
+        connection.query(
+            r#"SELECT id FROM orders"#,
+            "
+            |10|
+            ",
+        );
+        connection.query(
+            r#"SELECT now()"#,
+            "
+            |1970-01-01 00:16:40.002|
+            ",
+        );
+    });
+}
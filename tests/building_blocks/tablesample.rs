@@ -0,0 +1,46 @@
+use crate::runner::*;
+
+#[test]
+fn tablesample() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE digits (x INT)"#, "");
+        connection.query(
+            r#"INSERT INTO digits VALUES (0), (1), (2), (3), (4), (5), (6), (7), (8), (9)"#,
+            "",
+        );
+        connection.query(r#"CREATE TABLE numbers (x INT)"#, "");
+        connection.query(
+            r#"INSERT INTO numbers SELECT a.x * 100 + b.x * 10 + c.x FROM digits a, digits b, digits c"#,
+            "",
+        );
+
+        connection.query(
+            r#"SELECT count(*) FROM numbers TABLESAMPLE BERNOULLI(100)"#,
+            "
+        |1000|
+        ",
+        );
+
+        connection.query(
+            r#"SELECT count(*) FROM numbers TABLESAMPLE SYSTEM(100)"#,
+            "
+        |1000|
+        ",
+        );
+
+        connection.query(
+            r#"SELECT count(*) FROM numbers n TABLESAMPLE SYSTEM(0) WHERE n.x > 10"#,
+            "
+        |0|
+        ",
+        );
+
+        // A seed makes the sample repeatable
+        connection.query(
+            r#"SELECT count(*) FROM numbers TABLESAMPLE BERNOULLI(10) REPEATABLE(7)"#,
+            "
+        |101|
+        ",
+        );
+    });
+}
@@ -0,0 +1,42 @@
+use crate::runner::*;
+
+#[test]
+fn user_variables() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE t(id INT, name TEXT)"#, "");
+        connection.query(r#"INSERT INTO t VALUES (1, "a"), (2, "b"), (3, "c")"#, "");
+
+        connection.query(r#"SET @total = (SELECT sum(id) FROM t)"#, "");
+        connection.query(r#"SET @Prefix = "name: ""#, "");
+
+        connection.query(
+            r#"SELECT @prefix, name, type_of(@total) FROM t WHERE id * 2 = @total"#,
+            "\
+        |name: |c|INTEGER|
+        ",
+        );
+
+        connection.query(
+            r#"SELECT @unset, @total + 1, @@socket"#,
+            "\
+        |NULL|7||
+        ",
+        );
+
+        // The value is fixed when it's set
+        connection.query(r#"INSERT INTO t VALUES (4, "d")"#, "");
+        connection.query(
+            r#"SELECT @total"#,
+            "\
+        |6|
+        ",
+        );
+
+        assert!(connection
+            .execute_statement(r#"SET @x = (SELECT id FROM t)"#)
+            .is_err());
+        assert!(connection
+            .execute_statement(r#"SET @x = (SELECT id, name FROM t WHERE id = 1)"#)
+            .is_err());
+    });
+}
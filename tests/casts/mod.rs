@@ -19,6 +19,54 @@ fn test_to_from_date() {
     });
 }
 
+#[test]
+fn test_date_ranges() {
+    with_connection(|connection| {
+        connection.query(r#"create table events(name text, happened date)"#, "");
+        connection.query(
+            r#"insert into events values ("ides", date "-0044-03-15"), ("moon", date "1969-07-20"), ("far", date "+12020-01-01")"#,
+            "",
+        );
+        connection.query(
+            r#"select name, happened from events order by happened"#,
+            "
+        |ides|-0044-03-15|
+        |moon|1969-07-20|
+        |far|+12020-01-01|
+        ",
+        );
+        connection.query(
+            r#"select cast("-0044-03-15 12:30:00" as timestamp)"#,
+            "
+        |-0044-03-15 12:30:00|
+        ",
+        );
+
+        connection.query(r#"select cast("2021-02-29" as date)"#, "|NULL|");
+        connection.query(
+            r#"SHOW WARNINGS"#,
+            r#"
+        |Warning|1292|Cannot cast "2021-02-29" to DATE, day 29 doesn't exist in 2021-02|
+        "#,
+        );
+        connection.query(
+            r#"select cast("2021-13-01 10:00:00" as timestamp)"#,
+            "|NULL|",
+        );
+        connection.query(
+            r#"SHOW WARNINGS"#,
+            r#"
+        |Warning|1292|Cannot cast "2021-13-01 10:00:00" to TIMESTAMP, month 13 doesn't exist|
+        "#,
+        );
+
+        let (_, mut executor) = connection
+            .execute_statement(r#"select date_sub(happened, 200000000) from events"#)
+            .unwrap();
+        assert!(executor.next().is_err());
+    });
+}
+
 #[test]
 fn test_auto_cast() {
     with_connection(|connection| {
@@ -43,5 +91,193 @@ fn test_auto_cast() {
         | |   |  d|2|DECIMAL(10,2)||
         ",
         );
+
+        connection.query(
+            r#"EXPLAIN TYPES SELECT i + d, d * 2 from t where b > 1"#,
+            "
+        |PROJECT||||
+        | |output_exprs:||||
+        | |  _col1|0|DECIMAL(13,2)|`+`(to_decimal(<OFFSET 0>::INTEGER)::DECIMAL(10,0), <OFFSET 2>::DECIMAL(10,2))::DECIMAL(13,2)|
+        | |  _col2|1|DECIMAL(20,2)|`*`(<OFFSET 2>::DECIMAL(10,2), 2::DECIMAL(10,0))::DECIMAL(20,2)|
+        | |source:||||
+        | |  FILTER||||
+        | |   |predicate:||||
+        | |   |||BOOLEAN|`>`(<OFFSET 1>::BIGINT, 1::BIGINT)::BOOLEAN|
+        | |   |source:||||
+        | |   |  TABLE(t)||||
+        | |   |   |columns:||||
+        | |   |   |  i|0|INTEGER||
+        | |   |   |  b|1|BIGINT||
+        | |   |   |  d|2|DECIMAL(10,2)||
+        ",
+        );
+
+        connection.query(
+            r#"SELECT typeof(i + d), typeof(d * 2), typeof(sum(d)) from t"#,
+            "
+        |DECIMAL(13,2)|DECIMAL(20,2)|DECIMAL(28,2)|
+        ",
+        );
+    });
+}
+
+#[test]
+fn test_try_cast() {
+    with_connection(|connection| {
+        connection.query(
+            r#"select try_cast("abc" as int), try_to_int("12"), try_to_date("2010-13-45"), cast("abc" as int)"#,
+            "
+        |NULL|12|NULL|NULL|
+        ",
+        );
+        connection.query(
+            r#"SHOW WARNINGS"#,
+            r#"
+        |Warning|1292|Cannot cast "abc" to INTEGER|
+        "#,
+        );
+        connection.query(
+            r#"select cast(1.234 as decimal(10,2))"#,
+            "
+        |1.23|
+        ",
+        );
+        connection.query(
+            r#"SHOW WARNINGS"#,
+            "
+        |Note|1265|Data truncated, 1.234 rounded to 1.23|
+        ",
+        );
+
+        connection.query(r#"set strict_casts = on"#, "");
+
+        connection.query(
+            r#"select try_cast("abc" as int), cast(null as int), cast("12" as int)"#,
+            "
+        |NULL|NULL|12|
+        ",
+        );
+
+        let (_, mut executor) = connection
+            .execute_statement(r#"select cast("abc" as int)"#)
+            .unwrap();
+        assert!(executor.next().is_err());
+
+        connection.query(r#"create table raw_readings(id int, value text)"#, "");
+        connection.query(
+            r#"insert into raw_readings values (1, "5"), (2, "five")"#,
+            "",
+        );
+        connection.query(r#"create table readings(id int, value int)"#, "");
+        let (_, mut executor) = connection
+            .execute_statement(r#"insert into readings select id, to_int(value) from raw_readings"#)
+            .unwrap();
+        assert!(executor.next().is_err());
+
+        connection.query(
+            r#"insert into readings select id, try_to_int(value) from raw_readings"#,
+            "",
+        );
+        connection.query(
+            r#"select id, value from readings"#,
+            "
+        |1|5|
+        |2|NULL|
+        ",
+        );
+    });
+}
+
+#[test]
+fn test_decimal_overflow() {
+    with_connection(|connection| {
+        connection.query(r#"create table amounts(a decimal(5,2))"#, "");
+        connection.query(r#"insert into amounts values (999.99)"#, "");
+
+        let (_, mut executor) = connection
+            .execute_statement(r#"insert into amounts select a * 2 from amounts"#)
+            .unwrap();
+        assert!(executor.next().is_err());
+
+        connection.query(r#"set decimal_overflow = 'saturate'"#, "");
+        connection.query(r#"insert into amounts select a * 2 from amounts"#, "");
+        connection.query(
+            r#"SHOW WARNINGS"#,
+            "
+        |Warning|1264|Out of range value 1999.98 for DECIMAL(5,2), changed to 999.99|
+        ",
+        );
+
+        connection.query(r#"set decimal_overflow = 'rescale'"#, "");
+        // Both rows are 999.99 so they're stored as the one row with a freq of 2
+        connection.query(r#"insert into amounts select a - 2000 from amounts"#, "");
+        connection.query(
+            r#"SHOW WARNINGS"#,
+            "
+        |Warning|1264|Out of range value -1000.01 for DECIMAL(5,2), changed to -1000.0|
+        ",
+        );
+        connection.query(
+            r#"select count(*) from amounts where a < 0"#,
+            "
+        |2|
+        ",
+        );
+    });
+}
+
+#[test]
+fn test_invalid_utf8() {
+    with_connection(|connection| {
+        connection.query(r#"set names utf8mb4 collate utf8mb4_general_ci"#, "");
+        assert!(connection.execute_statement(r#"set names latin1"#).is_err());
+
+        connection.query(r#"set invalid_utf8 = 'replace'"#, "");
+        assert!(connection
+            .execute_statement(r#"set invalid_utf8 = 'ignore'"#)
+            .is_err());
+
+        connection.query(
+            r#"select cast(to_bytes("héllo") as text)"#,
+            "
+        |héllo|
+        ",
+        );
+    });
+}
+
+#[test]
+fn test_cast_matrix() {
+    with_connection(|connection| {
+        connection.query(
+            r#"select cast(cast("2010-10-23" as date) as timestamp), cast(cast("2010-10-23 10:15:00" as timestamp) as date)"#,
+            "
+        |2010-10-23 00:00:00|2010-10-23|
+        ",
+        );
+        assert!(connection
+            .execute_statement(r#"select cast(true as date)"#)
+            .is_err());
+
+        // Implicit casts line up the types of union alls and inserts
+        connection.query(
+            r#"select c from (select 1 as c union all select cast(2 as bigint)) t where c > 1"#,
+            "
+        |2|
+        ",
+        );
+        connection.query(r#"create table t(b bigint, d decimal(10, 2))"#, "");
+        connection.query(r#"insert into t values (1, 1)"#, "");
+        connection.query(r#"insert into t select 2, 2"#, "");
+        connection.query(
+            r#"select b from t"#,
+            "
+        |1|
+        |2|
+        ",
+        );
+        assert!(connection
+            .execute_statement(r#"insert into t select "3", 3"#)
+            .is_err());
     });
 }
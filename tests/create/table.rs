@@ -33,3 +33,189 @@ fn create_tables() {
         connection.query(r#"SELECT * FROM t1"#, "");
     });
 }
+
+#[test]
+fn create_checksummed_table() {
+    with_connection(|connection| {
+        connection.query(
+            r#"CREATE TABLE t2 (a INT, b TEXT) WITH (CHECKSUM = TRUE)"#,
+            "",
+        );
+        connection.query(r#"INSERT INTO t2 VALUES (1, "a"), (2, "b")"#, "");
+        connection.query(r#"DELETE FROM t2 WHERE a = 2"#, "");
+        connection.query(
+            r#"SELECT * FROM t2"#,
+            "
+                |1|a|
+            ",
+        );
+
+        connection.query(
+            r#"SELECT count(*) FROM incresql.tables t JOIN incresql.checksummed_tables c ON t.table_id = c.table_id WHERE t.name = "t2""#,
+            "|1|",
+        );
+        connection.query(r#"SELECT * FROM incresql.corrupted_rows"#, "");
+    });
+}
+
+#[test]
+fn create_null_bitmap_table() {
+    with_connection(|connection| {
+        connection.query(
+            r#"CREATE TABLE t3 (a INT, b TEXT, c INT, d INT) WITH (NULL_BITMAP = TRUE)"#,
+            "",
+        );
+        connection.query(
+            r#"INSERT INTO t3 VALUES (1, "a", null, null), (2, null, null, 4), (null, null, null, null)"#,
+            "",
+        );
+        connection.query(
+            r#"SELECT * FROM t3 WHERE a = 2"#,
+            "
+                |2|NULL|NULL|4|
+            ",
+        );
+        connection.query(r#"DELETE FROM t3 WHERE a = 2"#, "");
+        connection.query(
+            r#"SELECT * FROM t3 ORDER BY a"#,
+            "
+                |NULL|NULL|NULL|NULL|
+                |1|a|NULL|NULL|
+            ",
+        );
+
+        connection.query(
+            r#"SELECT count(*) FROM incresql.tables t JOIN incresql.null_bitmap_tables n ON t.table_id = n.table_id WHERE t.name = "t3""#,
+            "|1|",
+        );
+    });
+}
+
+#[test]
+fn create_table_with_primary_key() {
+    with_connection(|connection| {
+        connection.query(
+            r#"CREATE TABLE t4 (a INT, b TEXT, PRIMARY KEY (a DESC))"#,
+            "",
+        );
+        connection.query(r#"INSERT INTO t4 VALUES (1, "x"), (3, "z"), (2, "y")"#, "");
+        connection.query(
+            r#"SELECT * FROM t4"#,
+            "
+                |3|z|
+                |2|y|
+                |1|x|
+            ",
+        );
+        connection.query(
+            r#"SELECT * FROM t4 WHERE a = 2"#,
+            "
+                |2|y|
+            ",
+        );
+
+        // The key's already taken until its row is deleted, even by the same row
+        let (_, mut executor) = connection
+            .execute_statement(r#"INSERT INTO t4 VALUES (2, "w")"#)
+            .unwrap();
+        assert!(executor.next().is_err());
+        let (_, mut executor) = connection
+            .execute_statement(r#"INSERT INTO t4 VALUES (2, "y")"#)
+            .unwrap();
+        assert!(executor.next().is_err());
+        connection.query(r#"SELECT count(*) FROM t4 WHERE a = 2"#, "|1|");
+        connection.query(r#"DELETE FROM t4 WHERE a = 2"#, "");
+        connection.query(r#"INSERT INTO t4 VALUES (2, "w")"#, "");
+        connection.query(
+            r#"SELECT * FROM t4"#,
+            "
+                |3|z|
+                |2|w|
+                |1|x|
+            ",
+        );
+
+        // Keyed on a column that isn't the first
+        connection.query(
+            r#"CREATE TABLE t5 (a INT, b TEXT, c INT, PRIMARY KEY (b, c DESC))"#,
+            "",
+        );
+        connection.query(
+            r#"INSERT INTO t5 VALUES (1, "b", 1), (2, "a", 1), (3, "b", 2)"#,
+            "",
+        );
+        connection.query(
+            r#"SELECT * FROM t5"#,
+            "
+                |2|a|1|
+                |3|b|2|
+                |1|b|1|
+            ",
+        );
+        connection.query(
+            r#"SELECT a FROM t5 WHERE b = "b" AND c = 1"#,
+            "
+                |1|
+            ",
+        );
+
+        connection.query(
+            r#"SELECT t.name, p.columns FROM incresql.tables t JOIN incresql.primary_keys p ON t.table_id = p.table_id WHERE t.name IN ("t4", "t5") ORDER BY name"#,
+            "
+                |t4|[0]|
+                |t5|[1,2]|
+            ",
+        );
+    });
+}
+
+#[test]
+fn create_enum_table() {
+    with_connection(|connection| {
+        connection.query(
+            r#"CREATE TABLE orders (id INT, status ENUM('new', 'paid', 'void'))"#,
+            "",
+        );
+        connection.query(
+            r#"INSERT INTO orders VALUES (1, 'paid'), (2, 'new'), (3, null), (4, 'void')"#,
+            "",
+        );
+        assert!(connection
+            .execute_statement(r#"INSERT INTO orders VALUES (5, 'refunded')"#)
+            .is_err());
+
+        // Ordered by the labels' positions
+        connection.query(
+            r#"SELECT id, status FROM orders ORDER BY status, id"#,
+            "
+                |3|NULL|
+                |2|new|
+                |1|paid|
+                |4|void|
+            ",
+        );
+        connection.query(
+            r#"SELECT id FROM orders WHERE status = 'paid'"#,
+            "
+                |1|
+            ",
+        );
+        connection.query(
+            r#"SELECT id FROM orders WHERE status > CAST('new' AS ENUM('new', 'paid', 'void')) ORDER BY id"#,
+            "
+                |1|
+                |4|
+            ",
+        );
+        connection.query(
+            r#"SELECT CAST(status AS TEXT), type_of(status) FROM orders WHERE id = 4"#,
+            "
+                |void|ENUM('new','paid','void')|
+            ",
+        );
+        connection.query(
+            r#"SELECT CAST('refunded' AS ENUM('new', 'paid', 'void'))"#,
+            "|NULL|",
+        );
+    });
+}
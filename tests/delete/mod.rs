@@ -43,3 +43,23 @@ fn test_delete() {
         );
     });
 }
+
+#[test]
+fn test_delete_qualified_all() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE DATABASE other"#, "");
+        connection.query(r#"CREATE TABLE other.t1 (a INT, b TEXT)"#, "");
+        connection.query(r#"INSERT INTO other.t1 VALUES (1, "abc"), (2, "def")"#, "");
+
+        connection.query(r#"DELETE FROM other.t1 WHERE t1.a = 1"#, "");
+        connection.query(
+            r#"SELECT * FROM other.t1"#,
+            "
+            |2|def|
+        ",
+        );
+
+        connection.query(r#"DELETE FROM other.t1"#, "");
+        connection.query(r#"SELECT count(*) FROM other.t1"#, "|0|");
+    });
+}
@@ -0,0 +1,63 @@
+use crate::runner::*;
+use runtime::connection::Connection;
+use runtime::{ErrorCode, QueryError};
+
+/// Runs the statement through to the end and returns the code of the error it failed with
+fn error_code(connection: &Connection, sql: &str) -> ErrorCode {
+    let err = match connection.execute_statement(sql) {
+        Ok((_, mut executor)) => loop {
+            match executor.next() {
+                Ok(Some(_)) => {}
+                Ok(None) => panic!("{} didn't fail", sql),
+                Err(err) => break QueryError::from(err),
+            }
+        },
+        Err(err) => err,
+    };
+    err.error_code()
+}
+
+fn assert_error(connection: &Connection, sql: &str, code: u16, sql_state: &str) {
+    let error_code = error_code(connection, sql);
+    assert_eq!(
+        (error_code.code, error_code.sql_state),
+        (code, sql_state),
+        "{}",
+        sql
+    );
+}
+
+#[test]
+fn test_error_codes() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE t (a INT)"#, "");
+        connection.query(r#"INSERT INTO t VALUES (1), (2)"#, "");
+
+        assert_error(connection, "SELEC 1", 1064, "42000");
+        assert_error(connection, "SELECT * FROM nope", 1146, "42S02");
+        assert_error(connection, "CREATE TABLE t (a INT)", 1050, "42S01");
+        assert_error(connection, "SELECT * FROM nope.t", 1146, "42S02");
+        assert_error(connection, "DROP DATABASE nope", 1049, "42000");
+        assert_error(connection, "SELECT b FROM t", 1054, "42S22");
+        assert_error(connection, "SELECT nope(a) FROM t", 1305, "42000");
+        assert_error(connection, "INSERT INTO t VALUES (1, 2)", 1136, "21S01");
+        assert_error(connection, "SET nope = 1", 1193, "HY000");
+        assert_error(
+            connection,
+            "CREATE TABLE k (a INT, PRIMARY KEY (b))",
+            1054,
+            "42S22",
+        );
+        assert_error(
+            connection,
+            "CREATE TABLE k (a INT, PRIMARY KEY (a, a))",
+            1060,
+            "42S21",
+        );
+        // Errors raised while the query runs get codes too
+        assert_error(connection, "SELECT (SELECT a FROM t) FROM t", 1242, "21000");
+        connection.query(r#"CREATE TABLE k (a INT, b INT, PRIMARY KEY (a))"#, "");
+        connection.query(r#"INSERT INTO k VALUES (1, 1)"#, "");
+        assert_error(connection, "INSERT INTO k VALUES (1, 2)", 1062, "23000");
+    });
+}
@@ -0,0 +1,56 @@
+use crate::runner::*;
+
+#[test]
+fn test_events() {
+    with_virtual_clock(1_000_000, |clock, connection| {
+        connection.query(r#"CREATE TABLE ticks (n INT)"#, "");
+        connection.query(
+            r#"CREATE EVENT tick ON SCHEDULE EVERY '5 minutes' DO INSERT INTO ticks VALUES (1)"#,
+            "",
+        );
+        assert!(connection
+            .execute_statement("CREATE EVENT tick ON SCHEDULE EVERY '1 minute' DO SELECT 1")
+            .is_err());
+
+        // Nothing's due until the first five minutes are up
+        connection.runtime.run_due_events().unwrap();
+        connection.query(r#"SELECT count(*) FROM ticks"#, "|0|");
+
+        clock.advance(300_000);
+        connection.runtime.run_due_events().unwrap();
+        connection.runtime.run_due_events().unwrap();
+        connection.query(r#"SELECT count(*) FROM ticks"#, "|1|");
+
+        // Missed runs are only made up the once
+        clock.advance(1_000_000);
+        connection.runtime.run_due_events().unwrap();
+        connection.query(r#"SELECT count(*) FROM ticks"#, "|2|");
+        connection.query(
+            r#"SELECT name, every_ms, last_run_ms, last_error FROM incresql.scheduled_events"#,
+            "|tick|300000|2300000|NULL|",
+        );
+
+        connection.query(r#"DROP EVENT tick"#, "");
+        clock.advance(300_000);
+        connection.runtime.run_due_events().unwrap();
+        connection.query(r#"SELECT count(*) FROM ticks"#, "|2|");
+        assert!(connection.execute_statement("DROP EVENT tick").is_err());
+    });
+}
+
+#[test]
+fn test_failing_events() {
+    with_virtual_clock(1_000_000, |clock, connection| {
+        connection.query(r#"CREATE DATABASE jobs"#, "");
+        connection.query(
+            r#"CREATE EVENT jobs.cleanup ON SCHEDULE EVERY '1 hour' DO CALL expire_rows"#,
+            "",
+        );
+        clock.advance(3_600_000);
+        connection.runtime.run_due_events().unwrap();
+        connection.query(
+            r#"SELECT database, name, last_error FROM incresql.scheduled_events"#,
+            "|jobs|cleanup|Procedure jobs.expire_rows not found|",
+        );
+    });
+}
@@ -1,4 +1,7 @@
 use crate::runner::*;
+use runtime::QueryError;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
 
 #[test]
 fn test_select_from_csv_file() {
@@ -12,3 +15,397 @@ fn test_select_from_csv_file() {
         );
     });
 }
+
+#[test]
+fn test_infer_schema_json() {
+    with_connection(|connection| {
+        connection.query(
+            r#"infer schema from "test_data/json" with (format = "json")"#,
+            r#"
+        |name|TEXT|$.name|
+        |age|BIGINT|$.age|
+        |address_city|TEXT|$.address.city|
+        |address_postcode|TEXT|$.address.postcode|
+        |joined|TEXT|$.joined|
+        |tags|JSON|$.tags|
+        |score|DECIMAL(28,1)|$.score|
+        "#,
+        );
+    });
+}
+
+#[test]
+fn test_create_external_table_auto() {
+    with_connection(|connection| {
+        connection.query(
+            r#"create external table people from "test_data/json" with (format = "json") auto"#,
+            "",
+        );
+        connection.query(
+            r#"select name, age, address_city, joined from people"#,
+            r#"
+        |alice|34|Wanaka|2019-02-03|
+        |bob|3000000000|Dunedin|NULL|
+        |carol|NULL|Nelson|2020-11-30T10:15:00|
+        "#,
+        );
+    });
+}
+
+#[test]
+fn test_create_external_table_given_columns() {
+    with_connection(|connection| {
+        connection.query(
+            r#"create external table prices (id int, code text, price decimal(4,1)) from "test_data/csv""#,
+            "",
+        );
+        connection.query(
+            r#"select id, code, price + 1 from prices"#,
+            r#"
+        |123|abc|13.1|
+        |456|d,ef|14.2|
+        "#,
+        );
+    });
+}
+
+#[test]
+fn test_select_from_directory_columns() {
+    with_connection(|connection| {
+        connection.query(
+            r#"select city, age + 1, joined from directory "test_data/json" with (format = "json")
+               columns (city text path "$.address.city", age bigint path "$.age", joined date path "$.joined")
+               where city != "Nelson""#,
+            r#"
+        |Wanaka|35|2019-02-03|
+        |Dunedin|3000000001|NULL|
+        "#,
+        );
+    });
+}
+
+#[test]
+fn test_create_external_table_dotted_columns() {
+    with_connection(|connection| {
+        connection.query(
+            r#"create external table people (name text, address.city text, zip int path '$.address.postcode')
+               from "test_data/json" with (format = "json")"#,
+            "",
+        );
+        connection.query(
+            r#"select name, address_city, zip from people"#,
+            r#"
+        |alice|Wanaka|9305|
+        |bob|Dunedin|NULL|
+        |carol|Nelson|7010|
+        "#,
+        );
+    });
+}
+
+#[test]
+fn test_error_policies() {
+    with_connection(|connection| {
+        // The default policy fails the scan at the bad row
+        let (_, mut executor) = connection
+            .execute_statement(
+                r#"select * from directory "test_data/bad_json" with (format = "json")"#,
+            )
+            .unwrap();
+        assert!(executor.next().is_ok());
+        assert!(executor.next().is_err());
+
+        connection.query(
+            r#"select id from directory "test_data/bad_json" with (format = "json", on_error = "skip")
+               columns (id int path "$.id")"#,
+            r#"
+        |1|
+        |3|
+        "#,
+        );
+        connection.query(r#"select * from incresql.dead_letters"#, "");
+
+        connection.query(
+            r#"select id from directory "test_data/bad_json" with (format = "json", on_error = "dead_letter")
+               columns (id int path "$.id")"#,
+            r#"
+        |1|
+        |3|
+        "#,
+        );
+        assert_eq!(connection.session.warning_count(), 1);
+        connection.query(
+            r#"SHOW WARNINGS"#,
+            r#"
+        |Warning|1366|Row skipped, Invalid json document (test_data/bad_json/events.json at byte 10)|
+        "#,
+        );
+        connection.query(
+            r#"select source, `offset`, raw, error from incresql.dead_letters"#,
+            r#"
+        |test_data/bad_json/events.json|10|not json|Invalid json document|
+        "#,
+        );
+    });
+}
+
+#[test]
+fn test_resume() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE events (id INT)"#, "");
+        let insert = r#"INSERT INTO events SELECT id FROM directory "test_data/bad_json"
+            with (format = "json", on_error = "skip", resume = "true") columns (id int path "$.id")"#;
+
+        connection.query(insert, "");
+        connection.query(
+            r#"select source, `offset` from incresql.source_offsets"#,
+            r#"
+        |test_data/bad_json/events.json|29|
+        "#,
+        );
+
+        // Everything has already been ingested so the second insert doesn't add anything
+        connection.query(insert, "");
+        connection.query(
+            r#"SELECT id FROM events"#,
+            r#"
+        |1|
+        |3|
+        "#,
+        );
+
+        assert!(connection
+            .execute_statement(
+                r#"INSERT INTO events SELECT count(*) FROM directory "test_data/bad_json"
+                   with (format = "json", on_error = "skip", resume = "true")"#
+            )
+            .is_err());
+    });
+}
+
+#[test]
+fn test_throttling() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE events (id INT)"#, "");
+        connection.query(
+            r#"CREATE EXTERNAL TABLE event_source (id INT) FROM "test_data/bad_json"
+               WITH (format = "json", on_error = "skip", max_rows_per_second = "20", max_batch_rows = "1")"#,
+            "",
+        );
+
+        let started = Instant::now();
+        connection.query(r#"INSERT INTO events SELECT id FROM event_source"#, "");
+        // Three rows at 20 a second, the first isn't held up
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        connection.query(
+            r#"SELECT id FROM events"#,
+            r#"
+        |1|
+        |3|
+        "#,
+        );
+    });
+}
+
+#[test]
+fn test_pause_source() {
+    with_connection(|connection| {
+        connection.query(
+            r#"CREATE EXTERNAL TABLE event_source (id INT) FROM "test_data/bad_json"
+               WITH (format = "json", on_error = "skip")"#,
+            "",
+        );
+        connection.query(r#"ALTER SOURCE event_source PAUSE"#, "");
+        connection.query(
+            r#"SELECT * FROM incresql.paused_sources"#,
+            r#"
+        |test_data/bad_json|
+        "#,
+        );
+
+        // A paused scan waits until it's resumed or its connection is killed
+        let (_, mut executor) = connection
+            .execute_statement(r#"SELECT id FROM event_source"#)
+            .unwrap();
+        connection.session.kill_flag.store(true, Ordering::Relaxed);
+        assert!(executor.next().is_err());
+        connection.session.kill_flag.store(false, Ordering::Relaxed);
+
+        connection.query(r#"ALTER SOURCE event_source RESUME"#, "");
+        connection.query(r#"SELECT * FROM incresql.paused_sources"#, "");
+        connection.query(
+            r#"SELECT id FROM event_source"#,
+            r#"
+        |1|
+        |3|
+        "#,
+        );
+
+        connection.query(r#"CREATE TABLE t (id INT)"#, "");
+        assert!(connection
+            .execute_statement(r#"ALTER SOURCE t PAUSE"#)
+            .is_err());
+    });
+}
+
+#[test]
+fn test_change_stream() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE customers (id INT, name TEXT)"#, "");
+        let insert = r#"INSERT INTO customers SELECT * FROM directory "test_data/cdc/changes"
+            with (format = "json", changes = "debezium", snapshot = "test_data/cdc/snapshot", resume = "true")
+            columns (id int path "$.id", name text path "$.name")"#;
+
+        connection.query(insert, "");
+        connection.query(
+            r#"SELECT * FROM customers"#,
+            r#"
+        |1|alicia|
+        |3|carol|
+        |4|dave|
+        "#,
+        );
+
+        // Both the snapshot and the changes have been read so there's nothing new to apply
+        connection.query(insert, "");
+        connection.query(
+            r#"SELECT * FROM customers"#,
+            r#"
+        |1|alicia|
+        |3|carol|
+        |4|dave|
+        "#,
+        );
+    });
+}
+
+#[test]
+fn test_maxwell_changes() {
+    with_connection(|connection| {
+        connection.query(
+            r#"CREATE EXTERNAL TABLE customer_changes (id INT, name TEXT) FROM "test_data/maxwell"
+               WITH (format = "json", changes = "maxwell")"#,
+            "",
+        );
+        connection.query(r#"CREATE TABLE customers (id INT, name TEXT)"#, "");
+        connection.query(
+            r#"INSERT INTO customers SELECT * FROM customer_changes"#,
+            "",
+        );
+        connection.query(
+            r#"SELECT * FROM customers"#,
+            r#"
+        |1|alicia|
+        "#,
+        );
+    });
+}
+
+#[test]
+fn test_directory_privileges() {
+    with_connection(|connection| {
+        connection.query(
+            r#"create external table people from "test_data/json" with (format = "json") auto"#,
+            "",
+        );
+
+        connection.login("bob").unwrap();
+        assert!(connection
+            .execute_statement(r#"select * from directory "test_data/csv""#)
+            .is_err());
+        assert!(connection
+            .execute_statement(r#"infer schema from "test_data/json" with (format = "json")"#)
+            .is_err());
+        assert!(matches!(
+            connection.execute_statement(
+                r#"infer schema from "test_data/json" with (format = "parquet")"#
+            ),
+            Err(QueryError::PlannerError(_))
+        ));
+        // Only superusers can grant access
+        assert!(connection
+            .execute_statement(r#"GRANT EXECUTE ON DIRECTORY "test_data/" TO bob"#)
+            .is_err());
+        // Views over files are checked when they're created, not when they're read
+        connection.query(
+            r#"select name from people where age = 34"#,
+            "
+            |alice|
+            ",
+        );
+
+        connection.login("root").unwrap();
+        connection.query(r#"GRANT EXECUTE ON DIRECTORY "test_data/" TO bob"#, "");
+        connection.query(
+            r#"SELECT * FROM incresql.privileges"#,
+            "
+            |bob|EXECUTE|DIRECTORY|test_data/|
+            ",
+        );
+
+        connection.login("bob").unwrap();
+        connection.query(
+            r#"select * from directory "test_data/csv""#,
+            r#"
+        |["123","abc","12.1"]|
+        |["456","d,ef","13.2"]|
+        "#,
+        );
+        assert!(connection
+            .execute_statement(r#"select * from directory "test_data/../src""#)
+            .is_err());
+
+        connection.login("root").unwrap();
+        connection.query(r#"REVOKE EXECUTE ON DIRECTORY "test_data/" FROM bob"#, "");
+        assert!(connection
+            .execute_statement(r#"REVOKE EXECUTE ON DIRECTORY "test_data/" FROM bob"#)
+            .is_err());
+        connection.login("bob").unwrap();
+        assert!(connection
+            .execute_statement(r#"select * from directory "test_data/csv""#)
+            .is_err());
+    });
+}
+
+#[test]
+fn test_copy() {
+    with_connection(|connection| {
+        connection.query(
+            r#"CREATE TABLE prices (id INT, code TEXT, price DECIMAL(4,1))"#,
+            "",
+        );
+        connection.query(r#"COPY prices FROM "test_data/csv/simple.csv""#, "");
+        connection.query(
+            r#"SELECT id, code, price + 1 FROM prices"#,
+            r#"
+        |123|abc|13.1|
+        |456|d,ef|14.2|
+        "#,
+        );
+
+        // The bad row fails the copy by default, nothing is written
+        let (_, mut executor) = connection
+            .execute_statement(r#"COPY prices FROM "test_data/copy/prices.csv""#)
+            .unwrap();
+        assert!(executor.next().is_err());
+        connection.query(
+            r#"COPY prices FROM "test_data/copy/prices.csv" WITH (on_error = "skip")"#,
+            "",
+        );
+        connection.query(
+            r#"SELECT id, code, price FROM prices"#,
+            r#"
+        |1|abc|12.1|
+        |3|NULL|14.3|
+        |123|abc|12.1|
+        |456|d,ef|13.2|
+        "#,
+        );
+
+        assert!(connection
+            .execute_statement(
+                r#"COPY prices FROM "test_data/csv/simple.csv" WITH (format = "json")"#
+            )
+            .is_err());
+    });
+}
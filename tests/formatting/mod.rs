@@ -0,0 +1,62 @@
+use crate::runner::*;
+
+#[test]
+fn test_format_number() {
+    with_connection(|connection| {
+        connection.query(
+            r##"select format_number(1234567.891, "#,##0.00"), format_number(5, "0.0%"), format_number(null, "#")"##,
+            "
+        |1,234,567.89|500.0%|NULL|
+        ",
+        );
+    });
+}
+
+#[test]
+fn test_currency() {
+    with_connection(|connection| {
+        connection.query(
+            r#"select format_currency(-1234.5, "USD"), format_currency(2.5, "jpy"), format_currency(1, "XYZ")"#,
+            "
+        |-$1,234.50|¥2|NULL|
+        ",
+        );
+
+        connection.query(
+            r#"select round_bankers(2.5), round_bankers(1.235, 2), to_cents(0.125), from_cents(1234)"#,
+            "
+        |2|1.240|12|12.34|
+        ",
+        );
+    });
+}
+
+#[test]
+fn test_to_char() {
+    with_connection(|connection| {
+        connection.query(
+            r#"select to_char(cast("2020-03-01" as date), "Day DD Month YYYY")"#,
+            "
+        |Sunday 01 March 2020|
+        ",
+        );
+
+        connection.query(r#"set lc_time = "de_DE""#, "");
+
+        connection.query(
+            r#"select to_char(cast("2020-03-01T13:05:09" as timestamp), "Dy DD. MON YYYY HH24:MI")"#,
+            "
+        |So 01. MÄR 2020 13:05|
+        ",
+        );
+
+        connection.query(r#"set time_zone = "+01:00""#, "");
+
+        connection.query(
+            r#"select to_char(cast("2020-03-01T13:05:09" as timestamp), "HH24:MI TZ")"#,
+            "
+        |14:05 +01:00|
+        ",
+        );
+    });
+}
@@ -41,3 +41,75 @@ fn test_auto_convert_project() {
         );
     });
 }
+
+#[test]
+fn test_bool_and_bit_aggregates() {
+    with_connection(|connection| {
+        connection.query(r#"Create table test (c1 TEXT, c2 BOOLEAN, c3 INT)"#, "");
+        connection.query(
+            r#"INSERT INTO test VALUES
+        ("a", true, 6), ("a", false, 3), ("b", true, 12), ("b", NULL, 10), ("c", NULL, NULL)"#,
+            "",
+        );
+
+        connection.query(
+            r#"select c1, bool_and(c2), every(c2), bool_or(c2), bit_and(c3), bit_or(c3), bit_xor(c3)
+            from test group by c1 order by c1"#,
+            "
+            |a|FALSE|FALSE|TRUE|2|7|5|
+            |b|TRUE|TRUE|TRUE|8|14|6|
+            |c|NULL|NULL|NULL|NULL|NULL|NULL|
+        ",
+        );
+    });
+}
+
+#[test]
+fn test_any_value_first_last() {
+    with_connection(|connection| {
+        connection.query(r#"Create table test (c1 TEXT, c2 TEXT, c3 INT)"#, "");
+        connection.query(
+            r#"INSERT INTO test VALUES
+        ("a", "x", 2), ("a", "y", 1), ("a", "z", 3), ("b", NULL, 1), ("b", "w", NULL)"#,
+            "",
+        );
+
+        connection.query(
+            r#"select c1, any_value(c2) is not null, first(c2, c3), last(c2, c3)
+            from test group by c1 order by c1"#,
+            "
+            |a|TRUE|y|z|
+            |b|TRUE|NULL|NULL|
+        ",
+        );
+    });
+}
+
+#[test]
+fn test_time_bucket() {
+    with_connection(|connection| {
+        connection.query(r#"Create table test (ts TEXT, c1 INT)"#, "");
+        connection.query(
+            r#"INSERT INTO test VALUES
+        ("2020-03-01T13:01:09", 1), ("2020-03-01T13:04:59", 2), ("2020-03-01T13:05:00", 3),
+        ("2020-03-01T13:16:30", 4)"#,
+            "",
+        );
+
+        connection.query(
+            r#"select time_bucket("5 minutes", cast(ts as timestamp)) as bucket, sum(c1) from test group by time_bucket("5 minutes", cast(ts as timestamp)) order by bucket"#,
+            "
+            |2020-03-01 13:00:00|3|
+            |2020-03-01 13:05:00|3|
+            |2020-03-01 13:15:00|4|
+        ",
+        );
+
+        connection.query(
+            r#"select time_bucket(cast(3600000 as bigint), cast(ts as timestamp)), count(*) from test group by 1"#,
+            "
+            |2020-03-01 13:00:00|4|
+        ",
+        );
+    });
+}
@@ -47,3 +47,56 @@ fn test_insert_from_values() {
         );
     });
 }
+
+#[test]
+fn test_insert_max_value_bytes() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE t1 (a INT, b TEXT)"#, "");
+        connection.query(r#"SET max_value_bytes = 4"#, "");
+
+        connection.query(r#"INSERT INTO t1 VALUES (1, "abcd")"#, "");
+        let (_, mut executor) = connection
+            .execute_statement(r#"INSERT INTO t1 VALUES (2, "abcde")"#)
+            .unwrap();
+        assert!(executor.next().is_err());
+        assert!(connection
+            .execute_statement(r#"SET max_value_bytes = 0"#)
+            .is_err());
+
+        connection.query(r#"SET max_value_bytes = DEFAULT"#, "");
+        connection.query(r#"INSERT INTO t1 VALUES (2, "abcde")"#, "");
+        connection.query(
+            r#"SELECT * FROM t1"#,
+            "
+                |1|abcd|
+                |2|abcde|
+            ",
+        );
+    });
+}
+
+#[test]
+fn test_insert_columns() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE t1 (a INT, b TEXT, c BIGINT)"#, "");
+
+        connection.query(r#"INSERT INTO t1 (c, a) VALUES (3, 1)"#, "");
+        connection.query(r#"INSERT INTO t1 (b) SELECT "abc""#, "");
+
+        connection.query(r#"SELECT * FROM t1 WHERE a = 1"#, "|1|NULL|3|");
+        connection.query(r#"SELECT * FROM t1 WHERE b = "abc""#, "|NULL|abc|NULL|");
+
+        assert!(connection
+            .execute_statement(r#"INSERT INTO t1 (d) VALUES (1)"#)
+            .is_err());
+        assert!(connection
+            .execute_statement(r#"INSERT INTO t1 (a, a) VALUES (1, 2)"#)
+            .is_err());
+        assert!(connection
+            .execute_statement(r#"INSERT INTO t1 (a, b) SELECT 1"#)
+            .is_err());
+        assert!(connection
+            .execute_statement(r#"INSERT INTO t1 (a) SELECT "abc""#)
+            .is_err());
+    });
+}
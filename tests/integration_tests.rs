@@ -2,12 +2,25 @@ mod building_blocks;
 mod casts;
 mod create;
 mod delete;
+mod errors;
+mod events;
 mod file_sources;
+mod formatting;
 mod group;
 mod insert;
 mod join;
 mod json;
+mod network;
 mod optimize;
+mod policies;
+mod procedures;
 mod runner;
+mod schema_migration;
 mod show;
+mod sinks;
+mod sqllogictest;
+mod storage_tier;
+mod strings;
+mod transactions;
 mod views;
+mod window;
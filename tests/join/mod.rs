@@ -127,6 +127,20 @@ fn test_inner_joins() {
         |2|ld|1|rb|
         "#,
         );
+
+        // Test equi join with a condition on both sides that's left in the join
+        connection.query(
+            r#"SELECT * FROM t_left join t_right
+        on l_id = t_right.r_id AND l_id + r_id > 2
+        ORDER BY l_text, r_text
+        "#,
+            r#"
+        |2|lc|2|rc|
+        |2|lc|2|rd|
+        |2|ld|2|rc|
+        |2|ld|2|rd|
+        "#,
+        );
     });
 }
 
@@ -201,6 +215,26 @@ fn test_left_joins() {
         "#,
         );
 
+        // Test equi join with a condition on both sides that's left in the join
+        connection.query(
+            r#"SELECT * FROM t_left left outer join t_right
+        on l_id = t_right.r_id AND l_id + r_id > 2
+        ORDER BY l_text, r_text
+        "#,
+            r#"
+        |1|la|NULL|NULL|
+        |1|lb|NULL|NULL|
+        |2|lc|2|rc|
+        |2|lc|2|rd|
+        |2|ld|2|rc|
+        |2|ld|2|rd|
+        |3|le|NULL|NULL|
+        |3|lf|NULL|NULL|
+        |NULL|lg|NULL|NULL|
+        |NULL|lh|NULL|NULL|
+        "#,
+        );
+
         // Test constant (no joins succeed)
         connection.query(
             r#"SELECT * FROM t_left left outer join t_right
@@ -264,3 +298,382 @@ fn test_left_joins() {
         );
     });
 }
+
+#[test]
+fn test_right_joins() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE t_left (l_id INT, l_text TEXT)"#, "");
+
+        connection.query(
+            r#"INSERT INTO t_left VALUES
+        (1, "la"), (1, "lb"),
+        (2, "lc"), (2, "ld"),
+        (3, "le"), (3, "lf"),
+        (null, "lg"), (null, "lh")
+        "#,
+            "",
+        );
+
+        connection.query(r#"CREATE TABLE t_right (r_id INT, r_text TEXT)"#, "");
+
+        connection.query(
+            r#"INSERT INTO t_right VALUES
+        (1, "ra"), (1, "rb"),
+        (2, "rc"), (2, "rd"),
+        (4, "re"), (4, "rf"),
+        (null, "rg"), (null, "rh")
+        "#,
+            "",
+        );
+
+        // Test basic, the columns stay in the order they were written
+        connection.query(
+            r#"SELECT * FROM t_left right outer join t_right
+        on l_id = t_right.r_id
+        ORDER BY r_text, l_text
+        "#,
+            r#"
+        |1|la|1|ra|
+        |1|lb|1|ra|
+        |1|la|1|rb|
+        |1|lb|1|rb|
+        |2|lc|2|rc|
+        |2|ld|2|rc|
+        |2|lc|2|rd|
+        |2|ld|2|rd|
+        |NULL|NULL|4|re|
+        |NULL|NULL|4|rf|
+        |NULL|NULL|NULL|rg|
+        |NULL|NULL|NULL|rh|
+        "#,
+        );
+
+        // Test non_equi residual conditions
+        connection.query(
+            r#"SELECT l_text, r_text FROM t_left right join t_right
+        on l_id = t_right.r_id AND l_text != "la" AND r_id + l_id < 4
+        ORDER BY r_text, l_text
+        "#,
+            r#"
+        |lb|ra|
+        |lb|rb|
+        |NULL|rc|
+        |NULL|rd|
+        |NULL|re|
+        |NULL|rf|
+        |NULL|rg|
+        |NULL|rh|
+        "#,
+        );
+
+        // Test filters after joins
+        connection.query(
+            r#"SELECT * FROM t_left right join t_right
+        on l_id = t_right.r_id
+        WHERE l_id IS NULL
+        ORDER BY r_text
+        "#,
+            r#"
+        |NULL|NULL|4|re|
+        |NULL|NULL|4|rf|
+        |NULL|NULL|NULL|rg|
+        |NULL|NULL|NULL|rh|
+        "#,
+        );
+    });
+}
+
+#[test]
+fn test_full_joins() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE t_left (l_id INT, l_text TEXT)"#, "");
+
+        connection.query(
+            r#"INSERT INTO t_left VALUES
+        (1, "la"), (1, "lb"),
+        (2, "lc"), (2, "ld"),
+        (3, "le"), (3, "lf"),
+        (null, "lg"), (null, "lh")
+        "#,
+            "",
+        );
+
+        connection.query(r#"CREATE TABLE t_right (r_id INT, r_text TEXT)"#, "");
+
+        connection.query(
+            r#"INSERT INTO t_right VALUES
+        (1, "ra"), (1, "rb"),
+        (2, "rc"), (2, "rd"),
+        (4, "re"), (4, "rf"),
+        (null, "rg"), (null, "rh")
+        "#,
+            "",
+        );
+
+        // Test basic
+        connection.query(
+            r#"SELECT l_text, r_text FROM t_left full outer join t_right
+        on l_id = t_right.r_id
+        ORDER BY l_text, r_text
+        "#,
+            r#"
+        |NULL|re|
+        |NULL|rf|
+        |NULL|rg|
+        |NULL|rh|
+        |la|ra|
+        |la|rb|
+        |lb|ra|
+        |lb|rb|
+        |lc|rc|
+        |lc|rd|
+        |ld|rc|
+        |ld|rd|
+        |le|NULL|
+        |lf|NULL|
+        |lg|NULL|
+        |lh|NULL|
+        "#,
+        );
+
+        // Test non_equi residual conditions, rb is left without a match
+        connection.query(
+            r#"SELECT l_text, r_text FROM t_left full join t_right
+        on l_id = t_right.r_id AND r_text != "rb"
+        ORDER BY l_text, r_text
+        "#,
+            r#"
+        |NULL|rb|
+        |NULL|re|
+        |NULL|rf|
+        |NULL|rg|
+        |NULL|rh|
+        |la|ra|
+        |lb|ra|
+        |lc|rc|
+        |lc|rd|
+        |ld|rc|
+        |ld|rd|
+        |le|NULL|
+        |lf|NULL|
+        |lg|NULL|
+        |lh|NULL|
+        "#,
+        );
+
+        // Test constant (no joins succeed)
+        connection.query(
+            r#"SELECT count(*), count(l_text), count(r_text) FROM t_left full join t_right
+        on false
+        "#,
+            r#"
+        |16|8|8|
+        "#,
+        );
+
+        // Test filters after joins
+        connection.query(
+            r#"SELECT l_text, r_text FROM t_left full join t_right
+        on l_id = t_right.r_id
+        WHERE l_id IS NULL OR r_id IS NULL
+        ORDER BY l_text, r_text
+        "#,
+            r#"
+        |NULL|re|
+        |NULL|rf|
+        |NULL|rg|
+        |NULL|rh|
+        |le|NULL|
+        |lf|NULL|
+        |lg|NULL|
+        |lh|NULL|
+        "#,
+        );
+    });
+}
+
+#[test]
+fn test_subquery_joins() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE t_left (l_id INT, l_text TEXT)"#, "");
+
+        connection.query(
+            r#"INSERT INTO t_left VALUES
+        (1, "la"), (2, "lb"), (3, "lc"), (null, "ld")
+        "#,
+            "",
+        );
+
+        connection.query(r#"CREATE TABLE t_right (r_id INT, r_text TEXT)"#, "");
+
+        connection.query(
+            r#"INSERT INTO t_right VALUES
+        (1, "ra"), (1, "rb"), (2, "rc"), (4, "rd")
+        "#,
+            "",
+        );
+
+        // Correlated exists, right rows filtered by their own predicate too
+        connection.query(
+            r#"SELECT * FROM t_left
+        WHERE EXISTS (SELECT * FROM t_right WHERE r_id = l_id AND r_text != "rc")
+        ORDER BY l_text
+        "#,
+            r#"
+        |1|la|
+        "#,
+        );
+
+        connection.query(
+            r#"SELECT * FROM t_left
+        WHERE NOT EXISTS (SELECT * FROM t_right WHERE t_right.r_id = t_left.l_id)
+        ORDER BY l_text
+        "#,
+            r#"
+        |3|lc|
+        |NULL|ld|
+        "#,
+        );
+
+        connection.query(
+            r#"SELECT * FROM t_left
+        WHERE l_id IN (SELECT r_id FROM t_right)
+        ORDER BY l_text
+        "#,
+            r#"
+        |1|la|
+        |2|lb|
+        "#,
+        );
+
+        connection.query(
+            r#"SELECT * FROM t_left
+        WHERE l_id NOT IN (SELECT r_id FROM t_right)
+        ORDER BY l_text
+        "#,
+            r#"
+        |3|lc|
+        "#,
+        );
+
+        // A null in the subquery means nothing can be "not in" it.
+        connection.query(r#"INSERT INTO t_right VALUES (null, "re")"#, "");
+        connection.query(
+            r#"SELECT * FROM t_left
+        WHERE l_id NOT IN (SELECT r_id FROM t_right)
+        ORDER BY l_text
+        "#,
+            "",
+        );
+
+        // Correlated exists turns into a hash join on the correlated fields
+        connection.query(
+            r#"EXPLAIN SELECT * FROM t_left
+        WHERE EXISTS (SELECT * FROM t_right WHERE r_id = l_id)
+        "#,
+            r#"
+        |PROJECT||||
+        | |output_exprs:||||
+        | |  l_id|0|INTEGER|<OFFSET 1>|
+        | |  l_text|1|TEXT|<OFFSET 2>|
+        | |source:||||
+        | |  SEMI_JOIN||||
+        | |   |predicate:||||
+        | |   |||BOOLEAN|`=`(<OFFSET 0>, <OFFSET 3>)|
+        | |   |left:||||
+        | |   |  PROJECT||||
+        | |   |   |output_exprs:||||
+        | |   |   |  key_0|0|INTEGER|<OFFSET 0>|
+        | |   |   |  l_id|1|INTEGER|<OFFSET 0>|
+        | |   |   |  l_text|2|TEXT|<OFFSET 1>|
+        | |   |   |source:||||
+        | |   |   |  TABLE(t_left)||||
+        | |   |   |   |columns:||||
+        | |   |   |   |  l_id|0|INTEGER||
+        | |   |   |   |  l_text|1|TEXT||
+        | |   |right:||||
+        | |   |  PROJECT||||
+        | |   |   |output_exprs:||||
+        | |   |   |  key_0|0|INTEGER|<OFFSET 0>|
+        | |   |   |  r_id|1|INTEGER|<OFFSET 0>|
+        | |   |   |  r_text|2|TEXT|<OFFSET 1>|
+        | |   |   |source:||||
+        | |   |   |  TABLE(t_right)||||
+        | |   |   |   |columns:||||
+        | |   |   |   |  r_id|0|INTEGER||
+        | |   |   |   |  r_text|1|TEXT||
+        "#,
+        );
+    });
+}
+
+#[test]
+fn test_scalar_subqueries() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE t_left (l_id INT, l_text TEXT)"#, "");
+
+        connection.query(
+            r#"INSERT INTO t_left VALUES
+        (1, "la"), (2, "lb"), (3, "lc"), (null, "ld")
+        "#,
+            "",
+        );
+
+        connection.query(r#"CREATE TABLE t_right (r_id INT, r_text TEXT)"#, "");
+
+        connection.query(
+            r#"INSERT INTO t_right VALUES
+        (1, "ra"), (1, "rb"), (2, "rc"), (4, "rd")
+        "#,
+            "",
+        );
+
+        // Uncorrelated, in the select list and where clause
+        connection.query(
+            r#"SELECT l_text, (SELECT sum(r_id) FROM t_right) - l_id FROM t_left
+        WHERE l_id < (SELECT sum(r_id) FROM t_right) - 5
+        ORDER BY l_text
+        "#,
+            r#"
+        |la|7|
+        |lb|6|
+        "#,
+        );
+
+        // Correlated, rows without a match get a null
+        connection.query(
+            r#"SELECT l_text, (SELECT r_text FROM t_right WHERE r_id = l_id AND r_text != "rb")
+        FROM t_left
+        ORDER BY l_text
+        "#,
+            r#"
+        |la|ra|
+        |lb|rc|
+        |lc|NULL|
+        |ld|NULL|
+        "#,
+        );
+
+        // An empty subquery is null
+        connection.query(
+            r#"SELECT (SELECT r_text FROM t_right WHERE r_id = 5) IS NULL"#,
+            r#"
+        |TRUE|
+        "#,
+        );
+
+        // More than one row for la
+        let (_, mut executor) = connection
+            .execute_statement(
+                r#"SELECT l_text, (SELECT r_text FROM t_right WHERE r_id = l_id) FROM t_left
+            ORDER BY l_text"#,
+            )
+            .unwrap();
+        assert!(executor.next().is_err());
+
+        // Subqueries have to return a single column
+        assert!(connection
+            .execute_statement(r#"SELECT (SELECT r_id, r_text FROM t_right) FROM t_left"#)
+            .is_err());
+    });
+}
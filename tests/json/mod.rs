@@ -19,6 +19,78 @@ fn test_json_extract() {
     });
 }
 
+#[test]
+fn test_json_extract_filters() {
+    with_connection(|connection| {
+        let doc = r#"cast('{"items": [{"id": 1, "qty": 1, "price": 2}, {"id": 2, "qty": 3, "price": 5}]}' as json)"#;
+        connection.query(&format!(r#"select {}->"$.items[*].price""#, doc), "|[2,5]|");
+        connection.query(&format!(r#"select {}->"$..id""#, doc), "|[1,2]|");
+        connection.query(
+            &format!(r#"select {}->"$.items[?(@.qty > 1)].id""#, doc),
+            "|[2]|",
+        );
+        connection.query(
+            &format!(r#"select {}->"$.items[?(@.qty > 5)].id""#, doc),
+            "|[]|",
+        );
+    });
+}
+
+#[test]
+fn test_json_schemas() {
+    with_connection(|connection| {
+        let schema =
+            r#"{"type": "object", "required": ["id"], "properties": {"id": {"type": "integer"}}}"#;
+        connection.query(
+            &format!(
+                r#"select json_matches_schema(cast('{{"id": 1}}' as json), '{}'), json_matches_schema(cast('{{"id": "a"}}' as json), '{}')"#,
+                schema, schema
+            ),
+            "|TRUE|FALSE|",
+        );
+
+        connection.query(r#"CREATE TABLE orders (name TEXT, doc JSON)"#, "");
+        connection.query(
+            &format!(
+                r#"ALTER TABLE orders ALTER COLUMN doc SET SCHEMA '{}'"#,
+                schema
+            ),
+            "",
+        );
+        connection.query(
+            r#"INSERT INTO orders SELECT "a", cast('{"id": 1}' as json)"#,
+            "",
+        );
+        connection.query(r#"INSERT INTO orders VALUES ("b", NULL)"#, "");
+        let (_, mut executor) = connection
+            .execute_statement(r#"INSERT INTO orders SELECT "c", cast('{"id": "c"}' as json)"#)
+            .unwrap();
+        assert!(executor.next().is_err());
+        connection.query(
+            r#"SELECT name FROM orders ORDER BY name"#,
+            "
+            |a|
+            |b|
+            ",
+        );
+
+        // Schemas have to be valid and only go on json columns
+        assert!(connection
+            .execute_statement(r#"ALTER TABLE orders ALTER COLUMN name SET SCHEMA 'true'"#)
+            .is_err());
+        assert!(connection
+            .execute_statement(r#"ALTER TABLE orders ALTER COLUMN doc SET SCHEMA '[1]'"#)
+            .is_err());
+
+        connection.query(r#"ALTER TABLE orders ALTER COLUMN doc DROP SCHEMA"#, "");
+        connection.query(
+            r#"INSERT INTO orders SELECT "c", cast('{"id": "c"}' as json)"#,
+            "",
+        );
+        connection.query(r#"SELECT count(*) FROM orders"#, "|3|");
+    });
+}
+
 #[test]
 fn test_json_unquote() {
     with_connection(|connection| {
@@ -59,3 +131,23 @@ fn test_json_unquote() {
         );
     });
 }
+
+#[test]
+fn test_json_numbers() {
+    with_connection(|connection| {
+        // Equal numbers are the same json however they were written
+        connection.query(
+            r#"select count(*) from (select j from (select cast(s as json) as j from (select "1.50" as s union all select "1.5" union all select "15e-1")) t group by j) g"#,
+            "
+        |1|
+        ",
+        );
+        // Numbers too big for a decimal are kept as strings
+        connection.query(
+            r#"select cast("[1e300]" as json)"#,
+            r#"
+        |["1e300"]|
+        "#,
+        );
+    });
+}
@@ -0,0 +1,39 @@
+use crate::runner::*;
+
+#[test]
+fn test_url_functions() {
+    with_connection(|connection| {
+        connection.query(
+            r#"select url_extract_host("https://www.example.com:443/a/b?q=x%20y&p=1"), url_extract_path("https://www.example.com:443/a/b?q=x%20y&p=1"), url_extract_query("https://www.example.com:443/a/b?q=x%20y&p=1"), url_extract_param("https://www.example.com:443/a/b?q=x%20y&p=1", "q")"#,
+            "
+        |www.example.com|/a/b|q=x%20y&p=1|x y|
+        ",
+        );
+
+        connection.query(
+            r#"select url_encode("a b&c"), url_decode("a%20b%26c"), url_extract_param("/a?b=1", "c")"#,
+            "
+        |a%20b%26c|a b&c|NULL|
+        ",
+        );
+    });
+}
+
+#[test]
+fn test_inet_functions() {
+    with_connection(|connection| {
+        connection.query(
+            r#"select ipv4_to_int("192.168.0.1"), is_in_cidr("192.168.0.1", "192.168.0.0/16"), is_in_cidr("10.0.0.1", "192.168.0.0/16")"#,
+            "
+        |3232235521|TRUE|FALSE|
+        ",
+        );
+
+        connection.query(
+            r#"select ipv4_to_int("not an ip"), is_in_cidr("10.0.0.1", "10.0.0.0/99")"#,
+            "
+        |NULL|NULL|
+        ",
+        );
+    });
+}
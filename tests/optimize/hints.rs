@@ -0,0 +1,115 @@
+use crate::runner::*;
+
+#[test]
+fn test_no_pushdown() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE customers (id INT, name TEXT)"#, "");
+        connection.query(r#"CREATE TABLE orders (id INT, customer_id INT)"#, "");
+        connection.query(
+            r#"INSERT INTO customers VALUES (1, "alice"), (2, "bob")"#,
+            "",
+        );
+        connection.query(r#"INSERT INTO orders VALUES (10, 1), (11, 2)"#, "");
+
+        // The where clause stays on top of the join rather than being pushed down to orders
+        connection.query(
+            r#"EXPLAIN SELECT /*+ NO_PUSHDOWN */ c.name, o.id
+            FROM customers c JOIN orders o ON c.id = o.customer_id WHERE o.id > 10"#,
+            r#"
+        |PROJECT||||
+        | |output_exprs:||||
+        | |  name|0|TEXT|<OFFSET 1>|
+        | |  id|1|INTEGER|<OFFSET 2>|
+        | |source:||||
+        | |  FILTER||||
+        | |   |predicate:||||
+        | |   |||BOOLEAN|`>`(<OFFSET 2>, 10)|
+        | |   |source:||||
+        | |   |  PROJECT||||
+        | |   |   |output_exprs:||||
+        | |   |   |  id|0|INTEGER|<OFFSET 1>|
+        | |   |   |  name|1|TEXT|<OFFSET 2>|
+        | |   |   |  id|2|INTEGER|<OFFSET 4>|
+        | |   |   |  customer_id|3|INTEGER|<OFFSET 5>|
+        | |   |   |source:||||
+        | |   |   |  JOIN||||
+        | |   |   |   |predicate:||||
+        | |   |   |   |||BOOLEAN|`=`(<OFFSET 0>, <OFFSET 3>)|
+        | |   |   |   |left:||||
+        | |   |   |   |  PROJECT||||
+        | |   |   |   |   |output_exprs:||||
+        | |   |   |   |   |  key_0|0|INTEGER|<OFFSET 0>|
+        | |   |   |   |   |  id|1|INTEGER|<OFFSET 0>|
+        | |   |   |   |   |  name|2|TEXT|<OFFSET 1>|
+        | |   |   |   |   |source:||||
+        | |   |   |   |   |  TABLE(customers)(c)||||
+        | |   |   |   |   |   |columns:||||
+        | |   |   |   |   |   |  id|0|INTEGER||
+        | |   |   |   |   |   |  name|1|TEXT||
+        | |   |   |   |right:||||
+        | |   |   |   |  PROJECT||||
+        | |   |   |   |   |output_exprs:||||
+        | |   |   |   |   |  key_0|0|INTEGER|<OFFSET 1>|
+        | |   |   |   |   |  id|1|INTEGER|<OFFSET 0>|
+        | |   |   |   |   |  customer_id|2|INTEGER|<OFFSET 1>|
+        | |   |   |   |   |source:||||
+        | |   |   |   |   |  TABLE(orders)(o)||||
+        | |   |   |   |   |   |columns:||||
+        | |   |   |   |   |   |  id|0|INTEGER||
+        | |   |   |   |   |   |  customer_id|1|INTEGER||
+        "#,
+        );
+        connection.query(
+            r#"SELECT /*+ NO_PUSHDOWN */ c.name, o.id
+            FROM customers c JOIN orders o ON c.id = o.customer_id WHERE o.id > 10"#,
+            "|bob|11|",
+        );
+    })
+}
+
+#[test]
+fn test_hash_join() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE customers (id INT, name TEXT)"#, "");
+        connection.query(r#"CREATE TABLE orders (id INT, customer_id INT)"#, "");
+        connection.query(
+            r#"INSERT INTO customers VALUES (1, "alice"), (2, "bob")"#,
+            "",
+        );
+        connection.query(r#"INSERT INTO orders VALUES (10, 1), (11, 2)"#, "");
+
+        // Either way around the join gives the same rows
+        connection.query(
+            r#"SELECT /*+ HASH_JOIN(c o) */ c.name, o.id
+            FROM customers c JOIN orders o ON c.id = o.customer_id ORDER BY id"#,
+            "
+            |alice|10|
+            |bob|11|
+            ",
+        );
+        connection.query(
+            r#"SELECT /*+ HASH_JOIN(o c) */ c.name, o.id
+            FROM customers c JOIN orders o ON c.id = o.customer_id ORDER BY id"#,
+            "
+            |alice|10|
+            |bob|11|
+            ",
+        );
+
+        // Hints for joins that aren't there or can't be run that way are errors rather than
+        // being quietly ignored
+        assert!(connection
+            .execute_statement(
+                r#"SELECT /*+ HASH_JOIN(o x) */ * FROM customers c JOIN orders o ON c.id = o.customer_id"#
+            )
+            .is_err());
+        assert!(connection
+            .execute_statement(
+                r#"SELECT /*+ HASH_JOIN(o c) */ * FROM customers c LEFT JOIN orders o ON c.id = o.customer_id"#
+            )
+            .is_err());
+        assert!(connection
+            .execute_statement(r#"SELECT /*+ FULL_SPEED_AHEAD */ 1"#)
+            .is_err());
+    })
+}
@@ -0,0 +1,104 @@
+use crate::runner::*;
+
+#[test]
+fn test_long_in_lists() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE nums (id INT)"#, "");
+        connection.query(
+            r#"INSERT INTO nums VALUES (1), (2), (3), (4), (5), (NULL)"#,
+            "",
+        );
+
+        let list = (2..20)
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        // Long lists are turned into a join against the list's values
+        connection.query(
+            &format!("EXPLAIN SELECT id FROM nums WHERE id IN ({})", list),
+            r#"
+        |PROJECT||||
+        | |output_exprs:||||
+        | |  id|0|INTEGER|<OFFSET 1>|
+        | |source:||||
+        | |  SEMI_JOIN||||
+        | |   |predicate:||||
+        | |   |||BOOLEAN|`=`(<OFFSET 0>, <OFFSET 2>)|
+        | |   |left:||||
+        | |   |  PROJECT||||
+        | |   |   |output_exprs:||||
+        | |   |   |  key_0|0|INTEGER|<OFFSET 0>|
+        | |   |   |  id|1|INTEGER|<OFFSET 0>|
+        | |   |   |source:||||
+        | |   |   |  TABLE(nums)||||
+        | |   |   |   |columns:||||
+        | |   |   |   |  id|0|INTEGER||
+        | |   |right:||||
+        | |   |  PROJECT||||
+        | |   |   |output_exprs:||||
+        | |   |   |  key_0|0|INTEGER|<OFFSET 0>|
+        | |   |   |  value|1|INTEGER|<OFFSET 0>|
+        | |   |   |source:||||
+        | |   |   |  VALUES||||
+        "#,
+        );
+
+        connection.query(
+            &format!("SELECT id FROM nums WHERE id IN ({}) ORDER BY id", list),
+            r#"
+        |2|
+        |3|
+        |4|
+        |5|
+        "#,
+        );
+
+        connection.query(
+            &format!("SELECT id FROM nums WHERE id NOT IN ({}) ORDER BY id", list),
+            r#"
+        |1|
+        "#,
+        );
+
+        // NOT IN joins on the values too, with the nulls filtered out before the join
+        connection.query(
+            &format!("EXPLAIN SELECT id FROM nums WHERE id NOT IN ({})", list),
+            r#"
+        |PROJECT||||
+        | |output_exprs:||||
+        | |  id|0|INTEGER|<OFFSET 1>|
+        | |source:||||
+        | |  ANTI_JOIN||||
+        | |   |predicate:||||
+        | |   |||BOOLEAN|`=`(<OFFSET 0>, <OFFSET 2>)|
+        | |   |left:||||
+        | |   |  PROJECT||||
+        | |   |   |output_exprs:||||
+        | |   |   |  key_0|0|INTEGER|<OFFSET 0>|
+        | |   |   |  id|1|INTEGER|<OFFSET 0>|
+        | |   |   |source:||||
+        | |   |   |  FILTER||||
+        | |   |   |   |predicate:||||
+        | |   |   |   |||BOOLEAN|not(isnull(<OFFSET 0>))|
+        | |   |   |   |source:||||
+        | |   |   |   |  TABLE(nums)||||
+        | |   |   |   |   |columns:||||
+        | |   |   |   |   |  id|0|INTEGER||
+        | |   |right:||||
+        | |   |  PROJECT||||
+        | |   |   |output_exprs:||||
+        | |   |   |  key_0|0|INTEGER|<OFFSET 0>|
+        | |   |   |  value|1|INTEGER|<OFFSET 0>|
+        | |   |   |source:||||
+        | |   |   |  VALUES||||
+        "#,
+        );
+
+        // A null in the list means nothing can be not in the list
+        connection.query(
+            &format!("SELECT id FROM nums WHERE id NOT IN ({}, NULL)", list),
+            "",
+        );
+    });
+}
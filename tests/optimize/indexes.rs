@@ -0,0 +1,48 @@
+use crate::runner::*;
+
+#[test]
+fn test_indexes() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE orders (id INT, customer TEXT)"#, "");
+        connection.query(
+            r#"INSERT INTO orders VALUES (1, "b"), (2, "a"), (3, "b")"#,
+            "",
+        );
+        connection.query(r#"CREATE INDEX orders_customer ON orders (customer)"#, "");
+        // Rows written after the index is created end up in it too
+        connection.query(r#"INSERT INTO orders VALUES (4, "c"), (5, "b")"#, "");
+        connection.query(r#"DELETE FROM orders WHERE id = 3"#, "");
+
+        connection.query(
+            r#"SELECT id FROM orders WHERE customer = "b" ORDER BY id"#,
+            "
+            |1|
+            |5|
+            ",
+        );
+        connection.query(
+            r#"SELECT o.id, o.customer FROM orders o WHERE o.customer > "a" ORDER BY id"#,
+            "
+            |1|b|
+            |4|c|
+            |5|b|
+            ",
+        );
+        connection.query(
+            r#"SELECT i.name, i.column FROM incresql.indexes i JOIN incresql.tables t ON i.table_id = t.table_id WHERE t.name = "orders""#,
+            "
+            |orders_customer|customer|
+            ",
+        );
+
+        assert!(connection
+            .execute_statement(r#"CREATE INDEX orders_customer ON orders (id)"#)
+            .is_err());
+        assert!(connection
+            .execute_statement(r#"CREATE INDEX orders_total ON orders (total)"#)
+            .is_err());
+
+        connection.query(r#"DROP TABLE orders"#, "");
+        connection.query(r#"SELECT name FROM incresql.indexes"#, "");
+    });
+}
@@ -0,0 +1,89 @@
+use crate::runner::*;
+
+#[test]
+fn test_unused_unique_left_join() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE orders (id INT, customer_id INT)"#, "");
+        connection.query(r#"CREATE TABLE customers (id INT, name TEXT)"#, "");
+        connection.query(r#"INSERT INTO orders VALUES (1, 10), (2, 10), (3, 20)"#, "");
+        connection.query(
+            r#"INSERT INTO customers VALUES (10, "a"), (10, "b"), (20, "c")"#,
+            "",
+        );
+
+        // The right side is unique on id thanks to the group by, so the join can't
+        // change anything when its columns aren't used.
+        connection.query(
+            r#"EXPLAIN SELECT o.id FROM orders o
+            LEFT JOIN (SELECT id, count() as c FROM customers GROUP BY id) c ON o.customer_id = c.id
+            "#,
+            r#"
+        |PROJECT||||
+        | |output_exprs:||||
+        | |  id|0|INTEGER|<OFFSET 0>|
+        | |source:||||
+        | |  TABLE(orders)(o)||||
+        | |   |columns:||||
+        | |   |  id|0|INTEGER||
+        | |   |  customer_id|1|INTEGER||
+        "#,
+        );
+
+        connection.query(
+            r#"SELECT o.id FROM orders o
+            LEFT JOIN (SELECT id, count() as c FROM customers GROUP BY id) c ON o.customer_id = c.id
+            ORDER BY id
+            "#,
+            r#"
+        |1|
+        |2|
+        |3|
+        "#,
+        );
+
+        // Customers isn't unique on id so the join has to stay.
+        connection.query(
+            r#"SELECT o.id FROM orders o
+            LEFT JOIN customers c ON o.customer_id = c.id
+            ORDER BY id
+            "#,
+            r#"
+        |1|
+        |1|
+        |2|
+        |2|
+        |3|
+        "#,
+        );
+    });
+}
+
+#[test]
+fn test_unused_unique_left_join_within_another_join() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE orders (id INT, customer_id INT)"#, "");
+        connection.query(r#"CREATE TABLE customers (id INT, name TEXT)"#, "");
+        connection.query(r#"CREATE TABLE items (order_id INT, sku TEXT)"#, "");
+        connection.query(r#"INSERT INTO orders VALUES (1, 10), (2, 10), (3, 20)"#, "");
+        connection.query(r#"INSERT INTO customers VALUES (10, "a"), (20, "c")"#, "");
+        connection.query(
+            r#"INSERT INTO items VALUES (1, "x"), (3, "y"), (3, "z")"#,
+            "",
+        );
+
+        // The eliminated join is the left input of the inner join, the items columns have to
+        // move down to where the customers columns were.
+        connection.query(
+            r#"SELECT o.id, i.sku FROM orders o
+            LEFT JOIN (SELECT id, count() as c FROM customers GROUP BY id) c ON o.customer_id = c.id
+            JOIN items i ON i.order_id = o.id
+            ORDER BY id, sku
+            "#,
+            r#"
+        |1|x|
+        |3|y|
+        |3|z|
+        "#,
+        );
+    });
+}
@@ -0,0 +1,91 @@
+use crate::runner::*;
+
+#[test]
+fn test_analyze() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE orders (id INT, note TEXT)"#, "");
+        connection.query(
+            r#"INSERT INTO orders VALUES (1, "a"), (2, NULL), (3, NULL), (3, "b")"#,
+            "",
+        );
+        connection.query(r#"ANALYZE TABLE orders"#, "");
+        connection.query(
+            r#"SELECT table, column, stats FROM incresql.column_stats"#,
+            r#"
+            |orders|id|{"rows":4,"null_fraction":0,"distinct":3,"histogram":[1,2,3,3]}|
+            |orders|note|{"rows":4,"null_fraction":0.5,"distinct":2,"histogram":["a","a","b"]}|
+            "#,
+        );
+
+        // Views don't have any rows of their own to analyze
+        connection.query(r#"CREATE VIEW orders_view AS SELECT id FROM orders"#, "");
+        assert!(connection
+            .execute_statement(r#"ANALYZE orders_view"#)
+            .is_err());
+
+        // The stats go with the table
+        connection.query(r#"DROP TABLE orders"#, "");
+        connection.query(r#"SELECT table FROM incresql.column_stats"#, "");
+    })
+}
+
+#[test]
+fn test_join_order() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE customers (id INT, name TEXT)"#, "");
+        connection.query(r#"CREATE TABLE orders (id INT, customer_id INT)"#, "");
+        connection.query(
+            r#"INSERT INTO customers VALUES (1, "alice"), (2, "bob")"#,
+            "",
+        );
+        connection.query(
+            r#"INSERT INTO orders VALUES (10, 1), (11, 1), (12, 2), (13, 2), (14, 3)"#,
+            "",
+        );
+        connection.query(r#"ANALYZE TABLE customers"#, "");
+        connection.query(r#"ANALYZE TABLE orders"#, "");
+
+        // The larger orders table is read into the hash table when it's on the right so the
+        // inputs are swapped, the output's the same either way
+        connection.query(
+            r#"SELECT c.name, o.id FROM customers c JOIN orders o ON c.id = o.customer_id"#,
+            "
+            |alice|10|
+            |alice|11|
+            |bob|12|
+            |bob|13|
+            ",
+        );
+        connection.query(
+            r#"SELECT * FROM customers c JOIN orders o ON c.id = o.customer_id WHERE o.id > 12"#,
+            "
+            |2|bob|13|2|
+            ",
+        );
+    })
+}
+
+#[test]
+fn test_auto_analyze() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE orders (id INT)"#, "");
+        connection.query(r#"INSERT INTO orders VALUES (1), (2), (3), (4), (5)"#, "");
+        connection.query(r#"ANALYZE TABLE orders"#, "");
+
+        // Only 20% of the rows have changed so far
+        connection.query(r#"INSERT INTO orders VALUES (6)"#, "");
+        connection.query(r#"SELECT COUNT(*) FROM orders"#, "|6|");
+        connection.query(
+            r#"SELECT stats->>"$.rows" FROM incresql.column_stats"#,
+            "|5|",
+        );
+
+        // Now it's past the threshold the next query analyzes it again
+        connection.query(r#"INSERT INTO orders VALUES (7)"#, "");
+        connection.query(r#"SELECT COUNT(*) FROM orders"#, "|7|");
+        connection.query(
+            r#"SELECT stats->>"$.rows" FROM incresql.column_stats"#,
+            "|7|",
+        );
+    })
+}
@@ -1 +1,9 @@
 mod constant_folding;
+mod hints;
+mod in_list_joins;
+mod indexes;
+mod join_elimination;
+mod join_order;
+mod optimizer_rules;
+mod plan_validation;
+mod scan_ranges;
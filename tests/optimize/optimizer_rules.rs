@@ -0,0 +1,59 @@
+use crate::runner::*;
+
+#[test]
+fn test_optimizer_rules() {
+    with_connection(|connection| {
+        connection.query(r#"SET optimizer_rules = '-fold_constants'"#, "");
+        connection.query(
+            r#"EXPLAIN SELECT 1 + 2"#,
+            "
+            |PROJECT||||
+            | |output_exprs:||||
+            | |  _col1|0|INTEGER|`+`(1, 2)|
+            | |source:||||
+            | |  SINGLE||||
+            ",
+        );
+        // Turning a rule off only changes the plan, not the results
+        connection.query(r#"SELECT 1 + 2"#, "|3|");
+
+        connection.query(r#"SET optimizer_rules = '+fold_constants'"#, "");
+        connection.query(
+            r#"EXPLAIN SELECT 1 + 2"#,
+            "
+            |PROJECT||||
+            | |output_exprs:||||
+            | |  _col1|0|INTEGER|3|
+            | |source:||||
+            | |  SINGLE||||
+            ",
+        );
+
+        connection.query(
+            r#"SET optimizer_rules = '-fold_constants, -predicate_pushdown'"#,
+            "",
+        );
+        connection.query(r#"SET optimizer_rules = 'default'"#, "");
+        connection.query(
+            r#"EXPLAIN SELECT 1 + 2"#,
+            "
+            |PROJECT||||
+            | |output_exprs:||||
+            | |  _col1|0|INTEGER|3|
+            | |source:||||
+            | |  SINGLE||||
+            ",
+        );
+
+        assert!(connection
+            .execute_statement(r#"SET optimizer_rules = '-no_such_rule'"#)
+            .is_err());
+        assert!(connection
+            .execute_statement(r#"SET optimizer_rules = 'fold_constants'"#)
+            .is_err());
+        // Subqueries have to be decorrelated to be planned at all
+        assert!(connection
+            .execute_statement(r#"SET optimizer_rules = '-decorrelate_subqueries'"#)
+            .is_err());
+    });
+}
@@ -0,0 +1,47 @@
+use crate::runner::*;
+
+#[test]
+fn test_plan_validation() {
+    with_connection(|connection| {
+        connection.query(r#"SET plan_validation = 100"#, "");
+        connection.query(r#"CREATE TABLE customers (id INT, name TEXT)"#, "");
+        connection.query(r#"CREATE TABLE orders (id INT, customer_id INT)"#, "");
+        connection.query(
+            r#"INSERT INTO customers VALUES (1, "alice"), (2, "bob")"#,
+            "",
+        );
+        connection.query(
+            r#"INSERT INTO orders VALUES (10, 1), (11, 1), (12, 2), (13, 3)"#,
+            "",
+        );
+
+        // Each of these is also run without the optimizer and the results compared
+        connection.query(
+            r#"SELECT c.name, count() FROM customers c JOIN orders o ON c.id = o.customer_id
+            WHERE o.id > 10 AND 1 = 1 GROUP BY c.name ORDER BY name"#,
+            "
+            |alice|1|
+            |bob|1|
+            ",
+        );
+        connection.query(
+            r#"SELECT id FROM orders WHERE customer_id IN (SELECT id FROM customers)
+            ORDER BY id"#,
+            "
+            |10|
+            |11|
+            |12|
+            ",
+        );
+        // Queries that can't be repeated are only type checked
+        connection.query(r#"SELECT random() >= 0"#, "|TRUE|");
+
+        connection.query(r#"SET plan_validation = DEFAULT"#, "");
+        assert!(connection
+            .execute_statement(r#"SET plan_validation = 101"#)
+            .is_err());
+        assert!(connection
+            .execute_statement(r#"SET plan_validation = 'sometimes'"#)
+            .is_err());
+    });
+}
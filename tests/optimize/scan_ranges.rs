@@ -0,0 +1,46 @@
+use crate::runner::*;
+
+#[test]
+fn test_scan_range_predicates() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE points (x INT, y TEXT)"#, "");
+        connection.query(
+            r#"INSERT INTO points VALUES (1, "a"), (2, "b"), (2, "c"), (3, "d"), (NULL, "e"), (5, "f")"#,
+            "",
+        );
+
+        connection.query(
+            r#"SELECT x, y FROM points WHERE x > 1 AND x <= 3 ORDER BY y"#,
+            r#"
+        |2|b|
+        |2|c|
+        |3|d|
+        "#,
+        );
+
+        connection.query(
+            r#"SELECT x, y FROM points WHERE (x < 2 OR x >= 5) ORDER BY y"#,
+            r#"
+        |1|a|
+        |5|f|
+        "#,
+        );
+
+        connection.query(
+            r#"SELECT x, y FROM points WHERE x IN (2, 5) AND y > "b" ORDER BY y"#,
+            r#"
+        |2|c|
+        |5|f|
+        "#,
+        );
+
+        connection.query(
+            r#"SELECT x, y FROM points WHERE x BETWEEN 3 AND 10 AND x != 5"#,
+            r#"
+        |3|d|
+        "#,
+        );
+
+        connection.query(r#"SELECT x, y FROM points WHERE x > 3 AND x < 2"#, "");
+    });
+}
@@ -0,0 +1,216 @@
+use crate::runner::*;
+
+#[test]
+fn test_policies() {
+    with_connection(|connection| {
+        connection.login("admin").unwrap();
+        connection.query(r#"CREATE TABLE orders (tenant TEXT, amount INT)"#, "");
+        connection.query(
+            r#"INSERT INTO orders VALUES ("alice", 10), ("alice", 5), ("bob", 7)"#,
+            "",
+        );
+        connection.query(
+            r#"CREATE POLICY tenants ON orders USING (tenant = current_user())"#,
+            "",
+        );
+        connection.query(
+            r#"SELECT name, predicate FROM incresql.policies"#,
+            "
+            |tenants|tenant = current_user()|
+            ",
+        );
+
+        // The table's owner sees every row
+        connection.query(
+            r#"SELECT sum(amount) FROM orders"#,
+            "
+            |22|
+            ",
+        );
+
+        // Everyone else only sees the rows the policy lets them
+        connection.login("alice").unwrap();
+        connection.query(
+            r#"SELECT o.tenant, sum(o.amount) FROM orders o WHERE amount > 1 GROUP BY o.tenant"#,
+            "
+            |alice|15|
+            ",
+        );
+        // Policies only restrict reads, anyone can insert
+        connection.query(r#"INSERT INTO orders VALUES ("alice", 1), ("bob", 2)"#, "");
+        connection.query(
+            r#"SELECT sum(amount) FROM orders"#,
+            "
+            |16|
+            ",
+        );
+        connection.query(
+            r#"CREATE VIEW alices_orders AS SELECT amount FROM default.orders"#,
+            "",
+        );
+        connection.query(
+            r#"SELECT count(*) FROM alices_orders"#,
+            "
+            |3|
+            ",
+        );
+
+        // Only the table's owner can change its policies
+        assert!(connection
+            .execute_statement("DROP POLICY tenants ON orders")
+            .is_err());
+        assert!(connection
+            .execute_statement("CREATE POLICY everything ON orders USING (TRUE)")
+            .is_err());
+
+        // Policies are AND-ed together
+        connection.login("admin").unwrap();
+        connection.query(
+            r#"CREATE POLICY big_orders ON orders USING (amount > 6)"#,
+            "",
+        );
+        connection.login("alice").unwrap();
+        connection.query(
+            r#"SELECT amount FROM orders"#,
+            "
+            |10|
+            ",
+        );
+
+        connection.login("admin").unwrap();
+        connection.query(r#"DROP POLICY tenants ON orders"#, "");
+        connection.query(r#"DROP POLICY big_orders ON orders"#, "");
+        connection.login("alice").unwrap();
+        connection.query(
+            r#"SELECT sum(amount) FROM orders"#,
+            "
+            |25|
+            ",
+        );
+    });
+}
+
+#[test]
+fn test_policy_errors() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE orders (tenant TEXT)"#, "");
+        connection.query(r#"CREATE VIEW tenants AS SELECT tenant FROM orders"#, "");
+        assert!(connection
+            .execute_statement("CREATE POLICY p ON orders USING (missing = 1)")
+            .is_err());
+        assert!(connection
+            .execute_statement("CREATE POLICY p ON missing USING (tenant = 1)")
+            .is_err());
+        assert!(connection
+            .execute_statement("CREATE POLICY p ON tenants USING (tenant = current_user())")
+            .is_err());
+        connection.query(
+            r#"CREATE POLICY p ON orders USING (tenant = current_user())"#,
+            "",
+        );
+        assert!(connection
+            .execute_statement("CREATE POLICY p ON orders USING (tenant = current_user())")
+            .is_err());
+        connection.query(r#"DROP POLICY p ON orders"#, "");
+        assert!(connection
+            .execute_statement("DROP POLICY p ON orders")
+            .is_err());
+    });
+}
+
+#[test]
+fn test_column_masks() {
+    with_connection(|connection| {
+        connection.login("admin").unwrap();
+        connection.query(r#"CREATE TABLE customers (name TEXT, card TEXT)"#, "");
+        connection.query(
+            r#"INSERT INTO customers VALUES ("ann", "4111222233334444"), ("ben", NULL)"#,
+            "",
+        );
+        connection.query(
+            r#"ALTER TABLE customers ALTER COLUMN card SET MASK USING (mask_text(card, 4))"#,
+            "",
+        );
+        connection.query(
+            r#"SELECT column, mask FROM incresql.column_masks"#,
+            "
+            |card|mask_text(card, 4)|
+            ",
+        );
+
+        // The table's owner sees the real values
+        connection.query(
+            r#"SELECT name, card FROM customers WHERE name = "ann""#,
+            "
+            |ann|4111222233334444|
+            ",
+        );
+
+        // Everyone else sees the masked values, even when filtering on them
+        connection.login("analyst").unwrap();
+        connection.query(
+            r#"SELECT name, card FROM customers ORDER BY name"#,
+            "
+            |ann|************4444|
+            |ben|NULL|
+            ",
+        );
+        connection.query(
+            r#"SELECT count(*) FROM customers WHERE card = "4111222233334444""#,
+            "
+            |0|
+            ",
+        );
+        assert!(connection
+            .execute_statement(r#"DELETE FROM customers"#)
+            .is_err());
+        // Only the table's owner can change its masks
+        assert!(connection
+            .execute_statement(r#"ALTER TABLE customers ALTER card DROP MASK"#)
+            .is_err());
+        assert!(connection
+            .execute_statement(r#"ALTER TABLE customers ALTER COLUMN card SET MASK USING (card)"#)
+            .is_err());
+
+        connection.login("admin").unwrap();
+        connection.query(r#"ALTER TABLE customers ALTER card DROP MASK"#, "");
+        connection.login("analyst").unwrap();
+        connection.query(
+            r#"SELECT card FROM customers WHERE name = "ann""#,
+            "
+            |4111222233334444|
+            ",
+        );
+    });
+}
+
+#[test]
+fn test_column_mask_errors() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE customers (name TEXT, card TEXT)"#, "");
+        connection.query(
+            r#"CREATE VIEW customer_names AS SELECT name FROM customers"#,
+            "",
+        );
+        // Missing column
+        assert!(connection
+            .execute_statement(r#"ALTER TABLE customers ALTER COLUMN cvv SET MASK USING (NULL)"#)
+            .is_err());
+        // Mask that doesn't plan
+        assert!(connection
+            .execute_statement(
+                r#"ALTER TABLE customers ALTER COLUMN card SET MASK USING (mask_text(missing, 4))"#
+            )
+            .is_err());
+        // Views
+        assert!(connection
+            .execute_statement(
+                r#"ALTER TABLE customer_names ALTER COLUMN name SET MASK USING (NULL)"#
+            )
+            .is_err());
+        // No mask to drop
+        assert!(connection
+            .execute_statement(r#"ALTER TABLE customers ALTER COLUMN card DROP MASK"#)
+            .is_err());
+    });
+}
@@ -0,0 +1,98 @@
+use crate::runner::*;
+use runtime::QueryError;
+
+#[test]
+fn test_procedures() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE numbers (n INT, position TEXT)"#, "");
+        connection.query(
+            r#"CREATE PROCEDURE fill_numbers() BEGIN
+                SET @i = 1;
+                WHILE @i <= @count DO
+                    IF @i = 1 THEN
+                        INSERT INTO numbers SELECT @i, "first";
+                    ELSEIF @i < @count THEN
+                        INSERT INTO numbers SELECT @i, "middle";
+                    ELSE
+                        INSERT INTO numbers SELECT @i, "last";
+                    END IF;
+                    SET @i = @i + 1;
+                END WHILE;
+            END"#,
+            "",
+        );
+        connection.query(r#"SET @count = 4"#, "");
+        connection.query(r#"CALL fill_numbers()"#, "");
+        connection.query(
+            r#"SELECT n, position FROM numbers ORDER BY n"#,
+            "
+            |1|first|
+            |2|middle|
+            |3|middle|
+            |4|last|
+            ",
+        );
+        // The procedure's state is left in the session's variables
+        connection.query(r#"SELECT @i"#, "|5|");
+
+        // Names are unique within a database
+        assert!(connection
+            .execute_statement("CREATE PROCEDURE fill_numbers BEGIN SELECT 1; END")
+            .is_err());
+
+        connection.query(r#"DROP PROCEDURE fill_numbers"#, "");
+        assert!(connection.execute_statement("CALL fill_numbers").is_err());
+    });
+}
+
+#[test]
+fn test_procedure_errors_stop_the_call() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE log (step INT)"#, "");
+        connection.query(
+            r#"CREATE PROCEDURE failing BEGIN
+                INSERT INTO log VALUES (1);
+                SELECT * FROM missing_table;
+                INSERT INTO log VALUES (2);
+            END"#,
+            "",
+        );
+        assert!(connection.execute_statement("CALL failing").is_err());
+        connection.query(r#"SELECT step FROM log"#, "|1|");
+    });
+}
+
+#[test]
+fn test_procedure_recursion_limit() {
+    with_connection(|connection| {
+        connection.query(
+            r#"CREATE PROCEDURE countdown BEGIN
+                SET @n = @n - 1;
+                IF @n > 0 THEN
+                    CALL countdown;
+                END IF;
+            END"#,
+            "",
+        );
+        connection.query(r#"SET @n = 10"#, "");
+        connection.query(r#"CALL countdown"#, "");
+        connection.query(r#"SELECT @n"#, "|0|");
+
+        connection.query(r#"SET max_recursion_depth = 5"#, "");
+        connection.query(r#"SET @n = 10"#, "");
+        assert!(matches!(
+            connection.execute_statement("CALL countdown"),
+            Err(QueryError::ProcedureDepthExceeded(..))
+        ));
+
+        // Calls that never stop stop at the limit
+        connection.query(r#"CREATE PROCEDURE forever BEGIN CALL forever; END"#, "");
+        assert!(matches!(
+            connection.execute_statement("CALL forever"),
+            Err(QueryError::ProcedureDepthExceeded(..))
+        ));
+        // The depth's back to 0 after a failed call
+        connection.query(r#"SET @n = 3"#, "");
+        connection.query(r#"CALL countdown"#, "");
+    });
+}
@@ -1,4 +1,6 @@
+use data::Clock;
 use runtime::connection::Connection;
+use runtime::sqllogictest::SqlLogicTestRunner;
 use runtime::Runtime;
 
 /// Creates a new connection and passes it to the closure
@@ -8,6 +10,15 @@ pub fn with_connection<F: FnOnce(&Connection)>(f: F) {
     f(&connection)
 }
 
+/// Like with_connection but the runtime's time stands still at start_ms, only moving when the
+/// clock passed to the closure is advanced
+pub fn with_virtual_clock<F: FnOnce(&Clock, &Connection)>(start_ms: u64, f: F) {
+    let clock = Clock::new_virtual(start_ms);
+    let runtime = Runtime::new_for_test_with_clock(clock.clone());
+    let connection = runtime.new_connection();
+    f(&clock, &connection)
+}
+
 /// Test helper that creates a new runtime/connection and executes a single query
 pub fn query(query: &str, expected: &str) {
     with_connection(|connection| connection.query(query, expected))
@@ -78,3 +89,23 @@ impl TestQuery for Connection<'_> {
         }
     }
 }
+
+/// Runs every .slt script in the directory, each script gets a fresh runtime.
+pub fn run_sqllogictest_dir(dir: &str) {
+    let mut paths: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("slt"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "No .slt scripts found in {}", dir);
+
+    for path in paths {
+        with_connection(|connection| {
+            let mut runner = SqlLogicTestRunner::new(connection);
+            if let Err(err) = runner.run_file(&path) {
+                panic!("{}: {}", path.display(), err);
+            }
+        });
+    }
+}
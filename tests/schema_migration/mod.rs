@@ -0,0 +1,59 @@
+use crate::runner::*;
+
+#[test]
+fn test_modify_column() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE orders (id INT, amount TEXT)"#, "");
+        connection.query(
+            r#"INSERT INTO orders VALUES (1, "1.50"), (2, "2.25"), (2, "2.25")"#,
+            "",
+        );
+        connection.query(
+            r#"ALTER TABLE orders MODIFY COLUMN amount DECIMAL(10,2)"#,
+            "",
+        );
+        connection.query(
+            r#"SELECT id, amount + 1 FROM orders"#,
+            "
+            |1|2.50|
+            |2|3.25|
+            |2|3.25|
+            ",
+        );
+        connection.query(
+            r#"SELECT database, table, column, datatype, rows_total, rows_copied, status FROM incresql.schema_migrations"#,
+            "|default|orders|amount|DECIMAL(10,2)|2|2|done|",
+        );
+        // The shadow table's been swapped in so only the one table's left
+        connection.query(r#"SHOW TABLES"#, "|orders|");
+
+        // Writes after the migration take the new type
+        connection.query(
+            r#"INSERT INTO orders SELECT 3, CAST(1.5 AS DECIMAL(10,2))"#,
+            "",
+        );
+        connection.query(r#"SELECT amount FROM orders WHERE id = 3"#, "|1.50|");
+    })
+}
+
+#[test]
+fn test_modify_column_errors() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE orders (id INT)"#, "");
+        connection.query(r#"CREATE VIEW orders_view AS SELECT id FROM orders"#, "");
+        assert!(connection
+            .execute_statement(r#"ALTER TABLE orders MODIFY COLUMN amount INT"#)
+            .is_err());
+        assert!(connection
+            .execute_statement(r#"ALTER TABLE orders_view MODIFY COLUMN id BIGINT"#)
+            .is_err());
+        // Nothing's left behind by the failed attempts
+        connection.query(
+            r#"SHOW TABLES"#,
+            "
+            |orders|
+            |orders_view|
+            ",
+        );
+    })
+}
@@ -6,6 +6,7 @@ fn show_functions() {
         r#"SHOW FUNCTIONS"#,
         "
         |!=|
+        |&|
         |*|
         |+|
         |-|
@@ -13,36 +14,92 @@ fn show_functions() {
         |->>|
         |/|
         |<|
+        |<<|
         |<=|
         |=|
         |>|
         |>=|
+        |>>|
+        |^|
         |and|
+        |any_value|
+        |array_to_string|
         |avg|
         |between|
+        |bit_and|
+        |bit_or|
+        |bit_xor|
+        |bool_and|
+        |bool_or|
         |coalesce|
+        |collation_key|
         |count|
+        |current_user|
         |database|
         |date_sub|
+        |every|
+        |first|
+        |format_currency|
+        |format_number|
+        |from_cents|
         |if|
+        |ipv4_to_int|
+        |is_in_cidr|
         |isfalse|
         |isnull|
         |istrue|
         |json_extract|
+        |json_matches_schema|
         |json_unquote|
+        |last|
+        |levenshtein|
+        |mask_text|
+        |natural_sort_key|
         |not|
+        |now|
         |or|
+        |random|
+        |round_bankers|
+        |similarity|
+        |soundex|
+        |split_part|
+        |string_to_array|
         |sum|
+        |time_bucket|
         |to_bigint|
         |to_bool|
+        |to_bytes|
+        |to_cents|
+        |to_char|
         |to_date|
         |to_decimal|
+        |to_enum|
         |to_int|
         |to_json|
         |to_jsonpath|
         |to_text|
         |to_timestamp|
+        |try_to_bigint|
+        |try_to_bool|
+        |try_to_bytes|
+        |try_to_date|
+        |try_to_decimal|
+        |try_to_enum|
+        |try_to_int|
+        |try_to_json|
+        |try_to_jsonpath|
+        |try_to_text|
+        |try_to_timestamp|
         |type_of|
+        |typeof|
+        |url_decode|
+        |url_encode|
+        |url_extract_host|
+        |url_extract_param|
+        |url_extract_path|
+        |url_extract_query|
+        |||
+        |~|
         ",
     );
 }
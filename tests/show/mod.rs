@@ -1,3 +1,7 @@
 mod databases;
 mod functions;
+mod processlist;
+mod storage_stats;
 mod tables;
+mod user_defaults;
+mod workload_stats;
@@ -0,0 +1,37 @@
+use crate::runner::*;
+use runtime::Runtime;
+
+#[test]
+fn show_processlist() {
+    let runtime = Runtime::new_for_test();
+    let admin = runtime.new_connection();
+    let service = runtime.new_connection();
+    service
+        .session
+        .client_attributes
+        .write()
+        .unwrap()
+        .extend(vec![
+            ("program_name".to_string(), "orders".to_string()),
+            ("_client_name".to_string(), "libmysql".to_string()),
+            ("_client_version".to_string(), "5.6.6".to_string()),
+        ]);
+    service.login("bob").unwrap();
+    service.query("USE incresql", "");
+
+    admin.query(
+        "SHOW PROCESSLIST",
+        "
+        |1||default|NULL|NULL|NULL|SHOW PROCESSLIST|
+        |2|bob|incresql|orders|libmysql|5.6.6|USE incresql|
+        ",
+    );
+
+    // Other users only see their own connections
+    service.query(
+        "SHOW PROCESSLIST",
+        "
+        |2|bob|incresql|orders|libmysql|5.6.6|SHOW PROCESSLIST|
+        ",
+    );
+}
@@ -0,0 +1,21 @@
+use crate::runner::*;
+
+#[test]
+fn storage_stats() {
+    with_connection(|connection| {
+        // The in memory storage used by the tests doesn't keep any statistics
+        connection.query(
+            r#"SELECT name, value FROM incresql.storage_stats"#,
+            "
+            |block_cache_hit_ratio|0.0000|
+            |block_cache_hits|0.0000|
+            |block_cache_misses|0.0000|
+            |compaction_pending|0.0000|
+            |live_data_bytes|0.0000|
+            |memtable_bytes|0.0000|
+            |pending_compaction_bytes|0.0000|
+            |write_stall_micros|0.0000|
+       ",
+        );
+    })
+}
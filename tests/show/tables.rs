@@ -10,9 +10,32 @@ fn show_tables() {
         connection.query(
             r#"SHOW TABLES"#,
             "
+            |checksummed_tables|
+            |column_masks|
+            |column_schemas|
+            |column_stats|
+            |corrupted_rows|
             |databases|
+            |dead_letters|
+            |incremental_views|
+            |indexes|
+            |null_bitmap_tables|
+            |paused_sources|
+            |policies|
             |prefix_tables|
+            |primary_keys|
+            |privileges|
+            |procedures|
+            |scheduled_events|
+            |schema_migrations|
+            |sinks|
+            |source_offsets|
+            |state_ttls|
+            |storage_stats|
+            |table_owners|
             |tables|
+            |user_defaults|
+            |workload_stats|
        ",
         );
     })
@@ -0,0 +1,36 @@
+use crate::runner::*;
+use runtime::QueryError;
+
+#[test]
+fn user_defaults() {
+    with_connection(|connection| {
+        connection.query(r#"ALTER USER 'bob' SET sql_select_limit = 10"#, "");
+        connection.query(r#"ALTER USER bob SET lc_time = 'de_DE'"#, "");
+        connection.query(r#"ALTER USER alice SET deterministic_order = on"#, "");
+
+        connection.query(
+            r#"SELECT user, name, value FROM incresql.user_defaults"#,
+            "
+            |alice|deterministic_order|on|
+            |bob|lc_time|de_DE|
+            |bob|sql_select_limit|10|
+       ",
+        );
+
+        connection.query(r#"ALTER USER bob RESET lc_time"#, "");
+
+        connection.query(
+            r#"SELECT user, name, value FROM incresql.user_defaults WHERE user = 'bob'"#,
+            "
+            |bob|sql_select_limit|10|
+       ",
+        );
+
+        connection.login("bob").unwrap();
+        connection.query(r#"ALTER USER bob SET sql_select_limit = 20"#, "");
+        assert!(matches!(
+            connection.execute_statement(r#"ALTER USER alice RESET deterministic_order"#),
+            Err(QueryError::AlterUserNotAllowed(_))
+        ));
+    })
+}
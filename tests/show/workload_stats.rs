@@ -0,0 +1,23 @@
+use crate::runner::*;
+
+#[test]
+fn workload_stats() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE test (id INT)"#, "");
+        connection.query(r#"INSERT INTO test VALUES (1), (2)"#, "");
+        connection.query(
+            r#"CREATE MATERIALIZED VIEW test_view AS SELECT count(*) FROM test"#,
+            "",
+        );
+        connection.query(r#"REFRESH MATERIALIZED VIEW test_view"#, "");
+
+        // The select counts itself
+        connection.query(
+            r#"SELECT class, statements FROM incresql.workload_stats"#,
+            "
+            |interactive|3|
+            |maintenance|2|
+       ",
+        );
+    })
+}
@@ -0,0 +1,111 @@
+use crate::runner::*;
+use std::time::Duration;
+
+#[test]
+fn test_file_sink() {
+    let directory = std::env::temp_dir().join(format!("incresql_sink_{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("orders.json");
+    let path = path.to_str().unwrap();
+    // Sinks only read up to the ms before the flush
+    let settle = || std::thread::sleep(Duration::from_millis(5));
+
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE orders (id INT, item TEXT)"#, "");
+        connection.query(r#"INSERT INTO orders VALUES (1, "apple"), (2, "pear")"#, "");
+        settle();
+        connection.query(
+            &format!(
+                r#"CREATE SINK orders_sink FROM orders INTO FILE("{}") FORMAT JSON"#,
+                path
+            ),
+            "",
+        );
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap(),
+            concat!(
+                "{\"op\":\"c\",\"after\":{\"id\":1,\"item\":\"apple\"}}\n",
+                "{\"op\":\"c\",\"after\":{\"id\":2,\"item\":\"pear\"}}\n",
+            )
+        );
+
+        connection.query(r#"DELETE FROM orders WHERE id = 1"#, "");
+        connection.query(r#"INSERT INTO orders VALUES (3, "plum")"#, "");
+        settle();
+        connection.query(r#"FLUSH SINK orders_sink"#, "");
+        // Nothing's changed so nothing more is written
+        settle();
+        connection.query(r#"FLUSH SINK orders_sink"#, "");
+
+        // The events can be read straight back in as a change stream
+        connection.query(
+            &format!(
+                r#"CREATE EXTERNAL TABLE order_changes (id INT, item TEXT) FROM "{}"
+                   WITH (format = "json", changes = "debezium")"#,
+                directory.to_str().unwrap()
+            ),
+            "",
+        );
+        connection.query(r#"CREATE TABLE orders_copy (id INT, item TEXT)"#, "");
+        connection.query(r#"INSERT INTO orders_copy SELECT * FROM order_changes"#, "");
+        connection.query(
+            r#"SELECT * FROM orders_copy"#,
+            r#"
+            |2|pear|
+            |3|plum|
+            "#,
+        );
+
+        connection.query(r#"DROP SINK orders_sink"#, "");
+        assert!(connection
+            .execute_statement("FLUSH SINK orders_sink")
+            .is_err());
+        assert!(connection
+            .execute_statement(r#"CREATE SINK s FROM orders INTO KAFKA(topic = "orders")"#)
+            .is_err());
+    });
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn test_file_sink_privileges() {
+    let directory =
+        std::env::temp_dir().join(format!("incresql_sink_privileges_{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("orders.json");
+    let path = path.to_str().unwrap();
+
+    with_connection(|connection| {
+        connection.login("bob").unwrap();
+        connection.query(r#"CREATE TABLE orders (id INT, item TEXT)"#, "");
+        // Writing to a directory needs the same grant as reading from it
+        assert!(connection
+            .execute_statement(&format!(
+                r#"CREATE SINK orders_sink FROM orders INTO FILE("{}") FORMAT JSON"#,
+                path
+            ))
+            .is_err());
+        assert!(!std::path::Path::new(path).exists());
+
+        connection.login("root").unwrap();
+        connection.query(
+            &format!(
+                r#"GRANT EXECUTE ON DIRECTORY "{}" TO bob"#,
+                directory.to_str().unwrap()
+            ),
+            "",
+        );
+        connection.login("bob").unwrap();
+        connection.query(
+            &format!(
+                r#"CREATE SINK orders_sink FROM orders INTO FILE("{}") FORMAT JSON"#,
+                path
+            ),
+            "",
+        );
+        assert!(std::path::Path::new(path).exists());
+    });
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
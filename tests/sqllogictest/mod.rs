@@ -0,0 +1,6 @@
+use crate::runner::*;
+
+#[test]
+fn sqllogictest_scripts() {
+    run_sqllogictest_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/sqllogictest"));
+}
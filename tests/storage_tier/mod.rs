@@ -0,0 +1,116 @@
+use crate::runner::*;
+
+#[test]
+fn test_storage_tier() {
+    let directory =
+        std::env::temp_dir().join(format!("incresql_storage_tier_{}", std::process::id()));
+    let location = directory.join("events");
+    let location = location.to_str().unwrap();
+
+    with_connection(|connection| {
+        connection.query(
+            r#"CREATE TABLE raw_events (id INT, name TEXT, amount DECIMAL(3,2), at TEXT, ok BOOLEAN)"#,
+            "",
+        );
+        connection.query(
+            r#"INSERT INTO raw_events VALUES
+               (1, "a", 1.50, "2020-01-01T10:00:00", true),
+               (2, null, 2.25, "2020-01-02T10:00:00", false),
+               (2, null, 2.25, "2020-01-02T10:00:00", false)"#,
+            "",
+        );
+        connection.query(
+            r#"CREATE TABLE events (id INT, name TEXT, amount DECIMAL(3,2), at TIMESTAMP, ok BOOLEAN)"#,
+            "",
+        );
+        connection.query(
+            r#"INSERT INTO events SELECT id, name, amount, cast(at AS TIMESTAMP), ok FROM raw_events"#,
+            "",
+        );
+        connection.query(
+            &format!(
+                r#"ALTER TABLE events SET STORAGE TIER COLD LOCATION "{}""#,
+                location
+            ),
+            "",
+        );
+        connection.query(
+            r#"SELECT type FROM incresql.tables WHERE name = "events""#,
+            "|cold table|",
+        );
+        connection.query(
+            r#"SELECT * FROM events ORDER BY id"#,
+            r#"
+            |1|a|1.50|2020-01-01 10:00:00|TRUE|
+            |2|NULL|2.25|2020-01-02 10:00:00|FALSE|
+            |2|NULL|2.25|2020-01-02 10:00:00|FALSE|
+            "#,
+        );
+        assert!(connection
+            .execute_statement(r#"INSERT INTO events SELECT * FROM events"#)
+            .is_err());
+        assert!(connection
+            .execute_statement(&format!(
+                r#"ALTER TABLE events SET STORAGE TIER COLD LOCATION "{}""#,
+                location
+            ))
+            .is_err());
+
+        connection.query(r#"ALTER TABLE events SET STORAGE TIER HOT"#, "");
+        connection.query(
+            r#"SELECT type FROM incresql.tables WHERE name = "events""#,
+            "|table|",
+        );
+        connection.query(
+            r#"INSERT INTO events SELECT * FROM events WHERE id = 1"#,
+            "",
+        );
+        connection.query(
+            r#"SELECT id, name FROM events ORDER BY id"#,
+            r#"
+            |1|a|
+            |1|a|
+            |2|NULL|
+            |2|NULL|
+            "#,
+        );
+        assert!(connection
+            .execute_statement(r#"ALTER TABLE events SET STORAGE TIER HOT"#)
+            .is_err());
+        assert!(connection
+            .execute_statement(
+                r#"ALTER TABLE events SET STORAGE TIER COLD LOCATION "s3://bucket/events""#
+            )
+            .is_err());
+    });
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn test_storage_tier_privileges() {
+    let directory = std::env::temp_dir().join(format!(
+        "incresql_storage_tier_privileges_{}",
+        std::process::id()
+    ));
+    let location = directory.join("events");
+    let location = location.to_str().unwrap();
+
+    with_connection(|connection| {
+        connection.login("bob").unwrap();
+        connection.query(r#"CREATE TABLE events (id INT)"#, "");
+        connection.query(r#"INSERT INTO events VALUES (1)"#, "");
+        // Writing the table's files out needs a grant on where they're going
+        assert!(connection
+            .execute_statement(&format!(
+                r#"ALTER TABLE events SET STORAGE TIER COLD LOCATION "{}""#,
+                location
+            ))
+            .is_err());
+        assert!(!directory.exists());
+        connection.query(
+            r#"SELECT type FROM incresql.tables WHERE name = "events""#,
+            "|table|",
+        );
+    });
+}
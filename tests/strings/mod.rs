@@ -0,0 +1,78 @@
+use crate::runner::*;
+
+#[test]
+fn test_fuzzy_matching() {
+    with_connection(|connection| {
+        connection.query(
+            r#"select levenshtein("kitten", "sitting"), soundex("Robert"), similarity("word", "words")"#,
+            "
+        |3|R163|0.5714|
+        ",
+        );
+
+        connection.query(
+            r#"select levenshtein(null, "a"), soundex(null), similarity("a", null)"#,
+            "
+        |NULL|NULL|NULL|
+        ",
+        );
+    });
+}
+
+#[test]
+fn test_splitting() {
+    with_connection(|connection| {
+        connection.query(
+            r#"select split_part("GET /index.html 200", " ", 2), split_part("a,b,c", ",", -1), split_part("a,b", ",", 5)"#,
+            "
+        |/index.html|c||
+        ",
+        );
+
+        connection.query(
+            r#"select string_to_array("a,b,,c", ","), array_to_string(string_to_array("a,b,,c", ","), ";")"#,
+            r#"
+        |["a","b","","c"]|a;b;;c|
+        "#,
+        );
+
+        connection.query(
+            r#"select split_part(null, ",", 1), string_to_array("a", null), array_to_string(null, ",")"#,
+            "
+        |NULL|NULL|NULL|
+        ",
+        );
+    });
+}
+
+#[test]
+fn test_natural_sorting() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE files (name TEXT)"#, "");
+        connection.query(
+            r#"INSERT INTO files VALUES ("file10"), ("File2"), ("file1"), ("émile"), ("Emily")"#,
+            "",
+        );
+        connection.query(
+            r#"SELECT name FROM files ORDER BY natural_sort_key(name)"#,
+            "
+        |émile|
+        |Emily|
+        |file1|
+        |File2|
+        |file10|
+        ",
+        );
+        connection.query(
+            r#"SELECT name FROM files ORDER BY collation_key(name, "binary")"#,
+            "
+        |Emily|
+        |File2|
+        |file1|
+        |file10|
+        |émile|
+        ",
+        );
+        connection.query(r#"SELECT collation_key("a", "unknown")"#, "|NULL|");
+    });
+}
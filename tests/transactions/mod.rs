@@ -0,0 +1,60 @@
+use crate::runner::*;
+use runtime::Runtime;
+
+#[test]
+fn test_commit() {
+    let runtime = Runtime::new_for_test();
+    let connection = runtime.new_connection();
+    let other = runtime.new_connection();
+    connection.query(r#"CREATE TABLE t1 (a INT, b TEXT)"#, "");
+    connection.query(r#"INSERT INTO t1 VALUES (1, "abc")"#, "");
+
+    connection.query(r#"BEGIN"#, "");
+    connection.query(r#"INSERT INTO t1 VALUES (2, "def")"#, "");
+    connection.query(r#"DELETE FROM t1 WHERE a = 1"#, "");
+
+    // Only visible within the transaction until it's committed
+    connection.query(r#"SELECT * FROM t1"#, "|2|def|");
+    other.query(r#"SELECT * FROM t1"#, "|1|abc|");
+
+    connection.query(r#"COMMIT"#, "");
+    connection.query(r#"SELECT * FROM t1"#, "|2|def|");
+    other.query(r#"SELECT * FROM t1"#, "|2|def|");
+}
+
+#[test]
+fn test_rollback() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE t1 (a INT, b TEXT)"#, "");
+        connection.query(r#"INSERT INTO t1 VALUES (1, "abc")"#, "");
+
+        connection.query(r#"START TRANSACTION"#, "");
+        connection.query(r#"INSERT INTO t1 VALUES (2, "def")"#, "");
+        connection.query(
+            r#"SELECT * FROM t1"#,
+            "
+            |1|abc|
+            |2|def|
+        ",
+        );
+        connection.query(r#"ROLLBACK"#, "");
+        connection.query(r#"SELECT * FROM t1"#, "|1|abc|");
+
+        // Nothing to commit or roll back
+        connection.query(r#"COMMIT"#, "");
+        connection.query(r#"ROLLBACK"#, "");
+    });
+}
+
+#[test]
+fn test_begin_commits_open_transaction() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE t1 (a INT)"#, "");
+        connection.query(r#"BEGIN"#, "");
+        connection.query(r#"INSERT INTO t1 VALUES (1)"#, "");
+        connection.query(r#"BEGIN"#, "");
+        connection.query(r#"INSERT INTO t1 VALUES (2)"#, "");
+        connection.query(r#"ROLLBACK"#, "");
+        connection.query(r#"SELECT * FROM t1"#, "|1|");
+    });
+}
@@ -1,4 +1,5 @@
 use crate::runner::*;
+use runtime::QueryError;
 
 #[test]
 fn test_inner_joins() {
@@ -29,3 +30,435 @@ fn test_inner_joins() {
         );
     });
 }
+
+#[test]
+fn test_materialized_views() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE sales (region TEXT, amount INT)"#, "");
+        connection.query(
+            r#"INSERT INTO sales VALUES ("north", 10), ("north", 5), ("south", 7)"#,
+            "",
+        );
+        // Refreshes read just behind the current time
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        connection.query(
+            r#"CREATE MATERIALIZED VIEW totals AS SELECT region, sum(amount) AS total FROM sales GROUP BY region"#,
+            "",
+        );
+        connection.query(
+            r#"SHOW WARNINGS"#,
+            "
+            |Warning|1000|Materialized view default.totals can't be maintained incrementally, use REFRESH MATERIALIZED VIEW to update it|
+            ",
+        );
+        connection.query(
+            r#"SELECT * FROM totals ORDER BY region"#,
+            "
+            |north|15|
+            |south|7|
+            ",
+        );
+
+        // The view only changes when it's refreshed
+        connection.query(r#"INSERT INTO sales VALUES ("south", 1)"#, "");
+        connection.query(
+            r#"SELECT * FROM totals ORDER BY region"#,
+            "
+            |north|15|
+            |south|7|
+            ",
+        );
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        connection.query(r#"REFRESH MATERIALIZED VIEW CONCURRENTLY totals"#, "");
+        connection.query(
+            r#"SELECT * FROM totals ORDER BY region"#,
+            "
+            |north|15|
+            |south|8|
+            ",
+        );
+
+        assert!(connection
+            .execute_statement("REFRESH MATERIALIZED VIEW sales")
+            .is_err());
+        connection.query(r#"DROP TABLE totals"#, "");
+    });
+}
+
+#[test]
+fn test_incremental_materialized_views() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE sales (region TEXT, amount INT)"#, "");
+        connection.query(
+            r#"INSERT INTO sales VALUES ("north", 10), ("north", 500), ("south", 700)"#,
+            "",
+        );
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        connection.query(
+            r#"CREATE MATERIALIZED VIEW big_sales AS SELECT region, amount * 2 AS doubled FROM sales WHERE amount > 100"#,
+            "",
+        );
+        connection.query(r#"SHOW WARNINGS"#, "");
+        connection.query(
+            r#"SELECT name FROM incresql.incremental_views WHERE `database` = "default""#,
+            "
+            |big_sales|
+            ",
+        );
+        connection.query(
+            r#"SELECT * FROM big_sales ORDER BY doubled"#,
+            "
+            |north|1000|
+            |south|1400|
+            ",
+        );
+
+        // Only the changes since the last refresh are applied
+        connection.query(
+            r#"INSERT INTO sales VALUES ("south", 200), ("south", 1)"#,
+            "",
+        );
+        connection.query(r#"DELETE FROM sales WHERE amount = 500"#, "");
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        connection.query(r#"REFRESH MATERIALIZED VIEW big_sales"#, "");
+        connection.query(
+            r#"SELECT * FROM big_sales ORDER BY doubled"#,
+            "
+            |south|400|
+            |south|1400|
+            ",
+        );
+
+        // Refreshing again with no changes leaves the view as is
+        connection.query(r#"REFRESH MATERIALIZED VIEW big_sales"#, "");
+        connection.query(
+            r#"SELECT * FROM big_sales ORDER BY doubled"#,
+            "
+            |south|400|
+            |south|1400|
+            ",
+        );
+
+        connection.query(r#"DROP TABLE big_sales"#, "");
+        connection.query(r#"SELECT * FROM incresql.incremental_views"#, "");
+    });
+}
+
+#[test]
+fn test_non_immutable_materialized_views() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE events (id INT, at TEXT)"#, "");
+        connection.query(
+            r#"INSERT INTO events VALUES (1, "2000-01-01T00:00:00"), (2, "9999-01-01T00:00:00")"#,
+            "",
+        );
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        // now() would be different for each refresh so the view's recomputed rather than
+        // maintained from the changes, with now() as of the refresh
+        connection.query(
+            r#"CREATE MATERIALIZED VIEW past_events AS SELECT id FROM events WHERE cast(at AS TIMESTAMP) < now()"#,
+            "",
+        );
+        connection.query(
+            r#"SHOW WARNINGS"#,
+            "
+            |Warning|1000|Materialized view default.past_events can't be maintained incrementally, use REFRESH MATERIALIZED VIEW to update it|
+            ",
+        );
+        connection.query(
+            r#"SELECT name FROM incresql.incremental_views WHERE `database` = "default""#,
+            "",
+        );
+        connection.query(
+            r#"SELECT * FROM past_events"#,
+            "
+            |1|
+            ",
+        );
+    });
+}
+
+#[test]
+fn test_union_all_aggregate_views() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE online (region TEXT, amount INT)"#, "");
+        connection.query(r#"CREATE TABLE stores (region TEXT, amount INT)"#, "");
+        connection.query(
+            r#"INSERT INTO online VALUES ("north", 10), ("south", 5)"#,
+            "",
+        );
+        connection.query(r#"INSERT INTO stores VALUES ("north", 1), ("east", 2)"#, "");
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        connection.query(
+            r#"CREATE MATERIALIZED VIEW totals AS SELECT region, sum(amount) AS total, count(*) AS sales FROM (SELECT region, amount FROM online UNION ALL SELECT region, amount FROM stores) s GROUP BY region"#,
+            "",
+        );
+        connection.query(r#"SHOW WARNINGS"#, "");
+        connection.query(
+            r#"SELECT * FROM totals ORDER BY region"#,
+            "
+            |east|2|1|
+            |north|11|2|
+            |south|5|1|
+            ",
+        );
+        // Each source keeps its own partial state per group
+        connection.query(
+            r#"SELECT source, rows FROM totals_partial_states ORDER BY source, rows"#,
+            "
+            |0|1|
+            |0|1|
+            |1|1|
+            |1|1|
+            ",
+        );
+
+        // A change to one source only touches its own states
+        connection.query(r#"INSERT INTO stores VALUES ("north", 100)"#, "");
+        connection.query(r#"DELETE FROM stores WHERE region = "east""#, "");
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        connection.query(r#"REFRESH MATERIALIZED VIEW totals"#, "");
+        connection.query(
+            r#"SELECT * FROM totals ORDER BY region"#,
+            "
+            |north|111|3|
+            |south|5|1|
+            ",
+        );
+        connection.query(
+            r#"SELECT source, rows FROM totals_partial_states ORDER BY source, rows"#,
+            "
+            |0|1|
+            |0|1|
+            |1|2|
+            ",
+        );
+
+        connection.query(r#"DROP TABLE totals"#, "");
+        assert!(connection
+            .execute_statement("SELECT * FROM totals_partial_states")
+            .is_err());
+    });
+}
+
+#[test]
+fn test_materialized_view_state_ttl() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE events (at TEXT, amount INT)"#, "");
+        connection.query(
+            r#"INSERT INTO events VALUES ("2020-01-01T10:00:00", 1), ("2020-01-01T10:30:00", 2), ("2020-01-01T12:00:00", 4)"#,
+            "",
+        );
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        connection.query(
+            r#"CREATE MATERIALIZED VIEW hourly WITH (STATE_TTL = '1 hour', TIME_COLUMN = 'hour') AS SELECT time_bucket("1 hour", cast(at AS TIMESTAMP)) AS hour, sum(amount) AS total FROM events GROUP BY time_bucket("1 hour", cast(at AS TIMESTAMP))"#,
+            "",
+        );
+        connection.query(
+            r#"SELECT * FROM hourly ORDER BY hour"#,
+            "
+            |2020-01-01 10:00:00|3|
+            |2020-01-01 12:00:00|4|
+            ",
+        );
+
+        // The 10:00 group is more than an hour behind the latest so late rows for it are dropped
+        connection.query(
+            r#"INSERT INTO events VALUES ("2020-01-01T10:15:00", 8), ("2020-01-01T12:10:00", 16)"#,
+            "",
+        );
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        connection.query(r#"REFRESH MATERIALIZED VIEW hourly"#, "");
+        connection.query(
+            r#"SELECT * FROM hourly ORDER BY hour"#,
+            "
+            |2020-01-01 10:00:00|3|
+            |2020-01-01 12:00:00|20|
+            ",
+        );
+
+        connection.query(
+            r#"EXPLAIN MAINTENANCE FOR VIEW hourly"#,
+            "
+            |hourly|REFRESH|rows of the view with hour within 3600000ms of the latest|recompute the open rows on refresh|
+            |events|TABLE||1 row|
+            |events|GROUP|1 row per group, keyed on 1 expression(s)|update 1 group|
+            ",
+        );
+
+        assert!(connection
+            .execute_statement(
+                "CREATE MATERIALIZED VIEW bad WITH (STATE_TTL = '1 hour', TIME_COLUMN = 'amount') AS SELECT amount FROM events"
+            )
+            .is_err());
+    });
+}
+
+#[test]
+fn test_materialized_view_late_data() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE events (at TEXT, amount INT)"#, "");
+        connection.query(
+            r#"INSERT INTO events VALUES ("2020-01-01T10:00:00", 1), ("2020-01-01T12:00:00", 2)"#,
+            "",
+        );
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        connection.query(
+            r#"CREATE MATERIALIZED VIEW corrected WITH (STATE_TTL = '1 hour', TIME_COLUMN = 'hour', LATE_DATA = 'corrections') AS SELECT time_bucket("1 hour", cast(at AS TIMESTAMP)) AS hour, sum(amount) AS total FROM events GROUP BY time_bucket("1 hour", cast(at AS TIMESTAMP))"#,
+            "",
+        );
+        connection.query(
+            r#"CREATE MATERIALIZED VIEW retracted WITH (STATE_TTL = '1 hour', TIME_COLUMN = 'hour', LATE_DATA = 'retract') AS SELECT time_bucket("1 hour", cast(at AS TIMESTAMP)) AS hour, sum(amount) AS total FROM events GROUP BY time_bucket("1 hour", cast(at AS TIMESTAMP))"#,
+            "",
+        );
+        connection.query(r#"SELECT * FROM corrected_corrections"#, "");
+
+        connection.query(
+            r#"INSERT INTO events VALUES ("2020-01-01T10:15:00", 4), ("2020-01-01T12:10:00", 8)"#,
+            "",
+        );
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        connection.query(r#"REFRESH MATERIALIZED VIEW corrected"#, "");
+        connection.query(r#"REFRESH MATERIALIZED VIEW retracted"#, "");
+
+        // The closed 10:00 row stays put with its up to date version kept in the corrections
+        connection.query(
+            r#"SELECT * FROM corrected ORDER BY hour"#,
+            "
+            |2020-01-01 10:00:00|1|
+            |2020-01-01 12:00:00|10|
+            ",
+        );
+        connection.query(
+            r#"SELECT * FROM corrected_corrections"#,
+            "
+            |2020-01-01 10:00:00|5|
+            ",
+        );
+
+        // Later corrections replace the earlier ones
+        connection.query(
+            r#"INSERT INTO events VALUES ("2020-01-01T10:20:00", 16)"#,
+            "",
+        );
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        connection.query(r#"REFRESH MATERIALIZED VIEW corrected"#, "");
+        connection.query(r#"REFRESH MATERIALIZED VIEW retracted"#, "");
+        connection.query(
+            r#"SELECT * FROM corrected_corrections"#,
+            "
+            |2020-01-01 10:00:00|21|
+            ",
+        );
+
+        connection.query(
+            r#"SELECT * FROM retracted ORDER BY hour"#,
+            "
+            |2020-01-01 10:00:00|21|
+            |2020-01-01 12:00:00|10|
+            ",
+        );
+
+        connection.query(r#"DROP TABLE corrected"#, "");
+        assert!(connection
+            .execute_statement("SELECT * FROM corrected_corrections")
+            .is_err());
+    });
+}
+
+#[test]
+fn test_explain_maintenance() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE orders (customer_id INT, amount INT)"#, "");
+        connection.query(r#"CREATE TABLE customers (id INT, name TEXT)"#, "");
+        connection.query(
+            r#"CREATE VIEW spend AS SELECT name, sum(amount), any_value(amount) FROM orders JOIN customers ON customer_id = id GROUP BY name"#,
+            "",
+        );
+        connection.query(
+            r#"EXPLAIN MAINTENANCE FOR VIEW spend"#,
+            "
+            |orders|TABLE||1 row|
+            |orders|PROJECT||1 row|
+            |orders|JOIN(left)|rows of customers keyed on the join condition|probe customers for the matching rows|
+            |orders|PROJECT||1 row|
+            |orders|GROUP|1 row per group, keyed on 1 expression(s) plus the input rows of each group|recompute 1 group on removals, any_value can't be retracted|
+            |customers|TABLE||1 row|
+            |customers|PROJECT||1 row|
+            |customers|JOIN(right)|rows of orders keyed on the join condition|probe orders for the matching rows|
+            |customers|PROJECT||1 row|
+            |customers|GROUP|1 row per group, keyed on 1 expression(s) plus the input rows of each group|recompute 1 group on removals, any_value can't be retracted|
+            ",
+        );
+
+        connection.query(
+            r#"CREATE MATERIALIZED VIEW big_orders AS SELECT * FROM orders WHERE amount > 100"#,
+            "",
+        );
+        connection.query(
+            r#"EXPLAIN MAINTENANCE FOR VIEW big_orders"#,
+            "
+            |big_orders|REFRESH|all rows of the view|apply the changes to its tables since the last refresh|
+            |orders|TABLE||1 row|
+            |orders|FILTER||1 row|
+            |orders|PROJECT||1 row|
+            ",
+        );
+
+        assert!(connection
+            .execute_statement("EXPLAIN MAINTENANCE FOR VIEW orders")
+            .is_err());
+    });
+}
+
+#[test]
+fn test_view_nesting_limit() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE t (a INT)"#, "");
+        connection.query(r#"INSERT INTO t VALUES (1)"#, "");
+        connection.query(r#"CREATE VIEW v1 AS SELECT a FROM t"#, "");
+        connection.query(r#"CREATE VIEW v2 AS SELECT a FROM v1"#, "");
+        connection.query(r#"CREATE VIEW v3 AS SELECT a FROM v2"#, "");
+
+        connection.query(r#"SET max_recursion_depth = 2"#, "");
+        connection.query(r#"SELECT a FROM v2"#, "|1|");
+        assert!(matches!(
+            connection.execute_statement("SELECT a FROM v3"),
+            // ER_VIEW_RECURSIVE
+            Err(err) if err.error_code().code == 1462
+        ));
+
+        connection.query(r#"SET max_recursion_depth = DEFAULT"#, "");
+        connection.query(r#"SELECT a FROM v3"#, "|1|");
+    });
+}
+
+#[test]
+fn test_materialized_view_intermediate_rows() {
+    with_connection(|connection| {
+        connection.query(r#"CREATE TABLE sales (region TEXT, amount INT)"#, "");
+        connection.query(
+            r#"INSERT INTO sales VALUES ("north", 10), ("south", 5), ("east", 7)"#,
+            "",
+        );
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        connection.query(
+            r#"CREATE MATERIALIZED VIEW totals AS SELECT region, sum(amount) AS total FROM sales GROUP BY region"#,
+            "",
+        );
+
+        connection.query(r#"INSERT INTO sales VALUES ("west", 1)"#, "");
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        connection.query(r#"SET max_intermediate_rows = 2"#, "");
+        assert!(matches!(
+            connection.execute_statement("REFRESH MATERIALIZED VIEW totals"),
+            Err(QueryError::IntermediateRowsExceeded(..))
+        ));
+        // Nothing's written when the refresh fails
+        connection.query(r#"SELECT count(*) FROM totals"#, "|3|");
+
+        connection.query(r#"SET max_intermediate_rows = DEFAULT"#, "");
+        connection.query(r#"REFRESH MATERIALIZED VIEW totals"#, "");
+        connection.query(r#"SELECT count(*) FROM totals"#, "|4|");
+    });
+}
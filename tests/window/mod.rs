@@ -0,0 +1,96 @@
+use crate::runner::*;
+
+#[test]
+fn test_window_functions() {
+    with_connection(|connection| {
+        connection.query(r#"Create table test (c1 TEXT, c2 INT)"#, "");
+        connection.query(
+            r#"INSERT INTO test VALUES
+        ("a", 1), ("a", 2), ("a", 2), ("a", 4), ("b", 3), ("b", NULL), ("c", 5)"#,
+            "",
+        );
+
+        connection.query(
+            r#"select c1, c2, row_number() OVER (PARTITION BY c1 ORDER BY c2),
+            sum(c2) OVER (PARTITION BY c1 ORDER BY c2),
+            count(*) OVER (PARTITION BY c1)
+            from test order by c1, c2"#,
+            "
+            |a|1|1|1|4|
+            |a|2|2|5|4|
+            |a|2|3|5|4|
+            |a|4|4|9|4|
+            |b|NULL|1|NULL|2|
+            |b|3|2|3|2|
+            |c|5|1|5|1|
+        ",
+        );
+
+        // Window functions with different partitions/orderings in the same query
+        connection.query(
+            r#"select c1, c2, sum(c2) OVER (), sum(c2) OVER (ORDER BY c2 DESC) + 1
+            from test where c2 > 1 order by c2, c1"#,
+            "
+            |a|2|16|17|
+            |a|2|16|17|
+            |b|3|16|13|
+            |a|4|16|10|
+            |c|5|16|6|
+        ",
+        );
+    });
+}
+
+#[test]
+fn test_window_frames() {
+    with_connection(|connection| {
+        connection.query(r#"Create table test (c1 INT, c2 INT)"#, "");
+        connection.query(
+            r#"INSERT INTO test VALUES (1, 10), (2, 20), (3, 30), (4, 40)"#,
+            "",
+        );
+
+        connection.query(
+            r#"select c1,
+            sum(c2) OVER (ORDER BY c1 ROWS 1 PRECEDING),
+            avg(c2) OVER (ORDER BY c1 ROWS BETWEEN 2 PRECEDING AND CURRENT ROW),
+            first(c2) OVER (ORDER BY c1 ROWS 1 PRECEDING),
+            count(*) OVER (ORDER BY c1 ROWS UNBOUNDED PRECEDING)
+            from test order by c1"#,
+            "
+            |1|10|10.00000000000000|10|1|
+            |2|30|15.00000000000000|10|2|
+            |3|50|20.00000000000000|20|3|
+            |4|70|30.00000000000000|30|4|
+        ",
+        );
+
+        // Windows over a subquery can then be filtered on
+        connection.query(
+            r#"select c1 from (
+              select c1, row_number() OVER (ORDER BY c2 DESC) as rn from test
+            ) t where rn <= 2 order by c1"#,
+            "
+            |3|
+            |4|
+        ",
+        );
+    });
+}
+
+#[test]
+fn test_window_errors() {
+    with_connection(|connection| {
+        connection.query(r#"Create table test (c1 INT, c2 INT)"#, "");
+        for sql in &[
+            "select count(*), row_number() OVER () from test",
+            "select c1 from test group by row_number() OVER ()",
+            "select c1 from test where row_number() OVER () = 1",
+            "select c1 from test order by row_number() OVER ()",
+            "select abs(c1) OVER () from test",
+            "select sum(count(*)) OVER () from test",
+        ] {
+            assert!(connection.execute_statement(sql).is_err(), "{}", sql);
+        }
+    });
+}